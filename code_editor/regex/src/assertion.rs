@@ -0,0 +1,44 @@
+use crate::char_class;
+
+/// A zero-width assertion.
+///
+/// Note that `^` and `$` are line anchors (`StartOfLine` and `EndOfLine`), since that is what
+/// users of a text editor expect. Use `\A` and `\z` to anchor to the start and end of the text.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Assertion {
+    StartOfText,
+    EndOfText,
+    StartOfLine,
+    EndOfLine,
+    WordBoundary,
+    NotWordBoundary,
+}
+
+impl Assertion {
+    /// Returns the equivalent assertion for matching in the opposite direction.
+    pub fn reversed(self) -> Self {
+        match self {
+            Self::StartOfText => Self::EndOfText,
+            Self::EndOfText => Self::StartOfText,
+            Self::StartOfLine => Self::EndOfLine,
+            Self::EndOfLine => Self::StartOfLine,
+            Self::WordBoundary => Self::WordBoundary,
+            Self::NotWordBoundary => Self::NotWordBoundary,
+        }
+    }
+
+    pub fn is_satisfied(self, prev_char: Option<char>, next_char: Option<char>) -> bool {
+        match self {
+            Self::StartOfText => prev_char.is_none(),
+            Self::EndOfText => next_char.is_none(),
+            Self::StartOfLine => prev_char.is_none_or(|c| c == '\n'),
+            Self::EndOfLine => next_char.is_none_or(|c| c == '\n'),
+            Self::WordBoundary => is_word_boundary(prev_char, next_char),
+            Self::NotWordBoundary => !is_word_boundary(prev_char, next_char),
+        }
+    }
+}
+
+fn is_word_boundary(prev_char: Option<char>, next_char: Option<char>) -> bool {
+    prev_char.is_some_and(char_class::is_word_char) != next_char.is_some_and(char_class::is_word_char)
+}
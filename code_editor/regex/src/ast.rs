@@ -1,8 +1,15 @@
+use crate::{Assertion, CharClass};
+
+#[derive(Clone, Debug)]
 pub enum Ast {
+    Empty,
     Alt(Vec<Ast>),
     Cat(Vec<Ast>),
-    Quest(Box<Ast>),
-    Star(Box<Ast>),
-    Plus(Box<Ast>),
+    Quest(Box<Ast>, bool),
+    Star(Box<Ast>, bool),
+    Plus(Box<Ast>, bool),
+    Capture(Box<Ast>, usize),
+    Assert(Assertion),
     Char(char),
+    Class(CharClass),
 }
@@ -0,0 +1,65 @@
+use {
+    crate::{Cursor, Input},
+    makepad_collections::{
+        btree_string,
+        btree_string::{BTreeString, Slice},
+    },
+};
+
+impl<'a> Input for &'a BTreeString {
+    type Cursor = btree_string::Cursor<'a>;
+
+    fn cursor_front(&self) -> Self::Cursor {
+        BTreeString::cursor_front(self)
+    }
+
+    fn cursor_back(&self) -> Self::Cursor {
+        BTreeString::cursor_back(self)
+    }
+}
+
+impl<'a> Input for Slice<'a> {
+    type Cursor = btree_string::Cursor<'a>;
+
+    fn cursor_front(&self) -> Self::Cursor {
+        Slice::cursor_front(*self)
+    }
+
+    fn cursor_back(&self) -> Self::Cursor {
+        Slice::cursor_back(*self)
+    }
+}
+
+impl<'a> Cursor for btree_string::Cursor<'a> {
+    fn position(&self) -> usize {
+        btree_string::Cursor::position(self)
+    }
+
+    fn peek_next_char(&self) -> Option<char> {
+        if self.is_at_end() {
+            return None;
+        }
+        Some(self.char())
+    }
+
+    fn peek_prev_char(&self) -> Option<char> {
+        if self.index() > 0 {
+            return self.chunk()[..self.index()].chars().next_back();
+        }
+        if self.is_at_start() {
+            return None;
+        }
+        // The previous char is in the previous chunk, which we can only get to by moving.
+        let mut cursor = self.clone();
+        cursor.move_prev_char();
+        Some(cursor.char())
+    }
+
+    fn skip_next_char(&mut self) {
+        self.move_next_char();
+    }
+
+    fn skip_prev_char(&mut self) {
+        self.move_prev_char();
+    }
+}
@@ -0,0 +1,71 @@
+use makepad_ucd::{Ucd, WordBreak};
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CharClass {
+    pub negated: bool,
+    pub items: Vec<CharClassItem>,
+}
+
+impl CharClass {
+    pub fn any() -> Self {
+        Self {
+            negated: true,
+            items: vec![CharClassItem::Range('\n', '\n')],
+        }
+    }
+
+    pub fn perl(perl_class: PerlClass, negated: bool) -> Self {
+        Self {
+            negated,
+            items: vec![CharClassItem::Perl(perl_class, false)],
+        }
+    }
+
+    pub fn contains(&self, c: char) -> bool {
+        self.items.iter().any(|item| item.contains(c)) != self.negated
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CharClassItem {
+    Range(char, char),
+    Perl(PerlClass, bool),
+}
+
+impl CharClassItem {
+    fn contains(self, c: char) -> bool {
+        match self {
+            Self::Range(first, last) => first <= c && c <= last,
+            Self::Perl(perl_class, negated) => perl_class.contains(c) != negated,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PerlClass {
+    Digit,
+    Space,
+    Word,
+}
+
+impl PerlClass {
+    fn contains(self, c: char) -> bool {
+        match self {
+            Self::Digit => c.is_ascii_digit(),
+            Self::Space => c.is_whitespace(),
+            Self::Word => is_word_char(c),
+        }
+    }
+}
+
+/// Returns `true` if `c` can occur inside a word, according to its `Word_Break` property.
+pub fn is_word_char(c: char) -> bool {
+    matches!(
+        c.word_break(),
+        WordBreak::ALetter
+            | WordBreak::HebrewLetter
+            | WordBreak::Katakana
+            | WordBreak::Numeric
+            | WordBreak::ExtendNumLet
+    )
+}
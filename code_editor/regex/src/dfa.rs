@@ -0,0 +1,338 @@
+use {
+    crate::{
+        char_class,
+        input::Cursor,
+        program::{Inst, InstPtr},
+        Program, SparseSet,
+    },
+    std::collections::HashMap,
+};
+
+const UNKNOWN_STATE_PTR: StatePtr = StatePtr::MAX;
+
+// If the cache needs to be cleared more than this many times during a single run, and we did not
+// make enough progress since the last time, the cache is thrashing and we give up.
+const MIN_CACHE_CLEAR_COUNT: usize = 3;
+const MIN_CHARS_PER_STATE: usize = 10;
+
+/// A lazily built DFA.
+///
+/// States are built on demand while running, and cached until more than `state_limit` of them
+/// exist, after which the cache is cleared. An unanchored DFA finds the end of the leftmost-first
+/// match. An anchored DFA finds the end of the longest match that starts at the cursor, which is
+/// what we need to find the other end of a match found by an unanchored DFA running in the
+/// opposite direction.
+pub struct Dfa {
+    anchored: bool,
+    state_limit: usize,
+    states: Vec<State>,
+    state_ptrs_by_key: HashMap<StateKey, StatePtr>,
+    closure: SparseSet,
+    kernel: SparseSet,
+    stack: Vec<InstPtr>,
+    cache_clear_count: usize,
+    chars_since_cache_clear: usize,
+}
+
+impl Dfa {
+    pub fn new(program: &Program, anchored: bool, state_limit: usize) -> Self {
+        Self {
+            anchored,
+            state_limit: state_limit.max(2),
+            states: Vec::new(),
+            state_ptrs_by_key: HashMap::new(),
+            closure: SparseSet::new(program.insts.len()),
+            kernel: SparseSet::new(program.insts.len()),
+            stack: Vec::new(),
+            cache_clear_count: 0,
+            chars_since_cache_clear: 0,
+        }
+    }
+
+    /// Runs this `Dfa` on `cursor` and returns the position at which the match ends.
+    ///
+    /// If `earliest` is `true`, we stop at the first position where a match is found. If `limit`
+    /// is not `None`, we stop when the cursor reaches that position, so that an anchored `Dfa` does
+    /// not run past the position where the search started.
+    pub fn run<C: Cursor>(
+        &mut self,
+        program: &Program,
+        mut cursor: C,
+        earliest: bool,
+        limit: Option<usize>,
+    ) -> RunResult {
+        self.cache_clear_count = 0;
+        self.chars_since_cache_clear = 0;
+        let mut state = match self.start_state(program, cursor.peek_prev_char()) {
+            Some(state) => state,
+            None => return RunResult::GaveUp,
+        };
+        let mut end = None;
+        loop {
+            let c = cursor.peek_next_char();
+            state = match self.next_state(program, state, c) {
+                Some(state) => state,
+                None => return RunResult::GaveUp,
+            };
+            if self.states[state].key.flags & MATCH_BEFORE != 0 {
+                end = Some(cursor.position());
+                if earliest {
+                    break;
+                }
+            }
+            if c.is_none()
+                || self.states[state].key.kernel.is_empty()
+                || limit == Some(cursor.position())
+            {
+                break;
+            }
+            cursor.skip_next_char();
+            self.chars_since_cache_clear += 1;
+        }
+        match end {
+            Some(end) => RunResult::Matched(end),
+            None => RunResult::NotMatched,
+        }
+    }
+
+    fn start_state(&mut self, program: &Program, prev_char: Option<char>) -> Option<StatePtr> {
+        let key = StateKey {
+            kernel: vec![program.start].into_boxed_slice(),
+            flags: prev_char_flags(prev_char),
+        };
+        if let Some(&state) = self.state_ptrs_by_key.get(&key) {
+            return Some(state);
+        }
+        if self.states.len() >= self.state_limit {
+            if self.is_thrashing() {
+                return None;
+            }
+            self.clear_cache();
+        }
+        Some(self.add_state(key))
+    }
+
+    fn next_state(
+        &mut self,
+        program: &Program,
+        mut state: StatePtr,
+        c: Option<char>,
+    ) -> Option<StatePtr> {
+        let next_state = self.states[state].transition(c);
+        if next_state != UNKNOWN_STATE_PTR {
+            return Some(next_state);
+        }
+        let key = self.compute_next_state_key(program, state, c);
+        let next_state = match self.state_ptrs_by_key.get(&key) {
+            Some(&next_state) => next_state,
+            None => {
+                if self.states.len() >= self.state_limit {
+                    if self.is_thrashing() {
+                        return None;
+                    }
+                    let current_key = self.states[state].key.clone();
+                    self.clear_cache();
+                    state = self.add_state(current_key);
+                    // The current state is back in the cache, and may well be the next one.
+                    if let Some(&next_state) = self.state_ptrs_by_key.get(&key) {
+                        self.states[state].set_transition(c, next_state);
+                        return Some(next_state);
+                    }
+                }
+                self.add_state(key)
+            }
+        };
+        self.states[state].set_transition(c, next_state);
+        Some(next_state)
+    }
+
+    fn compute_next_state_key(
+        &mut self,
+        program: &Program,
+        state: StatePtr,
+        c: Option<char>,
+    ) -> StateKey {
+        let key = &self.states[state].key;
+        let prev_char = representative_prev_char(key.flags);
+        self.closure.clear();
+        for &inst in key.kernel.iter() {
+            add_closure(
+                &mut self.closure,
+                inst,
+                &program.insts,
+                prev_char,
+                c,
+                &mut self.stack,
+            );
+        }
+        self.kernel.clear();
+        let mut match_before = false;
+        for &inst in &self.closure {
+            match program.insts[inst] {
+                Inst::Match => {
+                    match_before = true;
+                    if !self.anchored {
+                        break;
+                    }
+                }
+                Inst::Char(next, c1) if c == Some(c1) => {
+                    self.kernel.insert(next);
+                }
+                Inst::Class(next, ref class) if c.is_some_and(|c| class.contains(c)) => {
+                    self.kernel.insert(next);
+                }
+                _ => {}
+            }
+        }
+        let matched = key.flags & MATCHED != 0 || match_before;
+        if !self.anchored && !matched {
+            self.kernel.insert(program.start);
+        }
+        let mut flags = prev_char_flags(c);
+        if matched {
+            flags |= MATCHED;
+        }
+        if match_before {
+            flags |= MATCH_BEFORE;
+        }
+        StateKey {
+            kernel: self.kernel.as_slice().into(),
+            flags,
+        }
+    }
+
+    fn add_state(&mut self, key: StateKey) -> StatePtr {
+        let state = self.states.len();
+        self.states.push(State::new(key.clone()));
+        self.state_ptrs_by_key.insert(key, state);
+        state
+    }
+
+    fn is_thrashing(&self) -> bool {
+        self.cache_clear_count >= MIN_CACHE_CLEAR_COUNT
+            && self.chars_since_cache_clear < MIN_CHARS_PER_STATE * self.states.len()
+    }
+
+    fn clear_cache(&mut self) {
+        self.states.clear();
+        self.state_ptrs_by_key.clear();
+        self.cache_clear_count += 1;
+        self.chars_since_cache_clear = 0;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RunResult {
+    Matched(usize),
+    NotMatched,
+    GaveUp,
+}
+
+type StatePtr = usize;
+
+// The properties of the previous char that assertions depend on.
+const PREV_NONE: u8 = 1 << 0;
+const PREV_NEWLINE: u8 = 1 << 1;
+const PREV_WORD: u8 = 1 << 2;
+// A match was found at an earlier position, so we no longer start new threads.
+const MATCHED: u8 = 1 << 3;
+// A match was found at the position before the char on which we transitioned to this state.
+const MATCH_BEFORE: u8 = 1 << 4;
+
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+struct StateKey {
+    kernel: Box<[InstPtr]>,
+    flags: u8,
+}
+
+struct State {
+    key: StateKey,
+    ascii_transitions: Box<[StatePtr; 128]>,
+    transitions: HashMap<char, StatePtr>,
+    end_transition: StatePtr,
+}
+
+impl State {
+    fn new(key: StateKey) -> Self {
+        Self {
+            key,
+            ascii_transitions: Box::new([UNKNOWN_STATE_PTR; 128]),
+            transitions: HashMap::new(),
+            end_transition: UNKNOWN_STATE_PTR,
+        }
+    }
+
+    fn transition(&self, c: Option<char>) -> StatePtr {
+        match c {
+            Some(c) if c.is_ascii() => self.ascii_transitions[c as usize],
+            Some(c) => self
+                .transitions
+                .get(&c)
+                .copied()
+                .unwrap_or(UNKNOWN_STATE_PTR),
+            None => self.end_transition,
+        }
+    }
+
+    fn set_transition(&mut self, c: Option<char>, state: StatePtr) {
+        match c {
+            Some(c) if c.is_ascii() => self.ascii_transitions[c as usize] = state,
+            Some(c) => {
+                self.transitions.insert(c, state);
+            }
+            None => self.end_transition = state,
+        }
+    }
+}
+
+fn prev_char_flags(prev_char: Option<char>) -> u8 {
+    match prev_char {
+        None => PREV_NONE,
+        Some('\n') => PREV_NEWLINE,
+        Some(c) if char_class::is_word_char(c) => PREV_WORD,
+        Some(_) => 0,
+    }
+}
+
+// A state only remembers the properties of the previous char that assertions depend on, so we
+// evaluate assertions against a char with the same properties.
+fn representative_prev_char(flags: u8) -> Option<char> {
+    if flags & PREV_NONE != 0 {
+        None
+    } else if flags & PREV_NEWLINE != 0 {
+        Some('\n')
+    } else if flags & PREV_WORD != 0 {
+        Some('a')
+    } else {
+        Some(' ')
+    }
+}
+
+fn add_closure(
+    closure: &mut SparseSet,
+    inst: InstPtr,
+    insts: &[Inst],
+    prev_char: Option<char>,
+    next_char: Option<char>,
+    stack: &mut Vec<InstPtr>,
+) {
+    stack.push(inst);
+    while let Some(mut inst) = stack.pop() {
+        while closure.insert(inst) {
+            match insts[inst] {
+                Inst::Nop(next) | Inst::Save(next, _) => inst = next,
+                Inst::Assert(next, assertion) => {
+                    if !assertion.is_satisfied(prev_char, next_char) {
+                        break;
+                    }
+                    inst = next;
+                }
+                Inst::Split(next_0, next_1) => {
+                    stack.push(next_1);
+                    inst = next_0;
+                }
+                _ => break,
+            }
+        }
+    }
+}
@@ -1,33 +1,56 @@
 use crate::{
     program,
     program::{Inst, InstPtr},
-    Ast, Program,
+    Assertion, Ast, CharClass, Program,
 };
 
 pub fn generate(regex: &Ast) -> Program {
-    GenerateContext { insts: Vec::new() }.generate(regex)
+    GenerateContext {
+        insts: Vec::new(),
+        slot_count: 2,
+        reversed: false,
+    }
+    .generate(regex)
+}
+
+/// Generates a program that matches `regex` backwards, to be run on a reversed cursor.
+///
+/// The slots of the reversed program are in the same order as those of the forward program, so
+/// that the even slot of each capture is always its lower position.
+pub fn generate_rev(regex: &Ast) -> Program {
+    GenerateContext {
+        insts: Vec::new(),
+        slot_count: 2,
+        reversed: true,
+    }
+    .generate(regex)
 }
 
 struct GenerateContext {
     insts: Vec<Inst>,
+    slot_count: usize,
+    reversed: bool,
 }
 
 impl GenerateContext {
     fn generate(mut self, ast: &Ast) -> Program {
-        self.emit_inst(Inst::Nop);
+        self.emit_inst(Inst::Nop(program::NULL_INST_PTR));
         let fragment = self.generate_recursive(ast);
+        let fragment = self.generate_capture(fragment, 0);
         let inst = self.emit_inst(Inst::Match);
         fragment.ends.fill(inst, &mut self.insts);
         Program {
             insts: self.insts,
             start: fragment.start,
+            slot_count: self.slot_count,
         }
     }
 
     fn generate_recursive(&mut self, ast: &Ast) -> Fragment {
         match *ast {
+            Ast::Empty => self.generate_empty(),
             Ast::Alt(ref asts) => {
-                let mut asts = asts.into_iter();
+                let mut asts = asts.iter();
                 let mut acc = self.generate_recursive(asts.next().unwrap());
                 for ast in asts {
                     let fragment = self.generate_recursive(ast);
@@ -36,30 +59,43 @@ impl GenerateContext {
                 acc
             }
             Ast::Cat(ref asts) => {
-                let mut asts = asts.into_iter();
-                let mut acc = self.generate_recursive(asts.next().unwrap());
-                for ast in asts {
-                    let fragment = self.generate_recursive(ast);
-                    acc = self.generate_cat(acc, fragment);
+                if self.reversed {
+                    self.generate_cats(asts.iter().rev())
+                } else {
+                    self.generate_cats(asts.iter())
                 }
-                acc
             }
-            Ast::Quest(ref asts) => {
-                let fragment = self.generate_recursive(asts);
-                self.generate_quest(fragment)
+            Ast::Quest(ref ast, greedy) => {
+                let fragment = self.generate_recursive(ast);
+                self.generate_quest(fragment, greedy)
+            }
+            Ast::Star(ref ast, greedy) => {
+                let fragment = self.generate_recursive(ast);
+                self.generate_star(fragment, greedy)
             }
-            Ast::Star(ref ast) => {
+            Ast::Plus(ref ast, greedy) => {
                 let fragment = self.generate_recursive(ast);
-                self.generate_star(fragment)
+                self.generate_plus(fragment, greedy)
             }
-            Ast::Plus(ref ast) => {
+            Ast::Capture(ref ast, index) => {
                 let fragment = self.generate_recursive(ast);
-                self.generate_plus(fragment)
+                self.generate_capture(fragment, index)
             }
+            Ast::Assert(assertion) => self.generate_assert(assertion),
             Ast::Char(c) => self.generate_char(c),
+            Ast::Class(ref class) => self.generate_class(class.clone()),
         }
     }
 
+    fn generate_cats<'a>(&mut self, mut asts: impl Iterator<Item = &'a Ast>) -> Fragment {
+        let mut acc = self.generate_recursive(asts.next().unwrap());
+        for ast in asts {
+            let fragment = self.generate_recursive(ast);
+            acc = self.generate_cat(acc, fragment);
+        }
+        acc
+    }
+
     fn generate_alt(&mut self, fragment_0: Fragment, fragment_1: Fragment) -> Fragment {
         let inst = self.emit_inst(Inst::split(fragment_0.start, fragment_1.start));
         Fragment {
@@ -69,36 +105,81 @@ impl GenerateContext {
     }
 
     fn generate_cat(&mut self, fragment_0: Fragment, fragment_1: Fragment) -> Fragment {
-        fragment_1.ends.fill(fragment_0.start, &mut self.insts);
+        fragment_0.ends.fill(fragment_1.start, &mut self.insts);
         Fragment {
-            start: fragment_1.start,
-            ends: fragment_0.ends,
+            start: fragment_0.start,
+            ends: fragment_1.ends,
         }
     }
 
-    fn generate_quest(&mut self, fragment: Fragment) -> Fragment {
-        let inst = self.emit_inst(Inst::split(fragment.start, program::NULL_INST_PTR));
+    fn generate_quest(&mut self, fragment: Fragment, greedy: bool) -> Fragment {
+        let (inst, hole) = self.emit_split(fragment.start, greedy);
         Fragment {
             start: inst,
-            ends: fragment.ends.append(HolePtr::next_1(inst), &mut self.insts),
+            ends: fragment.ends.append(hole, &mut self.insts),
         }
     }
 
-    fn generate_star(&mut self, fragment: Fragment) -> Fragment {
-        let inst = self.emit_inst(Inst::split(fragment.start, program::NULL_INST_PTR));
+    fn generate_star(&mut self, fragment: Fragment, greedy: bool) -> Fragment {
+        let (inst, hole) = self.emit_split(fragment.start, greedy);
         fragment.ends.fill(inst, &mut self.insts);
         Fragment {
             start: inst,
-            ends: HolePtrList::unit(HolePtr::next_1(inst)),
+            ends: HolePtrList::unit(hole),
         }
     }
 
-    fn generate_plus(&mut self, fragment: Fragment) -> Fragment {
-        let inst = self.emit_inst(Inst::split(fragment.start, program::NULL_INST_PTR));
+    fn generate_plus(&mut self, fragment: Fragment, greedy: bool) -> Fragment {
+        let (inst, hole) = self.emit_split(fragment.start, greedy);
         fragment.ends.fill(inst, &mut self.insts);
         Fragment {
             start: fragment.start,
-            ends: HolePtrList::unit(HolePtr::next_1(inst)),
+            ends: HolePtrList::unit(hole),
+        }
+    }
+
+    fn generate_empty(&mut self) -> Fragment {
+        let inst = self.emit_inst(Inst::Nop(program::NULL_INST_PTR));
+        Fragment {
+            start: inst,
+            ends: HolePtrList::unit(HolePtr::next_0(inst)),
+        }
+    }
+
+    fn generate_assert(&mut self, assertion: Assertion) -> Fragment {
+        let assertion = if self.reversed {
+            assertion.reversed()
+        } else {
+            assertion
+        };
+        let inst = self.emit_inst(Inst::assert(program::NULL_INST_PTR, assertion));
+        Fragment {
+            start: inst,
+            ends: HolePtrList::unit(HolePtr::next_0(inst)),
+        }
+    }
+
+    fn generate_class(&mut self, class: CharClass) -> Fragment {
+        let inst = self.emit_inst(Inst::class(program::NULL_INST_PTR, class));
+        Fragment {
+            start: inst,
+            ends: HolePtrList::unit(HolePtr::next_0(inst)),
+        }
+    }
+
+    fn generate_capture(&mut self, fragment: Fragment, index: usize) -> Fragment {
+        self.slot_count = self.slot_count.max(2 * index + 2);
+        let (start_slot, end_slot) = if self.reversed {
+            (2 * index + 1, 2 * index)
+        } else {
+            (2 * index, 2 * index + 1)
+        };
+        let start = self.emit_inst(Inst::save(fragment.start, start_slot));
+        let end = self.emit_inst(Inst::save(program::NULL_INST_PTR, end_slot));
+        fragment.ends.fill(end, &mut self.insts);
+        Fragment {
+            start,
+            ends: HolePtrList::unit(HolePtr::next_0(end)),
         }
     }
 
@@ -110,6 +191,17 @@ impl GenerateContext {
         }
     }
 
+    // Emits a split that prefers `next` if `greedy` is true, and the returned hole otherwise.
+    fn emit_split(&mut self, next: InstPtr, greedy: bool) -> (InstPtr, HolePtr) {
+        if greedy {
+            let inst = self.emit_inst(Inst::split(next, program::NULL_INST_PTR));
+            (inst, HolePtr::next_1(inst))
+        } else {
+            let inst = self.emit_inst(Inst::split(program::NULL_INST_PTR, next));
+            (inst, HolePtr::next_0(inst))
+        }
+    }
+
     fn emit_inst(&mut self, inst: Inst) -> InstPtr {
         let ptr = self.insts.len();
         self.insts.push(inst);
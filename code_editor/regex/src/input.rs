@@ -5,7 +5,26 @@ pub trait Input {
     fn cursor_back(&self) -> Self::Cursor;
 }
 
-pub trait Cursor {
+impl<'a> Input for &'a str {
+    type Cursor = StrCursor<'a>;
+
+    fn cursor_front(&self) -> Self::Cursor {
+        StrCursor {
+            string: self,
+            position: 0,
+        }
+    }
+
+    fn cursor_back(&self) -> Self::Cursor {
+        StrCursor {
+            string: self,
+            position: self.len(),
+        }
+    }
+}
+
+pub trait Cursor: Clone {
+    fn position(&self) -> usize;
     fn peek_next_char(&self) -> Option<char>;
     fn peek_prev_char(&self) -> Option<char>;
     fn skip_next_char(&mut self);
@@ -19,11 +38,44 @@ pub trait Cursor {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct StrCursor<'a> {
+    string: &'a str,
+    position: usize,
+}
+
+impl<'a> Cursor for StrCursor<'a> {
+    fn position(&self) -> usize {
+        self.position
+    }
+
+    fn peek_next_char(&self) -> Option<char> {
+        self.string[self.position..].chars().next()
+    }
+
+    fn peek_prev_char(&self) -> Option<char> {
+        self.string[..self.position].chars().next_back()
+    }
+
+    fn skip_next_char(&mut self) {
+        self.position += self.peek_next_char().unwrap().len_utf8();
+    }
+
+    fn skip_prev_char(&mut self) {
+        self.position -= self.peek_prev_char().unwrap().len_utf8();
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct Rev<C> {
     cursor: C,
 }
 
 impl<C: Cursor> Cursor for Rev<C> {
+    fn position(&self) -> usize {
+        self.cursor.position()
+    }
+
     fn peek_next_char(&self) -> Option<char> {
         self.cursor.peek_prev_char()
     }
@@ -1,4 +1,8 @@
+mod assertion;
 mod ast;
+mod btree_string;
+mod char_class;
+mod dfa;
 mod input;
 mod generator;
 mod nfa;
@@ -6,11 +10,23 @@ mod parser;
 mod program;
 mod sparse_set;
 
+pub use self::input::{Cursor, Input, Rev, StrCursor};
+
 use {
-    self::{ast::Ast, nfa::Nfa, program::Program, sparse_set::SparseSet},
-    std::{cell::RefCell, result, sync::Arc},
+    self::{
+        assertion::Assertion,
+        ast::Ast,
+        char_class::CharClass,
+        dfa::{Dfa, RunResult},
+        nfa::Nfa,
+        program::Program,
+        sparse_set::SparseSet,
+    },
+    std::{cell::RefCell, ops::Range, result, sync::Arc},
 };
 
+const DEFAULT_DFA_STATE_LIMIT: usize = 1024;
+
 pub struct Regex {
     unique: RefCell<Unique>,
     shared: Arc<Shared>,
@@ -18,23 +34,161 @@ pub struct Regex {
 
 impl Regex {
     pub fn new(pattern: &str) -> Result<Self> {
+        Builder::new().build(pattern)
+    }
+
+    pub fn capture_count(&self) -> usize {
+        self.shared.nfa_program.slot_count / 2
+    }
+
+    pub fn is_match<I: Input>(&self, input: I) -> bool {
+        let cursor = input.cursor_front();
+        let mut unique = self.unique.borrow_mut();
+        if self.shared.dfa_state_limit > 0 {
+            match unique
+                .dfa
+                .run(&self.shared.nfa_program, cursor.clone(), true, None)
+            {
+                RunResult::Matched(_) => return true,
+                RunResult::NotMatched => return false,
+                RunResult::GaveUp => {}
+            }
+        }
+        unique.nfa.run(&self.shared.nfa_program, cursor, &mut [])
+    }
+
+    pub fn find<I: Input>(&self, input: I) -> Option<Range<usize>> {
+        self.find_at(input.cursor_front())
+    }
+
+    pub fn find_at<C: Cursor>(&self, cursor: C) -> Option<Range<usize>> {
+        let mut unique = self.unique.borrow_mut();
+        if self.shared.dfa_state_limit > 0 {
+            if let Some(range) = unique.dfa_find_at(&self.shared, cursor.clone()) {
+                return range;
+            }
+        }
+        let mut slots = [None; 2];
+        if !unique.nfa.run(&self.shared.nfa_program, cursor, &mut slots) {
+            return None;
+        }
+        Some(slots[0].unwrap()..slots[1].unwrap())
+    }
+
+    pub fn find_iter<I: Input>(&self, input: I) -> FindIter<'_, I::Cursor> {
+        FindIter {
+            regex: self,
+            cursor: Some(input.cursor_front()),
+        }
+    }
+
+    /// Returns the range of the last match in `input`.
+    pub fn rfind<I: Input>(&self, input: I) -> Option<Range<usize>> {
+        self.rfind_at(input.cursor_back())
+    }
+
+    /// Returns the range of the match that ends closest before `cursor`, searching backwards.
+    pub fn rfind_at<C: Cursor>(&self, cursor: C) -> Option<Range<usize>> {
+        let mut unique = self.unique.borrow_mut();
+        if self.shared.dfa_state_limit > 0 {
+            if let Some(range) = unique.dfa_rfind_at(&self.shared, cursor.clone()) {
+                return range;
+            }
+        }
+        let mut slots = [None; 2];
+        if !unique
+            .rev_nfa
+            .run(&self.shared.rev_nfa_program, cursor.rev(), &mut slots)
+        {
+            return None;
+        }
+        Some(slots[0].unwrap()..slots[1].unwrap())
+    }
+
+    /// Returns an iterator over the matches in `input`, from last to first.
+    pub fn rfind_iter<I: Input>(&self, input: I) -> RFindIter<'_, I::Cursor> {
+        RFindIter {
+            regex: self,
+            cursor: Some(input.cursor_back()),
+        }
+    }
+
+    pub fn captures<I: Input>(&self, input: I) -> Option<Captures> {
+        self.captures_at(input.cursor_front())
+    }
+
+    pub fn captures_at<C: Cursor>(&self, mut cursor: C) -> Option<Captures> {
+        let mut unique = self.unique.borrow_mut();
+        if self.shared.dfa_state_limit > 0 {
+            // Use the DFA to find where the match starts, so that the NFA only has to run on the
+            // match itself.
+            match unique.dfa_find_at(&self.shared, cursor.clone()) {
+                Some(Some(range)) => move_cursor_to(&mut cursor, range.start),
+                Some(None) => return None,
+                None => {}
+            }
+        }
+        let mut slots = vec![None; self.shared.nfa_program.slot_count];
+        if !unique.nfa.run(&self.shared.nfa_program, cursor, &mut slots) {
+            return None;
+        }
+        Some(Captures { slots })
+    }
+}
+
+impl Clone for Regex {
+    fn clone(&self) -> Self {
+        Self {
+            unique: RefCell::new(Unique::new(&self.shared)),
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+pub struct Builder {
+    dfa_state_limit: usize,
+}
+
+impl Builder {
+    pub fn new() -> Self {
+        Self {
+            dfa_state_limit: DEFAULT_DFA_STATE_LIMIT,
+        }
+    }
+
+    /// Sets the maximum number of states that the lazy DFA caches before its cache is cleared.
+    ///
+    /// A limit of 0 disables the DFA, so that every search runs on the NFA.
+    pub fn dfa_state_limit(&mut self, dfa_state_limit: usize) -> &mut Self {
+        self.dfa_state_limit = dfa_state_limit;
+        self
+    }
+
+    pub fn build(&self, pattern: &str) -> Result<Regex> {
         let ast = parser::parse(pattern)?;
         let nfa_program = generator::generate(&ast);
-        Ok(Self {
-            unique: RefCell::new(Unique {
-                nfa: Nfa::new(&nfa_program),
-            }),
-            shared: Arc::new(Shared { nfa_program }),
+        let rev_nfa_program = generator::generate_rev(&ast);
+        let shared = Arc::new(Shared {
+            nfa_program,
+            rev_nfa_program,
+            dfa_state_limit: self.dfa_state_limit,
+        });
+        Ok(Regex {
+            unique: RefCell::new(Unique::new(&shared)),
+            shared,
         })
     }
+}
 
-    pub fn run<I: Input>(&self, input: I) -> bool {
-        
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 pub type Result<T> = result::Result<T, Error>;
 
+#[derive(Clone, Debug)]
 pub enum Error {
     Parser(parser::Error),
 }
@@ -45,10 +199,159 @@ impl From<parser::Error> for Error {
     }
 }
 
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Captures {
+    slots: Vec<Option<usize>>,
+}
+
+impl Captures {
+    pub fn len(&self) -> usize {
+        self.slots.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Range<usize>> {
+        match (self.slots.get(2 * index)?, self.slots.get(2 * index + 1)?) {
+            (Some(start), Some(end)) => Some(*start..*end),
+            _ => None,
+        }
+    }
+}
+
+pub struct FindIter<'a, C> {
+    regex: &'a Regex,
+    cursor: Option<C>,
+}
+
+impl<'a, C: Cursor> Iterator for FindIter<'a, C> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut cursor = self.cursor.take()?;
+        let range = self.regex.find_at(cursor.clone())?;
+        move_cursor_to(&mut cursor, range.end);
+        if range.is_empty() {
+            if cursor.peek_next_char().is_none() {
+                return Some(range);
+            }
+            cursor.skip_next_char();
+        }
+        self.cursor = Some(cursor);
+        Some(range)
+    }
+}
+
+pub struct RFindIter<'a, C> {
+    regex: &'a Regex,
+    cursor: Option<C>,
+}
+
+impl<'a, C: Cursor> Iterator for RFindIter<'a, C> {
+    type Item = Range<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut cursor = self.cursor.take()?;
+        let range = self.regex.rfind_at(cursor.clone())?;
+        move_cursor_to(&mut cursor, range.start);
+        if range.is_empty() {
+            if cursor.peek_prev_char().is_none() {
+                return Some(range);
+            }
+            cursor.skip_prev_char();
+        }
+        self.cursor = Some(cursor);
+        Some(range)
+    }
+}
+
 struct Unique {
     nfa: Nfa,
+    rev_nfa: Nfa,
+    dfa: Dfa,
+    anchored_dfa: Dfa,
+    rev_dfa: Dfa,
+    anchored_rev_dfa: Dfa,
+}
+
+impl Unique {
+    fn new(shared: &Shared) -> Self {
+        Self {
+            nfa: Nfa::new(&shared.nfa_program),
+            rev_nfa: Nfa::new(&shared.rev_nfa_program),
+            dfa: Dfa::new(&shared.nfa_program, false, shared.dfa_state_limit),
+            anchored_dfa: Dfa::new(&shared.nfa_program, true, shared.dfa_state_limit),
+            rev_dfa: Dfa::new(&shared.rev_nfa_program, false, shared.dfa_state_limit),
+            anchored_rev_dfa: Dfa::new(&shared.rev_nfa_program, true, shared.dfa_state_limit),
+        }
+    }
+
+    // Returns `None` if the DFA gave up, in which case the caller should fall back to the NFA.
+    //
+    // The forward DFA finds where the leftmost-first match ends. The leftmost-first match starts
+    // at the leftmost position from which any match exists, so running the reverse DFA from there
+    // to find the longest match that ends there, without running past the start of the search,
+    // gives us where it starts.
+    fn dfa_find_at<C: Cursor>(
+        &mut self,
+        shared: &Shared,
+        mut cursor: C,
+    ) -> Option<Option<Range<usize>>> {
+        let start = cursor.position();
+        let end = match self.dfa.run(&shared.nfa_program, cursor.clone(), false, None) {
+            RunResult::Matched(end) => end,
+            RunResult::NotMatched => return Some(None),
+            RunResult::GaveUp => return None,
+        };
+        move_cursor_to(&mut cursor, end);
+        match self
+            .anchored_rev_dfa
+            .run(&shared.rev_nfa_program, cursor.rev(), false, Some(start))
+        {
+            RunResult::Matched(start) => Some(Some(start..end)),
+            _ => None,
+        }
+    }
+
+    // Like `dfa_find_at`, but with the directions swapped.
+    fn dfa_rfind_at<C: Cursor>(
+        &mut self,
+        shared: &Shared,
+        mut cursor: C,
+    ) -> Option<Option<Range<usize>>> {
+        let end = cursor.position();
+        let start = match self
+            .rev_dfa
+            .run(&shared.rev_nfa_program, cursor.clone().rev(), false, None)
+        {
+            RunResult::Matched(start) => start,
+            RunResult::NotMatched => return Some(None),
+            RunResult::GaveUp => return None,
+        };
+        move_cursor_to(&mut cursor, start);
+        match self
+            .anchored_dfa
+            .run(&shared.nfa_program, cursor, false, Some(end))
+        {
+            RunResult::Matched(end) => Some(Some(start..end)),
+            _ => None,
+        }
+    }
 }
 
 struct Shared {
     nfa_program: Program,
+    rev_nfa_program: Program,
+    dfa_state_limit: usize,
+}
+
+fn move_cursor_to<C: Cursor>(cursor: &mut C, position: usize) {
+    while cursor.position() < position {
+        cursor.skip_next_char();
+    }
+    while cursor.position() > position {
+        cursor.skip_prev_char();
+    }
 }
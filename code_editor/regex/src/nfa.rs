@@ -7,27 +7,40 @@ use crate::{
 pub struct Nfa {
     current_threads: Threads,
     new_threads: Threads,
-    add_thread_stack: Vec<InstPtr>,
+    add_thread_stack: Vec<AddThreadFrame>,
+    slots: Box<[Option<usize>]>,
 }
 
 impl Nfa {
     pub fn new(program: &Program) -> Self {
         Self {
-            current_threads: Threads::new(program.insts.len()),
-            new_threads: Threads::new(program.insts.len()),
+            current_threads: Threads::new(program.insts.len(), program.slot_count),
+            new_threads: Threads::new(program.insts.len(), program.slot_count),
             add_thread_stack: Vec::new(),
+            slots: vec![None; program.slot_count].into_boxed_slice(),
         }
     }
 
-    pub fn run<C: Cursor>(&mut self, program: &Program, mut cursor: C) -> bool {
+    pub fn run<C: Cursor>(
+        &mut self,
+        program: &Program,
+        mut cursor: C,
+        slots: &mut [Option<usize>],
+    ) -> bool {
         use std::mem;
 
         let mut matched = false;
+        self.new_threads.inst.clear();
         loop {
             if !matched {
+                for slot in self.slots.iter_mut() {
+                    *slot = None;
+                }
                 self.new_threads.add_thread(
                     program.start,
                     &program.insts,
+                    &cursor,
+                    &mut self.slots,
                     &mut self.add_thread_stack,
                 );
             }
@@ -37,22 +50,35 @@ impl Nfa {
                 break;
             }
             let c0 = cursor.peek_next_char();
-            cursor.skip_next_char();
+            if c0.is_some() {
+                cursor.skip_next_char();
+            }
             for &inst in &self.current_threads.inst {
-                match program.insts[inst] {
+                let next = match program.insts[inst] {
                     Inst::Match => {
+                        let thread_slots = self.current_threads.slots(inst);
+                        slots.copy_from_slice(&thread_slots[..slots.len()]);
                         matched = true;
+                        if slots.is_empty() {
+                            return true;
+                        }
                         break;
                     }
-                    Inst::Char(next, c1) if c0.map_or(false, |c0| c0 == c1) => {
-                        self.new_threads.add_thread(
-                            next,
-                            &program.insts,
-                            &mut self.add_thread_stack,
-                        );
+                    Inst::Char(next, c1) if c0 == Some(c1) => next,
+                    Inst::Class(next, ref class) if c0.is_some_and(|c0| class.contains(c0)) => {
+                        next
                     }
-                    _ => {}
-                }
+                    _ => continue,
+                };
+                self.slots
+                    .copy_from_slice(self.current_threads.slots(inst));
+                self.new_threads.add_thread(
+                    next,
+                    &program.insts,
+                    &cursor,
+                    &mut self.slots,
+                    &mut self.add_thread_stack,
+                );
             }
             if c0.is_none() {
                 break;
@@ -64,27 +90,73 @@ impl Nfa {
 
 struct Threads {
     inst: SparseSet,
+    slots: Box<[Option<usize>]>,
+    slot_count: usize,
 }
 
 impl Threads {
-    fn new(count: usize) -> Self {
+    fn new(count: usize, slot_count: usize) -> Self {
         Self {
             inst: SparseSet::new(count),
+            slots: vec![None; count * slot_count].into_boxed_slice(),
+            slot_count,
         }
     }
 
-    fn add_thread(&mut self, inst: InstPtr, insts: &[Inst], stack: &mut Vec<InstPtr>) {
-        stack.push(inst);
-        while let Some(mut inst) = stack.pop() {
-            while self.inst.insert(inst) {
-                match insts[inst] {
-                    Inst::Split(next_0, next_1) => {
-                        stack.push(next_1);
-                        inst = next_0;
+    fn slots(&self, inst: InstPtr) -> &[Option<usize>] {
+        &self.slots[inst * self.slot_count..][..self.slot_count]
+    }
+
+    fn slots_mut(&mut self, inst: InstPtr) -> &mut [Option<usize>] {
+        &mut self.slots[inst * self.slot_count..][..self.slot_count]
+    }
+
+    fn add_thread<C: Cursor>(
+        &mut self,
+        inst: InstPtr,
+        insts: &[Inst],
+        cursor: &C,
+        slots: &mut [Option<usize>],
+        stack: &mut Vec<AddThreadFrame>,
+    ) {
+        stack.push(AddThreadFrame::AddThread(inst));
+        while let Some(frame) = stack.pop() {
+            match frame {
+                AddThreadFrame::AddThread(mut inst) => {
+                    while self.inst.insert(inst) {
+                        match insts[inst] {
+                            Inst::Nop(next) => inst = next,
+                            Inst::Assert(next, assertion) => {
+                                if !assertion
+                                    .is_satisfied(cursor.peek_prev_char(), cursor.peek_next_char())
+                                {
+                                    break;
+                                }
+                                inst = next;
+                            }
+                            Inst::Split(next_0, next_1) => {
+                                stack.push(AddThreadFrame::AddThread(next_1));
+                                inst = next_0;
+                            }
+                            Inst::Save(next, slot) => {
+                                stack.push(AddThreadFrame::RestoreSlot(slot, slots[slot]));
+                                slots[slot] = Some(cursor.position());
+                                inst = next;
+                            }
+                            _ => {
+                                self.slots_mut(inst).copy_from_slice(slots);
+                                break;
+                            }
+                        }
                     }
-                    _ => break,
                 }
+                AddThreadFrame::RestoreSlot(slot, value) => slots[slot] = value,
             }
         }
     }
 }
+
+enum AddThreadFrame {
+    AddThread(InstPtr),
+    RestoreSlot(usize, Option<usize>),
+}
@@ -1,11 +1,29 @@
-use {crate::Ast, std::result};
+use {
+    crate::{
+        char_class::{CharClassItem, PerlClass},
+        Assertion, Ast, CharClass,
+    },
+    std::result,
+};
+
+const MAX_REPEAT_COUNT: usize = 1000;
+// Counted repetitions are expanded into copies, so nesting them multiplies the size of the
+// program. This bounds the number of instructions the pattern compiles to.
+const MAX_PROGRAM_SIZE: usize = 100_000;
 
 pub fn parse(pattern: &str) -> Result<Ast> {
-    ParseContext { pattern, pos: 0 }.parse()
+    ParseContext {
+        pattern,
+        pos: 0,
+        group_count: 0,
+        size: 0,
+    }
+    .parse()
 }
 
 pub type Result<T> = result::Result<T, Error>;
 
+#[derive(Clone, Debug)]
 pub struct Error {
     pub pos: usize,
     pub message: String,
@@ -14,11 +32,18 @@ pub struct Error {
 struct ParseContext<'a> {
     pattern: &'a str,
     pos: usize,
+    group_count: usize,
+    // An estimate of the number of instructions in the program for what was parsed so far.
+    size: usize,
 }
 
 impl<'a> ParseContext<'a> {
     fn parse(&mut self) -> Result<Ast> {
-        self.parse_alt()
+        let ast = self.parse_alt()?;
+        if self.peek_char().is_some() {
+            return Err(self.error("unmatched ')'"));
+        }
+        Ok(ast)
     }
 
     fn parse_alt(&mut self) -> Result<Ast> {
@@ -30,6 +55,7 @@ impl<'a> ParseContext<'a> {
         loop {
             self.skip_char();
             asts.push(self.parse_cat()?);
+            self.grow(2)?;
             if self.peek_char() != Some('|') {
                 break;
             }
@@ -38,64 +64,315 @@ impl<'a> ParseContext<'a> {
     }
 
     fn parse_cat(&mut self) -> Result<Ast> {
-        let ast = self.parse_rep()?;
-        if self.peek_char().map_or(true, |c| c == '|' || c == ')') {
-            return Ok(ast);
-        }
-        let mut asts = vec![ast];
-        loop {
-            self.skip_char();
+        let mut asts = Vec::new();
+        while self.peek_char().is_some_and(|c| c != '|' && c != ')') {
             asts.push(self.parse_rep()?);
-            if self.peek_char().map_or(true, |c| c == '|' || c == ')') {
-                break;
-            }
         }
-        Ok(Ast::Alt(asts))
+        Ok(match asts.len() {
+            0 => Ast::Empty,
+            1 => asts.pop().unwrap(),
+            _ => Ast::Cat(asts),
+        })
     }
 
     fn parse_rep(&mut self) -> Result<Ast> {
+        let start_size = self.size;
         let ast = self.parse_atom()?;
-        Ok(match self.peek_char() {
+        let (min, max) = match self.peek_char() {
             Some('?') => {
                 self.skip_char();
-                Ast::Quest(Box::new(ast))
+                (0, Some(1))
             }
             Some('*') => {
                 self.skip_char();
-                Ast::Star(Box::new(ast))
+                (0, None)
             }
             Some('+') => {
                 self.skip_char();
-                Ast::Plus(Box::new(ast))
+                (1, None)
             }
-            _ => ast,
-        })
+            Some('{') if self.is_at_counts() => {
+                self.skip_char();
+                self.parse_counts()?
+            }
+            _ => return Ok(ast),
+        };
+        let greedy = if self.peek_char() == Some('?') {
+            self.skip_char();
+            false
+        } else {
+            true
+        };
+        if self.peek_char().is_some_and(|c| c == '?' || c == '*' || c == '+')
+            || self.is_at_counts()
+        {
+            return Err(self.error("repeated repetition operator"));
+        }
+        // Each copy gets a split or jump of its own on top of the instructions for the atom.
+        let size = self.size - start_size;
+        let copies = max.unwrap_or(min).max(1);
+        self.grow((size + 2) * copies - size)?;
+        Ok(repeat(ast, min, max, greedy))
+    }
+
+    // A `{` that does not start a well-formed repetition count is treated as a literal.
+    fn is_at_counts(&self) -> bool {
+        let mut chars = self.pattern[self.pos..].chars().peekable();
+        if chars.next() != Some('{') || !chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+            return false;
+        }
+        while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+            chars.next();
+        }
+        if chars.peek() == Some(&',') {
+            chars.next();
+            while chars.peek().is_some_and(|c| c.is_ascii_digit()) {
+                chars.next();
+            }
+        }
+        chars.next() == Some('}')
+    }
+
+    fn parse_counts(&mut self) -> Result<(usize, Option<usize>)> {
+        let min = self.parse_count()?;
+        let max = if self.peek_char() == Some(',') {
+            self.skip_char();
+            if self.peek_char() == Some('}') {
+                None
+            } else {
+                Some(self.parse_count()?)
+            }
+        } else {
+            Some(min)
+        };
+        if self.peek_char() != Some('}') {
+            return Err(self.error("expected '}'"));
+        }
+        self.skip_char();
+        if max.is_some_and(|max| max < min) {
+            return Err(self.error("invalid repetition count range"));
+        }
+        Ok((min, max))
+    }
+
+    fn parse_count(&mut self) -> Result<usize> {
+        let start = self.pos;
+        while self.peek_char().is_some_and(|c| c.is_ascii_digit()) {
+            self.skip_char();
+        }
+        if start == self.pos {
+            return Err(self.error("expected repetition count"));
+        }
+        match self.pattern[start..self.pos].parse::<usize>() {
+            Ok(count) if count <= MAX_REPEAT_COUNT => Ok(count),
+            _ => Err(self.error("repetition count too large")),
+        }
     }
 
     fn parse_atom(&mut self) -> Result<Ast> {
+        if self.peek_char() != Some('(') {
+            self.grow(1)?;
+        }
         Ok(match self.peek_char() {
             Some('(') => {
                 self.skip_char();
-                let ast = self.parse()?;
+                let index = if self.pattern[self.pos..].starts_with("?:") {
+                    self.pos += 2;
+                    None
+                } else {
+                    self.group_count += 1;
+                    Some(self.group_count)
+                };
+                let ast = self.parse_alt()?;
                 if self.peek_char() != Some(')') {
-                    return Err(self.error(""));
+                    return Err(self.error("expected ')'"));
+                }
+                self.skip_char();
+                if index.is_some() {
+                    self.grow(2)?;
+                }
+                match index {
+                    Some(index) => Ast::Capture(Box::new(ast), index),
+                    None => ast,
                 }
+            }
+            Some('[') => {
+                self.skip_char();
+                Ast::Class(self.parse_class()?)
+            }
+            Some('.') => {
+                self.skip_char();
+                Ast::Class(CharClass::any())
+            }
+            Some('^') => {
+                self.skip_char();
+                Ast::Assert(Assertion::StartOfLine)
+            }
+            Some('$') => {
+                self.skip_char();
+                Ast::Assert(Assertion::EndOfLine)
+            }
+            Some('\\') => {
                 self.skip_char();
-                ast
+                match self.parse_escape()? {
+                    Escape::Char(c) => Ast::Char(c),
+                    Escape::Perl(perl_class, negated) => {
+                        Ast::Class(CharClass::perl(perl_class, negated))
+                    }
+                    Escape::Assert(assertion) => Ast::Assert(assertion),
+                }
+            }
+            Some('|') | Some(')') | Some('?') | Some('*') | Some('+') => {
+                return Err(self.error("expected atom"))
             }
+            Some('{') if self.is_at_counts() => return Err(self.error("expected atom")),
             Some(c) => {
                 self.skip_char();
                 Ast::Char(c)
             }
-            None => return Err(self.error("")),
+            None => return Err(self.error("unexpected end of pattern")),
+        })
+    }
+
+    fn parse_class(&mut self) -> Result<CharClass> {
+        let negated = if self.peek_char() == Some('^') {
+            self.skip_char();
+            true
+        } else {
+            false
+        };
+        let mut items = Vec::new();
+        let mut first = true;
+        loop {
+            let first_char = match self.peek_char() {
+                Some(']') if !first => {
+                    self.skip_char();
+                    break;
+                }
+                Some(_) => match self.parse_class_char()? {
+                    Escape::Char(c) => c,
+                    Escape::Perl(perl_class, negated) => {
+                        items.push(CharClassItem::Perl(perl_class, negated));
+                        first = false;
+                        continue;
+                    }
+                    Escape::Assert(_) => {
+                        return Err(self.error("assertions are not allowed in a class"))
+                    }
+                },
+                None => return Err(self.error("expected ']'")),
+            };
+            first = false;
+            if self.peek_char() == Some('-')
+                && self.pattern[self.pos + 1..]
+                    .chars()
+                    .next()
+                    .is_some_and(|c| c != ']')
+            {
+                self.skip_char();
+                let last_char = match self.parse_class_char()? {
+                    Escape::Char(c) => c,
+                    _ => return Err(self.error("expected character")),
+                };
+                if last_char < first_char {
+                    return Err(self.error("invalid class range"));
+                }
+                items.push(CharClassItem::Range(first_char, last_char));
+            } else {
+                items.push(CharClassItem::Range(first_char, first_char));
+            }
+        }
+        Ok(CharClass { negated, items })
+    }
+
+    fn parse_class_char(&mut self) -> Result<Escape> {
+        match self.peek_char() {
+            Some('\\') => {
+                self.skip_char();
+                self.parse_escape()
+            }
+            Some(c) => {
+                self.skip_char();
+                Ok(Escape::Char(c))
+            }
+            None => Err(self.error("expected ']'")),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Escape> {
+        let c = match self.peek_char() {
+            Some(c) => c,
+            None => return Err(self.error("expected escape sequence")),
+        };
+        self.skip_char();
+        Ok(match c {
+            'd' => Escape::Perl(PerlClass::Digit, false),
+            'D' => Escape::Perl(PerlClass::Digit, true),
+            's' => Escape::Perl(PerlClass::Space, false),
+            'S' => Escape::Perl(PerlClass::Space, true),
+            'w' => Escape::Perl(PerlClass::Word, false),
+            'W' => Escape::Perl(PerlClass::Word, true),
+            'b' => Escape::Assert(Assertion::WordBoundary),
+            'B' => Escape::Assert(Assertion::NotWordBoundary),
+            'A' => Escape::Assert(Assertion::StartOfText),
+            'z' => Escape::Assert(Assertion::EndOfText),
+            'n' => Escape::Char('\n'),
+            'r' => Escape::Char('\r'),
+            't' => Escape::Char('\t'),
+            'f' => Escape::Char('\x0C'),
+            'v' => Escape::Char('\x0B'),
+            '0' => Escape::Char('\0'),
+            'x' => Escape::Char(self.parse_hex_escape()?),
+            c if c.is_ascii_alphanumeric() => {
+                return Err(self.error("unknown escape sequence"))
+            }
+            c => Escape::Char(c),
         })
     }
 
+    fn parse_hex_escape(&mut self) -> Result<char> {
+        let start;
+        let end;
+        if self.peek_char() == Some('{') {
+            self.skip_char();
+            start = self.pos;
+            while self.peek_char().is_some_and(|c| c.is_ascii_hexdigit()) {
+                self.skip_char();
+            }
+            end = self.pos;
+            if self.peek_char() != Some('}') {
+                return Err(self.error("expected '}'"));
+            }
+            self.skip_char();
+        } else {
+            start = self.pos;
+            for _ in 0..2 {
+                if !self.peek_char().is_some_and(|c| c.is_ascii_hexdigit()) {
+                    return Err(self.error("expected hexadecimal digit"));
+                }
+                self.skip_char();
+            }
+            end = self.pos;
+        }
+        u32::from_str_radix(&self.pattern[start..end], 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| self.error("invalid character code"))
+    }
+
+    fn grow(&mut self, size: usize) -> Result<()> {
+        self.size += size;
+        if self.size > MAX_PROGRAM_SIZE {
+            return Err(self.error("pattern too large"));
+        }
+        Ok(())
+    }
+
     fn error(&self, message: &str) -> Error {
-        return Error {
+        Error {
             pos: self.pos,
             message: message.to_string(),
-        };
+        }
     }
 
     fn peek_char(&self) -> Option<char> {
@@ -106,3 +383,48 @@ impl<'a> ParseContext<'a> {
         self.pos += self.peek_char().unwrap().len_utf8();
     }
 }
+
+enum Escape {
+    Char(char),
+    Perl(PerlClass, bool),
+    Assert(Assertion),
+}
+
+fn repeat(ast: Ast, min: usize, max: Option<usize>, greedy: bool) -> Ast {
+    match (min, max) {
+        (0, Some(1)) => return Ast::Quest(Box::new(ast), greedy),
+        (0, None) => return Ast::Star(Box::new(ast), greedy),
+        (1, None) => return Ast::Plus(Box::new(ast), greedy),
+        (1, Some(1)) => return ast,
+        (0, Some(0)) => return Ast::Empty,
+        _ => {}
+    }
+    let mut asts = Vec::new();
+    for _ in 0..min.saturating_sub(1) {
+        asts.push(ast.clone());
+    }
+    match max {
+        None => asts.push(Ast::Plus(Box::new(ast), greedy)),
+        Some(max) => {
+            if min > 0 {
+                asts.push(ast.clone());
+            }
+            // `a{n,m}` becomes `a...a(a(a...)?)?` so that the optional copies are nested.
+            let mut optional = None;
+            for _ in min..max {
+                optional = Some(Ast::Quest(
+                    Box::new(match optional {
+                        Some(optional) => Ast::Cat(vec![ast.clone(), optional]),
+                        None => ast.clone(),
+                    }),
+                    greedy,
+                ));
+            }
+            asts.extend(optional);
+        }
+    }
+    match asts.len() {
+        1 => asts.pop().unwrap(),
+        _ => Ast::Cat(asts),
+    }
+}
@@ -1,15 +1,21 @@
+use crate::{Assertion, CharClass};
+
 pub const NULL_INST_PTR: InstPtr = 0;
 
 pub struct Program {
     pub start: InstPtr,
     pub insts: Vec<Inst>,
+    pub slot_count: usize,
 }
 
 pub enum Inst {
-    Nop,
+    Nop(InstPtr),
     Match,
     Char(InstPtr, char),
+    Class(InstPtr, CharClass),
+    Assert(InstPtr, Assertion),
     Split(InstPtr, InstPtr),
+    Save(InstPtr, usize),
 }
 
 impl Inst {
@@ -17,13 +23,30 @@ impl Inst {
         Self::Char(next_0, c)
     }
 
+    pub fn class(next_0: InstPtr, class: CharClass) -> Self {
+        Self::Class(next_0, class)
+    }
+
+    pub fn assert(next_0: InstPtr, assertion: Assertion) -> Self {
+        Self::Assert(next_0, assertion)
+    }
+
     pub fn split(next_0: InstPtr, next_1: InstPtr) -> Self {
         Self::Split(next_0, next_1)
     }
 
+    pub fn save(next_0: InstPtr, slot: usize) -> Self {
+        Self::Save(next_0, slot)
+    }
+
     pub fn next_0(&self) -> &InstPtr {
         match self {
-            Self::Char(next_0, _) | Self::Split(next_0, _) => next_0,
+            Self::Nop(next_0)
+            | Self::Char(next_0, _)
+            | Self::Class(next_0, _)
+            | Self::Assert(next_0, _)
+            | Self::Split(next_0, _)
+            | Self::Save(next_0, _) => next_0,
             _ => panic!(),
         }
     }
@@ -37,7 +60,12 @@ impl Inst {
 
     pub fn next_0_mut(&mut self) -> &mut InstPtr {
         match self {
-            Self::Char(next_0, _) | Self::Split(next_0, _) => next_0,
+            Self::Nop(next_0)
+            | Self::Char(next_0, _)
+            | Self::Class(next_0, _)
+            | Self::Assert(next_0, _)
+            | Self::Split(next_0, _)
+            | Self::Save(next_0, _) => next_0,
             _ => panic!(),
         }
     }
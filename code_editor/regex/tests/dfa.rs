@@ -0,0 +1,85 @@
+use {
+    makepad_regex::{Builder, Regex},
+    std::ops::Range,
+};
+
+const PATTERNS: &[&str] = &[
+    "fn",
+    r"\bfn\s+\w+",
+    "[a-z]+_[a-z]+",
+    r"^\s*//.*$",
+    r"\d+(\.\d+)?",
+    "(?:let|const) mut",
+    "a.*?b",
+    "x{2,4}|y+z",
+    r"é\w*\b",
+    "(ab|a)(bc|c)?",
+    r"\B[aeiou]{2}",
+    "$",
+];
+
+// Generates pseudo-random text that looks enough like source code to make the patterns above
+// match in interesting ways.
+fn generate_text(len: usize) -> String {
+    const WORDS: &[&str] = &[
+        "fn", "let", "mut", "const", "foo_bar", "x", "xxx", "yyz", "ab", "abc", "é", "éa", "3.14",
+        "42", "//", " ", " ", "  ", "\n", "(", ")", "{", "}", ";", "aeiou",
+    ];
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut text = String::new();
+    while text.len() < len {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        text.push_str(WORDS[(state % WORDS.len() as u64) as usize]);
+    }
+    text
+}
+
+fn find_all(regex: &Regex, text: &str) -> (Vec<Range<usize>>, Vec<Range<usize>>) {
+    (
+        regex.find_iter(text).collect(),
+        regex.rfind_iter(text).collect(),
+    )
+}
+
+#[test]
+fn test_dfa_agrees_with_nfa() {
+    let text = generate_text(100_000);
+    for pattern in PATTERNS {
+        let dfa_regex = Regex::new(pattern).unwrap();
+        let nfa_regex = Builder::new().dfa_state_limit(0).build(pattern).unwrap();
+
+        let dfa_matches = find_all(&dfa_regex, &text);
+        let nfa_matches = find_all(&nfa_regex, &text);
+        assert_eq!(dfa_matches, nfa_matches, "pattern: {}", pattern);
+        assert_eq!(
+            dfa_regex.captures(text.as_str()),
+            nfa_regex.captures(text.as_str()),
+            "pattern: {}",
+            pattern
+        );
+    }
+}
+
+#[test]
+fn test_dfa_with_thrashing_cache_agrees_with_nfa() {
+    let text = generate_text(10_000);
+    for pattern in PATTERNS {
+        let dfa_regex = Builder::new().dfa_state_limit(2).build(pattern).unwrap();
+        let nfa_regex = Builder::new().dfa_state_limit(0).build(pattern).unwrap();
+        assert_eq!(
+            find_all(&dfa_regex, &text),
+            find_all(&nfa_regex, &text),
+            "pattern: {}",
+            pattern
+        );
+    }
+}
+
+#[test]
+fn test_dfa_is_match() {
+    let regex = Regex::new(r"\bmut\b").unwrap();
+    assert!(regex.is_match("let mut x"));
+    assert!(!regex.is_match("let mutable x"));
+}
@@ -0,0 +1,240 @@
+// Clippy checks patterns against the syntax of the `regex` crate, which differs from ours.
+#![allow(clippy::invalid_regex)]
+
+use makepad_regex::Regex;
+
+#[test]
+fn test_is_match() {
+    let regex = Regex::new("ab|cd").unwrap();
+    assert!(regex.is_match("xxcdxx"));
+    assert!(!regex.is_match("acbd"));
+}
+
+#[test]
+fn test_find() {
+    let regex = Regex::new("a(b|c)*d").unwrap();
+    assert_eq!(regex.find("xxabcbdxx"), Some(2..7));
+    assert_eq!(regex.find("xxabcbxx"), None);
+}
+
+#[test]
+fn test_find_leftmost_first() {
+    let regex = Regex::new("a|ab").unwrap();
+    assert_eq!(regex.find("ab"), Some(0..1));
+    let regex = Regex::new("ab|a").unwrap();
+    assert_eq!(regex.find("ab"), Some(0..2));
+}
+
+#[test]
+fn test_find_iter() {
+    let regex = Regex::new("a+").unwrap();
+    assert_eq!(
+        regex.find_iter("aabaaab").collect::<Vec<_>>(),
+        vec![0..2, 3..6]
+    );
+    let regex = Regex::new("a*").unwrap();
+    assert_eq!(
+        regex.find_iter("baa").collect::<Vec<_>>(),
+        vec![0..0, 1..3, 3..3]
+    );
+}
+
+#[test]
+fn test_captures() {
+    let regex = Regex::new("(a+)(b(c)?)").unwrap();
+    assert_eq!(regex.capture_count(), 4);
+    let captures = regex.captures("xaabx").unwrap();
+    assert_eq!(captures.get(0), Some(1..4));
+    assert_eq!(captures.get(1), Some(1..3));
+    assert_eq!(captures.get(2), Some(3..4));
+    assert_eq!(captures.get(3), None);
+}
+
+#[test]
+fn test_captures_last_iteration() {
+    let regex = Regex::new("(a|b)+").unwrap();
+    let captures = regex.captures("abba").unwrap();
+    assert_eq!(captures.get(0), Some(0..4));
+    assert_eq!(captures.get(1), Some(3..4));
+}
+
+#[test]
+fn test_non_ascii() {
+    let regex = Regex::new("é+").unwrap();
+    assert_eq!(regex.find("caféé!"), Some(3..7));
+}
+
+#[test]
+fn test_parse_error() {
+    assert!(Regex::new("(ab").is_err());
+    assert!(Regex::new("ab)").is_err());
+    assert!(Regex::new("*a").is_err());
+    assert!(Regex::new("a**").is_err());
+    assert!(Regex::new("a{3,2}").is_err());
+    assert!(Regex::new("[a").is_err());
+    assert!(Regex::new("[z-a]").is_err());
+    assert!(Regex::new(r"\q").is_err());
+}
+
+#[test]
+fn test_pattern_too_large() {
+    assert!(Regex::new("((a{1000}){1000}){1000}").is_err());
+    assert!(Regex::new("(a{1000}){1000}").is_err());
+    assert!(Regex::new("a{1000}a{1000}").is_ok());
+    assert!(Regex::new("(a{10}|b{10}){1000}").is_ok());
+}
+
+#[test]
+fn test_class() {
+    let regex = Regex::new("[a-c_]+").unwrap();
+    assert_eq!(regex.find("xx_abcd"), Some(2..6));
+    let regex = Regex::new("[^a-c]+").unwrap();
+    assert_eq!(regex.find("abxyzc"), Some(2..5));
+    let regex = Regex::new("[]a-]+").unwrap();
+    assert_eq!(regex.find("x]-a"), Some(1..4));
+    let regex = Regex::new(r"[\d.]+").unwrap();
+    assert_eq!(regex.find("v1.25"), Some(1..5));
+}
+
+#[test]
+fn test_dot() {
+    let regex = Regex::new("a.c").unwrap();
+    assert_eq!(regex.find("abc"), Some(0..3));
+    assert_eq!(regex.find("a\nc"), None);
+}
+
+#[test]
+fn test_perl_classes() {
+    let regex = Regex::new(r"\d+\s\w+").unwrap();
+    assert_eq!(regex.find("--42 straße--"), Some(2..12));
+    let regex = Regex::new(r"\D\S\W").unwrap();
+    assert_eq!(regex.find("1 ab!"), Some(2..5));
+}
+
+#[test]
+fn test_anchors() {
+    let regex = Regex::new("^ab$").unwrap();
+    assert_eq!(
+        regex.find_iter("ab\nxab\nab").collect::<Vec<_>>(),
+        vec![0..2, 7..9]
+    );
+    let regex = Regex::new(r"\Aab").unwrap();
+    assert_eq!(regex.find_iter("abab").collect::<Vec<_>>(), vec![0..2]);
+    let regex = Regex::new(r"ab\z").unwrap();
+    assert_eq!(regex.find_iter("abab").collect::<Vec<_>>(), vec![2..4]);
+}
+
+#[test]
+fn test_word_boundary() {
+    let regex = Regex::new(r"\bcat\b").unwrap();
+    assert_eq!(regex.find("concat cat"), Some(7..10));
+    let regex = Regex::new(r"\Bcat").unwrap();
+    assert_eq!(regex.find("cat concat"), Some(7..10));
+}
+
+#[test]
+fn test_counted_repetition() {
+    let regex = Regex::new("a{2,3}").unwrap();
+    assert_eq!(
+        regex.find_iter("a aa aaaa").collect::<Vec<_>>(),
+        vec![2..4, 5..8]
+    );
+    let regex = Regex::new("^a{2}$").unwrap();
+    assert!(regex.is_match("aa"));
+    assert!(!regex.is_match("aaa"));
+    let regex = Regex::new("a{2,}").unwrap();
+    assert_eq!(regex.find("aaaaa"), Some(0..5));
+    let regex = Regex::new("x{}").unwrap();
+    assert_eq!(regex.find("x{}"), Some(0..3));
+}
+
+#[test]
+fn test_non_greedy() {
+    let regex = Regex::new("<.+?>").unwrap();
+    assert_eq!(regex.find("<a><b>"), Some(0..3));
+    let regex = Regex::new("a{2,4}?").unwrap();
+    assert_eq!(regex.find("aaaa"), Some(0..2));
+    let regex = Regex::new("a??b").unwrap();
+    assert_eq!(regex.find("ab"), Some(0..2));
+}
+
+#[test]
+fn test_non_capturing_group() {
+    let regex = Regex::new("(?:a|b)+(c)").unwrap();
+    assert_eq!(regex.capture_count(), 2);
+    let captures = regex.captures("abac").unwrap();
+    assert_eq!(captures.get(1), Some(3..4));
+}
+
+#[test]
+fn test_escapes() {
+    let regex = Regex::new(r"\(\x41\x{1F600}\)\t").unwrap();
+    assert_eq!(regex.find("(A😀)\t"), Some(0..8));
+}
+
+#[test]
+fn test_empty_alternative() {
+    let regex = Regex::new("a(|b)c").unwrap();
+    assert!(regex.is_match("ac"));
+    assert!(regex.is_match("abc"));
+}
+
+#[test]
+fn test_rfind() {
+    let regex = Regex::new("a+b").unwrap();
+    assert_eq!(regex.rfind("aab xab ab"), Some(8..10));
+    assert_eq!(
+        regex.rfind_iter("aab xab ab").collect::<Vec<_>>(),
+        vec![8..10, 5..7, 0..3]
+    );
+    let regex = Regex::new("^a(b)").unwrap();
+    assert_eq!(
+        regex.rfind_iter("ab\nab ab").collect::<Vec<_>>(),
+        vec![3..5, 0..2]
+    );
+}
+
+#[test]
+fn test_rfind_at() {
+    use makepad_regex::{Cursor, Input};
+
+    let regex = Regex::new(r"\bfoo\b").unwrap();
+    let mut cursor = "foo foo foobar foo".cursor_front();
+    while cursor.position() != 12 {
+        cursor.skip_next_char();
+    }
+    assert_eq!(regex.rfind_at(cursor), Some(4..7));
+}
+
+#[test]
+fn test_btree_string() {
+    use makepad_collections::BTreeString;
+
+    let string = BTreeString::from("the quick brown fox jumps over the lazy dog, the end");
+    let regex = Regex::new(r"\bthe\b").unwrap();
+    assert_eq!(
+        regex.find_iter(&string).collect::<Vec<_>>(),
+        vec![0..3, 31..34, 45..48]
+    );
+    assert_eq!(
+        regex.rfind_iter(&string).collect::<Vec<_>>(),
+        vec![45..48, 31..34, 0..3]
+    );
+    let regex = Regex::new("q[a-z]+ (b)rown").unwrap();
+    let captures = regex.captures(&string).unwrap();
+    assert_eq!(captures.get(0), Some(4..15));
+    assert_eq!(captures.get(1), Some(10..11));
+}
+
+#[test]
+fn test_btree_string_slice() {
+    use makepad_collections::BTreeString;
+
+    let string = BTreeString::from("ééé abc ééé abc ééé");
+    let regex = Regex::new(r"é+").unwrap();
+    assert_eq!(
+        regex.find_iter(string.slice(2..21)).collect::<Vec<_>>(),
+        vec![0..4, 9..15]
+    );
+    assert_eq!(regex.rfind(string.slice(2..21)), Some(9..15));
+}
@@ -0,0 +1,235 @@
+use {
+    crate::{
+        makepad_platform::*,
+        button_logic::*,
+        frame_component::*,
+    }
+};
+
+live_register!{
+    use makepad_platform::shader::std::*;
+    use makepad_component::theme::*;
+
+    DrawCheckBox: {{DrawCheckBox}} {
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            let sz = 7.0;
+            let c = vec2(sz + 1.0, self.rect_size.y * 0.5);
+            let body = mix(COLOR_BG_EDITOR, COLOR_BG_ODD, self.hover);
+            let border = mix(COLOR_BG_HEADER, COLOR_BG_SELECTED, self.focus);
+            let mark = mix(COLOR_TEXT_DEFAULT, COLOR_TEXT_HOVER, self.hover);
+            match self.check_type {
+                CheckType::Check => {
+                    sdf.box(c.x - sz, c.y - sz, 2. * sz, 2. * sz, 2.0);
+                    sdf.fill_keep(body);
+                    sdf.stroke(border, 1.0);
+                    // the check mark grows in from its short leg
+                    let s = sz * 0.5;
+                    sdf.move_to(c.x - s, c.y);
+                    sdf.line_to(c.x - s * 0.25, c.y + s * 0.75);
+                    sdf.line_to(c.x + s, c.y - s * 0.75);
+                    sdf.stroke(vec4(mark.xyz, mark.w * self.selected), 1.25);
+                    return sdf.result;
+                }
+                CheckType::Radio => {
+                    sdf.circle(c.x, c.y, sz);
+                    sdf.fill_keep(body);
+                    sdf.stroke(border, 1.0);
+                    sdf.circle(c.x, c.y, sz * 0.45 * self.selected);
+                    sdf.fill(mark);
+                    return sdf.result;
+                }
+                CheckType::Toggle => {
+                    // a pill of twice the size of the knob, the knob slides to the right when selected
+                    sdf.box(c.x - sz, c.y - sz, 4. * sz, 2. * sz, sz);
+                    sdf.fill_keep(mix(body, COLOR_BG_SELECTED, self.selected));
+                    sdf.stroke(border, 1.0);
+                    sdf.circle(c.x + 2. * sz * self.selected, c.y, sz - 2.5);
+                    sdf.fill(mark);
+                    return sdf.result;
+                }
+            }
+            return #f00;
+        }
+    }
+
+    CheckBox: {{CheckBox}} {
+        label_text: {
+            text_style: FONT_LABEL {}
+            color: (COLOR_TEXT_DEFAULT)
+        }
+
+        walk: {
+            width: Size::Fit,
+            height: Size::Fit,
+            margin: {left: 1.0, right: 1.0, top: 1.0, bottom: 1.0},
+        }
+
+        layout: {
+            align: {y: 0.5},
+            padding: {left: 22.0, top: 5.0, right: 5.0, bottom: 5.0}
+        }
+
+        state: {
+            hover = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {check_quad: {hover: 0.0}}
+                }
+                on = {
+                    from: {all: Play::Snap}
+                    apply: {check_quad: {hover: 1.0}}
+                }
+            }
+            focus = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {check_quad: {focus: 0.0}}
+                }
+                on = {
+                    from: {all: Play::Snap}
+                    apply: {check_quad: {focus: 1.0}}
+                }
+            }
+            selected = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {check_quad: {selected: 0.0}}
+                }
+                on = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {check_quad: {selected: 1.0}}
+                }
+            }
+        }
+    }
+
+    Toggle: CheckBox {
+        check_type: CheckType::Toggle
+        layout: {
+            padding: {left: 36.0}
+        }
+    }
+
+    RadioButton: CheckBox {
+        check_type: CheckType::Radio
+    }
+}
+
+#[derive(Live, LiveHook)]
+#[repr(u32)]
+pub enum CheckType {
+    #[pick] Check,
+    Radio,
+    Toggle
+}
+
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawCheckBox {
+    draw_super: DrawQuad,
+    check_type: CheckType,
+    hover: f32,
+    focus: f32,
+    selected: f32
+}
+
+/// A box that is checked or not, with a label. `Toggle` and `RadioButton` are the same component
+/// drawn as a switch and as a round button. Clicking the label changes the value too, and with the
+/// key focus space does.
+///
+/// A radio button only turns itself on, turning the others off is up to its group, see
+/// `RadioGroup`. Whether the component starts out selected is set through its state, with
+/// `state: {selected = {default: on}}`.
+#[derive(Live, LiveHook, FrameComponent)]
+#[live_register(frame_component!(CheckBox))]
+pub struct CheckBox {
+    #[rust] button_logic: ButtonLogic,
+
+    state: State,
+
+    #[alias(check_type, check_quad.check_type)]
+    check_quad: DrawCheckBox,
+    label_text: DrawText,
+
+    #[alias(width, walk.width)]
+    #[alias(height, walk.height)]
+    #[alias(margin, walk.margin)]
+    walk: Walk,
+
+    layout: Layout,
+    label: String
+}
+
+#[derive(Clone, PartialEq, FrameComponentAction)]
+pub enum CheckBoxAction {
+    Changed(bool),
+    None
+}
+
+impl CheckBox {
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> CheckBoxAction {
+        self.state_handle_event(cx, event);
+        let res = self.button_logic.handle_event(cx, event, self.check_quad.area());
+
+        match res.state {
+            ButtonState::Pressed => {
+                cx.set_key_focus(self.check_quad.area());
+                self.animate_state(cx, ids!(hover.on));
+            }
+            ButtonState::Default => self.animate_state(cx, ids!(hover.off)),
+            ButtonState::Hover => self.animate_state(cx, ids!(hover.on)),
+            _ => ()
+        };
+        if let ButtonAction::WasClicked = res.action {
+            return self.toggle(cx)
+        }
+
+        match event.hits(cx, self.check_quad.area()) {
+            HitEvent::KeyFocus(_) => self.animate_state(cx, ids!(focus.on)),
+            HitEvent::KeyFocusLost(_) => self.animate_state(cx, ids!(focus.off)),
+            HitEvent::KeyDown(ke) if ke.key_code == KeyCode::Space && !ke.is_repeat => {
+                return self.toggle(cx)
+            }
+            _ => ()
+        }
+        CheckBoxAction::None
+    }
+
+    fn toggle(&mut self, cx: &mut Cx) -> CheckBoxAction {
+        let selected = !self.is_selected(cx);
+        if !selected && matches!(self.check_quad.check_type, CheckType::Radio) {
+            return CheckBoxAction::None
+        }
+        self.set_selected(cx, selected, Animate::Yes);
+        CheckBoxAction::Changed(selected)
+    }
+
+    pub fn is_selected(&self, cx: &mut Cx) -> bool {
+        self.state.is_in_state(cx, ids!(selected.on))
+    }
+
+    pub fn set_selected(&mut self, cx: &mut Cx, selected: bool, animate: Animate) {
+        self.toggle_state(cx, selected, animate, ids!(selected.on), ids!(selected.off))
+    }
+
+    pub fn area(&self) -> Area {
+        self.check_quad.area()
+    }
+
+    pub fn draw_label(&mut self, cx: &mut Cx2d, label: &str) {
+        self.check_quad.begin(cx, self.walk, self.layout);
+        self.label_text.draw_walk(cx, Walk::fit(), Align::default(), label);
+        self.check_quad.end(cx);
+    }
+
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        self.check_quad.begin(cx, walk, self.layout);
+        self.label_text.draw_walk(cx, Walk::fit(), Align::default(), &self.label);
+        self.check_quad.end(cx);
+    }
+}
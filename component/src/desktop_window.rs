@@ -43,6 +43,8 @@ pub struct DesktopWindow {
     #[rust] pub caption_size: Vec2,
     
     window: Window,
+    // the overlay views of popups, menus and tooltips are attached to the main view when it ends,
+    // so they are drawn over the frame
     main_view: View,
     pass: Pass,
     depth_texture: Texture,
@@ -53,6 +53,7 @@ live_register!{
         overlay_view: {
             //walk: {abs_pos: vec2(0.0, 0.0)}
             is_overlay: true
+            is_clipped: false
         }
         tab_bar: TabBar {}
         splitter: Splitter {}
@@ -108,7 +109,9 @@ impl Dock {
     }
     
     pub fn end(&mut self, cx: &mut Cx2d) {
-        if self.overlay_view.begin(cx, Walk::default(), Layout::flow_right()).is_ok() {
+        // the drag quad is drawn at an absolute rect, so the overlay itself has no size and
+        // doesn't keep the finger from the dock
+        if self.overlay_view.begin(cx, Walk::fit(), Layout::flow_right()).is_ok() {
             if let Some(drag) = self.drag.as_ref() {
                 let panel = self.panels[drag.panel_id].as_tab_panel();
                 let rect = compute_drag_rect(panel.contents_rect, drag.position);
@@ -0,0 +1,212 @@
+use crate::{
+    makepad_platform::*,
+    popup_menu::{PopupMenu, PopupMenuAction},
+    frame_component::*,
+};
+
+live_register!{
+    use makepad_platform::shader::std::*;
+    use makepad_component::theme::*;
+
+    DrawDropDown: {{DrawDropDown}} {
+        instance hover: 0.0
+        instance pressed: 0.0
+        instance focus: 0.0
+
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            sdf.box(
+                1.,
+                1.,
+                self.rect_size.x - 2.0,
+                self.rect_size.y - 2.0,
+                2.0
+            );
+            sdf.fill_keep(mix(
+                mix(COLOR_BG_EDITOR, COLOR_BG_ODD, self.hover),
+                COLOR_CONTROL_PRESSED,
+                self.pressed
+            ));
+            sdf.stroke(mix(COLOR_BG_EDITOR, COLOR_BG_SELECTED, self.focus), 1.0);
+
+            // the arrow at the right
+            let c = vec2(self.rect_size.x - 10.0, self.rect_size.y * 0.5);
+            sdf.move_to(c.x - 3.5, c.y - 2.0);
+            sdf.line_to(c.x + 3.5, c.y - 2.0);
+            sdf.line_to(c.x, c.y + 2.0);
+            sdf.close_path();
+            sdf.fill(mix(COLOR_TEXT_DEFAULT, COLOR_TEXT_HOVER, self.hover));
+            return sdf.result;
+        }
+    }
+
+    DropDown: {{DropDown}} {
+        label_text: {
+            text_style: FONT_LABEL {}
+            color: (COLOR_TEXT_DEFAULT)
+        }
+        walk: {
+            width: Size::Fixed(150.0),
+            height: Size::Fit,
+            margin: {left: 1.0, right: 1.0, top: 1.0, bottom: 1.0},
+        }
+        layout: {
+            align: {y: 0.5},
+            padding: {left: 8.0, top: 5.0, right: 24.0, bottom: 5.0}
+        }
+        state: {
+            hover = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {bg_quad: {pressed: 0.0, hover: 0.0}}
+                }
+                on = {
+                    from: {
+                        all: Play::Forward {duration: 0.1}
+                        pressed: Play::Forward {duration: 0.01}
+                    }
+                    apply: {bg_quad: {pressed: 0.0, hover: [{time: 0.0, value: 1.0}]}}
+                }
+                pressed = {
+                    from: {all: Play::Forward {duration: 0.2}}
+                    apply: {bg_quad: {pressed: [{time: 0.0, value: 1.0}], hover: 1.0}}
+                }
+            }
+            focus = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {bg_quad: {focus: 0.0}}
+                }
+                on = {
+                    from: {all: Play::Snap}
+                    apply: {bg_quad: {focus: 1.0}}
+                }
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveHook)]#[repr(C)]
+struct DrawDropDown {
+    draw_super: DrawQuad,
+}
+
+/// A select box: shows one of a list of labels, and a `PopupMenu` with all of them when clicked.
+/// With the key focus, the arrow keys pick the previous or next label without opening the menu.
+#[derive(Live, LiveHook, FrameComponent)]
+#[live_register(frame_component!(DropDown))]
+pub struct DropDown {
+    state: State,
+    bg_quad: DrawDropDown,
+    label_text: DrawText,
+    popup_menu: PopupMenu,
+
+    #[alias(width, walk.width)]
+    #[alias(height, walk.height)]
+    #[alias(margin, walk.margin)]
+    walk: Walk,
+    layout: Layout,
+
+    pub selected: usize,
+    #[rust] labels: Vec<String>,
+}
+
+#[derive(Clone, PartialEq, FrameComponentAction)]
+pub enum DropDownAction {
+    Changed(usize),
+    None
+}
+
+impl DropDown {
+    pub fn set_labels(&mut self, cx: &mut Cx, labels: Vec<String>) {
+        self.popup_menu.set_items(cx, labels.clone());
+        self.labels = labels;
+        self.bg_quad.area().redraw(cx);
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> DropDownAction {
+        self.state_handle_event(cx, event);
+
+        // a finger down on the drop down while the menu is shown only closes the menu
+        let mut dismissed = false;
+        match self.popup_menu.handle_event(cx, event) {
+            PopupMenuAction::Selected(index) => {
+                self.bg_quad.area().redraw(cx);
+                if index != self.selected {
+                    self.selected = index;
+                    return DropDownAction::Changed(index)
+                }
+            }
+            PopupMenuAction::Dismissed => dismissed = true,
+            PopupMenuAction::None => ()
+        }
+
+        match event.hits(cx, self.bg_quad.area()) {
+            HitEvent::KeyFocus(_) => {
+                self.animate_state(cx, ids!(focus.on));
+            }
+            HitEvent::KeyFocusLost(_) => {
+                self.animate_state(cx, ids!(focus.off));
+            }
+            HitEvent::FingerHover(fe) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Hand);
+                match fe.hover_state {
+                    HoverState::In => self.animate_state(cx, ids!(hover.on)),
+                    HoverState::Out => self.animate_state(cx, ids!(hover.off)),
+                    _ => ()
+                }
+            }
+            HitEvent::FingerDown(_) => {
+                self.animate_state(cx, ids!(hover.pressed));
+                if !dismissed {
+                    self.open_menu(cx);
+                }
+            }
+            HitEvent::FingerUp(fe) => {
+                if fe.is_over && fe.input_type.has_hovers() {
+                    self.animate_state(cx, ids!(hover.on));
+                }
+                else {
+                    self.animate_state(cx, ids!(hover.off));
+                }
+            }
+            HitEvent::KeyDown(ke) => match ke.key_code {
+                KeyCode::ArrowUp if self.selected > 0 => {
+                    self.selected -= 1;
+                    self.bg_quad.area().redraw(cx);
+                    return DropDownAction::Changed(self.selected)
+                }
+                KeyCode::ArrowDown if self.selected + 1 < self.labels.len() => {
+                    self.selected += 1;
+                    self.bg_quad.area().redraw(cx);
+                    return DropDownAction::Changed(self.selected)
+                }
+                KeyCode::Return | KeyCode::Space => self.open_menu(cx),
+                _ => ()
+            }
+            _ => ()
+        }
+        DropDownAction::None
+    }
+
+    fn open_menu(&mut self, cx: &mut Cx) {
+        // the menu gives the key focus back to the drop down when it closes
+        cx.set_key_focus(self.bg_quad.area());
+        let anchor = self.bg_quad.area().get_rect(cx);
+        self.popup_menu.open(cx, anchor, Some(self.selected));
+    }
+
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        self.bg_quad.begin(cx, walk, self.layout);
+        let label = self.labels.get(self.selected).map_or("", | label | label.as_str());
+        self.label_text.draw_walk(cx, Walk::fit(), Align::default(), label);
+        self.bg_quad.end(cx);
+        self.popup_menu.draw(cx);
+    }
+}
@@ -333,6 +333,7 @@ impl<'a> FromResidual for ChildResult<'a> {
     }
 }
 
+impl<'a> std::ops::Residual<()> for &'a mut Box<dyn FrameComponent> {type TryType = ChildResult<'a>;} // LOCAL-ONLY
 impl<'a> Try for ChildResult<'a>{
     type Output = ();
     type Residual = &'a mut Box<dyn FrameComponent >;
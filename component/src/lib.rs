@@ -1,4 +1,4 @@
-#![feature(try_trait_v2)]
+#![feature(try_trait_v2)]#![feature(try_trait_v2_residual)] // LOCAL-ONLY
 use makepad_platform::*;
 
 pub mod button_logic;
@@ -19,6 +19,15 @@ pub mod tab_close_button;
 pub mod color_picker;
 pub mod text_input;
 pub mod slider;
+pub mod list_view;
+pub mod table_view;
+pub mod popup;
+pub mod popup_menu;
+pub mod drop_down;
+pub mod tooltip;
+pub mod check_box;
+pub mod radio_group;
+pub mod number_input;
 
 #[macro_use]
 pub mod frame_component;
@@ -34,6 +43,8 @@ pub mod splitter;
 pub mod fold_header;
 
 mod theme;
+#[cfg(test)]
+mod test_cx;
 
 pub use crate::{
     bare_window::BareWindow,
@@ -41,6 +52,15 @@ pub use crate::{
     button_logic::{ButtonLogic, ButtonAction},
     button::{Button},
     text_input::{TextInput},
+    list_view::{ListView, ListDataSource},
+    table_view::{TableView, TableColumn},
+    popup::{Popup},
+    popup_menu::{PopupMenu},
+    drop_down::{DropDown},
+    tooltip::{Tooltip},
+    check_box::{CheckBox},
+    radio_group::{RadioGroup},
+    number_input::{NumberInput},
     link_button::{LinkButton},
     desktop_window::{DesktopWindow},
     scroll_view::{ScrollView},
@@ -85,4 +105,13 @@ pub fn live_register(cx: &mut Cx) {
     crate::tab_bar::live_register(cx);
     crate::dock::live_register(cx);
     crate::color_picker::live_register(cx);
+    crate::list_view::live_register(cx);
+    crate::table_view::live_register(cx);
+    crate::popup::live_register(cx);
+    crate::popup_menu::live_register(cx);
+    crate::drop_down::live_register(cx);
+    crate::tooltip::live_register(cx);
+    crate::check_box::live_register(cx);
+    crate::radio_group::live_register(cx);
+    crate::number_input::live_register(cx);
 }
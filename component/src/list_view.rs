@@ -0,0 +1,570 @@
+use {
+    std::{
+        cmp::Ordering,
+        collections::HashSet,
+    },
+    crate::{
+        makepad_platform::*,
+        scroll_view::ScrollView,
+    },
+};
+
+live_register!{
+    use makepad_platform::shader::std::*;
+    use makepad_component::theme::*;
+
+    DrawRowQuad: {{DrawRowQuad}} {
+        fn pixel(self) -> vec4 {
+            let color = mix(
+                mix(
+                    COLOR_BG_EDITOR,
+                    COLOR_BG_ODD,
+                    self.is_even
+                ),
+                COLOR_BG_SELECTED,
+                self.selected
+            );
+            // outline the row with the keyboard cursor
+            let pos = self.pos * self.rect_size;
+            let edge = min(min(pos.x, pos.y), min(self.rect_size.x - pos.x, self.rect_size.y - pos.y));
+            return mix(color, COLOR_TEXT_META, self.cursor * step(edge, 1.0));
+        }
+    }
+
+    ListView: {{ListView}} {
+        row_height: (DIM_DATA_ITEM_HEIGHT)
+        cell_padding: 5.0
+        cell_align: {y: 0.5}
+        cell_text: {
+            text_style: FONT_DATA {top_drop: 1.15}
+            color: (COLOR_TEXT_DEFAULT)
+        }
+        layout: {flow: Flow::Down}
+        scroll_view: {
+            h_show: false
+            view: {
+                debug_id: list_view
+            }
+        }
+    }
+}
+
+/// The rows shown by a `ListView` or `TableView`.
+///
+/// The views only ask for the rows they draw, and for the heights of all rows when the row count
+/// changes, so a data source can hold a large number of rows.
+pub trait ListDataSource {
+    fn row_count(&self) -> usize;
+
+    /// Returns the height of the given row, or `None` to use the row height of the view.
+    fn row_height(&self, _row: usize) -> Option<f32> {
+        None
+    }
+
+    /// Appends the text of the given cell to `text`. A `ListView` only asks for column 0.
+    fn cell_text(&self, row: usize, column: usize, text: &mut String);
+
+    /// Compares two rows when sorting on the given column. By default this compares the text of
+    /// their cells, data sources with many rows should override it.
+    fn compare_rows(&self, column: usize, a: usize, b: usize) -> Ordering {
+        let mut text_a = String::new();
+        let mut text_b = String::new();
+        self.cell_text(a, column, &mut text_a);
+        self.cell_text(b, column, &mut text_b);
+        text_a.cmp(&text_b)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ListSort {
+    pub column: usize,
+    pub descending: bool,
+}
+
+impl ListSort {
+    fn compare(&self, source: &dyn ListDataSource, a: usize, b: usize) -> Ordering {
+        let ordering = source.compare_rows(self.column, a, b);
+        if self.descending {ordering.reverse()} else {ordering}
+    }
+}
+
+#[derive(Live, LiveHook)]#[repr(C)]
+struct DrawRowQuad {
+    draw_super: DrawQuad,
+    is_even: f32,
+    selected: f32,
+    cursor: f32,
+}
+
+/// A scrolling list that only draws the rows in view.
+///
+/// Rows are identified by their index in the data source. Sorting changes the order in which
+/// they are shown, but not their index, so the selection survives sorting.
+#[derive(Live, LiveHook)]
+pub struct ListView {
+    scroll_view: ScrollView,
+    row_quad: DrawRowQuad,
+    cell_text: DrawText,
+
+    layout: Layout,
+    row_height: f32,
+    cell_padding: f32,
+    cell_align: Align,
+
+    // the top of each row in display order, followed by the total height
+    #[rust] row_offsets: Vec<f32>,
+    // maps display order to rows and back, both are empty if the rows are not sorted
+    #[rust] row_order: Vec<usize>,
+    #[rust] row_display_index: Vec<usize>,
+    #[rust] rows_valid: bool,
+    #[rust] sort: Option<ListSort>,
+
+    #[rust] selected_rows: HashSet<usize>,
+    #[rust] cursor_row: Option<usize>,
+    #[rust] anchor_row: Option<usize>,
+
+    #[rust] cell_buffer: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ListViewAction {
+    SelectionChanged,
+    RowActivated(usize),
+    None
+}
+
+impl ListView {
+
+    /// Draws the visible rows, showing column 0 of the data source.
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk, source: &dyn ListDataSource) {
+        self.draw_walk_with_columns(cx, walk, source, &[]);
+    }
+
+    /// Draws the visible rows, showing one column of the data source per given width.
+    pub fn draw_walk_with_columns(&mut self, cx: &mut Cx2d, walk: Walk, source: &dyn ListDataSource, column_widths: &[f32]) {
+        self.update_rows(source);
+        if self.scroll_view.begin(cx, walk, self.layout).is_err() {
+            return
+        }
+
+        let row_count = self.row_count();
+        let total_height = self.row_offsets[row_count];
+        let view_height = cx.turtle().rect().size.y;
+        let (first, last) = if view_height.is_nan() {
+            (0, row_count)
+        }
+        else {
+            let scroll = self.scroll_view.get_scroll_pos(cx);
+            (
+                self.display_index_at(scroll.y).unwrap_or(row_count),
+                self.row_offsets[..row_count].partition_point( | offset | *offset < scroll.y + view_height)
+            )
+        };
+        let last = last.max(first);
+
+        // everything above and below the visible rows is a single empty walk
+        cx.walk_turtle(Walk::size(Size::Fill, Size::Fixed(self.row_offsets[first])));
+
+        let has_focus = cx.has_key_focus(self.scroll_view.area());
+        for display_index in first..last {
+            let row = self.row_at(display_index);
+            let height = self.row_offsets[display_index + 1] - self.row_offsets[display_index];
+            self.row_quad.is_even = if display_index % 2 == 0 {1.0} else {0.0};
+            self.row_quad.selected = if self.selected_rows.contains(&row) {1.0} else {0.0};
+            self.row_quad.cursor = if has_focus && self.cursor_row == Some(row) {1.0} else {0.0};
+            self.row_quad.begin(cx, Walk::size(Size::Fill, Size::Fixed(height)), Layout::flow_right());
+            if column_widths.is_empty() {
+                self.draw_cell(cx, source, row, 0, Size::Fill);
+            }
+            else {
+                for (column, width) in column_widths.iter().enumerate() {
+                    self.draw_cell(cx, source, row, column, Size::Fixed(*width));
+                }
+            }
+            self.row_quad.end(cx);
+        }
+
+        cx.walk_turtle(Walk::size(Size::Fill, Size::Fixed(total_height - self.row_offsets[last])));
+        self.scroll_view.end(cx);
+    }
+
+    fn draw_cell(&mut self, cx: &mut Cx2d, source: &dyn ListDataSource, row: usize, column: usize, width: Size) {
+        let walk = Walk {
+            abs_pos: None,
+            margin: Margin {left: self.cell_padding, ..Margin::default()},
+            width: match width {
+                Size::Fixed(width) => Size::Fixed((width - self.cell_padding).max(0.0)),
+                width => width
+            },
+            height: Size::Fill,
+        };
+        self.cell_buffer.clear();
+        source.cell_text(row, column, &mut self.cell_buffer);
+        if self.cell_buffer.is_empty() {
+            // an empty text doesn't walk, but the next column still has to start in the right place
+            cx.walk_turtle(walk);
+        }
+        else {
+            self.cell_text.draw_walk(cx, walk, self.cell_align, &self.cell_buffer);
+        }
+    }
+
+    pub fn redraw(&mut self, cx: &mut Cx) {
+        self.scroll_view.redraw(cx);
+    }
+
+    /// Makes the view ask the data source for all rows again on the next draw. Rows that are
+    /// appended are picked up without this, call it when rows were changed or removed.
+    pub fn invalidate(&mut self, cx: &mut Cx) {
+        self.rows_valid = false;
+        self.scroll_view.redraw(cx);
+    }
+
+    pub fn sort(&self) -> Option<ListSort> {
+        self.sort
+    }
+
+    /// Shows the rows sorted on a column from the next draw, or in data source order for `None`.
+    pub fn set_sort(&mut self, cx: &mut Cx, sort: Option<ListSort>) {
+        if self.sort != sort {
+            self.sort = sort;
+            self.invalidate(cx);
+        }
+    }
+
+    /// Returns the selected rows in display order.
+    pub fn selected_rows(&self) -> Vec<usize> {
+        let mut rows: Vec<usize> = self.selected_rows.iter().cloned().collect();
+        rows.sort_by_key( | row | self.display_index_of(*row));
+        rows
+    }
+
+    pub fn is_row_selected(&self, row: usize) -> bool {
+        self.selected_rows.contains(&row)
+    }
+
+    /// Selects only the given row, and scrolls it into view.
+    pub fn select_row(&mut self, cx: &mut Cx, row: usize) {
+        if row < self.row_count() {
+            self.move_cursor(cx, self.display_index_of(row), false);
+        }
+    }
+
+    pub fn clear_selection(&mut self, cx: &mut Cx) {
+        self.selected_rows.clear();
+        self.cursor_row = None;
+        self.anchor_row = None;
+        self.scroll_view.redraw(cx);
+    }
+
+    pub fn handle_event_with_fn(
+        &mut self,
+        cx: &mut Cx,
+        event: &mut Event,
+        dispatch_action: &mut dyn FnMut(&mut Cx, ListViewAction),
+    ) {
+        if self.scroll_view.handle_event(cx, event) {
+            self.scroll_view.redraw(cx);
+        }
+
+        match event.hits(cx, self.scroll_view.area()) {
+            HitEvent::KeyFocus(_) | HitEvent::KeyFocusLost(_) => {
+                self.scroll_view.redraw(cx);
+            }
+            HitEvent::FingerDown(fe) => {
+                cx.set_key_focus(self.scroll_view.area());
+                if let Some(display_index) = self.display_index_at(fe.rel.y) {
+                    let row = self.row_at(display_index);
+                    let KeyModifiers {shift, control, logo, ..} = fe.modifiers;
+                    if shift {
+                        self.select_range(display_index, control || logo);
+                    }
+                    else if control || logo {
+                        if !self.selected_rows.remove(&row) {
+                            self.selected_rows.insert(row);
+                        }
+                        self.anchor_row = Some(row);
+                    }
+                    else {
+                        self.selected_rows.clear();
+                        self.selected_rows.insert(row);
+                        self.anchor_row = Some(row);
+                    }
+                    self.cursor_row = Some(row);
+                    self.scroll_view.redraw(cx);
+                    dispatch_action(cx, ListViewAction::SelectionChanged);
+                    if fe.tap_count == 2 {
+                        dispatch_action(cx, ListViewAction::RowActivated(row));
+                    }
+                }
+            }
+            HitEvent::KeyDown(ke) => {
+                let row_count = self.row_count();
+                if row_count == 0 {
+                    return
+                }
+                let command = ke.modifiers.control || ke.modifiers.logo;
+                let cursor = self.cursor_row.map(| row | self.display_index_of(row));
+                let page = (self.scroll_view.get_scroll_view_visible().y / self.row_height.max(1.0)) as usize;
+                let display_index = match ke.key_code {
+                    KeyCode::ArrowUp => cursor.map_or(0, | index | index.saturating_sub(1)),
+                    KeyCode::ArrowDown => cursor.map_or(0, | index | (index + 1).min(row_count - 1)),
+                    KeyCode::PageUp => cursor.map_or(0, | index | index.saturating_sub(page.max(1))),
+                    KeyCode::PageDown => cursor.map_or(0, | index | (index + page.max(1)).min(row_count - 1)),
+                    KeyCode::Home => 0,
+                    KeyCode::End => row_count - 1,
+                    KeyCode::KeyA if command => {
+                        self.selected_rows = (0..row_count).collect();
+                        self.scroll_view.redraw(cx);
+                        dispatch_action(cx, ListViewAction::SelectionChanged);
+                        return
+                    }
+                    KeyCode::Return => {
+                        if let Some(row) = self.cursor_row {
+                            dispatch_action(cx, ListViewAction::RowActivated(row));
+                        }
+                        return
+                    }
+                    _ => return
+                };
+                self.move_cursor(cx, display_index, ke.modifiers.shift);
+                dispatch_action(cx, ListViewAction::SelectionChanged);
+            }
+            _ => ()
+        }
+    }
+
+    fn move_cursor(&mut self, cx: &mut Cx, display_index: usize, extend: bool) {
+        let row = self.row_at(display_index);
+        if extend {
+            self.select_range(display_index, false);
+        }
+        else {
+            self.selected_rows.clear();
+            self.selected_rows.insert(row);
+            self.anchor_row = Some(row);
+        }
+        self.cursor_row = Some(row);
+        let top = self.row_offsets[display_index];
+        let height = self.row_offsets[display_index + 1] - top;
+        self.scroll_view.scroll_into_view(cx, Rect {pos: vec2(0.0, top), size: vec2(0.0, height)});
+        self.scroll_view.redraw(cx);
+    }
+
+    // selects the rows from the anchor up to and including the given one
+    fn select_range(&mut self, display_index: usize, keep_selection: bool) {
+        let anchor = self.anchor_row.map_or(display_index, | row | self.display_index_of(row));
+        if !keep_selection {
+            self.selected_rows.clear();
+        }
+        for index in anchor.min(display_index)..=anchor.max(display_index) {
+            self.selected_rows.insert(self.row_at(index));
+        }
+        if self.anchor_row.is_none() {
+            self.anchor_row = Some(self.row_at(display_index));
+        }
+    }
+
+    fn row_count(&self) -> usize {
+        self.row_offsets.len().saturating_sub(1)
+    }
+
+    fn row_at(&self, display_index: usize) -> usize {
+        if self.row_order.is_empty() {display_index} else {self.row_order[display_index]}
+    }
+
+    fn display_index_of(&self, row: usize) -> usize {
+        if self.row_display_index.is_empty() {row} else {self.row_display_index[row]}
+    }
+
+    fn display_index_at(&self, y: f32) -> Option<usize> {
+        let index = self.row_offsets.partition_point( | offset | *offset <= y);
+        if index == 0 || index > self.row_count() {
+            return None
+        }
+        Some(index - 1)
+    }
+
+    fn update_rows(&mut self, source: &dyn ListDataSource) {
+        let row_count = source.row_count();
+        let old_row_count = self.row_count();
+        if self.rows_valid && row_count == old_row_count {
+            return
+        }
+        if self.rows_valid && row_count > old_row_count {
+            // rows were appended, so only the new rows have to be measured or sorted in
+            if let Some(sort) = self.sort {
+                for row in old_row_count..row_count {
+                    let index = self.row_order.partition_point( | other | sort.compare(source, *other, row) != Ordering::Greater);
+                    self.row_order.insert(index, row);
+                }
+                self.update_display_index();
+                self.update_row_offsets(source, 0);
+            }
+            else {
+                self.update_row_offsets(source, old_row_count);
+            }
+            return
+        }
+
+        self.row_order.clear();
+        if let Some(sort) = self.sort {
+            self.row_order.extend(0..row_count);
+            self.row_order.sort_by( | a, b | sort.compare(source, *a, *b));
+        }
+        self.update_display_index();
+        self.update_row_offsets(source, 0);
+        self.selected_rows.retain( | row | *row < row_count);
+        self.cursor_row = self.cursor_row.filter( | row | *row < row_count);
+        self.anchor_row = self.anchor_row.filter( | row | *row < row_count);
+        self.rows_valid = true;
+    }
+
+    fn update_display_index(&mut self) {
+        self.row_display_index.clear();
+        self.row_display_index.resize(self.row_order.len(), 0);
+        for (display_index, row) in self.row_order.iter().enumerate() {
+            self.row_display_index[*row] = display_index;
+        }
+    }
+
+    // measures the rows from the given display index onwards
+    fn update_row_offsets(&mut self, source: &dyn ListDataSource, from: usize) {
+        self.row_offsets.truncate(from + 1);
+        if self.row_offsets.is_empty() {
+            self.row_offsets.push(0.0);
+        }
+        let mut offset = self.row_offsets[from];
+        for display_index in from..source.row_count() {
+            offset += source.row_height(self.row_at(display_index)).unwrap_or(self.row_height);
+            self.row_offsets.push(offset);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_cx::*,
+    };
+
+    struct Rows(Vec<&'static str>);
+
+    impl ListDataSource for Rows {
+        fn row_count(&self) -> usize {
+            self.0.len()
+        }
+
+        fn row_height(&self, row: usize) -> Option<f32> {
+            if self.0[row] == "tall" {Some(50.0)} else {None}
+        }
+
+        fn cell_text(&self, row: usize, _column: usize, text: &mut String) {
+            text.push_str(self.0[row]);
+        }
+    }
+
+    const SHIFT: KeyModifiers = KeyModifiers {shift: true, control: false, alt: false, logo: false};
+    const CONTROL: KeyModifiers = KeyModifiers {shift: false, control: true, alt: false, logo: false};
+
+    // a list view drawn over the whole pass
+    fn draw(test_cx: &mut TestCx, list_view: &mut ListView, rows: &Rows) {
+        test_cx.draw( | cx | list_view.draw_walk(cx, Walk::size(Size::Fill, Size::Fill), rows));
+    }
+
+    fn event(test_cx: &mut TestCx, list_view: &mut ListView, event: Event) -> Vec<ListViewAction> {
+        let mut actions = Vec::new();
+        test_cx.event(event, | cx, event | list_view.handle_event_with_fn(cx, event, &mut | _, action | actions.push(action)));
+        actions
+    }
+
+    fn click(test_cx: &mut TestCx, list_view: &mut ListView, y: f32, modifiers: KeyModifiers, tap_count: u32) -> Vec<ListViewAction> {
+        let actions = event(test_cx, list_view, finger_down(vec2(10.0, y), modifiers, tap_count));
+        event(test_cx, list_view, finger_up(vec2(10.0, y)));
+        actions
+    }
+
+    fn key(test_cx: &mut TestCx, list_view: &mut ListView, key_code: KeyCode, modifiers: KeyModifiers) {
+        event(test_cx, list_view, key_down(key_code, modifiers));
+    }
+
+    #[test]
+    fn keys_move_through_the_rows_in_sorted_order() {
+        let mut test_cx = TestCx::new();
+        let mut list_view: ListView = test_cx.new_component("makepad_component::list_view", id!(ListView));
+        let rows = Rows(vec!["c", "tall", "a"]);
+        list_view.set_sort(&mut test_cx.cx, Some(ListSort {column: 0, descending: false}));
+        draw(&mut test_cx, &mut list_view, &rows);
+        // clicking a row gives the list the key focus
+        click(&mut test_cx, &mut list_view, 1.0, KeyModifiers::default(), 1);
+        assert_eq!(list_view.selected_rows(), vec![2]);
+        key(&mut test_cx, &mut list_view, KeyCode::ArrowDown, KeyModifiers::default());
+        assert_eq!(list_view.selected_rows(), vec![0]);
+        key(&mut test_cx, &mut list_view, KeyCode::End, SHIFT);
+        assert_eq!(list_view.selected_rows(), vec![0, 1]);
+    }
+
+    #[test]
+    fn appended_rows_are_sorted_in() {
+        let mut test_cx = TestCx::new();
+        let mut list_view: ListView = test_cx.new_component("makepad_component::list_view", id!(ListView));
+        list_view.set_sort(&mut test_cx.cx, Some(ListSort {column: 0, descending: true}));
+        draw(&mut test_cx, &mut list_view, &Rows(vec!["b", "d"]));
+        draw(&mut test_cx, &mut list_view, &Rows(vec!["b", "d", "a", "c", "d"]));
+        click(&mut test_cx, &mut list_view, 1.0, KeyModifiers::default(), 1);
+        key(&mut test_cx, &mut list_view, KeyCode::KeyA, CONTROL);
+        // equal rows keep the order they were appended in
+        assert_eq!(list_view.selected_rows(), vec![1, 4, 3, 0, 2]);
+    }
+
+    #[test]
+    fn clicks_hit_rows_of_their_own_height() {
+        let mut test_cx = TestCx::new();
+        let mut list_view: ListView = test_cx.new_component("makepad_component::list_view", id!(ListView));
+        draw(&mut test_cx, &mut list_view, &Rows(vec!["a", "tall", "b"]));
+        let row_height = list_view.row_height;
+        click(&mut test_cx, &mut list_view, row_height + 40.0, KeyModifiers::default(), 1);
+        assert_eq!(list_view.selected_rows(), vec![1]);
+        click(&mut test_cx, &mut list_view, row_height + 60.0, KeyModifiers::default(), 1);
+        assert_eq!(list_view.selected_rows(), vec![2]);
+        // below the last row
+        assert!(click(&mut test_cx, &mut list_view, 2.0 * row_height + 60.0, KeyModifiers::default(), 1).is_empty());
+        assert_eq!(list_view.selected_rows(), vec![2]);
+        assert_eq!(
+            click(&mut test_cx, &mut list_view, 1.0, KeyModifiers::default(), 2),
+            vec![ListViewAction::SelectionChanged, ListViewAction::RowActivated(0)]
+        );
+    }
+
+    #[test]
+    fn shift_click_selects_from_the_anchor_in_display_order() {
+        let mut test_cx = TestCx::new();
+        let mut list_view: ListView = test_cx.new_component("makepad_component::list_view", id!(ListView));
+        list_view.set_sort(&mut test_cx.cx, Some(ListSort {column: 0, descending: false}));
+        draw(&mut test_cx, &mut list_view, &Rows(vec!["d", "b", "a", "c"]));
+        let row_height = list_view.row_height;
+        let y = | display_index: usize | (display_index as f32 + 0.5) * row_height;
+        click(&mut test_cx, &mut list_view, y(1), KeyModifiers::default(), 1);
+        click(&mut test_cx, &mut list_view, y(3), SHIFT, 1);
+        assert_eq!(list_view.selected_rows(), vec![1, 3, 0]);
+        click(&mut test_cx, &mut list_view, y(0), SHIFT, 1);
+        assert_eq!(list_view.selected_rows(), vec![2, 1]);
+        // control adds to the selection and moves the anchor
+        click(&mut test_cx, &mut list_view, y(3), CONTROL, 1);
+        click(&mut test_cx, &mut list_view, y(2), KeyModifiers {control: true, ..SHIFT}, 1);
+        assert_eq!(list_view.selected_rows(), vec![2, 1, 3, 0]);
+    }
+
+    #[test]
+    fn invalidating_drops_the_selection_of_removed_rows() {
+        let mut test_cx = TestCx::new();
+        let mut list_view: ListView = test_cx.new_component("makepad_component::list_view", id!(ListView));
+        draw(&mut test_cx, &mut list_view, &Rows(vec!["a", "b", "c"]));
+        click(&mut test_cx, &mut list_view, 1.0, KeyModifiers::default(), 1);
+        key(&mut test_cx, &mut list_view, KeyCode::KeyA, CONTROL);
+        list_view.invalidate(&mut test_cx.cx);
+        draw(&mut test_cx, &mut list_view, &Rows(vec!["a", "b"]));
+        assert_eq!(list_view.selected_rows(), vec![0, 1]);
+    }
+}
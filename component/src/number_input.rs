@@ -0,0 +1,382 @@
+use crate::{
+    makepad_platform::*,
+    frame_component::*,
+    text_input::{TextInput, TextInputAction},
+};
+
+live_register!{
+    use makepad_platform::shader::std::*;
+    use makepad_component::theme::*;
+
+    DrawNumberInput: {{DrawNumberInput}} {
+        instance hover: 0.0
+        instance focus: 0.0
+        instance drag: 0.0
+
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            sdf.box(
+                1.,
+                1.,
+                self.rect_size.x - 2.0,
+                self.rect_size.y - 2.0,
+                2.0
+            );
+            sdf.fill_keep(mix(COLOR_BG_EDITOR, COLOR_BG_ODD, self.hover));
+            sdf.stroke(mix(COLOR_BG_EDITOR, COLOR_BG_SELECTED, self.focus), 1.0);
+
+            // the up and down arrows of the spinner at the right
+            let c = vec2(self.rect_size.x - self.spinner_width * 0.5, self.rect_size.y * 0.5);
+            let arrow = mix(COLOR_TEXT_DEFAULT, COLOR_TEXT_HOVER, max(self.hover, self.drag));
+            sdf.move_to(c.x - 3.5, c.y - 2.0);
+            sdf.line_to(c.x + 3.5, c.y - 2.0);
+            sdf.line_to(c.x, c.y - 6.0);
+            sdf.close_path();
+            sdf.fill(arrow);
+            sdf.move_to(c.x - 3.5, c.y + 2.0);
+            sdf.line_to(c.x + 3.5, c.y + 2.0);
+            sdf.line_to(c.x, c.y + 6.0);
+            sdf.close_path();
+            sdf.fill(arrow);
+            return sdf.result;
+        }
+    }
+
+    NumberInput: {{NumberInput}} {
+        bg_quad: {
+            spinner_width: 16.0
+        }
+        text_input: {
+            walk: {
+                width: Size::Fill,
+                height: Size::Fit,
+                margin: {left: 0.0, right: 0.0, top: 0.0, bottom: 0.0},
+            }
+        }
+        walk: {
+            width: Size::Fixed(100.0),
+            height: Size::Fit,
+            margin: {left: 1.0, right: 1.0, top: 1.0, bottom: 1.0},
+        }
+        layout: {
+            align: {y: 0.5},
+            padding: {left: 6.0, top: 4.0, right: 18.0, bottom: 4.0}
+        }
+        drag_distance: 4.0
+        step: 1.0
+        precision: 2
+
+        state: {
+            hover = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {bg_quad: {hover: 0.0}}
+                }
+                on = {
+                    from: {all: Play::Snap}
+                    apply: {bg_quad: {hover: 1.0}}
+                }
+            }
+            focus = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {bg_quad: {focus: 0.0}}
+                }
+                on = {
+                    from: {all: Play::Snap}
+                    apply: {bg_quad: {focus: 1.0}}
+                }
+            }
+            drag = {
+                default: off
+                off = {
+                    from: {all: Play::Forward {duration: 0.1}}
+                    apply: {bg_quad: {drag: 0.0}}
+                }
+                on = {
+                    from: {all: Play::Snap}
+                    apply: {bg_quad: {drag: 1.0}}
+                }
+            }
+        }
+    }
+}
+
+#[derive(Live, LiveHook)]
+#[repr(C)]
+pub struct DrawNumberInput {
+    draw_super: DrawQuad,
+    spinner_width: f32,
+}
+
+/// A number that can be typed in, or changed with the spinner at its right.
+///
+/// Dragging the spinner up or down changes the value a step for every `drag_distance` the finger
+/// moves, and clicking it steps the value once. The typed text is taken over on return or when
+/// the text loses the key focus, and escape puts back the current value. With the key focus, the
+/// up and down keys step the value, ten steps at a time while shift is held.
+#[derive(Live, LiveHook, FrameComponent)]
+#[live_register(frame_component!(NumberInput))]
+pub struct NumberInput {
+    state: State,
+    bg_quad: DrawNumberInput,
+    text_input: TextInput,
+
+    #[alias(width, walk.width)]
+    #[alias(height, walk.height)]
+    #[alias(margin, walk.margin)]
+    walk: Walk,
+    layout: Layout,
+
+    drag_distance: f32,
+
+    pub value: f64,
+    #[live(-1000000.0)] pub min: f64,
+    #[live(1000000.0)] pub max: f64,
+    pub step: f64,
+    // the number of decimals the value is rounded to
+    pub precision: usize,
+
+    // the value when the drag started
+    #[rust] drag_start: Option<f64>,
+    #[rust] dragged: bool,
+}
+
+#[derive(Clone, PartialEq, FrameComponentAction)]
+pub enum NumberInputAction {
+    Changed(f64),
+    None
+}
+
+impl NumberInput {
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> NumberInputAction {
+        self.state_handle_event(cx, event);
+        let mut action = NumberInputAction::None;
+
+        match event {
+            // the up and down keys step the value instead of moving the cursor in the text
+            Event::KeyDown(ke) if self.has_key_focus(cx) => {
+                let steps = match ke.key_code {
+                    KeyCode::ArrowUp => 1.0,
+                    KeyCode::ArrowDown => -1.0,
+                    _ => 0.0
+                };
+                if steps != 0.0 {
+                    let steps = if ke.modifiers.shift {steps * 10.0} else {steps};
+                    return self.change_value(cx, self.value + steps * self.step)
+                }
+            }
+            Event::KeyFocus(kf) => {
+                if kf.prev == self.text_input.area() {
+                    action = self.commit_text(cx);
+                }
+                if self.has_key_focus(cx) {
+                    self.animate_state(cx, ids!(focus.on));
+                }
+                else {
+                    self.animate_state(cx, ids!(focus.off));
+                }
+            }
+            _ => ()
+        }
+
+        match self.text_input.handle_event(cx, event) {
+            TextInputAction::Return(_) => {
+                action = self.commit_text(cx);
+                self.text_input.select_all();
+            }
+            TextInputAction::Escape => {
+                self.update_text();
+                self.bg_quad.area().redraw(cx);
+            }
+            _ => ()
+        }
+
+        // the text input takes the finger on the text, this is the finger on the spinner
+        match event.hits(cx, self.bg_quad.area()) {
+            HitEvent::FingerHover(fe) => {
+                cx.set_hover_mouse_cursor(MouseCursor::NsResize);
+                match fe.hover_state {
+                    HoverState::In => self.animate_state(cx, ids!(hover.on)),
+                    HoverState::Out => self.animate_state(cx, ids!(hover.off)),
+                    _ => ()
+                }
+            }
+            HitEvent::FingerDown(_) => {
+                cx.set_key_focus(self.bg_quad.area());
+                cx.set_down_mouse_cursor(MouseCursor::NsResize);
+                self.animate_state(cx, ids!(drag.on));
+                self.drag_start = Some(self.value);
+                self.dragged = false;
+            }
+            HitEvent::FingerMove(fe) => {
+                if let Some(start) = self.drag_start {
+                    let steps = ((fe.rel_start.y - fe.rel.y) / self.drag_distance).trunc() as f64;
+                    self.dragged |= steps != 0.0;
+                    action = self.change_value(cx, start + steps * self.step);
+                }
+            }
+            HitEvent::FingerUp(fe) => {
+                self.animate_state(cx, ids!(drag.off));
+                if fe.is_over && fe.input_type.has_hovers() {
+                    self.animate_state(cx, ids!(hover.on));
+                }
+                else {
+                    self.animate_state(cx, ids!(hover.off));
+                }
+                self.drag_start = None;
+                // a finger that never moved far enough for a step clicks the upper or lower arrow
+                if fe.is_over && !self.dragged {
+                    let steps = if fe.rel.y < fe.rect.size.y * 0.5 {1.0} else {-1.0};
+                    action = self.change_value(cx, self.value + steps * self.step);
+                }
+            }
+            _ => ()
+        }
+        action
+    }
+
+    fn has_key_focus(&self, cx: &Cx) -> bool {
+        cx.has_key_focus(self.text_input.area()) || cx.has_key_focus(self.bg_quad.area())
+    }
+
+    fn commit_text(&mut self, cx: &mut Cx) -> NumberInputAction {
+        match self.text_input.value.trim().parse::<f64>() {
+            Ok(value) => self.change_value(cx, value),
+            Err(_) => {
+                self.update_text();
+                self.bg_quad.area().redraw(cx);
+                NumberInputAction::None
+            }
+        }
+    }
+
+    fn change_value(&mut self, cx: &mut Cx, value: f64) -> NumberInputAction {
+        // round to the shown decimals, so the steps don't pile up rounding errors
+        let scale = 10f64.powi(self.precision as i32);
+        let value = ((value * scale).round() / scale).max(self.min).min(self.max);
+        let changed = value != self.value;
+        self.value = value;
+        self.update_text();
+        self.bg_quad.area().redraw(cx);
+        if changed {
+            return NumberInputAction::Changed(value)
+        }
+        NumberInputAction::None
+    }
+
+    fn update_text(&mut self) {
+        self.text_input.value = format!("{:.*}", self.precision, self.value);
+    }
+
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        // the value can be changed from the outside, but not while it is being typed
+        if !cx.has_key_focus(self.text_input.area()) {
+            self.update_text();
+        }
+        self.bg_quad.begin(cx, walk, self.layout);
+        self.text_input.draw_walk(cx, self.text_input.get_walk());
+        self.bg_quad.end(cx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_cx::*,
+    };
+    
+    const SHIFT: KeyModifiers = KeyModifiers {shift: true, control: false, alt: false, logo: false};
+    const CONTROL: KeyModifiers = KeyModifiers {shift: false, control: true, alt: false, logo: false};
+    
+    struct Test {
+        test_cx: TestCx,
+        number_input: NumberInput,
+    }
+    
+    impl Test {
+        // a drawn number input from -10 to 10 in steps of 0.1, with its text clicked
+        fn new(precision: usize) -> Self {
+            let mut test_cx = TestCx::new();
+            let mut number_input: NumberInput = test_cx.new_component("makepad_component::number_input", id!(NumberInput));
+            number_input.min = -10.0;
+            number_input.max = 10.0;
+            number_input.step = 0.1;
+            number_input.precision = precision;
+            test_cx.draw( | cx | number_input.draw_walk(cx, number_input.walk));
+            let mut test = Self {test_cx, number_input};
+            test.event(finger_down(vec2(20.0, 10.0), KeyModifiers::default(), 1));
+            test.event(finger_up(vec2(20.0, 10.0)));
+            test
+        }
+        
+        fn event(&mut self, event: Event) -> NumberInputAction {
+            let number_input = &mut self.number_input;
+            self.test_cx.event(event, | cx, event | number_input.handle_event(cx, event))
+        }
+        
+        fn key(&mut self, key_code: KeyCode, modifiers: KeyModifiers) -> NumberInputAction {
+            self.event(key_down(key_code, modifiers))
+        }
+        
+        // replaces the text with the given text
+        fn type_text(&mut self, text: &str) {
+            self.key(KeyCode::KeyA, CONTROL);
+            self.event(text_input(text));
+        }
+    }
+    
+    #[test]
+    fn keys_step_the_value_without_rounding_errors() {
+        let mut test = Test::new(2);
+        test.key(KeyCode::ArrowUp, KeyModifiers::default());
+        test.key(KeyCode::ArrowUp, KeyModifiers::default());
+        assert!(test.key(KeyCode::ArrowUp, KeyModifiers::default()) == NumberInputAction::Changed(0.3));
+        assert!(test.key(KeyCode::ArrowDown, SHIFT) == NumberInputAction::Changed(-0.7));
+        assert_eq!(test.number_input.value, -0.7);
+    }
+    
+    #[test]
+    fn typed_values_are_rounded_and_clamped() {
+        let mut test = Test::new(1);
+        test.type_text("1.26");
+        assert!(test.key(KeyCode::Return, KeyModifiers::default()) == NumberInputAction::Changed(1.3));
+        test.type_text("25");
+        assert!(test.key(KeyCode::Return, KeyModifiers::default()) == NumberInputAction::Changed(10.0));
+        // the value is already at the maximum
+        test.type_text("11");
+        assert!(test.key(KeyCode::Return, KeyModifiers::default()) == NumberInputAction::None);
+        test.type_text("-10.04");
+        assert!(test.key(KeyCode::Return, KeyModifiers::default()) == NumberInputAction::Changed(-10.0));
+        // the stepped value stays in the range too
+        assert!(test.key(KeyCode::ArrowDown, KeyModifiers::default()) == NumberInputAction::None);
+    }
+    
+    #[test]
+    fn losing_the_key_focus_takes_the_typed_value() {
+        let mut test = Test::new(1);
+        test.type_text(" 4.44 ");
+        let number_input = &mut test.number_input;
+        let action = test.test_cx.set_key_focus(Area::Empty, | cx, event | number_input.handle_event(cx, event));
+        assert!(action == Some(NumberInputAction::Changed(4.4)));
+        // without the key focus the keys are left alone
+        assert!(test.key(KeyCode::ArrowUp, KeyModifiers::default()) == NumberInputAction::None);
+    }
+    
+    #[test]
+    fn invalid_text_puts_back_the_value() {
+        let mut test = Test::new(1);
+        test.type_text("4.4");
+        test.key(KeyCode::Return, KeyModifiers::default());
+        test.type_text("four");
+        assert!(test.key(KeyCode::Return, KeyModifiers::default()) == NumberInputAction::None);
+        assert_eq!(test.number_input.value, 4.4);
+        // the text shows the value again, so taking it over changes nothing
+        assert!(test.key(KeyCode::Return, KeyModifiers::default()) == NumberInputAction::None);
+        assert!(test.key(KeyCode::ArrowUp, KeyModifiers::default()) == NumberInputAction::Changed(4.5));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::makepad_platform::*;
+
+live_register!{
+    use makepad_platform::shader::std::*;
+    use makepad_component::theme::*;
+
+    Popup: {{Popup}} {
+        view: {
+            is_overlay: true
+        }
+        bg_quad: {
+            color: (COLOR_BG_HEADER)
+            fn pixel(self) -> vec4 {
+                let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                sdf.box(
+                    1.,
+                    1.,
+                    self.rect_size.x - 2.0,
+                    self.rect_size.y - 2.0,
+                    2.0
+                );
+                sdf.fill_keep(self.color);
+                sdf.stroke(COLOR_BG_APP, 1.0);
+                return sdf.result;
+            }
+        }
+        layout: {
+            flow: Flow::Down
+            padding: {left: 1.0, top: 3.0, right: 1.0, bottom: 3.0}
+        }
+        min_width: 0.0
+    }
+}
+
+/// A box drawn on top of everything else in the window, next to an anchor rect.
+///
+/// The popup is drawn into an overlay view, which the platform attaches to the main view of the
+/// window when that ends, so `DesktopWindow` draws it over its frame. While it is shown, content
+/// below it doesn't get the finger. It is placed below its anchor, or above it if there is no room
+/// below, and a finger down outside of it dismisses it.
+#[derive(Live, LiveHook)]
+pub struct Popup {
+    view: View,
+    bg_quad: DrawColor,
+    layout: Layout,
+    min_width: f32,
+
+    // the rect to show the popup next to, in window coordinates, `None` while closed
+    #[rust] anchor: Option<Rect>,
+    // the size of the popup when it was last drawn, used to place it
+    #[rust] size: Vec2,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PopupAction {
+    Dismissed,
+    None
+}
+
+impl Popup {
+    /// Shows the popup below the given rect, which is in window coordinates like the rect of
+    /// `Area::get_rect`. A popup that is already shown moves to the new rect.
+    pub fn open(&mut self, cx: &mut Cx, anchor: Rect) {
+        self.anchor = Some(anchor);
+        self.redraw(cx);
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        if self.anchor.take().is_some() {
+            self.redraw(cx);
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.anchor.is_some()
+    }
+
+    pub fn area(&self) -> Area {
+        self.view.area()
+    }
+
+    pub fn redraw(&mut self, cx: &mut Cx) {
+        self.view.redraw(cx);
+    }
+
+    pub fn begin(&mut self, cx: &mut Cx2d) -> ViewRedraw {
+        // the view the popup is drawn from is its parent, also while it is closed, so that
+        // redrawing a popup that was never shown redraws where it will be drawn from
+        cx.draw_lists[self.view.draw_list_id].codeflow_parent_id = cx.draw_list_stack.last().cloned();
+        let anchor = self.anchor.ok_or(()) ?;
+        let pass_size = cx.passes[cx.pass_id.unwrap()].pass_size;
+
+        // without a width from the anchor or the minimum width, the popup fits its content
+        let width = anchor.size.x.max(self.min_width);
+        let (width, walk_width) = if width > 0.0 {
+            (width, Size::Fixed(width))
+        }
+        else {
+            (self.size.x, Size::Fit)
+        };
+        let x = anchor.pos.x.min(pass_size.x - width).max(0.0);
+        let below = anchor.pos.y + anchor.size.y;
+        let y = if below + self.size.y > pass_size.y && anchor.pos.y - self.size.y >= 0.0 {
+            anchor.pos.y - self.size.y
+        }
+        else {
+            below
+        };
+
+        self.view.begin(cx, Walk {
+            abs_pos: Some(vec2(x, y)),
+            margin: Margin::default(),
+            width: walk_width,
+            height: Size::Fit,
+        }, Layout::flow_down()) ?;
+        self.bg_quad.begin(cx, Walk::size(walk_width, Size::Fit), self.layout);
+        Ok(())
+    }
+
+    pub fn end(&mut self, cx: &mut Cx2d) {
+        self.bg_quad.end(cx);
+        self.view.end(cx);
+        // the popup is placed using its size, which is only known now
+        let size = self.view.get_rect(cx).size;
+        if size != self.size {
+            self.size = size;
+            self.view.redraw(cx);
+        }
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> PopupAction {
+        if self.anchor.is_none() {
+            return PopupAction::None
+        }
+        match event {
+            Event::FingerDown(fe) => {
+                if !self.view.get_rect(cx).contains(fe.abs) {
+                    self.close(cx);
+                    return PopupAction::Dismissed
+                }
+            }
+            Event::WindowGeomChange(_) => {
+                self.close(cx);
+                return PopupAction::Dismissed
+            }
+            _ => ()
+        }
+        PopupAction::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_cx::*,
+    };
+
+    // the popup is placed with the size it had last time, so it takes two draws to settle
+    fn draw(test_cx: &mut TestCx, popup: &mut Popup) -> Rect {
+        for _ in 0..2 {
+            test_cx.draw( | cx | if popup.begin(cx).is_ok() {
+                cx.walk_turtle(Walk::size(Size::Fixed(40.0), Size::Fixed(30.0)));
+                popup.end(cx);
+            });
+        }
+        popup.area().get_rect(&test_cx.cx)
+    }
+
+    #[test]
+    fn opens_below_the_anchor_with_its_width() {
+        let mut test_cx = TestCx::new();
+        let mut popup: Popup = test_cx.new_component("makepad_component::popup", id!(Popup));
+        popup.open(&mut test_cx.cx, Rect {pos: vec2(10.0, 20.0), size: vec2(60.0, 10.0)});
+        let rect = draw(&mut test_cx, &mut popup);
+        assert_eq!(rect.pos, vec2(10.0, 30.0));
+        assert_eq!(rect.size.x, 60.0);
+    }
+
+    #[test]
+    fn opening_redraws_only_what_draws_the_popup() {
+        let mut test_cx = TestCx::new();
+        let mut popup: Popup = test_cx.new_component("makepad_component::popup", id!(Popup));
+        let mut view = View::new(&mut test_cx.cx);
+        let mut draw = | test_cx: &mut TestCx, popup: &mut Popup | test_cx.redraw( | cx | {
+            if view.begin(cx, Walk::default(), Layout::default()).is_ok() {
+                if popup.begin(cx).is_ok() {
+                    cx.walk_turtle(Walk::size(Size::Fixed(40.0), Size::Fixed(30.0)));
+                    popup.end(cx);
+                }
+                view.end(cx);
+            }
+        });
+        test_cx.cx.redraw_all();
+        draw(&mut test_cx, &mut popup);
+        // the popup was never drawn, but it knows where it is drawn from
+        popup.open(&mut test_cx.cx, Rect {pos: vec2(10.0, 20.0), size: vec2(60.0, 10.0)});
+        assert!(!test_cx.cx.new_draw_event.redraw_all);
+        draw(&mut test_cx, &mut popup);
+        draw(&mut test_cx, &mut popup);
+        assert_eq!(popup.area().get_rect(&test_cx.cx), Rect {pos: vec2(10.0, 30.0), size: vec2(60.0, 36.0)});
+    }
+
+    #[test]
+    fn flips_above_the_anchor_without_room_below() {
+        let mut test_cx = TestCx::new();
+        let mut popup: Popup = test_cx.new_component("makepad_component::popup", id!(Popup));
+        popup.open(&mut test_cx.cx, Rect {pos: vec2(10.0, 580.0), size: vec2(60.0, 10.0)});
+        let rect = draw(&mut test_cx, &mut popup);
+        assert_eq!(rect.pos.y + rect.size.y, 580.0);
+    }
+
+    #[test]
+    fn stays_inside_the_pass() {
+        let mut test_cx = TestCx::new();
+        let mut popup: Popup = test_cx.new_component("makepad_component::popup", id!(Popup));
+        popup.open(&mut test_cx.cx, Rect {pos: vec2(780.0, 20.0), size: vec2(100.0, 10.0)});
+        let rect = draw(&mut test_cx, &mut popup);
+        assert_eq!(rect.pos.x, 700.0);
+        // without a width the popup fits its content
+        popup.open(&mut test_cx.cx, Rect {pos: vec2(790.0, 20.0), size: Vec2::default()});
+        let rect = draw(&mut test_cx, &mut popup);
+        assert_eq!(rect.pos.x + rect.size.x, 800.0);
+    }
+
+    #[test]
+    fn finger_down_outside_dismisses() {
+        let mut test_cx = TestCx::new();
+        let mut popup: Popup = test_cx.new_component("makepad_component::popup", id!(Popup));
+        assert!(!popup.is_open());
+        popup.open(&mut test_cx.cx, Rect {pos: vec2(10.0, 20.0), size: vec2(60.0, 10.0)});
+        draw(&mut test_cx, &mut popup);
+        let mut event = | test_cx: &mut TestCx, popup: &mut Popup, abs | {
+            test_cx.event(finger_down(abs, KeyModifiers::default(), 1), | cx, event | popup.handle_event(cx, event))
+        };
+        assert_eq!(event(&mut test_cx, &mut popup, vec2(20.0, 40.0)), PopupAction::None);
+        assert!(popup.is_open());
+        assert_eq!(event(&mut test_cx, &mut popup, vec2(200.0, 40.0)), PopupAction::Dismissed);
+        assert!(!popup.is_open());
+        assert_eq!(event(&mut test_cx, &mut popup, vec2(200.0, 40.0)), PopupAction::None);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::{
+    makepad_platform::*,
+    popup::{Popup, PopupAction},
+};
+
+live_register!{
+    use makepad_platform::shader::std::*;
+    use makepad_component::theme::*;
+
+    DrawMenuItem: {{DrawMenuItem}} {
+        fn pixel(self) -> vec4 {
+            return mix(
+                mix(#0000, COLOR_BG_EDITOR, self.selected),
+                COLOR_BG_SELECTED,
+                self.hover
+            );
+        }
+    }
+
+    PopupMenu: {{PopupMenu}} {
+        popup: {
+            min_width: 150.0
+        }
+        item_text: {
+            text_style: FONT_LABEL {}
+            color: (COLOR_TEXT_DEFAULT)
+        }
+        item_height: (DIM_DATA_ITEM_HEIGHT)
+        item_layout: {
+            align: {y: 0.5}
+            padding: {left: 10.0, right: 10.0}
+        }
+    }
+}
+
+#[derive(Live, LiveHook)]#[repr(C)]
+struct DrawMenuItem {
+    draw_super: DrawQuad,
+    hover: f32,
+    selected: f32,
+}
+
+/// A list of items in a `Popup`, used for context menus and by `DropDown`.
+///
+/// The menu takes the key focus while it is shown, so the arrow keys move between items, return
+/// picks one and escape closes it.
+#[derive(Live, LiveHook)]
+pub struct PopupMenu {
+    popup: Popup,
+    item_quad: DrawMenuItem,
+    item_text: DrawText,
+    item_height: f32,
+    item_layout: Layout,
+
+    #[rust] items: Vec<String>,
+    #[rust] selected_item: Option<usize>,
+    #[rust] hover_item: Option<usize>,
+    #[rust] item_rects: Vec<Rect>,
+    #[rust] prev_key_focus: Area,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum PopupMenuAction {
+    Selected(usize),
+    Dismissed,
+    None
+}
+
+impl PopupMenu {
+    pub fn set_items(&mut self, cx: &mut Cx, items: Vec<String>) {
+        self.items = items;
+        self.hover_item = None;
+        self.popup.redraw(cx);
+    }
+
+    pub fn items(&self) -> &[String] {
+        &self.items
+    }
+
+    /// Shows the menu below the given rect, in window coordinates, with the given item marked.
+    pub fn open(&mut self, cx: &mut Cx, anchor: Rect, selected_item: Option<usize>) {
+        self.selected_item = selected_item;
+        self.hover_item = selected_item;
+        if !self.popup.is_open() {
+            self.prev_key_focus = cx.next_key_focus;
+        }
+        self.popup.open(cx, anchor);
+        cx.set_key_focus(self.popup.area());
+    }
+
+    /// Shows the menu at a position in window coordinates, such as the finger position for a
+    /// context menu.
+    pub fn open_at(&mut self, cx: &mut Cx, pos: Vec2) {
+        self.open(cx, Rect {pos, size: Vec2::default()}, None);
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        self.popup.close(cx);
+        // give the focus back, unless it already went somewhere else
+        if cx.has_key_focus(self.popup.area()) {
+            cx.set_key_focus(self.prev_key_focus);
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_open()
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx2d) {
+        if self.popup.begin(cx).is_err() {
+            return
+        }
+        self.item_rects.clear();
+        for (index, item) in self.items.iter().enumerate() {
+            self.item_quad.hover = if self.hover_item == Some(index) {1.0} else {0.0};
+            self.item_quad.selected = if self.selected_item == Some(index) {1.0} else {0.0};
+            self.item_quad.begin(cx, Walk::size(Size::Fill, Size::Fixed(self.item_height)), self.item_layout);
+            self.item_rects.push(cx.turtle().rect());
+            self.item_text.draw_walk(cx, Walk::fit(), Align::default(), item);
+            self.item_quad.end(cx);
+        }
+        self.popup.end(cx);
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> PopupMenuAction {
+        if !self.popup.is_open() {
+            return PopupMenuAction::None
+        }
+        if let PopupAction::Dismissed = self.popup.handle_event(cx, event) {
+            self.close(cx);
+            return PopupMenuAction::Dismissed
+        }
+        match event.hits(cx, self.popup.area()) {
+            HitEvent::KeyFocusLost(_) => {
+                self.close(cx);
+                return PopupMenuAction::Dismissed
+            }
+            HitEvent::FingerHover(fe) => {
+                let hover_item = self.item_at(fe.abs);
+                if hover_item != self.hover_item {
+                    self.hover_item = hover_item;
+                    self.popup.redraw(cx);
+                }
+            }
+            HitEvent::FingerUp(fe) => {
+                if let Some(item) = self.item_at(fe.abs) {
+                    self.close(cx);
+                    return PopupMenuAction::Selected(item)
+                }
+            }
+            HitEvent::KeyDown(ke) => {
+                let last_item = self.items.len().saturating_sub(1);
+                match ke.key_code {
+                    KeyCode::ArrowUp => {
+                        self.hover_item = Some(self.hover_item.map_or(last_item, | item | item.saturating_sub(1)));
+                        self.popup.redraw(cx);
+                    }
+                    KeyCode::ArrowDown => {
+                        self.hover_item = Some(self.hover_item.map_or(0, | item | (item + 1).min(last_item)));
+                        self.popup.redraw(cx);
+                    }
+                    KeyCode::Return | KeyCode::Space => if let Some(item) = self.hover_item.filter( | item | *item < self.items.len()) {
+                        self.close(cx);
+                        return PopupMenuAction::Selected(item)
+                    }
+                    KeyCode::Escape => {
+                        self.close(cx);
+                        return PopupMenuAction::Dismissed
+                    }
+                    _ => ()
+                }
+            }
+            _ => ()
+        }
+        PopupMenuAction::None
+    }
+
+    fn item_at(&self, abs: Vec2) -> Option<usize> {
+        self.item_rects.iter().position( | rect | rect.contains(abs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_cx::*,
+    };
+
+    const ANCHOR: Rect = Rect {pos: Vec2 {x: 10.0, y: 20.0}, size: Vec2 {x: 100.0, y: 10.0}};
+
+    // an open menu with three items and the key focus
+    fn open_menu(test_cx: &mut TestCx, selected_item: Option<usize>) -> PopupMenu {
+        let mut popup_menu: PopupMenu = test_cx.new_component("makepad_component::popup_menu", id!(PopupMenu));
+        popup_menu.set_items(&mut test_cx.cx, vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        popup_menu.open(&mut test_cx.cx, ANCHOR, selected_item);
+        test_cx.draw( | cx | popup_menu.draw(cx));
+        let area = popup_menu.popup.area();
+        test_cx.set_key_focus(area, | cx, event | popup_menu.handle_event(cx, event));
+        popup_menu
+    }
+
+    fn event(test_cx: &mut TestCx, popup_menu: &mut PopupMenu, event: Event) -> PopupMenuAction {
+        test_cx.event(event, | cx, event | popup_menu.handle_event(cx, event))
+    }
+
+    #[test]
+    fn keys_pick_an_item() {
+        let mut test_cx = TestCx::new();
+        let mut popup_menu = open_menu(&mut test_cx, Some(1));
+        event(&mut test_cx, &mut popup_menu, key_down(KeyCode::ArrowDown, KeyModifiers::default()));
+        event(&mut test_cx, &mut popup_menu, key_down(KeyCode::ArrowDown, KeyModifiers::default()));
+        assert_eq!(event(&mut test_cx, &mut popup_menu, key_down(KeyCode::Return, KeyModifiers::default())), PopupMenuAction::Selected(2));
+        assert!(!popup_menu.is_open());
+
+        let mut popup_menu = open_menu(&mut test_cx, None);
+        event(&mut test_cx, &mut popup_menu, key_down(KeyCode::ArrowUp, KeyModifiers::default()));
+        assert_eq!(event(&mut test_cx, &mut popup_menu, key_down(KeyCode::Space, KeyModifiers::default())), PopupMenuAction::Selected(2));
+    }
+
+    #[test]
+    fn escape_dismisses() {
+        let mut test_cx = TestCx::new();
+        let mut popup_menu = open_menu(&mut test_cx, None);
+        assert_eq!(event(&mut test_cx, &mut popup_menu, key_down(KeyCode::Escape, KeyModifiers::default())), PopupMenuAction::Dismissed);
+        assert!(!popup_menu.is_open());
+    }
+
+    #[test]
+    fn clicking_an_item_selects_it() {
+        let mut test_cx = TestCx::new();
+        let mut popup_menu = open_menu(&mut test_cx, None);
+        // the middle of the menu is the middle item
+        let rect = popup_menu.popup.area().get_rect(&test_cx.cx);
+        let abs = rect.pos + rect.size * 0.5;
+        assert_eq!(event(&mut test_cx, &mut popup_menu, finger_down(abs, KeyModifiers::default(), 1)), PopupMenuAction::None);
+        assert_eq!(event(&mut test_cx, &mut popup_menu, finger_up(abs)), PopupMenuAction::Selected(1));
+    }
+
+    #[test]
+    fn clicking_outside_dismisses() {
+        let mut test_cx = TestCx::new();
+        let mut popup_menu = open_menu(&mut test_cx, None);
+        assert_eq!(event(&mut test_cx, &mut popup_menu, finger_down(vec2(500.0, 500.0), KeyModifiers::default(), 1)), PopupMenuAction::Dismissed);
+        assert!(!popup_menu.is_open());
+    }
+}
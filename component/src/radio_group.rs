@@ -0,0 +1,129 @@
+use crate::{
+    makepad_platform::*,
+    check_box::{CheckBox, CheckBoxAction},
+    component_map::ComponentMap,
+    frame_component::*,
+};
+
+live_register!{
+    use makepad_component::check_box::RadioButton;
+
+    RadioGroup: {{RadioGroup}} {
+        radio: RadioButton {}
+        walk: {
+            width: Size::Fit,
+            height: Size::Fit,
+        }
+        layout: {
+            flow: Flow::Down
+        }
+    }
+}
+
+/// A column of `RadioButton`s with one of them selected, one for each label. With the key focus on
+/// one of the buttons, the arrow keys select the previous or next one.
+#[derive(Live, FrameComponent)]
+#[live_register(frame_component!(RadioGroup))]
+pub struct RadioGroup {
+    radio: Option<LivePtr>,
+
+    #[alias(width, walk.width)]
+    #[alias(height, walk.height)]
+    #[alias(margin, walk.margin)]
+    walk: Walk,
+    layout: Layout,
+
+    pub selected: usize,
+    #[rust] labels: Vec<String>,
+    #[rust] radios: ComponentMap<usize, CheckBox>,
+    // the view the group was drawn in, which is what redraws buttons that don't exist yet
+    #[rust] area: Area,
+}
+
+#[derive(Clone, PartialEq, FrameComponentAction)]
+pub enum RadioGroupAction {
+    Changed(usize),
+    None
+}
+
+impl LiveHook for RadioGroup {
+    fn after_apply(&mut self, cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) {
+        if let Some(index) = nodes.child_by_name(index, id!(radio).as_field()) {
+            for radio in self.radios.values_mut() {
+                radio.apply(cx, from, index, nodes);
+            }
+        }
+    }
+}
+
+impl RadioGroup {
+    pub fn set_labels(&mut self, cx: &mut Cx, labels: Vec<String>) {
+        self.labels = labels;
+        self.redraw(cx);
+    }
+
+    pub fn labels(&self) -> &[String] {
+        &self.labels
+    }
+
+    pub fn set_selected(&mut self, cx: &mut Cx, selected: usize, animate: Animate) {
+        self.selected = selected;
+        for (index, radio) in self.radios.iter_mut() {
+            radio.set_selected(cx, *index == selected, animate);
+        }
+    }
+
+    pub fn redraw(&mut self, cx: &mut Cx) {
+        self.area.redraw(cx);
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> RadioGroupAction {
+        let mut selected = None;
+        for (index, radio) in self.radios.iter_mut() {
+            if let CheckBoxAction::Changed(true) = radio.handle_event(cx, event) {
+                selected = Some(*index);
+            }
+        }
+
+        if let Event::KeyDown(ke) = event {
+            let focus = self.radios.iter().find( | (_, radio) | cx.has_key_focus(radio.area())).map( | (index, _) | *index);
+            if let Some(focus) = focus {
+                let next = match ke.key_code {
+                    KeyCode::ArrowUp | KeyCode::ArrowLeft => focus.checked_sub(1),
+                    KeyCode::ArrowDown | KeyCode::ArrowRight => Some(focus + 1).filter( | next | *next < self.labels.len()),
+                    _ => None
+                };
+                if let Some(radio) = next.and_then( | next | self.radios.get(&next)) {
+                    cx.set_key_focus(radio.area());
+                    selected = next;
+                }
+            }
+        }
+
+        match selected {
+            Some(selected) if selected != self.selected => {
+                self.set_selected(cx, selected, Animate::Yes);
+                RadioGroupAction::Changed(selected)
+            }
+            _ => RadioGroupAction::None
+        }
+    }
+
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
+        let draw_list_id = *cx.draw_list_stack.last().unwrap();
+        self.area = Area::DrawList(DrawListArea {draw_list_id, redraw_id: cx.draw_lists[draw_list_id].redraw_id});
+        cx.begin_turtle(walk, self.layout);
+        for (index, label) in self.labels.iter().enumerate() {
+            let radio_ptr = self.radio;
+            let selected = self.selected;
+            let radio = self.radios.get_or_insert(cx, index, | cx | {
+                let mut radio = CheckBox::new_from_ptr(cx, radio_ptr);
+                radio.set_selected(cx, index == selected, Animate::No);
+                radio
+            });
+            radio.draw_label(cx, label);
+        }
+        self.radios.retain_visible();
+        cx.end_turtle();
+    }
+}
@@ -2,7 +2,7 @@ use {
     crate::{
         makepad_platform::*,
         frame_component::*,
-        text_input::TextInput,
+        text_input::{TextInput, TextInputAction},
     }
 };
 
@@ -161,7 +161,7 @@ pub struct Slider {
     #[rust] pub dragging: Option<f32>,
 }
 
-#[derive(Clone, FrameComponentAction)]
+#[derive(Clone, PartialEq, FrameComponentAction)]
 pub enum SliderAction {
     StartSlide,
     Slide(f32),
@@ -173,7 +173,27 @@ impl Slider {
     
     pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> SliderAction {
         self.state_handle_event(cx, event);
-        self.text_input.handle_event(cx, event);
+        let mut action = SliderAction::None;
+        
+        // a value typed into the text input is taken when it is returned or left
+        if let Event::KeyFocus(kf) = event {
+            if kf.prev == self.text_input.area() {
+                action = self.commit_text(cx);
+            }
+        }
+        match self.text_input.handle_event(cx, event) {
+            TextInputAction::Return(_) => {
+                action = self.commit_text(cx);
+                self.text_input.select_all();
+            }
+            TextInputAction::Escape => {
+                self.update_text();
+                self.draw_slider.area().redraw(cx);
+            }
+            _ => ()
+        }
+        
+        // the text input takes the finger on the text, this is the finger on the rest of the slider
         match event.hits(cx, self.draw_slider.area()) {
             HitEvent::KeyFocusLost(_) => {
                 self.animate_state(cx, ids!(focus.off));
@@ -218,6 +238,7 @@ impl Slider {
                 // lets drag the fucker
                 if let Some(start_pos) = self.dragging {
                     self.value = (start_pos + (fe.rel.x - fe.rel_start.x) / fe.rect.size.x).max(0.0).min(1.0);
+                    self.update_text();
                     self.draw_slider.area().redraw(cx);
                     /*self.draw_slider.apply_over(cx, live!{
                         slide_pos: (self.value)
@@ -227,7 +248,26 @@ impl Slider {
             }
             _ => ()
         }
-        SliderAction::None
+        action
+    }
+    
+    fn commit_text(&mut self, cx: &mut Cx) -> SliderAction {
+        let value = self.text_input.value.trim().parse::<f32>().map( | value | value.max(0.0).min(1.0));
+        // text that isn't a number puts back the value
+        self.update_text();
+        self.draw_slider.area().redraw(cx);
+        match value {
+            Ok(value) if value != self.value => {
+                self.value = value;
+                self.update_text();
+                SliderAction::Slide(value)
+            }
+            _ => SliderAction::None
+        }
+    }
+    
+    fn update_text(&mut self) {
+        self.text_input.value = format!("{:.2}", self.value);
     }
     
     pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
@@ -235,7 +275,10 @@ impl Slider {
         self.draw_slider.begin(cx, walk, self.layout);
         // ok so. we wanna do a 'fill' here with the label and request a future
         if let Some(dw) = cx.defer_walk(self.label_walk){
-            self.text_input.value = format!("{:.2}", self.value); //, (self.value*100.0) as usize);
+            // the value can be changed from the outside, but not while it is being typed
+            if !cx.has_key_focus(self.text_input.area()) {
+                self.update_text();
+            }
             self.text_input.draw_walk(cx, self.text_input.get_walk());
             self.label_text.draw_walk(cx, dw.resolve(cx), self.label_align, &self.label);
         }
@@ -243,3 +286,83 @@ impl Slider {
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_cx::*,
+    };
+    
+    const CONTROL: KeyModifiers = KeyModifiers {shift: false, control: true, alt: false, logo: false};
+    
+    struct Test {
+        test_cx: TestCx,
+        slider: Slider,
+    }
+    
+    impl Test {
+        // a drawn slider at 0.5, with its text clicked
+        fn new() -> Self {
+            let mut test_cx = TestCx::new();
+            let mut slider: Slider = test_cx.new_component("makepad_component::slider", id!(Slider));
+            slider.value = 0.5;
+            test_cx.draw( | cx | slider.draw_walk(cx, Walk::fixed_size(200.0, 20.0)));
+            let mut test = Self {test_cx, slider};
+            let rect = test.slider.text_input.area().get_rect(&test.test_cx.cx);
+            let pos = rect.pos + rect.size * 0.5;
+            test.event(finger_down(pos, KeyModifiers::default(), 1));
+            test.event(finger_up(pos));
+            test
+        }
+        
+        fn event(&mut self, event: Event) -> SliderAction {
+            let slider = &mut self.slider;
+            self.test_cx.event(event, | cx, event | slider.handle_event(cx, event))
+        }
+        
+        fn key(&mut self, key_code: KeyCode) -> SliderAction {
+            self.event(key_down(key_code, KeyModifiers::default()))
+        }
+        
+        // replaces the text with the given text
+        fn type_text(&mut self, text: &str) {
+            self.event(key_down(KeyCode::KeyA, CONTROL));
+            self.event(text_input(text));
+        }
+    }
+    
+    #[test]
+    fn typed_values_are_clamped() {
+        let mut test = Test::new();
+        test.type_text("0.25");
+        assert!(test.key(KeyCode::Return) == SliderAction::Slide(0.25));
+        test.type_text("3");
+        assert!(test.key(KeyCode::Return) == SliderAction::Slide(1.0));
+        assert_eq!(test.slider.text_input.value, "1.00");
+        // the value is already at the maximum
+        test.type_text("2");
+        assert!(test.key(KeyCode::Return) == SliderAction::None);
+    }
+    
+    #[test]
+    fn losing_the_key_focus_takes_the_typed_value() {
+        let mut test = Test::new();
+        test.type_text(" 0.1 ");
+        let slider = &mut test.slider;
+        let action = test.test_cx.set_key_focus(Area::Empty, | cx, event | slider.handle_event(cx, event));
+        assert!(action == Some(SliderAction::Slide(0.1)));
+        assert_eq!(test.slider.text_input.value, "0.10");
+    }
+    
+    #[test]
+    fn invalid_or_escaped_text_puts_back_the_value() {
+        let mut test = Test::new();
+        test.type_text("half");
+        assert!(test.key(KeyCode::Return) == SliderAction::None);
+        assert_eq!(test.slider.text_input.value, "0.50");
+        test.type_text("0.7");
+        test.key(KeyCode::Escape);
+        assert_eq!(test.slider.text_input.value, "0.50");
+        assert_eq!(test.slider.value, 0.5);
+    }
+}
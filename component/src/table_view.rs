@@ -0,0 +1,214 @@
+use crate::{
+    makepad_platform::*,
+    component_map::ComponentMap,
+    list_view::{ListDataSource, ListSort, ListView, ListViewAction},
+    splitter::{Splitter, SplitterAction, SplitterAlign},
+};
+
+live_register!{
+    use makepad_platform::shader::std::*;
+    use makepad_component::theme::*;
+    use makepad_component::splitter::Splitter;
+
+    DrawSortArrow: {{DrawSortArrow}} {
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            let w = self.rect_size.x;
+            let h = self.rect_size.y;
+            if self.descending > 0.5 {
+                sdf.move_to(0., 0.);
+                sdf.line_to(w, 0.);
+                sdf.line_to(w * 0.5, h);
+            }
+            else {
+                sdf.move_to(0., h);
+                sdf.line_to(w, h);
+                sdf.line_to(w * 0.5, 0.);
+            }
+            sdf.close_path();
+            return sdf.fill(COLOR_TEXT_DEFAULT);
+        }
+    }
+
+    TableView: {{TableView}} {
+        header_height: (DIM_DATA_ITEM_HEIGHT)
+        header_padding: 5.0
+        header_quad: {color: (COLOR_BG_HEADER)}
+        header_text: {
+            text_style: FONT_DATA {top_drop: 1.15}
+            color: (COLOR_TEXT_DEFAULT)
+        }
+        sort_arrow: {
+            walk: {width: 7, height: 5, margin: {left: 5.0}}
+        }
+        column_splitter: Splitter {}
+        layout: {flow: Flow::Down}
+    }
+}
+
+#[derive(Live, LiveHook)]#[repr(C)]
+struct DrawSortArrow {
+    draw_super: DrawQuad,
+    walk: Walk,
+    descending: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TableColumn {
+    pub name: String,
+    pub width: f32,
+}
+
+/// A `ListView` with a header that names its columns. Dragging the bar between two column names
+/// resizes the column, clicking a column name sorts the rows on that column, and clicking it again
+/// reverses the order.
+#[derive(Live, LiveHook)]
+pub struct TableView {
+    list_view: ListView,
+    header_quad: DrawColor,
+    header_text: DrawText,
+    sort_arrow: DrawSortArrow,
+    column_splitter: Option<LivePtr>,
+
+    layout: Layout,
+    header_height: f32,
+    header_padding: f32,
+
+    #[rust] columns: Vec<TableColumn>,
+    // every column except the last ends in a splitter, column `i` is the first half of splitter `i`
+    #[rust] column_splitters: ComponentMap<usize, Splitter>,
+    // the left edge of each column relative to the header, followed by the right edge of the header
+    #[rust] column_edges: Vec<f32>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TableViewAction {
+    SelectionChanged,
+    RowActivated(usize),
+    SortChanged(Option<ListSort>),
+    None
+}
+
+impl TableView {
+    /// Replaces the columns. Their widths are the initial widths, the last column always fills
+    /// the remaining space.
+    pub fn set_columns(&mut self, cx: &mut Cx, columns: Vec<TableColumn>) {
+        self.column_splitters.clear();
+        self.columns = columns;
+        self.list_view.invalidate(cx);
+        self.header_quad.area().redraw(cx);
+    }
+
+    pub fn columns(&self) -> &[TableColumn] {
+        &self.columns
+    }
+
+    /// Returns the current widths of the columns, as drawn last.
+    pub fn column_widths(&self) -> Vec<f32> {
+        self.column_edges.windows(2).map( | edges | edges[1] - edges[0]).collect()
+    }
+
+    pub fn list_view(&mut self) -> &mut ListView {
+        &mut self.list_view
+    }
+
+    pub fn redraw(&mut self, cx: &mut Cx) {
+        self.header_quad.area().redraw(cx);
+        self.list_view.redraw(cx);
+    }
+
+    pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk, source: &dyn ListDataSource) {
+        cx.begin_turtle(walk, self.layout);
+        self.draw_header(cx);
+        let column_widths = self.column_widths();
+        self.list_view.draw_walk_with_columns(cx, Walk::default(), source, &column_widths);
+        cx.end_turtle();
+    }
+
+    fn draw_header(&mut self, cx: &mut Cx2d) {
+        self.header_quad.begin(cx, Walk::size(Size::Fill, Size::Fixed(self.header_height)), Layout::flow_right());
+        let header_rect = cx.turtle().rect();
+        let sort = self.list_view.sort();
+
+        self.column_edges.clear();
+        for column in 0..self.columns.len() {
+            let is_last = column + 1 == self.columns.len();
+            if !is_last {
+                let column_splitter = self.column_splitter;
+                let width = self.columns[column].width;
+                let splitter = self.column_splitters.get_or_insert(cx, column, | cx | {
+                    let mut splitter = Splitter::new_from_ptr(cx, column_splitter);
+                    splitter.set_axis(Axis::Horizontal);
+                    splitter.set_align(SplitterAlign::FromStart(width));
+                    splitter
+                });
+                splitter.begin(cx, Walk::default());
+            }
+            self.column_edges.push(cx.turtle().rect().pos.x - header_rect.pos.x);
+
+            cx.begin_turtle(Walk::default(), Layout {
+                padding: Padding {left: self.header_padding, ..Padding::default()},
+                align: Align {x: 0.0, y: 0.5},
+                ..Layout::flow_right()
+            });
+            self.header_text.draw_walk(cx, Walk::fit(), Align::default(), &self.columns[column].name);
+            if let Some(sort) = sort.filter( | sort | sort.column == column) {
+                self.sort_arrow.descending = if sort.descending {1.0} else {0.0};
+                let walk = self.sort_arrow.walk;
+                self.sort_arrow.draw_walk(cx, walk);
+            }
+            cx.end_turtle();
+
+            if !is_last {
+                self.column_splitters.get_mut(&column).unwrap().middle(cx);
+            }
+        }
+        for column in (0..self.columns.len().saturating_sub(1)).rev() {
+            self.column_splitters.get_mut(&column).unwrap().end(cx);
+        }
+        self.column_splitters.retain_visible();
+        self.column_edges.push(header_rect.size.x);
+
+        self.header_quad.end(cx);
+    }
+
+    pub fn handle_event_with_fn(
+        &mut self,
+        cx: &mut Cx,
+        event: &mut Event,
+        dispatch_action: &mut dyn FnMut(&mut Cx, TableViewAction),
+    ) {
+        let mut resized = false;
+        for splitter in self.column_splitters.values_mut() {
+            splitter.handle_event_with_fn(cx, event, &mut | _, action | {
+                if let SplitterAction::Changed {..} = action {
+                    resized = true;
+                }
+            });
+        }
+        if resized {
+            self.redraw(cx);
+        }
+
+        if let HitEvent::FingerDown(fe) = event.hits(cx, self.header_quad.area()) {
+            // the splitters take the finger when it is on a bar, so this is a click on a name
+            let column = self.column_edges.partition_point( | edge | *edge <= fe.rel.x);
+            if column > 0 && column <= self.columns.len() {
+                let column = column - 1;
+                let sort = match self.list_view.sort() {
+                    Some(sort) if sort.column == column => Some(ListSort {column, descending: !sort.descending}),
+                    _ => Some(ListSort {column, descending: false})
+                };
+                self.list_view.set_sort(cx, sort);
+                self.header_quad.area().redraw(cx);
+                dispatch_action(cx, TableViewAction::SortChanged(sort));
+            }
+        }
+
+        self.list_view.handle_event_with_fn(cx, event, &mut | cx, action | match action {
+            ListViewAction::SelectionChanged => dispatch_action(cx, TableViewAction::SelectionChanged),
+            ListViewAction::RowActivated(row) => dispatch_action(cx, TableViewAction::RowActivated(row)),
+            ListViewAction::None => ()
+        });
+    }
+}
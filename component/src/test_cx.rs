@@ -0,0 +1,105 @@
+use crate::makepad_platform::*;
+
+/// Runs components in tests without a window. Components are made from their live definitions
+/// and drawn into a pass of their own, so they have areas and take events like they do in an app.
+pub struct TestCx {
+    pub cx: Cx,
+    pass: Pass,
+    view: View,
+}
+
+impl TestCx {
+    pub fn new() -> Self {
+        let mut cx = Cx::default();
+        crate::live_register(&mut cx);
+        cx.live_expand();
+        cx.live_scan_dependencies();
+        let mut pass = Pass::new(&mut cx);
+        pass.set_size(&mut cx, vec2(800.0, 600.0));
+        let view = View::new(&mut cx);
+        Self {cx, pass, view}
+    }
+    
+    pub fn new_component<T: LiveNew>(&mut self, module_path: &str, id: LiveId) -> T {
+        T::new_from_module(&mut self.cx, module_path, id).unwrap()
+    }
+    
+    pub fn draw(&mut self, draw: impl FnOnce(&mut Cx2d)) {
+        self.cx.redraw_all();
+        self.redraw(draw);
+    }
+    
+    /// Draws what was asked to be redrawn since the last draw, like the platform does.
+    pub fn redraw(&mut self, draw: impl FnOnce(&mut Cx2d)) {
+        let mut event = Event::Draw(std::mem::take(&mut self.cx.new_draw_event));
+        let draw_event = if let Event::Draw(draw_event) = &mut event {draw_event} else {unreachable!()};
+        let cx = &mut Cx2d::new(&mut self.cx, draw_event);
+        cx.begin_pass(&self.pass);
+        if self.view.begin(cx, Walk::default(), Layout::default()).is_ok() {
+            draw(cx);
+            self.view.end(cx);
+        }
+        cx.end_pass(&self.pass);
+        let max = vec2(f32::INFINITY, f32::INFINITY);
+        self.update_clips(self.view.draw_list_id, Vec2::default(), (-max, max));
+    }
+    
+    // the platform clips views and draw calls while painting, and finger events are only hit
+    // within the clip
+    fn update_clips(&mut self, draw_list_id: usize, scroll: Vec2, clip: (Vec2, Vec2)) {
+        let draw_list = &mut self.cx.draw_lists[draw_list_id];
+        draw_list.parent_scroll = scroll;
+        let local_scroll = draw_list.get_local_scroll();
+        let clip = draw_list.intersect_clip(clip);
+        for draw_item_id in 0..draw_list.draw_items_len {
+            let draw_item = &mut self.cx.draw_lists[draw_list_id].draw_items[draw_item_id];
+            if let Some(sub_view_id) = draw_item.sub_view_id {
+                self.update_clips(sub_view_id, scroll + local_scroll, clip);
+            }
+            else if let Some(draw_call) = draw_item.draw_call.as_mut() {
+                draw_call.draw_uniforms.set_local_scroll(scroll, local_scroll, &draw_call.options);
+                draw_call.draw_uniforms.set_clip(clip);
+            }
+        }
+    }
+    
+    /// Hands the event to `handle_event`, and then moves the key focus if that was asked for,
+    /// with a `KeyFocus` event of its own like the platform sends.
+    pub fn event<R>(&mut self, mut event: Event, mut handle_event: impl FnMut(&mut Cx, &mut Event) -> R) -> R {
+        let ret = handle_event(&mut self.cx, &mut event);
+        self.apply_key_focus(&mut handle_event);
+        ret
+    }
+    
+    /// Moves the key focus, and returns what `handle_event` does with the `KeyFocus` event.
+    pub fn set_key_focus<R>(&mut self, area: Area, mut handle_event: impl FnMut(&mut Cx, &mut Event) -> R) -> Option<R> {
+        self.cx.set_key_focus(area);
+        self.apply_key_focus(&mut handle_event)
+    }
+    
+    fn apply_key_focus<R>(&mut self, handle_event: &mut impl FnMut(&mut Cx, &mut Event) -> R) -> Option<R> {
+        if self.cx.next_key_focus == self.cx.key_focus {
+            return None
+        }
+        self.cx.prev_key_focus = self.cx.key_focus;
+        self.cx.key_focus = self.cx.next_key_focus;
+        let mut event = Event::KeyFocus(KeyFocusEvent {prev: self.cx.prev_key_focus, focus: self.cx.key_focus});
+        Some(handle_event(&mut self.cx, &mut event))
+    }
+}
+
+pub fn key_down(key_code: KeyCode, modifiers: KeyModifiers) -> Event {
+    Event::KeyDown(KeyEvent {key_code, is_repeat: false, modifiers, time: 0.0})
+}
+
+pub fn text_input(input: &str) -> Event {
+    Event::TextInput(TextInputEvent {input: input.to_string(), replace_last: false, was_paste: false})
+}
+
+pub fn finger_down(abs: Vec2, modifiers: KeyModifiers, tap_count: u32) -> Event {
+    Event::FingerDown(FingerDownEvent {abs, modifiers, tap_count, ..FingerDownEvent::default()})
+}
+
+pub fn finger_up(abs: Vec2) -> Event {
+    Event::FingerUp(FingerUpEvent {abs, ..FingerUpEvent::default()})
+}
@@ -1,8 +1,7 @@
-#![allow(unused)]
 use {
+    std::ops::Range,
     crate::{
         makepad_platform::*,
-        button_logic::*,
         frame_component::*,
     }
 };
@@ -56,6 +55,16 @@ live_register!{
             }
         }
         
+        cursor_quad: {
+            color: #b
+        }
+        
+        selection_quad: {
+            color: (COLOR_BG_SELECTED)
+        }
+        
+        cursor_width: 2.0
+        
         walk: {
             width: Size::Fit,
             height: Size::Fill,
@@ -105,6 +114,7 @@ live_register!{
     }
 }
 
+
 #[derive(Live, LiveHook, FrameComponent)]
 #[live_register(frame_component!(TextInput))]
 pub struct TextInput {
@@ -112,44 +122,641 @@ pub struct TextInput {
     
     select_quad: DrawQuad,
     label_text: DrawText,
+    cursor_quad: DrawColor,
+    selection_quad: DrawColor,
     
     walk: Walk,
     align: Align,
     layout: Layout,
     
-    pub value: String
+    cursor_width: f32,
+    pub multiline: bool,
+    
+    pub value: String,
+    
+    // the cursor is a pair of char indices into the value, the selection runs from tail to head
+    #[rust] cursor_head: usize,
+    #[rust] cursor_tail: usize,
+    // where the text starts, relative to the select_quad
+    #[rust] text_rel: Vec2,
+    // the chars inserted by the last text input event, which an IME may ask us to replace
+    #[rust] last_input: Option<Range<usize >>,
+    #[rust] undo_group: Option<UndoGroup>,
+    #[rust] undo_stack: Vec<UndoItem>,
+    #[rust] redo_stack: Vec<UndoItem>,
 }
 
-#[derive(Copy, Clone, PartialEq, FrameComponentAction)]
+#[derive(Clone, PartialEq, FrameComponentAction)]
 pub enum TextInputAction {
+    Changed(String),
+    Return(String),
+    Escape,
     None
 }
 
+// consecutive edits of the same group (except Other) are undone in one go
+#[derive(Clone, Copy, PartialEq)]
+enum UndoGroup {
+    TextInput,
+    Backspace,
+    Delete,
+    Other
+}
+
+struct UndoItem {
+    value: String,
+    cursor_head: usize,
+    cursor_tail: usize,
+}
+
 impl TextInput {
     
     pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) -> TextInputAction {
         self.state_handle_event(cx, event);
+        match event.hits(cx, self.select_quad.area()) {
+            HitEvent::KeyFocusLost(_) => {
+                self.animate_state(cx, ids!(focus.off));
+                cx.hide_text_ime();
+                self.select_quad.area().redraw(cx);
+            }
+            HitEvent::KeyFocus(_) => {
+                self.undo_group = None;
+                self.animate_state(cx, ids!(focus.on));
+                self.show_text_ime(cx);
+                self.select_quad.area().redraw(cx);
+            }
+            HitEvent::FingerHover(fe) => {
+                cx.set_hover_mouse_cursor(MouseCursor::Text);
+                match fe.hover_state {
+                    HoverState::In => {
+                        self.animate_state(cx, ids!(hover.on));
+                    },
+                    HoverState::Out => {
+                        self.animate_state(cx, ids!(hover.off));
+                    },
+                    _ => ()
+                }
+            }
+            HitEvent::FingerDown(fe) => {
+                cx.set_key_focus(self.select_quad.area());
+                cx.set_down_mouse_cursor(MouseCursor::Text);
+                let index = self.position_to_index(cx, fe.rel);
+                let chars: Vec<char> = self.value.chars().collect();
+                match fe.tap_count {
+                    2 => {
+                        let (start, end) = word_range(&chars, index);
+                        self.set_cursor(start, end);
+                    }
+                    3 => {
+                        let line = line_range(&chars, index);
+                        self.set_cursor(line.start, line.end);
+                    }
+                    _ => {
+                        let tail = if fe.modifiers.shift {self.cursor_tail} else {index};
+                        self.set_cursor(tail, index);
+                    }
+                }
+                self.show_text_ime(cx);
+                self.select_quad.area().redraw(cx);
+            }
+            HitEvent::FingerMove(fe) => {
+                let index = self.position_to_index(cx, fe.rel);
+                if index != self.cursor_head {
+                    self.set_cursor(self.cursor_tail, index);
+                    self.select_quad.area().redraw(cx);
+                }
+            }
+            HitEvent::KeyDown(ke) => {
+                return self.handle_key_down(cx, ke)
+            }
+            HitEvent::TextInput(te) => {
+                let mut input = te.input.replace('\r', "");
+                if !self.multiline {
+                    input = input.replace('\n', " ");
+                }
+                if te.replace_last {
+                    if let Some(last_input) = self.last_input.take() {
+                        self.set_cursor(last_input.start, last_input.end);
+                    }
+                }
+                let start = self.selection_range().start;
+                let group = if te.was_paste {UndoGroup::Other} else {UndoGroup::TextInput};
+                if self.replace_selection(&input, group) {
+                    self.last_input = Some(start..self.cursor_head);
+                    return self.changed(cx)
+                }
+            }
+            HitEvent::TextCopy(tc) => {
+                let selection = self.selection_range();
+                tc.response = if selection.start != selection.end {
+                    Some(self.value.chars().skip(selection.start).take(selection.len()).collect())
+                }
+                else {
+                    None
+                };
+            }
+            _ => ()
+        }
         TextInputAction::None
-        /*
-        self.animator_handle_event(cx, event);
-        let res = self.button_logic.handle_event(cx, event, self.bg_quad.draw_vars.area);
-        
-        match res.state {
-            ButtonState::Pressed => self.animate_to(cx, self.pressed_state),
-            ButtonState::Default => self.animate_to(cx, self.default_state),
-            ButtonState::Hover => self.animate_to(cx, self.hover_state),
+    }
+    
+    fn handle_key_down(&mut self, cx: &mut Cx, ke: KeyEvent) -> TextInputAction {
+        let KeyModifiers {shift, control, alt, logo} = ke.modifiers;
+        // words are skipped with alt on macos and control elsewhere, shortcuts use control or logo
+        let word = alt || control;
+        let command = control || logo;
+        let chars: Vec<char> = self.value.chars().collect();
+        let selection = self.selection_range();
+        let head = self.cursor_head.min(chars.len());
+        match ke.key_code {
+            KeyCode::ArrowLeft => {
+                let index = if logo {
+                    line_range(&chars, head).start
+                }
+                else if word {
+                    prev_word_boundary(&chars, head)
+                }
+                else if !shift && selection.start != selection.end {
+                    selection.start
+                }
+                else {
+                    head.saturating_sub(1)
+                };
+                self.move_cursor(cx, index, shift);
+            }
+            KeyCode::ArrowRight => {
+                let index = if logo {
+                    line_range(&chars, head).end
+                }
+                else if word {
+                    next_word_boundary(&chars, head)
+                }
+                else if !shift && selection.start != selection.end {
+                    selection.end
+                }
+                else {
+                    (head + 1).min(chars.len())
+                };
+                self.move_cursor(cx, index, shift);
+            }
+            KeyCode::ArrowUp => {
+                let index = self.index_on_adjacent_line(cx, head, false);
+                self.move_cursor(cx, index, shift);
+            }
+            KeyCode::ArrowDown => {
+                let index = self.index_on_adjacent_line(cx, head, true);
+                self.move_cursor(cx, index, shift);
+            }
+            KeyCode::Home => {
+                self.move_cursor(cx, line_range(&chars, head).start, shift);
+            }
+            KeyCode::End => {
+                self.move_cursor(cx, line_range(&chars, head).end, shift);
+            }
+            KeyCode::Backspace => {
+                if selection.start == selection.end {
+                    self.cursor_tail = if word {prev_word_boundary(&chars, head)} else {head.saturating_sub(1)};
+                }
+                return self.edit(cx, "", UndoGroup::Backspace)
+            }
+            KeyCode::Delete => {
+                if selection.start == selection.end {
+                    self.cursor_tail = if word {next_word_boundary(&chars, head)} else {(head + 1).min(chars.len())};
+                }
+                return self.edit(cx, "", UndoGroup::Delete)
+            }
+            KeyCode::Return | KeyCode::NumpadEnter => {
+                if self.multiline && !command {
+                    return self.edit(cx, "\n", UndoGroup::Other)
+                }
+                return TextInputAction::Return(self.value.clone())
+            }
+            KeyCode::Escape => {
+                return TextInputAction::Escape
+            }
+            KeyCode::KeyA if command => {
+                self.select_all();
+                self.select_quad.area().redraw(cx);
+            }
+            KeyCode::KeyZ if command => {
+                let changed = if shift {self.redo()} else {self.undo()};
+                if changed {
+                    return self.changed(cx)
+                }
+            }
+            KeyCode::KeyX if command => {
+                // the platform already asked us for the selected text with a TextCopy event
+                return self.edit(cx, "", UndoGroup::Other)
+            }
             _ => ()
-        };
-        res.action*/
+        }
+        TextInputAction::None
     }
     
     pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk) {
-        //self.bg_quad.begin(cx, walk, self.layout);
-        self.label_text.draw_walk(cx, walk, self.align, &self.value);
-        // ok next problem.
-        // how will we get the text geom
-        // 
+        self.select_quad.begin(cx, walk, self.layout);
+        let origin = cx.turtle().origin();
+        
+        // the value can be changed from the outside, so keep the cursor within it
+        let chars: Vec<char> = self.value.chars().collect();
+        self.cursor_head = self.cursor_head.min(chars.len());
+        self.cursor_tail = self.cursor_tail.min(chars.len());
+        
+        let line_height = self.line_height();
+        let lines = line_ranges(&chars);
+        let line_positions: Vec<Vec<f32 >> = lines.iter().map( | line | self.line_positions(cx, &chars, line)).collect();
+        let text_size = vec2(
+            line_positions.iter().map( | positions | positions[positions.len() - 1]).fold(0.0, f32::max) + self.cursor_width,
+            line_height * lines.len() as f32
+        );
+        let rect = cx.walk_turtle(Walk {
+            abs_pos: None,
+            margin: Margin::default(),
+            width: if walk.width.is_fit() {Size::Fixed(text_size.x)} else {Size::Fill},
+            height: if walk.height.is_fit() {Size::Fixed(text_size.y)} else {Size::Fill},
+        });
+        let text_pos = rect.pos + (rect.size - text_size) * vec2(self.align.x, self.align.y);
+        self.text_rel = text_pos - origin;
+        
+        let selection = self.selection_range();
+        if selection.start != selection.end {
+            for (index, (line, positions)) in lines.iter().zip(&line_positions).enumerate() {
+                if selection.end < line.start || selection.start > line.end {
+                    continue
+                }
+                let start = selection.start.max(line.start) - line.start;
+                let end = selection.end.min(line.end) - line.start;
+                // show that the newline at the end of the line is selected too
+                let newline_width = if selection.end > line.end {self.cursor_width * 2.0} else {0.0};
+                self.selection_quad.draw_abs(cx, Rect {
+                    pos: text_pos + vec2(positions[start], line_height * index as f32),
+                    size: vec2(positions[end] - positions[start] + newline_width, line_height)
+                });
+            }
+        }
+        
+        for (index, line) in lines.iter().enumerate() {
+            let text: String = chars[line.clone()].iter().collect();
+            self.label_text.draw_inner(cx, text_pos + vec2(0.0, line_height * index as f32), line.start, &text);
+        }
+        
+        if cx.has_key_focus(self.select_quad.area()) {
+            let cursor_pos = self.index_to_position(cx, self.cursor_head);
+            self.cursor_quad.draw_abs(cx, Rect {
+                pos: text_pos + cursor_pos,
+                size: vec2(self.cursor_width, line_height)
+            });
+        }
+        
+        self.select_quad.end(cx);
+    }
+    
+    pub fn area(&self) -> Area {
+        self.select_quad.area()
+    }
+    
+    pub fn select_all(&mut self) {
+        self.set_cursor(0, self.value.chars().count());
+    }
+    
+    fn edit(&mut self, cx: &mut Cx, text: &str, group: UndoGroup) -> TextInputAction {
+        if self.replace_selection(text, group) {
+            return self.changed(cx)
+        }
+        TextInputAction::None
+    }
+    
+    fn changed(&mut self, cx: &mut Cx) -> TextInputAction {
+        self.show_text_ime(cx);
+        self.select_quad.area().redraw(cx);
+        TextInputAction::Changed(self.value.clone())
+    }
+    
+    fn set_cursor(&mut self, tail: usize, head: usize) {
+        self.cursor_tail = tail;
+        self.cursor_head = head;
+        self.last_input = None;
+        self.undo_group = None;
+    }
+    
+    fn move_cursor(&mut self, cx: &mut Cx, index: usize, select: bool) {
+        let tail = if select {self.cursor_tail} else {index};
+        self.set_cursor(tail, index);
+        self.show_text_ime(cx);
+        self.select_quad.area().redraw(cx);
+    }
+    
+    fn selection_range(&self) -> Range<usize> {
+        let len = self.value.chars().count();
+        let (head, tail) = (self.cursor_head.min(len), self.cursor_tail.min(len));
+        head.min(tail)..head.max(tail)
+    }
+    
+    // replaces the selection with the given text and puts the cursor behind it. Returns false if
+    // nothing changed.
+    fn replace_selection(&mut self, text: &str, group: UndoGroup) -> bool {
+        let selection = self.selection_range();
+        if selection.start == selection.end && text.is_empty() {
+            return false
+        }
+        if group == UndoGroup::Other || self.undo_group != Some(group) {
+            self.undo_stack.push(self.undo_item());
+            self.redo_stack.clear();
+        }
+        let start = byte_index(&self.value, selection.start);
+        let end = byte_index(&self.value, selection.end);
+        self.value.replace_range(start..end, text);
+        let index = selection.start + text.chars().count();
+        self.set_cursor(index, index);
+        self.undo_group = Some(group);
+        true
+    }
+    
+    fn undo_item(&self) -> UndoItem {
+        UndoItem {
+            value: self.value.clone(),
+            cursor_head: self.cursor_head,
+            cursor_tail: self.cursor_tail,
+        }
+    }
+    
+    fn apply_undo_item(&mut self, item: UndoItem) {
+        self.value = item.value;
+        self.set_cursor(item.cursor_tail, item.cursor_head);
+    }
+    
+    fn undo(&mut self) -> bool {
+        if let Some(item) = self.undo_stack.pop() {
+            self.redo_stack.push(self.undo_item());
+            self.apply_undo_item(item);
+            return true
+        }
+        false
+    }
+    
+    fn redo(&mut self) -> bool {
+        if let Some(item) = self.redo_stack.pop() {
+            self.undo_stack.push(self.undo_item());
+            self.apply_undo_item(item);
+            return true
+        }
+        false
+    }
+    
+    fn show_text_ime(&self, cx: &mut Cx) {
+        let rect = self.select_quad.area().get_rect(cx);
+        let pos = rect.pos + self.text_rel + self.index_to_position(cx, self.cursor_head);
+        cx.show_text_ime(pos.x, pos.y);
+    }
+    
+    fn line_height(&self) -> f32 {
+        let text_style = &self.label_text.text_style;
+        text_style.font_size * text_style.height_factor * self.label_text.font_scale
+    }
+    
+    fn line_positions(&self, cx: &Cx, chars: &[char], line: &Range<usize>) -> Vec<f32> {
+        let mut positions = Vec::new();
+        self.label_text.compute_char_positions(cx, &chars[line.clone()], &mut positions);
+        positions
+    }
+    
+    // the position of the cursor in front of the char at index, relative to the start of the text
+    fn index_to_position(&self, cx: &Cx, index: usize) -> Vec2 {
+        let chars: Vec<char> = self.value.chars().collect();
+        let index = index.min(chars.len());
+        let lines = line_ranges(&chars);
+        let line_index = lines.iter().position( | line | index <= line.end).unwrap();
+        let line = &lines[line_index];
+        let positions = self.line_positions(cx, &chars, line);
+        vec2(positions[index - line.start], self.line_height() * line_index as f32)
+    }
+    
+    // the index of the cursor closest to the given position relative to the select_quad
+    fn position_to_index(&self, cx: &Cx, rel: Vec2) -> usize {
+        let pos = rel - self.text_rel;
+        let chars: Vec<char> = self.value.chars().collect();
+        let lines = line_ranges(&chars);
+        let line_index = ((pos.y / self.line_height()).max(0.0) as usize).min(lines.len() - 1);
+        let line = &lines[line_index];
+        let positions = self.line_positions(cx, &chars, line);
+        let offset = positions.iter().enumerate().min_by( | (_, a), (_, b) | {
+            (*a - pos.x).abs().total_cmp(&(*b - pos.x).abs())
+        }).map_or(0, | (offset, _) | offset);
+        line.start + offset
+    }
+    
+    // moves the cursor up or down a line, keeping its horizontal position. Moving off the first
+    // or last line goes to the start or end of the text.
+    fn index_on_adjacent_line(&self, cx: &Cx, index: usize, down: bool) -> usize {
+        let chars: Vec<char> = self.value.chars().collect();
+        let lines = line_ranges(&chars);
+        let line_index = lines.iter().position( | line | index <= line.end).unwrap_or(0);
+        if !down && line_index == 0 {
+            return 0
+        }
+        if down && line_index + 1 == lines.len() {
+            return chars.len()
+        }
+        let pos = self.index_to_position(cx, index);
+        let line_height = self.line_height();
+        let dy = if down {line_height} else {-line_height};
+        self.position_to_index(cx, self.text_rel + vec2(pos.x, pos.y + dy + 0.5 * line_height))
+    }
+}
+
+fn byte_index(text: &str, char_index: usize) -> usize {
+    text.char_indices().nth(char_index).map_or(text.len(), | (index, _) | index)
+}
+
+// the char ranges of the lines in the text, without their newlines
+fn line_ranges(chars: &[char]) -> Vec<Range<usize >> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    for (index, c) in chars.iter().enumerate() {
+        if *c == '\n' {
+            lines.push(start..index);
+            start = index + 1;
+        }
+    }
+    lines.push(start..chars.len());
+    lines
+}
+
+fn line_range(chars: &[char], index: usize) -> Range<usize> {
+    line_ranges(chars).into_iter().find( | line | index <= line.end).unwrap()
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn prev_word_boundary(chars: &[char], mut index: usize) -> usize {
+    while index > 0 && !is_word_char(chars[index - 1]) {
+        index -= 1;
+    }
+    while index > 0 && is_word_char(chars[index - 1]) {
+        index -= 1;
+    }
+    index
+}
+
+fn next_word_boundary(chars: &[char], mut index: usize) -> usize {
+    while index < chars.len() && !is_word_char(chars[index]) {
+        index += 1;
+    }
+    while index < chars.len() && is_word_char(chars[index]) {
+        index += 1;
+    }
+    index
+}
+
+// the word around index, or just the char after it if that isn't part of a word
+fn word_range(chars: &[char], index: usize) -> (usize, usize) {
+    let (mut start, mut end) = (index, index);
+    while start > 0 && is_word_char(chars[start - 1]) {
+        start -= 1;
+    }
+    while end < chars.len() && is_word_char(chars[end]) {
+        end += 1;
+    }
+    if start == end {
+        end = (index + 1).min(chars.len());
+    }
+    (start, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::test_cx::*,
+    };
+    
+    const SHIFT: KeyModifiers = KeyModifiers {shift: true, control: false, alt: false, logo: false};
+    const CONTROL: KeyModifiers = KeyModifiers {shift: false, control: true, alt: false, logo: false};
+    const CONTROL_SHIFT: KeyModifiers = KeyModifiers {shift: true, control: true, alt: false, logo: false};
+    
+    struct Test {
+        test_cx: TestCx,
+        text_input: TextInput,
+    }
+    
+    impl Test {
+        // a drawn text input with the key focus
+        fn new(multiline: bool) -> Self {
+            let mut test_cx = TestCx::new();
+            let mut text_input: TextInput = test_cx.new_component("makepad_component::text_input", id!(TextInput));
+            text_input.multiline = multiline;
+            test_cx.draw( | cx | text_input.draw_walk(cx, Walk::default()));
+            test_cx.set_key_focus(text_input.area(), | cx, event | text_input.handle_event(cx, event));
+            Self {test_cx, text_input}
+        }
+        
+        fn event(&mut self, event: Event) -> TextInputAction {
+            let text_input = &mut self.text_input;
+            self.test_cx.event(event, | cx, event | text_input.handle_event(cx, event))
+        }
         
-        //self.bg_quad.end(cx);
+        fn key(&mut self, key_code: KeyCode, modifiers: KeyModifiers) -> TextInputAction {
+            self.event(key_down(key_code, modifiers))
+        }
+        
+        fn type_text(&mut self, text: &str) {
+            for c in text.chars() {
+                self.event(text_input(&c.to_string()));
+            }
+        }
+    }
+    
+    fn changed(value: &str) -> TextInputAction {
+        TextInputAction::Changed(value.to_string())
+    }
+    
+    #[test]
+    fn edits_by_char_and_selection() {
+        let mut test = Test::new(false);
+        assert!(test.event(text_input("héllo")) == changed("héllo"));
+        test.key(KeyCode::ArrowLeft, KeyModifiers::default());
+        test.key(KeyCode::ArrowLeft, SHIFT);
+        test.key(KeyCode::ArrowLeft, SHIFT);
+        test.key(KeyCode::ArrowLeft, SHIFT);
+        assert!(test.event(text_input("ey")) == changed("heyo"));
+        assert!(test.key(KeyCode::Backspace, KeyModifiers::default()) == changed("heo"));
+        assert!(test.key(KeyCode::Delete, KeyModifiers::default()) == changed("he"));
+        // nothing after the cursor to delete
+        assert!(test.key(KeyCode::Delete, KeyModifiers::default()) == TextInputAction::None);
+    }
+    
+    #[test]
+    fn single_line_input_turns_newlines_into_spaces() {
+        let mut test = Test::new(false);
+        assert!(test.event(text_input("a\r\nb")) == changed("a b"));
+        assert!(test.key(KeyCode::Return, KeyModifiers::default()) == TextInputAction::Return("a b".to_string()));
+    }
+    
+    #[test]
+    fn word_keys_skip_punctuation() {
+        let mut test = Test::new(false);
+        test.event(text_input("foo_bar, baz"));
+        test.key(KeyCode::ArrowLeft, CONTROL);
+        assert!(test.event(text_input("x")) == changed("foo_bar, xbaz"));
+        test.key(KeyCode::ArrowLeft, CONTROL);
+        test.key(KeyCode::ArrowLeft, CONTROL);
+        assert!(test.key(KeyCode::Delete, CONTROL) == changed(", xbaz"));
+        test.key(KeyCode::ArrowRight, CONTROL);
+        assert!(test.key(KeyCode::Backspace, CONTROL) == changed(", "));
+    }
+    
+    #[test]
+    fn home_and_end_stay_on_the_line() {
+        let mut test = Test::new(true);
+        test.event(text_input("ab"));
+        test.key(KeyCode::Return, KeyModifiers::default());
+        test.event(text_input("cd"));
+        assert!(test.key(KeyCode::Return, CONTROL) == TextInputAction::Return("ab\ncd".to_string()));
+        test.key(KeyCode::Home, KeyModifiers::default());
+        assert!(test.event(text_input("x")) == changed("ab\nxcd"));
+        test.key(KeyCode::ArrowLeft, KeyModifiers::default());
+        test.key(KeyCode::ArrowLeft, KeyModifiers::default());
+        test.key(KeyCode::Home, SHIFT);
+        assert!(test.event(text_input("y")) == changed("y\nxcd"));
+        test.key(KeyCode::End, KeyModifiers::default());
+        assert!(test.event(text_input("z")) == changed("yz\nxcd"));
+    }
+    
+    #[test]
+    fn undo_groups_consecutive_edits() {
+        let mut test = Test::new(false);
+        test.type_text("abc");
+        test.key(KeyCode::ArrowLeft, KeyModifiers::default());
+        test.key(KeyCode::ArrowLeft, SHIFT);
+        test.key(KeyCode::Backspace, KeyModifiers::default());
+        assert!(test.text_input.value == "ac");
+        
+        assert!(test.key(KeyCode::KeyZ, CONTROL) == changed("abc"));
+        assert!(test.key(KeyCode::KeyZ, CONTROL) == changed(""));
+        assert!(test.key(KeyCode::KeyZ, CONTROL) == TextInputAction::None);
+        assert!(test.key(KeyCode::KeyZ, CONTROL_SHIFT) == changed("abc"));
+        assert!(test.key(KeyCode::KeyZ, CONTROL_SHIFT) == changed("ac"));
+        assert!(test.key(KeyCode::KeyZ, CONTROL_SHIFT) == TextInputAction::None);
+    }
+    
+    #[test]
+    fn editing_clears_redo() {
+        let mut test = Test::new(false);
+        test.type_text("a");
+        test.key(KeyCode::KeyZ, CONTROL);
+        test.type_text("b");
+        assert!(test.key(KeyCode::KeyZ, CONTROL_SHIFT) == TextInputAction::None);
+        assert!(test.text_input.value == "b");
+    }
+    
+    #[test]
+    fn ignores_keys_without_the_key_focus() {
+        let mut test = Test::new(false);
+        let input = &mut test.text_input;
+        test.test_cx.set_key_focus(Area::Empty, | cx, event | input.handle_event(cx, event));
+        assert!(test.event(text_input("a")) == TextInputAction::None);
+        assert!(test.text_input.value.is_empty());
     }
 }
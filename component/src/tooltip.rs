@@ -0,0 +1,103 @@
+use crate::{
+    makepad_platform::*,
+    popup::Popup,
+};
+
+live_register!{
+    use makepad_component::theme::*;
+
+    Tooltip: {{Tooltip}} {
+        popup: {
+            layout: {
+                padding: {left: 8.0, top: 5.0, right: 8.0, bottom: 5.0}
+            }
+        }
+        label_text: {
+            text_style: FONT_LABEL {}
+            color: (COLOR_TEXT_HOVER)
+        }
+        delay: 0.6
+        offset: vec2(0.0, 18.0)
+    }
+}
+
+/// A text shown below the finger when it rests on an area for a while.
+///
+/// The tooltip watches the area that is passed to `handle_event`, without taking any finger events
+/// away from the component that owns it. The width of the tooltip follows its text.
+#[derive(Live, LiveHook)]
+pub struct Tooltip {
+    popup: Popup,
+    label_text: DrawText,
+    // seconds the finger has to rest on the area before the tooltip is shown
+    delay: f64,
+    // where the tooltip is shown relative to the finger
+    offset: Vec2,
+    pub text: String,
+
+    #[rust] timer: Timer,
+    #[rust] finger_pos: Option<Vec2>,
+}
+
+impl Tooltip {
+    pub fn set_text(&mut self, cx: &mut Cx, text: &str) {
+        if self.text != text {
+            self.text.clear();
+            self.text.push_str(text);
+            self.popup.redraw(cx);
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.popup.is_open()
+    }
+
+    pub fn close(&mut self, cx: &mut Cx) {
+        cx.stop_timer(self.timer);
+        self.timer = Timer::empty();
+        self.finger_pos = None;
+        self.popup.close(cx);
+    }
+
+    pub fn handle_event(&mut self, cx: &mut Cx, event: &mut Event, area: Area) {
+        match event {
+            Event::FingerHover(fe) => {
+                let is_over = area.get_rect(cx).contains(fe.abs)
+                    && !cx.is_covered_by_overlay(area, fe.window_id, fe.abs);
+                if !is_over {
+                    if self.finger_pos.is_some() {
+                        self.close(cx);
+                    }
+                }
+                else if !self.popup.is_open() {
+                    // wait for the finger to rest before showing the tooltip
+                    cx.stop_timer(self.timer);
+                    self.timer = cx.start_timer(self.delay, false);
+                    self.finger_pos = Some(fe.abs);
+                }
+            }
+            Event::FingerDown(_) | Event::FingerScroll(_) | Event::KeyDown(_) => {
+                if self.finger_pos.is_some() {
+                    self.close(cx);
+                }
+            }
+            Event::Timer(te) => if self.timer.is_timer(te) {
+                self.timer = Timer::empty();
+                if let Some(pos) = self.finger_pos {
+                    if !self.text.is_empty() {
+                        self.popup.open(cx, Rect {pos: pos + self.offset, size: Vec2::default()});
+                    }
+                }
+            }
+            _ => ()
+        }
+    }
+
+    pub fn draw(&mut self, cx: &mut Cx2d) {
+        if self.popup.begin(cx).is_err() {
+            return
+        }
+        self.label_text.draw_walk(cx, Walk::fit(), Align::default(), &self.text);
+        self.popup.end(cx);
+    }
+}
@@ -18,8 +18,8 @@ pub fn live_register_impl(input: TokenStream) -> TokenStream {
         tb.add("    let live_body = LiveBody {");
         tb.add("        module_path :").ident_with_span("module_path", span).add("!().to_string(),");
         tb.add("        file:").ident_with_span("file", span).add("!().to_string().replace(").string("\\").add(",").string("/").add("),");
-        tb.add("        line:").unsuf_usize(span.start().line - 1).add(",");
-        tb.add("        column:").unsuf_usize(span.start().column - 1).add(",");
+        tb.add("        line:").unsuf_usize(span.start().line() - 1).add(",");
+        tb.add("        column:").unsuf_usize(span.start().column() - 1).add(",");
         tb.add("        live_type_infos:{");
         tb.add("            let mut v = Vec::new();");
         for live_type in &live_types {
@@ -88,15 +88,15 @@ fn token_parser_to_whitespace_matching_string(parser: &mut TokenParser, span: Sp
     fn tp_to_str(parser: &mut TokenParser, span: Span, out: &mut String, live_types: &mut Vec<TokenStream>, last_end: &mut Option<Lc>) {
         fn lc_from_start(span: Span) -> Lc {
             Lc {
-                line: span.start().line,
-                column: span.start().column
+                line: span.start().line(),
+                column: span.start().column()
             }
         }
         
         fn lc_from_end(span: Span) -> Lc {
             Lc {
-                line: span.end().line,
-                column: span.end().column
+                line: span.end().line(),
+                column: span.end().column()
             }
         }
         
@@ -13,6 +13,7 @@ pub mod live_expander;
 pub mod live_ptr;
 pub mod live_eval;
 pub mod live_component;
+pub mod live_printer;
 
 pub use makepad_math;
 pub use makepad_derive_live;
@@ -35,6 +36,7 @@ pub use {
             live_eval,
             LiveEval
         },
+        live_printer::LivePrinter,
         live_registry::{
             LiveEditEvent,
             LiveRegistry,
@@ -126,6 +126,9 @@ impl<'a> LiveParser<'a> {
     }
     
     fn expect_float(&mut self) -> Result<f64, LiveError> {
+        if self.accept_token(LiveToken::Punct(id!(-))) {
+            return Ok(-self.expect_float() ?)
+        }
         match self.peek_token() {
             LiveToken::Float(v) => {
                 self.skip_token();
@@ -507,6 +510,20 @@ impl<'a> LiveParser<'a> {
                     value: LiveValue::Color(val)
                 });
             },
+            LiveToken::Punct(id!(-)) => { // negative number
+                self.skip_token();
+                let value = match self.peek_token() {
+                    LiveToken::Int(val) => LiveValue::Int(-val),
+                    LiveToken::Float(val) => LiveValue::Float(-val),
+                    token => return Err(self.error(format!("expected number after -, unexpected token `{}`", token), live_error_origin!()))
+                };
+                self.skip_token();
+                ld.nodes.push(LiveNode {
+                    origin,
+                    id: prop_id,
+                    value
+                });
+            },
             LiveToken::String {index, len} => {
                 self.skip_token();
                 ld.nodes.push(LiveNode {
@@ -0,0 +1,732 @@
+use {
+    crate::{
+        makepad_live_id::*,
+        makepad_live_tokenizer::{LiveId, Delim},
+        live_registry::{LiveRegistry, LiveFile},
+        live_node::{LiveNode, LiveValue, LivePropType, LiveBinOp, LiveUnOp},
+        live_node_vec::LiveNodeSlice,
+        live_token::{LiveToken, LiveTokenId},
+        live_ptr::LiveFileId,
+        span::TextPos,
+    }
+};
+
+/// Turns live nodes back into `live!{}` source, the way it would be written by hand.
+///
+/// Nodes that were parsed from a file still point at their tokens, and for those the comments,
+/// the empty lines and whether an object sits on a single line or on many are taken from the
+/// original source. Nodes without tokens, like the ones added by a design mode edit, get a line
+/// per property for objects and a single line for lists and enums.
+pub struct LivePrinter<'a> {
+    registry: &'a LiveRegistry,
+    out: String,
+    indent: usize,
+    // nonzero while printing the children of a node that sits on a single line
+    inline_depth: usize,
+}
+
+// the comments and empty lines in front of a token in the original source
+#[derive(Default)]
+struct Trivia {
+    // a comment on the same line as the token before
+    trailing: Option<String>,
+    empty_line: bool,
+    comments: Vec<String>,
+}
+
+const PUNCTS: [&str; 44] = [
+    "...", "..=", "<<=", ">>=",
+    "!=", "%=", "&&", "&=", "*=", "+=", "-=", "->", "..", "/=", "::", "<<", "<=", "==", "=>", ">=", ">>", "^=", "|=", "||",
+    "!", "#", "$", "%", "&", "*", "+", ",", "-", ".", "/", ":", ";", "<", "=", ">", "?", "@", "^", "|",
+];
+
+impl<'a> LivePrinter<'a> {
+    pub fn new(registry: &'a LiveRegistry) -> Self {
+        Self {
+            registry,
+            out: String::new(),
+            indent: 0,
+            inline_depth: 0,
+        }
+    }
+
+    pub fn into_string(self) -> String {
+        self.out
+    }
+
+    /// Prints the children of the open node at `index` a line each, without the braces around
+    /// them, like the body of a `live!{}` block. Returns the index after its close node.
+    pub fn print_body(&mut self, nodes: &[LiveNode], index: usize) -> usize {
+        let close_index = nodes.skip_node(index) - 1;
+        self.print_lines(nodes, index, close_index);
+        close_index + 1
+    }
+
+    /// Prints the node at `index` as a property, with its name and its value. Returns the index
+    /// of the node after it.
+    pub fn print_node(&mut self, nodes: &[LiveNode], index: usize) -> usize {
+        let node = &nodes[index];
+        match node.value {
+            LiveValue::None | LiveValue::Close => return index + 1,
+            LiveValue::DSL {token_start, token_count, ..} => {
+                self.print_dsl(node, token_start as usize, token_count as usize);
+                return index + 1
+            }
+            LiveValue::Use(module_id) => {
+                // the crate is filled in on registration, a `crate::` path is kept as it was written
+                let crate_id = match node.origin.token_id().map( | token_id | self.token(token_id)) {
+                    Some(Some(LiveToken::Ident(id!(crate)))) => id!(crate),
+                    _ => module_id.0
+                };
+                self.out.push_str(&format!("use {}::{}::{}", crate_id, module_id.1, id_or_star(node.id)));
+                return index + 1
+            }
+            LiveValue::UseComponent(component_id) => {
+                self.out.push_str(&format!("use {}::{}", component_id, id_or_star(node.id)));
+                return index + 1
+            }
+            _ => ()
+        }
+        if let Some(prefix) = self.registry.get_node_prefix(node.origin) {
+            self.out.push_str(&format!("{} ", prefix));
+        }
+        if !node.id.is_empty() && !is_anonymous_instance(node) {
+            self.out.push_str(&node.id.to_string());
+            self.print_edit_info(node);
+            self.out.push_str(match node.origin.prop_type() {
+                LivePropType::Field | LivePropType::Nameless => ": ",
+                LivePropType::Instance => " = ",
+                LivePropType::Template => " =? ",
+            });
+        }
+        self.print_value(nodes, index)
+    }
+
+    fn print_value(&mut self, nodes: &[LiveNode], index: usize) -> usize {
+        let node = &nodes[index];
+        match &node.value {
+            LiveValue::Str(v) => self.out.push_str(&string_literal(v)),
+            LiveValue::FittedString(v) => self.out.push_str(&string_literal(v.as_str())),
+            LiveValue::InlineString(v) => self.out.push_str(&string_literal(v.as_str())),
+            // a string of a document can only be found through its token
+            LiveValue::DocumentString {..} => if let Some(string) = self.registry.live_node_as_string(node) {
+                self.out.push_str(&string_literal(&string));
+            }
+            LiveValue::Dependency {..} => if let Some(string) = self.registry.live_node_as_string(node) {
+                self.out.push_str(&format!("d\"{}\"", string));
+            }
+            LiveValue::Bool(v) => self.out.push_str(&v.to_string()),
+            LiveValue::Int(_) | LiveValue::Float(_) | LiveValue::Color(_) => {
+                let source = self.value_token(node).and_then( | token_id | self.literal_source(&node.value, token_id));
+                self.print_literal(&node.value, source);
+            }
+            LiveValue::Vec2(v) => {
+                self.out.push_str(&format!("vec2({}, {})", f32_to_string(v.x), f32_to_string(v.y)));
+            }
+            LiveValue::Vec3(v) => {
+                self.out.push_str(&format!("vec3({}, {}, {})", f32_to_string(v.x), f32_to_string(v.y), f32_to_string(v.z)));
+            }
+            LiveValue::Vec4(v) => {
+                self.out.push_str(&format!(
+                    "vec4({}, {}, {}, {})",
+                    f32_to_string(v.x),
+                    f32_to_string(v.y),
+                    f32_to_string(v.z),
+                    f32_to_string(v.w)
+                ));
+            }
+            LiveValue::Id(id) => self.out.push_str(&id.to_string()),
+            LiveValue::BareEnum {base, variant} => self.out.push_str(&format!("{}::{}", base, variant)),
+            LiveValue::TupleEnum {base, variant} => {
+                self.out.push_str(&format!("{}::{}", base, variant));
+                return self.print_children(nodes, index, "(", ")")
+            }
+            LiveValue::NamedEnum {base, variant} => {
+                self.out.push_str(&format!("{}::{} ", base, variant));
+                return self.print_children(nodes, index, "{", "}")
+            }
+            LiveValue::Array => return self.print_children(nodes, index, "[", "]"),
+            LiveValue::Object => return self.print_children(nodes, index, "{", "}"),
+            LiveValue::Clone(base) => {
+                self.out.push_str(&format!("{} ", base));
+                return self.print_children(nodes, index, "{", "}")
+            }
+            LiveValue::Class {live_type, ..} => {
+                // a class without type info still parses, it just can't be expanded
+                if let Some(info) = self.registry.live_type_infos.get(live_type) {
+                    self.out.push_str(&format!("{{{{{}}}}} ", info.type_name));
+                }
+                else {
+                    self.out.push_str("{{}} ");
+                }
+                return self.print_children(nodes, index, "{", "}")
+            }
+            LiveValue::Expr {..} => {
+                self.out.push('(');
+                self.print_expr(nodes, index + 1, 0);
+                self.out.push(')');
+                return nodes.skip_node(index)
+            }
+            LiveValue::ExprBinOp(_) | LiveValue::ExprUnOp(_) | LiveValue::ExprMember(_) | LiveValue::ExprCall {..} => {
+                return self.print_expr(nodes, index, 0)
+            }
+            LiveValue::None | LiveValue::Close | LiveValue::DSL {..} | LiveValue::Use(_) | LiveValue::UseComponent(_) => ()
+        }
+        index + 1
+    }
+
+    fn print_literal(&mut self, value: &LiveValue, source: Option<String>) {
+        if let Some(source) = source {
+            self.out.push_str(&source);
+            return
+        }
+        match value {
+            LiveValue::Int(v) => self.out.push_str(&v.to_string()),
+            LiveValue::Float(v) => self.out.push_str(&f64_to_string(*v)),
+            LiveValue::Color(v) => self.out.push_str(&color_to_string(*v)),
+            _ => ()
+        }
+    }
+
+    // expressions are stored operator first, so they are printed from the top down with parens
+    // wherever the binding of an operand is weaker than the parser would give it
+    fn print_expr(&mut self, nodes: &[LiveNode], index: usize, min_prec: usize) -> usize {
+        let node = &nodes[index];
+        let prec = expr_prec(&node.value);
+        if prec < min_prec {
+            self.out.push('(');
+        }
+        let next = match &node.value {
+            LiveValue::ExprBinOp(op) => {
+                // the operators are left associative
+                let next = self.print_expr(nodes, index + 1, prec);
+                self.out.push_str(&format!(" {} ", bin_op_str(*op)));
+                self.print_expr(nodes, next, prec + 1)
+            }
+            LiveValue::ExprUnOp(op) => {
+                self.out.push_str(match op {
+                    LiveUnOp::Not => "!",
+                    LiveUnOp::Neg => "-",
+                });
+                self.print_expr(nodes, index + 1, prec)
+            }
+            LiveValue::ExprMember(ident) => {
+                let next = self.print_expr(nodes, index + 1, prec);
+                self.out.push_str(&format!(".{}", ident));
+                next
+            }
+            // the parser turns `a ? b : c` into a call
+            LiveValue::ExprCall {ident: id!(cond), args: 3} => {
+                let next = self.print_expr(nodes, index + 1, 1);
+                self.out.push_str(" ? ");
+                let next = self.print_expr(nodes, next, 0);
+                self.out.push_str(" : ");
+                self.print_expr(nodes, next, 0)
+            }
+            LiveValue::ExprCall {ident, args} => {
+                self.out.push_str(&format!("{}(", ident));
+                let mut next = index + 1;
+                for i in 0..*args {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+                    next = self.print_expr(nodes, next, 0);
+                }
+                self.out.push(')');
+                next
+            }
+            LiveValue::Int(_) | LiveValue::Float(_) | LiveValue::Color(_) => {
+                // the token of a literal in an expression is the one after it
+                let source = node.origin.token_id()
+                    .and_then( | token_id | token_id.token_index().checked_sub(1).map( | i | LiveTokenId::new(token_id.file_id(), i)))
+                    .and_then( | token_id | self.literal_source(&node.value, token_id));
+                self.print_literal(&node.value, source);
+                index + 1
+            }
+            _ => self.print_value(nodes, index)
+        };
+        if prec < min_prec {
+            self.out.push(')');
+        }
+        next
+    }
+
+    fn print_children(&mut self, nodes: &[LiveNode], index: usize, open: &str, close: &str) -> usize {
+        let close_index = nodes.skip_node(index) - 1;
+        self.out.push_str(open);
+        if index + 1 == close_index {
+            // nothing to lay out
+        }
+        else if self.inline_depth > 0 || self.is_inline(nodes, index, close_index) {
+            self.inline_depth += 1;
+            let mut child = index + 1;
+            let mut first = true;
+            while child < close_index {
+                if let LiveValue::None = nodes[child].value {
+                    child += 1;
+                    continue;
+                }
+                if !first {
+                    self.out.push_str(", ");
+                }
+                first = false;
+                child = self.print_node(nodes, child);
+            }
+            self.inline_depth -= 1;
+        }
+        else {
+            self.indent += 1;
+            self.print_lines(nodes, index, close_index);
+            self.indent -= 1;
+            self.newline();
+        }
+        self.out.push_str(close);
+        close_index + 1
+    }
+
+    fn print_lines(&mut self, nodes: &[LiveNode], index: usize, close_index: usize) {
+        // list items are separated with commas, properties aren't
+        let is_list = matches!(nodes[index].value, LiveValue::Array | LiveValue::TupleEnum {..});
+        let mut child = index + 1;
+        let mut first = true;
+        while child < close_index {
+            if let LiveValue::None = nodes[child].value {
+                child += 1;
+                continue;
+            }
+            self.print_trivia(self.first_token(&nodes[child]), !first);
+            self.newline();
+            child = self.print_node(nodes, child);
+            if is_list {
+                self.out.push(',');
+            }
+            first = false;
+        }
+        self.print_trivia(self.close_token(nodes, index, close_index), false);
+    }
+
+    fn print_edit_info(&mut self, node: &LiveNode) {
+        let (edit_info, token_id) = match (node.origin.edit_info(), node.origin.token_id()) {
+            (Some(edit_info), Some(token_id)) => (edit_info, token_id),
+            _ => return
+        };
+        let registry = self.registry;
+        let nodes = &registry.live_files[token_id.file_id().to_index()].original.edit_info;
+        if edit_info.edit_info_index() < nodes.len() {
+            self.out.push('.');
+            self.inline_depth += 1;
+            self.print_children(nodes, edit_info.edit_info_index(), "{", "}");
+            self.inline_depth -= 1;
+        }
+    }
+
+    fn print_dsl(&mut self, node: &LiveNode, token_start: usize, token_count: usize) {
+        let file_id = match node.origin.token_id() {
+            Some(token_id) => token_id.file_id(),
+            None => return
+        };
+        let file = &self.registry.live_files[file_id.to_index()];
+        let tokens = match file.original.tokens.get(token_start..token_start + token_count) {
+            Some(tokens) if !tokens.is_empty() => tokens,
+            _ => return
+        };
+        let (first, last) = (tokens[0].span, tokens[tokens.len() - 1].span);
+        match source_text(file, first.start, Some(first.end)).zip(source_text(file, first.start, Some(last.end))) {
+            // the source is only used while its text still lines up with the tokens
+            Some((fn_token, source)) if fn_token == "fn" && source.ends_with('}') => {
+                for (i, line) in source.lines().enumerate() {
+                    if i == 0 {
+                        self.out.push_str(line);
+                        continue;
+                    }
+                    // the body keeps its indentation relative to the `fn`
+                    let strip = line.chars()
+                        .take(first.start.column as usize)
+                        .take_while( | c | c.is_whitespace())
+                        .count();
+                    let line: String = line.chars().skip(strip).collect();
+                    self.out.push('\n');
+                    if !line.trim().is_empty() {
+                        self.out.push_str(&"    ".repeat(self.indent));
+                        self.out.push_str(&line);
+                    }
+                }
+            }
+            _ => {
+                let mut first = true;
+                for token in tokens {
+                    if !first {
+                        self.out.push(' ');
+                    }
+                    first = false;
+                    self.out.push_str(&token_to_string(file, &token.token));
+                }
+            }
+        }
+    }
+
+    fn print_trivia(&mut self, token_id: Option<LiveTokenId>, keep_empty_line: bool) {
+        let trivia = match token_id.and_then( | token_id | self.trivia_before(token_id)) {
+            Some(trivia) => trivia,
+            None => return
+        };
+        let at_line_start = self.out.is_empty() || self.out.ends_with('\n');
+        let mut comments = trivia.comments;
+        if let Some(trailing) = trivia.trailing {
+            if at_line_start {
+                comments.insert(0, trailing);
+            }
+            else {
+                self.out.push(' ');
+                self.out.push_str(&trailing);
+            }
+        }
+        if trivia.empty_line && (keep_empty_line || !comments.is_empty()) && !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        for comment in comments {
+            self.newline();
+            self.out.push_str(&comment);
+        }
+    }
+
+    fn newline(&mut self) {
+        if !self.out.is_empty() {
+            self.out.push('\n');
+        }
+        self.out.push_str(&"    ".repeat(self.indent));
+    }
+
+    fn is_inline(&self, nodes: &[LiveNode], index: usize, close_index: usize) -> bool {
+        let open = self.first_token(&nodes[index]).and_then( | token_id | self.token_line(token_id));
+        let close = self.close_token(nodes, index, close_index).and_then( | token_id | self.token_line(token_id));
+        if let (Some(open), Some(close)) = (open, close) {
+            return open == close
+        }
+        !matches!(nodes[index].value, LiveValue::Object | LiveValue::Clone(_) | LiveValue::Class {..})
+    }
+
+    // the first token of what was written for a node, including its prefix
+    fn first_token(&self, node: &LiveNode) -> Option<LiveTokenId> {
+        let token_id = node.origin.token_id()?;
+        let token_index = match node.value {
+            LiveValue::DSL {token_start, ..} => token_start as usize,
+            LiveValue::Use(_) | LiveValue::UseComponent(_) => token_id.token_index().checked_sub(1)?,
+            _ if node.origin.node_has_prefix() && node.origin.first_def() == Some(token_id) => {
+                token_id.token_index().checked_sub(1)?
+            }
+            _ => token_id.token_index()
+        };
+        Some(LiveTokenId::new(token_id.file_id(), token_index))
+    }
+
+    // the closing delimiter of an open node, or the end of the file for the root
+    fn close_token(&self, nodes: &[LiveNode], index: usize, close_index: usize) -> Option<LiveTokenId> {
+        let token_id = nodes[close_index].origin.token_id()?;
+        let token_id = match nodes[index].value {
+            LiveValue::Object | LiveValue::Clone(_) | LiveValue::Class {..} => token_id,
+            _ => LiveTokenId::new(token_id.file_id(), token_id.token_index().checked_sub(1)?)
+        };
+        match self.token(token_id)? {
+            LiveToken::Close(_) | LiveToken::Eof => Some(token_id),
+            _ => None
+        }
+    }
+
+    // the token of the value of a property
+    fn value_token(&self, node: &LiveNode) -> Option<LiveTokenId> {
+        let token_id = node.origin.token_id()?;
+        if node.id.is_empty() || node.origin.has_prop_type(LivePropType::Nameless) {
+            return Some(token_id)
+        }
+        let mut token_index = token_id.token_index() + 1;
+        if self.token(LiveTokenId::new(token_id.file_id(), token_index))? == LiveToken::Punct(id!(.)) {
+            while self.token(LiveTokenId::new(token_id.file_id(), token_index))? != LiveToken::Close(Delim::Brace) {
+                token_index += 1;
+            }
+            token_index += 1;
+        }
+        match self.token(LiveTokenId::new(token_id.file_id(), token_index))? {
+            LiveToken::Punct(id!(:)) => token_index += 1,
+            LiveToken::Punct(id!(=)) => {
+                token_index += 1;
+                if self.token(LiveTokenId::new(token_id.file_id(), token_index))? == LiveToken::Punct(id!( ?)) {
+                    token_index += 1;
+                }
+            }
+            _ => return None
+        }
+        Some(LiveTokenId::new(token_id.file_id(), token_index))
+    }
+
+    // the text a number or a color was written as, if it still holds the value
+    fn literal_source(&self, value: &LiveValue, token_id: LiveTokenId) -> Option<String> {
+        let file = self.registry.live_files.get(token_id.file_id().to_index())?;
+        let tokens = &file.original.tokens;
+        let start = tokens.get(token_id.token_index())?;
+        let negative = start.token == LiveToken::Punct(id!(-));
+        let end = if negative {tokens.get(token_id.token_index() + 1)?} else {start};
+        let matches = match (value, end.token) {
+            (LiveValue::Int(v), LiveToken::Int(t)) => *v == if negative {-t} else {t},
+            (LiveValue::Float(v), LiveToken::Float(t)) => *v == if negative {-t} else {t},
+            (LiveValue::Color(v), LiveToken::Color(t)) => !negative && *v == t,
+            _ => false
+        };
+        if !matches {
+            return None
+        }
+        let source = source_text(file, start.span.start, Some(end.span.end))?;
+        let valid = match value {
+            LiveValue::Color(_) => source.starts_with('#') && source[1..].chars().all( | c | c.is_ascii_alphanumeric()),
+            _ => source.chars().filter( | c | !c.is_whitespace()).collect::<String>().parse::<f64>().is_ok()
+        };
+        if valid {Some(source)} else {None}
+    }
+
+    fn token(&self, token_id: LiveTokenId) -> Option<LiveToken> {
+        let file = self.registry.live_files.get(token_id.file_id().to_index())?;
+        file.original.tokens.get(token_id.token_index()).map( | token | token.token)
+    }
+
+    fn token_line(&self, token_id: LiveTokenId) -> Option<u32> {
+        match self.token(token_id)? {
+            LiveToken::Eof => None,
+            _ => Some(self.registry.token_id_to_span(token_id).start.line)
+        }
+    }
+
+    fn trivia_before(&self, token_id: LiveTokenId) -> Option<Trivia> {
+        let file = self.registry.live_files.get(token_id.file_id().to_index())?;
+        let tokens = &file.original.tokens;
+        let token_index = token_id.token_index();
+        let token = tokens.get(token_index)?;
+        let start = if token_index > 0 {tokens[token_index - 1].span.end} else {file.start_pos};
+        // the end of file token has no span, its gap runs to the end of the source
+        let end = if let LiveToken::Eof = token.token {None} else {Some(token.span.start)};
+        parse_trivia(&source_text(file, start, end) ?)
+    }
+}
+
+impl LiveRegistry {
+    /// Prints the node at `index` back to live source, see `LivePrinter`.
+    pub fn node_to_live_source(&self, nodes: &[LiveNode], index: usize) -> String {
+        let mut printer = LivePrinter::new(self);
+        printer.print_node(nodes, index);
+        printer.into_string()
+    }
+
+    /// Prints the original nodes of a file back to the body of its `live!{}` block.
+    pub fn file_to_live_source(&self, file_id: LiveFileId) -> String {
+        let mut printer = LivePrinter::new(self);
+        printer.print_body(&self.live_files[file_id.to_index()].original.nodes, 0);
+        printer.into_string()
+    }
+}
+
+// the text between two positions of the source of a live file, `None` runs to its end
+fn source_text(file: &LiveFile, start: TextPos, end: Option<TextPos>) -> Option<String> {
+    if start.line < file.start_pos.line || end.is_some_and( | end | end < start) {
+        return None
+    }
+    let mut out = String::new();
+    let lines = file.source.lines().skip((start.line - file.start_pos.line) as usize);
+    for (i, line) in lines.enumerate() {
+        let line_index = start.line + i as u32;
+        // only the first line of the source starts at a column other than zero
+        let offset = if line_index == file.start_pos.line {file.start_pos.column} else {0};
+        let chars: Vec<char> = line.chars().collect();
+        let from = if line_index == start.line {start.column.checked_sub(offset)? as usize} else {0};
+        match end {
+            Some(end) if end.line == line_index => {
+                let to = end.column.checked_sub(offset)? as usize;
+                out.extend(chars.get(from..to)?);
+                return Some(out)
+            }
+            _ => {
+                out.extend(chars.get(from..)?);
+                out.push('\n');
+            }
+        }
+    }
+    if end.is_none() {Some(out)} else {None}
+}
+
+// splits the gap between two tokens into comments, `None` if it holds anything else
+fn parse_trivia(gap: &str) -> Option<Trivia> {
+    let chars: Vec<char> = gap.chars().collect();
+    let mut trivia = Trivia::default();
+    let mut seen_newline = false;
+    let mut line_is_empty = true;
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        match (chars[i], chars.get(i + 1)) {
+            ('\n', _) => {
+                if seen_newline && line_is_empty {
+                    trivia.empty_line = true;
+                }
+                seen_newline = true;
+                line_is_empty = true;
+                i += 1;
+                continue;
+            }
+            (c, _) if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            ('/', Some('/')) => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            ('/', Some('*')) => {
+                let mut depth = 0;
+                loop {
+                    match (chars.get(i), chars.get(i + 1)) {
+                        (Some('/'), Some('*')) => {
+                            depth += 1;
+                            i += 2;
+                        }
+                        (Some('*'), Some('/')) => {
+                            depth -= 1;
+                            i += 2;
+                            if depth == 0 {
+                                break
+                            }
+                        }
+                        (Some(_), _) => i += 1,
+                        (None, _) => return None
+                    }
+                }
+            }
+            _ => return None
+        }
+        let comment: String = chars[start..i].iter().collect();
+        let comment = comment.trim_end().to_string();
+        if !seen_newline && trivia.trailing.is_none() && trivia.comments.is_empty() {
+            trivia.trailing = Some(comment);
+        }
+        else {
+            trivia.comments.push(comment);
+        }
+        line_is_empty = false;
+    }
+    Some(trivia)
+}
+
+// the parser names an unnamed `Name {}` in a body `Name[n]`, which isn't written out
+fn is_anonymous_instance(node: &LiveNode) -> bool {
+    if let LiveValue::Clone(base) = node.value {
+        if node.origin.has_prop_type(LivePropType::Instance) {
+            let base = base.to_string();
+            return node.id.as_string( | id | id.is_some_and( | id | {
+                id.strip_prefix(&base).is_some_and( | rest | rest.starts_with('[') && rest.ends_with(']'))
+            }))
+        }
+    }
+    false
+}
+
+fn id_or_star(id: LiveId) -> String {
+    if id.is_empty() {"*".to_string()} else {id.to_string()}
+}
+
+fn expr_prec(value: &LiveValue) -> usize {
+    match value {
+        LiveValue::ExprBinOp(op) => match op {
+            LiveBinOp::Or => 1,
+            LiveBinOp::And => 2,
+            LiveBinOp::Eq | LiveBinOp::Ne => 3,
+            LiveBinOp::Lt | LiveBinOp::Le | LiveBinOp::Gt | LiveBinOp::Ge => 4,
+            LiveBinOp::Add | LiveBinOp::Sub => 5,
+            LiveBinOp::Mul | LiveBinOp::Div => 6,
+        },
+        LiveValue::ExprUnOp(_) => 7,
+        LiveValue::ExprMember(_) => 8,
+        LiveValue::ExprCall {ident: id!(cond), args: 3} => 0,
+        _ => 9
+    }
+}
+
+fn bin_op_str(op: LiveBinOp) -> &'static str {
+    match op {
+        LiveBinOp::Or => "||",
+        LiveBinOp::And => "&&",
+        LiveBinOp::Eq => "==",
+        LiveBinOp::Ne => "!=",
+        LiveBinOp::Lt => "<",
+        LiveBinOp::Le => "<=",
+        LiveBinOp::Gt => ">",
+        LiveBinOp::Ge => ">=",
+        LiveBinOp::Add => "+",
+        LiveBinOp::Sub => "-",
+        LiveBinOp::Mul => "*",
+        LiveBinOp::Div => "/",
+    }
+}
+
+// a float always gets its dot, `1` would parse as an int
+fn f64_to_string(v: f64) -> String {
+    let s = v.to_string();
+    if s.contains('.') {s} else {format!("{}.0", s)}
+}
+
+fn f32_to_string(v: f32) -> String {
+    let s = v.to_string();
+    if s.contains('.') {s} else {format!("{}.0", s)}
+}
+
+fn color_to_string(v: u32) -> String {
+    if v & 0xff == 0xff {
+        format!("#{:06x}", v >> 8)
+    }
+    else {
+        format!("#{:08x}", v)
+    }
+}
+
+// strings are kept with their escapes resolved, so they are escaped again to be printed
+fn string_literal(string: &str) -> String {
+    let mut out = String::from("\"");
+    for c in string.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\0' => out.push_str("\\0"),
+            c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// used for a DSL block whose source no longer matches its tokens
+fn token_to_string(file: &LiveFile, token: &LiveToken) -> String {
+    let string = | index: u32, len: u32 | -> String {
+        file.original.strings[index as usize..(index + len) as usize].iter().collect()
+    };
+    match token {
+        // punctuation ids aren't interned by the tokenizer
+        LiveToken::Punct(id) => PUNCTS.iter()
+            .find( | punct | LiveId::from_str_unchecked(punct) == *id)
+            .map_or_else( || id.to_string(), | punct | punct.to_string()),
+        LiveToken::Ident(id) => id.to_string(),
+        LiveToken::Open(Delim::Paren) => "(".to_string(),
+        LiveToken::Open(Delim::Bracket) => "[".to_string(),
+        LiveToken::Open(Delim::Brace) => "{".to_string(),
+        LiveToken::Close(Delim::Paren) => ")".to_string(),
+        LiveToken::Close(Delim::Bracket) => "]".to_string(),
+        LiveToken::Close(Delim::Brace) => "}".to_string(),
+        LiveToken::String {index, len} => string_literal(&string(*index, *len)),
+        LiveToken::Dependency {index, len} => format!("d\"{}\"", string(*index, *len)),
+        LiveToken::Bool(v) => v.to_string(),
+        LiveToken::Int(v) => v.to_string(),
+        LiveToken::Float(v) => f64_to_string(*v),
+        LiveToken::Color(v) => color_to_string(*v),
+        LiveToken::Eof => String::new(),
+    }
+}
@@ -180,13 +180,13 @@ impl LiveRegistry {
                 Some(v.as_str().to_string())
             }
             LiveValue::DocumentString {string_start, string_count} => {
-                let origin_doc = self.token_id_to_origin_doc(node.origin.token_id().unwrap());
+                let origin_doc = self.token_id_to_origin_doc(node.origin.token_id()?);
                 let mut out = String::new();
                 origin_doc.get_string(*string_start, *string_count, &mut out);
                 Some(out)
             }
             LiveValue::Dependency {string_start, string_count} => {
-                let origin_doc = self.token_id_to_origin_doc(node.origin.token_id().unwrap());
+                let origin_doc = self.token_id_to_origin_doc(node.origin.token_id()?);
                 let mut out = String::new();
                 origin_doc.get_string(*string_start, *string_count, &mut out);
                 Some(out)
@@ -417,13 +417,13 @@ impl LiveRegistry {
                             })
                         },
                         FullToken::String => {
-                            let len = full_token.len - 2;
+                            let col = pos.column as usize + 1;
+                            let index = strings.len();
+                            unescape_string(&line_chars[col..col + full_token.len - 2], &mut strings);
                             tokens.push(TokenWithSpan {span: span, token: LiveToken::String {
-                                index: strings.len() as u32,
-                                len: len as u32
+                                index: index as u32,
+                                len: (strings.len() - index) as u32
                             }});
-                            let col = pos.column as usize + 1;
-                            strings.extend(&line_chars[col..col + len]);
                         },
                         FullToken::Dependency => {
                             let len = full_token.len - 3;
@@ -502,9 +502,10 @@ impl LiveRegistry {
                             })
                         },
                         FullToken::String => {
-                            let new_len = full_token.len - 2;
                             let new_col = column as usize + 1;
-                            let new_chars = &line_chars[new_col..new_col + new_len];
+                            let mut new_chars = Vec::new();
+                            unescape_string(&line_chars[new_col..new_col + full_token.len - 2], &mut new_chars);
+                            let new_len = new_chars.len();
                             let new_string = LiveToken::String {
                                 index: new_strings.len() as u32,
                                 len: new_len as u32
@@ -532,7 +533,7 @@ impl LiveRegistry {
                                 }
                                 live_tokens[live_index] = TokenWithSpan {span: span, token: new_string};
                             }
-                            new_strings.extend(new_chars);
+                            new_strings.extend(&new_chars);
                             live_index += 1;
                         },
                         _ => match LiveToken::from_full_token(full_token.token) {
@@ -942,3 +943,47 @@ impl FileDepIter {
         }
     }
 }
+
+// Strings are stored with their escapes resolved, the way they are used. Escapes that aren't
+// known are kept as they were written.
+fn unescape_string(chars: &[char], out: &mut Vec<char>) {
+    let mut iter = chars.iter().copied().peekable();
+    while let Some(c) = iter.next() {
+        if c != '\\' {
+            out.push(c);
+            continue
+        }
+        match iter.peek().copied() {
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('t') => out.push('\t'),
+            Some('0') => out.push('\0'),
+            Some(c @ ('\\' | '"' | '\'')) => out.push(c),
+            Some('u') => {
+                // \u{7f}
+                let rest: String = iter.clone().skip(1).take_while( | c | *c != '}').collect();
+                let closed = iter.clone().nth(1 + rest.chars().count()) == Some('}');
+                let c = rest.strip_prefix('{')
+                    .filter( | _ | closed)
+                    .and_then( | hex | u32::from_str_radix(hex, 16).ok())
+                    .and_then(char::from_u32);
+                match c {
+                    Some(c) => {
+                        out.push(c);
+                        // the u, the digits in braces and the closing brace
+                        for _ in 0..rest.chars().count() + 2 {
+                            iter.next();
+                        }
+                    }
+                    None => out.push('\\'),
+                }
+                continue
+            }
+            _ => {
+                out.push('\\');
+                continue
+            }
+        }
+        iter.next();
+    }
+}
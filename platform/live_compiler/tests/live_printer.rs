@@ -0,0 +1,182 @@
+use makepad_live_compiler::{
+    makepad_live_id::{id, LiveId},
+    LiveRegistry,
+    LiveModuleId,
+    LiveTypeInfo,
+    LiveType,
+    LiveValue,
+    LiveNode,
+    LiveNodeOrigin,
+    TextPos,
+    live_node::{FittedString, InlineString},
+};
+
+const SOURCE: &str = r#"
+    use makepad_platform::shader::std::*;
+    use crate::theme::COLOR_BG;
+
+    // a class with a shader
+    DrawTest: {{DrawTest}} {
+        instance hover: 0.0
+        color: #f00
+
+        fn pixel(self) -> vec4 {
+            let c = vec2(1.0, 2.);
+            return mix(self.color, #fff, self.hover);
+        }
+    }
+
+    Test: DrawTest {
+        width: Size::Fixed(100.0) // a trailing comment
+        height: Size::Fit
+        margin: {left: -1.0, right: 1., top: 2, bottom: -3}
+        offset: vec2(-1.0, 0.5)
+        label: "hello"
+        quote: "say \"hi\"\\n\tthere"
+
+        /* the states */
+        state: {
+            hover = {
+                default: off
+                off = {from: {all: Play::Forward {duration: 0.1}}}
+            }
+        }
+        item =? Item {}
+        Button {}
+        Button {label: "two"}
+        list: [1, 2.5, #80808080]
+        pairs: [
+            {a: 1},
+            {a: 2},
+        ]
+        sum: ((1.0 + 2.0) * -COLOR_BG.x - (3 - 4))
+        pick: (a > 1 ? b : c)
+    }
+"#;
+
+const PRINTED: &str = r#"use makepad_platform::shader::std::*
+use crate::theme::COLOR_BG
+
+// a class with a shader
+DrawTest: {{DrawTest}} {
+    instance hover: 0.0
+    color: #f00
+
+    fn pixel(self) -> vec4 {
+        let c = vec2(1.0, 2.);
+        return mix(self.color, #fff, self.hover);
+    }
+}
+
+Test: DrawTest {
+    width: Size::Fixed(100.0) // a trailing comment
+    height: Size::Fit
+    margin: {left: -1.0, right: 1., top: 2, bottom: -3}
+    offset: vec2(-1.0, 0.5)
+    label: "hello"
+    quote: "say \"hi\"\\n\tthere"
+
+    /* the states */
+    state: {
+        hover = {
+            default: off
+            off = {from: {all: Play::Forward {duration: 0.1}}}
+        }
+    }
+    item =? Item {}
+    Button {}
+    Button {label: "two"}
+    list: [1, 2.5, #80808080]
+    pairs: [
+        {a: 1},
+        {a: 2},
+    ]
+    sum: ((1.0 + 2.0) * -COLOR_BG.x - (3 - 4))
+    pick: (a > 1 ? b : c)
+}"#;
+
+struct DrawTest();
+// a type is registered with a single module, the printed source gets its own
+struct DrawTestPrinted();
+
+fn register(live_registry: &mut LiveRegistry, file_name: &str, source: &str, live_type: LiveType) -> String {
+    let module_id = LiveModuleId::from_str(&format!("test::{}", file_name)).unwrap();
+    let live_type_info = LiveTypeInfo {
+        live_type,
+        type_name: id!(DrawTest),
+        module_id,
+        fields: Vec::new()
+    };
+    let file_id = live_registry.register_live_file(file_name, module_id, source.to_string(), vec![live_type_info], TextPos::default())
+        .unwrap_or_else( | err | panic!("{}", err));
+    live_registry.file_to_live_source(file_id)
+}
+
+#[test]
+fn prints_the_original_source() {
+    let mut live_registry = LiveRegistry::default();
+    let printed = register(&mut live_registry, "original", SOURCE, LiveType::of::<DrawTest>());
+    assert_eq!(printed, PRINTED);
+}
+
+#[test]
+fn printed_source_parses_to_the_same_nodes() {
+    let mut live_registry = LiveRegistry::default();
+    let printed = register(&mut live_registry, "original", SOURCE, LiveType::of::<DrawTest>());
+    let reprinted = register(&mut live_registry, "printed", &printed, LiveType::of::<DrawTestPrinted>());
+    assert_eq!(reprinted, printed);
+
+    let original = &live_registry.live_files[0].original.nodes;
+    let reparsed = &live_registry.live_files[1].original.nodes;
+    assert_eq!(original.len(), reparsed.len());
+    for (a, b) in original.iter().zip(reparsed.iter()) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.origin.prop_type() as usize, b.origin.prop_type() as usize);
+        // strings and shaders point into their own document, and the class into its own module
+        if !matches!(a.value, LiveValue::DocumentString {..} | LiveValue::DSL {..} | LiveValue::Class {..}) {
+            assert_eq!(a.value, b.value);
+        }
+        assert_eq!(live_registry.live_node_as_string(a), live_registry.live_node_as_string(b));
+    }
+}
+
+#[test]
+fn prints_nodes_without_a_source() {
+    let mut live_registry = LiveRegistry::default();
+    register(&mut live_registry, "original", SOURCE, LiveType::of::<DrawTest>());
+    let nodes = &live_registry.live_files[0].original.nodes;
+    let index = nodes.iter().position( | node | node.id == LiveId::from_str("margin").unwrap()).unwrap();
+    // without the tokens the layout falls back to a line per property
+    let mut detached = nodes[index..index + 6].to_vec();
+    for node in &mut detached {
+        node.origin = LiveNodeOrigin::field();
+    }
+    assert_eq!(
+        live_registry.node_to_live_source(&detached, 0),
+        "margin: {\n    left: -1.0\n    right: 1.0\n    top: 2\n    bottom: -3\n}"
+    );
+}
+
+#[test]
+fn printed_strings_parse_to_the_same_string() {
+    let string = "a \"quote\", a \\ backslash\nand a\ttab, a \u{1}control char and \\\"";
+    let values = vec![
+        LiveValue::Str(string),
+        LiveValue::FittedString(FittedString::from_string(string.to_string())),
+        LiveValue::InlineString(InlineString::from_str("tab\t\"").unwrap()),
+    ];
+    for value in values {
+        let mut live_registry = LiveRegistry::default();
+        let expected = match &value {
+            LiveValue::InlineString(v) => v.as_str().to_string(),
+            _ => string.to_string()
+        };
+        let node = LiveNode {origin: LiveNodeOrigin::field(), id: id!(label), value};
+        let source = format!("Test: {{{}}}", live_registry.node_to_live_source(&[node], 0));
+        assert!(!source.contains('\n') && !source.contains('\t'));
+        register(&mut live_registry, "string", &source, LiveType::of::<DrawTest>());
+        let nodes = &live_registry.live_files[0].original.nodes;
+        let label = nodes.iter().find( | node | node.id == id!(label)).unwrap();
+        assert_eq!(live_registry.live_node_as_string(label).unwrap(), expected);
+    }
+}
@@ -522,7 +522,7 @@ impl DoubleQuotedStringTailState {
                         FullToken::String,
                     );
                 }
-                ('\\', '"') => cursor.skip(2),
+                ('\\', '"') | ('\\', '\\') => cursor.skip(2),
                 _ => cursor.skip(1),
             }
         }
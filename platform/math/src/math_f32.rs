@@ -10,7 +10,7 @@ pub struct PrettyPrintedF32(pub f32);
 
 impl fmt::Display for PrettyPrintedF32 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        if self.0.abs().fract() < 0.00000001 {
+        if self.0.fract() == 0.0 {
             write!(f, "{}.0", self.0)
         } else {
             write!(f, "{}", self.0)
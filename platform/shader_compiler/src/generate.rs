@@ -9,13 +9,20 @@ use{
         makepad_live_id::{LiveId, id},
         makepad_live_compiler::{
             makepad_math::PrettyPrintedF32,
+            LiveError,
+            LiveErrorOrigin,
+            live_error_origin,
+            LiveTokenId,
             TokenSpan
         },
         shader_ast::*,
-        shader_registry::ShaderRegistry
+        shader_registry::ShaderRegistry,
+        swizzle::Swizzle,
     }
 };
 
+const COMPONENTS: [&str; 4] = ["x", "y", "z", "w"];
+
 
 struct VoidWrap();
 impl VoidWrap{
@@ -42,6 +49,75 @@ pub struct ClosureSiteInfo<'a> {
     pub call_ptr: FnPtr
 }
 
+/// Maps the lines of generated shader source back to the tokens of the DSL they were
+/// generated from, so errors of a backend compiler can point at the original code.
+#[derive(Clone, Debug, Default)]
+pub struct ShaderSourceMap {
+    // the 1 based first line of every function and statement, in order
+    lines: Vec<(usize, LiveTokenId)>,
+    scanned_len: usize,
+    scanned_lines: usize,
+}
+
+impl ShaderSourceMap {
+    // the code for span starts at the current end of the generated source
+    pub fn record(&mut self, string: &str, span: TokenSpan) {
+        self.scanned_lines += string[self.scanned_len..].matches('\n').count();
+        self.scanned_len = string.len();
+        let line = self.scanned_lines + 1;
+        // the outermost statement on a line wins
+        if self.lines.last().map_or(true, | (last, _) | *last != line) {
+            self.lines.push((line, span.token_id));
+        }
+    }
+    
+    pub fn line_to_token_id(&self, line: usize) -> Option<LiveTokenId> {
+        let index = self.lines.partition_point( | (first_line, _) | *first_line <= line);
+        if index == 0 {
+            return None
+        }
+        Some(self.lines[index - 1].1)
+    }
+    
+    /// Turns the error log of a backend compiler into errors on the DSL. `prelude_lines` is the
+    /// number of lines put in front of the generated source before it was compiled. Log lines
+    /// that don't point into generated code are skipped.
+    pub fn log_to_live_errors(&self, log: &str, prelude_lines: usize) -> Vec<LiveError> {
+        let mut errors = Vec::new();
+        for log_line in log.lines() {
+            if let Some((line, message)) = parse_log_line(log_line) {
+                if let Some(token_id) = line.checked_sub(prelude_lines).and_then( | line | self.line_to_token_id(line)) {
+                    errors.push(LiveError {
+                        origin: live_error_origin!(),
+                        span: token_id.into(),
+                        message: message.to_string()
+                    });
+                }
+            }
+        }
+        errors
+    }
+}
+
+// finds the line number in the GLSL ("0:12(5): ", "0(12) : ", "ERROR: 0:12: "),
+// Metal ("program_source:12:5: ") and HLSL ("file(12,5): ") log formats
+fn parse_log_line(log_line: &str) -> Option<(usize, &str)> {
+    for (at, _) in log_line.match_indices( | c | c == ':' || c == '(') {
+        let after = &log_line[at + 1..];
+        let digits = after.find( | c: char | !c.is_ascii_digit()).unwrap_or(after.len());
+        if digits == 0 || !after[digits..].starts_with( | c | matches!(c, ':' | '(' | ')' | ',')) {
+            continue;
+        }
+        let line = after[..digits].parse().ok()?;
+        let message = match after[digits..].find(": ") {
+            Some(index) => &after[digits + index + 2..],
+            None => log_line
+        };
+        return Some((line, message.trim()))
+    }
+    None
+}
+
 pub trait BackendWriter {
     fn needs_cstyle_struct_cons(&self)->bool;
     fn needs_mul_fn_for_matrix_multiplication(&self) -> bool;
@@ -51,6 +127,11 @@ pub trait BackendWriter {
     fn enum_is_float(&self)->bool;
     
     fn use_cons_fn(&self, what: &str) -> bool;
+    
+    fn needs_select_fn_for_cond_expr(&self) -> bool;
+    fn needs_assignment_as_stmt(&self) -> bool;
+    fn needs_ptr_for_inout_params(&self) -> bool;
+    fn needs_splat_for_builtin_args(&self) -> bool;
 
     fn write_var_decl(
         &self,
@@ -61,6 +142,10 @@ pub trait BackendWriter {
         ident: &dyn fmt::Display,
         ty: &Ty,
     ) -> bool;
+    
+    fn write_let_decl(&self, string: &mut String, ident: &dyn fmt::Display, ty: &Ty);
+    fn write_fn_def_ident(&self, string: &mut String, ident: &dyn fmt::Display, return_ty: &Ty);
+    fn write_fn_def_return_ty(&self, string: &mut String, return_ty: &Ty);
 
     fn write_call_expr_hidden_args(&self, string: &mut String, hidden_args:&BTreeSet<HiddenArgKind >, sep: &str);
     fn write_fn_def_hidden_params(&self, string: &mut String, hidden_args:&BTreeSet<HiddenArgKind >, sep: &str);
@@ -83,6 +168,7 @@ pub struct BlockGenerator<'a> {
     //pub use_generated_cons_fns: bool,
     pub indent_level: usize,
     pub string: &'a mut String,
+    pub source_map: &'a mut ShaderSourceMap,
 }
 
     
@@ -95,8 +181,9 @@ pub fn generate_cons_fn(backend_writer:&dyn BackendWriter, string: &mut String,
         return
     }
     
-    backend_writer.write_ty_lit(string, ty_lit);
-    write!(string, " {}(", cons_name).unwrap();
+    let ty = ty_lit.to_ty();
+    backend_writer.write_fn_def_ident(string, &cons_name, &ty);
+    write!(string, "(").unwrap();
     let mut sep = "";
     if param_tys.len() == 1 {
         backend_writer.write_var_decl(string, sep, false, false, &Ident(id!(x)), &param_tys[0]);
@@ -107,11 +194,12 @@ pub fn generate_cons_fn(backend_writer:&dyn BackendWriter, string: &mut String,
             sep = ", ";
         }
     }
-    writeln!(string, ") {{").unwrap();
+    write!(string, ")").unwrap();
+    backend_writer.write_fn_def_return_ty(string, &ty);
+    writeln!(string, " {{").unwrap();
     write!(string, "    return ").unwrap();
     backend_writer.write_ty_lit(string, ty_lit);
     write!(string, "(").unwrap();
-    let ty = ty_lit.to_ty();
     if param_tys.len() == 1 {
         let param_ty = &param_tys[0];
         match param_ty {
@@ -192,6 +280,7 @@ impl<'a> BlockGenerator<'a> {
     
     fn generate_stmt(&mut self, stmt: &Stmt) {
         self.write_indent();
+        self.source_map.record(self.string, stmt.span());
         match *stmt {
             Stmt::Break {span} => self.generate_break_stmt(span),
             Stmt::Continue {span} => self.generate_continue_stmt(span),
@@ -278,9 +367,11 @@ impl<'a> BlockGenerator<'a> {
         } else {
             -1
         };
+        write!(self.string, "for (").unwrap();
+        self.backend_writer.write_let_decl(self.string, &ident, &Ty::Int);
         write!(
             self.string,
-            "for (int {0} = {1}; {0} {2} {3}; {0} {4} {5}) ",
+            " = {1}; {0} {2} {3}; {0} {4} {5}) ",
             ident,
             if from <= to {from} else {from - 1},
             if from <= to {"<"} else {">="},
@@ -300,7 +391,7 @@ impl<'a> BlockGenerator<'a> {
         block_if_true: &Block,
         block_if_false: &Option<Box<Block >>,
     ) {
-        write!(self.string, "if").unwrap();
+        write!(self.string, "if ").unwrap();
         self.generate_expr(expr);
         write!(self.string, " ").unwrap();
         self.generate_block(block_if_true);
@@ -347,11 +438,8 @@ impl<'a> BlockGenerator<'a> {
         expr: &Option<Expr>,
         shadow: &Cell<Option<ScopeSymShadow >>
     ) {
-        self.backend_writer.write_var_decl(
+        self.backend_writer.write_let_decl(
             &mut self.string,
-            "",
-            false, 
-            false,
             &DisplayVarName(ident, shadow.get().unwrap()),
             ty.borrow().as_ref().unwrap()
         );
@@ -377,10 +465,73 @@ impl<'a> BlockGenerator<'a> {
     }
     
     fn generate_expr_stmt(&mut self, _span: TokenSpan, expr: &Expr) {
+        if self.backend_writer.needs_assignment_as_stmt() {
+            if let ExprKind::Bin {op, ref left_expr, ref right_expr, ..} = expr.kind {
+                if op.is_assign() {
+                    self.generate_assign_stmt(op, left_expr, right_expr);
+                    writeln!(self.string).unwrap();
+                    return
+                }
+            }
+            // only assignments and calls can be statements, anything else is assigned to _
+            let is_stmt = match expr.kind {
+                _ if matches!(*expr.const_val.borrow(), Some(Some(_))) => false,
+                ExprKind::Bin {op, ..} => op.is_assign(),
+                ExprKind::MethodCall {..} | ExprKind::PlainCall {..} => true,
+                _ => false
+            };
+            if !is_stmt {
+                write!(self.string, "_ = ").unwrap();
+            }
+        }
         self.generate_expr(expr);
         writeln!(self.string, ";").unwrap();
     }
     
+    // a chained assignment becomes a statement per target, innermost first, and a multi
+    // component swizzle is assigned a component at a time
+    fn generate_assign_stmt(&mut self, op: BinOp, left_expr: &Expr, right_expr: &Expr) {
+        let right_expr = match right_expr.kind {
+            ExprKind::Bin {op: inner_op, left_expr: ref inner_left_expr, right_expr: ref inner_right_expr, ..} if inner_op.is_assign() => {
+                self.generate_assign_stmt(inner_op, inner_left_expr, inner_right_expr);
+                writeln!(self.string).unwrap();
+                self.write_indent();
+                inner_left_expr
+            }
+            _ => right_expr
+        };
+        if let ExprKind::Field {ref expr, field_ident, ..} = left_expr.kind {
+            if let Some(swizzle) = Swizzle::parse(field_ident).filter( | swizzle | swizzle.len() > 1) {
+                if expr.ty.borrow().as_ref().unwrap().is_vector() {
+                    let right_is_vector = right_expr.ty.borrow().as_ref().unwrap().is_vector();
+                    write!(self.string, "{{\n").unwrap();
+                    self.indent_level += 1;
+                    self.write_indent();
+                    write!(self.string, "let rhs = ").unwrap();
+                    self.generate_expr(right_expr);
+                    write!(self.string, ";\n").unwrap();
+                    for (index, component) in swizzle.iter().enumerate() {
+                        self.write_indent();
+                        self.generate_expr(expr);
+                        write!(self.string, ".{} {} rhs", COMPONENTS[*component], op).unwrap();
+                        if right_is_vector {
+                            write!(self.string, ".{}", COMPONENTS[index]).unwrap();
+                        }
+                        write!(self.string, ";\n").unwrap();
+                    }
+                    self.indent_level -= 1;
+                    self.write_indent();
+                    write!(self.string, "}}").unwrap();
+                    return
+                }
+            }
+        }
+        self.generate_expr(left_expr);
+        write!(self.string, " {} ", op).unwrap();
+        self.generate_expr(right_expr);
+        write!(self.string, ";").unwrap();
+    }
+    
     fn generate_expr(&mut self, expr: &Expr) {
         ExprGenerator {
             closure_site_info: self.closure_site_info.clone(),
@@ -520,7 +671,7 @@ impl<'a> ExprGenerator<'a> {
                     span,
                     ident,
                     ref arg_exprs,
-                } => self.generate_builtin_call_expr(span, ident, arg_exprs),
+                } => self.generate_builtin_call_expr(span, ident, arg_exprs, in_expr.ty.borrow().as_ref().unwrap()),
                 /*ExprKind::ClosureCall {
                     span,
                     ident,
@@ -555,6 +706,16 @@ impl<'a> ExprGenerator<'a> {
         expr_if_true: &Expr,
         expr_if_false: &Expr,
     ) {
+        if self.backend_writer.needs_select_fn_for_cond_expr() {
+            write!(self.string, "select(").unwrap();
+            self.generate_expr(expr_if_false);
+            write!(self.string, ", ").unwrap();
+            self.generate_expr(expr_if_true);
+            write!(self.string, ", ").unwrap();
+            self.generate_expr(expr);
+            write!(self.string, ")").unwrap();
+            return
+        }
         write!(self.string, "(").unwrap();
         self.generate_expr(expr);
         write!(self.string, " ? ").unwrap();
@@ -566,6 +727,14 @@ impl<'a> ExprGenerator<'a> {
     
     fn generate_bin_expr(&mut self, _span: TokenSpan, op: BinOp, left_expr: &Expr, right_expr: &Expr) {
         
+        // if assignments are statements they can't be wrapped in parens
+        if op.is_assign() && self.backend_writer.needs_assignment_as_stmt() {
+            self.generate_expr(left_expr);
+            write!(self.string, " {} ", op).unwrap();
+            self.generate_expr(right_expr);
+            return
+        }
+        
         // if left_expr or right_expr is a matrix, HLSL needs to use mul()
         let left_is_mat = match left_expr.ty.borrow().as_ref().unwrap() {
             Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => true,
@@ -700,7 +869,7 @@ impl<'a> ExprGenerator<'a> {
             )).unwrap();
             
             let mut sep = "";
-            for arg_expr in arg_exprs {
+            for (arg_index, arg_expr) in arg_exprs.iter().enumerate() {
                 // check if the args is a closure, ifso skip it
                 match arg_expr.ty.borrow().as_ref().unwrap(){
                     Ty::ClosureDef(_)=>{
@@ -710,6 +879,7 @@ impl<'a> ExprGenerator<'a> {
                 }
                 
                 write!(self.string, "{}", sep).unwrap();
+                self.write_arg_ref_prefix(fn_def, arg_exprs.len(), arg_index);
                 self.generate_expr(arg_expr);
                 sep = ", ";
             }
@@ -737,8 +907,9 @@ impl<'a> ExprGenerator<'a> {
         else {
             write!(self.string, "{}_{} (", fn_def.fn_ptr, fn_def.ident).unwrap();
             let mut sep = "";
-            for arg_expr in arg_exprs {
+            for (arg_index, arg_expr) in arg_exprs.iter().enumerate() {
                 write!(self.string, "{}", sep).unwrap();
+                self.write_arg_ref_prefix(fn_def, arg_exprs.len(), arg_index);
                 self.generate_expr(arg_expr);
                 sep = ", ";
             }
@@ -749,6 +920,24 @@ impl<'a> ExprGenerator<'a> {
         }
     }
     
+    // inout params that are pointers need a reference to their arg. the args line up with the
+    // last params, since draw shader methods don't pass their self param
+    fn write_arg_ref_prefix(&mut self, fn_def: &FnDef, arg_count: usize, arg_index: usize) {
+        if !self.backend_writer.needs_ptr_for_inout_params() {
+            return
+        }
+        let param_index = fn_def.params.len().saturating_sub(arg_count) + arg_index;
+        if fn_def.params.get(param_index).map_or(false, | param | param.is_inout) {
+            write!(self.string, "&").unwrap();
+        }
+    }
+    
+    fn is_ptr_param(&self, ident: Ident, shadow: ScopeSymShadow) -> bool {
+        self.backend_writer.needs_ptr_for_inout_params() && self.fn_def.map_or(false, | fn_def | {
+            fn_def.params.iter().any( | param | param.is_inout && param.ident == ident && param.shadow.get() == Some(shadow))
+        })
+    }
+    
     fn generate_field_expr(&mut self, _span: TokenSpan, expr: &Expr, field_ident: Ident, ty:&Ty) {
         match expr.ty.borrow().as_ref() {
             Some(Ty::DrawShader(_)) => {
@@ -794,17 +983,32 @@ impl<'a> ExprGenerator<'a> {
     }
     
     
-    fn generate_builtin_call_expr(&mut self, _span: TokenSpan, ident: Ident, arg_exprs: &[Expr]) {
+    fn generate_builtin_call_expr(&mut self, _span: TokenSpan, ident: Ident, arg_exprs: &[Expr], ty: &Ty) {
         // lets create a fn name for this thing.
         
         self.backend_writer.write_builtin_call_ident(&mut self.string, ident, arg_exprs);
         
+        // builtins like max(vec2, float) need their float args splatted to the returned vector
+        // type on some backends. refract takes its ratio as a float everywhere
+        let splat_ty_lit = match ty {
+            Ty::Vec2 | Ty::Vec3 | Ty::Vec4 if self.backend_writer.needs_splat_for_builtin_args() && ident != Ident(id!(refract)) => ty.maybe_ty_lit(),
+            _ => None
+        };
+        
         write!(self.string, "(").unwrap();
         let mut sep = "";
         for arg_expr in arg_exprs {
             write!(self.string, "{}", sep).unwrap();
             
-            self.generate_expr(arg_expr);
+            match splat_ty_lit {
+                Some(ty_lit) if arg_expr.ty.borrow().as_ref() == Some(&Ty::Float) => {
+                    self.write_ty_lit(ty_lit);
+                    write!(self.string, "(").unwrap();
+                    self.generate_expr(arg_expr);
+                    write!(self.string, ")").unwrap();
+                }
+                _ => self.generate_expr(arg_expr)
+            }
             
             sep = ", ";
         }
@@ -901,6 +1105,9 @@ impl<'a> ExprGenerator<'a> {
             VarKind::Local {ident, shadow} => {
                 write!(self.string, "{}", DisplayVarName(ident, shadow)).unwrap();
             }
+            VarKind::MutLocal {ident, shadow} if self.is_ptr_param(ident, shadow) => {
+                write!(self.string, "(*{})", DisplayVarName(ident, shadow)).unwrap();
+            }
             VarKind::MutLocal {ident, shadow} => {
                 write!(self.string, "{}", DisplayVarName(ident, shadow)).unwrap();
             }
@@ -930,17 +1137,15 @@ pub struct FnDefGenerator<'a> {
     pub shader_registry: &'a ShaderRegistry,
     pub const_table_offset: Option<usize>,
     pub string: &'a mut String,
+    pub source_map: &'a mut ShaderSourceMap,
     pub backend_writer: &'a dyn BackendWriter
 }
 
 impl<'a> FnDefGenerator<'a> {
     pub fn generate_fn_def(&mut self) {
-        
-        self.backend_writer.write_var_decl(
+        self.source_map.record(self.string, self.fn_def.span);
+        self.backend_writer.write_fn_def_ident(
             &mut self.string,
-            "",
-            false,
-            false,
             &DisplayFnName(self.fn_def.fn_ptr, self.fn_def.ident), // here we must expand IdentPath to something
             self.fn_def.return_ty.borrow().as_ref().unwrap()
         );
@@ -961,7 +1166,9 @@ impl<'a> FnDefGenerator<'a> {
             }
         }
         self.backend_writer.write_fn_def_hidden_params(self.string, self.fn_def.hidden_args.borrow().as_ref().unwrap(), sep);
-        write!(self.string, ") ").unwrap();
+        write!(self.string, ")").unwrap();
+        self.backend_writer.write_fn_def_return_ty(self.string, self.fn_def.return_ty.borrow().as_ref().unwrap());
+        write!(self.string, " ").unwrap();
         self.generate_block(&self.fn_def.block);
         writeln!(self.string).unwrap();
         //self.visited.insert(self.decl.ident_path);
@@ -977,6 +1184,7 @@ impl<'a> FnDefGenerator<'a> {
             const_table_offset: self.const_table_offset,
             indent_level: 0,
             string: self.string,
+            source_map: self.source_map,
         }
         .generate_block(block)
     }
@@ -989,18 +1197,21 @@ pub struct FnDefWithClosureArgsGenerator<'a> {
     pub shader_registry: &'a ShaderRegistry,
     pub const_table_offset: Option<usize>,
     pub string: &'a mut String,
+    pub source_map: &'a mut ShaderSourceMap,
     pub backend_writer: &'a dyn BackendWriter
 }
 
 
 impl<'a> FnDefWithClosureArgsGenerator<'a> {
     pub fn generate_fn_def_with_all_closures(
-        string:&mut String,
+        string: &mut String,
+        source_map: &mut ShaderSourceMap,
         shader_registry: &ShaderRegistry,
         fn_def: &FnDef,
         call_def: &FnDef,
         backend_writer: &dyn BackendWriter,
-        const_table_offset: Option<usize>
+        const_table_offset: Option<usize>,
+        call_const_table_offset: Option<usize>
     ) {
         // so first we are collecting the closures in defs that are actually used
         for (closure_def_index, closure_def) in call_def.closure_defs.iter().enumerate() {
@@ -1017,9 +1228,11 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
                                 call_def,
                                 shader_registry: shader_registry,
                                 //env:self.env,
-                                const_table_offset,
+                                // the closure body is part of the caller, and so are its consts
+                                const_table_offset: call_const_table_offset,
                                 backend_writer,
                                 string: string,
+                                source_map,
                             }
                             .generate_fn_def()
                         }
@@ -1045,6 +1258,7 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
                     const_table_offset,
                     backend_writer,
                     string,
+                    source_map,
                 }
                 .generate_fn_def_with_closure_args()
             }
@@ -1052,12 +1266,9 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
     }
     
     pub fn generate_fn_def_with_closure_args(&mut self) {
-        
-        self.backend_writer.write_var_decl(
+        self.source_map.record(self.string, self.fn_def.span);
+        self.backend_writer.write_fn_def_ident(
             &mut self.string,
-            "",
-            false,
-            false,
             &DisplayFnNameWithClosureArgs(
                 self.closure_site_info.site_index,
                 self.call_def.fn_ptr,
@@ -1101,7 +1312,9 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
         merged_hidden_args.extend(self.call_def.hidden_args.borrow().as_ref().unwrap().iter().cloned());
         self.backend_writer.write_fn_def_hidden_params(self.string, &merged_hidden_args, sep);
         
-        write!(self.string, ") ").unwrap();
+        write!(self.string, ")").unwrap();
+        self.backend_writer.write_fn_def_return_ty(self.string, self.fn_def.return_ty.borrow().as_ref().unwrap());
+        write!(self.string, " ").unwrap();
         // alright so here the block is generated.. however
         // we need to know the names and the closed-over-args passthrough
         self.generate_block(&self.fn_def.block);
@@ -1121,6 +1334,7 @@ impl<'a> FnDefWithClosureArgsGenerator<'a> {
             const_table_offset: self.const_table_offset,
             indent_level: 0,
             string: self.string,
+            source_map: self.source_map,
         }
         .generate_block(block)
     }
@@ -1134,23 +1348,21 @@ pub struct ClosureDefGenerator<'a> {
     pub shader_registry: &'a ShaderRegistry,
     pub const_table_offset: Option<usize>,
     pub string: &'a mut String,
+    pub source_map: &'a mut ShaderSourceMap,
     pub backend_writer: &'a dyn BackendWriter
 }
 
 impl<'a> ClosureDefGenerator<'a> {
     pub fn generate_fn_def(&mut self) {
-        
+        self.source_map.record(self.string, self.closure_def.span);
         let fn_param = &self.fn_def.params[self.closure_site_arg.param_index];
         
         let mut sep = "";
         
-        if let TyExprKind::ClosureDecl {params, return_ty, ..} = &fn_param.ty_expr.kind {
+        let closure_return_ty = if let TyExprKind::ClosureDecl {params, return_ty, ..} = &fn_param.ty_expr.kind {
             
-            self.backend_writer.write_var_decl(
+            self.backend_writer.write_fn_def_ident(
                 &mut self.string,
-                "",
-                false,
-                false,
                 &DisplayClosureName(self.call_def.fn_ptr, self.closure_site_arg.closure_def_index), // here we must expand IdentPath to something
                 return_ty.borrow().as_ref().unwrap(),
             );
@@ -1172,10 +1384,11 @@ impl<'a> ClosureDefGenerator<'a> {
                     sep = ", ";
                 }
             }
+            return_ty.borrow().clone().unwrap()
         }
         else {
             panic!()
-        }
+        };
         
         for sym in self.closure_def.closed_over_syms.borrow().as_ref().unwrap() {
             if self.backend_writer.write_var_decl(
@@ -1195,7 +1408,9 @@ impl<'a> ClosureDefGenerator<'a> {
         merged_hidden_args.extend(self.call_def.hidden_args.borrow().as_ref().unwrap().iter().cloned());
         self.backend_writer.write_fn_def_hidden_params(self.string, &merged_hidden_args, sep);
         
-        writeln!(self.string, ") {{").unwrap();
+        write!(self.string, ")").unwrap();
+        self.backend_writer.write_fn_def_return_ty(self.string, &closure_return_ty);
+        writeln!(self.string, " {{").unwrap();
         
         match &self.closure_def.kind {
             ClosureDefKind::Expr(expr) => {
@@ -1222,6 +1437,7 @@ impl<'a> ClosureDefGenerator<'a> {
             const_table_offset: self.const_table_offset,
             indent_level: 0,
             string: self.string,
+            source_map: self.source_map,
         }
         .generate_block(block)
     }
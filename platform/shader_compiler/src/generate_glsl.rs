@@ -17,35 +17,48 @@ use {
 };
 
 pub fn generate_vertex_shader(draw_shader_def: &DrawShaderDef, const_table: &DrawShaderConstTable, shader_registry: &ShaderRegistry) -> String {
+    generate_vertex_shader_with_source_map(draw_shader_def, const_table, shader_registry).0
+}
+
+pub fn generate_vertex_shader_with_source_map(draw_shader_def: &DrawShaderDef, const_table: &DrawShaderConstTable, shader_registry: &ShaderRegistry) -> (String, ShaderSourceMap) {
     let mut string = String::new();
+    let mut source_map = ShaderSourceMap::default();
     DrawShaderGenerator {
         draw_shader_def,
         const_table,
         shader_registry,
         string: &mut string,
+        source_map: &mut source_map,
         backend_writer: &GlslBackendWriter {shader_registry, const_table}
     }
     .generate_vertex_shader();
-    string
+    (string, source_map)
 }
 
 pub fn generate_pixel_shader(draw_shader_def: &DrawShaderDef, const_table: &DrawShaderConstTable, shader_registry: &ShaderRegistry) -> String {
+    generate_pixel_shader_with_source_map(draw_shader_def, const_table, shader_registry).0
+}
+
+pub fn generate_pixel_shader_with_source_map(draw_shader_def: &DrawShaderDef, const_table: &DrawShaderConstTable, shader_registry: &ShaderRegistry) -> (String, ShaderSourceMap) {
     let mut string = String::new();
+    let mut source_map = ShaderSourceMap::default();
     DrawShaderGenerator {
         draw_shader_def,
         const_table,
         shader_registry,
         string: &mut string,
+        source_map: &mut source_map,
         backend_writer: &GlslBackendWriter {shader_registry, const_table}
     }
     .generate_pixel_shader();
-    string
+    (string, source_map)
 }
 
 struct DrawShaderGenerator<'a> {
     draw_shader_def: &'a DrawShaderDef,
     shader_registry: &'a ShaderRegistry,
     string: &'a mut String,
+    source_map: &'a mut ShaderSourceMap,
     const_table: &'a DrawShaderConstTable,
     backend_writer: &'a dyn BackendWriter
 }
@@ -223,11 +236,13 @@ impl<'a> DrawShaderGenerator<'a> {
                     if call_def.callees.borrow().as_ref().unwrap().contains(&fn_iter) {
                         FnDefWithClosureArgsGenerator::generate_fn_def_with_all_closures(
                             &mut self.string,
+                            self.source_map,
                             self.shader_registry,
                             fn_def,
                             call_def,
                             self.backend_writer,
-                            const_table_offset
+                            const_table_offset,
+                            self.const_table.offsets.get(call_iter).cloned()
                         );
                     }
                 }
@@ -239,6 +254,7 @@ impl<'a> DrawShaderGenerator<'a> {
                 shader_registry: self.shader_registry,
                 backend_writer: self.backend_writer,
                 string: self.string,
+                source_map: self.source_map,
             }
             .generate_fn_def();
             write!(self.string, "\n").unwrap();
@@ -794,6 +810,22 @@ impl<'a> BackendWriter for GlslBackendWriter<'a> {
         false
     }
     
+    fn needs_select_fn_for_cond_expr(&self) -> bool {
+        false
+    }
+    
+    fn needs_assignment_as_stmt(&self) -> bool {
+        false
+    }
+    
+    fn needs_ptr_for_inout_params(&self) -> bool {
+        false
+    }
+    
+    fn needs_splat_for_builtin_args(&self) -> bool {
+        false
+    }
+    
     fn write_var_decl(
         &self,
         string: &mut String,
@@ -919,6 +951,17 @@ impl<'a> BackendWriter for GlslBackendWriter<'a> {
         true
     }
     
+    fn write_let_decl(&self, string: &mut String, ident: &dyn fmt::Display, ty: &Ty) {
+        self.write_var_decl(string, "", false, false, ident, ty);
+    }
+    
+    fn write_fn_def_ident(&self, string: &mut String, ident: &dyn fmt::Display, return_ty: &Ty) {
+        self.write_var_decl(string, "", false, false, ident, return_ty);
+    }
+    
+    fn write_fn_def_return_ty(&self, _string: &mut String, _return_ty: &Ty) {
+    }
+    
     fn write_call_expr_hidden_args(&self, _string: &mut String, _hidden_args: &BTreeSet<HiddenArgKind >, _sep: &str) {
     }
     
@@ -15,23 +15,27 @@ use {
 
 pub struct MetalGeneratedShader{
     pub mtlsl: String,
+    pub source_map: ShaderSourceMap,
     pub fields_as_uniform_blocks:BTreeMap<Ident, Vec<(usize, Ident) >>   
 }
 
 pub fn generate_shader(draw_shader_def: &DrawShaderDef, const_table:&DrawShaderConstTable, shader_registry: &ShaderRegistry) -> MetalGeneratedShader {
     let mut string = String::new();
+    let mut source_map = ShaderSourceMap::default();
     let fields_as_uniform_blocks = draw_shader_def.fields_as_uniform_blocks();
     DrawShaderGenerator {
         draw_shader_def,
         shader_registry,
         const_table,
         string: &mut string,
+        source_map: &mut source_map,
         fields_as_uniform_blocks: &fields_as_uniform_blocks,
         backend_writer: &MetalBackendWriter {shader_registry, draw_shader_def, const_table}
     }
     .generate_shader();
     MetalGeneratedShader{
         mtlsl:string, 
+        source_map,
         fields_as_uniform_blocks
     }
 }
@@ -40,6 +44,7 @@ struct DrawShaderGenerator<'a> {
     draw_shader_def: &'a DrawShaderDef,
     shader_registry: &'a ShaderRegistry,
     string: &'a mut String,
+    source_map: &'a mut ShaderSourceMap,
     fields_as_uniform_blocks: &'a BTreeMap<Ident, Vec<(usize, Ident) >>,
     backend_writer: &'a dyn BackendWriter,
     const_table: &'a DrawShaderConstTable
@@ -90,11 +95,13 @@ impl<'a> DrawShaderGenerator<'a> {
                     if call_def.callees.borrow().as_ref().unwrap().contains(&fn_iter) {
                         FnDefWithClosureArgsGenerator::generate_fn_def_with_all_closures(
                             &mut self.string,
+                            self.source_map,
                             self.shader_registry,
                             fn_def,
                             call_def,
                             self.backend_writer,
-                            const_table_offset
+                            const_table_offset,
+                            self.const_table.offsets.get(call_iter).cloned()
                         );
                     }
                 }
@@ -106,6 +113,7 @@ impl<'a> DrawShaderGenerator<'a> {
                 shader_registry: self.shader_registry,
                 backend_writer: self.backend_writer,
                 string: self.string,
+                source_map: self.source_map,
             }
             .generate_fn_def()
         }
@@ -313,6 +321,7 @@ impl<'a> DrawShaderGenerator<'a> {
             const_table_offset,
             backend_writer: self.backend_writer,
             string: self.string,
+            source_map: self.source_map,
         }
         .generate_fn_def()
     }
@@ -474,6 +483,22 @@ impl<'a> BackendWriter for MetalBackendWriter<'a> {
         }
     }
     
+    fn needs_select_fn_for_cond_expr(&self) -> bool {
+        false
+    }
+    
+    fn needs_assignment_as_stmt(&self) -> bool {
+        false
+    }
+    
+    fn needs_ptr_for_inout_params(&self) -> bool {
+        false
+    }
+    
+    fn needs_splat_for_builtin_args(&self) -> bool {
+        false
+    }
+    
     fn write_var_decl(
         &self,
         string: &mut String,
@@ -611,6 +636,17 @@ impl<'a> BackendWriter for MetalBackendWriter<'a> {
         true
     }
     
+    fn write_let_decl(&self, string: &mut String, ident: &dyn fmt::Display, ty: &Ty) {
+        self.write_var_decl(string, "", false, false, ident, ty);
+    }
+    
+    fn write_fn_def_ident(&self, string: &mut String, ident: &dyn fmt::Display, return_ty: &Ty) {
+        self.write_var_decl(string, "", false, false, ident, return_ty);
+    }
+    
+    fn write_fn_def_return_ty(&self, _string: &mut String, _return_ty: &Ty) {
+    }
+    
     fn write_call_expr_hidden_args(&self, string: &mut String, hidden_args: &BTreeSet<HiddenArgKind >, sep: &str) {
         let mut sep = sep;
         if self.const_table.table.len()>0 {
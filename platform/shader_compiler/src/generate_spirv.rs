@@ -0,0 +1,1864 @@
+use {
+    std::collections::HashMap,
+    crate::{
+        makepad_live_id::{id, LiveId},
+        makepad_live_compiler::makepad_math::Vec4,
+        shader_ast::*,
+        shader_registry::ShaderRegistry,
+        swizzle::Swizzle,
+        spirv::{
+            self,
+            op,
+            glsl,
+            decoration,
+            storage_class,
+            SpirvWords,
+        },
+    }
+};
+
+// SPIR-V is generated straight from the analysed AST, with the same resource layout as the
+// WGSL backend: a single module with a vertex_main and a fragment_main entrypoint, the const
+// table, the live table and every uniform block are uniform buffers in set 0 holding arrays
+// of vec4s, textures share a sampler in set 1. Stage inputs and varyings are packed in vec4s.
+pub fn generate_shader(draw_shader_def: &DrawShaderDef, const_table: &DrawShaderConstTable, shader_registry: &ShaderRegistry) -> Vec<u32> {
+    let mut generator = DrawShaderGenerator {
+        draw_shader_def,
+        const_table,
+        shader_registry,
+        module: SpirvModule::default(),
+        glsl: 0,
+        fn_ids: HashMap::new(),
+        struct_tys: HashMap::new(),
+        ds_vars: HashMap::new(),
+        live_vars: HashMap::new(),
+        const_table_var: None,
+        live_table_var: None,
+        uniform_table_vars: Vec::new(),
+        sampler_var: None,
+        f: FnState::default(),
+    };
+    generator.generate_shader();
+    generator.module.finish()
+}
+
+// the sections of a module, in the order the spec requires them
+#[derive(Default)]
+struct SpirvModule {
+    bound: u32,
+    header: SpirvWords,
+    entry_points: SpirvWords,
+    execution_modes: SpirvWords,
+    names: SpirvWords,
+    decorations: SpirvWords,
+    globals: SpirvWords,
+    functions: SpirvWords,
+    // non aggregate types and constants have to be unique
+    unique: HashMap<(u16, Vec<u32>), u32>,
+}
+
+impl SpirvModule {
+    fn id(&mut self) -> u32 {
+        self.bound += 1;
+        self.bound
+    }
+
+    fn ty(&mut self, opcode: u16, operands: &[u32]) -> u32 {
+        if let Some(id) = self.unique.get(&(opcode, operands.to_vec())) {
+            return *id
+        }
+        let id = self.id();
+        let mut words = vec![id];
+        words.extend_from_slice(operands);
+        self.globals.inst(opcode, &words);
+        self.unique.insert((opcode, operands.to_vec()), id);
+        id
+    }
+
+    fn constant(&mut self, opcode: u16, ty: u32, operands: &[u32]) -> u32 {
+        let mut key = vec![ty];
+        key.extend_from_slice(operands);
+        if let Some(id) = self.unique.get(&(opcode, key.clone())) {
+            return *id
+        }
+        let id = self.id();
+        let mut words = vec![ty, id];
+        words.extend_from_slice(operands);
+        self.globals.inst(opcode, &words);
+        self.unique.insert((opcode, key), id);
+        id
+    }
+
+    fn name(&mut self, id: u32, name: &str) {
+        self.names.inst_with_string(op::NAME, &[id], name, &[]);
+    }
+
+    fn decorate(&mut self, id: u32, decoration: u32, literals: &[u32]) {
+        let mut words = vec![id, decoration];
+        words.extend_from_slice(literals);
+        self.decorations.inst(op::DECORATE, &words);
+    }
+
+    fn finish(self) -> Vec<u32> {
+        let mut words = vec![spirv::MAGIC, spirv::VERSION_1_0, 0, self.bound + 1, 0];
+        for section in [
+            self.header,
+            self.entry_points,
+            self.execution_modes,
+            self.names,
+            self.decorations,
+            self.globals,
+            self.functions
+        ] {
+            words.extend(section.0);
+        }
+        words
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum FnKey {
+    Plain(FnPtr),
+    WithClosureArgs {fn_ptr: FnPtr, call_ptr: FnPtr, site_index: usize},
+    Closure {call_ptr: FnPtr, closure_def_index: ClosureDefIndex},
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Kind {
+    Bool,
+    Int,
+    Float,
+}
+
+fn kind_of(ty: &Ty) -> Kind {
+    match ty {
+        Ty::Bool | Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => Kind::Bool,
+        Ty::Int | Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 => Kind::Int,
+        _ => Kind::Float
+    }
+}
+
+// the number of components of a scalar or vector, or the number of columns of a matrix
+fn width(ty: &Ty) -> usize {
+    match ty {
+        Ty::Bvec2 | Ty::Ivec2 | Ty::Vec2 | Ty::Mat2 => 2,
+        Ty::Bvec3 | Ty::Ivec3 | Ty::Vec3 | Ty::Mat3 => 3,
+        Ty::Bvec4 | Ty::Ivec4 | Ty::Vec4 | Ty::Mat4 => 4,
+        _ => 1
+    }
+}
+
+fn vec_ty(kind: Kind, n: usize) -> Ty {
+    match (kind, n) {
+        (Kind::Bool, 1) => Ty::Bool,
+        (Kind::Bool, 2) => Ty::Bvec2,
+        (Kind::Bool, 3) => Ty::Bvec3,
+        (Kind::Bool, _) => Ty::Bvec4,
+        (Kind::Int, 1) => Ty::Int,
+        (Kind::Int, 2) => Ty::Ivec2,
+        (Kind::Int, 3) => Ty::Ivec3,
+        (Kind::Int, _) => Ty::Ivec4,
+        (Kind::Float, 1) => Ty::Float,
+        (Kind::Float, 2) => Ty::Vec2,
+        (Kind::Float, 3) => Ty::Vec3,
+        (Kind::Float, _) => Ty::Vec4,
+    }
+}
+
+fn mat_dim(ty: &Ty) -> Option<usize> {
+    match ty {
+        Ty::Mat2 => Some(2),
+        Ty::Mat3 => Some(3),
+        Ty::Mat4 => Some(4),
+        _ => None
+    }
+}
+
+fn vec4_count(slots: usize) -> usize {
+    (slots + 3) >> 2
+}
+
+fn packed_var_sizes(mut packed_vars_size: usize) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let packed_var_size = packed_vars_size.min(4);
+        packed_vars_size -= packed_var_size;
+        if packed_var_size == 0 {None} else {Some(packed_var_size)}
+    })
+}
+
+// closures and draw shaders are not values, calls leave them out
+fn is_value_ty(ty: &Ty) -> bool {
+    !matches!(ty, Ty::Void | Ty::DrawShader(_) | Ty::ClosureDef(_) | Ty::ClosureDecl)
+}
+
+fn const_val_index(expr: &Expr) -> Option<u32> {
+    match expr.const_val.borrow().as_ref() {
+        Some(Some(Val::Int(v))) => Some(*v as u32),
+        Some(Some(Val::Float(v))) => Some(*v as u32),
+        _ => None
+    }
+}
+
+struct Local {
+    ident: Ident,
+    // loop counters are not tagged with a shadow by the analyser
+    shadow: Option<ScopeSymShadow>,
+    ptr: u32,
+    ty: Ty,
+}
+
+enum FnBody<'a> {
+    Block(&'a Block),
+    Expr(&'a Expr),
+}
+
+struct FnParam {
+    ident: Ident,
+    shadow: Option<ScopeSymShadow>,
+    ty: Ty,
+    is_inout: bool,
+}
+
+// the state of the function that is being generated
+#[derive(Default)]
+struct FnState<'a> {
+    // the function whose closure defs and sites the expressions refer to
+    ast_fn: Option<&'a FnDef>,
+    closure_site: Option<(FnPtr, ClosureSite)>,
+    const_table_offset: Option<usize>,
+    // variables have to come first in the entry block
+    vars: SpirvWords,
+    body: SpirvWords,
+    label: u32,
+    terminated: bool,
+    locals: Vec<Local>,
+    closed_over: Vec<(Ident, ScopeSymShadow, u32)>,
+    // merge and continue labels of the enclosing loops
+    loops: Vec<(u32, u32)>,
+}
+
+struct DrawShaderGenerator<'a> {
+    draw_shader_def: &'a DrawShaderDef,
+    const_table: &'a DrawShaderConstTable,
+    shader_registry: &'a ShaderRegistry,
+    module: SpirvModule,
+    glsl: u32,
+    fn_ids: HashMap<FnKey, u32>,
+    struct_tys: HashMap<StructPtr, u32>,
+    ds_vars: HashMap<Ident, u32>,
+    live_vars: HashMap<ValuePtr, u32>,
+    const_table_var: Option<u32>,
+    live_table_var: Option<u32>,
+    uniform_table_vars: Vec<u32>,
+    sampler_var: Option<u32>,
+    f: FnState<'a>,
+}
+
+impl<'a> DrawShaderGenerator<'a> {
+    fn generate_shader(&mut self) {
+        self.module.header.inst(op::CAPABILITY, &[spirv::capability::SHADER]);
+        self.glsl = self.module.id();
+        self.module.header.inst_with_string(op::EXT_INST_IMPORT, &[self.glsl], spirv::GLSL_STD_450, &[]);
+        self.module.header.inst(op::MEMORY_MODEL, &[spirv::addressing_model::LOGICAL, spirv::memory_model::GLSL450]);
+
+        self.generate_decls();
+        self.generate_shader_body();
+        self.generate_vertex_main();
+        self.generate_fragment_main();
+    }
+
+    fn generate_decls(&mut self) {
+        if !self.const_table.table.is_empty() {
+            self.const_table_var = Some(self.uniform_table("const_table", self.const_table.table.len(), 0));
+        }
+        let live_slots: usize = self.draw_shader_def.all_live_refs.borrow().values().map( | ty | ty.slots()).sum();
+        if live_slots > 0 {
+            self.live_table_var = Some(self.uniform_table("live_table", live_slots, 1));
+        }
+        for (binding, (ident, vec)) in self.draw_shader_def.fields_as_uniform_blocks().into_iter().enumerate() {
+            let slots = vec.iter().map( | (index, _) | self.field_ty(*index).slots()).sum();
+            let var = self.uniform_table(&format!("{}_table", ident), slots, binding as u32 + 2);
+            self.uniform_table_vars.push(var);
+        }
+
+        for (value_ptr, ty) in self.draw_shader_def.all_live_refs.borrow().iter() {
+            let ty_id = self.ty_id(ty);
+            let var = self.global_var(storage_class::PRIVATE, ty_id, &format!("{}", value_ptr));
+            self.live_vars.insert(*value_ptr, var);
+        }
+
+        let mut texture_binding = 0;
+        for (index, field) in self.draw_shader_def.fields.iter().enumerate() {
+            let ty = self.field_ty(index);
+            let ty_id = self.ty_id(&ty);
+            let name = format!("ds_{}", field.ident);
+            let var = if let DrawShaderFieldKind::Texture {..} = field.kind {
+                if texture_binding == 0 {
+                    let sampler_ty = self.module.ty(op::TYPE_SAMPLER, &[]);
+                    let sampler = self.global_var(storage_class::UNIFORM_CONSTANT, sampler_ty, "default_sampler");
+                    self.module.decorate(sampler, decoration::DESCRIPTOR_SET, &[1]);
+                    self.module.decorate(sampler, decoration::BINDING, &[0]);
+                    self.sampler_var = Some(sampler);
+                }
+                texture_binding += 1;
+                let var = self.global_var(storage_class::UNIFORM_CONSTANT, ty_id, &name);
+                self.module.decorate(var, decoration::DESCRIPTOR_SET, &[1]);
+                self.module.decorate(var, decoration::BINDING, &[texture_binding]);
+                var
+            }
+            else {
+                self.global_var(storage_class::PRIVATE, ty_id, &name)
+            };
+            self.ds_vars.insert(field.ident, var);
+        }
+    }
+
+    // a uniform buffer holding an array of vec4s
+    fn uniform_table(&mut self, name: &str, slots: usize, binding: u32) -> u32 {
+        let vec4 = self.ty_id(&Ty::Vec4);
+        let len = self.const_i32(vec4_count(slots) as i32);
+        // aggregates aren't deduplicated, each table gets its own decorated types
+        let array = self.module.id();
+        self.module.globals.inst(op::TYPE_ARRAY, &[array, vec4, len]);
+        self.module.decorate(array, decoration::ARRAY_STRIDE, &[16]);
+        let block = self.module.id();
+        self.module.globals.inst(op::TYPE_STRUCT, &[block, array]);
+        self.module.decorate(block, decoration::BLOCK, &[]);
+        self.module.decorations.inst(op::MEMBER_DECORATE, &[block, 0, decoration::OFFSET, 0]);
+        self.module.name(block, &format!("{}_block", name));
+        let var = self.global_var(storage_class::UNIFORM, block, name);
+        self.module.decorate(var, decoration::DESCRIPTOR_SET, &[0]);
+        self.module.decorate(var, decoration::BINDING, &[binding]);
+        var
+    }
+
+    fn global_var(&mut self, storage: u32, ty_id: u32, name: &str) -> u32 {
+        let ptr_ty = self.module.ty(op::TYPE_POINTER, &[storage, ty_id]);
+        let var = self.module.id();
+        self.module.globals.inst(op::VARIABLE, &[ptr_ty, var, storage]);
+        self.module.name(var, name);
+        var
+    }
+
+    fn field_ty(&self, index: usize) -> Ty {
+        self.draw_shader_def.fields[index].ty_expr.ty.borrow().clone().unwrap()
+    }
+
+    fn generate_shader_body(&mut self) {
+        let all_fns = self.draw_shader_def.all_fns.borrow().clone();
+        for fn_ptr in all_fns.iter().rev() {
+            let fn_def = self.shader_registry.all_fns.get(fn_ptr).unwrap();
+            if fn_def.has_closure_args() {
+                for call_ptr in all_fns.iter().rev() {
+                    // any function that depends on us, will have the closures we need
+                    let call_def = self.shader_registry.all_fns.get(call_ptr).unwrap();
+                    if call_def.callees.borrow().as_ref().unwrap().contains(fn_ptr) {
+                        self.generate_fn_def_with_all_closures(fn_def, call_def);
+                    }
+                }
+                continue
+            }
+            let params = fn_def.params.iter().filter_map( | param | self.fn_param(param)).collect();
+            self.generate_fn(
+                FnKey::Plain(fn_def.fn_ptr),
+                &format!("{}_{}", fn_def.fn_ptr, fn_def.ident),
+                FnState {
+                    ast_fn: Some(fn_def),
+                    const_table_offset: self.const_table.offsets.get(fn_ptr).cloned(),
+                    ..FnState::default()
+                },
+                params,
+                Vec::new(),
+                fn_def.return_ty.borrow().as_ref().unwrap(),
+                FnBody::Block(&fn_def.block)
+            );
+        }
+    }
+
+    fn fn_param(&self, param: &Param) -> Option<FnParam> {
+        let ty = param.ty_expr.ty.borrow().clone().unwrap();
+        if param.shadow.get().is_none() || !is_value_ty(&ty) {
+            return None
+        }
+        Some(FnParam {ident: param.ident, shadow: param.shadow.get(), ty, is_inout: param.is_inout})
+    }
+
+    fn generate_fn_def_with_all_closures(&mut self, fn_def: &'a FnDef, call_def: &'a FnDef) {
+        let closure_sites = call_def.closure_sites.borrow().clone().unwrap();
+        for (site_index, closure_site) in closure_sites.into_iter().enumerate() {
+            if closure_site.call_to != fn_def.fn_ptr {
+                continue;
+            }
+            for closure_site_arg in &closure_site.closure_args {
+                self.generate_closure_def(fn_def, call_def, *closure_site_arg);
+            }
+
+            // the closed over syms of all closures come after the regular params
+            let params = fn_def.params.iter().filter_map( | param | self.fn_param(param)).collect();
+            let closed_over = closure_site.all_closed_over.iter().filter( | sym | is_value_ty(&sym.ty)).cloned().collect();
+            self.generate_fn(
+                FnKey::WithClosureArgs {fn_ptr: fn_def.fn_ptr, call_ptr: call_def.fn_ptr, site_index},
+                &format!("site_{}_of_{}_{}", site_index, call_def.fn_ptr, fn_def.ident),
+                FnState {
+                    ast_fn: Some(fn_def),
+                    closure_site: Some((call_def.fn_ptr, closure_site)),
+                    const_table_offset: self.const_table.offsets.get(&fn_def.fn_ptr).cloned(),
+                    ..FnState::default()
+                },
+                params,
+                closed_over,
+                fn_def.return_ty.borrow().as_ref().unwrap(),
+                FnBody::Block(&fn_def.block)
+            );
+        }
+    }
+
+    fn generate_closure_def(&mut self, fn_def: &'a FnDef, call_def: &'a FnDef, closure_site_arg: ClosureSiteArg) {
+        let closure_def = &call_def.closure_defs[closure_site_arg.closure_def_index.0];
+        let fn_param = &fn_def.params[closure_site_arg.param_index];
+        let (decl_params, return_ty) = if let TyExprKind::ClosureDecl {params, return_ty, ..} = &fn_param.ty_expr.kind {
+            (params, return_ty.borrow().clone().unwrap())
+        }
+        else {
+            panic!()
+        };
+        // the param types come from the declaration, the names from the closure
+        let params = decl_params.iter().zip(closure_def.params.iter()).map( | (decl_param, closure_param) | FnParam {
+            ident: closure_param.ident,
+            shadow: closure_param.shadow.get(),
+            ty: decl_param.ty_expr.ty.borrow().clone().unwrap(),
+            is_inout: decl_param.is_inout,
+        }).collect();
+        let closed_over = closure_def.closed_over_syms.borrow().as_ref().unwrap().iter().filter( | sym | is_value_ty(&sym.ty)).cloned().collect();
+        let body = match &closure_def.kind {
+            ClosureDefKind::Expr(expr) => FnBody::Expr(expr),
+            ClosureDefKind::Block(block) => FnBody::Block(block),
+        };
+        self.generate_fn(
+            FnKey::Closure {call_ptr: call_def.fn_ptr, closure_def_index: closure_site_arg.closure_def_index},
+            &format!("closure_{}_in_{}", closure_site_arg.closure_def_index.0, call_def.fn_ptr),
+            FnState {
+                ast_fn: Some(call_def),
+                const_table_offset: self.const_table.offsets.get(&call_def.fn_ptr).cloned(),
+                ..FnState::default()
+            },
+            params,
+            closed_over,
+            &return_ty,
+            body
+        );
+    }
+
+    fn fn_id(&mut self, key: FnKey) -> u32 {
+        if let Some(id) = self.fn_ids.get(&key) {
+            return *id
+        }
+        let id = self.module.id();
+        self.fn_ids.insert(key, id);
+        id
+    }
+
+    // closed over syms are passed by value. inside a closure they are ordinary locals, inside
+    // a function that takes closures they are only passed on to the closures
+    #[allow(clippy::too_many_arguments)]
+    fn generate_fn(&mut self, key: FnKey, name: &str, state: FnState<'a>, params: Vec<FnParam>, closed_over: Vec<Sym>, return_ty: &Ty, body: FnBody<'a>) {
+        self.f = state;
+        let is_closure = matches!(key, FnKey::Closure {..});
+        let fn_id = self.fn_id(key);
+        self.module.name(fn_id, name);
+
+        let return_ty_id = self.ty_id(return_ty);
+        let mut param_ty_ids = Vec::new();
+        for param in &params {
+            let ty_id = self.ty_id(&param.ty);
+            param_ty_ids.push(if param.is_inout {
+                self.module.ty(op::TYPE_POINTER, &[storage_class::FUNCTION, ty_id])
+            } else {
+                ty_id
+            });
+        }
+        for sym in &closed_over {
+            param_ty_ids.push(self.ty_id(&sym.ty));
+        }
+        let mut fn_ty_operands = vec![return_ty_id];
+        fn_ty_operands.extend(param_ty_ids.iter().cloned());
+        let fn_ty = self.module.ty(op::TYPE_FUNCTION, &fn_ty_operands);
+
+        let mut header = SpirvWords::default();
+        header.inst(op::FUNCTION, &[return_ty_id, fn_id, 0, fn_ty]);
+        let mut param_ids = Vec::new();
+        for ty_id in param_ty_ids {
+            let param_id = self.module.id();
+            header.inst(op::FUNCTION_PARAMETER, &[ty_id, param_id]);
+            param_ids.push(param_id);
+        }
+        let entry = self.module.id();
+        self.f.label = entry;
+
+        let param_count = params.len();
+        for (param, param_id) in params.into_iter().zip(param_ids.iter()) {
+            let ptr = if param.is_inout {
+                *param_id
+            }
+            else {
+                let ptr = self.local_var(&param.ty);
+                self.store(ptr, *param_id);
+                ptr
+            };
+            if let Some(shadow) = param.shadow {
+                self.module.name(ptr, &format!("var_{}_{}", param.ident, shadow.0));
+            }
+            self.f.locals.push(Local {ident: param.ident, shadow: param.shadow, ptr, ty: param.ty});
+        }
+        for (sym, param_id) in closed_over.into_iter().zip(param_ids[param_count..].iter()) {
+            if is_closure {
+                let ptr = self.local_var(&sym.ty);
+                self.store(ptr, *param_id);
+                self.f.locals.push(Local {ident: sym.ident, shadow: Some(sym.shadow), ptr, ty: sym.ty});
+            }
+            else {
+                self.f.closed_over.push((sym.ident, sym.shadow, *param_id));
+            }
+        }
+
+        match body {
+            FnBody::Block(block) => self.gen_block(block),
+            FnBody::Expr(expr) => {
+                let value = self.gen_expr(expr);
+                self.emit(op::RETURN_VALUE, &[value]);
+                self.f.terminated = true;
+            }
+        }
+        if !self.f.terminated {
+            if let Ty::Void = return_ty {
+                self.emit(op::RETURN, &[]);
+            }
+            else {
+                self.emit(op::UNREACHABLE, &[]);
+            }
+        }
+        self.finish_fn(header, entry);
+    }
+
+    fn finish_fn(&mut self, header: SpirvWords, entry: u32) {
+        let f = std::mem::take(&mut self.f);
+        self.module.functions.0.extend(header.0);
+        self.module.functions.inst(op::LABEL, &[entry]);
+        self.module.functions.0.extend(f.vars.0);
+        self.module.functions.0.extend(f.body.0);
+        self.module.functions.inst(op::FUNCTION_END, &[]);
+    }
+}
+
+// stage entrypoints, they move the uniform tables and packed stage inputs into the
+// private vars the shader functions read
+impl<'a> DrawShaderGenerator<'a> {
+    fn generate_vertex_main(&mut self) {
+        let geometry_fields = self.field_indices( | kind | matches!(kind, DrawShaderFieldKind::Geometry {..}));
+        let instance_fields = self.field_indices( | kind | matches!(kind, DrawShaderFieldKind::Instance {..}));
+        let varying_fields = self.varying_field_indices();
+
+        let geometry_inputs = self.stage_vars(storage_class::INPUT, "packed_geometry", self.fields_slots(&geometry_fields), 0);
+        let instance_inputs = self.stage_vars(storage_class::INPUT, "packed_instance", self.fields_slots(&instance_fields), geometry_inputs.len());
+        let vec4 = self.ty_id(&Ty::Vec4);
+        let position = self.global_var(storage_class::OUTPUT, vec4, "position");
+        self.module.decorate(position, decoration::BUILT_IN, &[spirv::built_in::POSITION]);
+        let varying_outputs = self.stage_vars(storage_class::OUTPUT, "packed_varying", self.fields_slots(&varying_fields), 0);
+
+        let (fn_id, entry) = self.begin_entry_point();
+        self.generate_uniform_block_unpack();
+        self.generate_live_unpack();
+        self.unpack_fields(&geometry_inputs, &geometry_fields);
+        self.unpack_fields(&instance_inputs, &instance_fields);
+
+        let vertex_def = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, Ident(id!(vertex))).unwrap();
+        let vertex_fn = self.fn_id(FnKey::Plain(vertex_def.fn_ptr));
+        let value = self.op(op::FUNCTION_CALL, &Ty::Vec4, &[vertex_fn]);
+        self.store(position, value);
+        self.pack_fields(&varying_outputs, &varying_fields);
+
+        let mut interface: Vec<u32> = geometry_inputs.iter().chain(instance_inputs.iter()).map( | (var, _) | *var).collect();
+        interface.push(position);
+        interface.extend(varying_outputs.iter().map( | (var, _) | *var));
+        self.end_entry_point(fn_id, entry, "vertex_main", spirv::execution_model::VERTEX, &interface);
+    }
+
+    fn generate_fragment_main(&mut self) {
+        let varying_fields = self.varying_field_indices();
+        let varying_inputs = self.stage_vars(storage_class::INPUT, "packed_varying", self.fields_slots(&varying_fields), 0);
+        let vec4 = self.ty_id(&Ty::Vec4);
+        let color = self.global_var(storage_class::OUTPUT, vec4, "color");
+        self.module.decorate(color, decoration::LOCATION, &[0]);
+
+        let (fn_id, entry) = self.begin_entry_point();
+        self.generate_uniform_block_unpack();
+        self.generate_live_unpack();
+        self.unpack_fields(&varying_inputs, &varying_fields);
+
+        let pixel_def = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, Ident(id!(pixel))).unwrap();
+        let pixel_fn = self.fn_id(FnKey::Plain(pixel_def.fn_ptr));
+        let value = self.op(op::FUNCTION_CALL, &Ty::Vec4, &[pixel_fn]);
+        self.store(color, value);
+
+        let mut interface: Vec<u32> = varying_inputs.iter().map( | (var, _) | *var).collect();
+        interface.push(color);
+        self.end_entry_point(fn_id, entry, "fragment_main", spirv::execution_model::FRAGMENT, &interface);
+        self.module.execution_modes.inst(op::EXECUTION_MODE, &[fn_id, spirv::execution_mode::ORIGIN_UPPER_LEFT]);
+    }
+
+    fn begin_entry_point(&mut self) -> (u32, u32) {
+        self.f = FnState::default();
+        let fn_id = self.module.id();
+        let entry = self.module.id();
+        self.f.label = entry;
+        (fn_id, entry)
+    }
+
+    fn end_entry_point(&mut self, fn_id: u32, entry: u32, name: &str, execution_model: u32, interface: &[u32]) {
+        self.emit(op::RETURN, &[]);
+        let void = self.ty_id(&Ty::Void);
+        let fn_ty = self.module.ty(op::TYPE_FUNCTION, &[void]);
+        let mut header = SpirvWords::default();
+        header.inst(op::FUNCTION, &[void, fn_id, 0, fn_ty]);
+        self.finish_fn(header, entry);
+        self.module.name(fn_id, name);
+        self.module.entry_points.inst_with_string(op::ENTRY_POINT, &[execution_model, fn_id], name, interface);
+    }
+
+    fn field_indices(&self, filter: impl Fn(&DrawShaderFieldKind) -> bool) -> Vec<usize> {
+        self.draw_shader_def.fields.iter().enumerate().filter( | (_, field) | filter(&field.kind)).map( | (index, _) | index).collect()
+    }
+
+    // varyings, and the geometries and instances the pixel shader reads
+    fn varying_field_indices(&self) -> Vec<usize> {
+        self.field_indices( | kind | match kind {
+            DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} => is_used_in_pixel_shader.get(),
+            DrawShaderFieldKind::Instance {is_used_in_pixel_shader, ..} => is_used_in_pixel_shader.get(),
+            DrawShaderFieldKind::Varying {..} => true,
+            _ => false
+        })
+    }
+
+    fn fields_slots(&self, fields: &[usize]) -> usize {
+        fields.iter().map( | index | self.field_ty(*index).slots()).sum()
+    }
+
+    // packed stage inputs or outputs at sequential locations, with their sizes
+    fn stage_vars(&mut self, storage: u32, name: &str, slots: usize, first_location: usize) -> Vec<(u32, usize)> {
+        let mut vars = Vec::new();
+        for (index, size) in packed_var_sizes(slots).enumerate() {
+            let ty_id = self.ty_id(&vec_ty(Kind::Float, size));
+            let var = self.global_var(storage, ty_id, &format!("{}_{}", name, index));
+            self.module.decorate(var, decoration::LOCATION, &[(first_location + index) as u32]);
+            vars.push((var, size));
+        }
+        vars
+    }
+
+    fn unpack_fields(&mut self, inputs: &[(u32, usize)], fields: &[usize]) {
+        let mut loaded = Vec::new();
+        for (var, size) in inputs {
+            loaded.push(self.load(&vec_ty(Kind::Float, *size), *var));
+        }
+        let mut slot = 0;
+        for index in fields {
+            let ty = self.field_ty(*index);
+            let mut comps = Vec::new();
+            for _ in 0..ty.slots() {
+                let (value, size) = (loaded[slot >> 2], inputs[slot >> 2].1);
+                comps.push(if size == 1 {value} else {self.extract(&Ty::Float, value, &[(slot & 3) as u32])});
+                slot += 1;
+            }
+            let value = self.value_from_floats(&ty, &comps);
+            let var = self.ds_vars[&self.draw_shader_def.fields[*index].ident];
+            self.store(var, value);
+        }
+    }
+
+    fn pack_fields(&mut self, outputs: &[(u32, usize)], fields: &[usize]) {
+        let mut floats = Vec::new();
+        for index in fields {
+            let ty = self.field_ty(*index);
+            let var = self.ds_vars[&self.draw_shader_def.fields[*index].ident];
+            let value = self.load(&ty, var);
+            floats.extend(self.floats_from_value(&ty, value));
+        }
+        for (index, (var, size)) in outputs.iter().enumerate() {
+            let comps = &floats[index * 4..index * 4 + size];
+            let value = if *size == 1 {comps[0]} else {self.construct(&vec_ty(Kind::Float, *size), comps)};
+            self.store(*var, value);
+        }
+    }
+
+    fn generate_uniform_block_unpack(&mut self) {
+        for (block_index, (_, vec)) in self.draw_shader_def.fields_as_uniform_blocks().into_iter().enumerate() {
+            let table = self.uniform_table_vars[block_index];
+            let mut slots = 0;
+            for (index, _) in vec {
+                let ty = self.field_ty(index);
+                let value = self.unpack_table_value(table, slots, &ty);
+                let var = self.ds_vars[&self.draw_shader_def.fields[index].ident];
+                self.store(var, value);
+                slots += ty.slots();
+            }
+        }
+    }
+
+    fn generate_live_unpack(&mut self) {
+        let mut slots = 0;
+        let all_live_refs = self.draw_shader_def.all_live_refs.borrow().clone();
+        for (value_ptr, ty) in all_live_refs.iter() {
+            let value = self.unpack_table_value(self.live_table_var.unwrap(), slots, ty);
+            self.store(self.live_vars[value_ptr], value);
+            slots += ty.slots();
+        }
+    }
+
+    fn unpack_table_value(&mut self, table: u32, first_slot: usize, ty: &Ty) -> u32 {
+        let mut comps = Vec::new();
+        for slot in first_slot..first_slot + ty.slots() {
+            comps.push(self.load_table_slot(table, slot));
+        }
+        self.value_from_floats(ty, &comps)
+    }
+
+    fn load_table_slot(&mut self, table: u32, slot: usize) -> u32 {
+        let ptr_ty = self.ptr_ty(storage_class::UNIFORM, &Ty::Float);
+        let member = self.const_i32(0);
+        let index = self.const_i32((slot >> 2) as i32);
+        let comp = self.const_i32((slot & 3) as i32);
+        let ptr = self.op_id(op::ACCESS_CHAIN, ptr_ty, &[table, member, index, comp]);
+        self.load(&Ty::Float, ptr)
+    }
+
+    // builds a value from float slots, bools are > 0.5 like in the other backends
+    fn value_from_floats(&mut self, ty: &Ty, comps: &[u32]) -> u32 {
+        match ty {
+            Ty::Array {elem_ty, len} => {
+                let elem_slots = elem_ty.slots();
+                let mut elems = Vec::new();
+                for index in 0..*len {
+                    elems.push(self.value_from_floats(elem_ty, &comps[index * elem_slots..(index + 1) * elem_slots]));
+                }
+                self.construct(ty, &elems)
+            }
+            _ if ty.is_matrix() => {
+                let n = width(ty);
+                let mut cols = Vec::new();
+                for col in 0..n {
+                    cols.push(self.construct(&vec_ty(Kind::Float, n), &comps[col * n..(col + 1) * n]));
+                }
+                self.construct(ty, &cols)
+            }
+            _ => {
+                let mut scalars = Vec::new();
+                for comp in &comps[0..width(ty)] {
+                    scalars.push(match kind_of(ty) {
+                        Kind::Bool => {
+                            let half = self.const_f32(0.5);
+                            self.op(op::F_ORD_GREATER_THAN, &Ty::Bool, &[*comp, half])
+                        }
+                        Kind::Int => self.op(op::CONVERT_F_TO_S, &Ty::Int, &[*comp]),
+                        Kind::Float => *comp,
+                    });
+                }
+                if ty.is_vector() {self.construct(ty, &scalars)} else {scalars[0]}
+            }
+        }
+    }
+
+    fn floats_from_value(&mut self, ty: &Ty, value: u32) -> Vec<u32> {
+        match ty {
+            Ty::Array {elem_ty, len} => {
+                let mut floats = Vec::new();
+                for index in 0..*len {
+                    let elem = self.extract(elem_ty, value, &[index as u32]);
+                    floats.extend(self.floats_from_value(elem_ty, elem));
+                }
+                floats
+            }
+            _ => {
+                let scalar_ty = vec_ty(kind_of(ty), 1);
+                let mut floats = Vec::new();
+                for comp in self.components(value, ty) {
+                    floats.push(self.convert(comp, &scalar_ty, &Ty::Float));
+                }
+                floats
+            }
+        }
+    }
+}
+
+// types, constants and instructions
+impl<'a> DrawShaderGenerator<'a> {
+    fn ty_id(&mut self, ty: &Ty) -> u32 {
+        match ty {
+            Ty::Void => self.module.ty(op::TYPE_VOID, &[]),
+            Ty::Bool => self.module.ty(op::TYPE_BOOL, &[]),
+            Ty::Int => self.module.ty(op::TYPE_INT, &[32, 1]),
+            Ty::Float | Ty::Enum(_) => self.module.ty(op::TYPE_FLOAT, &[32]),
+            Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 |
+            Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 |
+            Ty::Vec2 | Ty::Vec3 | Ty::Vec4 => {
+                let elem = self.ty_id(&vec_ty(kind_of(ty), 1));
+                self.module.ty(op::TYPE_VECTOR, &[elem, width(ty) as u32])
+            }
+            Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                let n = width(ty);
+                let col = self.ty_id(&vec_ty(Kind::Float, n));
+                self.module.ty(op::TYPE_MATRIX, &[col, n as u32])
+            }
+            Ty::Texture2D => {
+                // 2D, not depth, not arrayed, not multisampled, sampled, unknown format
+                let float = self.ty_id(&Ty::Float);
+                self.module.ty(op::TYPE_IMAGE, &[float, 1, 0, 0, 0, 1, 0])
+            }
+            Ty::Array {elem_ty, len} => {
+                let elem = self.ty_id(elem_ty);
+                let len = self.const_i32(*len as i32);
+                self.module.ty(op::TYPE_ARRAY, &[elem, len])
+            }
+            Ty::Struct(struct_ptr) => self.struct_ty(*struct_ptr),
+            _ => panic!("{} has no SPIR-V type", ty)
+        }
+    }
+
+    fn struct_ty(&mut self, struct_ptr: StructPtr) -> u32 {
+        if let Some(id) = self.struct_tys.get(&struct_ptr) {
+            return *id
+        }
+        let struct_def = self.shader_registry.structs.get(&struct_ptr).unwrap();
+        let mut words = Vec::new();
+        for field in &struct_def.fields {
+            words.push(self.ty_id(field.ty_expr.ty.borrow().as_ref().unwrap()));
+        }
+        let id = self.module.id();
+        words.insert(0, id);
+        self.module.globals.inst(op::TYPE_STRUCT, &words);
+        self.module.name(id, &format!("{}", struct_ptr));
+        for (index, field) in struct_def.fields.iter().enumerate() {
+            self.module.names.inst_with_string(op::MEMBER_NAME, &[id, index as u32], &format!("f_{}", field.ident), &[]);
+        }
+        self.struct_tys.insert(struct_ptr, id);
+        id
+    }
+
+    fn struct_field_index(&self, struct_ptr: StructPtr, field_ident: Ident) -> u32 {
+        let struct_def = self.shader_registry.structs.get(&struct_ptr).unwrap();
+        struct_def.fields.iter().position( | field | field.ident == field_ident).unwrap() as u32
+    }
+
+    fn ptr_ty(&mut self, storage: u32, ty: &Ty) -> u32 {
+        let ty_id = self.ty_id(ty);
+        self.module.ty(op::TYPE_POINTER, &[storage, ty_id])
+    }
+
+    fn const_i32(&mut self, v: i32) -> u32 {
+        let ty = self.ty_id(&Ty::Int);
+        self.module.constant(op::CONSTANT, ty, &[v as u32])
+    }
+
+    fn const_f32(&mut self, v: f32) -> u32 {
+        let ty = self.ty_id(&Ty::Float);
+        self.module.constant(op::CONSTANT, ty, &[v.to_bits()])
+    }
+
+    fn const_bool(&mut self, v: bool) -> u32 {
+        let ty = self.ty_id(&Ty::Bool);
+        self.module.constant(if v {op::CONSTANT_TRUE} else {op::CONSTANT_FALSE}, ty, &[])
+    }
+
+    fn const_vec4(&mut self, v: Vec4) -> u32 {
+        let comps = [v.x, v.y, v.z, v.w].iter().map( | v | self.const_f32(*v)).collect::<Vec<_ >> ();
+        let ty = self.ty_id(&Ty::Vec4);
+        self.module.constant(op::CONSTANT_COMPOSITE, ty, &comps)
+    }
+
+    // a constant int or float scalar or vector with all components set to v
+    fn const_splat(&mut self, kind: Kind, n: usize, v: f32) -> u32 {
+        let scalar = match kind {
+            Kind::Int => self.const_i32(v as i32),
+            _ => self.const_f32(v)
+        };
+        if n == 1 {
+            return scalar
+        }
+        let ty = self.ty_id(&vec_ty(kind, n));
+        self.module.constant(op::CONSTANT_COMPOSITE, ty, &vec![scalar; n])
+    }
+
+    fn emit(&mut self, opcode: u16, operands: &[u32]) {
+        self.f.body.inst(opcode, operands);
+    }
+
+    fn op(&mut self, opcode: u16, ty: &Ty, operands: &[u32]) -> u32 {
+        let ty_id = self.ty_id(ty);
+        self.op_id(opcode, ty_id, operands)
+    }
+
+    fn op_id(&mut self, opcode: u16, ty_id: u32, operands: &[u32]) -> u32 {
+        let id = self.module.id();
+        let mut words = vec![ty_id, id];
+        words.extend_from_slice(operands);
+        self.f.body.inst(opcode, &words);
+        id
+    }
+
+    fn ext(&mut self, inst: u32, ty: &Ty, args: &[u32]) -> u32 {
+        let mut operands = vec![self.glsl, inst];
+        operands.extend_from_slice(args);
+        self.op(op::EXT_INST, ty, &operands)
+    }
+
+    fn label(&mut self, label: u32) {
+        self.emit(op::LABEL, &[label]);
+        self.f.label = label;
+        self.f.terminated = false;
+    }
+
+    fn branch(&mut self, target: u32) {
+        if !self.f.terminated {
+            self.emit(op::BRANCH, &[target]);
+            self.f.terminated = true;
+        }
+    }
+
+    // function variables are hoisted into the entry block
+    fn local_var(&mut self, ty: &Ty) -> u32 {
+        let ptr_ty = self.ptr_ty(storage_class::FUNCTION, ty);
+        let id = self.module.id();
+        self.f.vars.inst(op::VARIABLE, &[ptr_ty, id, storage_class::FUNCTION]);
+        id
+    }
+
+    fn load(&mut self, ty: &Ty, ptr: u32) -> u32 {
+        self.op(op::LOAD, ty, &[ptr])
+    }
+
+    fn store(&mut self, ptr: u32, value: u32) {
+        self.emit(op::STORE, &[ptr, value]);
+    }
+
+    fn extract(&mut self, ty: &Ty, value: u32, indices: &[u32]) -> u32 {
+        let mut operands = vec![value];
+        operands.extend_from_slice(indices);
+        self.op(op::COMPOSITE_EXTRACT, ty, &operands)
+    }
+
+    fn construct(&mut self, ty: &Ty, comps: &[u32]) -> u32 {
+        self.op(op::COMPOSITE_CONSTRUCT, ty, comps)
+    }
+
+    fn splat(&mut self, value: u32, ty: &Ty) -> u32 {
+        if ty.is_vector() {
+            self.construct(ty, &vec![value; width(ty)])
+        }
+        else {
+            value
+        }
+    }
+
+    // converts between scalars and vectors of another kind, scalars are splatted to vectors
+    fn convert(&mut self, value: u32, from: &Ty, to: &Ty) -> u32 {
+        if !from.is_vector() && to.is_vector() {
+            let value = self.convert(value, from, &vec_ty(kind_of(to), 1));
+            return self.splat(value, to)
+        }
+        let n = width(to);
+        match (kind_of(from), kind_of(to)) {
+            (Kind::Float, Kind::Int) => self.op(op::CONVERT_F_TO_S, to, &[value]),
+            (Kind::Int, Kind::Float) => self.op(op::CONVERT_S_TO_F, to, &[value]),
+            (Kind::Bool, Kind::Int) | (Kind::Bool, Kind::Float) => {
+                let one = self.const_splat(kind_of(to), n, 1.0);
+                let zero = self.const_splat(kind_of(to), n, 0.0);
+                self.op(op::SELECT, to, &[value, one, zero])
+            }
+            (Kind::Float, Kind::Bool) => {
+                let zero = self.const_splat(Kind::Float, n, 0.0);
+                self.op(op::F_ORD_NOT_EQUAL, to, &[value, zero])
+            }
+            (Kind::Int, Kind::Bool) => {
+                let zero = self.const_splat(Kind::Int, n, 0.0);
+                self.op(op::I_NOT_EQUAL, to, &[value, zero])
+            }
+            _ => value
+        }
+    }
+
+    // the scalar components of a value, matrices column by column
+    fn components(&mut self, value: u32, ty: &Ty) -> Vec<u32> {
+        let mut comps = Vec::new();
+        if let Some(n) = mat_dim(ty) {
+            for col in 0..n {
+                for row in 0..n {
+                    comps.push(self.extract(&Ty::Float, value, &[col as u32, row as u32]));
+                }
+            }
+        }
+        else if ty.is_vector() {
+            let scalar_ty = vec_ty(kind_of(ty), 1);
+            for index in 0..width(ty) {
+                comps.push(self.extract(&scalar_ty, value, &[index as u32]));
+            }
+        }
+        else {
+            comps.push(value);
+        }
+        comps
+    }
+}
+
+fn expr_ty(expr: &Expr) -> Ty {
+    expr.ty.borrow().clone().unwrap()
+}
+
+fn compare_opcode(bin_op: BinOp, kind: Kind) -> u16 {
+    match (bin_op, kind) {
+        (BinOp::Eq, Kind::Bool) => op::LOGICAL_EQUAL,
+        (BinOp::Eq, Kind::Int) => op::I_EQUAL,
+        (BinOp::Eq, Kind::Float) => op::F_ORD_EQUAL,
+        (BinOp::Ne, Kind::Bool) => op::LOGICAL_NOT_EQUAL,
+        (BinOp::Ne, Kind::Int) => op::I_NOT_EQUAL,
+        (BinOp::Ne, Kind::Float) => op::F_ORD_NOT_EQUAL,
+        (BinOp::Lt, Kind::Int) => op::S_LESS_THAN,
+        (BinOp::Lt, _) => op::F_ORD_LESS_THAN,
+        (BinOp::Le, Kind::Int) => op::S_LESS_THAN_EQUAL,
+        (BinOp::Le, _) => op::F_ORD_LESS_THAN_EQUAL,
+        (BinOp::Gt, Kind::Int) => op::S_GREATER_THAN,
+        (BinOp::Gt, _) => op::F_ORD_GREATER_THAN,
+        (BinOp::Ge, Kind::Int) => op::S_GREATER_THAN_EQUAL,
+        (BinOp::Ge, _) => op::F_ORD_GREATER_THAN_EQUAL,
+        _ => panic!("{:?} is not a comparison", bin_op)
+    }
+}
+
+fn arith_opcode(bin_op: BinOp, kind: Kind) -> u16 {
+    match (bin_op, kind) {
+        (BinOp::Add, Kind::Int) => op::I_ADD,
+        (BinOp::Add, _) => op::F_ADD,
+        (BinOp::Sub, Kind::Int) => op::I_SUB,
+        (BinOp::Sub, _) => op::F_SUB,
+        (BinOp::Mul, Kind::Int) => op::I_MUL,
+        (BinOp::Mul, _) => op::F_MUL,
+        (BinOp::Div, Kind::Int) => op::S_DIV,
+        (BinOp::Div, _) => op::F_DIV,
+        _ => panic!("{:?} is not an arithmetic operator", bin_op)
+    }
+}
+
+// statements, with structured control flow
+impl<'a> DrawShaderGenerator<'a> {
+    fn find_local(&self, ident: Ident, shadow: ScopeSymShadow) -> Option<(u32, Ty)> {
+        if let Some(local) = self.f.locals.iter().rev().find( | local | local.ident == ident && local.shadow == Some(shadow)) {
+            return Some((local.ptr, local.ty.clone()))
+        }
+        self.f.locals.iter().rev().find( | local | local.ident == ident && local.shadow.is_none()).map( | local | (local.ptr, local.ty.clone()))
+    }
+
+    fn gen_block(&mut self, block: &'a Block) {
+        let locals_len = self.f.locals.len();
+        for stmt in &block.stmts {
+            // anything after a return, break or continue is dead
+            if self.f.terminated {
+                break;
+            }
+            self.gen_stmt(stmt);
+        }
+        self.f.locals.truncate(locals_len);
+    }
+
+    fn gen_stmt(&mut self, stmt: &'a Stmt) {
+        match stmt {
+            Stmt::Break {..} => {
+                let (merge, _) = *self.f.loops.last().unwrap();
+                self.branch(merge);
+            }
+            Stmt::Continue {..} => {
+                let (_, cont) = *self.f.loops.last().unwrap();
+                self.branch(cont);
+            }
+            Stmt::For {ident, from_expr, to_expr, step_expr, block, ..} => {
+                self.gen_for_stmt(*ident, from_expr, to_expr, step_expr, block)
+            }
+            Stmt::If {expr, block_if_true, block_if_false, ..} => {
+                self.gen_if_stmt(expr, block_if_true, block_if_false.as_deref())
+            }
+            Stmt::Match {expr, matches, ..} => self.gen_match_stmt(expr, matches),
+            Stmt::Let {ty, shadow, ident, expr, ..} => {
+                let ty = ty.borrow().clone().unwrap();
+                if !is_value_ty(&ty) {
+                    return
+                }
+                let ptr = self.local_var(&ty);
+                if let Some(shadow) = shadow.get() {
+                    self.module.name(ptr, &format!("var_{}_{}", ident, shadow.0));
+                }
+                if let Some(expr) = expr {
+                    let value = self.gen_expr(expr);
+                    self.store(ptr, value);
+                }
+                self.f.locals.push(Local {ident: *ident, shadow: shadow.get(), ptr, ty});
+            }
+            Stmt::Return {expr, ..} => {
+                if let Some(expr) = expr {
+                    let value = self.gen_expr(expr);
+                    self.emit(op::RETURN_VALUE, &[value]);
+                }
+                else {
+                    self.emit(op::RETURN, &[]);
+                }
+                self.f.terminated = true;
+            }
+            Stmt::Block {block, ..} => self.gen_block(block),
+            Stmt::Expr {expr, ..} => {
+                self.gen_expr(expr);
+            }
+        }
+    }
+
+    // loop bounds are constants, the counter runs like in the other backends
+    fn gen_for_stmt(&mut self, ident: Ident, from_expr: &Expr, to_expr: &Expr, step_expr: &Option<Expr>, block: &'a Block) {
+        fn const_int(expr: &Expr) -> i32 {
+            match expr.const_val.borrow().as_ref().unwrap().as_ref().unwrap() {
+                Val::Float(v) => *v as i32,
+                val => val.to_int().unwrap()
+            }
+        }
+        let from = const_int(from_expr);
+        let to = const_int(to_expr);
+        let step = if let Some(step_expr) = step_expr {const_int(step_expr)} else if from < to {1} else {-1};
+        let (start, step) = if from <= to {(from, step.abs())} else {(from - 1, -step.abs())};
+
+        let counter = self.local_var(&Ty::Int);
+        self.module.name(counter, &format!("var_{}", ident));
+        let start = self.const_i32(start);
+        self.store(counter, start);
+
+        let header = self.module.id();
+        let cond = self.module.id();
+        let body = self.module.id();
+        let cont = self.module.id();
+        let merge = self.module.id();
+
+        self.branch(header);
+        self.label(header);
+        self.emit(op::LOOP_MERGE, &[merge, cont, 0]);
+        self.branch(cond);
+
+        self.label(cond);
+        let value = self.load(&Ty::Int, counter);
+        let to = self.const_i32(to);
+        let test = self.op(if step > 0 {op::S_LESS_THAN} else {op::S_GREATER_THAN_EQUAL}, &Ty::Bool, &[value, to]);
+        self.emit(op::BRANCH_CONDITIONAL, &[test, body, merge]);
+        self.f.terminated = true;
+
+        self.label(body);
+        let locals_len = self.f.locals.len();
+        self.f.locals.push(Local {ident, shadow: None, ptr: counter, ty: Ty::Int});
+        self.f.loops.push((merge, cont));
+        self.gen_block(block);
+        self.f.loops.pop();
+        self.f.locals.truncate(locals_len);
+        self.branch(cont);
+
+        self.label(cont);
+        let value = self.load(&Ty::Int, counter);
+        let step = self.const_i32(step);
+        let next = self.op(op::I_ADD, &Ty::Int, &[value, step]);
+        self.store(counter, next);
+        self.branch(header);
+
+        self.label(merge);
+    }
+
+    fn gen_if_stmt(&mut self, expr: &'a Expr, block_if_true: &'a Block, block_if_false: Option<&'a Block>) {
+        let cond = self.gen_expr(expr);
+        let if_true = self.module.id();
+        let merge = self.module.id();
+        let if_false = if block_if_false.is_some() {self.module.id()} else {merge};
+        self.emit(op::SELECTION_MERGE, &[merge, 0]);
+        self.emit(op::BRANCH_CONDITIONAL, &[cond, if_true, if_false]);
+        self.f.terminated = true;
+
+        self.label(if_true);
+        self.gen_block(block_if_true);
+        let mut all_terminated = self.f.terminated;
+        self.branch(merge);
+        if let Some(block_if_false) = block_if_false {
+            self.label(if_false);
+            self.gen_block(block_if_false);
+            all_terminated &= self.f.terminated;
+            self.branch(merge);
+        }
+        else {
+            all_terminated = false;
+        }
+
+        self.label(merge);
+        if all_terminated {
+            self.emit(op::UNREACHABLE, &[]);
+            self.f.terminated = true;
+        }
+    }
+
+    fn gen_match_stmt(&mut self, expr: &'a Expr, matches: &'a [Match]) {
+        let value = self.gen_expr(expr);
+        // enums are floats
+        let selector = if kind_of(&expr_ty(expr)) == Kind::Int {
+            value
+        }
+        else {
+            let value = self.ext(glsl::ROUND, &Ty::Float, &[value]);
+            self.op(op::CONVERT_F_TO_S, &Ty::Int, &[value])
+        };
+        let merge = self.module.id();
+        let labels: Vec<u32> = matches.iter().map( | _ | self.module.id()).collect();
+        let mut operands = vec![selector, merge];
+        for (match_item, label) in matches.iter().zip(labels.iter()) {
+            if let Some(enum_value) = match_item.enum_value.get() {
+                operands.push(enum_value as u32);
+                operands.push(*label);
+            }
+        }
+        self.emit(op::SELECTION_MERGE, &[merge, 0]);
+        self.emit(op::SWITCH, &operands);
+        self.f.terminated = true;
+        for (match_item, label) in matches.iter().zip(labels) {
+            self.label(label);
+            self.gen_block(&match_item.block);
+            self.branch(merge);
+        }
+        self.label(merge);
+    }
+}
+
+// expressions
+impl<'a> DrawShaderGenerator<'a> {
+    fn gen_expr(&mut self, expr: &'a Expr) -> u32 {
+        let ty = expr_ty(expr);
+        let const_val = expr.const_val.borrow().clone();
+        if let Some(Some(val)) = const_val {
+            return self.gen_const_val(&val, expr.const_index.get(), &ty)
+        }
+        match &expr.kind {
+            ExprKind::Cond {expr: cond_expr, expr_if_true, expr_if_false, ..} => {
+                self.gen_cond_expr(cond_expr, expr_if_true, expr_if_false, &ty)
+            }
+            ExprKind::Bin {op: bin_op, left_expr, right_expr, ..} => self.gen_bin_expr(*bin_op, left_expr, right_expr, &ty),
+            ExprKind::Un {op: UnOp::Not, expr: operand, ..} => {
+                let value = self.gen_expr(operand);
+                self.op(op::LOGICAL_NOT, &ty, &[value])
+            }
+            ExprKind::Un {op: UnOp::Neg, expr: operand, ..} => {
+                let value = self.gen_expr(operand);
+                self.negate(value, &ty)
+            }
+            ExprKind::Field {expr: base_expr, field_ident, ..} => self.gen_field_expr(base_expr, *field_ident, &ty),
+            ExprKind::Index {expr: base_expr, index_expr, ..} => self.gen_index_expr(base_expr, index_expr, &ty),
+            ExprKind::MethodCall {ident, arg_exprs, closure_site_index, ..} => {
+                match expr_ty(&arg_exprs[0]) {
+                    Ty::Struct(struct_ptr) => {
+                        let fn_def = self.shader_registry.struct_method_decl_from_ident(
+                            self.shader_registry.structs.get(&struct_ptr).unwrap(),
+                            *ident
+                        ).unwrap();
+                        self.gen_call(fn_def, arg_exprs, closure_site_index.get())
+                    }
+                    Ty::DrawShader(shader_ptr) => {
+                        let fn_def = self.shader_registry.draw_shader_method_decl_from_ident(
+                            self.shader_registry.draw_shader_defs.get(&shader_ptr).unwrap(),
+                            *ident
+                        ).unwrap();
+                        self.gen_call(fn_def, &arg_exprs[1..], closure_site_index.get())
+                    }
+                    _ => panic!(),
+                }
+            }
+            ExprKind::PlainCall {fn_ptr, arg_exprs, param_index, closure_site_index, ..} => {
+                if let Some(param_index) = param_index.get() {
+                    self.gen_closure_call(param_index, arg_exprs)
+                }
+                else {
+                    let fn_def = self.shader_registry.all_fns.get(&fn_ptr.unwrap()).unwrap();
+                    self.gen_call(fn_def, arg_exprs, closure_site_index.get())
+                }
+            }
+            ExprKind::BuiltinCall {ident, arg_exprs, ..} => self.gen_builtin_call(*ident, arg_exprs, &ty),
+            ExprKind::ClosureDef(_) => 0,
+            ExprKind::ConsCall {ty_lit, arg_exprs, ..} => self.gen_cons_call(ty_lit.to_ty(), arg_exprs),
+            ExprKind::StructCons {struct_ptr, args, ..} => {
+                let struct_def = self.shader_registry.structs.get(struct_ptr).unwrap();
+                let mut values = Vec::new();
+                for field in &struct_def.fields {
+                    let (_, arg_expr) = args.iter().find( | (ident, _) | *ident == field.ident).unwrap();
+                    values.push(self.gen_expr(arg_expr));
+                }
+                self.construct(&ty, &values)
+            }
+            ExprKind::Var {kind, ..} => {
+                if !is_value_ty(&ty) {
+                    return 0
+                }
+                let (ptr, _) = self.gen_var_place(kind.get().unwrap());
+                self.load(&ty, ptr)
+            }
+            ExprKind::Lit {lit, ..} => {
+                let (value, lit_ty) = match lit {
+                    Lit::Bool(v) => (self.const_bool(*v), Ty::Bool),
+                    Lit::Int(v) => (self.const_i32(*v), Ty::Int),
+                    Lit::Float(v) => (self.const_f32(*v), Ty::Float),
+                    Lit::Color(v) => (self.const_vec4(Vec4::from_u32(*v)), Ty::Vec4),
+                };
+                self.convert(value, &lit_ty, &ty)
+            }
+        }
+    }
+
+    // consts with a slot in the const table are read from it, so they can be patched live
+    fn gen_const_val(&mut self, val: &Val, const_index: Option<usize>, ty: &Ty) -> u32 {
+        let (value, val_ty) = match (val, const_index, self.f.const_table_offset) {
+            (Val::Float(_), Some(index), Some(offset)) => {
+                let table = self.const_table_var.unwrap();
+                (self.load_table_slot(table, offset + index), Ty::Float)
+            }
+            (Val::Vec4(_), Some(index), Some(offset)) => {
+                let table = self.const_table_var.unwrap();
+                let mut comps = Vec::new();
+                for slot in 0..4 {
+                    comps.push(self.load_table_slot(table, offset + index + slot));
+                }
+                (self.construct(&Ty::Vec4, &comps), Ty::Vec4)
+            }
+            (Val::Bool(v), _, _) => (self.const_bool(*v), Ty::Bool),
+            (Val::Int(v), _, _) => (self.const_i32(*v), Ty::Int),
+            (Val::Float(v), _, _) => (self.const_f32(*v), Ty::Float),
+            (Val::Vec4(v), _, _) => (self.const_vec4(*v), Ty::Vec4),
+        };
+        self.convert(value, &val_ty, ty)
+    }
+
+    fn gen_cond_expr(&mut self, cond_expr: &'a Expr, expr_if_true: &'a Expr, expr_if_false: &'a Expr, ty: &Ty) -> u32 {
+        let cond = self.gen_expr(cond_expr);
+        let if_true = self.module.id();
+        let if_false = self.module.id();
+        let merge = self.module.id();
+        self.emit(op::SELECTION_MERGE, &[merge, 0]);
+        self.emit(op::BRANCH_CONDITIONAL, &[cond, if_true, if_false]);
+        self.f.terminated = true;
+
+        self.label(if_true);
+        let a = self.gen_expr(expr_if_true);
+        let a_label = self.f.label;
+        self.branch(merge);
+        self.label(if_false);
+        let b = self.gen_expr(expr_if_false);
+        let b_label = self.f.label;
+        self.branch(merge);
+
+        self.label(merge);
+        self.op(op::PHI, ty, &[a, a_label, b, b_label])
+    }
+
+    // the right side is only evaluated when it decides the result
+    fn gen_logical_expr(&mut self, is_and: bool, left_expr: &'a Expr, right_expr: &'a Expr) -> u32 {
+        let a = self.gen_expr(left_expr);
+        let a_label = self.f.label;
+        let rhs = self.module.id();
+        let merge = self.module.id();
+        self.emit(op::SELECTION_MERGE, &[merge, 0]);
+        if is_and {
+            self.emit(op::BRANCH_CONDITIONAL, &[a, rhs, merge]);
+        }
+        else {
+            self.emit(op::BRANCH_CONDITIONAL, &[a, merge, rhs]);
+        }
+        self.f.terminated = true;
+
+        self.label(rhs);
+        let b = self.gen_expr(right_expr);
+        let b_label = self.f.label;
+        self.branch(merge);
+
+        self.label(merge);
+        self.op(op::PHI, &Ty::Bool, &[a, a_label, b, b_label])
+    }
+
+    fn gen_bin_expr(&mut self, bin_op: BinOp, left_expr: &'a Expr, right_expr: &'a Expr, ty: &Ty) -> u32 {
+        let left_ty = expr_ty(left_expr);
+        let right_ty = expr_ty(right_expr);
+        let arith_op = match bin_op {
+            BinOp::Assign => {
+                let value = self.gen_expr(right_expr);
+                self.assign(left_expr, value);
+                return value
+            }
+            BinOp::Or => return self.gen_logical_expr(false, left_expr, right_expr),
+            BinOp::And => return self.gen_logical_expr(true, left_expr, right_expr),
+            BinOp::Eq | BinOp::Ne => {
+                let a = self.gen_expr(left_expr);
+                let b = self.gen_expr(right_expr);
+                return self.equal(a, b, &left_ty, matches!(bin_op, BinOp::Eq))
+            }
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                let a = self.gen_expr(left_expr);
+                let b = self.gen_expr(right_expr);
+                return self.op(compare_opcode(bin_op, kind_of(&left_ty)), ty, &[a, b])
+            }
+            BinOp::AddAssign => BinOp::Add,
+            BinOp::SubAssign => BinOp::Sub,
+            BinOp::MulAssign => BinOp::Mul,
+            BinOp::DivAssign => BinOp::Div,
+            _ => bin_op
+        };
+        let b = self.gen_expr(right_expr);
+        let a = self.gen_expr(left_expr);
+        let value = self.arith(arith_op, a, &left_ty, b, &right_ty);
+        if !matches!(bin_op, BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div) {
+            self.assign(left_expr, value);
+        }
+        value
+    }
+
+    fn arith(&mut self, bin_op: BinOp, a: u32, a_ty: &Ty, b: u32, b_ty: &Ty) -> u32 {
+        let is_mul = matches!(bin_op, BinOp::Mul);
+        match (mat_dim(a_ty), mat_dim(b_ty)) {
+            (Some(_), Some(_)) if is_mul => self.op(op::MATRIX_TIMES_MATRIX, a_ty, &[a, b]),
+            (Some(_), None) if is_mul && b_ty.is_vector() => self.op(op::MATRIX_TIMES_VECTOR, b_ty, &[a, b]),
+            (None, Some(_)) if is_mul && a_ty.is_vector() => self.op(op::VECTOR_TIMES_MATRIX, a_ty, &[a, b]),
+            (Some(_), None) if is_mul => self.op(op::MATRIX_TIMES_SCALAR, a_ty, &[a, b]),
+            (None, Some(_)) if is_mul => self.op(op::MATRIX_TIMES_SCALAR, b_ty, &[b, a]),
+            (Some(n), _) | (_, Some(n)) => {
+                // the other matrix operators work column by column
+                let mat_ty = if mat_dim(a_ty).is_some() {a_ty} else {b_ty};
+                let col_ty = vec_ty(Kind::Float, n);
+                let mut cols = Vec::new();
+                for col in 0..n {
+                    let a_col = if mat_dim(a_ty).is_some() {self.extract(&col_ty, a, &[col as u32])} else {self.splat(a, &col_ty)};
+                    let b_col = if mat_dim(b_ty).is_some() {self.extract(&col_ty, b, &[col as u32])} else {self.splat(b, &col_ty)};
+                    cols.push(self.op(arith_opcode(bin_op, Kind::Float), &col_ty, &[a_col, b_col]));
+                }
+                self.construct(mat_ty, &cols)
+            }
+            (None, None) => {
+                let is_float = kind_of(a_ty) == Kind::Float;
+                if is_mul && is_float && a_ty.is_vector() && !b_ty.is_vector() {
+                    return self.op(op::VECTOR_TIMES_SCALAR, a_ty, &[a, b])
+                }
+                if is_mul && is_float && !a_ty.is_vector() && b_ty.is_vector() {
+                    return self.op(op::VECTOR_TIMES_SCALAR, b_ty, &[b, a])
+                }
+                let (a, b, ty) = if a_ty.is_vector() && !b_ty.is_vector() {
+                    (a, self.splat(b, a_ty), a_ty)
+                }
+                else if !a_ty.is_vector() && b_ty.is_vector() {
+                    (self.splat(a, b_ty), b, b_ty)
+                }
+                else {
+                    (a, b, a_ty)
+                };
+                self.op(arith_opcode(bin_op, kind_of(ty)), ty, &[a, b])
+            }
+        }
+    }
+
+    fn negate(&mut self, value: u32, ty: &Ty) -> u32 {
+        if let Some(n) = mat_dim(ty) {
+            let col_ty = vec_ty(Kind::Float, n);
+            let mut cols = Vec::new();
+            for col in 0..n {
+                let col = self.extract(&col_ty, value, &[col as u32]);
+                cols.push(self.op(op::F_NEGATE, &col_ty, &[col]));
+            }
+            return self.construct(ty, &cols)
+        }
+        self.op(if kind_of(ty) == Kind::Int {op::S_NEGATE} else {op::F_NEGATE}, ty, &[value])
+    }
+
+    // structs and matrices compare member by member
+    fn equal(&mut self, a: u32, b: u32, ty: &Ty, is_eq: bool) -> u32 {
+        let mut results = Vec::new();
+        if let Ty::Struct(struct_ptr) = ty {
+            let struct_def = self.shader_registry.structs.get(struct_ptr).unwrap();
+            for (index, field) in struct_def.fields.iter().enumerate() {
+                let field_ty = field.ty_expr.ty.borrow().clone().unwrap();
+                let a = self.extract(&field_ty, a, &[index as u32]);
+                let b = self.extract(&field_ty, b, &[index as u32]);
+                results.push(self.equal(a, b, &field_ty, is_eq));
+            }
+        }
+        else if let Some(n) = mat_dim(ty) {
+            let col_ty = vec_ty(Kind::Float, n);
+            for col in 0..n {
+                let a = self.extract(&col_ty, a, &[col as u32]);
+                let b = self.extract(&col_ty, b, &[col as u32]);
+                results.push(self.equal(a, b, &col_ty, is_eq));
+            }
+        }
+        else {
+            let bin_op = if is_eq {BinOp::Eq} else {BinOp::Ne};
+            let result = self.op(compare_opcode(bin_op, kind_of(ty)), &vec_ty(Kind::Bool, width(ty)), &[a, b]);
+            if !ty.is_vector() {
+                return result
+            }
+            return self.op(if is_eq {op::ALL} else {op::ANY}, &Ty::Bool, &[result])
+        }
+        let mut result = results[0];
+        for other in &results[1..] {
+            result = self.op(if is_eq {op::LOGICAL_AND} else {op::LOGICAL_OR}, &Ty::Bool, &[result, *other]);
+        }
+        result
+    }
+
+    fn gen_field_expr(&mut self, base_expr: &'a Expr, field_ident: Ident, ty: &Ty) -> u32 {
+        match expr_ty(base_expr) {
+            Ty::DrawShader(_) => {
+                let var = self.ds_vars[&field_ident];
+                self.load(ty, var)
+            }
+            Ty::Struct(struct_ptr) => {
+                let base = self.gen_expr(base_expr);
+                let index = self.struct_field_index(struct_ptr, field_ident);
+                self.extract(ty, base, &[index])
+            }
+            base_ty => {
+                let base = self.gen_expr(base_expr);
+                let swizzle = Swizzle::parse(field_ident).unwrap();
+                let indices: Vec<u32> = swizzle.iter().map( | index | *index as u32).collect();
+                if !base_ty.is_vector() {
+                    self.splat(base, ty)
+                }
+                else if indices.len() == 1 {
+                    self.extract(ty, base, &indices)
+                }
+                else {
+                    let mut operands = vec![base, base];
+                    operands.extend(indices);
+                    self.op(op::VECTOR_SHUFFLE, ty, &operands)
+                }
+            }
+        }
+    }
+
+    fn gen_index_expr(&mut self, base_expr: &'a Expr, index_expr: &'a Expr, ty: &Ty) -> u32 {
+        let base_ty = expr_ty(base_expr);
+        let base = self.gen_expr(base_expr);
+        if let Some(index) = const_val_index(index_expr) {
+            return self.extract(ty, base, &[index])
+        }
+        // dynamic indexing goes through memory
+        let tmp = self.local_var(&base_ty);
+        self.store(tmp, base);
+        let index = self.gen_index(index_expr);
+        let ptr_ty = self.ptr_ty(storage_class::FUNCTION, ty);
+        let ptr = self.op_id(op::ACCESS_CHAIN, ptr_ty, &[tmp, index]);
+        self.load(ty, ptr)
+    }
+
+    fn gen_index(&mut self, index_expr: &'a Expr) -> u32 {
+        if let Some(index) = const_val_index(index_expr) {
+            return self.const_i32(index as i32)
+        }
+        let value = self.gen_expr(index_expr);
+        if kind_of(&expr_ty(index_expr)) == Kind::Int {
+            value
+        }
+        else {
+            self.op(op::CONVERT_F_TO_S, &Ty::Int, &[value])
+        }
+    }
+
+    fn gen_var_place(&mut self, var_kind: VarKind) -> (u32, u32) {
+        match var_kind {
+            VarKind::Local {ident, shadow} | VarKind::MutLocal {ident, shadow} => {
+                let (ptr, _) = self.find_local(ident, shadow).unwrap_or_else( || panic!("local {} not found", ident));
+                (ptr, storage_class::FUNCTION)
+            }
+            VarKind::LiveValue(value_ptr) => (self.live_vars[&value_ptr], storage_class::PRIVATE),
+        }
+    }
+
+    // a pointer to what an assignable expression refers to, with its storage class
+    fn gen_place(&mut self, expr: &'a Expr) -> (u32, u32) {
+        let ty = expr_ty(expr);
+        let (base_expr, index) = match &expr.kind {
+            ExprKind::Var {kind, ..} => return self.gen_var_place(kind.get().unwrap()),
+            ExprKind::Field {expr: base_expr, field_ident, ..} => {
+                let index = match expr_ty(base_expr) {
+                    Ty::DrawShader(_) => return (self.ds_vars[field_ident], storage_class::PRIVATE),
+                    Ty::Struct(struct_ptr) => self.struct_field_index(struct_ptr, *field_ident),
+                    _ => *Swizzle::parse(*field_ident).unwrap().iter().next().unwrap() as u32,
+                };
+                (base_expr, self.const_i32(index as i32))
+            }
+            ExprKind::Index {expr: base_expr, index_expr, ..} => (base_expr, self.gen_index(index_expr)),
+            _ => panic!("expression is not assignable")
+        };
+        let (base, storage) = self.gen_place(base_expr);
+        let ptr_ty = self.ptr_ty(storage, &ty);
+        (self.op_id(op::ACCESS_CHAIN, ptr_ty, &[base, index]), storage)
+    }
+
+    fn assign(&mut self, expr: &'a Expr, value: u32) {
+        if let ExprKind::Field {expr: base_expr, field_ident, ..} = &expr.kind {
+            let base_ty = expr_ty(base_expr);
+            if base_ty.is_vector() {
+                let swizzle = Swizzle::parse(*field_ident).unwrap();
+                if swizzle.len() > 1 {
+                    // a multi component swizzle writes a shuffle of the old and the new value
+                    let (ptr, _) = self.gen_place(base_expr);
+                    let old = self.load(&base_ty, ptr);
+                    let n = width(&base_ty);
+                    let mut operands = vec![old, value];
+                    for index in 0..n {
+                        operands.push(match swizzle.iter().position( | c | *c == index) {
+                            Some(pos) => (n + pos) as u32,
+                            None => index as u32
+                        });
+                    }
+                    let new = self.op(op::VECTOR_SHUFFLE, &base_ty, &operands);
+                    self.store(ptr, new);
+                    return
+                }
+            }
+        }
+        let (ptr, _) = self.gen_place(expr);
+        self.store(ptr, value);
+    }
+
+    // inout args are passed as pointers to a copy that is written back after the call
+    fn push_arg(&mut self, arg_expr: &'a Expr, is_inout: bool, args: &mut Vec<u32>, write_backs: &mut Vec<(&'a Expr, u32)>) {
+        let value = self.gen_expr(arg_expr);
+        if is_inout {
+            let tmp = self.local_var(&expr_ty(arg_expr));
+            self.store(tmp, value);
+            args.push(tmp);
+            write_backs.push((arg_expr, tmp));
+        }
+        else {
+            args.push(value);
+        }
+    }
+
+    fn write_back(&mut self, write_backs: Vec<(&'a Expr, u32)>) {
+        for (arg_expr, tmp) in write_backs {
+            let value = self.load(&expr_ty(arg_expr), tmp);
+            self.assign(arg_expr, value);
+        }
+    }
+
+    fn gen_call(&mut self, fn_def: &'a FnDef, arg_exprs: &'a [Expr], closure_site_index: Option<usize>) -> u32 {
+        let ast_fn = self.f.ast_fn.unwrap();
+        let key = if let Some(site_index) = closure_site_index {
+            FnKey::WithClosureArgs {fn_ptr: fn_def.fn_ptr, call_ptr: ast_fn.fn_ptr, site_index}
+        }
+        else {
+            FnKey::Plain(fn_def.fn_ptr)
+        };
+        let mut args = vec![self.fn_id(key)];
+        let mut write_backs = Vec::new();
+        let param_offset = fn_def.params.len() - arg_exprs.len();
+        for (arg_expr, param) in arg_exprs.iter().zip(fn_def.params[param_offset..].iter()) {
+            if self.fn_param(param).is_none() {
+                continue;
+            }
+            self.push_arg(arg_expr, param.is_inout, &mut args, &mut write_backs);
+        }
+        if let Some(site_index) = closure_site_index {
+            let closure_site = ast_fn.closure_sites.borrow().as_ref().unwrap()[site_index].clone();
+            for sym in closure_site.all_closed_over.iter().filter( | sym | is_value_ty(&sym.ty)) {
+                let (ptr, ty) = self.find_local(sym.ident, sym.shadow).unwrap_or_else( || panic!("closed over {} not found", sym.ident));
+                args.push(self.load(&ty, ptr));
+            }
+        }
+        let result = self.op(op::FUNCTION_CALL, fn_def.return_ty.borrow().as_ref().unwrap(), &args);
+        self.write_back(write_backs);
+        result
+    }
+
+    fn gen_closure_call(&mut self, param_index: usize, arg_exprs: &'a [Expr]) -> u32 {
+        let (call_ptr, closure_site) = self.f.closure_site.clone().unwrap();
+        let closure_def_index = closure_site.closure_args.iter().find( | arg | arg.param_index == param_index).unwrap().closure_def_index;
+        let call_def = self.shader_registry.all_fns.get(&call_ptr).unwrap();
+        let closure_def = &call_def.closure_defs[closure_def_index.0];
+        let fn_param = &self.f.ast_fn.unwrap().params[param_index];
+        let (decl_params, return_ty) = if let TyExprKind::ClosureDecl {params, return_ty, ..} = &fn_param.ty_expr.kind {
+            (params, return_ty.borrow().clone().unwrap())
+        }
+        else {
+            panic!()
+        };
+
+        let mut args = vec![self.fn_id(FnKey::Closure {call_ptr, closure_def_index})];
+        let mut write_backs = Vec::new();
+        for (arg_expr, decl_param) in arg_exprs.iter().zip(decl_params.iter()) {
+            self.push_arg(arg_expr, decl_param.is_inout, &mut args, &mut write_backs);
+        }
+        for sym in closure_def.closed_over_syms.borrow().as_ref().unwrap().iter().filter( | sym | is_value_ty(&sym.ty)) {
+            let (_, _, value) = self.f.closed_over.iter().find( | (ident, shadow, _) | *ident == sym.ident && *shadow == sym.shadow).unwrap();
+            args.push(*value);
+        }
+        let result = self.op(op::FUNCTION_CALL, &return_ty, &args);
+        self.write_back(write_backs);
+        result
+    }
+
+    fn gen_builtin_call(&mut self, ident: Ident, arg_exprs: &'a [Expr], ty: &Ty) -> u32 {
+        // float args are splatted to a returned vector, refract takes its ratio as a float
+        let splat_ty = if ty.is_vector() && kind_of(ty) == Kind::Float && ident != Ident(id!(refract)) {Some(ty.clone())} else {None};
+        let mut args = Vec::new();
+        let mut arg_tys = Vec::new();
+        for arg_expr in arg_exprs {
+            let mut arg = self.gen_expr(arg_expr);
+            let mut arg_ty = expr_ty(arg_expr);
+            if let (Some(splat_ty), Ty::Float) = (&splat_ty, &arg_ty) {
+                arg = self.splat(arg, splat_ty);
+                arg_ty = splat_ty.clone();
+            }
+            args.push(arg);
+            arg_tys.push(arg_ty);
+        }
+        let is_int = arg_tys.first().is_some_and( | arg_ty | kind_of(arg_ty) == Kind::Int);
+        let inst = match ident.0 {
+            id!(abs) => if is_int {glsl::S_ABS} else {glsl::F_ABS},
+            id!(acos) => glsl::ACOS,
+            id!(asin) => glsl::ASIN,
+            id!(atan) => if args.len() == 2 {glsl::ATAN2} else {glsl::ATAN},
+            id!(ceil) => glsl::CEIL,
+            id!(clamp) => if is_int {glsl::S_CLAMP} else {glsl::F_CLAMP},
+            id!(cos) => glsl::COS,
+            id!(cross) => glsl::CROSS,
+            id!(degrees) => glsl::DEGREES,
+            id!(distance) => glsl::DISTANCE,
+            id!(exp) => glsl::EXP,
+            id!(exp2) => glsl::EXP2,
+            id!(faceforward) => glsl::FACE_FORWARD,
+            id!(floor) => glsl::FLOOR,
+            id!(fract) => glsl::FRACT,
+            id!(inverse) => glsl::MATRIX_INVERSE,
+            id!(inversesqrt) => glsl::INVERSE_SQRT,
+            id!(length) => glsl::LENGTH,
+            id!(log) => glsl::LOG,
+            id!(log2) => glsl::LOG2,
+            id!(max) => if is_int {glsl::S_MAX} else {glsl::F_MAX},
+            id!(min) => if is_int {glsl::S_MIN} else {glsl::F_MIN},
+            id!(mix) => glsl::F_MIX,
+            id!(normalize) => glsl::NORMALIZE,
+            id!(pow) => glsl::POW,
+            id!(radians) => glsl::RADIANS,
+            id!(reflect) => glsl::REFLECT,
+            id!(refract) => glsl::REFRACT,
+            id!(sign) => if is_int {glsl::S_SIGN} else {glsl::F_SIGN},
+            id!(sin) => glsl::SIN,
+            id!(smoothstep) => glsl::SMOOTH_STEP,
+            id!(sqrt) => glsl::SQRT,
+            id!(step) => glsl::STEP,
+            id!(tan) => glsl::TAN,
+            _ => return self.gen_core_builtin(ident, &args, &arg_tys, ty)
+        };
+        self.ext(inst, ty, &args)
+    }
+
+    // the builtins that are core instructions rather than GLSL.std.450 ones
+    fn gen_core_builtin(&mut self, ident: Ident, args: &[u32], arg_tys: &[Ty], ty: &Ty) -> u32 {
+        let compare = | bin_op | compare_opcode(bin_op, kind_of(&arg_tys[0]));
+        match ident.0 {
+            id!(dFdx) => self.op(op::DPDX, ty, args),
+            id!(dFdy) => self.op(op::DPDY, ty, args),
+            id!(dot) => self.op(if arg_tys[0].is_vector() {op::DOT} else {op::F_MUL}, ty, args),
+            id!(mod) => self.op(op::F_MOD, ty, args),
+            id!(equal) => self.op(compare(BinOp::Eq), ty, args),
+            id!(notEqual) => self.op(compare(BinOp::Ne), ty, args),
+            id!(lessThan) => self.op(compare(BinOp::Lt), ty, args),
+            id!(lessThanEqual) => self.op(compare(BinOp::Le), ty, args),
+            id!(greaterThan) => self.op(compare(BinOp::Gt), ty, args),
+            id!(greaterThanEqual) => self.op(compare(BinOp::Ge), ty, args),
+            id!(not) => self.op(op::LOGICAL_NOT, ty, args),
+            id!(all) => self.op(op::ALL, ty, args),
+            id!(any) => self.op(op::ANY, ty, args),
+            id!(matrixCompMult) => {
+                let n = width(ty);
+                let col_ty = vec_ty(Kind::Float, n);
+                let mut cols = Vec::new();
+                for col in 0..n {
+                    let a = self.extract(&col_ty, args[0], &[col as u32]);
+                    let b = self.extract(&col_ty, args[1], &[col as u32]);
+                    cols.push(self.op(op::F_MUL, &col_ty, &[a, b]));
+                }
+                self.construct(ty, &cols)
+            }
+            id!(transpose) => self.op(op::TRANSPOSE, ty, args),
+            id!(sample2d) => {
+                let sampler_ty = self.module.ty(op::TYPE_SAMPLER, &[]);
+                let sampler = self.op_id(op::LOAD, sampler_ty, &[self.sampler_var.unwrap()]);
+                let image_ty = self.ty_id(&Ty::Texture2D);
+                let sampled_image_ty = self.module.ty(op::TYPE_SAMPLED_IMAGE, &[image_ty]);
+                let sampled_image = self.op_id(op::SAMPLED_IMAGE, sampled_image_ty, &[args[0], sampler]);
+                // there are no mipmaps, and implicit lods are not allowed outside of uniform control flow
+                let lod = self.const_f32(0.0);
+                self.op(op::IMAGE_SAMPLE_EXPLICIT_LOD, ty, &[sampled_image, args[1], spirv::image_operands::LOD, lod])
+            }
+            _ => panic!("builtin {} has no SPIR-V counterpart", ident)
+        }
+    }
+
+    fn gen_cons_call(&mut self, ty: Ty, arg_exprs: &'a [Expr]) -> u32 {
+        let mut args = Vec::new();
+        for arg_expr in arg_exprs {
+            args.push((self.gen_expr(arg_expr), expr_ty(arg_expr)));
+        }
+        if let Some(n) = mat_dim(&ty) {
+            let col_ty = vec_ty(Kind::Float, n);
+            let comps = match args.as_slice() {
+                [(arg, arg_ty)] if mat_dim(arg_ty).is_some() => {
+                    // a matrix is resized, the new part comes from the identity
+                    let m = width(arg_ty);
+                    let mut comps = Vec::new();
+                    for col in 0..n {
+                        for row in 0..n {
+                            comps.push(if col < m && row < m {
+                                self.extract(&Ty::Float, *arg, &[col as u32, row as u32])
+                            } else {
+                                self.const_f32(if col == row {1.0} else {0.0})
+                            });
+                        }
+                    }
+                    comps
+                }
+                [(arg, arg_ty)] if !arg_ty.is_vector() => {
+                    // a scalar fills the diagonal
+                    let value = self.convert(*arg, arg_ty, &Ty::Float);
+                    let zero = self.const_f32(0.0);
+                    (0..n * n).map( | index | if index % (n + 1) == 0 {value} else {zero}).collect()
+                }
+                _ => self.flatten(&args, Kind::Float, n * n)
+            };
+            let mut cols = Vec::new();
+            for col in comps.chunks(n) {
+                cols.push(self.construct(&col_ty, col));
+            }
+            return self.construct(&ty, &cols)
+        }
+        if let [(arg, arg_ty)] = args.as_slice() {
+            if !arg_ty.is_vector() || width(arg_ty) == width(&ty) {
+                return self.convert(*arg, arg_ty, &ty)
+            }
+        }
+        let comps = self.flatten(&args, kind_of(&ty), width(&ty));
+        if ty.is_vector() {self.construct(&ty, &comps)} else {comps[0]}
+    }
+
+    // the components of all args converted to one kind, as many as are needed
+    fn flatten(&mut self, args: &[(u32, Ty)], kind: Kind, len: usize) -> Vec<u32> {
+        let scalar_ty = vec_ty(kind, 1);
+        let mut comps = Vec::new();
+        for (arg, arg_ty) in args {
+            let arg_scalar_ty = vec_ty(kind_of(arg_ty), 1);
+            for comp in self.components(*arg, arg_ty) {
+                if comps.len() < len {
+                    comps.push(self.convert(comp, &arg_scalar_ty, &scalar_ty));
+                }
+            }
+        }
+        comps
+    }
+}
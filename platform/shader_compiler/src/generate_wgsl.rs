@@ -0,0 +1,879 @@
+use {
+    std::{
+        fmt,
+        fmt::Write,
+        collections::BTreeSet,
+    },
+    crate::{
+        makepad_live_id::{
+            id,
+            LiveId,
+        },
+        generate::*,
+        shader_ast::*,
+        shader_registry::ShaderRegistry
+    }
+};
+
+// WGSL has a single module for both stages, with a vertex_main and a fragment_main entrypoint.
+// the const table, the live table and every uniform block are uniform buffers in group 0
+// holding arrays of vec4s, textures share a sampler in group 1.
+pub fn generate_shader(draw_shader_def: &DrawShaderDef, const_table: &DrawShaderConstTable, shader_registry: &ShaderRegistry) -> String {
+    let mut string = String::new();
+    let mut source_map = ShaderSourceMap::default();
+    DrawShaderGenerator {
+        draw_shader_def,
+        const_table,
+        shader_registry,
+        string: &mut string,
+        source_map: &mut source_map,
+        backend_writer: &WgslBackendWriter {shader_registry}
+    }
+    .generate_shader();
+    string
+}
+
+struct DrawShaderGenerator<'a> {
+    draw_shader_def: &'a DrawShaderDef,
+    shader_registry: &'a ShaderRegistry,
+    string: &'a mut String,
+    source_map: &'a mut ShaderSourceMap,
+    const_table: &'a DrawShaderConstTable,
+    backend_writer: &'a dyn BackendWriter
+}
+
+impl<'a> DrawShaderGenerator<'a> {
+    fn generate_shader(&mut self) {
+        let packed_geometries_slots = self.compute_packed_geometries_slots();
+        let packed_instances_slots = self.compute_packed_instances_slots();
+        let packed_varyings_slots = self.compute_packed_varyings_slots();
+
+        self.generate_builtin_helpers();
+        self.generate_decls(packed_varyings_slots);
+
+        for field in &self.draw_shader_def.fields {
+            match field.kind {
+                DrawShaderFieldKind::Geometry {..} |
+                DrawShaderFieldKind::Instance {..} |
+                DrawShaderFieldKind::Varying {..} => {
+                    write!(self.string, "var<private> ").unwrap();
+                    self.write_var_decl(
+                        &DisplayDsIdent(field.ident),
+                        field.ty_expr.ty.borrow().as_ref().unwrap(),
+                    );
+                    writeln!(self.string, ";").unwrap();
+                }
+                _ => {}
+            }
+        }
+        write!(self.string, "\n").unwrap();
+
+        self.generate_shader_body(&self.draw_shader_def.all_fns.borrow(), &self.draw_shader_def.all_structs.borrow());
+
+        self.generate_vertex_main(packed_geometries_slots, packed_instances_slots, packed_varyings_slots);
+        write!(self.string, "\n").unwrap();
+        self.generate_fragment_main(packed_varyings_slots);
+    }
+
+    fn generate_builtin_helpers(&mut self) {
+        let mut builtin_deps = BTreeSet::new();
+        for fn_iter in self.draw_shader_def.all_fns.borrow().iter() {
+            let fn_def = self.shader_registry.all_fns.get(fn_iter).unwrap();
+            builtin_deps.extend(fn_def.builtin_deps.borrow().as_ref().unwrap().iter().cloned());
+        }
+
+        if builtin_deps.contains(&Ident(id!(sample2d))) {
+            // textureSample is only allowed in uniform control flow in the fragment stage
+            writeln!(self.string, "fn sample2d(tex: texture_2d<f32>, pos: vec2<f32>) -> vec4<f32> {{return textureSampleLevel(tex, default_sampler, pos, 0.0);}}").unwrap();
+        }
+
+        for ident in builtin_deps {
+            if !WgslBackendWriter::needs_builtin_helper(ident) {
+                continue;
+            }
+            let builtin = self.shader_registry.builtins.get(&ident).unwrap();
+            // builtins are stored in a hashmap, sort them to keep the output stable
+            let mut param_tys: Vec<_> = builtin.return_tys.iter()
+                .filter( | (param_tys, _) | param_tys.iter().all( | param_ty | *param_ty == param_tys[0]))
+                .map( | (param_tys, return_ty) | (format!("{}", param_tys[0]), param_tys.clone(), return_ty.clone()))
+                .collect();
+            param_tys.sort_by( | a, b | a.0.cmp(&b.0));
+            for (_, param_tys, return_ty) in param_tys {
+                self.generate_builtin_helper(ident, &param_tys, &return_ty);
+            }
+        }
+        write!(self.string, "\n").unwrap();
+    }
+
+    fn generate_builtin_helper(&mut self, ident: Ident, param_tys: &[Ty], return_ty: &Ty) {
+        write!(self.string, "fn {}_{}(", ident, param_tys[0]).unwrap();
+        let mut sep = "";
+        for (index, param_ty) in param_tys.iter().enumerate() {
+            self.backend_writer.write_var_decl(self.string, sep, false, false, &DisplaConstructorArg(index), param_ty);
+            sep = ", ";
+        }
+        write!(self.string, ")").unwrap();
+        self.backend_writer.write_fn_def_return_ty(self.string, return_ty);
+        write!(self.string, " {{return ").unwrap();
+        match ident {
+            Ident(id!(mod)) => write!(self.string, "x0 - x1 * floor(x0 / x1)").unwrap(),
+            Ident(id!(equal)) => write!(self.string, "x0 == x1").unwrap(),
+            Ident(id!(notEqual)) => write!(self.string, "x0 != x1").unwrap(),
+            Ident(id!(lessThan)) => write!(self.string, "x0 < x1").unwrap(),
+            Ident(id!(lessThanEqual)) => write!(self.string, "x0 <= x1").unwrap(),
+            Ident(id!(greaterThan)) => write!(self.string, "x0 > x1").unwrap(),
+            Ident(id!(greaterThanEqual)) => write!(self.string, "x0 >= x1").unwrap(),
+            Ident(id!(not)) => write!(self.string, "!x0").unwrap(),
+            Ident(id!(matrixCompMult)) => {
+                self.write_ty_lit(return_ty.maybe_ty_lit().unwrap());
+                write!(self.string, "(").unwrap();
+                let mut sep = "";
+                for col_index in 0..mat_dim(return_ty) {
+                    write!(self.string, "{}x0[{1}] * x1[{1}]", sep, col_index).unwrap();
+                    sep = ", ";
+                }
+                write!(self.string, ")").unwrap();
+            }
+            _ => panic!(),
+        }
+        writeln!(self.string, ";}}").unwrap();
+    }
+
+    fn generate_shader_body(&mut self, fn_deps: &Vec<FnPtr>, struct_deps: &Vec<StructPtr>) {
+
+        let mut all_constructor_fns = BTreeSet::new();
+
+        for callee in fn_deps.iter().rev() {
+            let decl = self.shader_registry.all_fns.get(callee).unwrap();
+            all_constructor_fns.extend(decl.constructor_fn_deps.borrow().as_ref().unwrap().iter().cloned());
+        }
+
+        for struct_ptr in struct_deps.iter().rev() {
+            let struct_def = self.shader_registry.structs.get(struct_ptr).unwrap();
+            self.generate_struct_def(*struct_ptr, struct_def);
+        }
+
+        for (ty_lit, param_tys) in all_constructor_fns {
+            generate_cons_fn(self.backend_writer, self.string, ty_lit, &param_tys);
+        }
+        write!(self.string, "\n").unwrap();
+        for fn_iter in fn_deps.iter().rev() {
+            let const_table_offset = self.const_table.offsets.get(fn_iter).cloned();
+            let fn_def = self.shader_registry.all_fns.get(fn_iter).unwrap();
+            if fn_def.has_closure_args() {
+                for call_iter in fn_deps.iter().rev() {
+                    // any function that depends on us, will have the closures we need
+                    let call_def = self.shader_registry.all_fns.get(call_iter).unwrap();
+                    if call_def.callees.borrow().as_ref().unwrap().contains(&fn_iter) {
+                        FnDefWithClosureArgsGenerator::generate_fn_def_with_all_closures(
+                            &mut self.string,
+                            self.source_map,
+                            self.shader_registry,
+                            fn_def,
+                            call_def,
+                            self.backend_writer,
+                            const_table_offset,
+                            self.const_table.offsets.get(call_iter).cloned()
+                        );
+                    }
+                }
+                continue
+            }
+            FnDefGenerator {
+                fn_def,
+                const_table_offset,
+                shader_registry: self.shader_registry,
+                backend_writer: self.backend_writer,
+                string: self.string,
+                source_map: self.source_map,
+            }
+            .generate_fn_def();
+            write!(self.string, "\n").unwrap();
+        }
+    }
+
+    fn generate_vertex_main(&mut self, packed_geometries_slots: usize, packed_instances_slots: usize, packed_varyings_slots: usize) {
+        write!(self.string, "@vertex\nfn vertex_main(").unwrap();
+        let mut location = 0;
+        let mut sep = "";
+        for (packed_var_name, packed_vars_size) in [("packed_geometry", packed_geometries_slots), ("packed_instance", packed_instances_slots)] {
+            for (packed_var_index, packed_var_size) in packed_var_sizes(packed_vars_size).enumerate() {
+                write!(self.string, "{}@location({}) {}_{}: ", sep, location, packed_var_name, packed_var_index).unwrap();
+                write_packed_ty(self.string, packed_var_size);
+                location += 1;
+                sep = ", ";
+            }
+        }
+        writeln!(self.string, ") -> Varyings {{").unwrap();
+
+        self.generate_uniform_block_unpack();
+        self.generate_live_unpack();
+
+        let mut geometry_unpacker = VarUnpacker::new(
+            "packed_geometry",
+            packed_geometries_slots,
+            &mut self.string,
+        );
+        for decl in &self.draw_shader_def.fields {
+            match decl.kind {
+                DrawShaderFieldKind::Geometry {..} => {
+                    geometry_unpacker
+                        .unpack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                _ => {}
+            }
+        }
+
+        let mut instance_unpacker = VarUnpacker::new(
+            "packed_instance",
+            packed_instances_slots,
+            &mut self.string,
+        );
+        for decl in &self.draw_shader_def.fields {
+            match decl.kind {
+                DrawShaderFieldKind::Instance {..} => {
+                    instance_unpacker
+                        .unpack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                _ => {}
+            }
+        }
+        write!(self.string, "\n").unwrap();
+        let vertex_def = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, Ident(id!(vertex))).unwrap();
+
+        writeln!(self.string, "    var varyings: Varyings;").unwrap();
+        writeln!(self.string, "    varyings.position = {}();", DisplayFnName(vertex_def.fn_ptr, vertex_def.ident)).unwrap();
+        write!(self.string, "\n").unwrap();
+        let mut varying_packer = VarPacker::new(
+            "varyings.packed_varying",
+            packed_varyings_slots,
+            &mut self.string,
+        );
+        for decl in &self.draw_shader_def.fields {
+            match &decl.kind {
+                DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_packer.pack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Instance {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_packer.pack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Varying {..} => {
+                    varying_packer.pack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                _ => {}
+            }
+        }
+        writeln!(self.string, "    return varyings;").unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_fragment_main(&mut self, packed_varyings_slots: usize) {
+        writeln!(self.string, "@fragment\nfn fragment_main(varyings: Varyings) -> @location(0) vec4<f32> {{").unwrap();
+
+        self.generate_uniform_block_unpack();
+        self.generate_live_unpack();
+
+        let mut varying_unpacker = VarUnpacker::new(
+            "varyings.packed_varying",
+            packed_varyings_slots,
+            &mut self.string,
+        );
+        for decl in &self.draw_shader_def.fields {
+            match &decl.kind {
+                DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_unpacker
+                        .unpack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Instance {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    varying_unpacker
+                        .unpack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                DrawShaderFieldKind::Varying {..} => {
+                    varying_unpacker
+                        .unpack_var(decl.ident, decl.ty_expr.ty.borrow().as_ref().unwrap());
+                }
+                _ => {}
+            }
+        }
+        let pixel_decl = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, Ident(id!(pixel))).unwrap();
+        write!(self.string, "\n").unwrap();
+        writeln!(self.string, "    return {}();", DisplayFnName(pixel_decl.fn_ptr, pixel_decl.ident)).unwrap();
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_uniform_block_unpack(
+        &mut self,
+    ) {
+        for (ident, vec) in self.draw_shader_def.fields_as_uniform_blocks() {
+
+            let mut slots = 0;
+
+            let table = format!("{}_table", ident);
+
+            for (index, _item) in vec {
+                let field = &self.draw_shader_def.fields[index];
+
+                write!(self.string, "    {} = ", &DisplayDsIdent(field.ident)).unwrap();
+
+                let ty_expr = field.ty_expr.ty.borrow();
+
+                self.write_uniform_ty_unpack(ty_expr.as_ref().unwrap(), &table, slots);
+                write!(self.string, ";\n").unwrap();
+                slots += ty_expr.as_ref().unwrap().slots();
+            }
+            write!(self.string, "\n").unwrap();
+        }
+    }
+
+    fn generate_live_unpack(
+        &mut self,
+    ) {
+        let mut slots = 0;
+        for (live_ref, ty) in self.draw_shader_def.all_live_refs.borrow().iter() {
+
+            write!(self.string, "    {} = ", &live_ref).unwrap();
+            self.write_uniform_ty_unpack(ty, "live_table", slots);
+            write!(self.string, ";\n").unwrap();
+            slots += ty.slots();
+        }
+    }
+
+    fn generate_decls(
+        &mut self,
+        packed_varyings_size: usize,
+    ) {
+
+        if self.const_table.table.len()>0 {
+            writeln!(self.string, "@group(0) @binding(0) var<uniform> const_table: array<vec4<f32>, {}>;", vec4_count(self.const_table.table.len())).unwrap();
+        }
+        write!(self.string, "\n").unwrap();
+
+        let live_slots = self.calc_live_slots();
+        if live_slots >0 {
+            writeln!(self.string, "@group(0) @binding(1) var<uniform> live_table: array<vec4<f32>, {}>;", vec4_count(live_slots)).unwrap();
+        }
+
+        for (live_ref, ty) in self.draw_shader_def.all_live_refs.borrow().iter() {
+            write!(self.string, "var<private> ").unwrap();
+            self.write_var_decl(live_ref, ty);
+            writeln!(self.string, ";").unwrap();
+        }
+
+        for (binding, (ident, vec)) in self.draw_shader_def.fields_as_uniform_blocks().into_iter().enumerate() {
+            let mut slots = 0;
+
+            for (index, _item) in &vec {
+                let field = &self.draw_shader_def.fields[*index];
+                slots += field.ty_expr.ty.borrow().as_ref().unwrap().slots();
+            }
+
+            writeln!(self.string, "@group(0) @binding({}) var<uniform> {}_table: array<vec4<f32>, {}>;", binding + 2, ident, vec4_count(slots)).unwrap();
+
+            for (index, _item) in vec {
+                let field = &self.draw_shader_def.fields[index];
+                if let DrawShaderFieldKind::Uniform {..} = &field.kind {
+                    self.generate_uniform_decl(field);
+                }
+                else {
+                    panic!()
+                }
+            }
+            write!(self.string, "\n").unwrap();
+        }
+
+        let mut texture_binding = 0;
+        for decl in &self.draw_shader_def.fields {
+            match decl.kind {
+                DrawShaderFieldKind::Texture {..} => {
+                    if texture_binding == 0 {
+                        writeln!(self.string, "@group(1) @binding(0) var default_sampler: sampler;").unwrap();
+                    }
+                    texture_binding += 1;
+                    write!(self.string, "@group(1) @binding({}) var ", texture_binding).unwrap();
+                    self.write_var_decl(
+                        &DisplayDsIdent(decl.ident),
+                        decl.ty_expr.ty.borrow().as_ref().unwrap(),
+                    );
+                    writeln!(self.string, ";").unwrap();
+                }
+                _ => {}
+            }
+        }
+        write!(self.string, "\n").unwrap();
+
+        writeln!(self.string, "struct Varyings {{").unwrap();
+        writeln!(self.string, "    @builtin(position) position: vec4<f32>,").unwrap();
+        for (packed_var_index, packed_var_size) in packed_var_sizes(packed_varyings_size).enumerate() {
+            write!(self.string, "    @location({0}) packed_varying_{0}: ", packed_var_index).unwrap();
+            write_packed_ty(self.string, packed_var_size);
+            writeln!(self.string, ",").unwrap();
+        }
+        writeln!(self.string, "}}").unwrap();
+        write!(self.string, "\n").unwrap();
+    }
+
+    fn generate_struct_def(&mut self, struct_ptr: StructPtr, struct_def: &StructDef) {
+        write!(self.string, "struct {} {{", struct_ptr).unwrap();
+        if !struct_def.fields.is_empty() {
+            writeln!(self.string).unwrap();
+            for field in &struct_def.fields {
+                write!(self.string, "    ").unwrap();
+                self.write_var_decl(
+                    &DisplayStructField(field.ident),
+                    field.ty_expr.ty.borrow().as_ref().unwrap(),
+                );
+                writeln!(self.string, ",").unwrap();
+            }
+        }
+        writeln!(self.string, "}}").unwrap();
+    }
+
+    fn generate_uniform_decl(&mut self, decl: &DrawShaderFieldDef) {
+        write!(self.string, "var<private> ").unwrap();
+        self.write_var_decl(
+            &DisplayDsIdent(decl.ident),
+            decl.ty_expr.ty.borrow().as_ref().unwrap(),
+        );
+        writeln!(self.string, ";").unwrap();
+    }
+
+    pub fn calc_live_slots(&self) -> usize {
+        let mut slots = 0;
+        for (_, ty) in self.draw_shader_def.all_live_refs.borrow().iter() {
+            slots += ty.slots();
+        }
+        slots
+    }
+
+    fn compute_packed_geometries_slots(&self) -> usize {
+        let mut packed_attributes_size = 0;
+        for field in &self.draw_shader_def.fields {
+            packed_attributes_size += match field.kind {
+                DrawShaderFieldKind::Geometry {..} => field.ty_expr.ty.borrow().as_ref().unwrap().slots(),
+                _ => 0,
+            }
+        }
+        packed_attributes_size
+    }
+
+    fn compute_packed_instances_slots(&self) -> usize {
+        let mut packed_instances_size = 0;
+        for field in &self.draw_shader_def.fields {
+            packed_instances_size += match field.kind {
+                DrawShaderFieldKind::Instance {..} => field.ty_expr.ty.borrow().as_ref().unwrap().slots(),
+                _ => 0,
+            }
+        }
+        packed_instances_size
+    }
+
+    fn compute_packed_varyings_slots(&self) -> usize {
+        let mut packed_varyings_size = 0;
+        for field in &self.draw_shader_def.fields {
+            packed_varyings_size += match &field.kind {
+                DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    field.ty_expr.ty.borrow().as_ref().unwrap().slots()
+                }
+                DrawShaderFieldKind::Instance {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => {
+                    field.ty_expr.ty.borrow().as_ref().unwrap().slots()
+                }
+                DrawShaderFieldKind::Varying {..} => field.ty_expr.ty.borrow().as_ref().unwrap().slots(),
+                _ => 0,
+            }
+        }
+        packed_varyings_size
+    }
+
+    fn write_uniform_ty_unpack(&mut self, ty: &Ty, table: &str, s: usize) {
+        let slot = | index | DisplayTableSlot(table, s + index);
+        match ty {
+            Ty::Bool => write!(self.string, "{} > 0.5", slot(0)).unwrap(),
+            Ty::Int => write!(self.string, "i32({})", slot(0)).unwrap(),
+            Ty::Float | Ty::Enum {..} => write!(self.string, "{}", slot(0)).unwrap(),
+            Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 |
+            Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 |
+            Ty::Vec2 | Ty::Vec3 | Ty::Vec4 |
+            Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                self.write_ty_lit(ty.maybe_ty_lit().unwrap());
+                write!(self.string, "(").unwrap();
+                let mut sep = "";
+                for index in 0..ty.slots() {
+                    match ty {
+                        Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => write!(self.string, "{}{} > 0.5", sep, slot(index)).unwrap(),
+                        Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 => write!(self.string, "{}i32({})", sep, slot(index)).unwrap(),
+                        _ => write!(self.string, "{}{}", sep, slot(index)).unwrap(),
+                    }
+                    sep = ", ";
+                }
+                write!(self.string, ")").unwrap();
+            }
+            _ => panic!("unexpected as initializeable type {:?}", ty),
+        }
+    }
+
+    fn write_var_decl(&mut self, ident: &dyn fmt::Display, ty: &Ty) {
+        self.backend_writer.write_var_decl(&mut self.string, "", false, false, ident, ty);
+    }
+
+    fn write_ty_lit(&mut self, ty_lit: TyLit) {
+        self.backend_writer.write_ty_lit(&mut self.string, ty_lit);
+    }
+}
+
+fn vec4_count(slots: usize) -> usize {
+    (slots + 3) >> 2
+}
+
+fn mat_dim(ty: &Ty) -> usize {
+    match ty {
+        Ty::Mat2 => 2,
+        Ty::Mat3 => 3,
+        Ty::Mat4 => 4,
+        _ => panic!(),
+    }
+}
+
+fn packed_var_sizes(mut packed_vars_size: usize) -> impl Iterator<Item = usize> {
+    std::iter::from_fn(move || {
+        let packed_var_size = packed_vars_size.min(4);
+        packed_vars_size -= packed_var_size;
+        if packed_var_size == 0 {None} else {Some(packed_var_size)}
+    })
+}
+
+fn write_packed_ty(string: &mut String, packed_var_size: usize) {
+    write!(
+        string,
+        "{}",
+        match packed_var_size {
+            1 => "f32",
+            2 => "vec2<f32>",
+            3 => "vec3<f32>",
+            4 => "vec4<f32>",
+            _ => panic!(),
+        }
+    ).unwrap();
+}
+
+// a single component of a var, WGSL can't assign to multi component swizzles
+// so we pack and unpack one component at a time
+struct DisplayComponent<'a>(&'a dyn fmt::Display, &'a Ty, usize);
+impl<'a> fmt::Display for DisplayComponent<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.1 {
+            Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                let dim = mat_dim(self.1);
+                write!(f, "{}[{}][{}]", self.0, self.2 / dim, self.2 % dim)
+            }
+            _ if self.1.slots() > 1 => write!(f, "{}.{}", self.0, ["x", "y", "z", "w"][self.2]),
+            _ => write!(f, "{}", self.0),
+        }
+    }
+}
+
+struct DisplayTableSlot<'a>(&'a str, usize);
+impl<'a> fmt::Display for DisplayTableSlot<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}[{}].{}", self.0, self.1 >> 2, ["x", "y", "z", "w"][self.1 & 3])
+    }
+}
+
+struct VarPacker<'a> {
+    packed_var_name: &'a str,
+    packed_vars_size: usize,
+    packed_var_index: usize,
+    packed_var_size: usize,
+    packed_var_offset: usize,
+    string: &'a mut String,
+}
+
+impl<'a> VarPacker<'a> {
+    fn new(
+        packed_var_name: &'a str,
+        packed_vars_size: usize,
+        string: &'a mut String,
+    ) -> VarPacker<'a> {
+        VarPacker {
+            packed_var_name,
+            packed_vars_size,
+            packed_var_index: 0,
+            packed_var_size: packed_vars_size.min(4),
+            packed_var_offset: 0,
+            string,
+        }
+    }
+
+    fn pack_var(&mut self, ident: Ident, ty: &Ty) {
+        for var_offset in 0..ty.slots() {
+            let packed_var = format!("{}_{}", self.packed_var_name, self.packed_var_index);
+            let packed_ty = if self.packed_var_size > 1 {Ty::Vec4} else {Ty::Float};
+            writeln!(
+                self.string,
+                "    {} = {};",
+                DisplayComponent(&packed_var, &packed_ty, self.packed_var_offset),
+                DisplayComponent(&DisplayDsIdent(ident), ty, var_offset)
+            ).unwrap();
+            self.packed_var_offset += 1;
+            if self.packed_var_offset == self.packed_var_size {
+                self.packed_vars_size -= self.packed_var_size;
+                self.packed_var_index += 1;
+                self.packed_var_size = self.packed_vars_size.min(4);
+                self.packed_var_offset = 0;
+            }
+        }
+    }
+}
+
+struct VarUnpacker<'a> {
+    packed_var_name: &'a str,
+    packed_vars_size: usize,
+    packed_var_index: usize,
+    packed_var_size: usize,
+    packed_var_offset: usize,
+    string: &'a mut String,
+}
+
+impl<'a> VarUnpacker<'a> {
+    fn new(
+        packed_var_name: &'a str,
+        packed_vars_size: usize,
+        string: &'a mut String,
+    ) -> VarUnpacker<'a> {
+        VarUnpacker {
+            packed_var_name,
+            packed_vars_size,
+            packed_var_index: 0,
+            packed_var_size: packed_vars_size.min(4),
+            packed_var_offset: 0,
+            string,
+        }
+    }
+
+    fn unpack_var(&mut self, ident: Ident, ty: &Ty) {
+        for var_offset in 0..ty.slots() {
+            let packed_var = format!("{}_{}", self.packed_var_name, self.packed_var_index);
+            let packed_ty = if self.packed_var_size > 1 {Ty::Vec4} else {Ty::Float};
+            writeln!(
+                self.string,
+                "    {} = {};",
+                DisplayComponent(&DisplayDsIdent(ident), ty, var_offset),
+                DisplayComponent(&packed_var, &packed_ty, self.packed_var_offset)
+            ).unwrap();
+            self.packed_var_offset += 1;
+            if self.packed_var_offset == self.packed_var_size {
+                self.packed_vars_size -= self.packed_var_size;
+                self.packed_var_index += 1;
+                self.packed_var_size = self.packed_vars_size.min(4);
+                self.packed_var_offset = 0;
+            }
+        }
+    }
+}
+
+struct WgslBackendWriter<'a> {
+    pub shader_registry: &'a ShaderRegistry,
+}
+
+impl<'a> WgslBackendWriter<'a> {
+    // these GLSL builtins have no WGSL counterpart, we emit a helper fn per argument type
+    fn needs_builtin_helper(ident: Ident) -> bool {
+        match ident {
+            Ident(id!(mod)) |
+            Ident(id!(equal)) |
+            Ident(id!(notEqual)) |
+            Ident(id!(lessThan)) |
+            Ident(id!(lessThanEqual)) |
+            Ident(id!(greaterThan)) |
+            Ident(id!(greaterThanEqual)) |
+            Ident(id!(not)) |
+            Ident(id!(matrixCompMult)) => true,
+            _ => false
+        }
+    }
+
+    fn write_ty(&self, string: &mut String, ty: &Ty) -> bool {
+        match *ty {
+            Ty::Void | Ty::DrawShader(_) | Ty::ClosureDef {..} | Ty::ClosureDecl => {
+                return false
+            }
+            Ty::Array {ref elem_ty, len} => {
+                write!(string, "array<").unwrap();
+                self.write_ty(string, elem_ty);
+                write!(string, ", {}>", len).unwrap();
+            }
+            Ty::Struct(ptr) => {
+                write!(string, "{}", ptr).unwrap();
+            }
+            Ty::Enum(_) => {
+                write!(string, "f32").unwrap();
+            }
+            Ty::Texture2D => {
+                self.write_ty_lit(string, TyLit::Texture2D);
+            }
+            _ => {
+                self.write_ty_lit(string, ty.maybe_ty_lit().unwrap());
+            }
+        }
+        true
+    }
+}
+
+impl<'a> BackendWriter for WgslBackendWriter<'a> {
+
+    fn needs_cstyle_struct_cons(&self) -> bool {
+        true
+    }
+
+    fn enum_is_float(&self) -> bool {
+        true
+    }
+
+    fn needs_mul_fn_for_matrix_multiplication(&self) -> bool {
+        false
+    }
+
+    fn needs_unpack_for_matrix_multiplication(&self) -> bool {
+        false
+    }
+
+    fn const_table_is_vec4(&self) -> bool {
+        true
+    }
+
+    fn use_cons_fn(&self, what: &str) -> bool {
+        // WGSL has no matrix from matrix constructors
+        match what {
+            "consfn_mat2_mat3" => true,
+            "consfn_mat2_mat4" => true,
+            "consfn_mat3_mat2" => true,
+            "consfn_mat3_mat4" => true,
+            "consfn_mat4_mat2" => true,
+            "consfn_mat4_mat3" => true,
+            _ => false
+        }
+    }
+
+    fn needs_select_fn_for_cond_expr(&self) -> bool {
+        true
+    }
+
+    fn needs_assignment_as_stmt(&self) -> bool {
+        true
+    }
+
+    fn needs_ptr_for_inout_params(&self) -> bool {
+        true
+    }
+
+    fn needs_splat_for_builtin_args(&self) -> bool {
+        true
+    }
+
+    fn write_var_decl(
+        &self,
+        string: &mut String,
+        sep: &'static str,
+        is_inout: bool,
+        _is_packed: bool,
+        ident: &dyn fmt::Display,
+        ty: &Ty,
+    ) -> bool {
+        let mut ty_string = String::new();
+        if !self.write_ty(&mut ty_string, ty) {
+            return false
+        }
+        if is_inout {
+            write!(string, "{}{}: ptr<function, {}>", sep, ident, ty_string).unwrap();
+        }
+        else {
+            write!(string, "{}{}: {}", sep, ident, ty_string).unwrap();
+        }
+        true
+    }
+
+    fn write_let_decl(&self, string: &mut String, ident: &dyn fmt::Display, ty: &Ty) {
+        write!(string, "var ").unwrap();
+        self.write_var_decl(string, "", false, false, ident, ty);
+    }
+
+    fn write_fn_def_ident(&self, string: &mut String, ident: &dyn fmt::Display, _return_ty: &Ty) {
+        write!(string, "fn {}", ident).unwrap();
+    }
+
+    fn write_fn_def_return_ty(&self, string: &mut String, return_ty: &Ty) {
+        let mut ty_string = String::new();
+        if self.write_ty(&mut ty_string, return_ty) {
+            write!(string, " -> {}", ty_string).unwrap();
+        }
+    }
+
+    fn write_call_expr_hidden_args(&self, _string: &mut String, _hidden_args: &BTreeSet<HiddenArgKind >, _sep: &str) {
+    }
+
+    fn write_fn_def_hidden_params(&self, _string: &mut String, _hidden_args: &BTreeSet<HiddenArgKind >, _sep: &str) {
+    }
+
+    fn generate_live_value_prefix(&self, _string: &mut String) {
+    }
+
+    fn generate_draw_shader_field_expr(&self, string: &mut String, field_ident: Ident, _ty: &Ty) {
+        write!(string, "{}", &DisplayDsIdent(field_ident)).unwrap();
+    }
+
+    fn write_ty_lit(&self, string: &mut String, ty_lit: TyLit) {
+        write!(
+            string,
+            "{}",
+            match ty_lit {
+                TyLit::Bool => "bool",
+                TyLit::Int => "i32",
+                TyLit::Float => "f32",
+                TyLit::Bvec2 => "vec2<bool>",
+                TyLit::Bvec3 => "vec3<bool>",
+                TyLit::Bvec4 => "vec4<bool>",
+                TyLit::Ivec2 => "vec2<i32>",
+                TyLit::Ivec3 => "vec3<i32>",
+                TyLit::Ivec4 => "vec4<i32>",
+                TyLit::Vec2 => "vec2<f32>",
+                TyLit::Vec3 => "vec3<f32>",
+                TyLit::Vec4 => "vec4<f32>",
+                TyLit::Mat2 => "mat2x2<f32>",
+                TyLit::Mat3 => "mat3x3<f32>",
+                TyLit::Mat4 => "mat4x4<f32>",
+                TyLit::Texture2D => "texture_2d<f32>",
+            }
+        )
+            .unwrap();
+    }
+
+    fn write_builtin_call_ident(&self, string: &mut String, ident: Ident, arg_exprs: &[Expr]) {
+        match ident {
+            Ident(id!(atan)) => {
+                if arg_exprs.len() == 2 {
+                    write!(string, "atan2").unwrap();
+                }
+                else {
+                    write!(string, "atan").unwrap();
+                }
+            }
+            Ident(id!(dFdx)) => {
+                write!(string, "dpdx").unwrap();
+            }
+            Ident(id!(dFdy)) => {
+                write!(string, "dpdy").unwrap();
+            }
+            Ident(id!(inversesqrt)) => {
+                write!(string, "inverseSqrt").unwrap();
+            }
+            Ident(id!(faceforward)) => {
+                write!(string, "faceForward").unwrap();
+            }
+            _ if Self::needs_builtin_helper(ident) => {
+                write!(string, "{}_{}", ident, arg_exprs[0].ty.borrow().as_ref().unwrap()).unwrap();
+            }
+            _ => {
+                write!(string, "{}", ident).unwrap()
+            }
+        }
+    }
+}
@@ -0,0 +1,1227 @@
+use{
+    std::{
+        ops,
+        collections::HashMap,
+    },
+    crate::{
+        makepad_live_id::{id, LiveId},
+        makepad_live_compiler::{
+            makepad_math::{Vec2, Vec4, Mat4},
+            LiveRegistry,
+            LiveError,
+            LiveEval,
+            live_eval,
+        },
+        shader_ast::*,
+        shader_registry::ShaderRegistry,
+        swizzle::Swizzle,
+    }
+};
+
+// The interpreter walks the analysed AST directly, so it can run a draw shader on the cpu
+// for tests, software rendering and debugging. Floats carry their screen space derivatives
+// along (forward mode), which is what makes dFdx / dFdy work without running 2x2 quads.
+
+/// A float together with its derivatives along screen x and y.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Dual {
+    pub v: f32,
+    pub dx: f32,
+    pub dy: f32,
+}
+
+impl Dual {
+    pub fn new(v: f32) -> Self {
+        Self {v, dx: 0.0, dy: 0.0}
+    }
+
+    pub fn with_derivs(v: f32, dx: f32, dy: f32) -> Self {
+        Self {v, dx, dy}
+    }
+
+    // applies a function with value v and derivative d at self
+    fn chain(self, v: f32, d: f32) -> Self {
+        Self {v, dx: self.dx * d, dy: self.dy * d}
+    }
+
+    fn sqrt(self) -> Self {
+        let v = self.v.sqrt();
+        self.chain(v, if v > 0.0 {0.5 / v} else {0.0})
+    }
+
+    fn min(self, other: Self) -> Self {
+        if other.v < self.v {other} else {self}
+    }
+
+    fn max(self, other: Self) -> Self {
+        if other.v > self.v {other} else {self}
+    }
+
+    fn floor(self) -> Self {
+        Self::new(self.v.floor())
+    }
+}
+
+impl ops::Add for Dual {
+    type Output = Dual;
+    fn add(self, o: Dual) -> Dual {
+        Dual {v: self.v + o.v, dx: self.dx + o.dx, dy: self.dy + o.dy}
+    }
+}
+
+impl ops::Sub for Dual {
+    type Output = Dual;
+    fn sub(self, o: Dual) -> Dual {
+        Dual {v: self.v - o.v, dx: self.dx - o.dx, dy: self.dy - o.dy}
+    }
+}
+
+impl ops::Mul for Dual {
+    type Output = Dual;
+    fn mul(self, o: Dual) -> Dual {
+        Dual {v: self.v * o.v, dx: self.dx * o.v + self.v * o.dx, dy: self.dy * o.v + self.v * o.dy}
+    }
+}
+
+impl ops::Div for Dual {
+    type Output = Dual;
+    fn div(self, o: Dual) -> Dual {
+        let inv = 1.0 / o.v;
+        let v = self.v * inv;
+        Dual {v, dx: (self.dx - v * o.dx) * inv, dy: (self.dy - v * o.dy) * inv}
+    }
+}
+
+impl ops::Neg for Dual {
+    type Output = Dual;
+    fn neg(self) -> Dual {
+        Dual {v: -self.v, dx: -self.dx, dy: -self.dy}
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderClosure {
+    pub fn_ptr: FnPtr,
+    pub closure_def_index: ClosureDefIndex,
+    pub closed_over: Vec<(Ident, ScopeSymShadow, Value)>,
+}
+
+/// A runtime value. Vectors and matrices are flat component lists, matrices column major.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Void,
+    Bool(TyLit, Vec<bool>),
+    Int(TyLit, Vec<i32>),
+    Float(TyLit, Vec<Dual>),
+    Struct(StructPtr, Vec<Value>),
+    Texture(usize),
+    Closure(Box<ShaderClosure>),
+}
+
+fn ty_lit_len(ty_lit: TyLit) -> usize {
+    match ty_lit {
+        TyLit::Bool | TyLit::Int | TyLit::Float | TyLit::Texture2D => 1,
+        TyLit::Bvec2 | TyLit::Ivec2 | TyLit::Vec2 => 2,
+        TyLit::Bvec3 | TyLit::Ivec3 | TyLit::Vec3 => 3,
+        TyLit::Bvec4 | TyLit::Ivec4 | TyLit::Vec4 | TyLit::Mat2 => 4,
+        TyLit::Mat3 => 9,
+        TyLit::Mat4 => 16,
+    }
+}
+
+fn mat_dim(ty_lit: TyLit) -> Option<usize> {
+    match ty_lit {
+        TyLit::Mat2 => Some(2),
+        TyLit::Mat3 => Some(3),
+        TyLit::Mat4 => Some(4),
+        _ => None
+    }
+}
+
+fn float_ty_lit(len: usize) -> TyLit {
+    match len {
+        1 => TyLit::Float,
+        2 => TyLit::Vec2,
+        3 => TyLit::Vec3,
+        _ => TyLit::Vec4,
+    }
+}
+
+fn bool_ty_lit(len: usize) -> TyLit {
+    match len {
+        1 => TyLit::Bool,
+        2 => TyLit::Bvec2,
+        3 => TyLit::Bvec3,
+        _ => TyLit::Bvec4,
+    }
+}
+
+// a single component of any kind, used by constructors
+#[derive(Clone, Copy)]
+enum Scalar {
+    Bool(bool),
+    Int(i32),
+    Float(Dual),
+}
+
+impl Scalar {
+    fn to_bool(self) -> bool {
+        match self {
+            Scalar::Bool(v) => v,
+            Scalar::Int(v) => v != 0,
+            Scalar::Float(v) => v.v != 0.0,
+        }
+    }
+
+    fn to_int(self) -> i32 {
+        match self {
+            Scalar::Bool(v) => v as i32,
+            Scalar::Int(v) => v,
+            Scalar::Float(v) => v.v as i32,
+        }
+    }
+
+    fn to_dual(self) -> Dual {
+        match self {
+            Scalar::Bool(v) => Dual::new(if v {1.0} else {0.0}),
+            Scalar::Int(v) => Dual::new(v as f32),
+            Scalar::Float(v) => v,
+        }
+    }
+}
+
+impl Value {
+    pub fn float(v: f32) -> Self {
+        Value::Float(TyLit::Float, vec![Dual::new(v)])
+    }
+
+    pub fn int(v: i32) -> Self {
+        Value::Int(TyLit::Int, vec![v])
+    }
+
+    pub fn bool(v: bool) -> Self {
+        Value::Bool(TyLit::Bool, vec![v])
+    }
+
+    pub fn vec2(v: Vec2) -> Self {
+        Self::from_f32s(TyLit::Vec2, &[v.x, v.y])
+    }
+
+    pub fn vec4(v: Vec4) -> Self {
+        Self::from_f32s(TyLit::Vec4, &[v.x, v.y, v.z, v.w])
+    }
+
+    pub fn mat4(m: &Mat4) -> Self {
+        Self::from_f32s(TyLit::Mat4, &m.v)
+    }
+
+    /// Builds a float, vector or matrix value from its components, without derivatives.
+    pub fn from_f32s(ty_lit: TyLit, v: &[f32]) -> Self {
+        Value::Float(ty_lit, v.iter().map( | v | Dual::new(*v)).collect())
+    }
+
+    /// Builds a value from the raw slots a draw call would upload for a field of type `ty`.
+    pub fn from_slots(ty: &Ty, slots: &[f32]) -> Self {
+        match ty {
+            Ty::Bool => Value::bool(slots[0] != 0.0),
+            Ty::Int => Value::int(slots[0] as i32),
+            Ty::Enum(_) => Value::int(slots[0].round() as i32),
+            _ => match ty.maybe_ty_lit() {
+                Some(ty_lit) if ty.is_scalar() || ty.is_vector() || ty.is_matrix() => {
+                    Value::from_f32s(ty_lit, &slots[0..ty_lit_len(ty_lit)])
+                }
+                _ => Value::Void
+            }
+        }
+    }
+
+    /// The zero value of a type, which is what an uninitialized `let` holds.
+    pub fn zero(ty: &Ty, shader_registry: &ShaderRegistry) -> Self {
+        match ty {
+            Ty::Bool | Ty::Bvec2 | Ty::Bvec3 | Ty::Bvec4 => {
+                let ty_lit = ty.maybe_ty_lit().unwrap();
+                Value::Bool(ty_lit, vec![false; ty_lit_len(ty_lit)])
+            }
+            Ty::Int | Ty::Ivec2 | Ty::Ivec3 | Ty::Ivec4 => {
+                let ty_lit = ty.maybe_ty_lit().unwrap();
+                Value::Int(ty_lit, vec![0; ty_lit_len(ty_lit)])
+            }
+            Ty::Float | Ty::Vec2 | Ty::Vec3 | Ty::Vec4 | Ty::Mat2 | Ty::Mat3 | Ty::Mat4 => {
+                let ty_lit = ty.maybe_ty_lit().unwrap();
+                Value::Float(ty_lit, vec![Dual::default(); ty_lit_len(ty_lit)])
+            }
+            Ty::Enum(_) => Value::int(0),
+            Ty::Texture2D => Value::Texture(0),
+            Ty::Struct(struct_ptr) => {
+                let struct_def = shader_registry.structs.get(struct_ptr).unwrap();
+                Value::Struct(*struct_ptr, struct_def.fields.iter().map( | field | {
+                    Value::zero(field.ty_expr.ty.borrow().as_ref().unwrap(), shader_registry)
+                }).collect())
+            }
+            _ => Value::Void
+        }
+    }
+
+    fn from_val(val: &Val) -> Self {
+        match val {
+            Val::Bool(v) => Value::bool(*v),
+            Val::Int(v) => Value::int(*v),
+            Val::Float(v) => Value::float(*v),
+            Val::Vec4(v) => Value::vec4(*v),
+        }
+    }
+
+    fn from_lit(lit: Lit) -> Self {
+        match lit {
+            Lit::Bool(v) => Value::bool(v),
+            Lit::Int(v) => Value::int(v),
+            Lit::Float(v) => Value::float(v),
+            Lit::Color(v) => Value::vec4(Vec4::from_u32(v)),
+        }
+    }
+
+    /// The components of a float, vector or matrix value, without derivatives.
+    pub fn to_f32s(&self) -> Option<Vec<f32>> {
+        match self {
+            Value::Float(_, v) => Some(v.iter().map( | v | v.v).collect()),
+            Value::Int(_, v) => Some(v.iter().map( | v | *v as f32).collect()),
+            _ => None
+        }
+    }
+
+    pub fn to_f32(&self) -> Option<f32> {
+        match self {
+            Value::Float(TyLit::Float, v) => Some(v[0].v),
+            _ => None
+        }
+    }
+
+    pub fn to_vec2(&self) -> Option<Vec2> {
+        match self {
+            Value::Float(TyLit::Vec2, v) => Some(Vec2 {x: v[0].v, y: v[1].v}),
+            _ => None
+        }
+    }
+
+    pub fn to_vec4(&self) -> Option<Vec4> {
+        match self {
+            Value::Float(TyLit::Vec4, v) => Some(Vec4 {x: v[0].v, y: v[1].v, z: v[2].v, w: v[3].v}),
+            _ => None
+        }
+    }
+
+    pub fn to_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(TyLit::Bool, v) => Some(v[0]),
+            _ => None
+        }
+    }
+
+    pub fn to_int(&self) -> Option<i32> {
+        match self {
+            Value::Int(TyLit::Int, v) => Some(v[0]),
+            _ => None
+        }
+    }
+
+    fn floats(&self) -> &[Dual] {
+        match self {
+            Value::Float(_, v) => v,
+            _ => panic!("expected a float value, got {:?}", self)
+        }
+    }
+
+    fn ty_lit(&self) -> Option<TyLit> {
+        match self {
+            Value::Bool(ty_lit, _) | Value::Int(ty_lit, _) | Value::Float(ty_lit, _) => Some(*ty_lit),
+            _ => None
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Value::Bool(_, v) => v.len(),
+            Value::Int(_, v) => v.len(),
+            Value::Float(_, v) => v.len(),
+            _ => 0
+        }
+    }
+
+    fn scalar(&self, index: usize) -> Scalar {
+        match self {
+            Value::Bool(_, v) => Scalar::Bool(v[index]),
+            Value::Int(_, v) => Scalar::Int(v[index]),
+            Value::Float(_, v) => Scalar::Float(v[index]),
+            _ => panic!("expected a scalar or vector value, got {:?}", self)
+        }
+    }
+
+    fn set_scalar(&mut self, index: usize, scalar: Scalar) {
+        match self {
+            Value::Bool(_, v) => v[index] = scalar.to_bool(),
+            Value::Int(_, v) => v[index] = scalar.to_int(),
+            Value::Float(_, v) => v[index] = scalar.to_dual(),
+            _ => panic!("expected a scalar or vector value, got {:?}", self)
+        }
+    }
+
+    // the value as an enum variant index, instance buffers pass enums as floats
+    fn to_enum_index(&self) -> usize {
+        match self {
+            Value::Int(_, v) => v[0] as usize,
+            Value::Float(_, v) => v[0].v.round() as usize,
+            _ => panic!("expected an enum value, got {:?}", self)
+        }
+    }
+
+    // compares the values, ignoring derivatives
+    fn same_as(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Float(_, a), Value::Float(_, b)) => a.iter().zip(b.iter()).all( | (a, b) | a.v == b.v),
+            (Value::Struct(_, a), Value::Struct(_, b)) => a.iter().zip(b.iter()).all( | (a, b) | a.same_as(b)),
+            _ => self == other
+        }
+    }
+}
+
+// applies f componentwise, scalar arguments are splatted to the longest argument
+fn map_floats(args: &[Value], f: &dyn Fn(&[Dual]) -> Dual) -> Value {
+    let (ty_lit, len) = args.iter().fold((TyLit::Float, 1), | (ty_lit, len), arg | {
+        if arg.len() > len {(arg.ty_lit().unwrap(), arg.len())} else {(ty_lit, len)}
+    });
+    let mut out = Vec::with_capacity(len);
+    let mut comps = Vec::with_capacity(args.len());
+    for i in 0..len {
+        comps.clear();
+        for arg in args {
+            let v = arg.floats();
+            comps.push(if v.len() == 1 {v[0]} else {v[i]});
+        }
+        out.push(f(&comps));
+    }
+    Value::Float(ty_lit, out)
+}
+
+fn map_ints(a: &Value, b: &Value, f: &dyn Fn(i32, i32) -> i32) -> Value {
+    if let (Value::Int(a_ty, a), Value::Int(b_ty, b)) = (a, b) {
+        let ty_lit = if a.len() >= b.len() {*a_ty} else {*b_ty};
+        let len = a.len().max(b.len());
+        let v = (0..len).map( | i | {
+            f(if a.len() == 1 {a[0]} else {a[i]}, if b.len() == 1 {b[0]} else {b[i]})
+        }).collect();
+        return Value::Int(ty_lit, v)
+    }
+    panic!("expected int values, got {:?} and {:?}", a, b)
+}
+
+fn compare_componentwise(a: &Value, b: &Value, f: &dyn Fn(f32, f32) -> bool) -> Value {
+    let v: Vec<bool> = (0..a.len()).map( | i | {
+        let (x, y) = match (a.scalar(i), b.scalar(i)) {
+            (Scalar::Bool(x), Scalar::Bool(y)) => (x as i32 as f32, y as i32 as f32),
+            (x, y) => (x.to_dual().v, y.to_dual().v)
+        };
+        f(x, y)
+    }).collect();
+    Value::Bool(bool_ty_lit(v.len()), v)
+}
+
+fn dot(a: &[Dual], b: &[Dual]) -> Dual {
+    a.iter().zip(b.iter()).fold(Dual::default(), | acc, (a, b) | acc + *a * *b)
+}
+
+fn mat_mul_vec(m: &[Dual], v: &[Dual], n: usize) -> Vec<Dual> {
+    (0..n).map( | row | (0..n).fold(Dual::default(), | acc, col | acc + m[col * n + row] * v[col])).collect()
+}
+
+fn vec_mul_mat(v: &[Dual], m: &[Dual], n: usize) -> Vec<Dual> {
+    (0..n).map( | col | dot(v, &m[col * n..col * n + n])).collect()
+}
+
+fn mat_mul_mat(a: &[Dual], b: &[Dual], n: usize) -> Vec<Dual> {
+    let mut out = vec![Dual::default(); n * n];
+    for col in 0..n {
+        for row in 0..n {
+            out[col * n + row] = (0..n).fold(Dual::default(), | acc, k | acc + a[k * n + row] * b[col * n + k]);
+        }
+    }
+    out
+}
+
+fn arith(op: BinOp, a: &Value, b: &Value) -> Value {
+    match (a, b) {
+        (Value::Int(..), Value::Int(..)) => match op {
+            BinOp::Add => map_ints(a, b, &| a, b | a.wrapping_add(b)),
+            BinOp::Sub => map_ints(a, b, &| a, b | a.wrapping_sub(b)),
+            BinOp::Mul => map_ints(a, b, &| a, b | a.wrapping_mul(b)),
+            BinOp::Div => map_ints(a, b, &| a, b | if b == 0 {0} else {a.wrapping_div(b)}),
+            _ => panic!("unexpected int op {}", op)
+        },
+        (Value::Float(a_ty, a_v), Value::Float(b_ty, b_v)) => {
+            if let BinOp::Mul = op {
+                match (mat_dim(*a_ty), mat_dim(*b_ty)) {
+                    (Some(n), Some(_)) => return Value::Float(*a_ty, mat_mul_mat(a_v, b_v, n)),
+                    (Some(n), None) if b_v.len() == n => return Value::Float(*b_ty, mat_mul_vec(a_v, b_v, n)),
+                    (None, Some(n)) if a_v.len() == n => return Value::Float(*a_ty, vec_mul_mat(a_v, b_v, n)),
+                    _ => ()
+                }
+            }
+            let args = [a.clone(), b.clone()];
+            match op {
+                BinOp::Add => map_floats(&args, &| v | v[0] + v[1]),
+                BinOp::Sub => map_floats(&args, &| v | v[0] - v[1]),
+                BinOp::Mul => map_floats(&args, &| v | v[0] * v[1]),
+                BinOp::Div => map_floats(&args, &| v | v[0] / v[1]),
+                _ => panic!("unexpected float op {}", op)
+            }
+        }
+        _ => panic!("cannot apply {} to {:?} and {:?}", op, a, b)
+    }
+}
+
+// control flow out of a statement
+enum Flow {
+    Next,
+    Break,
+    Continue,
+    Return(Value),
+}
+
+struct Local {
+    ident: Ident,
+    // loop counters are not tagged with a shadow by the analyser
+    shadow: Option<ScopeSymShadow>,
+    value: Value,
+}
+
+struct Frame<'a> {
+    fn_def: &'a FnDef,
+    locals: Vec<Local>,
+    closures: Vec<(usize, ShaderClosure)>,
+}
+
+impl<'a> Frame<'a> {
+    fn find_local(&mut self, ident: Ident, shadow: ScopeSymShadow) -> Option<&mut Local> {
+        if let Some(index) = self.locals.iter().rposition( | local | local.ident == ident && local.shadow == Some(shadow)) {
+            return Some(&mut self.locals[index])
+        }
+        self.locals.iter_mut().rev().find( | local | local.ident == ident && local.shadow.is_none())
+    }
+}
+
+/// Evaluates the functions of an analysed draw shader on the cpu.
+///
+/// The draw shader inputs (geometries, instances, uniforms, textures) and the varyings are
+/// all read and written through `fields`, keyed by their name in the shader.
+pub struct ShaderInterpreter<'a> {
+    pub shader_registry: &'a ShaderRegistry,
+    pub draw_shader_def: &'a DrawShaderDef,
+    pub fields: HashMap<Ident, Value>,
+    pub live_values: HashMap<ValuePtr, Value>,
+    /// Samples texture `index` at a uv coordinate, textures read as transparent black without it.
+    pub sampler: Option<Box<dyn Fn(usize, Vec2) -> Vec4 + 'a >>,
+    frames: Vec<Frame<'a >>,
+}
+
+impl<'a> ShaderInterpreter<'a> {
+    pub fn new(shader_registry: &'a ShaderRegistry, draw_shader_def: &'a DrawShaderDef) -> Self {
+        Self {
+            shader_registry,
+            draw_shader_def,
+            fields: HashMap::new(),
+            live_values: HashMap::new(),
+            sampler: None,
+            frames: Vec::new(),
+        }
+    }
+
+    /// Reads the current values of all live values the shader refers to from the registry.
+    pub fn load_live_values(&mut self, live_registry: &LiveRegistry) -> Result<(), LiveError> {
+        // methods that vertex and pixel don't reach are not in all_live_refs,
+        // but they can still be called directly
+        let mut live_refs = self.draw_shader_def.all_live_refs.borrow().clone();
+        for fn_ptr in &self.draw_shader_def.methods {
+            if let Some(fn_live_refs) = self.shader_registry.all_fns.get(fn_ptr).and_then( | fn_def | fn_def.live_refs.borrow().clone()) {
+                live_refs.extend(fn_live_refs);
+            }
+        }
+        for (value_ptr, ty) in live_refs.iter() {
+            let (nodes, index) = live_registry.ptr_to_nodes_index(value_ptr.0);
+            let mut next_index = index;
+            let value = match live_eval(live_registry, index, &mut next_index, nodes) ? {
+                LiveEval::Float(v) => Value::float(v as f32),
+                LiveEval::Int(v) if *ty == Ty::Float => Value::float(v as f32),
+                LiveEval::Int(v) => Value::int(v as i32),
+                LiveEval::Bool(v) => Value::bool(v),
+                LiveEval::Vec2(v) => Value::vec2(v),
+                LiveEval::Vec3(v) => Value::from_f32s(TyLit::Vec3, &[v.x, v.y, v.z]),
+                LiveEval::Vec4(v) => Value::vec4(v),
+                LiveEval::String(_) => continue,
+            };
+            self.live_values.insert(*value_ptr, value);
+        }
+        Ok(())
+    }
+
+    pub fn set_field(&mut self, ident: Ident, value: Value) {
+        self.fields.insert(ident, value);
+    }
+
+    pub fn field(&self, ident: Ident) -> Option<&Value> {
+        self.fields.get(&ident)
+    }
+
+    /// Runs the vertex function, returning the clip space position.
+    pub fn vertex(&mut self) -> Value {
+        self.call_method(Ident(id!(vertex)), Vec::new())
+    }
+
+    /// Runs the pixel function, returning the color.
+    pub fn pixel(&mut self) -> Value {
+        self.call_method(Ident(id!(pixel)), Vec::new())
+    }
+
+    /// Calls a method of the draw shader with the given arguments, not counting self.
+    pub fn call_method(&mut self, ident: Ident, args: Vec<Value>) -> Value {
+        let fn_def = self.shader_registry.draw_shader_method_decl_from_ident(self.draw_shader_def, ident)
+            .unwrap_or_else( || panic!("draw shader has no method {}", ident));
+        self.call_fn(fn_def, args).0
+    }
+
+    // args line up with the last params, since draw shader methods don't take their self
+    // param. returns the result and the final values of the inout params
+    fn call_fn(&mut self, fn_def: &'a FnDef, args: Vec<Value>) -> (Value, Vec<Option<Value >>) {
+        let param_offset = fn_def.params.len() - args.len();
+        let mut frame = Frame {
+            fn_def,
+            locals: Vec::new(),
+            closures: Vec::new()
+        };
+        for (arg_index, arg) in args.into_iter().enumerate() {
+            let param = &fn_def.params[param_offset + arg_index];
+            match arg {
+                Value::Closure(closure) => frame.closures.push((param_offset + arg_index, *closure)),
+                value => frame.locals.push(Local {ident: param.ident, shadow: param.shadow.get(), value})
+            }
+        }
+        self.frames.push(frame);
+        let ret = match self.exec_block(&fn_def.block) {
+            Flow::Return(value) => value,
+            _ => Value::Void
+        };
+        let mut frame = self.frames.pop().unwrap();
+        let outs = fn_def.params[param_offset..].iter().map( | param | {
+            match (param.is_inout, param.shadow.get()) {
+                (true, Some(shadow)) => frame.find_local(param.ident, shadow).map( | local | local.value.clone()),
+                _ => None
+            }
+        }).collect();
+        (ret, outs)
+    }
+
+    fn frame(&mut self) -> &mut Frame<'a> {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn exec_block(&mut self, block: &'a Block) -> Flow {
+        let locals_len = self.frame().locals.len();
+        let mut flow = Flow::Next;
+        for stmt in &block.stmts {
+            flow = self.exec_stmt(stmt);
+            if !matches!(flow, Flow::Next) {
+                break;
+            }
+        }
+        self.frame().locals.truncate(locals_len);
+        flow
+    }
+
+    fn exec_stmt(&mut self, stmt: &'a Stmt) -> Flow {
+        match stmt {
+            Stmt::Break {..} => Flow::Break,
+            Stmt::Continue {..} => Flow::Continue,
+            Stmt::For {ident, from_expr, to_expr, step_expr, block, ..} => {
+                self.exec_for_stmt(*ident, from_expr, to_expr, step_expr, block)
+            }
+            Stmt::If {expr, block_if_true, block_if_false, ..} => {
+                if self.eval_expr(expr).to_bool().unwrap() {
+                    self.exec_block(block_if_true)
+                }
+                else if let Some(block_if_false) = block_if_false {
+                    self.exec_block(block_if_false)
+                }
+                else {
+                    Flow::Next
+                }
+            }
+            Stmt::Match {expr, matches, ..} => {
+                let index = self.eval_expr(expr).to_enum_index();
+                if let Some(match_item) = matches.iter().find( | m | m.enum_value.get() == Some(index)) {
+                    return self.exec_block(&match_item.block)
+                }
+                Flow::Next
+            }
+            Stmt::Let {ty, shadow, ident, expr, ..} => {
+                let value = if let Some(expr) = expr {
+                    self.eval_expr(expr)
+                }
+                else {
+                    Value::zero(ty.borrow().as_ref().unwrap(), self.shader_registry)
+                };
+                self.frame().locals.push(Local {ident: *ident, shadow: shadow.get(), value});
+                Flow::Next
+            }
+            Stmt::Return {expr, ..} => {
+                Flow::Return(if let Some(expr) = expr {self.eval_expr(expr)} else {Value::Void})
+            }
+            Stmt::Block {block, ..} => self.exec_block(block),
+            Stmt::Expr {expr, ..} => {
+                self.eval_expr(expr);
+                Flow::Next
+            }
+        }
+    }
+
+    fn exec_for_stmt(&mut self, ident: Ident, from_expr: &Expr, to_expr: &Expr, step_expr: &Option<Expr>, block: &'a Block) -> Flow {
+        fn const_int(expr: &Expr) -> i32 {
+            expr.const_val.borrow().as_ref().unwrap().as_ref().unwrap().to_int().unwrap()
+        }
+        let from = const_int(from_expr);
+        let to = const_int(to_expr);
+        let step = if let Some(step_expr) = step_expr {const_int(step_expr)} else if from < to {1} else {-1};
+        // same iteration order as the generated code
+        let (mut counter, step) = if from <= to {(from, step.abs())} else {(from - 1, -step.abs())};
+        let locals_len = self.frame().locals.len();
+        self.frame().locals.push(Local {ident, shadow: None, value: Value::int(counter)});
+        let mut flow = Flow::Next;
+        while (step > 0 && counter < to) || (step < 0 && counter >= to) {
+            self.frame().locals[locals_len].value = Value::int(counter);
+            match self.exec_block(block) {
+                Flow::Break => break,
+                Flow::Return(value) => {
+                    flow = Flow::Return(value);
+                    break;
+                }
+                _ => ()
+            }
+            counter += step;
+        }
+        self.frame().locals.truncate(locals_len);
+        flow
+    }
+
+    pub fn eval_expr(&mut self, expr: &'a Expr) -> Value {
+        if let Some(Some(val)) = expr.const_val.borrow().as_ref() {
+            return Value::from_val(val)
+        }
+        match &expr.kind {
+            ExprKind::Cond {expr, expr_if_true, expr_if_false, ..} => {
+                if self.eval_expr(expr).to_bool().unwrap() {
+                    self.eval_expr(expr_if_true)
+                }
+                else {
+                    self.eval_expr(expr_if_false)
+                }
+            }
+            ExprKind::Bin {op, left_expr, right_expr, ..} => self.eval_bin_expr(*op, left_expr, right_expr),
+            ExprKind::Un {op, expr, ..} => {
+                match (op, self.eval_expr(expr)) {
+                    (UnOp::Not, Value::Bool(ty_lit, v)) => Value::Bool(ty_lit, v.iter().map( | v | !v).collect()),
+                    (UnOp::Neg, Value::Int(ty_lit, v)) => Value::Int(ty_lit, v.iter().map( | v | v.wrapping_neg()).collect()),
+                    (UnOp::Neg, Value::Float(ty_lit, v)) => Value::Float(ty_lit, v.iter().map( | v | -*v).collect()),
+                    (op, value) => panic!("cannot apply {} to {:?}", op, value)
+                }
+            }
+            ExprKind::Field {expr: base_expr, field_ident, ..} => self.eval_field_expr(expr, base_expr, *field_ident),
+            ExprKind::Index {expr, index_expr, ..} => {
+                let value = self.eval_expr(expr);
+                let index = self.eval_expr(index_expr).to_int().unwrap() as usize;
+                match value {
+                    Value::Float(ty_lit, v) if mat_dim(ty_lit).is_some() => {
+                        let n = mat_dim(ty_lit).unwrap();
+                        Value::Float(float_ty_lit(n), v[index * n..index * n + n].to_vec())
+                    }
+                    value => match value.scalar(index) {
+                        Scalar::Bool(v) => Value::bool(v),
+                        Scalar::Int(v) => Value::int(v),
+                        Scalar::Float(v) => Value::Float(TyLit::Float, vec![v]),
+                    }
+                }
+            }
+            ExprKind::MethodCall {ident, arg_exprs, ..} => {
+                match arg_exprs[0].ty.borrow().as_ref().unwrap() {
+                    Ty::Struct(struct_ptr) => {
+                        let fn_def = self.shader_registry.struct_method_decl_from_ident(
+                            self.shader_registry.structs.get(struct_ptr).unwrap(),
+                            *ident
+                        ).unwrap();
+                        self.eval_call(fn_def, arg_exprs)
+                    }
+                    Ty::DrawShader(shader_ptr) => {
+                        let fn_def = self.shader_registry.draw_shader_method_decl_from_ident(
+                            self.shader_registry.draw_shader_defs.get(shader_ptr).unwrap(),
+                            *ident
+                        ).unwrap();
+                        self.eval_call(fn_def, &arg_exprs[1..])
+                    }
+                    _ => panic!(),
+                }
+            }
+            ExprKind::PlainCall {fn_ptr, arg_exprs, param_index, ..} => {
+                if let Some(param_index) = param_index.get() {
+                    self.eval_closure_call(param_index, arg_exprs)
+                }
+                else {
+                    let fn_def = self.shader_registry.all_fns.get(&fn_ptr.unwrap()).unwrap();
+                    self.eval_call(fn_def, arg_exprs)
+                }
+            }
+            ExprKind::BuiltinCall {ident, arg_exprs, ..} => {
+                let args: Vec<Value> = arg_exprs.iter().map( | arg_expr | self.eval_expr(arg_expr)).collect();
+                self.eval_builtin(*ident, &args)
+            }
+            ExprKind::ClosureDef(closure_def_index) => self.make_closure(*closure_def_index),
+            ExprKind::ConsCall {ty_lit, arg_exprs, ..} => {
+                let args: Vec<Value> = arg_exprs.iter().map( | arg_expr | self.eval_expr(arg_expr)).collect();
+                construct(*ty_lit, &args)
+            }
+            ExprKind::StructCons {struct_ptr, args, ..} => {
+                let struct_def = self.shader_registry.structs.get(struct_ptr).unwrap();
+                let values = struct_def.fields.iter().map( | field | {
+                    let arg = args.iter().find( | (ident, _) | field.ident == *ident).unwrap();
+                    self.eval_expr(&arg.1)
+                }).collect();
+                Value::Struct(*struct_ptr, values)
+            }
+            ExprKind::Var {kind, ..} => {
+                let ty = expr.ty.borrow();
+                let ty = ty.as_ref().unwrap();
+                if let Ty::DrawShader(_) = ty {
+                    return Value::Void
+                }
+                match kind.get().unwrap() {
+                    VarKind::Local {ident, shadow} | VarKind::MutLocal {ident, shadow} => {
+                        if let Some(local) = self.frame().find_local(ident, shadow) {
+                            return local.value.clone()
+                        }
+                        Value::zero(ty, self.shader_registry)
+                    }
+                    VarKind::LiveValue(value_ptr) => {
+                        if let Some(value) = self.live_values.get(&value_ptr) {
+                            return value.clone()
+                        }
+                        Value::zero(ty, self.shader_registry)
+                    }
+                }
+            }
+            ExprKind::Lit {lit, ..} => Value::from_lit(*lit),
+        }
+    }
+
+    fn eval_bin_expr(&mut self, op: BinOp, left_expr: &'a Expr, right_expr: &'a Expr) -> Value {
+        match op {
+            BinOp::Assign => {
+                let value = self.eval_expr(right_expr);
+                self.assign(left_expr, value.clone());
+                value
+            }
+            BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign => {
+                let right = self.eval_expr(right_expr);
+                let left = self.eval_expr(left_expr);
+                let value = arith(match op {
+                    BinOp::AddAssign => BinOp::Add,
+                    BinOp::SubAssign => BinOp::Sub,
+                    BinOp::MulAssign => BinOp::Mul,
+                    _ => BinOp::Div,
+                }, &left, &right);
+                self.assign(left_expr, value.clone());
+                value
+            }
+            BinOp::Or => Value::bool(self.eval_expr(left_expr).to_bool().unwrap() || self.eval_expr(right_expr).to_bool().unwrap()),
+            BinOp::And => Value::bool(self.eval_expr(left_expr).to_bool().unwrap() && self.eval_expr(right_expr).to_bool().unwrap()),
+            BinOp::Eq | BinOp::Ne => {
+                let same = self.eval_expr(left_expr).same_as(&self.eval_expr(right_expr));
+                Value::bool(if let BinOp::Eq = op {same} else {!same})
+            }
+            BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => {
+                let left = self.eval_expr(left_expr).scalar(0).to_dual().v;
+                let right = self.eval_expr(right_expr).scalar(0).to_dual().v;
+                Value::bool(match op {
+                    BinOp::Lt => left < right,
+                    BinOp::Le => left <= right,
+                    BinOp::Gt => left > right,
+                    _ => left >= right,
+                })
+            }
+            BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div => {
+                let left = self.eval_expr(left_expr);
+                let right = self.eval_expr(right_expr);
+                arith(op, &left, &right)
+            }
+        }
+    }
+
+    fn eval_field_expr(&mut self, expr: &'a Expr, base_expr: &'a Expr, field_ident: Ident) -> Value {
+        match base_expr.ty.borrow().as_ref().unwrap() {
+            Ty::DrawShader(_) => {
+                if let Some(value) = self.fields.get(&field_ident) {
+                    return value.clone()
+                }
+                Value::zero(expr.ty.borrow().as_ref().unwrap(), self.shader_registry)
+            }
+            Ty::Struct(struct_ptr) => {
+                let field_index = self.struct_field_index(*struct_ptr, field_ident);
+                match self.eval_expr(base_expr) {
+                    Value::Struct(_, mut fields) => fields.swap_remove(field_index),
+                    value => panic!("expected a struct value, got {:?}", value)
+                }
+            }
+            _ => {
+                let swizzle = Swizzle::parse(field_ident).unwrap();
+                let value = self.eval_expr(base_expr);
+                let mut out = Value::zero(expr.ty.borrow().as_ref().unwrap(), self.shader_registry);
+                for (out_index, index) in swizzle.iter().enumerate() {
+                    out.set_scalar(out_index, value.scalar(*index));
+                }
+                out
+            }
+        }
+    }
+
+    fn struct_field_index(&self, struct_ptr: StructPtr, field_ident: Ident) -> usize {
+        let struct_def = self.shader_registry.structs.get(&struct_ptr).unwrap();
+        struct_def.fields.iter().position( | field | field.ident == field_ident).unwrap()
+    }
+
+    // writes a value to the place an lhs checked expression refers to
+    fn assign(&mut self, expr: &'a Expr, value: Value) {
+        match &expr.kind {
+            ExprKind::Var {kind, ..} => match kind.get().unwrap() {
+                VarKind::Local {ident, shadow} | VarKind::MutLocal {ident, shadow} => {
+                    if let Some(local) = self.frame().find_local(ident, shadow) {
+                        local.value = value;
+                    }
+                    else {
+                        self.frame().locals.push(Local {ident, shadow: Some(shadow), value});
+                    }
+                }
+                VarKind::LiveValue(_) => ()
+            },
+            ExprKind::Field {expr: base_expr, field_ident, ..} => {
+                match base_expr.ty.borrow().as_ref().unwrap() {
+                    Ty::DrawShader(_) => {
+                        self.fields.insert(*field_ident, value);
+                    }
+                    Ty::Struct(struct_ptr) => {
+                        let field_index = self.struct_field_index(*struct_ptr, *field_ident);
+                        let mut base = self.eval_expr(base_expr);
+                        if let Value::Struct(_, fields) = &mut base {
+                            fields[field_index] = value;
+                        }
+                        self.assign(base_expr, base);
+                    }
+                    _ => {
+                        let swizzle = Swizzle::parse(*field_ident).unwrap();
+                        let mut base = self.eval_expr(base_expr);
+                        for (value_index, index) in swizzle.iter().enumerate() {
+                            base.set_scalar(*index, value.scalar(value_index));
+                        }
+                        self.assign(base_expr, base);
+                    }
+                }
+            }
+            ExprKind::Index {expr: base_expr, index_expr, ..} => {
+                let index = self.eval_expr(index_expr).to_int().unwrap() as usize;
+                let mut base = self.eval_expr(base_expr);
+                match &mut base {
+                    Value::Float(ty_lit, v) if mat_dim(*ty_lit).is_some() => {
+                        let n = mat_dim(*ty_lit).unwrap();
+                        v[index * n..index * n + n].copy_from_slice(value.floats());
+                    }
+                    base => base.set_scalar(index, value.scalar(0))
+                }
+                self.assign(base_expr, base);
+            }
+            _ => panic!("cannot assign to expression")
+        }
+    }
+
+    fn eval_call(&mut self, fn_def: &'a FnDef, arg_exprs: &'a [Expr]) -> Value {
+        let args = arg_exprs.iter().map( | arg_expr | self.eval_expr(arg_expr)).collect();
+        let (ret, outs) = self.call_fn(fn_def, args);
+        for (arg_expr, out) in arg_exprs.iter().zip(outs) {
+            if let Some(out) = out {
+                self.assign(arg_expr, out);
+            }
+        }
+        ret
+    }
+
+    // closures capture the values of the syms they close over, like the generated code does
+    fn make_closure(&mut self, closure_def_index: ClosureDefIndex) -> Value {
+        let frame = self.frame();
+        let fn_def = frame.fn_def;
+        let closure_def = &fn_def.closure_defs[closure_def_index.0];
+        let mut closed_over = Vec::new();
+        for sym in closure_def.closed_over_syms.borrow().as_ref().unwrap() {
+            if let Ty::DrawShader(_) = sym.ty {
+                continue;
+            }
+            if let Some(local) = frame.find_local(sym.ident, sym.shadow) {
+                closed_over.push((sym.ident, sym.shadow, local.value.clone()));
+            }
+        }
+        Value::Closure(Box::new(ShaderClosure {
+            fn_ptr: fn_def.fn_ptr,
+            closure_def_index,
+            closed_over
+        }))
+    }
+
+    fn eval_closure_call(&mut self, param_index: usize, arg_exprs: &'a [Expr]) -> Value {
+        let closure = self.frame().closures.iter().find( | (index, _) | *index == param_index)
+            .map( | (_, closure) | closure.clone())
+            .expect("closure argument missing");
+        let args: Vec<Value> = arg_exprs.iter().map( | arg_expr | self.eval_expr(arg_expr)).collect();
+
+        let fn_def = self.shader_registry.all_fns.get(&closure.fn_ptr).unwrap();
+        let closure_def = &fn_def.closure_defs[closure.closure_def_index.0];
+        let mut frame = Frame {
+            fn_def,
+            locals: Vec::new(),
+            closures: Vec::new()
+        };
+        for (ident, shadow, value) in closure.closed_over {
+            frame.locals.push(Local {ident, shadow: Some(shadow), value});
+        }
+        for (param, value) in closure_def.params.iter().zip(args) {
+            frame.locals.push(Local {ident: param.ident, shadow: param.shadow.get(), value});
+        }
+        self.frames.push(frame);
+        let ret = match &closure_def.kind {
+            ClosureDefKind::Expr(expr) => self.eval_expr(expr),
+            ClosureDefKind::Block(block) => match self.exec_block(block) {
+                Flow::Return(value) => value,
+                _ => Value::Void
+            }
+        };
+        self.frames.pop();
+        ret
+    }
+
+    fn eval_builtin(&mut self, ident: Ident, args: &[Value]) -> Value {
+        match ident.0 {
+            id!(abs) => match &args[0] {
+                Value::Int(ty_lit, v) => Value::Int(*ty_lit, v.iter().map( | v | v.wrapping_abs()).collect()),
+                _ => map_floats(args, &| v | if v[0].v < 0.0 {-v[0]} else {v[0]}),
+            },
+            id!(acos) => map_floats(args, &| v | v[0].chain(v[0].v.acos(), -1.0 / (1.0 - v[0].v * v[0].v).sqrt())),
+            id!(asin) => map_floats(args, &| v | v[0].chain(v[0].v.asin(), 1.0 / (1.0 - v[0].v * v[0].v).sqrt())),
+            id!(atan) if args.len() == 2 => map_floats(args, &| v | {
+                let (y, x) = (v[0], v[1]);
+                let len_sq = x.v * x.v + y.v * y.v;
+                let inv = if len_sq > 0.0 {1.0 / len_sq} else {0.0};
+                Dual {
+                    v: y.v.atan2(x.v),
+                    dx: (x.v * y.dx - y.v * x.dx) * inv,
+                    dy: (x.v * y.dy - y.v * x.dy) * inv,
+                }
+            }),
+            id!(atan) => map_floats(args, &| v | v[0].chain(v[0].v.atan(), 1.0 / (1.0 + v[0].v * v[0].v))),
+            id!(ceil) => map_floats(args, &| v | Dual::new(v[0].v.ceil())),
+            id!(clamp) => map_floats(args, &| v | v[0].max(v[1]).min(v[2])),
+            id!(cos) => map_floats(args, &| v | v[0].chain(v[0].v.cos(), -v[0].v.sin())),
+            id!(cross) => {
+                let (a, b) = (args[0].floats(), args[1].floats());
+                Value::Float(TyLit::Vec3, vec![
+                    a[1] * b[2] - a[2] * b[1],
+                    a[2] * b[0] - a[0] * b[2],
+                    a[0] * b[1] - a[1] * b[0],
+                ])
+            }
+            id!(degrees) => map_floats(args, &| v | v[0] * Dual::new(180.0 / std::f32::consts::PI)),
+            id!(dFdx) => map_floats(args, &| v | Dual::new(v[0].dx)),
+            id!(dFdy) => map_floats(args, &| v | Dual::new(v[0].dy)),
+            id!(distance) => {
+                let d = arith(BinOp::Sub, &args[0], &args[1]);
+                Value::Float(TyLit::Float, vec![dot(d.floats(), d.floats()).sqrt()])
+            }
+            id!(dot) => Value::Float(TyLit::Float, vec![dot(args[0].floats(), args[1].floats())]),
+            id!(equal) => compare_componentwise(&args[0], &args[1], &| a, b | a == b),
+            id!(notEqual) => compare_componentwise(&args[0], &args[1], &| a, b | a != b),
+            id!(lessThan) => compare_componentwise(&args[0], &args[1], &| a, b | a < b),
+            id!(lessThanEqual) => compare_componentwise(&args[0], &args[1], &| a, b | a <= b),
+            id!(greaterThan) => compare_componentwise(&args[0], &args[1], &| a, b | a > b),
+            id!(greaterThanEqual) => compare_componentwise(&args[0], &args[1], &| a, b | a >= b),
+            id!(exp) => map_floats(args, &| v | {
+                let e = v[0].v.exp();
+                v[0].chain(e, e)
+            }),
+            id!(exp2) => map_floats(args, &| v | {
+                let e = v[0].v.exp2();
+                v[0].chain(e, e * std::f32::consts::LN_2)
+            }),
+            id!(faceforward) => {
+                if dot(args[2].floats(), args[1].floats()).v < 0.0 {
+                    args[0].clone()
+                }
+                else {
+                    map_floats(&args[0..1], &| v | -v[0])
+                }
+            }
+            id!(floor) => map_floats(args, &| v | v[0].floor()),
+            id!(fract) => map_floats(args, &| v | v[0] - v[0].floor()),
+            id!(inversesqrt) => map_floats(args, &| v | {
+                let r = 1.0 / v[0].v.sqrt();
+                v[0].chain(r, -0.5 * r / v[0].v)
+            }),
+            id!(inverse) => {
+                let mut m = Mat4::default();
+                m.v.copy_from_slice(&args[0].to_f32s().unwrap());
+                Value::mat4(&m.invert())
+            }
+            id!(length) => {
+                let v = args[0].floats();
+                Value::Float(TyLit::Float, vec![dot(v, v).sqrt()])
+            }
+            id!(log) => map_floats(args, &| v | v[0].chain(v[0].v.ln(), 1.0 / v[0].v)),
+            id!(log2) => map_floats(args, &| v | v[0].chain(v[0].v.log2(), 1.0 / (v[0].v * std::f32::consts::LN_2))),
+            id!(matrixCompMult) => match (&args[0], &args[1]) {
+                (Value::Float(ty_lit, a), Value::Float(_, b)) => {
+                    Value::Float(*ty_lit, a.iter().zip(b.iter()).map( | (a, b) | *a * *b).collect())
+                }
+                _ => panic!()
+            },
+            id!(max) => map_floats(args, &| v | v[0].max(v[1])),
+            id!(min) => map_floats(args, &| v | v[0].min(v[1])),
+            id!(mix) => map_floats(args, &| v | v[0] + (v[1] - v[0]) * v[2]),
+            id!(mod) => map_floats(args, &| v | v[0] - v[1] * (v[0] / v[1]).floor()),
+            id!(normalize) => {
+                let v = args[0].floats();
+                let len = dot(v, v).sqrt();
+                Value::Float(args[0].ty_lit().unwrap(), v.iter().map( | v | *v / len).collect())
+            }
+            id!(not) => match &args[0] {
+                Value::Bool(ty_lit, v) => Value::Bool(*ty_lit, v.iter().map( | v | !v).collect()),
+                _ => panic!()
+            },
+            id!(all) => match &args[0] {
+                Value::Bool(_, v) => Value::bool(v.iter().all( | v | *v)),
+                _ => panic!()
+            },
+            id!(any) => match &args[0] {
+                Value::Bool(_, v) => Value::bool(v.iter().any( | v | *v)),
+                _ => panic!()
+            },
+            id!(pow) => map_floats(args, &| v | {
+                let (x, y) = (v[0], v[1]);
+                let p = x.v.powf(y.v);
+                let dp_dx = if x.v != 0.0 {y.v * x.v.powf(y.v - 1.0)} else {0.0};
+                let dp_dy = if x.v > 0.0 {p * x.v.ln()} else {0.0};
+                Dual {
+                    v: p,
+                    dx: dp_dx * x.dx + dp_dy * y.dx,
+                    dy: dp_dx * x.dy + dp_dy * y.dy,
+                }
+            }),
+            id!(radians) => map_floats(args, &| v | v[0] * Dual::new(std::f32::consts::PI / 180.0)),
+            id!(reflect) => {
+                let d = dot(args[1].floats(), args[0].floats());
+                map_floats(&args[0..2], &| v | v[0] - Dual::new(2.0) * d * v[1])
+            }
+            id!(refract) => {
+                let eta = args[2].floats()[0];
+                let d = dot(args[1].floats(), args[0].floats());
+                let k = Dual::new(1.0) - eta * eta * (Dual::new(1.0) - d * d);
+                if k.v < 0.0 {
+                    map_floats(&args[0..1], &| _ | Dual::default())
+                }
+                else {
+                    let s = eta * d + k.sqrt();
+                    map_floats(&args[0..2], &| v | eta * v[0] - s * v[1])
+                }
+            }
+            id!(sample2d) => {
+                let texture = match args[0] {
+                    Value::Texture(texture) => texture,
+                    _ => panic!()
+                };
+                let uv = args[1].to_vec2().unwrap();
+                let color = if let Some(sampler) = &self.sampler {sampler(texture, uv)} else {Vec4::default()};
+                Value::vec4(color)
+            }
+            id!(sign) => map_floats(args, &| v | Dual::new(if v[0].v > 0.0 {1.0} else if v[0].v < 0.0 {-1.0} else {0.0})),
+            id!(sin) => map_floats(args, &| v | v[0].chain(v[0].v.sin(), v[0].v.cos())),
+            id!(smoothstep) => map_floats(args, &| v | {
+                let t = ((v[2] - v[0]) / (v[1] - v[0])).max(Dual::new(0.0)).min(Dual::new(1.0));
+                t * t * (Dual::new(3.0) - Dual::new(2.0) * t)
+            }),
+            id!(sqrt) => map_floats(args, &| v | v[0].sqrt()),
+            id!(step) => map_floats(args, &| v | Dual::new(if v[1].v < v[0].v {0.0} else {1.0})),
+            id!(tan) => map_floats(args, &| v | {
+                let c = v[0].v.cos();
+                v[0].chain(v[0].v.tan(), 1.0 / (c * c))
+            }),
+            id!(transpose) => match &args[0] {
+                Value::Float(ty_lit, v) => {
+                    let n = mat_dim(*ty_lit).unwrap();
+                    Value::Float(*ty_lit, (0..n * n).map( | i | v[(i % n) * n + i / n]).collect())
+                }
+                _ => panic!()
+            },
+            _ => panic!("builtin {} is not supported by the interpreter", ident)
+        }
+    }
+}
+
+// vector and matrix constructors follow glsl: a single scalar splats (or fills the diagonal
+// of a matrix), a single matrix is resized, anything else is flattened component by component
+fn construct(ty_lit: TyLit, args: &[Value]) -> Value {
+    let len = ty_lit_len(ty_lit);
+    let mut out = match ty_lit {
+        TyLit::Bool | TyLit::Bvec2 | TyLit::Bvec3 | TyLit::Bvec4 => Value::Bool(ty_lit, vec![false; len]),
+        TyLit::Int | TyLit::Ivec2 | TyLit::Ivec3 | TyLit::Ivec4 => Value::Int(ty_lit, vec![0; len]),
+        _ => Value::Float(ty_lit, vec![Dual::default(); len]),
+    };
+    if let (Some(n), [arg]) = (mat_dim(ty_lit), args) {
+        match arg {
+            Value::Float(src_ty_lit, v) if mat_dim(*src_ty_lit).is_some() => {
+                let m = mat_dim(*src_ty_lit).unwrap();
+                for col in 0..n {
+                    for row in 0..n {
+                        out.set_scalar(col * n + row, Scalar::Float(if col < m && row < m {
+                            v[col * m + row]
+                        } else {
+                            Dual::new(if col == row {1.0} else {0.0})
+                        }));
+                    }
+                }
+                return out
+            }
+            arg if arg.len() == 1 => {
+                for i in 0..n {
+                    out.set_scalar(i * n + i, arg.scalar(0));
+                }
+                return out
+            }
+            _ => ()
+        }
+    }
+    if let [arg] = args {
+        if arg.len() == 1 {
+            for i in 0..len {
+                out.set_scalar(i, arg.scalar(0));
+            }
+            return out
+        }
+    }
+    let mut index = 0;
+    for arg in args {
+        for i in 0..arg.len() {
+            if index < len {
+                out.set_scalar(index, arg.scalar(i));
+                index += 1;
+            }
+        }
+    }
+    out
+}
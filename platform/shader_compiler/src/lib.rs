@@ -21,6 +21,12 @@ pub mod generate_glsl;
 pub mod generate_metal;
 //#[cfg(any(target_os = "windows", test))]
 pub mod generate_hlsl;
+//#[cfg(any(target_arch = "wasm32", test))]
+pub mod generate_wgsl;
+pub mod spirv;
+pub mod generate_spirv;
+
+pub mod interpreter;
 
 pub use makepad_live_compiler;
 pub use makepad_live_compiler::makepad_math;
@@ -44,7 +50,8 @@ pub use {
             ShaderEnum,
             ShaderRegistry,
             DrawShaderQuery
-        }
+        },
+        generate::ShaderSourceMap,
     }
 };
 
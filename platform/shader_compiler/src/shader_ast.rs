@@ -346,6 +346,22 @@ pub enum Stmt {
     },
 }
 
+impl Stmt {
+    pub fn span(&self) -> TokenSpan {
+        match self {
+            Stmt::Break {span} |
+            Stmt::Continue {span} |
+            Stmt::For {span, ..} |
+            Stmt::If {span, ..} |
+            Stmt::Match {span, ..} |
+            Stmt::Let {span, ..} |
+            Stmt::Return {span, ..} |
+            Stmt::Block {span, ..} |
+            Stmt::Expr {span, ..} => *span
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct Match {
     pub span: TokenSpan,
@@ -867,6 +883,13 @@ impl BinOp {
         }
     }
     
+    pub fn is_assign(self) -> bool {
+        match self {
+            BinOp::Assign | BinOp::AddAssign | BinOp::SubAssign | BinOp::MulAssign | BinOp::DivAssign => true,
+            _ => false
+        }
+    }
+    
     pub fn from_or_op(token: LiveToken) -> Option<BinOp> {
         match token {
             LiveToken::Punct(id!( ||)) => Some(BinOp::Or),
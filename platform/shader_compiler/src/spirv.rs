@@ -0,0 +1,759 @@
+use std::{
+    fmt::Write,
+    collections::HashMap,
+};
+
+// The SPIR-V binary format, as far as the shader compiler emits it: the opcode and enum
+// numbering, a word writer per module section, and a disassembler / assembler pair for the
+// textual form that tests and debugging output use.
+
+pub const MAGIC: u32 = 0x07230203;
+pub const VERSION_1_0: u32 = 0x00010000;
+
+pub const GLSL_STD_450: &str = "GLSL.std.450";
+
+pub mod op {
+    pub const NAME: u16 = 5;
+    pub const MEMBER_NAME: u16 = 6;
+    pub const EXT_INST_IMPORT: u16 = 11;
+    pub const EXT_INST: u16 = 12;
+    pub const MEMORY_MODEL: u16 = 14;
+    pub const ENTRY_POINT: u16 = 15;
+    pub const EXECUTION_MODE: u16 = 16;
+    pub const CAPABILITY: u16 = 17;
+    pub const TYPE_VOID: u16 = 19;
+    pub const TYPE_BOOL: u16 = 20;
+    pub const TYPE_INT: u16 = 21;
+    pub const TYPE_FLOAT: u16 = 22;
+    pub const TYPE_VECTOR: u16 = 23;
+    pub const TYPE_MATRIX: u16 = 24;
+    pub const TYPE_IMAGE: u16 = 25;
+    pub const TYPE_SAMPLER: u16 = 26;
+    pub const TYPE_SAMPLED_IMAGE: u16 = 27;
+    pub const TYPE_ARRAY: u16 = 28;
+    pub const TYPE_STRUCT: u16 = 30;
+    pub const TYPE_POINTER: u16 = 32;
+    pub const TYPE_FUNCTION: u16 = 33;
+    pub const CONSTANT_TRUE: u16 = 41;
+    pub const CONSTANT_FALSE: u16 = 42;
+    pub const CONSTANT: u16 = 43;
+    pub const CONSTANT_COMPOSITE: u16 = 44;
+    pub const FUNCTION: u16 = 54;
+    pub const FUNCTION_PARAMETER: u16 = 55;
+    pub const FUNCTION_END: u16 = 56;
+    pub const FUNCTION_CALL: u16 = 57;
+    pub const VARIABLE: u16 = 59;
+    pub const LOAD: u16 = 61;
+    pub const STORE: u16 = 62;
+    pub const ACCESS_CHAIN: u16 = 65;
+    pub const DECORATE: u16 = 71;
+    pub const MEMBER_DECORATE: u16 = 72;
+    pub const VECTOR_EXTRACT_DYNAMIC: u16 = 77;
+    pub const VECTOR_SHUFFLE: u16 = 79;
+    pub const COMPOSITE_CONSTRUCT: u16 = 80;
+    pub const COMPOSITE_EXTRACT: u16 = 81;
+    pub const COMPOSITE_INSERT: u16 = 82;
+    pub const TRANSPOSE: u16 = 84;
+    pub const SAMPLED_IMAGE: u16 = 86;
+    pub const IMAGE_SAMPLE_EXPLICIT_LOD: u16 = 88;
+    pub const CONVERT_F_TO_S: u16 = 110;
+    pub const CONVERT_S_TO_F: u16 = 111;
+    pub const S_NEGATE: u16 = 126;
+    pub const F_NEGATE: u16 = 127;
+    pub const I_ADD: u16 = 128;
+    pub const F_ADD: u16 = 129;
+    pub const I_SUB: u16 = 130;
+    pub const F_SUB: u16 = 131;
+    pub const I_MUL: u16 = 132;
+    pub const F_MUL: u16 = 133;
+    pub const S_DIV: u16 = 135;
+    pub const F_DIV: u16 = 136;
+    pub const F_MOD: u16 = 141;
+    pub const VECTOR_TIMES_SCALAR: u16 = 142;
+    pub const MATRIX_TIMES_SCALAR: u16 = 143;
+    pub const VECTOR_TIMES_MATRIX: u16 = 144;
+    pub const MATRIX_TIMES_VECTOR: u16 = 145;
+    pub const MATRIX_TIMES_MATRIX: u16 = 146;
+    pub const DOT: u16 = 148;
+    pub const ANY: u16 = 154;
+    pub const ALL: u16 = 155;
+    pub const LOGICAL_EQUAL: u16 = 164;
+    pub const LOGICAL_NOT_EQUAL: u16 = 165;
+    pub const LOGICAL_OR: u16 = 166;
+    pub const LOGICAL_AND: u16 = 167;
+    pub const LOGICAL_NOT: u16 = 168;
+    pub const SELECT: u16 = 169;
+    pub const I_EQUAL: u16 = 170;
+    pub const I_NOT_EQUAL: u16 = 171;
+    pub const S_GREATER_THAN: u16 = 173;
+    pub const S_GREATER_THAN_EQUAL: u16 = 175;
+    pub const S_LESS_THAN: u16 = 177;
+    pub const S_LESS_THAN_EQUAL: u16 = 179;
+    pub const F_ORD_EQUAL: u16 = 180;
+    pub const F_ORD_NOT_EQUAL: u16 = 182;
+    pub const F_ORD_LESS_THAN: u16 = 184;
+    pub const F_ORD_GREATER_THAN: u16 = 186;
+    pub const F_ORD_LESS_THAN_EQUAL: u16 = 188;
+    pub const F_ORD_GREATER_THAN_EQUAL: u16 = 190;
+    pub const DPDX: u16 = 207;
+    pub const DPDY: u16 = 208;
+    pub const PHI: u16 = 245;
+    pub const LOOP_MERGE: u16 = 246;
+    pub const SELECTION_MERGE: u16 = 247;
+    pub const LABEL: u16 = 248;
+    pub const BRANCH: u16 = 249;
+    pub const BRANCH_CONDITIONAL: u16 = 250;
+    pub const SWITCH: u16 = 251;
+    pub const RETURN: u16 = 253;
+    pub const RETURN_VALUE: u16 = 254;
+    pub const UNREACHABLE: u16 = 255;
+}
+
+pub mod capability {
+    pub const SHADER: u32 = 1;
+}
+
+pub mod addressing_model {
+    pub const LOGICAL: u32 = 0;
+}
+
+pub mod memory_model {
+    pub const GLSL450: u32 = 1;
+}
+
+pub mod execution_model {
+    pub const VERTEX: u32 = 0;
+    pub const FRAGMENT: u32 = 4;
+}
+
+pub mod execution_mode {
+    pub const ORIGIN_UPPER_LEFT: u32 = 7;
+}
+
+pub mod storage_class {
+    pub const UNIFORM_CONSTANT: u32 = 0;
+    pub const INPUT: u32 = 1;
+    pub const UNIFORM: u32 = 2;
+    pub const OUTPUT: u32 = 3;
+    pub const PRIVATE: u32 = 6;
+    pub const FUNCTION: u32 = 7;
+}
+
+pub mod decoration {
+    pub const BLOCK: u32 = 2;
+    pub const ARRAY_STRIDE: u32 = 6;
+    pub const BUILT_IN: u32 = 11;
+    pub const LOCATION: u32 = 30;
+    pub const BINDING: u32 = 33;
+    pub const DESCRIPTOR_SET: u32 = 34;
+    pub const OFFSET: u32 = 35;
+}
+
+pub mod built_in {
+    pub const POSITION: u32 = 0;
+}
+
+pub mod image_operands {
+    pub const LOD: u32 = 0x2;
+}
+
+pub mod glsl {
+    pub const ROUND: u32 = 1;
+    pub const TRUNC: u32 = 3;
+    pub const F_ABS: u32 = 4;
+    pub const S_ABS: u32 = 5;
+    pub const F_SIGN: u32 = 6;
+    pub const S_SIGN: u32 = 7;
+    pub const FLOOR: u32 = 8;
+    pub const CEIL: u32 = 9;
+    pub const FRACT: u32 = 10;
+    pub const RADIANS: u32 = 11;
+    pub const DEGREES: u32 = 12;
+    pub const SIN: u32 = 13;
+    pub const COS: u32 = 14;
+    pub const TAN: u32 = 15;
+    pub const ASIN: u32 = 16;
+    pub const ACOS: u32 = 17;
+    pub const ATAN: u32 = 18;
+    pub const ATAN2: u32 = 25;
+    pub const POW: u32 = 26;
+    pub const EXP: u32 = 27;
+    pub const LOG: u32 = 28;
+    pub const EXP2: u32 = 29;
+    pub const LOG2: u32 = 30;
+    pub const SQRT: u32 = 31;
+    pub const INVERSE_SQRT: u32 = 32;
+    pub const DETERMINANT: u32 = 33;
+    pub const MATRIX_INVERSE: u32 = 34;
+    pub const F_MIN: u32 = 37;
+    pub const S_MIN: u32 = 39;
+    pub const F_MAX: u32 = 40;
+    pub const S_MAX: u32 = 42;
+    pub const F_CLAMP: u32 = 43;
+    pub const S_CLAMP: u32 = 45;
+    pub const F_MIX: u32 = 46;
+    pub const STEP: u32 = 48;
+    pub const SMOOTH_STEP: u32 = 49;
+    pub const LENGTH: u32 = 66;
+    pub const DISTANCE: u32 = 67;
+    pub const CROSS: u32 = 68;
+    pub const NORMALIZE: u32 = 69;
+    pub const FACE_FORWARD: u32 = 70;
+    pub const REFLECT: u32 = 71;
+    pub const REFRACT: u32 = 72;
+}
+
+// operand layouts for the disassembler and assembler, one char per operand:
+// t result type, r result id, i id, l literal, c literal typed by the result type,
+// s string, k enum of the kind following it, * any number of ids, # any number of literals,
+// p (literal, id) pairs
+const OPCODES: &[(u16, &str, &str)] = &[
+    (op::NAME, "OpName", "is"),
+    (op::MEMBER_NAME, "OpMemberName", "ils"),
+    (op::EXT_INST_IMPORT, "OpExtInstImport", "rs"),
+    (op::EXT_INST, "OpExtInst", "trix*"),
+    (op::MEMORY_MODEL, "OpMemoryModel", "kAkM"),
+    (op::ENTRY_POINT, "OpEntryPoint", "kEis*"),
+    (op::EXECUTION_MODE, "OpExecutionMode", "ikX#"),
+    (op::CAPABILITY, "OpCapability", "kC"),
+    (op::TYPE_VOID, "OpTypeVoid", "r"),
+    (op::TYPE_BOOL, "OpTypeBool", "r"),
+    (op::TYPE_INT, "OpTypeInt", "rll"),
+    (op::TYPE_FLOAT, "OpTypeFloat", "rl"),
+    (op::TYPE_VECTOR, "OpTypeVector", "ril"),
+    (op::TYPE_MATRIX, "OpTypeMatrix", "ril"),
+    (op::TYPE_IMAGE, "OpTypeImage", "rilllll#"),
+    (op::TYPE_SAMPLER, "OpTypeSampler", "r"),
+    (op::TYPE_SAMPLED_IMAGE, "OpTypeSampledImage", "ri"),
+    (op::TYPE_ARRAY, "OpTypeArray", "rii"),
+    (op::TYPE_STRUCT, "OpTypeStruct", "r*"),
+    (op::TYPE_POINTER, "OpTypePointer", "rkSi"),
+    (op::TYPE_FUNCTION, "OpTypeFunction", "ri*"),
+    (op::CONSTANT_TRUE, "OpConstantTrue", "tr"),
+    (op::CONSTANT_FALSE, "OpConstantFalse", "tr"),
+    (op::CONSTANT, "OpConstant", "trc"),
+    (op::CONSTANT_COMPOSITE, "OpConstantComposite", "tr*"),
+    (op::FUNCTION, "OpFunction", "trli"),
+    (op::FUNCTION_PARAMETER, "OpFunctionParameter", "tr"),
+    (op::FUNCTION_END, "OpFunctionEnd", ""),
+    (op::FUNCTION_CALL, "OpFunctionCall", "tri*"),
+    (op::VARIABLE, "OpVariable", "trkS*"),
+    (op::LOAD, "OpLoad", "tri#"),
+    (op::STORE, "OpStore", "ii#"),
+    (op::ACCESS_CHAIN, "OpAccessChain", "tri*"),
+    (op::DECORATE, "OpDecorate", "ikD"),
+    (op::MEMBER_DECORATE, "OpMemberDecorate", "ilkD"),
+    (op::VECTOR_EXTRACT_DYNAMIC, "OpVectorExtractDynamic", "trii"),
+    (op::VECTOR_SHUFFLE, "OpVectorShuffle", "trii#"),
+    (op::COMPOSITE_CONSTRUCT, "OpCompositeConstruct", "tr*"),
+    (op::COMPOSITE_EXTRACT, "OpCompositeExtract", "tri#"),
+    (op::COMPOSITE_INSERT, "OpCompositeInsert", "trii#"),
+    (op::TRANSPOSE, "OpTranspose", "tri"),
+    (op::SAMPLED_IMAGE, "OpSampledImage", "trii"),
+    (op::IMAGE_SAMPLE_EXPLICIT_LOD, "OpImageSampleExplicitLod", "triil*"),
+    (op::CONVERT_F_TO_S, "OpConvertFToS", "tri"),
+    (op::CONVERT_S_TO_F, "OpConvertSToF", "tri"),
+    (op::S_NEGATE, "OpSNegate", "tri"),
+    (op::F_NEGATE, "OpFNegate", "tri"),
+    (op::I_ADD, "OpIAdd", "trii"),
+    (op::F_ADD, "OpFAdd", "trii"),
+    (op::I_SUB, "OpISub", "trii"),
+    (op::F_SUB, "OpFSub", "trii"),
+    (op::I_MUL, "OpIMul", "trii"),
+    (op::F_MUL, "OpFMul", "trii"),
+    (op::S_DIV, "OpSDiv", "trii"),
+    (op::F_DIV, "OpFDiv", "trii"),
+    (op::F_MOD, "OpFMod", "trii"),
+    (op::VECTOR_TIMES_SCALAR, "OpVectorTimesScalar", "trii"),
+    (op::MATRIX_TIMES_SCALAR, "OpMatrixTimesScalar", "trii"),
+    (op::VECTOR_TIMES_MATRIX, "OpVectorTimesMatrix", "trii"),
+    (op::MATRIX_TIMES_VECTOR, "OpMatrixTimesVector", "trii"),
+    (op::MATRIX_TIMES_MATRIX, "OpMatrixTimesMatrix", "trii"),
+    (op::DOT, "OpDot", "trii"),
+    (op::ANY, "OpAny", "tri"),
+    (op::ALL, "OpAll", "tri"),
+    (op::LOGICAL_EQUAL, "OpLogicalEqual", "trii"),
+    (op::LOGICAL_NOT_EQUAL, "OpLogicalNotEqual", "trii"),
+    (op::LOGICAL_OR, "OpLogicalOr", "trii"),
+    (op::LOGICAL_AND, "OpLogicalAnd", "trii"),
+    (op::LOGICAL_NOT, "OpLogicalNot", "tri"),
+    (op::SELECT, "OpSelect", "triii"),
+    (op::I_EQUAL, "OpIEqual", "trii"),
+    (op::I_NOT_EQUAL, "OpINotEqual", "trii"),
+    (op::S_GREATER_THAN, "OpSGreaterThan", "trii"),
+    (op::S_GREATER_THAN_EQUAL, "OpSGreaterThanEqual", "trii"),
+    (op::S_LESS_THAN, "OpSLessThan", "trii"),
+    (op::S_LESS_THAN_EQUAL, "OpSLessThanEqual", "trii"),
+    (op::F_ORD_EQUAL, "OpFOrdEqual", "trii"),
+    (op::F_ORD_NOT_EQUAL, "OpFOrdNotEqual", "trii"),
+    (op::F_ORD_LESS_THAN, "OpFOrdLessThan", "trii"),
+    (op::F_ORD_GREATER_THAN, "OpFOrdGreaterThan", "trii"),
+    (op::F_ORD_LESS_THAN_EQUAL, "OpFOrdLessThanEqual", "trii"),
+    (op::F_ORD_GREATER_THAN_EQUAL, "OpFOrdGreaterThanEqual", "trii"),
+    (op::DPDX, "OpDPdx", "tri"),
+    (op::DPDY, "OpDPdy", "tri"),
+    (op::PHI, "OpPhi", "tr*"),
+    (op::LOOP_MERGE, "OpLoopMerge", "iil#"),
+    (op::SELECTION_MERGE, "OpSelectionMerge", "il"),
+    (op::LABEL, "OpLabel", "r"),
+    (op::BRANCH, "OpBranch", "i"),
+    (op::BRANCH_CONDITIONAL, "OpBranchConditional", "iii#"),
+    (op::SWITCH, "OpSwitch", "iip"),
+    (op::RETURN, "OpReturn", ""),
+    (op::RETURN_VALUE, "OpReturnValue", "i"),
+    (op::UNREACHABLE, "OpUnreachable", ""),
+];
+
+const CAPABILITIES: &[(u32, &str)] = &[(capability::SHADER, "Shader")];
+const ADDRESSING_MODELS: &[(u32, &str)] = &[(addressing_model::LOGICAL, "Logical")];
+const MEMORY_MODELS: &[(u32, &str)] = &[(memory_model::GLSL450, "GLSL450")];
+const EXECUTION_MODELS: &[(u32, &str)] = &[(execution_model::VERTEX, "Vertex"), (execution_model::FRAGMENT, "Fragment")];
+const EXECUTION_MODES: &[(u32, &str)] = &[(execution_mode::ORIGIN_UPPER_LEFT, "OriginUpperLeft")];
+const STORAGE_CLASSES: &[(u32, &str)] = &[
+    (storage_class::UNIFORM_CONSTANT, "UniformConstant"),
+    (storage_class::INPUT, "Input"),
+    (storage_class::UNIFORM, "Uniform"),
+    (storage_class::OUTPUT, "Output"),
+    (storage_class::PRIVATE, "Private"),
+    (storage_class::FUNCTION, "Function"),
+];
+const DECORATIONS: &[(u32, &str)] = &[
+    (decoration::BLOCK, "Block"),
+    (decoration::ARRAY_STRIDE, "ArrayStride"),
+    (decoration::BUILT_IN, "BuiltIn"),
+    (decoration::LOCATION, "Location"),
+    (decoration::BINDING, "Binding"),
+    (decoration::DESCRIPTOR_SET, "DescriptorSet"),
+    (decoration::OFFSET, "Offset"),
+];
+const BUILT_INS: &[(u32, &str)] = &[(built_in::POSITION, "Position")];
+const GLSL_INSTS: &[(u32, &str)] = &[
+    (glsl::ROUND, "Round"),
+    (glsl::TRUNC, "Trunc"),
+    (glsl::F_ABS, "FAbs"),
+    (glsl::S_ABS, "SAbs"),
+    (glsl::F_SIGN, "FSign"),
+    (glsl::S_SIGN, "SSign"),
+    (glsl::FLOOR, "Floor"),
+    (glsl::CEIL, "Ceil"),
+    (glsl::FRACT, "Fract"),
+    (glsl::RADIANS, "Radians"),
+    (glsl::DEGREES, "Degrees"),
+    (glsl::SIN, "Sin"),
+    (glsl::COS, "Cos"),
+    (glsl::TAN, "Tan"),
+    (glsl::ASIN, "Asin"),
+    (glsl::ACOS, "Acos"),
+    (glsl::ATAN, "Atan"),
+    (glsl::ATAN2, "Atan2"),
+    (glsl::POW, "Pow"),
+    (glsl::EXP, "Exp"),
+    (glsl::LOG, "Log"),
+    (glsl::EXP2, "Exp2"),
+    (glsl::LOG2, "Log2"),
+    (glsl::SQRT, "Sqrt"),
+    (glsl::INVERSE_SQRT, "InverseSqrt"),
+    (glsl::DETERMINANT, "Determinant"),
+    (glsl::MATRIX_INVERSE, "MatrixInverse"),
+    (glsl::F_MIN, "FMin"),
+    (glsl::S_MIN, "SMin"),
+    (glsl::F_MAX, "FMax"),
+    (glsl::S_MAX, "SMax"),
+    (glsl::F_CLAMP, "FClamp"),
+    (glsl::S_CLAMP, "SClamp"),
+    (glsl::F_MIX, "FMix"),
+    (glsl::STEP, "Step"),
+    (glsl::SMOOTH_STEP, "SmoothStep"),
+    (glsl::LENGTH, "Length"),
+    (glsl::DISTANCE, "Distance"),
+    (glsl::CROSS, "Cross"),
+    (glsl::NORMALIZE, "Normalize"),
+    (glsl::FACE_FORWARD, "FaceForward"),
+    (glsl::REFLECT, "Reflect"),
+    (glsl::REFRACT, "Refract"),
+];
+
+fn enum_table(kind: char) -> &'static [(u32, &'static str)] {
+    match kind {
+        'C' => CAPABILITIES,
+        'A' => ADDRESSING_MODELS,
+        'M' => MEMORY_MODELS,
+        'E' => EXECUTION_MODELS,
+        'X' => EXECUTION_MODES,
+        'S' => STORAGE_CLASSES,
+        'D' => DECORATIONS,
+        'B' => BUILT_INS,
+        _ => panic!("unknown enum kind {}", kind)
+    }
+}
+
+/// A sequence of instructions, one per module section or function body.
+#[derive(Clone, Default, Debug)]
+pub struct SpirvWords(pub Vec<u32>);
+
+impl SpirvWords {
+    pub fn inst(&mut self, opcode: u16, operands: &[u32]) {
+        self.0.push(((operands.len() as u32 + 1) << 16) | opcode as u32);
+        self.0.extend_from_slice(operands);
+    }
+
+    /// An instruction whose operands end in a literal string.
+    pub fn inst_with_string(&mut self, opcode: u16, operands: &[u32], string: &str, rest: &[u32]) {
+        let mut all = operands.to_vec();
+        all.extend(string_to_words(string));
+        all.extend_from_slice(rest);
+        self.inst(opcode, &all);
+    }
+}
+
+/// Strings are nul terminated and padded to a whole number of little endian words.
+pub fn string_to_words(string: &str) -> Vec<u32> {
+    let mut bytes = string.as_bytes().to_vec();
+    bytes.push(0);
+    while !bytes.len().is_multiple_of(4) {
+        bytes.push(0);
+    }
+    bytes.chunks(4).map( | c | u32::from_le_bytes([c[0], c[1], c[2], c[3]])).collect()
+}
+
+fn words_to_string(words: &[u32]) -> Option<(String, usize)> {
+    let mut bytes = Vec::new();
+    for (index, word) in words.iter().enumerate() {
+        for byte in word.to_le_bytes() {
+            if byte == 0 {
+                return String::from_utf8(bytes).ok().map( | string | (string, index + 1))
+            }
+            bytes.push(byte);
+        }
+    }
+    None
+}
+
+/// Turns a binary module into text, one instruction per line in the style of spirv-dis.
+/// Ids are printed as %<number>, so the output can be assembled back to the same words.
+pub fn disassemble(words: &[u32]) -> Result<String, String> {
+    if words.len() < 5 {
+        return Err("module is shorter than its header".to_string())
+    }
+    if words[0] != MAGIC {
+        return Err(format!("bad magic number {:#010x}", words[0]))
+    }
+    let mut out = String::new();
+    writeln!(out, "; SPIR-V").unwrap();
+    writeln!(out, "; Version: {}.{}", (words[1] >> 16) & 0xff, (words[1] >> 8) & 0xff).unwrap();
+    writeln!(out, "; Generator: {}", words[2]).unwrap();
+    writeln!(out, "; Bound: {}", words[3]).unwrap();
+    writeln!(out, "; Schema: {}", words[4]).unwrap();
+
+    let opcodes: HashMap<u16, (&str, &str)> = OPCODES.iter().map( | (opcode, name, layout) | (*opcode, (*name, *layout))).collect();
+    let mut float_tys = Vec::new();
+    let mut glsl_sets = Vec::new();
+
+    let mut pos = 5;
+    while pos < words.len() {
+        let word_count = (words[pos] >> 16) as usize;
+        let opcode = (words[pos] & 0xffff) as u16;
+        if word_count == 0 || pos + word_count > words.len() {
+            return Err(format!("bad word count at word {}", pos))
+        }
+        let operands = &words[pos + 1..pos + word_count];
+        pos += word_count;
+
+        let (name, layout) = opcodes.get(&opcode).ok_or_else( || format!("unknown opcode {}", opcode)) ?;
+        let mut result_ty = None;
+        let mut result = None;
+        let mut parts = Vec::new();
+        let mut index = 0;
+        let mut layout_chars = layout.chars();
+        while let Some(kind) = layout_chars.next() {
+            match kind {
+                '*' | '#' | 'p' => {
+                    while index < operands.len() {
+                        if kind == 'p' {
+                            parts.push(format!("{}", operands[index]));
+                            index += 1;
+                            if index == operands.len() {
+                                return Err(format!("{} is missing a label", name))
+                            }
+                        }
+                        let word = operands[index];
+                        parts.push(if kind == '#' {format!("{}", word)} else {format!("%{}", word)});
+                        index += 1;
+                    }
+                    continue;
+                }
+                _ => ()
+            }
+            if index >= operands.len() {
+                // trailing optional operands are covered by the * and # kinds
+                return Err(format!("{} is missing operands", name))
+            }
+            let word = operands[index];
+            match kind {
+                't' => result_ty = Some(word),
+                'r' => result = Some(word),
+                'i' => parts.push(format!("%{}", word)),
+                'l' => parts.push(format!("{}", word)),
+                'x' => {
+                    let set = operands[index - 1];
+                    match GLSL_INSTS.iter().find( | (value, _) | *value == word) {
+                        Some((_, name)) if glsl_sets.contains(&set) => parts.push(name.to_string()),
+                        _ => parts.push(format!("{}", word))
+                    }
+                }
+                'c' => {
+                    if float_tys.contains(&result_ty.unwrap()) {
+                        parts.push(format!("{:?}", f32::from_bits(word)));
+                    }
+                    else {
+                        parts.push(format!("{}", word as i32));
+                    }
+                }
+                's' => {
+                    let (string, len) = words_to_string(&operands[index..]).ok_or_else( || format!("{} has a bad string", name)) ?;
+                    if *name == "OpExtInstImport" && string == GLSL_STD_450 {
+                        glsl_sets.push(result.unwrap());
+                    }
+                    parts.push(format!("{:?}", string));
+                    index += len;
+                    continue;
+                }
+                'k' => {
+                    let enum_kind = layout_chars.next().unwrap();
+                    let table = enum_table(enum_kind);
+                    match table.iter().find( | (value, _) | *value == word) {
+                        Some((_, name)) => parts.push(name.to_string()),
+                        None => parts.push(format!("{}", word))
+                    }
+                    // decorations carry their own literals, BuiltIn names its builtin
+                    if enum_kind == 'D' {
+                        index += 1;
+                        while index < operands.len() {
+                            let literal = operands[index];
+                            match BUILT_INS.iter().find( | (value, _) | *value == literal) {
+                                Some((_, name)) if word == decoration::BUILT_IN => parts.push(name.to_string()),
+                                _ => parts.push(format!("{}", literal))
+                            }
+                            index += 1;
+                        }
+                        continue;
+                    }
+                }
+                _ => panic!()
+            }
+            index += 1;
+        }
+        if index != operands.len() {
+            return Err(format!("{} has {} operands left over", name, operands.len() - index))
+        }
+        if opcode == op::TYPE_FLOAT {
+            float_tys.push(result.unwrap());
+        }
+
+        if let Some(result) = result {
+            write!(out, "%{} = ", result).unwrap();
+        }
+        write!(out, "{}", name).unwrap();
+        if let Some(result_ty) = result_ty {
+            write!(out, " %{}", result_ty).unwrap();
+        }
+        for part in parts {
+            write!(out, " {}", part).unwrap();
+        }
+        writeln!(out).unwrap();
+    }
+    Ok(out)
+}
+
+fn tokenize_line(line: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        if c == '"' {
+            let mut string = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => string.push('\n'),
+                        Some('t') => string.push('\t'),
+                        Some(c) => string.push(c),
+                        None => return Err("unterminated string".to_string())
+                    },
+                    Some(c) => string.push(c),
+                    None => return Err("unterminated string".to_string())
+                }
+            }
+            tokens.push(format!("\"{}", string));
+            continue;
+        }
+        let mut token = c.to_string();
+        while let Some(c) = chars.peek() {
+            if c.is_whitespace() {
+                break;
+            }
+            token.push(*c);
+            chars.next();
+        }
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn parse_id(token: &str) -> Result<u32, String> {
+    token.strip_prefix('%').and_then( | id | id.parse().ok()).ok_or_else( || format!("expected an id, got {}", token))
+}
+
+fn parse_literal(token: &str) -> Result<u32, String> {
+    token.parse::<u32>().or_else( | _ | token.parse::<i32>().map( | v | v as u32)).map_err( | _ | format!("expected a literal, got {}", token))
+}
+
+fn parse_enum(table: &[(u32, &str)], token: &str) -> Result<u32, String> {
+    match table.iter().find( | (_, name) | *name == token) {
+        Some((value, _)) => Ok(*value),
+        None => parse_literal(token)
+    }
+}
+
+/// Turns the text that `disassemble` produces back into a binary module.
+pub fn assemble(text: &str) -> Result<Vec<u32>, String> {
+    let mut header = [MAGIC, VERSION_1_0, 0, 0, 0];
+    let mut body = SpirvWords::default();
+    let opcodes: HashMap<&str, (u16, &str)> = OPCODES.iter().map( | (opcode, name, layout) | (*name, (*opcode, *layout))).collect();
+    let mut float_tys = Vec::new();
+    let mut glsl_sets = Vec::new();
+    let mut max_id = 0;
+
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(comment) = line.strip_prefix(';') {
+            let comment = comment.trim();
+            if let Some(version) = comment.strip_prefix("Version:") {
+                let mut parts = version.trim().split('.');
+                let major: u32 = parts.next().and_then( | v | v.parse().ok()).ok_or("bad version") ?;
+                let minor: u32 = parts.next().and_then( | v | v.parse().ok()).ok_or("bad version") ?;
+                header[1] = (major << 16) | (minor << 8);
+            }
+            else if let Some(generator) = comment.strip_prefix("Generator:") {
+                header[2] = parse_literal(generator.trim()) ?;
+            }
+            else if let Some(bound) = comment.strip_prefix("Bound:") {
+                header[3] = parse_literal(bound.trim()) ?;
+            }
+            else if let Some(schema) = comment.strip_prefix("Schema:") {
+                header[4] = parse_literal(schema.trim()) ?;
+            }
+            continue;
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let mut tokens = tokenize_line(line) ?;
+        let result = if tokens.len() > 2 && tokens[1] == "=" {
+            let result = parse_id(&tokens[0]) ?;
+            tokens.drain(0..2);
+            Some(result)
+        }
+        else {
+            None
+        };
+        let name = tokens.remove(0);
+        let (opcode, layout) = *opcodes.get(name.as_str()).ok_or_else( || format!("unknown instruction {}", name)) ?;
+
+        let mut operands = Vec::new();
+        let mut tokens = tokens.into_iter().peekable();
+        let mut result_ty = None;
+        let mut layout_chars = layout.chars();
+        while let Some(kind) = layout_chars.next() {
+            match kind {
+                'r' => {
+                    let result = result.ok_or_else( || format!("{} needs a result id", name)) ?;
+                    max_id = max_id.max(result);
+                    operands.push(result);
+                }
+                '*' | 'p' | '#' => {
+                    for token in tokens.by_ref() {
+                        if token.starts_with('%') {
+                            let id = parse_id(&token) ?;
+                            max_id = max_id.max(id);
+                            operands.push(id);
+                        }
+                        else {
+                            operands.push(parse_literal(&token) ?);
+                        }
+                    }
+                }
+                _ => {
+                    let token = tokens.next().ok_or_else( || format!("{} is missing operands", name)) ?;
+                    match kind {
+                        't' => {
+                            let id = parse_id(&token) ?;
+                            result_ty = Some(id);
+                            operands.push(id);
+                        }
+                        'i' => {
+                            let id = parse_id(&token) ?;
+                            max_id = max_id.max(id);
+                            operands.push(id);
+                        }
+                        'l' => operands.push(parse_literal(&token) ?),
+                        'x' => {
+                            let set = *operands.last().unwrap();
+                            match GLSL_INSTS.iter().find( | (_, name) | *name == token) {
+                                Some((value, _)) if glsl_sets.contains(&set) => operands.push(*value),
+                                _ => operands.push(parse_literal(&token) ?)
+                            }
+                        }
+                        'c' => {
+                            if float_tys.contains(&result_ty.unwrap()) {
+                                operands.push(token.parse::<f32>().map_err( | _ | format!("expected a float, got {}", token)) ?.to_bits());
+                            }
+                            else {
+                                operands.push(parse_literal(&token) ?);
+                            }
+                        }
+                        's' => {
+                            let string = token.strip_prefix('"').ok_or_else( || format!("expected a string, got {}", token)) ?;
+                            if name == "OpExtInstImport" && string == GLSL_STD_450 {
+                                glsl_sets.push(result.unwrap());
+                            }
+                            operands.extend(string_to_words(string));
+                        }
+                        'k' => {
+                            let enum_kind = layout_chars.next().unwrap();
+                            let value = parse_enum(enum_table(enum_kind), &token) ?;
+                            operands.push(value);
+                            if enum_kind == 'D' {
+                                for token in tokens.by_ref() {
+                                    if value == decoration::BUILT_IN {
+                                        operands.push(parse_enum(BUILT_INS, &token) ?);
+                                    }
+                                    else {
+                                        operands.push(parse_literal(&token) ?);
+                                    }
+                                }
+                            }
+                        }
+                        _ => panic!()
+                    }
+                }
+            }
+        }
+        if let Some(token) = tokens.next() {
+            return Err(format!("unexpected operand {} for {}", token, name))
+        }
+        if opcode == op::TYPE_FLOAT {
+            float_tys.push(result.unwrap());
+        }
+        body.inst(opcode, &operands);
+    }
+    if header[3] == 0 {
+        header[3] = max_id + 1;
+    }
+    let mut words = header.to_vec();
+    words.extend(body.0);
+    Ok(words)
+}
@@ -0,0 +1,141 @@
+// every test binary includes this module, but not all of them use all of it
+#![allow(dead_code)]
+
+use makepad_shader_compiler::{
+    makepad_live_id::{id, LiveId},
+    makepad_live_compiler::{
+        LiveRegistry,
+        LiveModuleId,
+        LiveTypeInfo,
+        LiveFieldKind,
+        TextPos,
+    },
+    shader_ast::{Ty, DrawShaderDef},
+    DrawShaderPtr,
+    DrawShaderQuery,
+    ShaderRegistry,
+};
+
+// the std library shapes are tested through the live body of the platform std module
+pub const STD: &str = include_str!("../../../src/shader/std.rs");
+
+/// A draw shader that goes through most of the language: a struct with inout methods and a
+/// closure param, swizzle assignments, branches, the std `Sdf2d` and a texture.
+pub const DRAW_TEST: &str = r#"
+    Counter: Struct {
+        field count: float
+        fn bump(inout self, by: float) {
+            self.count += by;
+        }
+        fn apply(inout self, f: fn(x: float) -> float) {
+            self.count = f(self.count);
+        }
+    }
+
+    DrawTest: {{DrawTest}} {
+        varying pos: vec2
+        instance color: #f00
+        texture tex: texture2d
+
+        fn scroll_and_clip_quad(self) -> vec4 {
+            let scr = self.draw_scroll.xy;
+            let clipped: vec2 = clamp(
+                self.geom_pos * self.rect_size + self.rect_pos - scr,
+                self.draw_clip.xy,
+                self.draw_clip.zw
+            );
+            self.pos = (clipped + scr - self.rect_pos) / self.rect_size;
+            return self.camera_projection * (self.camera_view * (self.view_transform * vec4(
+                clipped.x,
+                clipped.y,
+                self.draw_depth + self.draw_zbias,
+                1.
+            )));
+        }
+
+        fn vertex(self) -> vec4 {
+            return self.scroll_and_clip_quad();
+        }
+
+        fn language(self) -> vec4 {
+            let c = Counter {count: 1.0};
+            c.bump(2.0);
+            let k = 2.0;
+            c.apply( | x | x * k + 1.0);
+            let acc = 0.0;
+            if c.count > 5.0 {
+                acc = 10.0;
+            }
+            else {
+                acc = -1.0;
+            }
+            let v = vec4(1.0, 2.0, 3.0, 4.0);
+            v.xy = v.wz;
+            let w = 0.0;
+            if v.z > 2.0 && !(v.w < 0.0) {
+                w = PI;
+            }
+            return vec4(c.count, acc, v.x + v.y, w);
+        }
+
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            sdf.circle(5.0, 5.0, 4.0);
+            // an unbound texture reads as transparent black, and language is only there to be
+            // generated, it doesn't change the color
+            return sdf.fill(self.color) + sample2d(self.tex, self.pos) + self.language() * 0.0;
+        }
+    }
+"#;
+
+pub struct DrawTest();
+
+pub struct Setup {
+    pub live_registry: LiveRegistry,
+    pub shader_registry: ShaderRegistry,
+    pub draw_shader_ptr: DrawShaderPtr,
+    // the live source the errors and spans point into, with the std library in front
+    pub source: String,
+}
+
+impl Setup {
+    pub fn draw_shader_def(&self) -> &DrawShaderDef {
+        self.shader_registry.draw_shader_defs.get(&self.draw_shader_ptr).unwrap()
+    }
+}
+
+/// Registers `source` after the std library as `file_name`, and analyses its `DrawTest` with
+/// the inputs `DrawQuad` gives a shader.
+pub fn setup(file_name: &str, source: &str) -> Setup {
+    let std_body = &STD[STD.find("live_register!{").unwrap() + "live_register!{".len()..STD.rfind('}').unwrap()];
+    let source = format!("{}\n{}", std_body, source);
+
+    let module_id = LiveModuleId::from_str(&format!("test::{}", file_name.trim_end_matches(".rs"))).unwrap();
+    let mut live_registry = LiveRegistry::default();
+    let live_type_info = LiveTypeInfo {
+        live_type: std::any::TypeId::of::<DrawTest>(),
+        type_name: id!(DrawTest),
+        module_id,
+        fields: Vec::new()
+    };
+    if let Err(err) = live_registry.register_live_file(file_name, module_id, source.clone(), vec![live_type_info], TextPos::default()) {
+        panic!("{}", err);
+    }
+    let mut errors = Vec::new();
+    live_registry.expand_all_documents(&mut errors);
+    for err in errors {
+        panic!("{}", live_registry.live_error_to_live_file_error(err));
+    }
+
+    let draw_shader_ptr = DrawShaderPtr(live_registry.module_id_and_name_to_ptr(module_id, id!(DrawTest)).unwrap());
+    let mut shader_registry = ShaderRegistry::new();
+    shader_registry.analyse_draw_shader(&live_registry, draw_shader_ptr, | _, _, span, query, _, draw_shader_def | {
+        if let DrawShaderQuery::DrawShader = query {
+            draw_shader_def.add_geometry(id!(geom_pos), Ty::Vec2, span);
+            draw_shader_def.add_instance(id!(rect_pos), Ty::Vec2, span, LiveFieldKind::Calc);
+            draw_shader_def.add_instance(id!(rect_size), Ty::Vec2, span, LiveFieldKind::Calc);
+            draw_shader_def.add_instance(id!(draw_depth), Ty::Float, span, LiveFieldKind::Calc);
+        }
+    }).unwrap_or_else( | err | panic!("{}", live_registry.live_error_to_live_file_error(err)));
+    Setup {live_registry, shader_registry, draw_shader_ptr, source}
+}
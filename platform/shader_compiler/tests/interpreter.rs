@@ -0,0 +1,80 @@
+mod common;
+
+use makepad_shader_compiler::{
+    makepad_live_id::{id, LiveId},
+    makepad_live_compiler::makepad_math::{Vec2, Vec4, Mat4},
+    shader_ast::{Ident, TyLit},
+    interpreter::{ShaderInterpreter, Value, Dual},
+};
+
+fn interpreter(setup: &common::Setup) -> ShaderInterpreter {
+    let mut interp = ShaderInterpreter::new(&setup.shader_registry, setup.draw_shader_def());
+    interp.load_live_values(&setup.live_registry).unwrap();
+    interp
+}
+
+fn assert_near(a: Vec4, b: Vec4) {
+    assert!(
+        (a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4 && (a.z - b.z).abs() < 1e-4 && (a.w - b.w).abs() < 1e-4,
+        "{:?} != {:?}",
+        a,
+        b
+    );
+}
+
+#[test]
+fn language() {
+    let setup = common::setup("interpreter.rs", common::DRAW_TEST);
+    let mut interp = interpreter(&setup);
+
+    let result = interp.call_method(Ident(id!(language)), Vec::new()).to_vec4().unwrap();
+    assert_near(result, Vec4 {x: 7.0, y: 10.0, z: 7.0, w: std::f32::consts::PI});
+}
+
+#[test]
+fn vertex() {
+    let setup = common::setup("interpreter.rs", common::DRAW_TEST);
+    let mut interp = interpreter(&setup);
+
+    let identity = Value::mat4(&Mat4::identity());
+    interp.set_field(Ident(id!(camera_projection)), identity.clone());
+    interp.set_field(Ident(id!(camera_view)), identity.clone());
+    interp.set_field(Ident(id!(view_transform)), identity);
+    interp.set_field(Ident(id!(draw_clip)), Value::vec4(Vec4 {x: 0.0, y: 0.0, z: 15.0, w: 100.0}));
+    interp.set_field(Ident(id!(draw_scroll)), Value::vec4(Vec4::default()));
+    interp.set_field(Ident(id!(draw_zbias)), Value::float(0.5));
+    interp.set_field(Ident(id!(draw_depth)), Value::float(1.0));
+    interp.set_field(Ident(id!(rect_pos)), Value::vec2(Vec2 {x: 10.0, y: 20.0}));
+    interp.set_field(Ident(id!(rect_size)), Value::vec2(Vec2 {x: 10.0, y: 10.0}));
+    interp.set_field(Ident(id!(geom_pos)), Value::vec2(Vec2 {x: 1.0, y: 1.0}));
+
+    // the right edge is clipped at x = 15
+    let position = interp.vertex().to_vec4().unwrap();
+    assert_near(position, Vec4 {x: 15.0, y: 30.0, z: 1.5, w: 1.0});
+    let pos = interp.field(Ident(id!(pos))).unwrap().to_vec2().unwrap();
+    assert_eq!(pos, Vec2 {x: 0.5, y: 1.0});
+}
+
+#[test]
+fn pixel() {
+    let setup = common::setup("interpreter.rs", common::DRAW_TEST);
+    let mut interp = interpreter(&setup);
+    interp.set_field(Ident(id!(rect_size)), Value::vec2(Vec2 {x: 10.0, y: 10.0}));
+    interp.set_field(Ident(id!(color)), Value::vec4(Vec4 {x: 1.0, y: 0.0, z: 0.0, w: 1.0}));
+
+    let mut shade = | x: f32, y: f32 | {
+        // one pixel step along x or y moves pos by 1 / rect_size
+        interp.set_field(Ident(id!(pos)), Value::Float(TyLit::Vec2, vec![
+            Dual::with_derivs(x / 10.0, 0.1, 0.0),
+            Dual::with_derivs(y / 10.0, 0.0, 0.1),
+        ]));
+        interp.pixel().to_vec4().unwrap()
+    };
+
+    assert_near(shade(5.0, 5.0), Vec4 {x: 1.0, y: 0.0, z: 0.0, w: 1.0});
+    assert_near(shade(0.5, 0.5), Vec4::default());
+    // half a pixel inside the edge the coverage is partial
+    let edge = shade(8.5, 5.0);
+    assert!(edge.w > 0.2 && edge.w < 0.5, "{:?}", edge);
+    assert_eq!(edge.x, edge.w);
+}
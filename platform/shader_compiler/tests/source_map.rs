@@ -0,0 +1,93 @@
+mod common;
+
+use makepad_shader_compiler::{
+    makepad_live_compiler::LiveRegistry,
+    generate_glsl,
+    ShaderSourceMap,
+};
+
+const SOURCE: &str = r#"
+    DrawTest: {{DrawTest}} {
+        varying pos: vec2
+        instance color: #f00
+
+        fn vertex(self) -> vec4 {
+            self.pos = self.geom_pos;
+            return vec4(self.geom_pos * self.rect_size + self.rect_pos, self.draw_depth, 1.);
+        }
+
+        fn pixel(self) -> vec4 {
+            let fade = 0.25;
+            let marker = 42.0;
+            return self.color * fade * marker;
+        }
+    }
+"#;
+
+struct Generated {
+    live_registry: LiveRegistry,
+    source: String,
+    pixel: String,
+    source_map: ShaderSourceMap,
+}
+
+fn generate() -> Generated {
+    let setup = common::setup("source_map.rs", SOURCE);
+    let const_table = setup.shader_registry.compute_const_table(setup.draw_shader_ptr);
+    let (pixel, source_map) = generate_glsl::generate_pixel_shader_with_source_map(setup.draw_shader_def(), &const_table, &setup.shader_registry);
+    Generated {live_registry: setup.live_registry, source: setup.source, pixel, source_map}
+}
+
+// the 1 based line of the generated code and the 0 based line of the DSL declaring the local
+fn lines_of(gen: &Generated, local: &str) -> (usize, usize) {
+    let generated = gen.pixel.lines().position( | line | line.contains(&format!("var_{}_", local))).unwrap_or_else( || panic!("{} not in\n{}", local, gen.pixel));
+    let dsl = gen.source.lines().position( | line | line.contains(&format!("let {} =", local))).unwrap();
+    (generated + 1, dsl)
+}
+
+fn mapped_lines(gen: &Generated, log: &str, prelude_lines: usize) -> Vec<(usize, String)> {
+    gen.source_map.log_to_live_errors(log, prelude_lines).into_iter().map( | err | {
+        let err = gen.live_registry.live_error_to_live_file_error(err);
+        assert_eq!(err.file, "source_map.rs");
+        (err.span.start.line as usize, err.message)
+    }).collect()
+}
+
+#[test]
+fn log_formats() {
+    let gen = generate();
+    let (line, dsl_line) = lines_of(&gen, "marker");
+    for log in [
+        format!("0:{}(12): error: bad marker", line),
+        format!("0({}) : error C1008: bad marker", line),
+        format!("ERROR: 0:{}: bad marker", line),
+        format!("program_source:{}:5: error: bad marker", line),
+        format!("shader.hlsl({},5): error X3004: bad marker", line),
+    ] {
+        let errors = mapped_lines(&gen, &log, 0);
+        assert_eq!(errors.len(), 1, "{}", log);
+        assert_eq!(errors[0].0, dsl_line, "{}\n{}", log, gen.pixel);
+        assert!(errors[0].1.ends_with("bad marker"), "{}", errors[0].1);
+    }
+}
+
+#[test]
+fn statements() {
+    let gen = generate();
+    // neighbouring statements map to their own line in the DSL
+    for local in ["fade", "marker"] {
+        let (line, dsl_line) = lines_of(&gen, local);
+        let errors = mapped_lines(&gen, &format!("0:{}(1): error: x", line), 0);
+        assert_eq!(errors[0].0, dsl_line, "{}\n{}", local, gen.pixel);
+    }
+    assert!(gen.source_map.line_to_token_id(0).is_none());
+}
+
+#[test]
+fn prelude() {
+    let gen = generate();
+    let (line, dsl_line) = lines_of(&gen, "marker");
+    let log = format!("0:3(1): error: in the prelude\n0:{}(1): error: bad marker\nlink failed", line + 7);
+    let errors = mapped_lines(&gen, &log, 7);
+    assert_eq!(errors, vec![(dsl_line, "error: bad marker".to_string())]);
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::collections::HashSet;
+use makepad_shader_compiler::{
+    generate_spirv,
+    spirv,
+};
+
+fn generate() -> Vec<u32> {
+    let setup = common::setup("spirv.rs", common::DRAW_TEST);
+    let const_table = setup.shader_registry.compute_const_table(setup.draw_shader_ptr);
+    generate_spirv::generate_shader(setup.draw_shader_def(), &const_table, &setup.shader_registry)
+}
+
+// naga is an independent reader of the module, so this checks the ids, types and
+// instructions against the SPIR-V rules rather than against our own assembler
+#[test]
+fn validates() {
+    let words = generate();
+    let bytes: Vec<u8> = words.iter().flat_map( | word | word.to_le_bytes()).collect();
+    let module = naga::front::spv::parse_u8_slice(&bytes, &naga::front::spv::Options::default())
+        .unwrap_or_else( | err | panic!("{:?}\n{}", err, spirv::disassemble(&words).unwrap()));
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .unwrap_or_else( | err | panic!("{:?}\n{}", err, spirv::disassemble(&words).unwrap()));
+}
+
+#[test]
+fn interface() {
+    let text = spirv::disassemble(&generate()).unwrap();
+    for expected in [
+        "OpCapability Shader",
+        "OpExtInstImport \"GLSL.std.450\"",
+        "OpMemoryModel Logical GLSL450",
+        "OpEntryPoint Vertex",
+        "\"vertex_main\"",
+        "OpEntryPoint Fragment",
+        "\"fragment_main\"",
+        "OpExecutionMode",
+        "OriginUpperLeft",
+        "BuiltIn Position",
+        "OpImageSampleExplicitLod",
+    ] {
+        assert!(text.contains(expected), "missing {}\n{}", expected, text);
+    }
+    // the uniform tables are bound in set 0, the texture and its sampler in set 1
+    for expected in ["DescriptorSet 0", "DescriptorSet 1"] {
+        assert!(text.contains(expected), "missing {}\n{}", expected, text);
+    }
+}
+
+#[test]
+fn ids() {
+    let words = generate();
+    let bound = words[3];
+    let text = spirv::disassemble(&words).unwrap();
+    let mut defined = HashSet::new();
+    for line in text.lines().filter( | line | !line.starts_with(';')) {
+        if let Some((result, _)) = line.trim().split_once(" = ") {
+            assert!(defined.insert(result.to_string()), "{} is defined twice", result);
+        }
+    }
+    assert!(defined.len() < bound as usize);
+    // forward references are fine, but every id has to be defined somewhere
+    for line in text.lines() {
+        for word in line.split_whitespace().filter( | word | word.starts_with('%')) {
+            assert!(defined.contains(word), "{} is not defined\n{}", word, line);
+        }
+    }
+}
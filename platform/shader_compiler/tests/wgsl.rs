@@ -0,0 +1,84 @@
+mod common;
+
+use makepad_shader_compiler::generate_wgsl;
+
+// every shape, combinator and color helper of the std library, so the whole of it goes through
+// the WGSL backend
+const STD_SHADER: &str = r#"
+    DrawTest: {{DrawTest}} {
+        varying pos: vec2
+        instance color: #f00
+
+        fn vertex(self) -> vec4 {
+            self.pos = self.geom_pos;
+            let p = vec4(self.geom_pos * self.rect_size + self.rect_pos, self.draw_depth, 1.);
+            return self.camera_projection * (self.camera_view * (self.view_transform * p));
+        }
+
+        fn pixel(self) -> vec4 {
+            let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+            sdf.clear(Pal::premul(self.color));
+            sdf.translate(1.0, 2.0);
+            sdf.rotate(0.5, 3.0, 3.0);
+            sdf.scale(2.0, 3.0, 3.0);
+            sdf.circle(5.0, 5.0, 4.0);
+            sdf.box(1.0, 1.0, 8.0, 8.0, 2.0);
+            sdf.union();
+            sdf.box_x(1.0, 1.0, 8.0, 8.0, 1.0, 2.0);
+            sdf.intersect();
+            sdf.box_y(1.0, 1.0, 8.0, 8.0, 1.0, 2.0);
+            sdf.subtract();
+            sdf.box_all(1.0, 1.0, 8.0, 8.0, 1.0, 2.0, 3.0, 4.0);
+            sdf.gloop(0.5);
+            sdf.rect(0.0, 0.0, 4.0, 4.0);
+            sdf.blend(0.5);
+            sdf.hexagon(5.0, 5.0, 3.0);
+            sdf.fill_keep(self.color);
+            sdf.stroke_keep(self.color, 1.0);
+            sdf.glow_keep(self.color, 1.0);
+            sdf.move_to(0.0, 0.0);
+            sdf.line_to(10.0, 0.0);
+            sdf.line_to(5.0, 10.0);
+            sdf.close_path();
+            sdf.stroke(#fff, 1.0);
+            sdf.glow(#0f0, 2.0);
+            let hsv = Pal::rgb2hsv(sdf.fill(self.color));
+            let pal = Pal::iq0(hsv.x) + Pal::iq1(hsv.y) + Pal::iq2(hsv.z) + Pal::iq3(hsv.x)
+                + Pal::iq4(hsv.y) + Pal::iq5(hsv.z) + Pal::iq6(hsv.x) + Pal::iq7(hsv.y);
+            let rot = Math::rotate_2d(self.pos, hsv.x * TORAD);
+            return Pal::hsv2rgb(hsv) + vec4(pal, rot.x * rot.y) * 0.0;
+        }
+    }
+"#;
+
+fn generate(file_name: &str, source: &str) -> String {
+    let setup = common::setup(file_name, source);
+    let const_table = setup.shader_registry.compute_const_table(setup.draw_shader_ptr);
+    generate_wgsl::generate_shader(setup.draw_shader_def(), &const_table, &setup.shader_registry)
+}
+
+// naga is what wgpu compiles WGSL with, so parsing and validating with it is what a browser or
+// a native wgpu backend would do with the output
+fn validate(wgsl: &str) {
+    let module = naga::front::wgsl::parse_str(wgsl)
+        .unwrap_or_else( | err | panic!("{}\n{}", err.emit_to_string(wgsl), wgsl));
+    naga::valid::Validator::new(naga::valid::ValidationFlags::all(), naga::valid::Capabilities::all())
+        .validate(&module)
+        .unwrap_or_else( | err | panic!("{:?}\n{}", err, wgsl));
+}
+
+#[test]
+fn std_library_validates() {
+    validate(&generate("wgsl_std.rs", STD_SHADER));
+}
+
+#[test]
+fn language_validates() {
+    validate(&generate("wgsl_language.rs", common::DRAW_TEST));
+}
+
+#[test]
+fn chained_assignments_are_split() {
+    let wgsl = generate("wgsl_std.rs", STD_SHADER);
+    assert!(!wgsl.lines().any( | line | line.matches(" = ").count() > 1), "{}", wgsl);
+}
@@ -93,6 +93,7 @@ pub struct Cx {
     
     pub draw_lists: Vec<DrawList>,
     pub draw_lists_free: Rc<RefCell<Vec<usize >> >,
+    pub overlay_draw_lists: Vec<usize>,
     
     pub textures: Vec<CxTexture>,
     pub textures_free: Arc<RefCell<Vec<usize >> >,
@@ -165,6 +166,7 @@ pub enum PlatformType {
     MsWindows,
     OSX,
     Linux {custom_window_chrome: bool},
+    Headless,
     WebBrowser {protocol: String, host:String, hostname: String, pathname: String, search: String, hash: String}
 }
 
@@ -175,6 +177,7 @@ impl PlatformType {
             PlatformType::MsWindows => true,
             PlatformType::OSX => true,
             PlatformType::Linux {..} => true,
+            PlatformType::Headless => true,
             PlatformType::WebBrowser {..} => false
         }
     }
@@ -242,6 +245,7 @@ impl Default for Cx {
             
             draw_lists: Vec::new(),
             draw_lists_free: Rc::new(RefCell::new(Vec::new())),
+            overlay_draw_lists: Vec::new(),
             
             textures: textures,
             textures_free: Arc::new(RefCell::new(Vec::new())),
@@ -256,6 +256,37 @@ impl Cx {
         self.key_focus == focus_area
     }
     
+    // returns true if an overlay view that is on top of the area covers the position in the window,
+    // content below an open popup shouldn't respond to a finger on the popup
+    pub fn is_covered_by_overlay(&self, area: Area, window_id: usize, abs: Vec2) -> bool {
+        // areas in an overlay are only covered by the overlays drawn after it
+        let mut first_on_top = 0;
+        let mut next = area.draw_list_id();
+        while let Some(draw_list_id) = next {
+            if let Some(index) = self.overlay_draw_lists.iter().position( | id | *id == draw_list_id) {
+                first_on_top = index + 1;
+                break;
+            }
+            next = self.draw_lists[draw_list_id].codeflow_parent_id;
+        }
+        self.overlay_draw_lists[first_on_top..].iter().any( | overlay_id | {
+            let overlay = &self.draw_lists[*overlay_id];
+            self.get_pass_window_id(overlay.pass_id) == Some(window_id) && overlay.rect.contains(abs)
+        })
+    }
+    
+    pub fn get_pass_window_id(&self, pass_id: usize) -> Option<usize> {
+        let mut pass_id_walk = pass_id;
+        for _ in 0..25 {
+            match self.passes[pass_id_walk].parent {
+                CxPassParent::Window(window_id) => return Some(window_id),
+                CxPassParent::Pass(next_pass_id) => pass_id_walk = next_pass_id,
+                _ => break
+            }
+        }
+        None
+    }
+    
     pub fn new_next_frame(&mut self) -> NextFrame {
         let res = NextFrame(self.next_frame_id);
         self.next_frame_id += 1;
@@ -9,6 +9,7 @@ use {
     },
     crate::{
         makepad_shader_compiler::*,
+        makepad_live_compiler::LiveFileError,
         makepad_live_id::*,
         live_traits::*,
         draw_vars::DrawVars,
@@ -78,6 +79,7 @@ pub struct CxDrawShaders {
     pub fingerprints: Vec<DrawShaderFingerprint>,
     pub error_set: HashSet<DrawShaderPtr>,
     pub error_fingerprints: Vec<Vec<LiveNode >>,
+    pub compile_errors: Vec<LiveFileError>,
 }
 
 impl Cx {
@@ -89,6 +91,18 @@ impl Cx {
         self.draw_shaders.fingerprints.clear();
         self.draw_shaders.error_set.clear();
         self.draw_shaders.error_fingerprints.clear();
+        self.draw_shaders.compile_errors.clear();
+    }
+    
+    // maps a backend compiler log back onto the live DSL and keeps the errors around for the log view
+    pub fn add_draw_shader_compile_errors(&mut self, source_map: &ShaderSourceMap, log: &str, prelude_lines: usize) {
+        let live_registry = self.live_registry.borrow();
+        for live_error in source_map.log_to_live_errors(log, prelude_lines) {
+            self.draw_shaders.compile_errors.push(live_registry.live_error_to_live_file_error(live_error));
+        }
+        drop(live_registry);
+        // shaders compile after the draw, so the log view only gets to show the errors on the next one
+        self.redraw_all();
     }
 }
 
@@ -176,11 +190,11 @@ pub struct DrawShaderInput {
 }
 
 
-#[cfg(any(target_os = "linux", target_arch = "wasm32", test))]
+#[cfg(any(target_os = "linux", target_arch = "wasm32"))]
 pub const DRAW_SHADER_INPUT_PACKING: DrawShaderInputPacking = DrawShaderInputPacking::UniformsGLSL;
-#[cfg(any(target_os = "macos", test))]
+#[cfg(target_os = "macos")]
 pub const DRAW_SHADER_INPUT_PACKING: DrawShaderInputPacking = DrawShaderInputPacking::UniformsMetal;
-#[cfg(any(target_os = "windows", test))]
+#[cfg(target_os = "windows")]
 pub const DRAW_SHADER_INPUT_PACKING: DrawShaderInputPacking = DrawShaderInputPacking::UniformsHLSL;
 
 impl DrawShaderInputs {
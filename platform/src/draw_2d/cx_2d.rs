@@ -34,6 +34,16 @@ pub struct Cx2d<'a> {
     pub turtle_walks: Vec<TurtleWalk>,
     pub align_list: Vec<Area>,
     pub current_dpi_factor: f32,
+    pub pending_overlay_draw_lists: Vec<usize>,
+    pub overlay_layouts: Vec<OverlayLayout>,
+}
+
+// the layout of the code that began an overlay view, put aside while the overlay is drawn so the
+// overlay is laid out on its own and never moved by the alignment of the turtle it was drawn in
+pub struct OverlayLayout {
+    turtles: Vec<Turtle>,
+    turtle_walks: Vec<TurtleWalk>,
+    align_list: Vec<Area>,
 }
 
 impl<'a> Deref for Cx2d<'a> {type Target = Cx; fn deref(&self) -> &Self::Target {self.cx}}
@@ -51,6 +61,8 @@ impl<'a> Cx2d<'a> {
             turtle_walks: Vec::new(),
             turtles: Vec::new(),
             align_list: Vec::new(),
+            pending_overlay_draw_lists: Vec::new(),
+            overlay_layouts: Vec::new(),
         }
     }
     
@@ -89,6 +101,25 @@ impl<'a> Cx2d<'a> {
         if self.turtles.len()>0 {
             panic!("Turtle stack disaligned, forgot an end_turtle()");
         }
+        if !self.pending_overlay_draw_lists.is_empty() {
+            panic!("Overlay views drawn outside of the main view of the pass");
+        }
+    }
+    
+    pub fn begin_overlay_layout(&mut self) {
+        let layout = OverlayLayout {
+            turtles: std::mem::take(&mut self.turtles),
+            turtle_walks: std::mem::take(&mut self.turtle_walks),
+            align_list: std::mem::take(&mut self.align_list),
+        };
+        self.overlay_layouts.push(layout);
+    }
+    
+    pub fn end_overlay_layout(&mut self) {
+        let layout = self.overlay_layouts.pop().unwrap();
+        self.turtles = layout.turtles;
+        self.turtle_walks = layout.turtle_walks;
+        self.align_list = layout.align_list;
     }
     
     pub fn get_scroll_pos(&self) -> Vec2 {
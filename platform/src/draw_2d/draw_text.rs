@@ -1,4 +1,9 @@
 use {
+    std::{
+        cell::RefCell,
+        ops::Range,
+    },
+    makepad_font::{bidi, script_runs, ShapedGlyph},
     crate::{
         makepad_derive_live::*,
         makepad_math::*,
@@ -11,7 +16,7 @@ use {
         draw_2d::cx_2d::Cx2d,
         live_traits::*,
         draw_2d::turtle::{Walk, Size, Flow, Align},
-        font::{CxFontsAtlasTodo, Font},
+        font::{CxFontsAtlasTodo, CxFont, Font, FontFallbacks},
         draw_2d::view::ManyInstances,
         draw_vars::DrawVars,
         shader::geometry_gen::GeometryQuad2D,
@@ -127,6 +132,7 @@ live_register!{
 #[derive(Clone, Live, LiveHook)]
 pub struct TextStyle {
     #[live()] pub font: Font,
+    #[live] pub fallbacks: FontFallbacks,
     #[live(9.0)] pub font_size: f32,
     #[live(1.0)] pub brightness: f32,
     #[live(0.6)] pub curve: f32,
@@ -134,6 +140,98 @@ pub struct TextStyle {
     #[live(1.1)] pub top_drop: f32,
     #[live(1.3)] pub height_factor: f32,
 }
+
+impl TextStyle {
+    /// Returns the id of the first font in the fallback chain whose cmap maps `c`, together with
+    /// the index of its glyph in that font. Characters that no font has map to the missing glyph
+    /// of the primary font. Returns `None` if the primary font is not loaded.
+    pub fn resolve_glyph(&self, fonts: &[Option<CxFont>], c: char) -> Option<(usize, usize)> {
+        let font_id = self.font.font_id?;
+        let font = &fonts[font_id].as_ref()?.ttf_font;
+        if let Some(glyph_id) = font.char_code_to_glyph_index_map.get(c as u32) {
+            return Some((font_id, glyph_id))
+        }
+        for fallback_id in &self.fallbacks.font_ids {
+            if let Some(Some(cxfont)) = fonts.get(*fallback_id) {
+                if let Some(glyph_id) = cxfont.ttf_font.char_code_to_glyph_index_map.get(c as u32) {
+                    return Some((*fallback_id, glyph_id))
+                }
+            }
+        }
+        Some((font_id, 0))
+    }
+    
+    /// Shapes `chars` into glyphs, in visual order from left to right. The text is split into
+    /// bidi runs, and each run into segments of the same script and font, which are shaped by
+    /// that font. Glyphs of the same font that end up next to each other are kerned.
+    pub fn shape(&self, fonts: &[Option<CxFont>], chars: &[char], glyphs: &mut Vec<TextGlyph>, shaped_glyphs: &mut Vec<ShapedGlyph>) {
+        glyphs.clear();
+        for run in bidi::visual_runs(chars) {
+            let run_start = glyphs.len();
+            let is_rtl = run.is_rtl();
+            self.shape_run(fonts, chars, run.range, is_rtl, glyphs, shaped_glyphs);
+            for index in run_start + 1..glyphs.len() {
+                let (left, right) = (glyphs[index - 1], glyphs[index]);
+                if left.font_id != right.font_id {
+                    continue
+                }
+                let font = &fonts[left.font_id].as_ref().unwrap().ttf_font;
+                // the kerning table is keyed on the logical order of the glyphs
+                glyphs[index - 1].advance += if is_rtl {
+                    font.kerning(right.glyph_id, left.glyph_id)
+                }
+                else {
+                    font.kerning(left.glyph_id, right.glyph_id)
+                };
+            }
+        }
+    }
+    
+    /// Shapes the characters in `range` as a single run in one direction, without kerning, and
+    /// appends the glyphs in visual order. `shaped_glyphs` is scratch space for the font shaper.
+    pub fn shape_run(&self, fonts: &[Option<CxFont>], chars: &[char], range: Range<usize>, is_rtl: bool, glyphs: &mut Vec<TextGlyph>, shaped_glyphs: &mut Vec<ShapedGlyph>) {
+        let run_start = glyphs.len();
+        for (script_range, script) in script_runs(chars, range) {
+            let mut start = script_range.start;
+            while start < script_range.end {
+                let font_id = match self.resolve_glyph(fonts, chars[start]) {
+                    Some((font_id, _)) => font_id,
+                    None => return
+                };
+                let mut end = start + 1;
+                while end < script_range.end && self.resolve_glyph(fonts, chars[end]).map(|(id, _)| id) == Some(font_id) {
+                    end += 1;
+                }
+                let font = &fonts[font_id].as_ref().unwrap().ttf_font;
+                shaped_glyphs.clear();
+                font.shape(chars, start..end, script, is_rtl, shaped_glyphs);
+                for shaped_glyph in shaped_glyphs.iter() {
+                    glyphs.push(TextGlyph {
+                        font_id,
+                        glyph_id: shaped_glyph.glyph_index,
+                        char_index: shaped_glyph.char_index,
+                        advance: font.glyphs.get(shaped_glyph.glyph_index).map_or(0.0, |glyph| glyph.horizontal_metrics.advance_width)
+                    });
+                }
+                start = end;
+            }
+        }
+        if is_rtl {
+            glyphs[run_start..].reverse();
+        }
+    }
+}
+
+/// A glyph produced by shaping a piece of text.
+#[derive(Clone, Copy, Debug)]
+pub struct TextGlyph {
+    pub font_id: usize,
+    pub glyph_id: usize,
+    /// The index of the (first) character in the text that the glyph was made from.
+    pub char_index: usize,
+    /// The horizontal advance, including kerning, in units of the font.
+    pub advance: f32,
+}
 /*
 #[derive(Debug, Clone, Copy, Live, LiveHook)]
 pub enum Overflow {
@@ -142,6 +240,14 @@ pub enum Overflow {
     #[live] None
 }*/
 
+// reused between draws so shaping doesn't allocate every frame
+#[derive(Default)]
+struct ShapeBuffers {
+    chars: Vec<char>,
+    glyphs: Vec<TextGlyph>,
+    shaped_glyphs: Vec<ShapedGlyph>,
+}
+
 pub struct TextGeom {
     pub eval_width: f32,
     pub eval_height: f32,
@@ -172,6 +278,9 @@ pub struct DrawText {
     #[calc] pub base: Vec2,
     #[calc] pub font_size: f32,
     #[calc] pub char_offset: f32,
+    
+    // behind a RefCell so that measuring text can shape it without a mutable DrawText
+    #[rust] buffers: RefCell<ShapeBuffers>,
 }
 
 impl DrawText {
@@ -212,123 +321,37 @@ impl DrawText {
     }
     
     pub fn draw_inner_fix_later_when_editor_rep_is_not_vec_of_char(&mut self, cx: &mut Cx2d, pos: Vec2, char_offset: usize, chunk: &[char]) {
-        if !self.draw_vars.can_instance()
-            || pos.x.is_nan()
-            || pos.y.is_nan()
-            || self.text_style.font.font_id.is_none() {
-            return
-        }
-        
-        let in_many = self.many_instances.is_some();
-        let font_id = self.text_style.font.font_id.unwrap();
-        
-        if cx.fonts[font_id].is_none() {
-            return
-        }
-        
-        if !in_many {
-            self.begin_many_instances(cx);
-        }
-        
-        let mut walk_x = pos.x;
-        let mut char_offset = char_offset;
-        
-        let cxfont = cx.cx.fonts[font_id].as_mut().unwrap();
-        let dpi_factor = cx.current_dpi_factor;
-        
-        let atlas_page_id = cxfont.get_atlas_page_id(dpi_factor, self.text_style.font_size);
-        
-        let font = &mut cxfont.ttf_font;
-        
-        let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
-        let font_size_pixels = font_size_logical * dpi_factor;
-        
-        let atlas_page = &mut cxfont.atlas_pages[atlas_page_id];
-        
-        let mi = if let Some(mi) = &mut self.many_instances {mi} else {return};
-        let zbias_step = 0.00001;
-        let mut char_depth = self.draw_depth;
-        for wc in chunk {
-            
-            let unicode = *wc as usize;
-            let glyph_id = font.char_code_to_glyph_index_map[unicode];
-            
-            let glyph = &font.glyphs[glyph_id];
-            
-            let advance = glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale;
-            
-            // snap width/height to pixel granularity
-            let w = ((glyph.bounds.p_max.x - glyph.bounds.p_min.x) * font_size_pixels).ceil() + 1.0;
-            let h = ((glyph.bounds.p_max.y - glyph.bounds.p_min.y) * font_size_pixels).ceil() + 1.0;
-            
-            // this one needs pixel snapping
-            let min_pos_x = walk_x + font_size_logical * glyph.bounds.p_min.x;
-            let min_pos_y = pos.y - font_size_logical * glyph.bounds.p_min.y + self.text_style.font_size * self.text_style.top_drop;
-            
-            // compute subpixel shift
-            let subpixel_x_fract = min_pos_x - (min_pos_x * dpi_factor).floor() / dpi_factor;
-            let subpixel_y_fract = min_pos_y - (min_pos_y * dpi_factor).floor() / dpi_factor;
-            
-            // scale and snap it
-            let scaled_min_pos_x = walk_x + font_size_logical * self.font_scale * glyph.bounds.p_min.x - subpixel_x_fract;
-            let scaled_min_pos_y = pos.y - font_size_logical * self.font_scale * glyph.bounds.p_min.y + self.text_style.font_size * self.font_scale * self.text_style.top_drop - subpixel_y_fract;
-            
-            // only use a subpixel id for small fonts
-            let subpixel_id = if self.text_style.font_size>32.0 {
-                0
-            }
-            else { // subtle 64 index subpixel id
-                ((subpixel_y_fract * 7.0) as usize) << 3 |
-                (subpixel_x_fract * 7.0) as usize
-            };
-            
-            let tc = if let Some(tc) = &atlas_page.atlas_glyphs[glyph_id][subpixel_id] {
-                //println!("{} {} {} {}", tc.tx1,tc.tx2,tc.ty1,tc.ty2);
-                tc
-            }
-            else {
-                // see if we can fit it
-                // allocate slot
-                cx.cx.fonts_atlas.atlas_todo.push(CxFontsAtlasTodo {
-                    subpixel_x_fract,
-                    subpixel_y_fract,
+        // no shaping or kerning here, the code editor lays out its text on a monospace grid and
+        // needs a glyph for every char
+        let mut buffers = std::mem::take(self.buffers.get_mut());
+        let glyphs = &mut buffers.glyphs;
+        glyphs.clear();
+        for (char_index, c) in chunk.iter().enumerate() {
+            if let Some((font_id, glyph_id)) = self.text_style.resolve_glyph(&cx.cx.fonts, *c) {
+                let font = &cx.cx.fonts[font_id].as_ref().unwrap().ttf_font;
+                glyphs.push(TextGlyph {
                     font_id,
-                    atlas_page_id,
                     glyph_id,
-                    subpixel_id
+                    char_index,
+                    advance: font.glyphs[glyph_id].horizontal_metrics.advance_width
                 });
-                
-                atlas_page.atlas_glyphs[glyph_id][subpixel_id] = Some(
-                    cx.cx.fonts_atlas.alloc_atlas_glyph(w, h)
-                );
-                
-                atlas_page.atlas_glyphs[glyph_id][subpixel_id].as_ref().unwrap()
-            };
-            
-            // give the callback a chance to do things
-            self.font_t1.x = tc.tx1;
-            self.font_t1.y = tc.ty1;
-            self.font_t2.x = tc.tx2;
-            self.font_t2.y = tc.ty2;
-            self.rect_pos = vec2(scaled_min_pos_x, scaled_min_pos_y);
-            self.rect_size = vec2(w * self.font_scale / dpi_factor, h * self.font_scale / dpi_factor);
-            self.char_depth = char_depth;
-            self.base.x = walk_x;
-            self.base.y = pos.y;
-            self.font_size = self.text_style.font_size;
-            self.char_offset = char_offset as f32;
-            char_depth += zbias_step;
-            mi.instances.extend_from_slice(self.draw_vars.as_slice());
-            walk_x += advance;
-            char_offset += 1;
-        }
-        
-        if !in_many {
-            self.end_many_instances(cx)
+            }
         }
+        self.draw_glyphs(cx, pos, char_offset, glyphs);
+        *self.buffers.get_mut() = buffers;
     }
     
     pub fn draw_inner(&mut self, cx: &mut Cx2d, pos: Vec2, char_offset: usize, chunk: &str) {
+        let mut buffers = std::mem::take(self.buffers.get_mut());
+        let ShapeBuffers {chars, glyphs, shaped_glyphs} = &mut buffers;
+        chars.clear();
+        chars.extend(chunk.chars());
+        self.text_style.shape(&cx.cx.fonts, chars, glyphs, shaped_glyphs);
+        self.draw_glyphs(cx, pos, char_offset, glyphs);
+        *self.buffers.get_mut() = buffers;
+    }
+    
+    fn draw_glyphs(&mut self, cx: &mut Cx2d, pos: Vec2, char_offset: usize, glyphs: &[TextGlyph]) {
         if !self.draw_vars.can_instance()
             || pos.x.is_nan()
             || pos.y.is_nan()
@@ -348,31 +371,29 @@ impl DrawText {
         }
         
         let mut walk_x = pos.x;
-        let mut char_offset = char_offset;
         
-        let cxfont = cx.cx.fonts[font_id].as_mut().unwrap();
         let dpi_factor = cx.current_dpi_factor;
         
-        let atlas_page_id = cxfont.get_atlas_page_id(dpi_factor, self.text_style.font_size);
-        
-        let font = &mut cxfont.ttf_font;
-        
-        let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
-        let font_size_pixels = font_size_logical * dpi_factor;
-        
-        let atlas_page = &mut cxfont.atlas_pages[atlas_page_id];
-        
         let mi = if let Some(mi) = &mut self.many_instances {mi} else {return};
         let zbias_step = 0.00001;
         let mut char_depth = self.draw_depth;
-        for wc in chunk.chars() {
+        for text_glyph in glyphs {
+            
+            // glyphs of different fonts in the fallback chain all go into the same atlas
+            let font_id = text_glyph.font_id;
+            let glyph_id = text_glyph.glyph_id;
+            
+            let cxfont = cx.cx.fonts[font_id].as_mut().unwrap();
+            let atlas_page_id = cxfont.get_atlas_page_id(dpi_factor, self.text_style.font_size);
+            let font = &cxfont.ttf_font;
+            let atlas_page = &mut cxfont.atlas_pages[atlas_page_id];
             
-            let unicode = wc as usize;
-            let glyph_id = font.char_code_to_glyph_index_map[unicode];
+            let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
+            let font_size_pixels = font_size_logical * dpi_factor;
             
             let glyph = &font.glyphs[glyph_id];
             
-            let advance = glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale;
+            let advance = text_glyph.advance * font_size_logical * self.font_scale;
             
             // snap width/height to pixel granularity
             let w = ((glyph.bounds.p_max.x - glyph.bounds.p_min.x) * font_size_pixels).ceil() + 1.0;
@@ -433,11 +454,10 @@ impl DrawText {
             self.base.x = walk_x;
             self.base.y = pos.y;
             self.font_size = self.text_style.font_size;
-            self.char_offset = char_offset as f32;
+            self.char_offset = (char_offset + text_glyph.char_index) as f32;
             char_depth += zbias_step;
             mi.instances.extend_from_slice(self.draw_vars.as_slice());
             walk_x += advance;
-            char_offset += 1;
         }
         
         if !in_many {
@@ -453,21 +473,20 @@ impl DrawText {
             return None
         }
         
-        let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * cx.fonts[font_id].as_ref().unwrap().ttf_font.units_per_em);
         let measured_height = self.text_style.font_size * self.text_style.height_factor * self.font_scale;
         let eval_width = cx.turtle().eval_width(walk.width, walk.margin, Flow::Right);
         let eval_height = cx.turtle().eval_height(walk.height, walk.margin, Flow::Right);
         
+        let mut buffers = self.buffers.borrow_mut();
+        let ShapeBuffers {chars, glyphs, shaped_glyphs} = &mut *buffers;
+        chars.clear();
+        chars.extend(text.chars());
+        self.text_style.shape(&cx.fonts, chars, glyphs, shaped_glyphs);
+        
         // if we have a fit width, we simply fit
         // if we have a fixed width, we can apply align + ellipsis
         if walk.width.is_fit() {
-            let mut measured_width = 0.0;
-            for c in text.chars() {
-                if let Some(glyph) = cx.fonts[font_id].as_ref().unwrap().ttf_font.get_glyph(c) {
-                    let adv = glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale;
-                    measured_width += adv;
-                }
-            }
+            let measured_width = glyphs.iter().map(|glyph| self.glyph_width(cx, glyph)).sum();
             Some(TextGeom {
                 eval_width,
                 eval_height,
@@ -478,41 +497,42 @@ impl DrawText {
         }
         else {
             
-            let ellip_width = if let Some(glyph) = cx.fonts[font_id].as_ref().unwrap().ttf_font.get_glyph('.') {
+            let font = &cx.fonts[font_id].as_ref().unwrap().ttf_font;
+            let ellip_width = if let Some(glyph) = font.get_glyph('.') {
+                let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
                 glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale
             }
             else {
                 0.0
             };
             
+            // the ellipsis cuts off the end of the text, so walk the glyphs in logical order
+            glyphs.sort_by_key(|glyph| glyph.char_index);
             let mut measured_width = 0.0;
             let mut ellip_pt = None;
-            for (i, c) in text.chars().enumerate() {
+            for glyph in glyphs.iter() {
                 if measured_width + ellip_width * 3.0 < eval_width {
-                    ellip_pt = Some((i, measured_width, 3));
+                    ellip_pt = Some((glyph.char_index, measured_width, 3));
                 }
-                if let Some(glyph) = cx.fonts[font_id].as_ref().unwrap().ttf_font.get_glyph(c) {
-                    let adv = glyph.horizontal_metrics.advance_width * font_size_logical * self.font_scale;
-                    // ok so now what.
-                    if measured_width + adv >= eval_width { // we have to drop back to ellip_pt
-                        // if we don't have an ellip_pt, set it to 0
-                        if ellip_pt.is_none() {
-                            let dots = if ellip_width * 3.0 < eval_width {3}
-                            else if ellip_width * 2.0 < eval_width {2}
-                            else if ellip_width < eval_width {1}
-                            else {0};
-                            ellip_pt = Some((0, 0.0, dots));
-                        }
-                        return Some(TextGeom {
-                            eval_width,
-                            eval_height,
-                            measured_width: ellip_pt.unwrap().1 + ellip_width,
-                            measured_height,
-                            ellip_pt
-                        })
+                let adv = self.glyph_width(cx, glyph);
+                if measured_width + adv >= eval_width { // we have to drop back to ellip_pt
+                    // if we don't have an ellip_pt, set it to 0
+                    if ellip_pt.is_none() {
+                        let dots = if ellip_width * 3.0 < eval_width {3}
+                        else if ellip_width * 2.0 < eval_width {2}
+                        else if ellip_width < eval_width {1}
+                        else {0};
+                        ellip_pt = Some((0, 0.0, dots));
                     }
-                    measured_width += adv;
+                    return Some(TextGeom {
+                        eval_width,
+                        eval_height,
+                        measured_width: ellip_pt.unwrap().1 + ellip_width,
+                        measured_height,
+                        ellip_pt
+                    })
                 }
+                measured_width += adv;
             }
             
             Some(TextGeom {
@@ -525,6 +545,36 @@ impl DrawText {
         }
     }
     
+    /// Computes the horizontal offset of every character boundary in `chars`, relative to the
+    /// start of the text. `positions[i]` is where a caret in front of character `i` goes, and the
+    /// last entry is the width of the whole text. Glyphs are measured in logical order, like the
+    /// ellipsis in `compute_geom`.
+    pub fn compute_char_positions(&self, cx: &Cx, chars: &[char], positions: &mut Vec<f32>) {
+        positions.clear();
+        let mut buffers = self.buffers.borrow_mut();
+        let ShapeBuffers {glyphs, shaped_glyphs, ..} = &mut *buffers;
+        glyphs.clear();
+        if self.text_style.font.font_id.is_some_and(|font_id| cx.fonts[font_id].is_some()) {
+            self.text_style.shape(&cx.fonts, chars, glyphs, shaped_glyphs);
+        }
+        glyphs.sort_by_key(|glyph| glyph.char_index);
+        let mut glyphs = glyphs.iter().peekable();
+        let mut x = 0.0;
+        for index in 0..chars.len() {
+            positions.push(x);
+            while let Some(glyph) = glyphs.next_if(|glyph| glyph.char_index == index) {
+                x += self.glyph_width(cx, glyph);
+            }
+        }
+        positions.push(x);
+    }
+    
+    fn glyph_width(&self, cx: &Cx, glyph: &TextGlyph) -> f32 {
+        let font = &cx.fonts[glyph.font_id].as_ref().unwrap().ttf_font;
+        let font_size_logical = self.text_style.font_size * 96.0 / (72.0 * font.units_per_em);
+        glyph.advance * font_size_logical * self.font_scale
+    }
+    
     pub fn draw_walk(&mut self, cx: &mut Cx2d, walk: Walk, align: Align, text: &str) {
         
         // lets compute the geom
@@ -560,6 +610,7 @@ impl DrawText {
                         width: Size::Fixed(geom.eval_width),
                         height: Size::Fixed(height)
                     });
+                    let ellip = text.char_indices().nth(ellip).map_or(text.len(), |(index, _)| index);
                     self.draw_inner(cx, rect.pos+ vec2(0.0, y_align), 0, &text[0..ellip]);
                     self.draw_inner(cx, rect.pos + vec2(at_x, y_align), 0, &"..."[0..dots]);
                 }
@@ -642,7 +693,7 @@ impl DrawText {
             return Vec2::default();
         }
         let font = &cx.fonts[font_id].as_ref().unwrap().ttf_font;
-        let slot = font.char_code_to_glyph_index_map[33];
+        let slot = font.glyph_index('!');
         let glyph = &font.glyphs[slot];
         
         //let font_size = if let Some(font_size) = font_size{font_size}else{self.font_size};
@@ -171,7 +171,8 @@ impl<'a> Cx2d<'a> {
             (o, w, h)
         }
         else {
-            let o = Vec2 {x: walk.margin.left, y: walk.margin.top};
+            // overlays start a turtle stack of their own, placed where they ask to be
+            let o = walk.margin.left_top() + walk.abs_pos.unwrap_or(Vec2::default());
             let w = walk.width.fixed_or_nan();
             let h = walk.height.fixed_or_nan();
             (o, w, h)
@@ -287,7 +288,7 @@ impl<'a> Cx2d<'a> {
         
         if self.turtles.len() == 0 {
             return Rect {
-                pos: vec2(0.0, 0.0),
+                pos: if turtle.walk.abs_pos.is_some() {turtle.origin} else {vec2(0.0, 0.0)},
                 size: vec2(w.fixed_or_zero(), h.fixed_or_zero())
             }
         }
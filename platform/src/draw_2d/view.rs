@@ -55,6 +55,31 @@ pub struct View { // draw info per UI element
     pub draw_lists_free: Rc<RefCell<Vec<usize >> >,
 }
 
+fn append_sub_view(cx: &mut Cx, parent_id: usize, sub_view_id: usize) {
+    let redraw_id = cx.redraw_id;
+    let parent = &mut cx.draw_lists[parent_id];
+    
+    // see if we need to add a new one
+    if parent.draw_items_len >= parent.draw_items.len() {
+        parent.draw_items.push({
+            DrawItem {
+                draw_list_id: parent_id,
+                draw_item_id: parent.draw_items.len(),
+                redraw_id,
+                sub_view_id: Some(sub_view_id),
+                draw_call: None
+            }
+        });
+        parent.draw_items_len += 1;
+    }
+    else { // or reuse a sub list node
+        let draw_item = &mut parent.draw_items[parent.draw_items_len];
+        draw_item.sub_view_id = Some(sub_view_id);
+        draw_item.redraw_id = redraw_id;
+        parent.draw_items_len += 1;
+    }
+}
+
 impl Drop for View {
     fn drop(&mut self) {
         self.draw_lists_free.borrow_mut().push(self.draw_list_id)
@@ -195,7 +220,8 @@ impl View {
             cx.draw_list_stack.last().cloned()
         };
         
-        // push ourselves up the parent draw_stack
+        // push ourselves up the parent draw_stack. Overlays are attached to the main view when it
+        // ends, so that they are drawn on top of everything else in the pass
         if let Some(parent_id) = parent_id {
             // copy the view transform
             
@@ -206,26 +232,11 @@ impl View {
                 }
             }
             
-            let parent = &mut cx.cx.draw_lists[parent_id];
-            
-            // see if we need to add a new one
-            if parent.draw_items_len >= parent.draw_items.len() {
-                parent.draw_items.push({
-                    DrawItem {
-                        draw_list_id: parent_id,
-                        draw_item_id: parent.draw_items.len(),
-                        redraw_id: cx.cx.redraw_id,
-                        sub_view_id: Some(self.draw_list_id),
-                        draw_call: None
-                    }
-                });
-                parent.draw_items_len += 1;
+            if self.is_overlay {
+                cx.pending_overlay_draw_lists.push(self.draw_list_id);
             }
-            else { // or reuse a sub list node
-                let draw_item = &mut parent.draw_items[parent.draw_items_len];
-                draw_item.sub_view_id = Some(self.draw_list_id);
-                draw_item.redraw_id = cx.cx.redraw_id;
-                parent.draw_items_len += 1;
+            else {
+                append_sub_view(cx, parent_id, self.draw_list_id);
             }
         }
         
@@ -237,6 +248,9 @@ impl View {
             && cx.cx.draw_lists[self.draw_list_id].draw_items_len != 0
             && !view_will_redraw {
             
+            if self.is_overlay {
+                return Err(());
+            }
             let w = Size::Fixed(cx.cx.draw_lists[self.draw_list_id].rect.size.x);
             let h = Size::Fixed(cx.cx.draw_lists[self.draw_list_id].rect.size.y);
             let walk = Walk {abs_pos: None, width: w, height: h, margin: walk.margin};
@@ -266,6 +280,9 @@ impl View {
         let new_area = Area::DrawList(DrawListArea {draw_list_id: self.draw_list_id, redraw_id: cx.redraw_id});
         
         cx.update_area_refs(old_area, new_area);
+        if self.is_overlay {
+            cx.begin_overlay_layout();
+        }
         cx.begin_turtle_with_guard(walk, layout, new_area);
         
         cx.align_list.push(new_area);
@@ -278,9 +295,24 @@ impl View {
         // let view_id = self.view_id.unwrap();
         let view_area = Area::DrawList(DrawListArea {draw_list_id: self.draw_list_id, redraw_id: cx.redraw_id});
         let rect = cx.end_turtle_with_guard(view_area);
+        if self.is_overlay {
+            cx.end_overlay_layout();
+        }
         let cxview = &mut cx.draw_lists[self.draw_list_id];
         cxview.rect = rect;
         cx.draw_list_stack.pop();
+        
+        let pass_id = cx.pass_id.unwrap();
+        if cx.passes[pass_id].main_draw_list_id == Some(self.draw_list_id) {
+            let pending = std::mem::take(&mut cx.pending_overlay_draw_lists);
+            let cx = &mut *cx.cx;
+            let draw_lists = &cx.draw_lists;
+            cx.overlay_draw_lists.retain( | overlay_id | draw_lists[*overlay_id].pass_id != pass_id);
+            for overlay_id in pending {
+                append_sub_view(cx, self.draw_list_id, overlay_id);
+                cx.overlay_draw_lists.push(overlay_id);
+            }
+        }
         view_area
     }
     
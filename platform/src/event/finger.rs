@@ -335,7 +335,8 @@ impl Event {
             },
             Event::FingerScroll(fe) => {
                 let rect = area.get_rect(&cx);
-                if rect_contains_with_margin(&rect, fe.abs, &options.margin) {
+                if rect_contains_with_margin(&rect, fe.abs, &options.margin)
+                    && !cx.is_covered_by_overlay(area, fe.window_id, fe.abs) {
                     //fe.handled = true;
                     return HitEvent::FingerScroll(FingerScrollHitEvent {
                         rel: fe.abs - rect.pos,
@@ -346,6 +347,8 @@ impl Event {
             },
             Event::FingerHover(fe) => {
                 let rect = area.get_rect(&cx);
+                let is_over = rect_contains_with_margin(&rect, fe.abs, &options.margin)
+                    && !cx.is_covered_by_overlay(area, fe.window_id, fe.abs);
                 
                 if  cx.fingers[fe.digit]._over_last == area {
                     let mut any_down = false;
@@ -355,7 +358,7 @@ impl Event {
                             break;
                         }
                     }
-                    if !fe.handled && rect_contains_with_margin(&rect, fe.abs, &options.margin) {
+                    if !fe.handled && is_over {
                         fe.handled = true;
                         //if let HoverState::Out = fe.hover_state {
                         //    cx.finger_over_last_area = Area::Empty;
@@ -383,7 +386,7 @@ impl Event {
                     }
                 }
                 else {
-                    if !fe.handled && rect_contains_with_margin(&rect, fe.abs, &options.margin) {
+                    if !fe.handled && is_over {
                         let mut any_down = false;
                         for finger in &cx.fingers {
                             if finger.captured == area {
@@ -423,7 +426,8 @@ impl Event {
             Event::FingerDown(fe) => {
                 if !fe.handled {
                     let rect = area.get_rect(&cx);
-                    if rect_contains_with_margin(&rect, fe.abs, &options.margin) {
+                    if rect_contains_with_margin(&rect, fe.abs, &options.margin)
+                        && !cx.is_covered_by_overlay(area, fe.window_id, fe.abs) {
                         // scan if any of the fingers already captured this area
                         if !options.use_multi_touch {
                             for finger in &cx.fingers {
@@ -135,6 +135,48 @@ impl LiveHook for Font {
     }
 }
 
+/// A list of fonts that are tried in order for characters the primary font of a `TextStyle` does
+/// not have, written in the DSL as an array of dependencies, e.g.
+/// `fallbacks: [d"resources/NotoSansCJK.ttf", d"resources/NotoSansSymbols.ttf"]`.
+#[derive(Debug, Default, Clone)]
+pub struct FontFallbacks {
+    pub font_ids: Vec<usize>
+}
+
+live_primitive!(
+    FontFallbacks,
+    FontFallbacks::default(),
+    fn apply(&mut self, cx: &mut Cx, from: ApplyFrom, index: usize, nodes: &[LiveNode]) -> usize {
+        match &nodes[index].value {
+            LiveValue::Array => {
+                self.font_ids.clear();
+                let mut child_index = nodes.first_child(index);
+                while let Some(index) = child_index {
+                    let mut path = LiveDependency::default();
+                    path.apply(cx, from, index, nodes);
+                    if !path.as_ref().is_empty() {
+                        self.font_ids.push(cx.get_font_by_path(path.as_ref()));
+                    }
+                    child_index = nodes.next_child(index);
+                }
+                nodes.skip_node(index)
+            }
+            LiveValue::Dependency {..} => {
+                let mut path = LiveDependency::default();
+                let next_index = path.apply(cx, from, index, nodes);
+                self.font_ids.clear();
+                self.font_ids.push(cx.get_font_by_path(path.as_ref()));
+                next_index
+            }
+            _ => {
+                cx.apply_error_wrong_value_type_for_primitive(live_error_origin!(), index, nodes, "FontFallbacks");
+                nodes.skip_node(index)
+            }
+        }
+    },
+    fn to_live_value(&self) -> LiveValue {panic!()}
+);
+
 impl Cx {
     pub fn get_font_by_path(&mut self, path: &str) -> usize {
 
@@ -154,10 +196,10 @@ impl Cx {
                         }
                         Ok(mut cxfont) => {
                             if path == "resources/IBMPlexSans-Text.ttf" {
-                                cxfont.ttf_font.char_code_to_glyph_index_map['g' as usize] = 11;
-                                cxfont.ttf_font.char_code_to_glyph_index_map['9' as usize] = 70;
-                                cxfont.ttf_font.char_code_to_glyph_index_map['0' as usize] = 60;
-                                cxfont.ttf_font.char_code_to_glyph_index_map['@' as usize] = 72;
+                                cxfont.ttf_font.char_code_to_glyph_index_map.insert('g' as u32, 11);
+                                cxfont.ttf_font.char_code_to_glyph_index_map.insert('9' as u32, 70);
+                                cxfont.ttf_font.char_code_to_glyph_index_map.insert('0' as u32, 60);
+                                cxfont.ttf_font.char_code_to_glyph_index_map.insert('@' as u32, 72);
                             }
                             self.fonts[font_id] = Some(cxfont);
                         }
@@ -236,11 +278,7 @@ impl DrawTrapezoidText {
             let cxfont = cx.fonts[font_id].as_ref().unwrap();
             let font = &cxfont.ttf_font;
             
-            let slot = if c < '\u{10000}' {
-                cxfont.ttf_font.char_code_to_glyph_index_map[c as usize]
-            } else {
-                0
-            };
+            let slot = cxfont.ttf_font.glyph_index(c);
             
             if slot == 0 {
                 return
@@ -299,9 +337,9 @@ impl DrawTrapezoidText {
                 let atlas_page = &cxfont.atlas_pages[todo.atlas_page_id];
                 let glyph = &font.glyphs[todo.glyph_id];
                 
-                if todo.glyph_id == font.char_code_to_glyph_index_map[10] ||
-                todo.glyph_id == font.char_code_to_glyph_index_map[9] ||
-                todo.glyph_id == font.char_code_to_glyph_index_map[13] {
+                if todo.glyph_id == font.glyph_index('\n') ||
+                todo.glyph_id == font.glyph_index('\t') ||
+                todo.glyph_id == font.glyph_index('\r') {
                     return
                 }
                 
@@ -599,11 +599,12 @@ pub struct SlErr {
 impl Cx {
     
     pub fn mtl_compile_shaders(&mut self, metal_cx: &MetalCx) {
+        let mut compile_errors = Vec::new();
         for draw_shader_ptr in &self.draw_shaders.compile_set {
             if let Some(item) = self.draw_shaders.ptr_to_item.get(&draw_shader_ptr) {
                 let cx_shader = &mut self.draw_shaders.shaders[item.draw_shader_id];
                 let draw_shader_def = self.shader_registry.draw_shader_defs.get(&draw_shader_ptr);
-                let gen = generate_metal::generate_shader(
+                let mut gen = generate_metal::generate_shader(
                     draw_shader_def.as_ref().unwrap(),
                     &cx_shader.mapping.const_table,
                     &self.shader_registry
@@ -620,13 +621,21 @@ impl Cx {
                     }
                 }
                 if cx_shader.platform.is_none() {
-                    if let Some(shp) = CxPlatformDrawShader::new(metal_cx, gen) {
-                        cx_shader.platform = Some(self.draw_shaders.platform.len());
-                        self.draw_shaders.platform.push(shp);
+                    let source_map = std::mem::take(&mut gen.source_map);
+                    match CxPlatformDrawShader::new(metal_cx, gen) {
+                        Ok(shp) => {
+                            cx_shader.platform = Some(self.draw_shaders.platform.len());
+                            self.draw_shaders.platform.push(shp);
+                        }
+                        Err(log) => compile_errors.push((source_map, log))
                     }
                 }
             }
         }
+        // the metal source has no prelude, so its lines match the source map directly
+        for (source_map, log) in compile_errors {
+            self.add_draw_shader_compile_errors(&source_map, &log, 0);
+        }
         self.draw_shaders.compile_set.clear();
     }
 }
@@ -670,7 +679,7 @@ impl CxPlatformDrawShader {
     pub fn new(
         metal_cx: &MetalCx,
         shader: MetalGeneratedShader,
-    ) -> Option<Self> {
+    ) -> Result<Self, String> {
         let options = RcObjcId::from_owned(unsafe {msg_send![class!(MTLCompileOptions), new]});
         unsafe {
             let _: () = msg_send![options.as_id(), setFastMathEnabled: YES];
@@ -694,7 +703,7 @@ impl CxPlatformDrawShader {
                 for (index, line) in shader.mtlsl.split("\n").enumerate() {
                     println!("{}: {}", index + 1, line);
                 }
-                panic!("{}", string);
+                return Err(string);
             }
         });
         
@@ -752,7 +761,7 @@ impl CxPlatformDrawShader {
             buffer_id += 1;
         }
         
-        return Some(Self {
+        return Ok(Self {
             _library: library,
             render_pipeline_state,
             draw_uniform_buffer_id,
@@ -0,0 +1,572 @@
+use {
+    std::{
+        io,
+        fs::File,
+        io::Write,
+        sync::Mutex,
+        collections::{HashSet, VecDeque},
+    },
+    crate::{
+        makepad_math::Vec2,
+        platform::{
+            cx_desktop::CxDesktop,
+            headless::png::encode_png_bgra,
+        },
+        audio::{
+            AudioTime,
+            AudioOutputBuffer
+        },
+        event::{
+            WebSocket,
+            WebSocketAutoReconnect,
+            WebSocketErrorEvent,
+            Timer,
+            TimerEvent,
+            Signal,
+            SignalEvent,
+            Event,
+            WindowGeom,
+            WindowGeomChangeEvent,
+            WindowClosedEvent,
+            DraggedItem
+        },
+        menu::Menu,
+        cx_api::{CxPlatformApi},
+        cx::{Cx, PlatformType},
+        window::{CxWindowState, CxWindowCmd},
+        pass::CxPassParent,
+        texture::{CxTexture, TextureDesc, TextureFormat},
+    }
+};
+
+// signals can be posted from any thread, they are picked up between script steps
+static HEADLESS_SIGNALS: Mutex<Vec<Signal>> = Mutex::new(Vec::new());
+
+// the virtual clock advances in steps of one frame while waiting, so animations progress deterministically
+const HEADLESS_FRAME_TIME: f64 = 1.0 / 60.0;
+
+/// One step of the script the headless backend drives `Cx` with.
+pub enum HeadlessCmd {
+    /// Delivers an event as if the window system produced it.
+    Event(Event),
+    /// Advances the virtual clock, running timers and next frames that come due.
+    Wait(f64),
+    /// Writes the last painted frame of a window to a png file.
+    Screenshot {window_id: usize, path: String},
+}
+
+pub struct HeadlessWindow {
+    pub window_id: usize,
+    pub window_geom: WindowGeom,
+    pub texture_id: usize,
+}
+
+pub struct HeadlessTimer {
+    pub timer_id: u64,
+    pub interval: f64,
+    pub repeats: bool,
+    pub fire_at: f64,
+}
+
+impl Cx {
+
+    pub fn event_loop<F>(&mut self, mut event_handler: F)
+    where F: FnMut(&mut Cx, &mut Event),
+    {
+        // the handler only lives for the duration of the loop, erase its lifetime to store it
+        let event_handler: *mut (dyn FnMut(&mut Cx, &mut Event) + '_) = &mut event_handler;
+        self.event_handler = Some(unsafe {std::mem::transmute(event_handler)});
+        self.event_loop_core();
+        self.event_handler = None;
+    }
+
+    pub fn event_loop_core(&mut self) {
+        self.platform_type = PlatformType::Headless;
+
+        self.call_event_handler(&mut Event::Construct);
+
+        self.redraw_all();
+
+        let mut passes_todo = Vec::new();
+        self.headless_paint(&mut passes_todo);
+
+        while let Some(cmd) = self.platform.script.pop_front() {
+            match cmd {
+                HeadlessCmd::Event(mut event) => {
+                    self.headless_dispatch(&mut event);
+                    self.headless_paint(&mut passes_todo);
+                }
+                HeadlessCmd::Wait(duration) => {
+                    let end = self.platform.time + duration;
+                    while self.platform.time < end {
+                        let mut step = (self.platform.time + HEADLESS_FRAME_TIME).min(end);
+                        for timer in &self.platform.timers {
+                            step = step.min(timer.fire_at);
+                        }
+                        self.platform.time = step;
+                        self.headless_fire_timers();
+                        self.headless_paint(&mut passes_todo);
+                    }
+                }
+                HeadlessCmd::Screenshot {window_id, path} => {
+                    // a script that can't take its screenshots has nothing to check, so it fails
+                    if let Err(err) = self.headless_save_window_png(window_id, &path) {
+                        panic!("Headless screenshot of window {} to {} failed: {}", window_id, path, err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Appends a step to the script run by `event_loop`.
+    pub fn headless_push(&mut self, cmd: HeadlessCmd) {
+        self.platform.script.push_back(cmd);
+    }
+
+    /// Returns the size and pixels (0xAARRGGBB, premultiplied) of the last frame painted into a window.
+    pub fn headless_window_image(&self, window_id: usize) -> Option<(usize, usize, &[u32])> {
+        let window = self.platform.windows.iter().find( | w | w.window_id == window_id) ?;
+        let cxtexture = &self.textures[window.texture_id];
+        Some((cxtexture.desc.width?, cxtexture.desc.height?, &cxtexture.image_u32))
+    }
+
+    pub fn headless_save_window_png(&self, window_id: usize, path: &str) -> io::Result<()> {
+        if let Some((width, height, pixels)) = self.headless_window_image(window_id) {
+            let mut file = File::create(path) ?;
+            file.write_all(&encode_png_bgra(width, height, pixels))
+        }
+        else {
+            Err(io::Error::new(io::ErrorKind::NotFound, "window has not been painted"))
+        }
+    }
+
+    fn headless_dispatch(&mut self, event: &mut Event) {
+        self.process_desktop_pre_event(event);
+        match event {
+            Event::WindowSetHoverCursor(mc) => {
+                self.set_hover_mouse_cursor(*mc);
+            },
+            Event::WindowGeomChange(re) => {
+                if let Some(window) = self.platform.windows.iter_mut().find( | w | w.window_id == re.window_id) {
+                    window.window_geom = re.new_geom.clone();
+                    self.windows[re.window_id].window_geom = re.new_geom.clone();
+                    // redraw just this windows root draw list
+                    if re.old_geom.inner_size != re.new_geom.inner_size {
+                        if let Some(main_pass_id) = self.windows[re.window_id].main_pass_id {
+                            self.redraw_pass_and_child_passes(main_pass_id);
+                        }
+                    }
+                }
+                self.call_event_handler(event);
+            },
+            Event::Signal(_) => {
+                self.call_event_handler(event);
+                self.call_signals_and_triggers();
+            },
+            _ => {
+                self.call_event_handler(event);
+                self.call_live_edit();
+                self.call_signals_and_triggers();
+            }
+        }
+        self.process_desktop_post_event(event);
+    }
+
+    fn headless_fire_timers(&mut self) {
+        let time = self.platform.time;
+        let mut fired = Vec::new();
+        self.platform.timers.retain_mut( | timer | {
+            if timer.fire_at > time {
+                return true
+            }
+            fired.push(timer.timer_id);
+            timer.fire_at += timer.interval;
+            timer.repeats
+        });
+        for timer_id in fired {
+            self.headless_dispatch(&mut Event::Timer(TimerEvent {timer_id}));
+        }
+    }
+
+    fn headless_handle_signals(&mut self) {
+        let signals: HashSet<Signal> = if let Ok(mut signals) = HEADLESS_SIGNALS.lock() {
+            signals.drain(..).collect()
+        }
+        else {
+            return
+        };
+        if signals.len() > 0 {
+            self.headless_dispatch(&mut Event::Signal(SignalEvent {signals}));
+        }
+    }
+
+    fn headless_paint(&mut self, passes_todo: &mut Vec<usize>) {
+        self.headless_handle_signals();
+
+        // construct or destruct windows
+        for window_id in 0..self.windows.len() {
+            match self.windows[window_id].window_state.clone() {
+                CxWindowState::Create {inner_size, position, ..} => {
+                    let window_geom = WindowGeom {
+                        dpi_factor: self.platform.dpi_factor,
+                        inner_size,
+                        outer_size: inner_size,
+                        position: position.unwrap_or(Vec2::default()),
+                        ..WindowGeom::default()
+                    };
+                    let texture_id = self.textures.len();
+                    self.textures.push(CxTexture {
+                        desc: TextureDesc {
+                            format: TextureFormat::ImageBGRA,
+                            ..TextureDesc::default()
+                        },
+                        ..CxTexture::default()
+                    });
+                    self.windows[window_id].window_geom = window_geom.clone();
+                    self.windows[window_id].window_state = CxWindowState::Created;
+                    self.platform.windows.push(HeadlessWindow {
+                        window_id,
+                        window_geom,
+                        texture_id
+                    });
+                },
+                CxWindowState::Close => {
+                    self.windows[window_id].window_state = CxWindowState::Closed;
+                    if let Some(index) = self.platform.windows.iter().position( | w | w.window_id == window_id) {
+                        self.platform.windows.remove(index);
+                    }
+                    self.headless_dispatch(&mut Event::WindowClosed(WindowClosedEvent {window_id}));
+                },
+                _ => ()
+            }
+            // there is no window manager to restore, maximize or minimize
+            self.windows[window_id].window_command = CxWindowCmd::None;
+        }
+
+        // there are no window decorations, so the outer size is the inner size
+        let position = self.platform.set_window_position.take();
+        let outer_size = self.platform.set_window_outer_size.take();
+        if position.is_some() || outer_size.is_some() {
+            for index in 0..self.platform.windows.len() {
+                let window = &self.platform.windows[index];
+                let old_geom = window.window_geom.clone();
+                let mut new_geom = old_geom.clone();
+                if let Some(position) = position {
+                    new_geom.position = position;
+                }
+                if let Some(outer_size) = outer_size {
+                    new_geom.inner_size = outer_size;
+                    new_geom.outer_size = outer_size;
+                }
+                self.headless_dispatch(&mut Event::WindowGeomChange(WindowGeomChangeEvent {
+                    window_id: window.window_id,
+                    old_geom,
+                    new_geom
+                }));
+            }
+        }
+
+        let _vsync = self.process_desktop_paint_callbacks(self.platform.time);
+        self.sw_compile_shaders();
+
+        // build a list of renderpasses to repaint
+        let mut windows_need_repaint = 0;
+        self.compute_passes_to_repaint(passes_todo, &mut windows_need_repaint);
+
+        if passes_todo.len() > 0 {
+            self.repaint_id += 1;
+            for pass_id in passes_todo.iter() {
+                match self.passes[*pass_id].parent.clone() {
+                    CxPassParent::Window(window_id) => {
+                        if let Some(window) = self.platform.windows.iter().find( | w | w.window_id == window_id) {
+                            let texture_id = window.texture_id;
+                            let dpi_factor = window.window_geom.dpi_factor;
+                            let inner_size = window.window_geom.inner_size;
+                            self.sw_draw_pass_to_window(*pass_id, dpi_factor, inner_size, texture_id);
+                        }
+                    }
+                    CxPassParent::Pass(parent_pass_id) => {
+                        let dpi_factor = self.get_delegated_dpi_factor(parent_pass_id);
+                        self.sw_draw_pass_to_texture(*pass_id, dpi_factor);
+                    },
+                    CxPassParent::None => {
+                        self.sw_draw_pass_to_texture(*pass_id, 1.0);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl CxPlatformApi for Cx {
+
+    fn show_text_ime(&mut self, x: f32, y: f32) {
+        self.platform.set_ime_position = Some(Vec2 {x: x, y: y});
+    }
+
+    fn hide_text_ime(&mut self) {
+    }
+
+    fn set_window_outer_size(&mut self, size: Vec2) {
+        self.platform.set_window_outer_size = Some(size);
+    }
+
+    fn set_window_position(&mut self, pos: Vec2) {
+        self.platform.set_window_position = Some(pos);
+    }
+
+    fn start_timer(&mut self, interval: f64, repeats: bool) -> Timer {
+        self.timer_id += 1;
+        self.platform.timers.push(HeadlessTimer {
+            timer_id: self.timer_id,
+            interval,
+            repeats,
+            fire_at: self.platform.time + interval
+        });
+        Timer(self.timer_id)
+    }
+
+    fn stop_timer(&mut self, timer: Timer) {
+        self.platform.timers.retain( | t | t.timer_id != timer.0);
+    }
+
+    fn post_signal(signal: Signal) {
+        if let Ok(mut signals) = HEADLESS_SIGNALS.lock() {
+            signals.push(signal);
+        }
+    }
+
+    fn spawn_thread<F>(&mut self, f: F) where F: FnOnce() + Send + 'static {
+        std::thread::spawn(f);
+    }
+
+    // there is no network here, sockets fail right after the current step of the script
+    fn web_socket_open(&mut self, _url: String, _rec: WebSocketAutoReconnect) -> WebSocket {
+        let web_socket = WebSocket(self.web_socket_id);
+        self.web_socket_id += 1;
+        self.platform.script.push_front(HeadlessCmd::Event(Event::WebSocketClose(web_socket)));
+        self.platform.script.push_front(HeadlessCmd::Event(Event::WebSocketError(WebSocketErrorEvent {
+            web_socket,
+            error: "web sockets are not supported by the headless platform".to_string()
+        })));
+        web_socket
+    }
+
+    fn web_socket_send(&mut self, web_socket: WebSocket, _data: Vec<u8>) {
+        self.platform.script.push_front(HeadlessCmd::Event(Event::WebSocketError(WebSocketErrorEvent {
+            web_socket,
+            error: "web socket is closed".to_string()
+        })));
+    }
+
+    fn start_midi_input(&mut self) {
+    }
+
+    fn spawn_audio_output<F>(&mut self, _f: F) where F: FnMut(AudioTime, &mut dyn AudioOutputBuffer) + Send + 'static {
+    }
+
+    fn update_menu(&mut self, _menu: &Menu) {
+    }
+
+    fn start_dragging(&mut self, _dragged_item: DraggedItem) {
+    }
+}
+
+pub struct CxPlatform {
+    pub bytes_written: usize,
+    pub draw_calls_done: usize,
+    pub set_window_position: Option<Vec2>,
+    pub set_window_outer_size: Option<Vec2>,
+    pub set_ime_position: Option<Vec2>,
+    pub text_clipboard_response: Option<String>,
+    pub desktop: CxDesktop,
+    pub dpi_factor: f32,
+    pub time: f64,
+    pub script: VecDeque<HeadlessCmd>,
+    pub timers: Vec<HeadlessTimer>,
+    pub windows: Vec<HeadlessWindow>,
+}
+
+impl Default for CxPlatform {
+    fn default() -> Self {
+        Self {
+            bytes_written: 0,
+            draw_calls_done: 0,
+            set_window_position: None,
+            set_window_outer_size: None,
+            set_ime_position: None,
+            text_clipboard_response: None,
+            desktop: CxDesktop::default(),
+            dpi_factor: 1.0,
+            time: 0.0,
+            script: VecDeque::new(),
+            timers: Vec::new(),
+            windows: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::*;
+    use super::HeadlessCmd;
+    use crate::platform::headless::png::decode_png_bgra;
+
+    live_register!{
+        use makepad_platform::shader::std::*;
+
+        HeadlessTest: {{HeadlessTest}} {
+            window: {inner_size: vec2(64, 24)}
+            pass: {clear_color: #102030}
+            rect: {color: #f80}
+            image: {
+                texture tex: texture2d
+                fn pixel(self) -> vec4 {
+                    return sample2d(self.tex, self.pos);
+                }
+            }
+            text: {
+                color: #fff
+                text_style: {font_size: 8.0}
+            }
+            circle: {
+                color: #08f
+                fn pixel(self) -> vec4 {
+                    let sdf = Sdf2d::viewport(self.pos * self.rect_size);
+                    sdf.circle(8.0, 8.0, 6.0);
+                    return sdf.fill(self.color);
+                }
+            }
+        }
+    }
+
+    #[derive(Live)]
+    struct HeadlessTest {
+        window: Window,
+        pass: Pass,
+        main_view: View,
+        rect: DrawColor,
+        circle: DrawColor,
+        image: DrawColor,
+        text: DrawText,
+        #[rust] texture: Option<Texture>,
+    }
+
+    impl LiveHook for HeadlessTest {
+        fn after_new(&mut self, cx: &mut Cx) {
+            self.window.set_pass(cx, &self.pass);
+            // a 2x2 checkerboard, sampled with bilinear filtering
+            let texture = Texture::new(cx);
+            texture.set_desc(cx, TextureDesc {
+                format: TextureFormat::ImageBGRA,
+                width: Some(2),
+                height: Some(2),
+                multisample: None
+            });
+            texture.swap_image_u32(cx, &mut vec![0xffffffff, 0xff000000, 0xff000000, 0xffffffff]);
+            self.image.draw_super.draw_vars.set_texture(0, &texture);
+            self.texture = Some(texture);
+        }
+    }
+
+    impl HeadlessTest {
+        fn handle_event(&mut self, cx: &mut Cx, event: &mut Event) {
+            if let Event::Draw(draw_event) = event {
+                let cx = &mut Cx2d::new(cx, draw_event);
+                cx.begin_pass(&self.pass);
+                self.main_view.begin(cx, Walk::default(), Layout::default()).unwrap();
+                self.rect.draw_abs(cx, Rect {pos: vec2(2.0, 2.0), size: vec2(12.0, 8.0)});
+                self.circle.draw_abs(cx, Rect {pos: vec2(12.0, 4.0), size: vec2(16.0, 16.0)});
+                self.image.draw_abs(cx, Rect {pos: vec2(30.0, 4.0), size: vec2(8.0, 8.0)});
+                self.text.draw_abs(cx, vec2(40.0, 4.0), "Hi");
+                self.main_view.end(cx);
+                cx.end_pass(&self.pass);
+            }
+        }
+    }
+
+    fn new_cx() -> Cx {
+        let mut cx = Cx::default();
+        crate::live_cx::live_register(&mut cx);
+        live_register(&mut cx);
+        cx.live_expand();
+        cx.live_scan_dependencies();
+        // the fonts are in the resources of the repository
+        for (path, dependency) in &mut cx.dependencies {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("..").join(path);
+            dependency.data = Some(std::fs::read(path).map_err( | err | err.to_string()));
+        }
+        cx
+    }
+
+    // runs the script with the test app, and calls `on_event` with every event after the app
+    fn run(cx: &mut Cx, mut on_event: impl FnMut(&mut Cx, &mut Event)) {
+        let mut app = None;
+        cx.event_loop( | cx, event | {
+            if let Event::Construct = event {
+                app = Some(HeadlessTest::new_from_module(cx, &module_path!(), id!(HeadlessTest)).unwrap());
+            }
+            app.as_mut().unwrap().handle_event(cx, event);
+            // draws the glyphs of the text into the font atlas, like `main_app!` does
+            cx.after_handle_event(event);
+            on_event(cx, event);
+        });
+    }
+
+    // set MAKEPAD_UPDATE_GOLDEN to write the current output over the golden image
+    #[test]
+    fn screenshot_matches_golden() {
+        let golden_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/platform/headless/golden/headless_test.png");
+        let path = std::env::temp_dir().join(format!("makepad_headless_test_{}.png", std::process::id()));
+
+        let mut cx = new_cx();
+        cx.headless_push(HeadlessCmd::Screenshot {window_id: 0, path: path.to_str().unwrap().to_string()});
+        run(&mut cx, | _, _ | ());
+
+        let png = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        if std::env::var("MAKEPAD_UPDATE_GOLDEN").is_ok() {
+            std::fs::write(golden_path, &png).unwrap();
+        }
+        let (width, height, pixels) = decode_png_bgra(&png).unwrap();
+        let (golden_width, golden_height, golden) = decode_png_bgra(&std::fs::read(golden_path).unwrap()).unwrap();
+        assert_eq!((width, height), (golden_width, golden_height));
+        // leave a little room for float differences between platforms
+        for (pixel, golden) in pixels.iter().zip(golden.iter()) {
+            let close = pixel.to_le_bytes().iter().zip(golden.to_le_bytes().iter()).all( | (a, b) | (*a as i32 - *b as i32).abs() <= 4);
+            assert!(close, "{:08x} != {:08x}", pixel, golden);
+        }
+    }
+
+    #[test]
+    fn window_outer_size_resizes_the_frame() {
+        let mut cx = new_cx();
+        run(&mut cx, | cx, event | if let Event::Construct = event {
+            cx.set_window_outer_size(vec2(40.0, 20.0));
+            cx.set_window_position(vec2(5.0, 6.0));
+        });
+        let (width, height, _) = cx.headless_window_image(0).unwrap();
+        assert_eq!((width, height), (40, 20));
+        assert_eq!(cx.windows[0].window_geom.position, vec2(5.0, 6.0));
+    }
+
+    #[test]
+    fn web_sockets_fail_and_close() {
+        let mut cx = new_cx();
+        cx.headless_push(HeadlessCmd::Wait(0.1));
+        let mut web_socket = None;
+        let mut events = Vec::new();
+        run(&mut cx, | cx, event | match event {
+            Event::Construct => {
+                let socket = cx.web_socket_open("ws://localhost".to_string(), WebSocketAutoReconnect::No);
+                cx.web_socket_send(socket, vec![1, 2, 3]);
+                web_socket = Some(socket);
+            }
+            Event::WebSocketError(event) => events.push(("error", event.web_socket)),
+            Event::WebSocketClose(socket) => events.push(("close", *socket)),
+            _ => ()
+        });
+        let web_socket = web_socket.unwrap();
+        assert_eq!(events, vec![("error", web_socket), ("error", web_socket), ("close", web_socket)]);
+    }
+}
@@ -0,0 +1,6 @@
+pub mod headless;
+pub mod software;
+pub mod png;
+
+pub use crate::platform::headless::headless::*;
+pub use crate::platform::headless::software::*;
@@ -0,0 +1,206 @@
+// A minimal png writer so headless screenshots don't need an image crate.
+// The image data is stored with uncompressed deflate blocks, which every decoder accepts.
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+
+// the largest payload a stored deflate block can carry
+const MAX_STORED_BLOCK: usize = 0xffff;
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {(crc >> 1) ^ 0xedb8_8320} else {crc >> 1};
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a = 1u32;
+    let mut b = 0u32;
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+fn write_chunk(out: &mut Vec<u8>, ty: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(ty);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len() + data.len() / MAX_STORED_BLOCK * 5 + 16);
+    // deflate with a 32k window, no preset dictionary
+    out.push(0x78);
+    out.push(0x01);
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(if blocks.peek().is_none() {1} else {0});
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+/// Encodes premultiplied 0xAARRGGBB pixels as an 8 bit RGBA png.
+pub fn encode_png_bgra(width: usize, height: usize, pixels: &[u32]) -> Vec<u8> {
+    let mut raw = Vec::with_capacity((width * 4 + 1) * height);
+    for y in 0..height {
+        // filter type none
+        raw.push(0);
+        for pixel in &pixels[y * width..(y + 1) * width] {
+            let a = (pixel >> 24) & 0xff;
+            let unpremul = | c: u32 | if a == 0 {0} else {((c * 255 + a / 2) / a).min(255) as u8};
+            raw.push(unpremul((pixel >> 16) & 0xff));
+            raw.push(unpremul((pixel >> 8) & 0xff));
+            raw.push(unpremul(pixel & 0xff));
+            raw.push(a as u8);
+        }
+    }
+
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&(width as u32).to_be_bytes());
+    ihdr.extend_from_slice(&(height as u32).to_be_bytes());
+    // bit depth 8, color type RGBA, deflate, adaptive filtering, no interlace
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+    let mut out = Vec::new();
+    out.extend_from_slice(&PNG_SIGNATURE);
+    write_chunk(&mut out, b"IHDR", &ihdr);
+    write_chunk(&mut out, b"IDAT", &zlib_stored(&raw));
+    write_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+fn be_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn zlib_unstored(data: &[u8]) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut pos = 2;
+    loop {
+        let header = *data.get(pos) ?;
+        // only stored blocks, which is what zlib_stored writes
+        if header & 0b110 != 0 {
+            return None
+        }
+        let len = u16::from_le_bytes([*data.get(pos + 1) ?, *data.get(pos + 2) ?]) as usize;
+        out.extend_from_slice(data.get(pos + 5..pos + 5 + len) ?);
+        pos += 5 + len;
+        if header & 1 != 0 {
+            break;
+        }
+    }
+    let adler = be_u32(data.get(pos..pos + 4) ?);
+    if adler != adler32(&out) {
+        return None
+    }
+    Some(out)
+}
+
+/// Decodes a png written by `encode_png_bgra` back into width, height and premultiplied
+/// 0xAARRGGBB pixels, so screenshots can be compared against golden images.
+/// Returns None for anything else, like compressed or filtered image data.
+pub fn decode_png_bgra(data: &[u8]) -> Option<(usize, usize, Vec<u32>)> {
+    if data.get(0..8) ? != PNG_SIGNATURE {
+        return None
+    }
+    let mut pos = 8;
+    let mut size = None;
+    let mut idat = Vec::new();
+    while pos + 12 <= data.len() {
+        let len = be_u32(&data[pos..pos + 4]) as usize;
+        let ty = &data[pos + 4..pos + 8];
+        let chunk = data.get(pos + 8..pos + 8 + len) ?;
+        let crc = be_u32(data.get(pos + 8 + len..pos + 12 + len) ?);
+        if crc != crc32(&data[pos + 4..pos + 8 + len]) {
+            return None
+        }
+        match ty {
+            b"IHDR" => {
+                if chunk.len() != 13 || chunk[8..13] != [8, 6, 0, 0, 0] {
+                    return None
+                }
+                let width = be_u32(&chunk[0..4]) as usize;
+                let height = be_u32(&chunk[4..8]) as usize;
+                size = Some((width, height));
+            }
+            b"IDAT" => idat.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => ()
+        }
+        pos += 12 + len;
+    }
+    let (width, height) = size ?;
+    let raw = zlib_unstored(&idat) ?;
+    if raw.len() != (width * 4 + 1) * height {
+        return None
+    }
+    let mut pixels = Vec::with_capacity(width * height);
+    for row in raw.chunks_exact(width * 4 + 1) {
+        if row[0] != 0 {
+            return None
+        }
+        for rgba in row[1..].chunks_exact(4) {
+            let a = rgba[3] as u32;
+            let premul = | c: u8 | (c as u32 * a + 127) / 255;
+            pixels.push((a << 24) | (premul(rgba[0]) << 16) | (premul(rgba[1]) << 8) | premul(rgba[2]));
+        }
+    }
+    Some((width, height, pixels))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let width = 300;
+        let height = 250;
+        // large enough to need more than one stored block
+        let pixels: Vec<u32> = (0..width * height).map( | i | {
+            match i % 3 {
+                0 => 0xff00_0000 | (i as u32).wrapping_mul(2654435761) >> 8,
+                1 => 0,
+                _ => 0x8040_2010,
+            }
+        }).collect();
+        let png = encode_png_bgra(width, height, &pixels);
+        assert!((width * 4 + 1) * height > MAX_STORED_BLOCK);
+        assert_eq!(decode_png_bgra(&png), Some((width, height, pixels)));
+    }
+
+    #[test]
+    fn empty_image() {
+        let png = encode_png_bgra(0, 0, &[]);
+        assert_eq!(decode_png_bgra(&png), Some((0, 0, Vec::new())));
+    }
+
+    #[test]
+    fn rejects_corrupt_data() {
+        let mut png = encode_png_bgra(2, 2, &[0xff00_00ff; 4]);
+        let last = png.len() - 20;
+        png[last] ^= 1;
+        assert_eq!(decode_png_bgra(&png), None);
+    }
+}
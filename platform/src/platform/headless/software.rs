@@ -0,0 +1,557 @@
+use {
+    std::mem,
+    crate::{
+        makepad_shader_compiler::{
+            shader_ast::{Ident, TyLit},
+            interpreter::{ShaderInterpreter, Value, Dual},
+            DrawShaderPtr,
+            DrawShaderFieldKind,
+            ShaderTy,
+        },
+        makepad_math::*,
+        cx::Cx,
+        cx_draw_shaders::DrawShaderInputs,
+        pass::{PassClearColor, PassClearDepth},
+        texture::{CxTexture, TextureFormat},
+    },
+};
+
+// pixels are premultiplied 0xAARRGGBB, which is the byte order of TextureFormat::ImageBGRA in memory
+pub fn pack_bgra(color: Vec4) -> u32 {
+    let c = | v: f32 | (v.max(0.0).min(1.0) * 255.0 + 0.5) as u32;
+    (c(color.w) << 24) | (c(color.x) << 16) | (c(color.y) << 8) | c(color.z)
+}
+
+pub fn unpack_bgra(pixel: u32) -> Vec4 {
+    let c = | shift: u32 | ((pixel >> shift) & 0xff) as f32 / 255.0;
+    Vec4 {x: c(16), y: c(8), z: c(0), w: c(24)}
+}
+
+/// A color and depth buffer the software rasterizer draws into.
+pub struct SwTarget<'a> {
+    pub width: usize,
+    pub height: usize,
+    pub color: &'a mut [u32],
+    pub depth: &'a mut [f32],
+}
+
+impl<'a> SwTarget<'a> {
+    pub fn clear(&mut self, clear_color: Option<Vec4>, clear_depth: Option<f32>) {
+        if let Some(clear_color) = clear_color {
+            let pixel = pack_bgra(clear_color);
+            self.color.iter_mut().for_each( | p | *p = pixel);
+        }
+        if let Some(clear_depth) = clear_depth {
+            self.depth.iter_mut().for_each( | d | *d = clear_depth);
+        }
+    }
+
+    /// Rasterizes a clip space triangle with a less-or-equal depth test and premultiplied alpha blending.
+    /// `shade` gets the barycentric weights of each covered pixel, and how much they change one pixel
+    /// to the right and one pixel down, and returns its color, or None to discard it.
+    pub fn draw_triangle<F>(&mut self, clip: [Vec4; 3], mut shade: F)
+    where F: FnMut(Vec3, Vec3, Vec3) -> Option<Vec4>
+    {
+        if clip.iter().any( | v | v.w <= 0.0) {
+            return
+        }
+        let width = self.width as f32;
+        let height = self.height as f32;
+        let mut v = [Vec3::default(); 3];
+        for i in 0..3 {
+            let ndc = Vec3 {x: clip[i].x / clip[i].w, y: clip[i].y / clip[i].w, z: clip[i].z / clip[i].w};
+            v[i] = Vec3 {
+                x: (ndc.x * 0.5 + 0.5) * width,
+                y: (0.5 - ndc.y * 0.5) * height,
+                z: ndc.z
+            };
+        }
+        // keep a consistent winding so edges shared by two triangles are traversed in opposite directions
+        let mut order = [0, 1, 2];
+        let mut area = orient(v[0], v[1], v[2]);
+        if area == 0.0 {
+            return
+        }
+        if area < 0.0 {
+            order = [0, 2, 1];
+            area = -area;
+        }
+        let (a, b, c) = (v[order[0]], v[order[1]], v[order[2]]);
+
+        // the weights are affine in screen space, so their steps are the same for every pixel
+        let mut bary_dx = [0.0; 3];
+        let mut bary_dy = [0.0; 3];
+        for (i, (from, to)) in [(b, c), (c, a), (a, b)].iter().enumerate() {
+            bary_dx[order[i]] = (from.y - to.y) / area;
+            bary_dy[order[i]] = (to.x - from.x) / area;
+        }
+        let bary_dx = Vec3 {x: bary_dx[0], y: bary_dx[1], z: bary_dx[2]};
+        let bary_dy = Vec3 {x: bary_dy[0], y: bary_dy[1], z: bary_dy[2]};
+
+        let min_x = a.x.min(b.x).min(c.x).floor().max(0.0) as usize;
+        let min_y = a.y.min(b.y).min(c.y).floor().max(0.0) as usize;
+        let max_x = (a.x.max(b.x).max(c.x).ceil().min(width) as usize).min(self.width);
+        let max_y = (a.y.max(b.y).max(c.y).ceil().min(height) as usize).min(self.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let p = Vec3 {x: x as f32 + 0.5, y: y as f32 + 0.5, z: 0.0};
+                let w0 = edge(b, c, p);
+                let w1 = edge(c, a, p);
+                let w2 = edge(a, b, p);
+                if !covers(w0, b, c) || !covers(w1, c, a) || !covers(w2, a, b) {
+                    continue
+                }
+                let (w0, w1, w2) = (w0 / area, w1 / area, w2 / area);
+                let z = w0 * a.z + w1 * b.z + w2 * c.z;
+                let index = y * self.width + x;
+                if z > self.depth[index] {
+                    continue
+                }
+                let mut bary = [0.0; 3];
+                bary[order[0]] = w0;
+                bary[order[1]] = w1;
+                bary[order[2]] = w2;
+                if let Some(src) = shade(Vec3 {x: bary[0], y: bary[1], z: bary[2]}, bary_dx, bary_dy) {
+                    let dst = unpack_bgra(self.color[index]);
+                    let inv = 1.0 - src.w;
+                    self.color[index] = pack_bgra(Vec4 {
+                        x: src.x + dst.x * inv,
+                        y: src.y + dst.y * inv,
+                        z: src.z + dst.z * inv,
+                        w: src.w + dst.w * inv,
+                    });
+                    self.depth[index] = z;
+                }
+            }
+        }
+    }
+}
+
+fn orient(a: Vec3, b: Vec3, p: Vec3) -> f32 {
+    (b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x)
+}
+
+// evaluated with the endpoints in a fixed order, so the two triangles sharing an edge get exactly
+// opposite values and rounding can't leave a pixel on it outside both
+fn edge(a: Vec3, b: Vec3, p: Vec3) -> f32 {
+    if (a.y, a.x) < (b.y, b.x) {orient(a, b, p)} else {-orient(b, a, p)}
+}
+
+// pixels exactly on an edge belong to only one of the two triangles sharing it
+fn covers(w: f32, a: Vec3, b: Vec3) -> bool {
+    if w != 0.0 {
+        return w > 0.0
+    }
+    let dx = b.x - a.x;
+    let dy = b.y - a.y;
+    dy > 0.0 || (dy == 0.0 && dx < 0.0)
+}
+
+// feeds the inputs of one block (instances, a uniform block, a geometry vertex) to the interpreter
+fn set_inputs(interp: &mut ShaderInterpreter, inputs: &DrawShaderInputs, slots: &[f32]) {
+    for input in &inputs.inputs {
+        let value = match input.ty {
+            // enums are stored as the bits of their index, like the gpu backends upload them
+            ShaderTy::Enum(_) => Value::int(slots[input.offset].to_bits() as i32),
+            _ => Value::from_slots(&input.ty, &slots[input.offset..input.offset + input.slots])
+        };
+        interp.set_field(Ident(input.id), value);
+    }
+}
+
+/// Samples a texture with bilinear filtering and clamped edges, textures without pixels read as
+/// transparent black.
+pub fn sample_texture(cxtexture: &CxTexture, uv: Vec2) -> Vec4 {
+    let (width, height) = match (cxtexture.desc.width, cxtexture.desc.height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 && cxtexture.image_u32.len() == width * height => (width, height),
+        _ => return Vec4::default()
+    };
+    let x = (uv.x * width as f32 - 0.5).max(0.0).min((width - 1) as f32);
+    let y = (uv.y * height as f32 - 0.5).max(0.0).min((height - 1) as f32);
+    let (x0, y0) = (x as usize, y as usize);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+    let texel = | x: usize, y: usize | unpack_bgra(cxtexture.image_u32[y * width + x]);
+    let mix = | a: Vec4, b: Vec4, f: f32 | Vec4 {
+        x: a.x + (b.x - a.x) * f,
+        y: a.y + (b.y - a.y) * f,
+        z: a.z + (b.z - a.z) * f,
+        w: a.w + (b.w - a.w) * f,
+    };
+    mix(mix(texel(x0, y0), texel(x1, y0), fx), mix(texel(x0, y1), texel(x1, y1), fx), fy)
+}
+
+impl Cx {
+
+    fn sw_render_view(
+        &mut self,
+        pass_id: usize,
+        draw_list_id: usize,
+        scroll: Vec2,
+        clip: (Vec2, Vec2),
+        zbias: &mut f32,
+        zbias_step: f32,
+        target: &mut SwTarget,
+    ) {
+        // tad ugly otherwise the borrow checker locks 'self' and we can't recur
+        let draw_items_len = self.draw_lists[draw_list_id].draw_items_len;
+        self.draw_lists[draw_list_id].uniform_view_transform(&Mat4::identity());
+        self.draw_lists[draw_list_id].parent_scroll = scroll;
+        let local_scroll = self.draw_lists[draw_list_id].get_local_scroll();
+        let clip = self.draw_lists[draw_list_id].intersect_clip(clip);
+
+        let live_registry = self.live_registry.clone();
+        let live_registry = live_registry.borrow();
+
+        for draw_item_id in 0..draw_items_len {
+            if let Some(sub_view_id) = self.draw_lists[draw_list_id].draw_items[draw_item_id].sub_view_id {
+                self.sw_render_view(
+                    pass_id,
+                    sub_view_id,
+                    Vec2 {x: local_scroll.x + scroll.x, y: local_scroll.y + scroll.y},
+                    clip,
+                    zbias,
+                    zbias_step,
+                    target,
+                );
+            }
+            else {
+                let draw_list = &mut self.draw_lists[draw_list_id];
+                let draw_call = draw_list.draw_items[draw_item_id].draw_call.as_mut().unwrap();
+                let sh = &self.draw_shaders.shaders[draw_call.draw_shader.draw_shader_id];
+                if sh.platform.is_none() { // shader didnt compile somehow
+                    continue;
+                }
+                let shp = &self.draw_shaders.platform[sh.platform.unwrap()];
+
+                draw_call.instance_dirty = false;
+                draw_call.draw_uniforms.set_zbias(*zbias);
+                draw_call.draw_uniforms.set_local_scroll(
+                    scroll,
+                    local_scroll,
+                    &draw_call.options
+                );
+                draw_call.draw_uniforms.set_clip(clip);
+                *zbias += zbias_step;
+
+                let slots = sh.mapping.instances.total_slots;
+                let instances = draw_call.instances.as_ref().unwrap();
+                if instances.len() == 0 {
+                    continue;
+                }
+                let geometry = if let Some(geometry_id) = draw_call.geometry_id {&self.geometries[geometry_id]}
+                else {
+                    continue;
+                };
+                let geometry_slots = sh.mapping.geometries.total_slots;
+
+                let draw_shader_def = self.shader_registry.draw_shader_defs.get(&shp.draw_shader_ptr).unwrap();
+                let mut interp = ShaderInterpreter::new(&self.shader_registry, draw_shader_def);
+                // a shader whose live values don't evaluate already failed to compile, and is
+                // skipped like the gpu backends skip it
+                if interp.load_live_values(&live_registry).is_err() {
+                    continue;
+                }
+                let textures = &self.textures;
+                interp.sampler = Some(Box::new(move | texture_id, uv | sample_texture(&textures[texture_id], uv)));
+
+                set_inputs(&mut interp, &sh.mapping.pass_uniforms, self.passes[pass_id].pass_uniforms.as_slice());
+                set_inputs(&mut interp, &sh.mapping.view_uniforms, draw_list.draw_list_uniforms.as_slice());
+                set_inputs(&mut interp, &sh.mapping.draw_uniforms, draw_call.draw_uniforms.as_slice());
+                set_inputs(&mut interp, &sh.mapping.user_uniforms, &draw_call.user_uniforms);
+                for (slot, texture) in sh.mapping.textures.iter().enumerate() {
+                    interp.set_field(Ident(texture.id), Value::Texture(draw_call.texture_slots[slot].unwrap_or(0)));
+                }
+
+                for instance in instances.chunks_exact(slots) {
+                    set_inputs(&mut interp, &sh.mapping.instances, instance);
+                    for triangle in geometry.indices.chunks_exact(3) {
+                        let mut clip_pos = [Vec4::default(); 3];
+                        let mut varyings = [Vec::new(), Vec::new(), Vec::new()];
+                        for i in 0..3 {
+                            let vertex = triangle[i] as usize * geometry_slots;
+                            set_inputs(&mut interp, &sh.mapping.geometries, &geometry.vertices[vertex..vertex + geometry_slots]);
+                            clip_pos[i] = interp.vertex().to_vec4().unwrap_or_default();
+                            varyings[i] = shp.varyings.iter().map( | varying | {
+                                interp.field(varying.ident).and_then( | value | value.to_f32s()).unwrap_or_else( || vec![0.0; varying.slots])
+                            }).collect::<Vec<_ >>();
+                        }
+                        // varyings are interpolated affinely in screen space, which is exact as long as w is 1
+                        target.draw_triangle(clip_pos, | bary, bary_dx, bary_dy | {
+                            for (index, varying) in shp.varyings.iter().enumerate() {
+                                let duals = (0..varying.slots).map( | slot | {
+                                    let v = Vec3 {x: varyings[0][index][slot], y: varyings[1][index][slot], z: varyings[2][index][slot]};
+                                    Dual::with_derivs(bary.dot(v), bary_dx.dot(v), bary_dy.dot(v))
+                                }).collect();
+                                interp.set_field(varying.ident, Value::Float(varying.ty_lit, duals));
+                            }
+                            interp.pixel().to_vec4()
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn setup_render_pass(&mut self, pass_id: usize, inherit_dpi_factor: f32) -> f32 {
+        let pass_size = self.passes[pass_id].pass_size;
+        self.passes[pass_id].set_matrix(Vec2::default(), pass_size);
+        self.passes[pass_id].paint_dirty = false;
+
+        let dpi_factor = if let Some(override_dpi_factor) = self.passes[pass_id].override_dpi_factor {
+            override_dpi_factor
+        }
+        else {
+            inherit_dpi_factor
+        };
+        self.passes[pass_id].set_dpi_factor(dpi_factor);
+        dpi_factor
+    }
+
+    // (re)allocates the pixels of a texture, returns true if it was resized so InitWith clears apply
+    fn sw_alloc_texture(&mut self, texture_id: usize, width: usize, height: usize) -> bool {
+        let cxtexture = &mut self.textures[texture_id];
+        cxtexture.desc.format = TextureFormat::ImageBGRA;
+        if cxtexture.desc.width == Some(width) && cxtexture.desc.height == Some(height) && cxtexture.image_u32.len() == width * height {
+            return false
+        }
+        cxtexture.desc.width = Some(width);
+        cxtexture.desc.height = Some(height);
+        cxtexture.image_u32 = vec![0; width * height];
+        true
+    }
+
+    fn sw_draw_pass(&mut self, pass_id: usize, texture_id: usize, width: usize, height: usize, clear_color: Option<Vec4>, clear_depth: Option<f32>) {
+        let draw_list_id = if let Some(draw_list_id) = self.passes[pass_id].main_draw_list_id {draw_list_id} else {return};
+
+        let mut color = mem::take(&mut self.textures[texture_id].image_u32);
+        let mut depth = mem::take(&mut self.passes[pass_id].platform.depth);
+        let depth_resized = depth.len() != width * height;
+        if depth_resized {
+            depth = vec![1.0; width * height];
+        }
+
+        let mut target = SwTarget {
+            width,
+            height,
+            color: &mut color,
+            depth: &mut depth
+        };
+        target.clear(clear_color, if depth_resized {None} else {clear_depth});
+
+        let mut zbias = 0.0;
+        let zbias_step = self.passes[pass_id].zbias_step;
+        self.sw_render_view(
+            pass_id,
+            draw_list_id,
+            Vec2::default(),
+            (Vec2 {x: -50000., y: -50000.}, Vec2 {x: 50000., y: 50000.}),
+            &mut zbias,
+            zbias_step,
+            &mut target
+        );
+
+        self.textures[texture_id].image_u32 = color;
+        self.textures[texture_id].update_image = true;
+        self.passes[pass_id].platform.depth = depth;
+    }
+
+    pub fn sw_draw_pass_to_window(&mut self, pass_id: usize, dpi_factor: f32, inner_size: Vec2, texture_id: usize) {
+        self.setup_render_pass(pass_id, dpi_factor);
+
+        let width = (inner_size.x * dpi_factor) as usize;
+        let height = (inner_size.y * dpi_factor) as usize;
+        self.sw_alloc_texture(texture_id, width, height);
+
+        let clear_color = if self.passes[pass_id].color_textures.len() == 0 {
+            self.passes[pass_id].clear_color
+        }
+        else {
+            match self.passes[pass_id].color_textures[0].clear_color {
+                PassClearColor::InitWith(color) => color,
+                PassClearColor::ClearWith(color) => color
+            }
+        };
+        let clear_depth = match self.passes[pass_id].clear_depth {
+            PassClearDepth::InitWith(depth) => depth,
+            PassClearDepth::ClearWith(depth) => depth
+        };
+        self.sw_draw_pass(pass_id, texture_id, width, height, Some(clear_color), Some(clear_depth));
+    }
+
+    pub fn sw_draw_pass_to_texture(&mut self, pass_id: usize, dpi_factor: f32) {
+        let pass_size = self.passes[pass_id].pass_size;
+        let dpi_factor = self.setup_render_pass(pass_id, dpi_factor);
+
+        // the software rasterizer only renders into the first color target
+        let color_texture = if let Some(color_texture) = self.passes[pass_id].color_textures.get(0) {
+            color_texture.clone()
+        }
+        else {
+            return
+        };
+        let desc = self.textures[color_texture.texture_id].desc;
+        let width = desc.width.unwrap_or((pass_size.x * dpi_factor) as usize);
+        let height = desc.height.unwrap_or((pass_size.y * dpi_factor) as usize);
+        let is_initial = self.sw_alloc_texture(color_texture.texture_id, width, height);
+
+        let clear_color = match color_texture.clear_color {
+            PassClearColor::InitWith(color) => if is_initial {Some(color)} else {None},
+            PassClearColor::ClearWith(color) => Some(color)
+        };
+        let clear_depth = match self.passes[pass_id].clear_depth {
+            PassClearDepth::InitWith(depth) => if is_initial {Some(depth)} else {None},
+            PassClearDepth::ClearWith(depth) => Some(depth)
+        };
+        self.sw_draw_pass(pass_id, color_texture.texture_id, width, height, clear_color, clear_depth);
+    }
+
+    pub fn sw_compile_shaders(&mut self) {
+        for draw_shader_ptr in &self.draw_shaders.compile_set {
+            if let Some(item) = self.draw_shaders.ptr_to_item.get(&draw_shader_ptr) {
+                let draw_shader_def = self.shader_registry.draw_shader_defs.get(draw_shader_ptr).unwrap();
+                // geometries the pixel shader reads are passed along like varyings
+                let varyings = draw_shader_def.fields.iter().filter_map( | field | {
+                    match &field.kind {
+                        DrawShaderFieldKind::Varying {..} => (),
+                        DrawShaderFieldKind::Geometry {is_used_in_pixel_shader, ..} if is_used_in_pixel_shader.get() => (),
+                        _ => return None
+                    }
+                    let ty = field.ty_expr.ty.borrow().clone().unwrap();
+                    Some(SwVarying {ident: field.ident, ty_lit: ty.maybe_ty_lit().unwrap(), slots: ty.slots()})
+                }).collect();
+                let cx_shader = &mut self.draw_shaders.shaders[item.draw_shader_id];
+                cx_shader.platform = Some(self.draw_shaders.platform.len());
+                self.draw_shaders.platform.push(CxPlatformDrawShader {
+                    draw_shader_ptr: *draw_shader_ptr,
+                    varyings
+                });
+            }
+        }
+        self.draw_shaders.compile_set.clear();
+    }
+}
+
+#[derive(Clone)]
+pub struct SwVarying {
+    pub ident: Ident,
+    pub ty_lit: TyLit,
+    pub slots: usize,
+}
+
+/// The software renderer runs the shader through the interpreter, so all it keeps is which shader
+/// it is and what to interpolate between the vertex and the pixel stage.
+#[derive(Clone)]
+pub struct CxPlatformDrawShader {
+    pub draw_shader_ptr: DrawShaderPtr,
+    pub varyings: Vec<SwVarying>,
+}
+
+#[derive(Clone, Default)]
+pub struct CxPlatformGeometry {
+}
+
+#[derive(Clone, Default)]
+pub struct CxPlatformView {
+}
+
+#[derive(Default, Clone)]
+pub struct CxPlatformDrawCall {
+}
+
+#[derive(Default, Clone)]
+pub struct CxPlatformTexture {
+}
+
+#[derive(Default, Clone)]
+pub struct CxPlatformPass {
+    pub depth: Vec<f32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::TextureDesc;
+
+    fn target_test<F>(width: usize, height: usize, f: F) -> Vec<u32> where F: FnOnce(&mut SwTarget) {
+        let mut color = vec![0; width * height];
+        let mut depth = vec![1.0; width * height];
+        f(&mut SwTarget {width, height, color: &mut color, depth: &mut depth});
+        color
+    }
+
+    #[test]
+    fn shared_edges_are_drawn_once() {
+        let half = Vec4 {x: 0.5, y: 0.0, z: 0.0, w: 0.5};
+        // a 16 by 16 quad at 12, 4 has its diagonal through pixel centers, and corners that
+        // don't land on exact floats in clip space
+        let clip = | x: f32, y: f32 | Vec4 {x: x / 32.0 * 2.0 - 1.0, y: 1.0 - y / 24.0 * 2.0, z: 0.0, w: 1.0};
+        let (tl, tr, bl, br) = (clip(12.0, 4.0), clip(28.0, 4.0), clip(12.0, 20.0), clip(28.0, 20.0));
+        let color = target_test(32, 24, | target | {
+            target.draw_triangle([tl, tr, br], | _, _, _ | Some(half));
+            target.draw_triangle([tl, br, bl], | _, _, _ | Some(half));
+        });
+        // a pixel on the diagonal missed by both would stay 0, or blended twice come out at 0.75 alpha
+        for y in 0..24 {
+            for x in 0..32 {
+                let inside = x >= 12 && x < 28 && y >= 4 && y < 20;
+                assert_eq!(color[y * 32 + x], if inside {pack_bgra(half)} else {0}, "{} {}", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn barycentric_steps_match_neighbouring_pixels() {
+        let clip = [
+            Vec4 {x: -0.9, y: 0.8, z: 0.0, w: 1.0},
+            Vec4 {x: 0.7, y: 0.3, z: 0.0, w: 1.0},
+            Vec4 {x: -0.2, y: -0.9, z: 0.0, w: 1.0},
+        ];
+        let screen: Vec<Vec2> = clip.iter().map( | v | Vec2 {x: (v.x * 0.5 + 0.5) * 16.0, y: (0.5 - v.y * 0.5) * 16.0}).collect();
+        let mut weights = vec![None; 16 * 16];
+        let mut steps = None;
+        target_test(16, 16, | target | {
+            target.draw_triangle(clip, | bary, dx, dy | {
+                steps = Some((dx, dy));
+                // the weights point back at the center of the pixel being shaded
+                let x = bary.x * screen[0].x + bary.y * screen[1].x + bary.z * screen[2].x;
+                let y = bary.x * screen[0].y + bary.y * screen[1].y + bary.z * screen[2].y;
+                assert!((x.fract() - 0.5).abs() < 1e-3 && (y.fract() - 0.5).abs() < 1e-3, "{} {}", x, y);
+                weights[y as usize * 16 + x as usize] = Some(bary);
+                None
+            });
+        });
+        let (dx, dy) = steps.unwrap();
+        let near = | a: Vec3, b: Vec3 | (a - b).dot(a - b) < 1e-8;
+        let mut checked = 0;
+        for y in 0..15 {
+            for x in 0..15 {
+                if let (Some(w), Some(right), Some(down)) = (weights[y * 16 + x], weights[y * 16 + x + 1], weights[(y + 1) * 16 + x]) {
+                    assert!(near(right - w, dx) && near(down - w, dy));
+                    checked += 1;
+                }
+            }
+        }
+        assert!(checked > 10);
+    }
+
+    #[test]
+    fn bilinear_sampling() {
+        let texture = CxTexture {
+            desc: TextureDesc {
+                format: TextureFormat::ImageBGRA,
+                width: Some(2),
+                height: Some(1),
+                ..TextureDesc::default()
+            },
+            image_u32: vec![0xff00_0000, 0xffff_ffff],
+            ..CxTexture::default()
+        };
+        assert_eq!(sample_texture(&texture, Vec2 {x: 0.25, y: 0.5}), Vec4 {x: 0.0, y: 0.0, z: 0.0, w: 1.0});
+        assert_eq!(sample_texture(&texture, Vec2 {x: 0.5, y: 0.5}), Vec4 {x: 0.5, y: 0.5, z: 0.5, w: 1.0});
+        // outside the texture the edge texels repeat
+        assert_eq!(sample_texture(&texture, Vec2 {x: 2.0, y: -1.0}), Vec4 {x: 1.0, y: 1.0, z: 1.0, w: 1.0});
+        assert_eq!(sample_texture(&CxTexture::default(), Vec2 {x: 0.5, y: 0.5}), Vec4::default());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod xlib;
+pub mod opengl;
+pub mod linux;
+
+pub use crate::platform::linux::opengl::*;
+pub use crate::platform::linux::linux::*;
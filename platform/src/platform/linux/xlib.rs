@@ -1,19 +1,48 @@
-use crate::cx::*;
-use libc;
-use libc::timeval;
-use makepad_x11_sys as X11_sys;
-use std::collections::{HashMap, BTreeSet, VecDeque};
-use std::ffi::CString;
-use std::ffi::CStr;
-use std::slice;
-use std::sync::Mutex;
-use std::fs::File;
-use std::io::Write;
-use std::os::unix::io::FromRawFd;
-use std::mem;
-use std::os::raw::{c_char, c_uchar, c_int, c_uint, c_ulong, c_long, c_void};
-use std::ptr;
-use time::precise_time_ns;
+use {
+    std::{
+        collections::{HashMap, HashSet, VecDeque},
+        ffi::{CStr, CString},
+        slice,
+        sync::Mutex,
+        fs::File,
+        io::Write,
+        os::unix::io::FromRawFd,
+        mem,
+        os::raw::{c_char, c_uchar, c_int, c_uint, c_ulong, c_long, c_void},
+        ptr,
+    },
+    libc::{self, timeval},
+    time::precise_time_ns,
+    makepad_x11_sys as X11_sys,
+    crate::{
+        makepad_math::Vec2,
+        event::{
+            WindowGeom,
+            NUM_FINGERS,
+            Event,
+            Signal,
+            SignalEvent,
+            TimerEvent,
+            KeyCode,
+            KeyEvent,
+            KeyModifiers,
+            TextInputEvent,
+            TextCopyEvent,
+            FingerInputType,
+            FingerDownEvent,
+            FingerUpEvent,
+            FingerMoveEvent,
+            FingerHoverEvent,
+            FingerScrollEvent,
+            WindowDragQueryResponse,
+            WindowDragQueryEvent,
+            WindowGeomChangeEvent,
+            WindowCloseRequestedEvent,
+            WindowClosedEvent,
+        },
+        cursor::MouseCursor,
+    },
+};
 
 #[cfg(target_arch = "arm")]
 pub const LINUX_CUSTOM_WINDOW_CHROME: bool = false;
@@ -112,6 +141,7 @@ impl XlibApp {
             let xim = X11_sys::XOpenIM(display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
             let mut signal_fds = [0, 0];
             libc::pipe(signal_fds.as_mut_ptr());
+            libc::fcntl(signal_fds[0], libc::F_SETFL, libc::O_NONBLOCK);
             XlibApp {
                 atom_clipboard: X11_sys::XInternAtom(display, CString::new("CLIPBOARD").unwrap().as_ptr(), 0),
                 atom_net_wm_moveresize: X11_sys::XInternAtom(display, CString::new("_NET_WM_MOVERESIZE").unwrap().as_ptr(), 0),
@@ -163,10 +193,9 @@ impl XlibApp {
     where F: FnMut(&mut XlibApp, &mut Vec<Event>) -> bool,
     {
         unsafe {
-            self.event_callback = Some(
-                &mut event_handler as *const dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool
-                as *mut dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool
-            );
+            // the callback only lives for the duration of this loop, erase its lifetime to store it
+            let event_callback: *mut (dyn FnMut(&mut XlibApp, &mut Vec<Event>) -> bool + '_) = &mut event_handler;
+            self.event_callback = Some(mem::transmute(event_callback));
             
             self.do_callback(&mut vec![
                 Event::Paint,
@@ -184,14 +213,14 @@ impl XlibApp {
                     // If there are any timers, we set the timeout for select to the `delta_timeout`
                     // of the first timer that should be fired. Otherwise, we set the timeout to
                     // None, so that select will block indefinitely.
-                    let timeout = if let Some(timer) = self.timers.front() {
+                    let mut timeout = if let Some(timer) = self.timers.front() {
                         // println!("Select wait {}",(timer.delta_timeout.fract() * 1000000.0) as i64);
                         Some(timeval {
                             // `tv_sec` is in seconds, so take the integer part of `delta_timeout`
                             tv_sec: timer.delta_timeout.trunc() as libc::time_t,
                             // `tv_usec` is in microseconds, so take the fractional part of
                             // `delta_timeout` 1000000.0.
-                            tv_usec: (timer.delta_timeout.fract() * 1000000.0) as libc::time_t,
+                            tv_usec: (timer.delta_timeout.fract() * 1000000.0) as libc::suseconds_t,
                         })
                     }
                     else {
@@ -202,8 +231,11 @@ impl XlibApp {
                         fds.as_mut_ptr(),
                         ptr::null_mut(),
                         ptr::null_mut(),
-                        if let Some(mut timeout) = timeout {&mut timeout} else {ptr::null_mut()}
+                        timeout.as_mut().map_or(ptr::null_mut(), | timeout | timeout as *mut _)
                     );
+                    // drain the wakeup bytes written by post_signal
+                    let mut buffer = [0u8; 64];
+                    while libc::read(self.signal_fds[0], buffer.as_mut_ptr() as *mut c_void, buffer.len()) > 0 {}
                 }
                 // Update the current time, and compute the amount of time that elapsed since we
                 // last recorded the current time.
@@ -353,6 +385,8 @@ impl XlibApp {
                                     window_id: window.window_id,
                                 })]);
                             }
+                            // the window is dropped by the event handler, don't keep a dangling pointer around
+                            self.window_map.remove(&destroy_window.window);
                         },
                         X11_sys::ConfigureNotify => {
                             let cfg = event.xconfigure;
@@ -369,15 +403,12 @@ impl XlibApp {
                             if crossing.detail == 4 {
                                 if let Some(window_ptr) = self.window_map.get(&crossing.window) {
                                     let window = &mut (**window_ptr);
+                                    // hover a point outside of the window so every area gets a hover out
                                     window.do_callback(&mut vec![Event::FingerHover(FingerHoverEvent {
                                         digit: 0,
                                         window_id: window.window_id,
-                                        any_down: false,
-                                        abs: window.last_mouse_pos,
-                                        rel: window.last_mouse_pos,
-                                        rect: Rect::default(),
+                                        abs: Vec2 {x: -100000.0, y: -100000.0},
                                         handled: false,
-                                        hover_state: HoverState::Out,
                                         modifiers: KeyModifiers::default(),
                                         time: window.time_now()
                                     })]);
@@ -391,7 +422,7 @@ impl XlibApp {
                                 let mut x = motion.x;
                                 let mut y = motion.y;
                                 if window.window.is_none() {
-                                    return; // shutdown
+                                    continue; // shutdown
                                 }
                                 if motion.window != window.window.unwrap() {
                                     // find the right child
@@ -486,8 +517,6 @@ impl XlibApp {
                                             y: if button.button == 4 {-speed as f32} else if button.button == 5 {speed as f32} else {0.}
                                         },
                                         abs: window.last_mouse_pos,
-                                        rel: window.last_mouse_pos,
-                                        rect: Rect::default(),
                                         input_type: FingerInputType::Mouse,
                                         modifiers: self.xkeystate_to_modifiers(button.state),
                                         handled_x: false,
@@ -596,7 +625,6 @@ impl XlibApp {
                                                         // store the text on the clipboard
                                                         self.clipboard = response.clone();
                                                         // lets set the owner
-                                                        println!("Set selection owner");
                                                         X11_sys::XSetSelectionOwner(
                                                             self.display,
                                                             self.atom_clipboard,
@@ -666,7 +694,9 @@ impl XlibApp {
                             if event.message_type == self.atom_wm_protocols {
                                 if let Some(window_ptr) = self.window_map.get(&event.window) {
                                     let window = &mut (**window_ptr);
-                                    window.close_window();
+                                    if window.send_close_requested_event() {
+                                        window.close_window();
+                                    }
                                 }
                             }
                             if event.message_type == self.dnd.atoms.enter {
@@ -794,16 +824,15 @@ impl XlibApp {
         }
     }
     
-    pub fn post_signal(signal: Signal, status: StatusId) {
+    pub fn post_signal(signal: Signal) {
         unsafe {
             if let Ok(mut signals_locked) = (*GLOBAL_XLIB_APP).signals.lock() {
-                let mut signals = HashMap::new();
-                let mut set = BTreeSet::new();
-                set.insert(status);
-                signals.insert(signal, set);
+                let mut signals = HashSet::new();
+                signals.insert(signal);
                 signals_locked.push(Event::Signal(SignalEvent {signals}));
-                let mut f = unsafe { File::from_raw_fd((*GLOBAL_XLIB_APP).signal_fds[1]) };
-                let _ = write!(&mut f, "\0");
+                // wake up the select in the event loop, without closing the pipe on drop
+                let mut f = mem::ManuallyDrop::new(File::from_raw_fd((*GLOBAL_XLIB_APP).signal_fds[1]));
+                let _ = write!(&mut *f, "\0");
             }
         }
     }
@@ -1428,10 +1457,22 @@ impl XlibWindow {
         }
     }
     
-    pub fn set_position(&mut self, _pos: Vec2) {
+    pub fn set_position(&mut self, pos: Vec2) {
+        if let Some(window) = self.window {
+            unsafe {
+                let display = (*self.xlib_app).display;
+                X11_sys::XMoveWindow(display, window, pos.x as c_int, pos.y as c_int);
+            }
+        }
     }
     
-    pub fn set_outer_size(&self, _size: Vec2) {
+    pub fn set_outer_size(&self, size: Vec2) {
+        if let Some(window) = self.window {
+            unsafe {
+                let display = (*self.xlib_app).display;
+                X11_sys::XResizeWindow(display, window, size.x as c_uint, size.y as c_uint);
+            }
+        }
     }
     
     pub fn set_inner_size(&self, _size: Vec2) {
@@ -1494,11 +1535,11 @@ impl XlibWindow {
     }
     
     pub fn send_focus_event(&mut self) {
-        self.do_callback(&mut vec![Event::AppFocus]);
+        self.do_callback(&mut vec![Event::AppGotFocus]);
     }
     
     pub fn send_focus_lost_event(&mut self) {
-        self.do_callback(&mut vec![Event::AppFocusLost]);
+        self.do_callback(&mut vec![Event::AppLostFocus]);
     }
     
     pub fn send_finger_down(&mut self, digit: usize, modifiers: KeyModifiers) {
@@ -1515,8 +1556,6 @@ impl XlibWindow {
         self.do_callback(&mut vec![Event::FingerDown(FingerDownEvent {
             window_id: self.window_id,
             abs: self.last_mouse_pos,
-            rel: self.last_mouse_pos,
-            rect: Rect::default(),
             digit: digit,
             handled: false,
             input_type: FingerInputType::Mouse,
@@ -1540,12 +1579,7 @@ impl XlibWindow {
         self.do_callback(&mut vec![Event::FingerUp(FingerUpEvent {
             window_id: self.window_id,
             abs: self.last_mouse_pos,
-            rel: self.last_mouse_pos,
-            rect: Rect::default(),
-            abs_start: Vec2::default(),
-            rel_start: Vec2::default(),
             digit: digit,
-            is_over: false,
             input_type: FingerInputType::Mouse,
             modifiers: modifiers,
             time: self.time_now()
@@ -1560,12 +1594,7 @@ impl XlibWindow {
                 events.push(Event::FingerMove(FingerMoveEvent {
                     window_id: self.window_id,
                     abs: pos,
-                    rel: pos,
-                    rect: Rect::default(),
                     digit: digit,
-                    abs_start: Vec2::default(),
-                    rel_start: Vec2::default(),
-                    is_over: false,
                     input_type: FingerInputType::Mouse,
                     modifiers: modifiers.clone(),
                     time: self.time_now()
@@ -1576,11 +1605,7 @@ impl XlibWindow {
             digit: 0,
             window_id: self.window_id,
             abs: pos,
-            rel: pos,
-            any_down: false,
-            rect: Rect::default(),
             handled: false,
-            hover_state: HoverState::Over,
             modifiers: modifiers,
             time: self.time_now()
         }));
@@ -1616,15 +1641,8 @@ struct MwmHints {
     pub status: c_ulong,
 }
 
-const MWM_HINTS_FUNCTIONS: c_ulong = 1 << 0;
 const MWM_HINTS_DECORATIONS: c_ulong = 1 << 1;
 
-const MWM_FUNC_ALL: c_ulong = 1 << 0;
-const MWM_FUNC_RESIZE: c_ulong = 1 << 1;
-const MWM_FUNC_MOVE: c_ulong = 1 << 2;
-const MWM_FUNC_MINIMIZE: c_ulong = 1 << 3;
-const MWM_FUNC_MAXIMIZE: c_ulong = 1 << 4;
-const MWM_FUNC_CLOSE: c_ulong = 1 << 5;
 const _NET_WM_MOVERESIZE_SIZE_TOPLEFT: c_long = 0;
 const _NET_WM_MOVERESIZE_SIZE_TOP: c_long = 1;
 const _NET_WM_MOVERESIZE_SIZE_TOPRIGHT: c_long = 2;
@@ -1763,47 +1781,9 @@ impl Dnd {
         // data type. This request is always sent in response to a XDndDrop event, so this event
         // should only be received after a drop operation has completed.
         
-        //let source_window = event.requestor;
-        //let selection = CString::new(self.get_selection_property(source_window)).unwrap();
-        
         // TODO: Actually use the selection
     }
     
-    /// Gets the XDndSelection property from the source window.
-    unsafe fn get_selection_property(&mut self, source_window: X11_sys::Window) -> Vec<c_uchar> {
-        let mut selection = Vec::new();
-        let mut offset = 0;
-        let length = 1024;
-        let mut actual_type = 0;
-        let mut actual_format = 0;
-        let mut nitems = 0;
-        let mut bytes_after = 0;
-        let mut prop = ptr::null_mut();
-        loop {
-            X11_sys::XGetWindowProperty(
-                self.display,
-                source_window,
-                self.atoms.selection,
-                offset,
-                length,
-                X11_sys::False as c_int,
-                self.atoms.uri_list,
-                &mut actual_type,
-                &mut actual_format,
-                &mut nitems,
-                &mut bytes_after,
-                &mut prop,
-            );
-            selection.extend_from_slice(slice::from_raw_parts(prop as *mut c_uchar, nitems as usize));
-            X11_sys::XFree(prop as *mut c_void);
-            if bytes_after == 0 {
-                break;
-            }
-            offset += length;
-        };
-        selection
-    }
-    
     /// Gets the XDndTypeList property from the source window.
     unsafe fn get_type_list_property(&mut self, source_window: X11_sys::Window) -> Vec<X11_sys::Atom> {
         let mut type_list = Vec::new();
@@ -1,17 +1,29 @@
 #[macro_use]
-#[cfg(any(target_os = "linux", target_os="macos", target_os="windows"))]
+#[cfg(any(target_os = "linux", target_os="macos", target_os="windows", feature = "headless"))]
 pub mod cx_desktop;
 
 #[macro_use]
 pub mod cx_shared;
 
 
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 pub mod apple;
 
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", not(feature = "headless")))]
 pub use crate::platform::apple::*;
 
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
+pub mod linux;
+
+#[cfg(all(target_os = "linux", not(feature = "headless")))]
+pub use crate::platform::linux::*;
+
+#[cfg(feature = "headless")]
+pub mod headless;
+
+#[cfg(feature = "headless")]
+pub use crate::platform::headless::*;
+
 #[cfg(target_arch = "wasm32")]
 pub mod web_browser;
 
@@ -0,0 +1,329 @@
+//! A simplified version of the Unicode Bidirectional Algorithm (UAX #9).
+//!
+//! Explicit embeddings, overrides and isolates are not supported, and bidi classes are derived
+//! from a small table of ranges rather than the full Unicode character database. That is enough to
+//! lay out mixed left-to-right and right-to-left text on a single line.
+
+use std::ops::Range;
+
+/// The bidi class of a character.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum BidiClass {
+    /// Left-to-right letter.
+    L,
+    /// Right-to-left letter.
+    R,
+    /// Arabic letter.
+    AL,
+    /// European number.
+    EN,
+    /// European number separator.
+    ES,
+    /// European number terminator.
+    ET,
+    /// Arabic number.
+    AN,
+    /// Common number separator.
+    CS,
+    /// Nonspacing mark.
+    NSM,
+    /// Paragraph separator.
+    B,
+    /// Segment separator.
+    S,
+    /// Whitespace.
+    WS,
+    /// Other neutral.
+    ON,
+}
+
+impl BidiClass {
+    /// Returns the (approximate) bidi class of `c`.
+    pub fn of(c: char) -> BidiClass {
+        use BidiClass::*;
+
+        match c as u32 {
+            0x0A | 0x0D | 0x1C..=0x1E | 0x85 | 0x2029 => B,
+            0x09 | 0x0B | 0x1F => S,
+            0x0C | 0x20 | 0x1680 | 0x2000..=0x200A | 0x2028 | 0x205F | 0x3000 => WS,
+            0x30..=0x39 | 0xB2 | 0xB3 | 0xB9 | 0x6F0..=0x6F9 | 0x2070..=0x2079 | 0x2080..=0x2089 | 0xFF10..=0xFF19 => EN,
+            0x2B | 0x2D | 0x207A | 0x207B | 0x208A | 0x208B | 0x2212 | 0xFF0B | 0xFF0D => ES,
+            0x23..=0x25 | 0xA2..=0xA5 | 0xB0 | 0xB1 | 0x66A | 0x2030..=0x2034 | 0x20A0..=0x20CF | 0xFF03..=0xFF05 => ET,
+            0x2C | 0x2E | 0x2F | 0x3A | 0xA0 | 0x60C | 0x202F | 0x2044 | 0xFF0C | 0xFF0E | 0xFF0F | 0xFF1A => CS,
+            0x600..=0x605 | 0x660..=0x669 | 0x66B | 0x66C | 0x6DD => AN,
+            0x300..=0x36F
+            | 0x483..=0x489
+            | 0x591..=0x5BD
+            | 0x5BF
+            | 0x5C1
+            | 0x5C2
+            | 0x5C4
+            | 0x5C5
+            | 0x5C7
+            | 0x610..=0x61A
+            | 0x64B..=0x65F
+            | 0x670
+            | 0x6D6..=0x6DC
+            | 0x6DF..=0x6E4
+            | 0x6E7
+            | 0x6E8
+            | 0x6EA..=0x6ED
+            | 0x900..=0x902
+            | 0x93A
+            | 0x93C
+            | 0x941..=0x948
+            | 0x94D
+            | 0x951..=0x957
+            | 0x962
+            | 0x963
+            | 0x200B..=0x200D
+            | 0x20D0..=0x20FF
+            | 0xFE00..=0xFE0F
+            | 0xFE20..=0xFE2F => NSM,
+            0x200E => L,
+            0x200F => R,
+            0x61C => AL,
+            0x590..=0x5FF | 0x7C0..=0x85F | 0xFB1D..=0xFB4F | 0x10800..=0x10FFF | 0x1E800..=0x1EFFF => R,
+            0xFD3E | 0xFD3F => ON,
+            0x600..=0x7BF | 0x860..=0x8FF | 0xFB50..=0xFDFF | 0xFE70..=0xFEFF => AL,
+            0x21 | 0x22 | 0x26..=0x2A | 0x3B..=0x40 | 0x5B..=0x60 | 0x7B..=0x7E => ON,
+            0xA1 | 0xA6..=0xA9 | 0xAB | 0xAC | 0xAE | 0xAF | 0xB4 | 0xB6..=0xB8 | 0xBB..=0xBF | 0xD7 | 0xF7 => ON,
+            0x2010..=0x2027 | 0x2035..=0x2043 | 0x2045..=0x205E | 0x2100..=0x2101 | 0x2190..=0x2BFF => ON,
+            0x3001..=0x3004 | 0x3008..=0x3020 | 0xFE10..=0xFE19 | 0xFE30..=0xFE4F => ON,
+            0xFF01 | 0xFF02 | 0xFF06..=0xFF0A | 0xFF1B..=0xFF20 | 0xFF3B..=0xFF40 | 0xFF5B..=0xFF65 => ON,
+            _ => L,
+        }
+    }
+
+    fn is_strong(self) -> bool {
+        matches!(self, BidiClass::L | BidiClass::R | BidiClass::AL)
+    }
+
+    fn is_neutral(self) -> bool {
+        matches!(self, BidiClass::B | BidiClass::S | BidiClass::WS | BidiClass::ON)
+    }
+}
+
+/// A run of characters with the same embedding level. Runs with an odd level are right-to-left.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct BidiRun {
+    pub range: Range<usize>,
+    pub level: u8,
+}
+
+impl BidiRun {
+    pub fn is_rtl(&self) -> bool {
+        self.level % 2 == 1
+    }
+}
+
+/// Splits `chars` into runs with the same embedding level, and returns them in visual order, from
+/// left to right. The characters within a right-to-left run are still in logical order.
+///
+/// The paragraph direction is that of the first strong character.
+pub fn visual_runs(chars: &[char]) -> Vec<BidiRun> {
+    let original_classes: Vec<BidiClass> = chars.iter().map(|&c| BidiClass::of(c)).collect();
+    let paragraph_level = original_classes
+        .iter()
+        .find_map(|class| match class {
+            BidiClass::L => Some(0),
+            BidiClass::R | BidiClass::AL => Some(1),
+            _ => None,
+        })
+        .unwrap_or(0);
+    let mut classes = original_classes.clone();
+    resolve_weak_types(&mut classes, paragraph_level);
+    resolve_neutral_types(&mut classes, paragraph_level);
+    let mut levels: Vec<u8> = classes
+        .iter()
+        .map(|class| match (paragraph_level, class) {
+            (0, BidiClass::R) => 1,
+            (0, BidiClass::AN) | (0, BidiClass::EN) => 2,
+            (1, BidiClass::L) | (1, BidiClass::AN) | (1, BidiClass::EN) => 2,
+            _ => paragraph_level,
+        })
+        .collect();
+
+    // Separators, and whitespace before them or at the end of the line, get the paragraph level.
+    let mut is_trailing = true;
+    for (level, class) in levels.iter_mut().zip(original_classes.iter()).rev() {
+        match class {
+            BidiClass::B | BidiClass::S => {
+                *level = paragraph_level;
+                is_trailing = true;
+            }
+            BidiClass::WS if is_trailing => *level = paragraph_level,
+            _ => is_trailing = false,
+        }
+    }
+
+    let mut runs: Vec<BidiRun> = Vec::new();
+    for (index, &level) in levels.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run) if run.level == level => run.range.end = index + 1,
+            _ => runs.push(BidiRun {
+                range: index..index + 1,
+                level,
+            }),
+        }
+    }
+
+    // From the highest level to the lowest odd level, reverse every sequence of runs that are at
+    // that level or higher.
+    let max_level = runs.iter().map(|run| run.level).max().unwrap_or(0);
+    let min_odd_level = runs
+        .iter()
+        .map(|run| run.level)
+        .filter(|level| level % 2 == 1)
+        .min()
+        .unwrap_or(max_level + 1);
+    for level in (min_odd_level..=max_level).rev() {
+        let mut start = 0;
+        while start < runs.len() {
+            if runs[start].level < level {
+                start += 1;
+                continue;
+            }
+            let mut end = start;
+            while end < runs.len() && runs[end].level >= level {
+                end += 1;
+            }
+            runs[start..end].reverse();
+            start = end;
+        }
+    }
+    runs
+}
+
+/// Returns the mirrored form of `c`, which replaces it in right-to-left runs, if it has one.
+pub fn mirror(c: char) -> Option<char> {
+    Some(match c {
+        '(' => ')',
+        ')' => '(',
+        '<' => '>',
+        '>' => '<',
+        '[' => ']',
+        ']' => '[',
+        '{' => '}',
+        '}' => '{',
+        '«' => '»',
+        '»' => '«',
+        '‹' => '›',
+        '›' => '‹',
+        '⁅' => '⁆',
+        '⁆' => '⁅',
+        '≤' => '≥',
+        '≥' => '≤',
+        '〈' => '〉',
+        '〉' => '〈',
+        '《' => '》',
+        '》' => '《',
+        '「' => '」',
+        '」' => '「',
+        '『' => '』',
+        '』' => '『',
+        '【' => '】',
+        '】' => '【',
+        _ => return None,
+    })
+}
+
+fn resolve_weak_types(classes: &mut [BidiClass], paragraph_level: u8) {
+    use BidiClass::*;
+
+    let sos = if paragraph_level == 0 { L } else { R };
+
+    // Nonspacing marks get the class of the character before them, and European numbers after an
+    // Arabic letter become Arabic numbers, after which Arabic letters are just right-to-left.
+    let mut previous = sos;
+    let mut last_strong = sos;
+    for class in classes.iter_mut() {
+        if *class == NSM {
+            *class = previous;
+        }
+        if *class == EN && last_strong == AL {
+            *class = AN;
+        }
+        if class.is_strong() {
+            last_strong = *class;
+        }
+        previous = *class;
+    }
+    for class in classes.iter_mut() {
+        if *class == AL {
+            *class = R;
+        }
+    }
+
+    // A single separator between two numbers of the same type becomes a number of that type.
+    for index in 1..classes.len().saturating_sub(1) {
+        let (before, after) = (classes[index - 1], classes[index + 1]);
+        classes[index] = match (before, classes[index], after) {
+            (EN, ES, EN) | (EN, CS, EN) => EN,
+            (AN, CS, AN) => AN,
+            (_, class, _) => class,
+        };
+    }
+
+    // Terminators next to European numbers become European numbers.
+    let mut index = 0;
+    while index < classes.len() {
+        if classes[index] != ET {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < classes.len() && classes[index] == ET {
+            index += 1;
+        }
+        if (start > 0 && classes[start - 1] == EN) || classes.get(index) == Some(&EN) {
+            for class in &mut classes[start..index] {
+                *class = EN;
+            }
+        }
+    }
+
+    // The remaining separators and terminators are neutral, and European numbers after a
+    // left-to-right letter are left-to-right.
+    let mut last_strong = sos;
+    for class in classes.iter_mut() {
+        match *class {
+            ES | ET | CS => *class = ON,
+            EN if last_strong == L => *class = L,
+            L | R => last_strong = *class,
+            _ => {}
+        }
+    }
+}
+
+fn resolve_neutral_types(classes: &mut [BidiClass], paragraph_level: u8) {
+    use BidiClass::*;
+
+    let embedding_direction = if paragraph_level == 0 { L } else { R };
+    // Numbers count as right-to-left when resolving neutrals.
+    let direction = |class: BidiClass| match class {
+        L => L,
+        _ => R,
+    };
+    let mut index = 0;
+    while index < classes.len() {
+        if !classes[index].is_neutral() {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < classes.len() && classes[index].is_neutral() {
+            index += 1;
+        }
+        let before = if start == 0 {
+            embedding_direction
+        } else {
+            direction(classes[start - 1])
+        };
+        let after = classes.get(index).map_or(embedding_direction, |&class| direction(class));
+        let resolved = if before == after { before } else { embedding_direction };
+        for class in &mut classes[start..index] {
+            *class = resolved;
+        }
+    }
+}
@@ -0,0 +1,175 @@
+/// A map from char codes to glyph indices.
+///
+/// The map is stored as a sorted list of ranges of consecutive char codes that map to consecutive
+/// glyph indices, so that it can cover the entire Unicode range without taking up much space.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CharCodeToGlyphIndexMap {
+    ranges: Vec<CharCodeRange>,
+    variation_sequences: Vec<VariationSequence>,
+}
+
+impl CharCodeToGlyphIndexMap {
+    /// Creates a new empty map.
+    pub fn new() -> CharCodeToGlyphIndexMap {
+        CharCodeToGlyphIndexMap::default()
+    }
+
+    /// Returns the glyph index for the given char code, if it is mapped.
+    pub fn get(&self, char_code: u32) -> Option<usize> {
+        let index = self.find_range(char_code).ok()?;
+        Some(self.ranges[index].glyph_index(char_code))
+    }
+
+    /// Returns the glyph index for the given char code followed by the given variation selector,
+    /// if the variation sequence is mapped.
+    ///
+    /// Variation sequences that use the default glyph map to the same glyph index as the char
+    /// code itself.
+    pub fn get_variant(&self, char_code: u32, variation_selector: u32) -> Option<usize> {
+        let index = self
+            .variation_sequences
+            .binary_search_by_key(&(char_code, variation_selector), |sequence| {
+                (sequence.char_code, sequence.variation_selector)
+            })
+            .ok()?;
+        match self.variation_sequences[index].glyph_index {
+            Some(glyph_index) => Some(glyph_index),
+            None => self.get(char_code),
+        }
+    }
+
+    /// Maps the given char code to the given glyph index, replacing the previous mapping if it
+    /// exists.
+    ///
+    /// Inserting char codes in increasing order is fast.
+    pub fn insert(&mut self, char_code: u32, glyph_index: usize) {
+        if let Some(last_range) = self.ranges.last_mut() {
+            if char_code > last_range.last_char_code {
+                if char_code == last_range.last_char_code + 1
+                    && glyph_index == last_range.glyph_index(last_range.last_char_code) + 1
+                {
+                    last_range.last_char_code = char_code;
+                } else {
+                    self.ranges.push(CharCodeRange::unit(char_code, glyph_index));
+                }
+                return;
+            }
+        }
+        match self.find_range(char_code) {
+            Ok(index) => {
+                let range = self.ranges[index];
+                if range.glyph_index(char_code) == glyph_index {
+                    return;
+                }
+                let mut new_ranges = Vec::with_capacity(3);
+                if range.first_char_code < char_code {
+                    new_ranges.push(CharCodeRange {
+                        last_char_code: char_code - 1,
+                        ..range
+                    });
+                }
+                new_ranges.push(CharCodeRange::unit(char_code, glyph_index));
+                if char_code < range.last_char_code {
+                    new_ranges.push(CharCodeRange {
+                        first_char_code: char_code + 1,
+                        last_char_code: range.last_char_code,
+                        first_glyph_index: range.glyph_index(char_code + 1),
+                    });
+                }
+                self.ranges.splice(index..index + 1, new_ranges);
+            }
+            Err(index) => self
+                .ranges
+                .insert(index, CharCodeRange::unit(char_code, glyph_index)),
+        }
+    }
+
+    /// Maps the given range of char codes to consecutive glyph indices, starting at
+    /// `first_glyph_index`.
+    pub fn insert_range(&mut self, first_char_code: u32, last_char_code: u32, first_glyph_index: usize) {
+        if first_char_code > last_char_code {
+            return;
+        }
+        if self
+            .ranges
+            .last()
+            .is_none_or(|last_range| first_char_code > last_range.last_char_code)
+        {
+            self.insert(first_char_code, first_glyph_index);
+            self.ranges.last_mut().unwrap().last_char_code = last_char_code;
+            return;
+        }
+        for (offset, char_code) in (first_char_code..=last_char_code).enumerate() {
+            self.insert(char_code, first_glyph_index + offset);
+        }
+    }
+
+    /// Adds a variation sequence. If `glyph_index` is `None`, the variation sequence uses the
+    /// default glyph for the char code.
+    pub fn insert_variant(&mut self, char_code: u32, variation_selector: u32, glyph_index: Option<usize>) {
+        let sequence = VariationSequence {
+            char_code,
+            variation_selector,
+            glyph_index,
+        };
+        match self
+            .variation_sequences
+            .binary_search_by_key(&(char_code, variation_selector), |sequence| {
+                (sequence.char_code, sequence.variation_selector)
+            }) {
+            Ok(index) => self.variation_sequences[index] = sequence,
+            Err(index) => self.variation_sequences.insert(index, sequence),
+        }
+    }
+
+    /// Returns an iterator over the mapped char codes and their glyph indices, in increasing
+    /// order of char code.
+    pub fn iter(&self) -> impl Iterator<Item = (u32, usize)> + '_ {
+        self.ranges.iter().flat_map(|range| {
+            (range.first_char_code..=range.last_char_code)
+                .map(move |char_code| (char_code, range.glyph_index(char_code)))
+        })
+    }
+
+    fn find_range(&self, char_code: u32) -> Result<usize, usize> {
+        self.ranges.binary_search_by(|range| {
+            use std::cmp::Ordering;
+
+            if range.last_char_code < char_code {
+                Ordering::Less
+            } else if range.first_char_code > char_code {
+                Ordering::Greater
+            } else {
+                Ordering::Equal
+            }
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CharCodeRange {
+    first_char_code: u32,
+    last_char_code: u32,
+    first_glyph_index: usize,
+}
+
+impl CharCodeRange {
+    fn unit(char_code: u32, glyph_index: usize) -> CharCodeRange {
+        CharCodeRange {
+            first_char_code: char_code,
+            last_char_code: char_code,
+            first_glyph_index: glyph_index,
+        }
+    }
+
+    fn glyph_index(&self, char_code: u32) -> usize {
+        self.first_glyph_index + (char_code - self.first_char_code) as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct VariationSequence {
+    char_code: u32,
+    variation_selector: u32,
+    glyph_index: Option<usize>,
+}
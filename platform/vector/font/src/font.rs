@@ -1,4 +1,5 @@
-use crate::Glyph;
+use crate::{shaper, CharCodeToGlyphIndexMap, Glyph, KerningTable, Script, ShapedGlyph, SubstitutionTable};
+use std::ops::Range;
 use makepad_geometry::Rectangle;
 
 /// A font.
@@ -9,18 +10,39 @@ pub struct TTFFont {
     pub descender: f32,
     pub line_gap: f32,
     pub bounds: Rectangle,
-    pub char_code_to_glyph_index_map: Vec<usize>,
+    pub char_code_to_glyph_index_map: CharCodeToGlyphIndexMap,
     pub glyphs: Vec<Glyph>,
+    pub kerning_table: KerningTable,
+    pub substitution_table: SubstitutionTable,
 }
 
 
 impl TTFFont{
+    /// Returns the index of the glyph for `c`, or 0 (the `.notdef` glyph) if the font has none.
+    pub fn glyph_index(&self, c: char) -> usize {
+        self.char_code_to_glyph_index_map.get(c as u32).unwrap_or(0)
+    }
+
     pub fn get_glyph(&self, c:char)->Option<&Glyph>{
-        if c < '\u{10000}' {
-            Some(&self.glyphs[self.char_code_to_glyph_index_map[c as usize]])
-        } else {
-            None
-        }
+        self.glyphs.get(self.glyph_index(c))
     }
-}
 
+    /// Returns the horizontal adjustment, in font units, to apply between the glyph with index
+    /// `left` and the glyph with index `right` that follows it.
+    pub fn kerning(&self, left: usize, right: usize) -> f32 {
+        self.kerning_table.get(left, right)
+    }
+
+    /// Shapes the given range of `chars`, which must all be in the same script and direction, and
+    /// appends the resulting glyphs to `glyphs` in logical order.
+    pub fn shape(
+        &self,
+        chars: &[char],
+        range: Range<usize>,
+        script: Script,
+        is_rtl: bool,
+        glyphs: &mut Vec<ShapedGlyph>,
+    ) {
+        shaper::shape(self, chars, range, script, is_rtl, glyphs)
+    }
+}
@@ -0,0 +1,178 @@
+/// The kerning adjustments for pairs of glyphs in a font, in font units.
+///
+/// A kerning table consists of a list of lookups, each of which consists of a list of subtables.
+/// The adjustment for a pair of glyphs is the sum of the adjustments of each lookup, where the
+/// adjustment of a lookup is that of the first subtable that applies to the pair. This is how
+/// lookups in the `GPOS` table work. Each subtable in the `kern` table becomes a lookup of its own.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KerningTable {
+    pub lookups: Vec<KerningLookup>,
+}
+
+impl KerningTable {
+    /// Creates a new empty kerning table.
+    pub fn new() -> KerningTable {
+        KerningTable::default()
+    }
+
+    /// Returns `true` if this kerning table does not contain any adjustments.
+    pub fn is_empty(&self) -> bool {
+        self.lookups.iter().all(|lookup| lookup.subtables.is_empty())
+    }
+
+    /// Returns the horizontal adjustment between the glyph with index `left` and the glyph with
+    /// index `right` that follows it.
+    pub fn get(&self, left: usize, right: usize) -> f32 {
+        self.lookups
+            .iter()
+            .filter_map(|lookup| {
+                lookup
+                    .subtables
+                    .iter()
+                    .find_map(|subtable| subtable.get(left, right))
+            })
+            .sum()
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct KerningLookup {
+    pub subtables: Vec<KerningSubtable>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum KerningSubtable {
+    /// A list of pairs of glyph indices and their adjustments, sorted by glyph indices.
+    Pairs(Vec<KerningPair>),
+    /// Adjustments between classes of glyphs. The subtable only applies if the left glyph is
+    /// covered. Glyphs that are not in a class are in class 0.
+    Classes {
+        coverage: GlyphClassMap,
+        left_classes: GlyphClassMap,
+        right_classes: GlyphClassMap,
+        right_class_count: usize,
+        values: Vec<f32>,
+    },
+}
+
+impl KerningSubtable {
+    /// Creates a subtable from a list of pairs, which do not need to be sorted.
+    pub fn from_pairs(mut pairs: Vec<KerningPair>) -> KerningSubtable {
+        pairs.sort_by_key(|pair| (pair.left, pair.right));
+        KerningSubtable::Pairs(pairs)
+    }
+
+    /// Returns the adjustment for the given pair of glyphs, or `None` if this subtable does not
+    /// apply to it.
+    pub fn get(&self, left: usize, right: usize) -> Option<f32> {
+        match self {
+            KerningSubtable::Pairs(pairs) => {
+                let index = pairs
+                    .binary_search_by_key(&(left, right), |pair| (pair.left, pair.right))
+                    .ok()?;
+                Some(pairs[index].value)
+            }
+            KerningSubtable::Classes {
+                coverage,
+                left_classes,
+                right_classes,
+                right_class_count,
+                values,
+            } => {
+                coverage.get(left)?;
+                let left_class = left_classes.get(left).unwrap_or(0);
+                let right_class = right_classes.get(right).unwrap_or(0);
+                if right_class >= *right_class_count {
+                    return None;
+                }
+                values.get(left_class * right_class_count + right_class).copied()
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KerningPair {
+    pub left: usize,
+    pub right: usize,
+    pub value: f32,
+}
+
+/// A map from glyph indices to classes, stored as a sorted list of ranges of glyph indices that
+/// have the same class.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GlyphClassMap {
+    ranges: Vec<GlyphClassRange>,
+}
+
+impl GlyphClassMap {
+    /// Creates a new empty map.
+    pub fn new() -> GlyphClassMap {
+        GlyphClassMap::default()
+    }
+
+    /// Returns the class of the glyph with the given index, if it has one.
+    pub fn get(&self, glyph_index: usize) -> Option<usize> {
+        let index = self
+            .ranges
+            .binary_search_by(|range| {
+                use std::cmp::Ordering;
+
+                if range.last_glyph_index < glyph_index {
+                    Ordering::Less
+                } else if range.first_glyph_index > glyph_index {
+                    Ordering::Greater
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .ok()?;
+        Some(self.ranges[index].class)
+    }
+
+    /// Returns an iterator over the ranges of glyph indices in this map, as triples of the first
+    /// glyph index, the last glyph index and their class.
+    pub fn ranges(&self) -> impl Iterator<Item = (usize, usize, usize)> + '_ {
+        self.ranges
+            .iter()
+            .map(|range| (range.first_glyph_index, range.last_glyph_index, range.class))
+    }
+
+    /// Assigns the given class to the given range of glyph indices, which must not overlap with
+    /// any range that was inserted before.
+    ///
+    /// Inserting ranges in increasing order is fast.
+    pub fn insert_range(&mut self, first_glyph_index: usize, last_glyph_index: usize, class: usize) {
+        if first_glyph_index > last_glyph_index {
+            return;
+        }
+        let range = GlyphClassRange {
+            first_glyph_index,
+            last_glyph_index,
+            class,
+        };
+        match self.ranges.last_mut() {
+            Some(last_range) if last_range.last_glyph_index < first_glyph_index => {
+                if last_range.last_glyph_index + 1 == first_glyph_index && last_range.class == class {
+                    last_range.last_glyph_index = last_glyph_index;
+                } else {
+                    self.ranges.push(range);
+                }
+            }
+            None => self.ranges.push(range),
+            _ => {
+                let index = self
+                    .ranges
+                    .partition_point(|range| range.last_glyph_index < first_glyph_index);
+                self.ranges.insert(index, range);
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct GlyphClassRange {
+    first_glyph_index: usize,
+    last_glyph_index: usize,
+    class: usize,
+}
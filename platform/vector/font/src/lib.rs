@@ -1,12 +1,24 @@
+pub mod bidi;
 pub mod outline;
 
+mod char_code_to_glyph_index_map;
 mod font;
 mod glyph;
 mod horizontal_metrics;
+mod kerning_table;
 mod outline_point;
+mod shaper;
+mod substitution_table;
 
+pub use self::char_code_to_glyph_index_map::CharCodeToGlyphIndexMap;
 pub use self::font::TTFFont;
 pub use self::glyph::Glyph;
 pub use self::horizontal_metrics::HorizontalMetrics;
+pub use self::kerning_table::{GlyphClassMap, KerningLookup, KerningPair, KerningSubtable, KerningTable};
 pub use self::outline::Outline;
 pub use self::outline_point::OutlinePoint;
+pub use self::shaper::{script_runs, Script, ShapedGlyph};
+pub use self::substitution_table::{
+    ContextRule, ContextSubtable, GlyphMatcher, Ligature, SubstitutionFeature, SubstitutionLookup,
+    SubstitutionScript, SubstitutionSubtable, SubstitutionTable,
+};
@@ -11,8 +11,14 @@ use std::slice::Iter;
 ///
 /// An outline consists of one or more closed contours, each of which consists of one or more
 /// quadratic b-spline curve segments, which are described by a sequence of outline points.
+///
+/// Alternatively, an outline can be cubic, as is the case for fonts with CFF outlines. Each
+/// contour of a cubic outline starts with an on curve point, and consists of line segments, which
+/// are described by a single on curve point, and cubic Bezier curve segments, which are described
+/// by two off curve points followed by an on curve point.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct Outline {
+    is_cubic: bool,
     contour_ends: Vec<usize>,
     points: Vec<OutlinePoint>,
 }
@@ -23,6 +29,19 @@ impl Outline {
         Outline::default()
     }
 
+    /// Creates a new empty cubic outline.
+    pub fn new_cubic() -> Outline {
+        Outline {
+            is_cubic: true,
+            ..Outline::default()
+        }
+    }
+
+    /// Returns `true` if `self` is a cubic outline.
+    pub fn is_cubic(&self) -> bool {
+        self.is_cubic
+    }
+
     /// Returns an iterator over the contours of `self`.
     pub fn contours(&self) -> Contours {
         Contours {
@@ -40,6 +59,7 @@ impl Outline {
     /// Returns an iterator over the path commands that correspond to `self`.
     pub fn commands(&self) -> Commands {
         Commands {
+            is_cubic: self.is_cubic,
             contours: self.contours(),
         }
     }
@@ -130,9 +150,61 @@ impl<'a> Contour<'a> {
 /// Returns an iterator over the path commands that correspond to an outline.
 #[derive(Clone, Debug)]
 pub struct Commands<'a> {
+    is_cubic: bool,
     contours: Contours<'a>,
 }
 
+impl<'a> Commands<'a> {
+    fn for_each_cubic<F>(self, f: &mut F) -> bool
+    where
+        F: FnMut(PathCommand) -> bool,
+    {
+        for contour in self.contours {
+            let mut points = contour.points().iter();
+            let first_point = match points.next() {
+                Some(point) => point.point,
+                None => continue,
+            };
+            if !f(PathCommand::MoveTo(first_point)) {
+                return false;
+            }
+            // The off curve points we encountered since the last on curve point.
+            let mut off_curve_points: [Point; 2] = [Point::origin(); 2];
+            let mut off_curve_point_count = 0;
+            for point in points {
+                if !point.is_on_curve {
+                    if off_curve_point_count < 2 {
+                        off_curve_points[off_curve_point_count] = point.point;
+                    }
+                    off_curve_point_count += 1;
+                    continue;
+                }
+                let command = match off_curve_point_count {
+                    0 => PathCommand::LineTo(point.point),
+                    1 => PathCommand::QuadraticTo(off_curve_points[0], point.point),
+                    _ => PathCommand::CubicTo(off_curve_points[0], off_curve_points[1], point.point),
+                };
+                if !f(command) {
+                    return false;
+                }
+                off_curve_point_count = 0;
+            }
+            let command = match off_curve_point_count {
+                0 => PathCommand::LineTo(first_point),
+                1 => PathCommand::QuadraticTo(off_curve_points[0], first_point),
+                _ => PathCommand::CubicTo(off_curve_points[0], off_curve_points[1], first_point),
+            };
+            if !f(command) {
+                return false;
+            }
+            if !f(PathCommand::Close) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
 impl<'a> InternalIterator for Commands<'a> {
     type Item = PathCommand;
 
@@ -140,6 +212,9 @@ impl<'a> InternalIterator for Commands<'a> {
     where
         F: FnMut(PathCommand) -> bool,
     {
+        if self.is_cubic {
+            return self.for_each_cubic(f);
+        }
         // To convert a sequence of quadratic b-spline curve segments to a sequence of quadratic
         // Bezier curve segments, we need to insert a new endpoint at the midpoint of each pair
         // of adjacent off curve points.
@@ -0,0 +1,330 @@
+use crate::{bidi, TTFFont};
+use std::ops::Range;
+
+/// A glyph produced by shaping a run of characters.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ShapedGlyph {
+    pub glyph_index: usize,
+    /// The index of the first character that this glyph was produced from.
+    pub char_index: usize,
+    /// A mask that determines which of the shaper's features apply to this glyph.
+    pub features: u32,
+}
+
+/// The scripts that the shaper distinguishes between.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum Script {
+    /// Characters such as spaces, punctuation and marks, which take the script of the characters
+    /// around them, and scripts that need no special treatment.
+    Common,
+    Latin,
+    Greek,
+    Cyrillic,
+    Hebrew,
+    Arabic,
+    Devanagari,
+}
+
+impl Script {
+    /// Returns the (approximate) script of `c`.
+    pub fn of(c: char) -> Script {
+        match c as u32 {
+            0x41..=0x5A | 0x61..=0x7A | 0xAA | 0xBA | 0xC0..=0xD6 | 0xD8..=0xF6 | 0xF8..=0x24F => Script::Latin,
+            0x1E00..=0x1EFF | 0x2C60..=0x2C7F | 0xA720..=0xA7FF | 0xFB00..=0xFB06 => Script::Latin,
+            0x370..=0x3FF | 0x1F00..=0x1FFF => Script::Greek,
+            0x400..=0x52F | 0x1C80..=0x1C8F | 0x2DE0..=0x2DFF | 0xA640..=0xA69F => Script::Cyrillic,
+            0x591..=0x5FF | 0xFB1D..=0xFB4F => Script::Hebrew,
+            0x600..=0x604 | 0x606..=0x60B | 0x60D..=0x61A | 0x61C..=0x61E | 0x620..=0x63F | 0x641..=0x64A => Script::Arabic,
+            0x656..=0x66F | 0x671..=0x6DC | 0x6DE..=0x6FF | 0x750..=0x77F | 0x8A0..=0x8FF => Script::Arabic,
+            0xFB50..=0xFDFF | 0xFE70..=0xFEFF => Script::Arabic,
+            0x900..=0x950 | 0x953..=0x963 | 0x966..=0x97F | 0xA8E0..=0xA8FF => Script::Devanagari,
+            _ => Script::Common,
+        }
+    }
+
+    /// Returns the `GSUB` script tags for this script, in order of preference.
+    pub fn tags(self) -> &'static [[u8; 4]] {
+        const LATN: [u8; 4] = *b"latn";
+        const GREK: [u8; 4] = *b"grek";
+        const CYRL: [u8; 4] = *b"cyrl";
+        const HEBR: [u8; 4] = *b"hebr";
+        const ARAB: [u8; 4] = *b"arab";
+        const DEV2: [u8; 4] = *b"dev2";
+        const DEVA: [u8; 4] = *b"deva";
+
+        match self {
+            Script::Common => &[],
+            Script::Latin => &[LATN],
+            Script::Greek => &[GREK],
+            Script::Cyrillic => &[CYRL],
+            Script::Hebrew => &[HEBR],
+            Script::Arabic => &[ARAB],
+            Script::Devanagari => &[DEV2, DEVA],
+        }
+    }
+}
+
+/// Splits the given range of `chars` into runs of the same script, where characters of the common
+/// script take the script of the character before them, or after them at the start of the range.
+pub fn script_runs(chars: &[char], range: Range<usize>) -> Vec<(Range<usize>, Script)> {
+    let mut runs: Vec<(Range<usize>, Script)> = Vec::new();
+    for index in range {
+        let script = Script::of(chars[index]);
+        match runs.last_mut() {
+            Some((run, run_script)) if script == Script::Common || script == *run_script => {
+                run.end = index + 1;
+            }
+            Some((run, run_script)) if *run_script == Script::Common => {
+                run.end = index + 1;
+                *run_script = script;
+            }
+            _ => runs.push((index..index + 1, script)),
+        }
+    }
+    runs
+}
+
+/// The features that the shaper applies, in the order of the bits in the `features` mask of a
+/// `ShapedGlyph`. The features in `CONDITIONAL_FEATURES` only apply to the glyphs that the shaper
+/// selects for them, the others apply to every glyph.
+const FEATURE_TAGS: [[u8; 4]; 24] = [
+    *b"ccmp", *b"locl", *b"nukt", *b"akhn", *b"rphf", *b"rkrf", *b"blwf", *b"half",
+    *b"pstf", *b"vatu", *b"cjct", *b"isol", *b"fina", *b"medi", *b"init", *b"pres",
+    *b"abvs", *b"blws", *b"psts", *b"haln", *b"rlig", *b"calt", *b"liga", *b"clig",
+];
+
+const RPHF: u32 = 1 << 4;
+const HALF: u32 = 1 << 7;
+const ISOL: u32 = 1 << 11;
+const FINA: u32 = 1 << 12;
+const MEDI: u32 = 1 << 13;
+const INIT: u32 = 1 << 14;
+const CONDITIONAL_FEATURES: u32 = RPHF | HALF | ISOL | FINA | MEDI | INIT;
+
+/// Shapes the given range of `chars`, which must all be in the same script and direction, with
+/// `font`, and appends the resulting glyphs to `glyphs` in logical order.
+///
+/// Characters in a right-to-left run are replaced by their mirrored forms. If the font has no
+/// substitutions, every character becomes the glyph that the font maps it to.
+pub fn shape(
+    font: &TTFFont,
+    chars: &[char],
+    range: Range<usize>,
+    script: Script,
+    is_rtl: bool,
+    glyphs: &mut Vec<ShapedGlyph>,
+) {
+    let mut run: Vec<ShapedGlyph> = range
+        .map(|char_index| {
+            let mut c = chars[char_index];
+            if is_rtl {
+                c = bidi::mirror(c).unwrap_or(c);
+            }
+            ShapedGlyph {
+                glyph_index: font.glyph_index(c),
+                char_index,
+                features: !CONDITIONAL_FEATURES,
+            }
+        })
+        .collect();
+    if !font.substitution_table.is_empty() {
+        match script {
+            Script::Arabic => select_arabic_forms(chars, &mut run),
+            Script::Devanagari => reorder_devanagari(chars, &mut run),
+            _ => {}
+        }
+        font.substitution_table.apply(script.tags(), &FEATURE_TAGS, &mut run);
+    }
+    glyphs.extend(run);
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum JoiningType {
+    NonJoining,
+    Transparent,
+    RightJoining,
+    DualJoining,
+    JoinCausing,
+}
+
+impl JoiningType {
+    fn of(c: char) -> JoiningType {
+        use JoiningType::*;
+
+        match c as u32 {
+            0x300..=0x36F | 0x610..=0x61A | 0x64B..=0x65F | 0x670 | 0x6D6..=0x6DC | 0x6DF..=0x6E4 => Transparent,
+            0x6E7 | 0x6E8 | 0x6EA..=0x6ED | 0x8D3..=0x8E1 | 0x8E3..=0x8FF => Transparent,
+            0x640 | 0x200D => JoinCausing,
+            0x622..=0x625 | 0x627 | 0x629 | 0x62F..=0x632 | 0x648 | 0x671..=0x673 | 0x675..=0x677 => RightJoining,
+            0x688..=0x699 | 0x6C0 | 0x6C3..=0x6CB | 0x6CD | 0x6CF | 0x6D2 | 0x6D3 | 0x6D5 | 0x6EE | 0x6EF => RightJoining,
+            0x759..=0x75B | 0x76B | 0x76C | 0x771 | 0x773 | 0x774 | 0x778 | 0x779 => RightJoining,
+            0x620 | 0x626 | 0x628 | 0x62A..=0x62E | 0x633..=0x63F | 0x641..=0x647 | 0x649 | 0x64A => DualJoining,
+            0x66E | 0x66F | 0x678..=0x687 | 0x69A..=0x6BF | 0x6C1 | 0x6C2 | 0x6CC | 0x6CE | 0x6D0 | 0x6D1 => DualJoining,
+            0x6FA..=0x6FC | 0x6FF | 0x750..=0x77F | 0x8A0..=0x8C7 => DualJoining,
+            _ => NonJoining,
+        }
+    }
+
+    fn joins_to_next(self) -> bool {
+        matches!(self, JoiningType::DualJoining | JoiningType::JoinCausing)
+    }
+
+    fn joins_to_previous(self) -> bool {
+        matches!(
+            self,
+            JoiningType::RightJoining | JoiningType::DualJoining | JoiningType::JoinCausing
+        )
+    }
+}
+
+// Selects the isolated, final, medial or initial form of every Arabic letter, depending on which
+// of the letters around it, skipping marks, it joins with.
+fn select_arabic_forms(chars: &[char], glyphs: &mut [ShapedGlyph]) {
+    let joining_types: Vec<JoiningType> = glyphs
+        .iter()
+        .map(|glyph| JoiningType::of(chars[glyph.char_index]))
+        .collect();
+    let mut previous: Option<JoiningType> = None;
+    for index in 0..glyphs.len() {
+        let joining_type = joining_types[index];
+        if joining_type == JoiningType::Transparent {
+            continue;
+        }
+        let next = joining_types[index + 1..]
+            .iter()
+            .copied()
+            .find(|&joining_type| joining_type != JoiningType::Transparent);
+        let joins_previous = joining_type.joins_to_previous() && previous.is_some_and(JoiningType::joins_to_next);
+        let joins_next = joining_type.joins_to_next() && next.is_some_and(JoiningType::joins_to_previous);
+        if matches!(joining_type, JoiningType::RightJoining | JoiningType::DualJoining) {
+            glyphs[index].features |= match (joins_previous, joins_next) {
+                (true, true) => MEDI,
+                (true, false) => FINA,
+                (false, true) => INIT,
+                (false, false) => ISOL,
+            };
+        }
+        previous = Some(joining_type);
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum IndicCategory {
+    Other,
+    Consonant,
+    Ra,
+    Vowel,
+    Nukta,
+    Halant,
+    PreBaseMatra,
+    Matra,
+    Modifier,
+    Joiner,
+}
+
+impl IndicCategory {
+    fn of(c: char) -> IndicCategory {
+        use IndicCategory::*;
+
+        match c as u32 {
+            0x930 => Ra,
+            0x915..=0x939 | 0x958..=0x95F | 0x978..=0x97F => Consonant,
+            0x904..=0x914 | 0x960 | 0x961 | 0x972..=0x977 => Vowel,
+            0x93C => Nukta,
+            0x94D => Halant,
+            0x93F => PreBaseMatra,
+            0x93A | 0x93B | 0x93E | 0x940..=0x94C | 0x94E | 0x94F | 0x955..=0x957 | 0x962 | 0x963 => Matra,
+            0x900..=0x903 => Modifier,
+            0x200C | 0x200D => Joiner,
+            _ => Other,
+        }
+    }
+
+    fn is_consonant(self) -> bool {
+        matches!(self, IndicCategory::Consonant | IndicCategory::Ra)
+    }
+}
+
+// Splits the glyphs into syllables, selects the glyphs that form a reph or half forms, and moves
+// reph to the end of its syllable and pre-base matras to the start of theirs. This is a subset of
+// what a full Indic shaper does, but it covers the common cases of Devanagari text.
+fn reorder_devanagari(chars: &[char], glyphs: &mut [ShapedGlyph]) {
+    let category = |glyph: &ShapedGlyph| IndicCategory::of(chars[glyph.char_index]);
+    let mut start = 0;
+    while start < glyphs.len() {
+        let first = category(&glyphs[start]);
+        if !first.is_consonant() && first != IndicCategory::Vowel {
+            start += 1;
+            continue;
+        }
+
+        // The consonant cluster: consonants with their nukta, joined by halants.
+        let mut end = start + 1;
+        let mut cluster_end = end;
+        if first.is_consonant() {
+            loop {
+                if end < glyphs.len() && category(&glyphs[end]) == IndicCategory::Nukta {
+                    end += 1;
+                }
+                cluster_end = end;
+                if end < glyphs.len() && category(&glyphs[end]) == IndicCategory::Halant {
+                    end += 1;
+                    if end < glyphs.len() && category(&glyphs[end]) == IndicCategory::Joiner {
+                        end += 1;
+                    }
+                    if end < glyphs.len() && category(&glyphs[end]).is_consonant() {
+                        end += 1;
+                        continue;
+                    }
+                    cluster_end = end;
+                }
+                break;
+            }
+        }
+        // The matras and modifiers that follow it.
+        while end < glyphs.len()
+            && matches!(
+                category(&glyphs[end]),
+                IndicCategory::Nukta
+                    | IndicCategory::Halant
+                    | IndicCategory::PreBaseMatra
+                    | IndicCategory::Matra
+                    | IndicCategory::Modifier
+            )
+        {
+            end += 1;
+        }
+
+        let has_reph = cluster_end - start > 2
+            && category(&glyphs[start]) == IndicCategory::Ra
+            && category(&glyphs[start + 1]) == IndicCategory::Halant
+            && category(&glyphs[start + 2]).is_consonant();
+        let cluster_start = if has_reph { start + 2 } else { start };
+        for index in cluster_start..cluster_end.saturating_sub(2) {
+            if category(&glyphs[index]).is_consonant()
+                && category(&glyphs[index + 1]) == IndicCategory::Halant
+                && category(&glyphs[index + 2]).is_consonant()
+            {
+                glyphs[index].features |= HALF;
+                glyphs[index + 1].features |= HALF;
+            }
+        }
+        if let Some(offset) = glyphs[cluster_end..end]
+            .iter()
+            .position(|glyph| category(glyph) == IndicCategory::PreBaseMatra)
+        {
+            glyphs[cluster_start..cluster_end + offset + 1].rotate_right(1);
+        }
+        if has_reph {
+            glyphs[start].features |= RPHF;
+            glyphs[start + 1].features |= RPHF;
+            let modifier_count = glyphs[start..end]
+                .iter()
+                .rev()
+                .take_while(|glyph| category(glyph) == IndicCategory::Modifier)
+                .count();
+            glyphs[start..end - modifier_count].rotate_left(2);
+        }
+        start = end;
+    }
+}
@@ -0,0 +1,349 @@
+use crate::{GlyphClassMap, ShapedGlyph};
+
+/// The maximum depth of nested lookups that contextual substitutions may apply.
+const MAX_NESTING_DEPTH: usize = 8;
+
+/// The glyph substitutions in a font, as described by its `GSUB` table.
+///
+/// A substitution table consists of a list of scripts, each of which enables a list of features,
+/// each of which consists of a list of lookups. Shaping a run of glyphs applies the lookups of the
+/// enabled features in the order in which they appear in the lookup list, not in the order of the
+/// features.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubstitutionTable {
+    pub scripts: Vec<SubstitutionScript>,
+    pub features: Vec<SubstitutionFeature>,
+    pub lookups: Vec<SubstitutionLookup>,
+    /// The glyph classes from the `GDEF` table, which lookups use to skip glyphs: 1 for base
+    /// glyphs, 2 for ligatures, 3 for marks and 4 for components.
+    pub glyph_classes: GlyphClassMap,
+}
+
+impl SubstitutionTable {
+    /// Creates a new empty substitution table.
+    pub fn new() -> SubstitutionTable {
+        SubstitutionTable::default()
+    }
+
+    /// Returns `true` if this substitution table does not contain any lookups.
+    pub fn is_empty(&self) -> bool {
+        self.lookups.iter().all(|lookup| lookup.subtables.is_empty())
+    }
+
+    /// Applies the features with the given tags to `glyphs`, using the first script in
+    /// `script_tags` that the table has. If it has none of them, the default script is used.
+    ///
+    /// Bit `i` of the `features` mask of a glyph determines whether the feature with tag
+    /// `feature_tags[i]` applies to it.
+    pub fn apply(
+        &self,
+        script_tags: &[[u8; 4]],
+        feature_tags: &[[u8; 4]],
+        glyphs: &mut Vec<ShapedGlyph>,
+    ) {
+        let script = match script_tags
+            .iter()
+            .chain([*b"DFLT", *b"dflt", *b"latn"].iter())
+            .find_map(|tag| self.scripts.iter().find(|script| script.tag == *tag))
+        {
+            Some(script) => script,
+            None => return,
+        };
+        let mut lookup_masks = Vec::new();
+        for &feature_index in &script.feature_indices {
+            let feature = match self.features.get(feature_index) {
+                Some(feature) => feature,
+                None => continue,
+            };
+            if let Some(bit) = feature_tags.iter().position(|tag| *tag == feature.tag) {
+                for &lookup_index in &feature.lookup_indices {
+                    lookup_masks.push((lookup_index, 1u32 << bit));
+                }
+            }
+        }
+        lookup_masks.sort_unstable_by_key(|&(lookup_index, _)| lookup_index);
+        let mut index = 0;
+        while index < lookup_masks.len() {
+            let (lookup_index, mut mask) = lookup_masks[index];
+            index += 1;
+            while index < lookup_masks.len() && lookup_masks[index].0 == lookup_index {
+                mask |= lookup_masks[index].1;
+                index += 1;
+            }
+            if let Some(lookup) = self.lookups.get(lookup_index) {
+                self.apply_lookup(lookup, mask, glyphs);
+            }
+        }
+    }
+
+    fn apply_lookup(&self, lookup: &SubstitutionLookup, mask: u32, glyphs: &mut Vec<ShapedGlyph>) {
+        let mut index = 0;
+        while index < glyphs.len() {
+            if glyphs[index].features & mask != 0 && !self.is_ignored(lookup, glyphs[index].glyph_index) {
+                if let Some(next_index) = self.apply_lookup_at(lookup, glyphs, index, 0) {
+                    index = next_index;
+                    continue;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    // Applies the first subtable of `lookup` that applies to the glyph at `index`, and returns the
+    // index of the glyph after the ones it consumed, or `None` if no subtable applies.
+    fn apply_lookup_at(
+        &self,
+        lookup: &SubstitutionLookup,
+        glyphs: &mut Vec<ShapedGlyph>,
+        index: usize,
+        depth: usize,
+    ) -> Option<usize> {
+        let glyph_index = glyphs.get(index)?.glyph_index;
+        for subtable in &lookup.subtables {
+            match subtable {
+                SubstitutionSubtable::Single(substitutions) => {
+                    if let Ok(position) = substitutions.binary_search_by_key(&glyph_index, |&(from, _)| from) {
+                        glyphs[index].glyph_index = substitutions[position].1;
+                        return Some(index + 1);
+                    }
+                }
+                SubstitutionSubtable::Ligature(ligatures) => {
+                    let start = ligatures.partition_point(|ligature| ligature.first < glyph_index);
+                    for ligature in ligatures[start..]
+                        .iter()
+                        .take_while(|ligature| ligature.first == glyph_index)
+                    {
+                        if let Some(positions) = self.match_forward(lookup, glyphs, index, ligature.components.len(), |k, glyph_index| {
+                            ligature.components[k] == glyph_index
+                        }) {
+                            glyphs[index].glyph_index = ligature.glyph_index;
+                            for position in positions.into_iter().rev() {
+                                glyphs.remove(position);
+                            }
+                            return Some(index + 1);
+                        }
+                    }
+                }
+                SubstitutionSubtable::Context(context) => {
+                    if let Some(next_index) = self.apply_context_at(lookup, context, glyphs, index, depth) {
+                        return Some(next_index);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn apply_context_at(
+        &self,
+        lookup: &SubstitutionLookup,
+        context: &ContextSubtable,
+        glyphs: &mut Vec<ShapedGlyph>,
+        index: usize,
+        depth: usize,
+    ) -> Option<usize> {
+        let glyph_index = glyphs[index].glyph_index;
+        let coverage_index = context.coverage.get(glyph_index)?;
+        let rule_set_index = if context.rule_sets_by_class {
+            context.input_classes.get(glyph_index).unwrap_or(0)
+        } else {
+            coverage_index
+        };
+        for rule in context.rule_sets.get(rule_set_index)? {
+            let input_positions = match self.match_forward(lookup, glyphs, index, rule.input.len(), |k, glyph_index| {
+                rule.input[k].matches(&context.input_classes, glyph_index)
+            }) {
+                Some(input_positions) => input_positions,
+                None => continue,
+            };
+            if !self.match_backward(lookup, glyphs, index, &rule.backtrack, &context.backtrack_classes) {
+                continue;
+            }
+            let last = input_positions.last().copied().unwrap_or(index);
+            if self
+                .match_forward(lookup, glyphs, last, rule.lookahead.len(), |k, glyph_index| {
+                    rule.lookahead[k].matches(&context.lookahead_classes, glyph_index)
+                })
+                .is_none()
+            {
+                continue;
+            }
+            let mut positions = vec![index];
+            positions.extend(input_positions);
+            let mut end = last + 1;
+            for &(sequence_index, lookup_index) in &rule.lookups {
+                if depth >= MAX_NESTING_DEPTH {
+                    break;
+                }
+                let (position, nested_lookup) = match (positions.get(sequence_index), self.lookups.get(lookup_index)) {
+                    (Some(&position), Some(nested_lookup)) => (position, nested_lookup),
+                    _ => continue,
+                };
+                let len = glyphs.len();
+                self.apply_lookup_at(nested_lookup, glyphs, position, depth + 1);
+                // A nested ligature substitution removes the glyphs it consumed, so the positions
+                // of the glyphs after it move.
+                let removed = len - glyphs.len();
+                if removed > 0 {
+                    for later_position in positions.iter_mut().filter(|later_position| **later_position > position) {
+                        *later_position = (*later_position - removed).max(position);
+                    }
+                    end -= removed;
+                }
+            }
+            return Some(end);
+        }
+        None
+    }
+
+    // Matches the `count` glyphs after the one at `index` that are not ignored by `lookup`, and
+    // returns their positions.
+    fn match_forward<F>(
+        &self,
+        lookup: &SubstitutionLookup,
+        glyphs: &[ShapedGlyph],
+        index: usize,
+        count: usize,
+        mut f: F,
+    ) -> Option<Vec<usize>>
+    where
+        F: FnMut(usize, usize) -> bool,
+    {
+        let mut positions = Vec::with_capacity(count);
+        let mut position = index + 1;
+        while positions.len() < count {
+            let glyph_index = glyphs.get(position)?.glyph_index;
+            if !self.is_ignored(lookup, glyph_index) {
+                if !f(positions.len(), glyph_index) {
+                    return None;
+                }
+                positions.push(position);
+            }
+            position += 1;
+        }
+        Some(positions)
+    }
+
+    // Matches the glyphs before the one at `index` that are not ignored by `lookup`, closest first.
+    fn match_backward(
+        &self,
+        lookup: &SubstitutionLookup,
+        glyphs: &[ShapedGlyph],
+        index: usize,
+        backtrack: &[GlyphMatcher],
+        classes: &GlyphClassMap,
+    ) -> bool {
+        let mut position = index;
+        for matcher in backtrack {
+            loop {
+                if position == 0 {
+                    return false;
+                }
+                position -= 1;
+                if !self.is_ignored(lookup, glyphs[position].glyph_index) {
+                    break;
+                }
+            }
+            if !matcher.matches(classes, glyphs[position].glyph_index) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn is_ignored(&self, lookup: &SubstitutionLookup, glyph_index: usize) -> bool {
+        if lookup.flags & 0xE == 0 {
+            return false;
+        }
+        match self.glyph_classes.get(glyph_index) {
+            Some(1) => lookup.flags & 0x2 != 0,
+            Some(2) => lookup.flags & 0x4 != 0,
+            Some(3) => lookup.flags & 0x8 != 0,
+            _ => false,
+        }
+    }
+}
+
+/// The features that a script enables in its default language system.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubstitutionScript {
+    pub tag: [u8; 4],
+    pub feature_indices: Vec<usize>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SubstitutionFeature {
+    pub tag: [u8; 4],
+    pub lookup_indices: Vec<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubstitutionLookup {
+    pub flags: u16,
+    pub subtables: Vec<SubstitutionSubtable>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SubstitutionSubtable {
+    /// Pairs of glyph indices and the glyph indices that replace them, sorted by the former.
+    Single(Vec<(usize, usize)>),
+    /// Ligatures, sorted by their first component. Ligatures with the same first component are in
+    /// order of preference.
+    Ligature(Vec<Ligature>),
+    /// Rules that apply other lookups to a sequence of glyphs if it appears in a given context.
+    Context(ContextSubtable),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ligature {
+    pub first: usize,
+    /// The components after the first one.
+    pub components: Vec<usize>,
+    pub glyph_index: usize,
+}
+
+/// A contextual or chained contextual substitution subtable. Contextual subtables are chained
+/// contextual subtables without backtrack and lookahead.
+///
+/// The subtable only applies if the first glyph of the input sequence is covered. Its rules are
+/// grouped into rule sets, which are indexed by the class of the first glyph if
+/// `rule_sets_by_class` is set, or by its coverage index otherwise.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContextSubtable {
+    pub coverage: GlyphClassMap,
+    pub backtrack_classes: GlyphClassMap,
+    pub input_classes: GlyphClassMap,
+    pub lookahead_classes: GlyphClassMap,
+    pub rule_sets_by_class: bool,
+    pub rule_sets: Vec<Vec<ContextRule>>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ContextRule {
+    /// The glyphs before the input sequence, closest first.
+    pub backtrack: Vec<GlyphMatcher>,
+    /// The glyphs of the input sequence after the first one.
+    pub input: Vec<GlyphMatcher>,
+    pub lookahead: Vec<GlyphMatcher>,
+    /// Pairs of an index into the input sequence and the index of the lookup to apply there.
+    pub lookups: Vec<(usize, usize)>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GlyphMatcher {
+    Glyph(usize),
+    /// A class in the class map for the part of the context the matcher is in. Glyphs that are not
+    /// in a class are in class 0.
+    Class(usize),
+    Coverage(GlyphClassMap),
+}
+
+impl GlyphMatcher {
+    fn matches(&self, classes: &GlyphClassMap, glyph_index: usize) -> bool {
+        match self {
+            GlyphMatcher::Glyph(other_glyph_index) => *other_glyph_index == glyph_index,
+            GlyphMatcher::Class(class) => classes.get(glyph_index).unwrap_or(0) == *class,
+            GlyphMatcher::Coverage(coverage) => coverage.get(glyph_index).is_some(),
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use makepad_font::bidi::{self, BidiRun};
+use makepad_font::{
+    CharCodeToGlyphIndexMap, ContextRule, ContextSubtable, GlyphClassMap, GlyphMatcher,
+    KerningTable, Ligature, Script, SubstitutionFeature, SubstitutionLookup, SubstitutionScript,
+    SubstitutionSubtable, SubstitutionTable, TTFFont,
+};
+use makepad_geometry::Rectangle;
+
+fn runs(text: &str) -> Vec<(usize, usize, u8)> {
+    let chars: Vec<char> = text.chars().collect();
+    bidi::visual_runs(&chars)
+        .into_iter()
+        .map(|BidiRun { range, level }| (range.start, range.end, level))
+        .collect()
+}
+
+#[test]
+fn bidi() {
+    assert_eq!(runs("abc def"), vec![(0, 7, 0)]);
+    assert_eq!(runs("abc אבג def"), vec![(0, 4, 0), (4, 7, 1), (7, 11, 0)]);
+    // The whitespace between two right-to-left words stays right-to-left.
+    assert_eq!(runs("abc אבג דהו."), vec![(0, 4, 0), (4, 11, 1), (11, 12, 0)]);
+    // In a right-to-left paragraph, left-to-right text is one level up and runs are reversed.
+    assert_eq!(runs("אבג abc 123"), vec![(4, 11, 2), (0, 4, 1)]);
+    // Numbers after an Arabic letter are Arabic numbers, which are left-to-right within it.
+    assert_eq!(runs("بب 12 بب"), vec![(5, 8, 1), (3, 5, 2), (0, 3, 1)]);
+    // Trailing whitespace gets the paragraph level.
+    assert_eq!(runs("abc אבג "), vec![(0, 4, 0), (4, 7, 1), (7, 8, 0)]);
+    assert_eq!(bidi::mirror('('), Some(')'));
+    assert_eq!(bidi::mirror('a'), None);
+}
+
+// Returns a font in which the characters in `text` map to glyphs 1, 2, 3 and so on, and that has
+// the given substitution lookups, each of which is the only lookup of a feature of its own.
+fn font(text: &str, lookups: Vec<([u8; 4], SubstitutionSubtable)>) -> TTFFont {
+    let mut char_code_to_glyph_index_map = CharCodeToGlyphIndexMap::new();
+    for (index, c) in text.chars().enumerate() {
+        char_code_to_glyph_index_map.insert(c as u32, index + 1);
+    }
+    let mut substitution_table = SubstitutionTable::new();
+    for (index, (tag, subtable)) in lookups.into_iter().enumerate() {
+        substitution_table.features.push(SubstitutionFeature {
+            tag,
+            lookup_indices: vec![index],
+        });
+        substitution_table.lookups.push(SubstitutionLookup {
+            flags: 0,
+            subtables: vec![subtable],
+        });
+    }
+    substitution_table.scripts.push(SubstitutionScript {
+        tag: *b"DFLT",
+        feature_indices: (0..substitution_table.features.len()).collect(),
+    });
+    TTFFont {
+        units_per_em: 1000.0,
+        ascender: 800.0,
+        descender: -200.0,
+        line_gap: 0.0,
+        bounds: Rectangle::default(),
+        char_code_to_glyph_index_map,
+        glyphs: Vec::new(),
+        kerning_table: KerningTable::new(),
+        substitution_table,
+    }
+}
+
+fn shape(font: &TTFFont, text: &str, script: Script) -> Vec<usize> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut glyphs = Vec::new();
+    font.shape(&chars, 0..chars.len(), script, false, &mut glyphs);
+    glyphs.iter().map(|glyph| glyph.glyph_index).collect()
+}
+
+#[test]
+fn arabic_forms() {
+    // Beh joins on both sides, alef only to the letter before it.
+    let font = font(
+        "با",
+        vec![
+            (*b"isol", SubstitutionSubtable::Single(vec![(1, 11), (2, 21)])),
+            (*b"fina", SubstitutionSubtable::Single(vec![(1, 12), (2, 22)])),
+            (*b"medi", SubstitutionSubtable::Single(vec![(1, 13)])),
+            (*b"init", SubstitutionSubtable::Single(vec![(1, 14)])),
+        ],
+    );
+    assert_eq!(shape(&font, "ببب", Script::Arabic), vec![14, 13, 12]);
+    assert_eq!(shape(&font, "بابب", Script::Arabic), vec![14, 22, 14, 12]);
+    assert_eq!(shape(&font, "اب", Script::Arabic), vec![21, 11]);
+    // Marks do not break joining.
+    assert_eq!(shape(&font, "ب\u{64E}ب", Script::Arabic), vec![14, 0, 12]);
+}
+
+#[test]
+fn ligatures_and_contexts() {
+    let font = font(
+        "=>ab",
+        vec![
+            (
+                *b"calt",
+                SubstitutionSubtable::Context(ContextSubtable {
+                    coverage: coverage(&[3]),
+                    rule_sets: vec![vec![ContextRule {
+                        lookahead: vec![GlyphMatcher::Glyph(4)],
+                        lookups: vec![(0, 2)],
+                        ..ContextRule::default()
+                    }]],
+                    ..ContextSubtable::default()
+                }),
+            ),
+            (
+                *b"liga",
+                SubstitutionSubtable::Ligature(vec![Ligature {
+                    first: 1,
+                    components: vec![2],
+                    glyph_index: 30,
+                }]),
+            ),
+            (*b"xxxx", SubstitutionSubtable::Single(vec![(3, 40)])),
+        ],
+    );
+    assert_eq!(shape(&font, "a=>b", Script::Common), vec![3, 30, 4]);
+    assert_eq!(shape(&font, "ab=", Script::Common), vec![40, 4, 1]);
+    assert_eq!(shape(&font, "aab", Script::Common), vec![3, 40, 4]);
+}
+
+fn coverage(glyph_indices: &[usize]) -> GlyphClassMap {
+    let mut coverage = GlyphClassMap::new();
+    for (coverage_index, &glyph_index) in glyph_indices.iter().enumerate() {
+        coverage.insert_range(glyph_index, glyph_index, coverage_index);
+    }
+    coverage
+}
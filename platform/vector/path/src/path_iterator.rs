@@ -1,5 +1,5 @@
 use crate::{LinePathCommand, PathCommand};
-use makepad_geometry::QuadraticSegment;
+use makepad_geometry::{CubicSegment, QuadraticSegment};
 use makepad_internal_iter::InternalIterator;
 
 /// An extension trait for iterators over path commands.
@@ -58,6 +58,14 @@ where
                             f(LinePathCommand::LineTo(p))
                         })
                 }
+                PathCommand::CubicTo(p1, p2, p) => {
+                    CubicSegment::new(current_point.unwrap(), p1, p2, p)
+                        .linearize(epsilon)
+                        .for_each(&mut |p| {
+                            current_point = Some(p);
+                            f(LinePathCommand::LineTo(p))
+                        })
+                }
                 PathCommand::Close => {
                     current_point = initial_point;
                     f(LinePathCommand::Close)
@@ -23,6 +23,10 @@ impl Trapezoidator {
     /// Returns an iterator over trapezoids corresponding to the given iterator over line path
     /// commands.
     pub fn trapezoidate<P: LinePathIterator>(&mut self, path: P)->Option<Trapezoidate>{
+        // A previous call may have bailed out on an invalid segment, or its iterator may not have
+        // been run to completion, so start from a clean slate.
+        self.event_queue.clear();
+        self.active_segments.clear();
         let mut initial_point = None;
         let mut current_point = None;
         if !path.for_each(&mut |command| {
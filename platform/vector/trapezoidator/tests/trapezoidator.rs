@@ -0,0 +1,59 @@
+use makepad_geometry::{Point, Trapezoid};
+use makepad_internal_iter::InternalIterator;
+use makepad_path::{Path, PathIterator};
+use makepad_trapezoidator::Trapezoidator;
+
+fn area(trapezoids: &[Trapezoid]) -> f32 {
+    let mut area = 0.0;
+    for trapezoid in trapezoids {
+        area += (trapezoid.xs[1] - trapezoid.xs[0])
+            * ((trapezoid.ys[2] - trapezoid.ys[0]) + (trapezoid.ys[3] - trapezoid.ys[1]))
+            / 2.0;
+    }
+    area
+}
+
+fn trapezoidate(path: &Path, epsilon: f32) -> Vec<Trapezoid> {
+    let mut trapezoids = Vec::new();
+    Trapezoidator::new()
+        .trapezoidate(path.commands().linearize(epsilon))
+        .unwrap()
+        .for_each(&mut |trapezoid| {
+            trapezoids.push(trapezoid);
+            true
+        });
+    trapezoids
+}
+
+#[test]
+fn cubic_circle() {
+    // The standard approximation of a circle with four cubic Bezier curve segments.
+    let r = 100.0;
+    let k = 0.552_284_8 * r;
+    let mut path = Path::new();
+    path.move_to(Point::new(r, 0.0));
+    path.cubic_to(Point::new(r, k), Point::new(k, r), Point::new(0.0, r));
+    path.cubic_to(Point::new(-k, r), Point::new(-r, k), Point::new(-r, 0.0));
+    path.cubic_to(Point::new(-r, -k), Point::new(-k, -r), Point::new(0.0, -r));
+    path.cubic_to(Point::new(k, -r), Point::new(r, -k), Point::new(r, 0.0));
+    path.close();
+    let trapezoids = trapezoidate(&path, 0.1);
+    assert!(trapezoids.len() > 8);
+    let expected_area = std::f32::consts::PI * r * r;
+    assert!((area(&trapezoids) - expected_area).abs() / expected_area < 0.001);
+}
+
+#[test]
+fn mixed_segments() {
+    // A unit square whose right edge bulges out as a cubic curve, with x = 1 + 9/8 t (1 - t), and
+    // whose top edge bulges up as a quadratic curve, with y = 1 + 2 t (1 - t). These add an area
+    // of 9/48 and 1/3 respectively.
+    let mut path = Path::new();
+    path.move_to(Point::new(0.0, 0.0));
+    path.line_to(Point::new(1.0, 0.0));
+    path.cubic_to(Point::new(1.375, 1.0 / 3.0), Point::new(1.375, 2.0 / 3.0), Point::new(1.0, 1.0));
+    path.quadratic_to(Point::new(0.5, 2.0), Point::new(0.0, 1.0));
+    path.close();
+    let trapezoids = trapezoidate(&path, 0.001);
+    assert!((area(&trapezoids) - (1.0 + 9.0 / 48.0 + 1.0 / 3.0)).abs() < 0.01);
+}
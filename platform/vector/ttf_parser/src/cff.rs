@@ -0,0 +1,755 @@
+//! Parsing of glyph outlines from `CFF ` and `CFF2` tables, which describe them with Type 2
+//! charstrings.
+
+use crate::{Error, Reader, Result};
+use makepad_font::{Outline, OutlinePoint};
+use makepad_geometry::Point;
+use makepad_internal_iter::ExtendFromInternalIterator;
+use std::mem;
+
+const MAX_CFF_STACK_LEN: usize = 48;
+const MAX_CFF2_STACK_LEN: usize = 513;
+const MAX_SUBR_DEPTH: usize = 10;
+
+const CHAR_STRINGS_OP: u16 = 17;
+const PRIVATE_OP: u16 = 18;
+const SUBRS_OP: u16 = 19;
+const VSINDEX_OP: u16 = 22;
+const VSTORE_OP: u16 = 24;
+const CHARSTRING_TYPE_OP: u16 = 0x0C06;
+const ROS_OP: u16 = 0x0C1E;
+const FD_ARRAY_OP: u16 = 0x0C24;
+const FD_SELECT_OP: u16 = 0x0C25;
+
+/// Parses the outlines of all glyphs in a `CFF ` table.
+pub fn parse_cff_outlines(bytes: &[u8]) -> Result<Vec<Outline>> {
+    let mut reader = Reader::new(bytes);
+    let major_version = reader.read_u8()?;
+    reader.skip(1)?;
+    let header_size = reader.read_u8()? as usize;
+    if major_version != 1 {
+        return Err(Error);
+    }
+    let mut reader = Reader::new(bytes.get(header_size..).ok_or(Error)?);
+    read_index(&mut reader, false)?;
+    let top_dicts = read_index(&mut reader, false)?;
+    read_index(&mut reader, false)?;
+    let global_subrs = read_index(&mut reader, false)?;
+    let top_dict = parse_dict(top_dicts.first().ok_or(Error)?)?;
+    if top_dict.get_usize(CHARSTRING_TYPE_OP).unwrap_or(2) != 2 {
+        return Err(Error);
+    }
+    let char_strings = read_index_at(bytes, top_dict.get_usize(CHAR_STRINGS_OP).ok_or(Error)?, false)?;
+    let (font_dicts, fd_indices) = if top_dict.get(ROS_OP).is_some() {
+        parse_font_dicts(bytes, &top_dict, char_strings.len(), false)?
+    } else {
+        (vec![parse_private_dict(bytes, &top_dict, false)?], Vec::new())
+    };
+    parse_outlines(
+        &char_strings,
+        &CharStringContext {
+            is_cff2: false,
+            global_subrs: &global_subrs,
+            font_dicts: &font_dicts,
+            fd_indices: &fd_indices,
+            region_counts: &[],
+        },
+    )
+}
+
+/// Parses the outlines of all glyphs in a `CFF2` table, for the default instance of the font.
+pub fn parse_cff2_outlines(bytes: &[u8]) -> Result<Vec<Outline>> {
+    let mut reader = Reader::new(bytes);
+    let major_version = reader.read_u8()?;
+    reader.skip(1)?;
+    let header_size = reader.read_u8()? as usize;
+    let top_dict_length = reader.read_u16()? as usize;
+    if major_version != 2 {
+        return Err(Error);
+    }
+    let top_dict_bytes = bytes
+        .get(header_size..header_size + top_dict_length)
+        .ok_or(Error)?;
+    let top_dict = parse_dict(top_dict_bytes)?;
+    let global_subrs = read_index_at(bytes, header_size + top_dict_length, true)?;
+    let char_strings = read_index_at(bytes, top_dict.get_usize(CHAR_STRINGS_OP).ok_or(Error)?, true)?;
+    let (font_dicts, fd_indices) = parse_font_dicts(bytes, &top_dict, char_strings.len(), true)?;
+    let region_counts = match top_dict.get_usize(VSTORE_OP) {
+        Some(offset) => parse_region_counts(bytes.get(offset..).ok_or(Error)?)?,
+        None => Vec::new(),
+    };
+    parse_outlines(
+        &char_strings,
+        &CharStringContext {
+            is_cff2: true,
+            global_subrs: &global_subrs,
+            font_dicts: &font_dicts,
+            fd_indices: &fd_indices,
+            region_counts: &region_counts,
+        },
+    )
+}
+
+fn parse_outlines(char_strings: &[&[u8]], context: &CharStringContext) -> Result<Vec<Outline>> {
+    let mut outlines = Vec::with_capacity(char_strings.len());
+    for (index, char_string) in char_strings.iter().enumerate() {
+        let font_dict_index = context.fd_indices.get(index).cloned().unwrap_or(0);
+        let font_dict = context.font_dicts.get(font_dict_index).ok_or(Error)?;
+        let mut interpreter = CharStringInterpreter::new(context, font_dict);
+        interpreter.run(char_string, 0)?;
+        outlines.push(interpreter.finish());
+    }
+    Ok(outlines)
+}
+
+// Parses the font dicts of a CID-keyed `CFF ` table or a `CFF2` table, and the index of the font
+// dict for each glyph.
+fn parse_font_dicts<'a>(
+    bytes: &'a [u8],
+    top_dict: &Dict,
+    glyph_count: usize,
+    is_cff2: bool,
+) -> Result<(Vec<FontDict<'a>>, Vec<usize>)> {
+    let font_dicts = read_index_at(bytes, top_dict.get_usize(FD_ARRAY_OP).ok_or(Error)?, is_cff2)?
+        .iter()
+        .map(|font_dict_bytes| parse_private_dict(bytes, &parse_dict(font_dict_bytes)?, is_cff2))
+        .collect::<Result<Vec<_>>>()?;
+    let fd_indices = match top_dict.get_usize(FD_SELECT_OP) {
+        Some(offset) => parse_fd_select(bytes.get(offset..).ok_or(Error)?, glyph_count)?,
+        // The FDSelect is optional in a `CFF2` table with a single font dict.
+        None if is_cff2 => Vec::new(),
+        None => return Err(Error),
+    };
+    if fd_indices.iter().any(|&index| index >= font_dicts.len()) {
+        return Err(Error);
+    }
+    Ok((font_dicts, fd_indices))
+}
+
+// Parses the private dict that the given top or font dict points to, if any, and the local
+// subroutines that it points to in turn.
+fn parse_private_dict<'a>(bytes: &'a [u8], dict: &Dict, is_cff2: bool) -> Result<FontDict<'a>> {
+    let operands = match dict.get(PRIVATE_OP) {
+        Some(operands) if operands.len() == 2 => operands,
+        Some(_) => return Err(Error),
+        None => return Ok(FontDict::default()),
+    };
+    let size = operands[0] as usize;
+    let offset = operands[1] as usize;
+    let private_dict = parse_dict(bytes.get(offset..offset + size).ok_or(Error)?)?;
+    let local_subrs = match private_dict.get_usize(SUBRS_OP) {
+        // The offset to the local subroutines is relative to the start of the private dict.
+        Some(subrs_offset) => read_index_at(bytes, offset + subrs_offset, is_cff2)?,
+        None => Vec::new(),
+    };
+    Ok(FontDict {
+        local_subrs,
+        vsindex: private_dict.get_usize(VSINDEX_OP).unwrap_or(0),
+    })
+}
+
+fn parse_fd_select(bytes: &[u8], glyph_count: usize) -> Result<Vec<usize>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u8()?;
+    let mut fd_indices = Vec::with_capacity(glyph_count);
+    match format {
+        0 => {
+            for _ in 0..glyph_count {
+                fd_indices.push(reader.read_u8()? as usize);
+            }
+        }
+        3 | 4 => {
+            let read_glyph_index = |reader: &mut Reader| -> Result<usize> {
+                Ok(if format == 3 {
+                    reader.read_u16()? as usize
+                } else {
+                    reader.read_u32()? as usize
+                })
+            };
+            let range_count = if format == 3 {
+                reader.read_u16()? as usize
+            } else {
+                reader.read_u32()? as usize
+            };
+            let mut first = read_glyph_index(&mut reader)?;
+            for _ in 0..range_count {
+                let fd_index = if format == 3 {
+                    reader.read_u8()? as usize
+                } else {
+                    reader.read_u16()? as usize
+                };
+                let next_first = read_glyph_index(&mut reader)?;
+                if first != fd_indices.len() || next_first < first {
+                    return Err(Error);
+                }
+                fd_indices.resize(next_first, fd_index);
+                first = next_first;
+            }
+        }
+        _ => return Err(Error),
+    }
+    if fd_indices.len() < glyph_count {
+        return Err(Error);
+    }
+    Ok(fd_indices)
+}
+
+// Returns the number of variation regions for each item variation data subtable in the item
+// variation store of a `CFF2` table. This is the number of deltas that follow each default value
+// in the operands of a `blend` operator.
+fn parse_region_counts(bytes: &[u8]) -> Result<Vec<usize>> {
+    // The item variation store is preceded by its length.
+    let bytes = bytes.get(2..).ok_or(Error)?;
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    if format != 1 {
+        return Err(Error);
+    }
+    reader.skip(4)?;
+    let data_count = reader.read_u16()? as usize;
+    let mut region_counts = Vec::with_capacity(data_count);
+    for _ in 0..data_count {
+        let data_offset = reader.read_u32()? as usize;
+        let mut reader = Reader::new(bytes.get(data_offset..).ok_or(Error)?);
+        reader.skip(4)?;
+        region_counts.push(reader.read_u16()? as usize);
+    }
+    Ok(region_counts)
+}
+
+fn read_index_at(bytes: &[u8], offset: usize, is_cff2: bool) -> Result<Vec<&[u8]>> {
+    read_index(&mut Reader::new(bytes.get(offset..).ok_or(Error)?), is_cff2)
+}
+
+// Reads an INDEX, which is a list of variable-sized objects, and advances the reader past it.
+fn read_index<'a>(reader: &mut Reader<'a>, is_cff2: bool) -> Result<Vec<&'a [u8]>> {
+    let count = if is_cff2 {
+        reader.read_u32()? as usize
+    } else {
+        reader.read_u16()? as usize
+    };
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    let offset_size = reader.read_u8()? as usize;
+    if !(1..=4).contains(&offset_size) {
+        return Err(Error);
+    }
+    let mut offsets = Vec::with_capacity(count + 1);
+    for _ in 0..=count {
+        let mut offset = 0;
+        for _ in 0..offset_size {
+            offset = offset << 8 | reader.read_u8()? as usize;
+        }
+        // Offsets are relative to the byte that precedes the object data.
+        if offset == 0 {
+            return Err(Error);
+        }
+        offsets.push(offset - 1);
+    }
+    let data = reader.bytes.get(..offsets[count]).ok_or(Error)?;
+    reader.skip(data.len())?;
+    offsets
+        .windows(2)
+        .map(|window| data.get(window[0]..window[1]).ok_or(Error))
+        .collect()
+}
+
+#[derive(Clone, Debug, Default)]
+struct Dict {
+    entries: Vec<(u16, Vec<f64>)>,
+}
+
+impl Dict {
+    fn get(&self, op: u16) -> Option<&[f64]> {
+        self.entries
+            .iter()
+            .find(|(entry_op, _)| *entry_op == op)
+            .map(|(_, operands)| operands.as_slice())
+    }
+
+    fn get_usize(&self, op: u16) -> Option<usize> {
+        let operand = *self.get(op)?.first()?;
+        if operand < 0.0 {
+            return None;
+        }
+        Some(operand as usize)
+    }
+}
+
+fn parse_dict(bytes: &[u8]) -> Result<Dict> {
+    let mut reader = Reader::new(bytes);
+    let mut entries = Vec::new();
+    let mut operands = Vec::new();
+    while !reader.bytes.is_empty() {
+        let b0 = reader.read_u8()?;
+        match b0 {
+            0..=11 | 13..=27 => entries.push((b0 as u16, mem::take(&mut operands))),
+            12 => {
+                let b1 = reader.read_u8()?;
+                entries.push((0x0C00 | b1 as u16, mem::take(&mut operands)));
+            }
+            28 => operands.push(reader.read_i16()? as f64),
+            29 => operands.push(reader.read_u32()? as i32 as f64),
+            30 => operands.push(read_real(&mut reader)?),
+            32..=246 => operands.push(b0 as f64 - 139.0),
+            247..=250 => {
+                let b1 = reader.read_u8()?;
+                operands.push((b0 as f64 - 247.0) * 256.0 + b1 as f64 + 108.0)
+            }
+            251..=254 => {
+                let b1 = reader.read_u8()?;
+                operands.push(-(b0 as f64 - 251.0) * 256.0 - b1 as f64 - 108.0)
+            }
+            _ => return Err(Error),
+        }
+    }
+    Ok(Dict { entries })
+}
+
+// Reads a real number, which is encoded as a sequence of nibbles that represent the characters of
+// its decimal representation.
+fn read_real(reader: &mut Reader) -> Result<f64> {
+    let mut string = String::new();
+    loop {
+        let byte = reader.read_u8()?;
+        for &nibble in &[byte >> 4, byte & 0xF] {
+            match nibble {
+                0..=9 => string.push((b'0' + nibble) as char),
+                0xA => string.push('.'),
+                0xB => string.push('E'),
+                0xC => string.push_str("E-"),
+                0xE => string.push('-'),
+                0xF => return string.parse().map_err(|_| Error),
+                _ => return Err(Error),
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct FontDict<'a> {
+    local_subrs: Vec<&'a [u8]>,
+    vsindex: usize,
+}
+
+struct CharStringContext<'a> {
+    is_cff2: bool,
+    global_subrs: &'a [&'a [u8]],
+    font_dicts: &'a [FontDict<'a>],
+    fd_indices: &'a [usize],
+    region_counts: &'a [usize],
+}
+
+struct CharStringInterpreter<'a> {
+    context: &'a CharStringContext<'a>,
+    font_dict: &'a FontDict<'a>,
+    stack: Vec<f32>,
+    stem_count: usize,
+    seen_width: bool,
+    vsindex: usize,
+    point: Point,
+    contour: Vec<OutlinePoint>,
+    outline: Outline,
+}
+
+impl<'a> CharStringInterpreter<'a> {
+    fn new(context: &'a CharStringContext<'a>, font_dict: &'a FontDict<'a>) -> Self {
+        Self {
+            context,
+            font_dict,
+            stack: Vec::new(),
+            stem_count: 0,
+            // Only `CFF ` charstrings start with an optional width.
+            seen_width: context.is_cff2,
+            vsindex: font_dict.vsindex,
+            point: Point::origin(),
+            contour: Vec::new(),
+            outline: Outline::new_cubic(),
+        }
+    }
+
+    fn finish(mut self) -> Outline {
+        self.end_contour();
+        self.outline
+    }
+
+    // Runs the given charstring, and returns `true` if it ended the glyph.
+    fn run(&mut self, bytes: &[u8], depth: usize) -> Result<bool> {
+        if depth > MAX_SUBR_DEPTH {
+            return Err(Error);
+        }
+        let mut reader = Reader::new(bytes);
+        while !reader.bytes.is_empty() {
+            let b0 = reader.read_u8()?;
+            match b0 {
+                // hstem, vstem, hstemhm, vstemhm
+                1 | 3 | 18 | 23 => {
+                    self.take_width(self.stack.len() % 2 == 1);
+                    self.stem_count += self.stack.len() / 2;
+                    self.stack.clear();
+                }
+                // vmoveto
+                4 => {
+                    self.take_width(self.stack.len() > 1);
+                    let dy = self.arg(0)?;
+                    self.move_to(0.0, dy);
+                    self.stack.clear();
+                }
+                // rlineto
+                5 => {
+                    for index in (0..self.stack.len() / 2 * 2).step_by(2) {
+                        let (dx, dy) = (self.stack[index], self.stack[index + 1]);
+                        self.line_to_relative(dx, dy);
+                    }
+                    self.stack.clear();
+                }
+                // hlineto, vlineto
+                6 | 7 => {
+                    let mut is_horizontal = b0 == 6;
+                    for index in 0..self.stack.len() {
+                        let d = self.stack[index];
+                        if is_horizontal {
+                            self.line_to_relative(d, 0.0);
+                        } else {
+                            self.line_to_relative(0.0, d);
+                        }
+                        is_horizontal = !is_horizontal;
+                    }
+                    self.stack.clear();
+                }
+                // rrcurveto
+                8 => {
+                    for index in (0..self.stack.len() / 6 * 6).step_by(6) {
+                        let s = &self.stack[index..index + 6];
+                        let (dx1, dy1, dx2, dy2, dx3, dy3) = (s[0], s[1], s[2], s[3], s[4], s[5]);
+                        self.curve_to_relative(dx1, dy1, dx2, dy2, dx3, dy3);
+                    }
+                    self.stack.clear();
+                }
+                // callsubr
+                10 => {
+                    let subrs = &self.font_dict.local_subrs;
+                    let subr = self.pop_subr(subrs)?;
+                    if self.run(subr, depth + 1)? {
+                        return Ok(true);
+                    }
+                }
+                // return
+                11 => return Ok(false),
+                12 => {
+                    let b1 = reader.read_u8()?;
+                    self.run_escaped(b1)?;
+                }
+                // endchar
+                14 => {
+                    if self.context.is_cff2 {
+                        return Err(Error);
+                    }
+                    // An endchar with 4 more arguments is the deprecated `seac` form, which
+                    // composes an accented character. We only draw what we have so far.
+                    self.take_width(self.stack.len() == 1 || self.stack.len() == 5);
+                    self.stack.clear();
+                    return Ok(true);
+                }
+                // vsindex
+                15 if self.context.is_cff2 => {
+                    self.vsindex = self.arg(0)? as usize;
+                    self.stack.clear();
+                }
+                // blend
+                16 if self.context.is_cff2 => self.blend()?,
+                // hintmask, cntrmask
+                19 | 20 => {
+                    self.take_width(self.stack.len() % 2 == 1);
+                    self.stem_count += self.stack.len() / 2;
+                    self.stack.clear();
+                    reader.skip(self.stem_count.div_ceil(8))?;
+                }
+                // rmoveto
+                21 => {
+                    self.take_width(self.stack.len() > 2);
+                    let dx = self.arg(0)?;
+                    let dy = self.arg(1)?;
+                    self.move_to(dx, dy);
+                    self.stack.clear();
+                }
+                // hmoveto
+                22 => {
+                    self.take_width(self.stack.len() > 1);
+                    let dx = self.arg(0)?;
+                    self.move_to(dx, 0.0);
+                    self.stack.clear();
+                }
+                // rcurveline
+                24 => {
+                    if self.stack.len() < 8 {
+                        return Err(Error);
+                    }
+                    let curve_arg_count = (self.stack.len() - 2) / 6 * 6;
+                    for index in (0..curve_arg_count).step_by(6) {
+                        let s = &self.stack[index..index + 6];
+                        let (dx1, dy1, dx2, dy2, dx3, dy3) = (s[0], s[1], s[2], s[3], s[4], s[5]);
+                        self.curve_to_relative(dx1, dy1, dx2, dy2, dx3, dy3);
+                    }
+                    let (dx, dy) = (self.arg(curve_arg_count)?, self.arg(curve_arg_count + 1)?);
+                    self.line_to_relative(dx, dy);
+                    self.stack.clear();
+                }
+                // rlinecurve
+                25 => {
+                    if self.stack.len() < 8 {
+                        return Err(Error);
+                    }
+                    let line_arg_count = (self.stack.len() - 6) / 2 * 2;
+                    for index in (0..line_arg_count).step_by(2) {
+                        let (dx, dy) = (self.stack[index], self.stack[index + 1]);
+                        self.line_to_relative(dx, dy);
+                    }
+                    let s = &self.stack[line_arg_count..];
+                    if s.len() < 6 {
+                        return Err(Error);
+                    }
+                    let (dx1, dy1, dx2, dy2, dx3, dy3) = (s[0], s[1], s[2], s[3], s[4], s[5]);
+                    self.curve_to_relative(dx1, dy1, dx2, dy2, dx3, dy3);
+                    self.stack.clear();
+                }
+                // vvcurveto, hhcurveto
+                26 | 27 => {
+                    let is_horizontal = b0 == 27;
+                    let mut index = 0;
+                    let mut d1 = 0.0;
+                    if self.stack.len() % 4 == 1 {
+                        d1 = self.stack[0];
+                        index = 1;
+                    }
+                    while index + 4 <= self.stack.len() {
+                        let s = &self.stack[index..index + 4];
+                        let (da, dxb, dyb, dc) = (s[0], s[1], s[2], s[3]);
+                        if is_horizontal {
+                            self.curve_to_relative(da, d1, dxb, dyb, dc, 0.0);
+                        } else {
+                            self.curve_to_relative(d1, da, dxb, dyb, 0.0, dc);
+                        }
+                        d1 = 0.0;
+                        index += 4;
+                    }
+                    self.stack.clear();
+                }
+                // callgsubr
+                29 => {
+                    let subrs = self.context.global_subrs;
+                    let subr = self.pop_subr(subrs)?;
+                    if self.run(subr, depth + 1)? {
+                        return Ok(true);
+                    }
+                }
+                // vhcurveto, hvcurveto
+                30 | 31 => {
+                    let mut is_horizontal = b0 == 31;
+                    let mut index = 0;
+                    while index + 4 <= self.stack.len() {
+                        let s = &self.stack[index..];
+                        // The last curve has an optional fifth argument.
+                        let d_last = if s.len() == 5 { s[4] } else { 0.0 };
+                        let (d1, dx2, dy2, d3) = (s[0], s[1], s[2], s[3]);
+                        if is_horizontal {
+                            self.curve_to_relative(d1, 0.0, dx2, dy2, d_last, d3);
+                        } else {
+                            self.curve_to_relative(0.0, d1, dx2, dy2, d3, d_last);
+                        }
+                        is_horizontal = !is_horizontal;
+                        index += 4;
+                    }
+                    self.stack.clear();
+                }
+                28 => {
+                    let value = reader.read_i16()? as f32;
+                    self.push(value)?;
+                }
+                32..=246 => self.push(b0 as f32 - 139.0)?,
+                247..=250 => {
+                    let b1 = reader.read_u8()?;
+                    self.push((b0 as f32 - 247.0) * 256.0 + b1 as f32 + 108.0)?;
+                }
+                251..=254 => {
+                    let b1 = reader.read_u8()?;
+                    self.push(-(b0 as f32 - 251.0) * 256.0 - b1 as f32 - 108.0)?;
+                }
+                255 => {
+                    let value = reader.read_u32()? as i32 as f32 / 65536.0;
+                    self.push(value)?;
+                }
+                _ => return Err(Error),
+            }
+        }
+        // A `CFF2` charstring ends at the end of its bytes, rather than with an `endchar`.
+        Ok(depth == 0)
+    }
+
+    fn run_escaped(&mut self, b1: u8) -> Result<()> {
+        let s = &self.stack;
+        match b1 {
+            // flex
+            35 => {
+                if s.len() < 13 {
+                    return Err(Error);
+                }
+                let s: Vec<f32> = s[..12].to_vec();
+                self.curve_to_relative(s[0], s[1], s[2], s[3], s[4], s[5]);
+                self.curve_to_relative(s[6], s[7], s[8], s[9], s[10], s[11]);
+            }
+            // hflex
+            34 => {
+                if s.len() < 7 {
+                    return Err(Error);
+                }
+                let (dx1, dx2, dy2, dx3, dx4, dx5, dx6) = (s[0], s[1], s[2], s[3], s[4], s[5], s[6]);
+                self.curve_to_relative(dx1, 0.0, dx2, dy2, dx3, 0.0);
+                self.curve_to_relative(dx4, 0.0, dx5, -dy2, dx6, 0.0);
+            }
+            // hflex1
+            36 => {
+                if s.len() < 9 {
+                    return Err(Error);
+                }
+                let (dx1, dy1, dx2, dy2, dx3) = (s[0], s[1], s[2], s[3], s[4]);
+                let (dx4, dx5, dy5, dx6) = (s[5], s[6], s[7], s[8]);
+                self.curve_to_relative(dx1, dy1, dx2, dy2, dx3, 0.0);
+                self.curve_to_relative(dx4, 0.0, dx5, dy5, dx6, -(dy1 + dy2 + dy5));
+            }
+            // flex1
+            37 => {
+                if s.len() < 11 {
+                    return Err(Error);
+                }
+                let s: Vec<f32> = s[..11].to_vec();
+                let dx = s[0] + s[2] + s[4] + s[6] + s[8];
+                let dy = s[1] + s[3] + s[5] + s[7] + s[9];
+                let (dx6, dy6) = if dx.abs() > dy.abs() {
+                    (s[10], -dy)
+                } else {
+                    (-dx, s[10])
+                };
+                self.curve_to_relative(s[0], s[1], s[2], s[3], s[4], s[5]);
+                self.curve_to_relative(s[6], s[7], s[8], s[9], dx6, dy6);
+            }
+            // The arithmetic and storage operators were removed from the spec, and are not used
+            // in practice.
+            _ => return Err(Error),
+        }
+        self.stack.clear();
+        Ok(())
+    }
+
+    // Replaces the operands of a `blend` operator with the default values.
+    fn blend(&mut self) -> Result<()> {
+        let value_count = self.stack.pop().ok_or(Error)? as usize;
+        let region_count = *self.context.region_counts.get(self.vsindex).ok_or(Error)?;
+        let operand_count = value_count * (region_count + 1);
+        if operand_count > self.stack.len() {
+            return Err(Error);
+        }
+        let start = self.stack.len() - operand_count;
+        self.stack.truncate(start + value_count);
+        Ok(())
+    }
+
+    // The first stack clearing operator in a `CFF ` charstring is preceded by an extra argument
+    // for the width of the glyph, if it differs from the default. We get the width from the `hmtx`
+    // table instead, so we drop it.
+    fn take_width(&mut self, has_width: bool) {
+        if !self.seen_width {
+            self.seen_width = true;
+            if has_width && !self.stack.is_empty() {
+                self.stack.remove(0);
+            }
+        }
+    }
+
+    fn pop_subr(&mut self, subrs: &[&'a [u8]]) -> Result<&'a [u8]> {
+        let index = self.stack.pop().ok_or(Error)? as i32 + subr_bias(subrs.len());
+        if index < 0 {
+            return Err(Error);
+        }
+        subrs.get(index as usize).cloned().ok_or(Error)
+    }
+
+    fn push(&mut self, value: f32) -> Result<()> {
+        let max_stack_len = if self.context.is_cff2 {
+            MAX_CFF2_STACK_LEN
+        } else {
+            MAX_CFF_STACK_LEN
+        };
+        if self.stack.len() >= max_stack_len {
+            return Err(Error);
+        }
+        self.stack.push(value);
+        Ok(())
+    }
+
+    fn arg(&self, index: usize) -> Result<f32> {
+        self.stack.get(index).cloned().ok_or(Error)
+    }
+
+    fn move_to(&mut self, dx: f32, dy: f32) {
+        self.end_contour();
+        self.point = Point::new(self.point.x + dx, self.point.y + dy);
+        self.contour.push(OutlinePoint {
+            is_on_curve: true,
+            point: self.point,
+        });
+    }
+
+    fn line_to_relative(&mut self, dx: f32, dy: f32) {
+        self.push_point(dx, dy, true);
+    }
+
+    fn curve_to_relative(&mut self, dx1: f32, dy1: f32, dx2: f32, dy2: f32, dx3: f32, dy3: f32) {
+        self.push_point(dx1, dy1, false);
+        self.push_point(dx2, dy2, false);
+        self.push_point(dx3, dy3, true);
+    }
+
+    fn push_point(&mut self, dx: f32, dy: f32, is_on_curve: bool) {
+        // A path operator without a preceding move to starts at the origin.
+        if self.contour.is_empty() {
+            self.contour.push(OutlinePoint {
+                is_on_curve: true,
+                point: self.point,
+            });
+        }
+        self.point = Point::new(self.point.x + dx, self.point.y + dy);
+        self.contour.push(OutlinePoint {
+            is_on_curve,
+            point: self.point,
+        });
+    }
+
+    fn end_contour(&mut self) {
+        // Contours are closed implicitly, so if the last point is the same as the first, it is
+        // redundant.
+        if self.contour.len() > 1 {
+            let last_point = *self.contour.last().unwrap();
+            if last_point.is_on_curve && last_point.point == self.contour[0].point {
+                self.contour.pop();
+            }
+        }
+        if self.contour.len() > 1 {
+            let mut contour = self.outline.begin_contour();
+            contour.extend_from_internal_iter(self.contour.iter().cloned());
+            contour.end();
+        }
+        self.contour.clear();
+    }
+}
+
+fn subr_bias(subr_count: usize) -> i32 {
+    if subr_count < 1240 {
+        107
+    } else if subr_count < 33900 {
+        1131
+    } else {
+        32768
+    }
+}
@@ -1,4 +1,11 @@
-use makepad_font::{TTFFont, Glyph, HorizontalMetrics, Outline, OutlinePoint};
+mod cff;
+
+use makepad_font::{
+    CharCodeToGlyphIndexMap, ContextRule, ContextSubtable, GlyphClassMap, GlyphMatcher,
+    KerningLookup, KerningPair, KerningSubtable, KerningTable, Ligature, SubstitutionFeature,
+    SubstitutionLookup, SubstitutionScript, SubstitutionSubtable, SubstitutionTable, TTFFont,
+    Glyph, HorizontalMetrics, Outline, OutlinePoint,
+};
 use makepad_geometry::{
     AffineTransformation, LinearTransformation, Point, Rectangle, Transform, Vector,
 };
@@ -99,23 +106,7 @@ impl<'a> GlyphsParser<'a> {
     }
 
     fn parse_horizontal_metrics(&self, index: usize) -> Result<HorizontalMetrics> {
-        let mut reader = Reader::new(self.hmtx_table_bytes);
-        if index < self.advance_width_count {
-            reader.skip(index * 4)?;
-            Ok(HorizontalMetrics {
-                advance_width: reader.read_u16()? as f32,
-                left_side_bearing: reader.read_i16()? as f32,
-            })
-        } else {
-            reader.skip((self.advance_width_count - 1) * 4)?;
-            let advance_width = reader.read_u16()? as f32;
-            reader.skip(2)?;
-            reader.skip((index - self.advance_width_count) * 2)?;
-            Ok(HorizontalMetrics {
-                advance_width,
-                left_side_bearing: reader.read_i16()? as f32,
-            })
-        }
+        parse_horizontal_metrics(self.hmtx_table_bytes, self.advance_width_count, index)
     }
 
     fn parse_simple_glyph(
@@ -494,6 +485,12 @@ impl<'a> Reader<'a> {
         Ok(u16::from_be_bytes(bytes))
     }
 
+    fn read_u24(&mut self) -> Result<u32> {
+        let mut bytes = [0; 3];
+        self.read(&mut bytes)?;
+        Ok(u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]))
+    }
+
     fn read_u32(&mut self) -> Result<u32> {
         let mut bytes = [0; mem::size_of::<u32>()];
         self.read(&mut bytes)?;
@@ -516,16 +513,22 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<TTFFont> {
     }
     let mut reader = Reader::new(&bytes[0..12]);
     let sfnt_version = reader.read_u32()?;
-    if ![0x00010000, u32::from_be_bytes(*b"true")].contains(&sfnt_version) {
+    if ![0x00010000, u32::from_be_bytes(*b"true"), u32::from_be_bytes(*b"OTTO")].contains(&sfnt_version) {
         return Err(Error);
     }
     let table_count = reader.read_u16()? as usize;
     reader.skip(6)?;
+    let mut cff_table_bytes = None;
+    let mut cff2_table_bytes = None;
     let mut cmap_table_bytes = None;
+    let mut gdef_table_bytes = None;
     let mut glyf_table_bytes = None;
+    let mut gpos_table_bytes = None;
+    let mut gsub_table_bytes = None;
     let mut head_table_bytes = None;
     let mut hhea_table_bytes = None;
     let mut hmtx_table_bytes = None;
+    let mut kern_table_bytes = None;
     let mut loca_table_bytes = None;
     let mut maxp_table_bytes = None;
     for index in 0..table_count {
@@ -542,22 +545,26 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<TTFFont> {
         }
         let table_bytes = &bytes[offset..][..length];
         match &table_tag.to_be_bytes() {
+            b"CFF " => cff_table_bytes = Some(table_bytes),
+            b"CFF2" => cff2_table_bytes = Some(table_bytes),
             b"cmap" => cmap_table_bytes = Some(table_bytes),
+            b"GDEF" => gdef_table_bytes = Some(table_bytes),
             b"glyf" => glyf_table_bytes = Some(table_bytes),
+            b"GPOS" => gpos_table_bytes = Some(table_bytes),
+            b"GSUB" => gsub_table_bytes = Some(table_bytes),
             b"head" => head_table_bytes = Some(table_bytes),
             b"hhea" => hhea_table_bytes = Some(table_bytes),
             b"hmtx" => hmtx_table_bytes = Some(table_bytes),
+            b"kern" => kern_table_bytes = Some(table_bytes),
             b"loca" => loca_table_bytes = Some(table_bytes),
             b"maxp" => maxp_table_bytes = Some(table_bytes),
             _ => {}
         }
     }
     let cmap_table_bytes = cmap_table_bytes.ok_or(Error)?;
-    let glyf_table_bytes = glyf_table_bytes.ok_or(Error)?;
     let head_table_bytes = head_table_bytes.ok_or(Error)?;
     let hhea_table_bytes = hhea_table_bytes.ok_or(Error)?;
     let hmtx_table_bytes = hmtx_table_bytes.ok_or(Error)?;
-    let loca_table_bytes = loca_table_bytes.ok_or(Error)?;
     let maxp_table_bytes = maxp_table_bytes.ok_or(Error)?;
     let mut reader = Reader::new(hhea_table_bytes);
     reader.skip(4)?;
@@ -569,7 +576,6 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<TTFFont> {
     let mut reader = Reader::new(maxp_table_bytes);
     reader.skip(4)?;
     let glyph_count = reader.read_u16()? as usize;
-    reader.skip(26)?;
     let mut reader = Reader::new(head_table_bytes);
     reader.skip(18)?;
     let units_per_em = reader.read_u16()? as f32;
@@ -588,43 +594,106 @@ pub fn parse_ttf(bytes: &[u8]) -> Result<TTFFont> {
         line_gap,
         bounds,
         char_code_to_glyph_index_map: parse_char_code_to_glyph_index_map(cmap_table_bytes)?,
-        glyphs: GlyphsParser::new(
-            glyph_count,
-            advance_width_count,
-            hmtx_table_bytes,
-            index_to_loc_format,
-            loca_table_bytes,
-            glyf_table_bytes,
-        )
-        .parse_glyphs()?,
+        glyphs: match (glyf_table_bytes, loca_table_bytes, cff_table_bytes, cff2_table_bytes) {
+            (Some(glyf_table_bytes), Some(loca_table_bytes), _, _) => GlyphsParser::new(
+                glyph_count,
+                advance_width_count,
+                hmtx_table_bytes,
+                index_to_loc_format,
+                loca_table_bytes,
+                glyf_table_bytes,
+            )
+            .parse_glyphs()?,
+            (_, _, Some(cff_table_bytes), _) => parse_cff_glyphs(
+                cff::parse_cff_outlines(cff_table_bytes)?,
+                glyph_count,
+                advance_width_count,
+                hmtx_table_bytes,
+            )?,
+            (_, _, _, Some(cff2_table_bytes)) => parse_cff_glyphs(
+                cff::parse_cff2_outlines(cff2_table_bytes)?,
+                glyph_count,
+                advance_width_count,
+                hmtx_table_bytes,
+            )?,
+            _ => return Err(Error),
+        },
+        kerning_table: parse_kerning_table(gpos_table_bytes, kern_table_bytes)?,
+        substitution_table: match gsub_table_bytes {
+            Some(bytes) => parse_substitution_table(bytes, gdef_table_bytes)?,
+            None => SubstitutionTable::new(),
+        },
     })
 }
 
-fn parse_char_code_to_glyph_index_map(bytes: &[u8]) -> Result<Vec<usize>> {
+fn parse_char_code_to_glyph_index_map(bytes: &[u8]) -> Result<CharCodeToGlyphIndexMap> {
     let mut reader = Reader::new(bytes);
     reader.skip(2)?;
-    let mut subtable_bytes = None;
     let subtable_count = reader.read_u16()? as usize;
+    // The best subtable we found so far, and its priority. We prefer subtables that cover the
+    // entire Unicode range over those that only cover the BMP, and those over the legacy formats.
+    let mut best_subtable_bytes = None;
+    let mut best_priority = 0;
+    let mut variation_sequences_subtable_bytes = None;
     for _ in 0..subtable_count {
         let platform_id = reader.read_u16()?;
         let encoding_id = reader.read_u16()?;
         let offset = reader.read_u32()? as usize;
-        if let (0, _) | (3, 1) | (3, 10) = (platform_id, encoding_id) {
-            subtable_bytes = Some(&bytes[offset..]);
-            break;
+        let subtable_bytes = bytes.get(offset..).ok_or(Error)?;
+        let format = Reader::new(subtable_bytes).read_u16()?;
+        let priority = match (platform_id, encoding_id, format) {
+            (0, 5, 14) => {
+                variation_sequences_subtable_bytes = Some(subtable_bytes);
+                continue;
+            }
+            (0, _, 12) | (3, 10, 12) => 4,
+            (0, _, 4) | (3, 1, 4) => 3,
+            (0, _, 6) | (3, 1, 6) => 2,
+            (1, 0, 0) | (1, 0, 6) => 1,
+            _ => continue,
+        };
+        if priority > best_priority {
+            best_subtable_bytes = Some(subtable_bytes);
+            best_priority = priority;
         }
     }
-    let subtable_bytes = subtable_bytes.ok_or(Error)?;
+    let subtable_bytes = best_subtable_bytes.ok_or(Error)?;
     let mut reader = Reader::new(subtable_bytes);
     let format = reader.read_u16()?;
     let bytes = &subtable_bytes[2..];
+    let mut char_code_to_glyph_index_map = CharCodeToGlyphIndexMap::new();
     match format {
-        4 => parse_char_code_to_glyph_index_map_format_4(bytes),
-        _ => Err(Error),
+        0 => parse_char_code_to_glyph_index_map_format_0(bytes, &mut char_code_to_glyph_index_map)?,
+        4 => parse_char_code_to_glyph_index_map_format_4(bytes, &mut char_code_to_glyph_index_map)?,
+        6 => parse_char_code_to_glyph_index_map_format_6(bytes, &mut char_code_to_glyph_index_map)?,
+        12 => parse_char_code_to_glyph_index_map_format_12(bytes, &mut char_code_to_glyph_index_map)?,
+        _ => return Err(Error),
+    }
+    if let Some(subtable_bytes) = variation_sequences_subtable_bytes {
+        parse_variation_sequences_format_14(subtable_bytes, &mut char_code_to_glyph_index_map)?;
+    }
+    Ok(char_code_to_glyph_index_map)
+}
+
+fn parse_char_code_to_glyph_index_map_format_0(
+    bytes: &[u8],
+    char_code_to_glyph_index_map: &mut CharCodeToGlyphIndexMap,
+) -> Result<()> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(4)?;
+    for code in 0..256 {
+        let id = reader.read_u8()? as usize;
+        if id != 0 {
+            char_code_to_glyph_index_map.insert(code, id);
+        }
     }
+    Ok(())
 }
 
-fn parse_char_code_to_glyph_index_map_format_4(bytes: &[u8]) -> Result<Vec<usize>> {
+fn parse_char_code_to_glyph_index_map_format_4(
+    bytes: &[u8],
+    char_code_to_glyph_index_map: &mut CharCodeToGlyphIndexMap,
+) -> Result<()> {
     let mut reader = Reader::new(bytes);
     reader.skip(4)?;
     let seg_count = reader.read_u16()? as usize / 2;
@@ -633,6 +702,9 @@ fn parse_char_code_to_glyph_index_map_format_4(bytes: &[u8]) -> Result<Vec<usize
     let start_code_bytes_start = end_code_bytes_end + 2;
     let id_delta_bytes_start = start_code_bytes_start + seg_count * 2;
     let id_range_offset_bytes_start = id_delta_bytes_start + seg_count * 2;
+    if bytes.len() < id_range_offset_bytes_start + seg_count * 2 {
+        return Err(Error);
+    }
     let end_code_bytes = &bytes[end_code_bytes_start..end_code_bytes_end];
     let start_code_bytes = &bytes[start_code_bytes_start..id_delta_bytes_start];
     let id_delta_bytes = &bytes[id_delta_bytes_start..id_range_offset_bytes_start];
@@ -641,28 +713,786 @@ fn parse_char_code_to_glyph_index_map_format_4(bytes: &[u8]) -> Result<Vec<usize
     let mut start_code_reader = Reader::new(start_code_bytes);
     let mut id_delta_reader = Reader::new(id_delta_bytes);
     let mut id_range_offset_reader = Reader::new(id_range_offset_bytes);
-    let mut char_code_to_glyph_index_map = Vec::new();
     for seg_index in 0..seg_count {
-        let end_code = end_code_reader.read_u16()?;
-        let start_code = start_code_reader.read_u16()?;
+        let end_code = end_code_reader.read_u16()? as u32;
+        let start_code = start_code_reader.read_u16()? as u32;
         let id_delta = id_delta_reader.read_u16()? as usize;
         let id_range_offset = id_range_offset_reader.read_u16()? as usize;
-        for code in start_code..end_code {
-            let mut id = if id_range_offset == 0 {
-                code
+        for code in start_code..=end_code {
+            let id = if id_range_offset == 0 {
+                (code as usize + id_delta) % 65536
             } else {
                 let id_range_bytes = &id_range_offset_bytes[(seg_index * 2)..];
                 let mut reader = Reader::new(id_range_bytes);
                 reader.skip(id_range_offset + (code - start_code) as usize * 2)?;
-                let id = reader.read_u16()?;
-                id
-            } as usize;
+                let id = reader.read_u16()? as usize;
+                if id != 0 {
+                    (id + id_delta) % 65536
+                } else {
+                    0
+                }
+            };
             if id != 0 {
-                id = (id + id_delta) % 65536;
+                char_code_to_glyph_index_map.insert(code, id);
             }
-            char_code_to_glyph_index_map.resize(code as usize + 1, 0);
-            char_code_to_glyph_index_map[code as usize] = id;
         }
     }
-    Ok(char_code_to_glyph_index_map)
+    Ok(())
+}
+
+fn parse_char_code_to_glyph_index_map_format_6(
+    bytes: &[u8],
+    char_code_to_glyph_index_map: &mut CharCodeToGlyphIndexMap,
+) -> Result<()> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(4)?;
+    let first_code = reader.read_u16()? as u32;
+    let entry_count = reader.read_u16()? as u32;
+    for code in first_code..(first_code + entry_count) {
+        let id = reader.read_u16()? as usize;
+        if id != 0 {
+            char_code_to_glyph_index_map.insert(code, id);
+        }
+    }
+    Ok(())
+}
+
+fn parse_char_code_to_glyph_index_map_format_12(
+    bytes: &[u8],
+    char_code_to_glyph_index_map: &mut CharCodeToGlyphIndexMap,
+) -> Result<()> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(10)?;
+    let group_count = reader.read_u32()? as usize;
+    for _ in 0..group_count {
+        let start_char_code = reader.read_u32()?;
+        let end_char_code = reader.read_u32()?;
+        let start_glyph_id = reader.read_u32()? as usize;
+        if start_char_code > end_char_code || end_char_code > 0x10FFFF {
+            return Err(Error);
+        }
+        char_code_to_glyph_index_map.insert_range(start_char_code, end_char_code, start_glyph_id);
+    }
+    Ok(())
+}
+
+// Unlike the other formats, this one takes the bytes of the entire subtable, because the offsets
+// in it are relative to the start of the subtable.
+fn parse_variation_sequences_format_14(
+    bytes: &[u8],
+    char_code_to_glyph_index_map: &mut CharCodeToGlyphIndexMap,
+) -> Result<()> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(6)?;
+    let var_selector_record_count = reader.read_u32()? as usize;
+    for _ in 0..var_selector_record_count {
+        let var_selector = reader.read_u24()?;
+        let default_uvs_offset = reader.read_u32()? as usize;
+        let non_default_uvs_offset = reader.read_u32()? as usize;
+        if default_uvs_offset != 0 {
+            let mut reader = Reader::new(bytes.get(default_uvs_offset..).ok_or(Error)?);
+            let range_count = reader.read_u32()? as usize;
+            for _ in 0..range_count {
+                let start_unicode_value = reader.read_u24()?;
+                let additional_count = reader.read_u8()? as u32;
+                for code in start_unicode_value..=(start_unicode_value + additional_count) {
+                    char_code_to_glyph_index_map.insert_variant(code, var_selector, None);
+                }
+            }
+        }
+        if non_default_uvs_offset != 0 {
+            let mut reader = Reader::new(bytes.get(non_default_uvs_offset..).ok_or(Error)?);
+            let mapping_count = reader.read_u32()? as usize;
+            for _ in 0..mapping_count {
+                let unicode_value = reader.read_u24()?;
+                let glyph_id = reader.read_u16()? as usize;
+                char_code_to_glyph_index_map.insert_variant(unicode_value, var_selector, Some(glyph_id));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Fonts that have both a `GPOS` table with kerning and a `kern` table only keep the latter for
+// older software, so we only fall back to it if the former is missing.
+fn parse_kerning_table(
+    gpos_table_bytes: Option<&[u8]>,
+    kern_table_bytes: Option<&[u8]>,
+) -> Result<KerningTable> {
+    if let Some(bytes) = gpos_table_bytes {
+        let kerning_table = parse_gpos_kerning_table(bytes)?;
+        if !kerning_table.is_empty() {
+            return Ok(kerning_table);
+        }
+    }
+    match kern_table_bytes {
+        Some(bytes) => parse_kern_table(bytes),
+        None => Ok(KerningTable::new()),
+    }
+}
+
+fn parse_kern_table(bytes: &[u8]) -> Result<KerningTable> {
+    let mut reader = Reader::new(bytes);
+    let version = reader.read_u16()?;
+    let mut kerning_table = KerningTable::new();
+    // Version 1 is the Apple variant of the table, which we don't support.
+    if version != 0 {
+        return Ok(kerning_table);
+    }
+    let subtable_count = reader.read_u16()? as usize;
+    let mut offset = 4;
+    for _ in 0..subtable_count {
+        let mut reader = Reader::new(bytes.get(offset..).ok_or(Error)?);
+        reader.skip(2)?;
+        let length = reader.read_u16()? as usize;
+        let coverage = reader.read_u16()?;
+        offset += length;
+        // We only support horizontal kerning values (bit 0), not minimum (bit 1) or cross-stream
+        // (bit 2) values, in format 0.
+        let format = coverage >> 8;
+        if coverage & 0x7 != 0x1 || format != 0 {
+            continue;
+        }
+        let pair_count = reader.read_u16()? as usize;
+        reader.skip(6)?;
+        let mut pairs = Vec::with_capacity(pair_count);
+        for _ in 0..pair_count {
+            pairs.push(KerningPair {
+                left: reader.read_u16()? as usize,
+                right: reader.read_u16()? as usize,
+                value: reader.read_i16()? as f32,
+            });
+        }
+        kerning_table.lookups.push(KerningLookup {
+            subtables: vec![KerningSubtable::from_pairs(pairs)],
+        });
+    }
+    Ok(kerning_table)
+}
+
+fn parse_gpos_kerning_table(bytes: &[u8]) -> Result<KerningTable> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(6)?;
+    let feature_list_offset = reader.read_u16()? as usize;
+    let lookup_list_offset = reader.read_u16()? as usize;
+    let feature_list_bytes = bytes.get(feature_list_offset..).ok_or(Error)?;
+    let lookup_list_bytes = bytes.get(lookup_list_offset..).ok_or(Error)?;
+
+    // We apply the lookups of every `kern` feature, regardless of script and language.
+    let mut lookup_indices = Vec::new();
+    let mut reader = Reader::new(feature_list_bytes);
+    let feature_count = reader.read_u16()? as usize;
+    for _ in 0..feature_count {
+        let feature_tag = reader.read_u32()?;
+        let feature_offset = reader.read_u16()? as usize;
+        if &feature_tag.to_be_bytes() != b"kern" {
+            continue;
+        }
+        let mut reader = Reader::new(feature_list_bytes.get(feature_offset..).ok_or(Error)?);
+        reader.skip(2)?;
+        let lookup_index_count = reader.read_u16()? as usize;
+        for _ in 0..lookup_index_count {
+            lookup_indices.push(reader.read_u16()? as usize);
+        }
+    }
+    lookup_indices.sort_unstable();
+    lookup_indices.dedup();
+
+    let mut kerning_table = KerningTable::new();
+    let mut reader = Reader::new(lookup_list_bytes);
+    let lookup_count = reader.read_u16()? as usize;
+    for lookup_index in lookup_indices {
+        if lookup_index >= lookup_count {
+            return Err(Error);
+        }
+        let mut reader = Reader::new(lookup_list_bytes);
+        reader.skip(2 + lookup_index * 2)?;
+        let lookup_offset = reader.read_u16()? as usize;
+        let lookup_bytes = lookup_list_bytes.get(lookup_offset..).ok_or(Error)?;
+        let mut reader = Reader::new(lookup_bytes);
+        let lookup_type = reader.read_u16()?;
+        reader.skip(2)?;
+        let subtable_count = reader.read_u16()? as usize;
+        let mut lookup = KerningLookup::default();
+        for _ in 0..subtable_count {
+            let subtable_offset = reader.read_u16()? as usize;
+            let mut subtable_bytes = lookup_bytes.get(subtable_offset..).ok_or(Error)?;
+            let mut subtable_type = lookup_type;
+            // Extension subtables point to a subtable of another type with a 32-bit offset.
+            if subtable_type == 9 {
+                let mut reader = Reader::new(subtable_bytes);
+                reader.skip(2)?;
+                subtable_type = reader.read_u16()?;
+                let extension_offset = reader.read_u32()? as usize;
+                subtable_bytes = subtable_bytes.get(extension_offset..).ok_or(Error)?;
+            }
+            if subtable_type != 2 {
+                continue;
+            }
+            if let Some(subtable) = parse_pair_adjustment_subtable(subtable_bytes)? {
+                lookup.subtables.push(subtable);
+            }
+        }
+        kerning_table.lookups.push(lookup);
+    }
+    Ok(kerning_table)
+}
+
+fn parse_pair_adjustment_subtable(bytes: &[u8]) -> Result<Option<KerningSubtable>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let coverage_offset = reader.read_u16()? as usize;
+    let value_format_1 = reader.read_u16()?;
+    let value_format_2 = reader.read_u16()?;
+    let value_record_1_size = value_record_size(value_format_1);
+    let value_record_size = value_record_1_size + value_record_size(value_format_2);
+    let coverage = parse_coverage(bytes.get(coverage_offset..).ok_or(Error)?)?;
+    Ok(match format {
+        1 => {
+            let pair_set_count = reader.read_u16()? as usize;
+            if pair_set_count != coverage.len() {
+                return Err(Error);
+            }
+            let mut pairs = Vec::new();
+            for &left in &coverage {
+                let pair_set_offset = reader.read_u16()? as usize;
+                let mut reader = Reader::new(bytes.get(pair_set_offset..).ok_or(Error)?);
+                let pair_value_count = reader.read_u16()? as usize;
+                for _ in 0..pair_value_count {
+                    let right = reader.read_u16()? as usize;
+                    let value_bytes = reader.bytes.get(..value_record_size).ok_or(Error)?;
+                    pairs.push(KerningPair {
+                        left,
+                        right,
+                        value: read_x_advance(value_bytes, value_format_1)?,
+                    });
+                    reader.skip(value_record_size)?;
+                }
+            }
+            Some(KerningSubtable::from_pairs(pairs))
+        }
+        2 => {
+            let left_class_def_offset = reader.read_u16()? as usize;
+            let right_class_def_offset = reader.read_u16()? as usize;
+            let left_class_count = reader.read_u16()? as usize;
+            let right_class_count = reader.read_u16()? as usize;
+            let mut values = Vec::with_capacity(left_class_count * right_class_count);
+            for _ in 0..left_class_count * right_class_count {
+                let value_bytes = reader.bytes.get(..value_record_size).ok_or(Error)?;
+                values.push(read_x_advance(value_bytes, value_format_1)?);
+                reader.skip(value_record_size)?;
+            }
+            let mut coverage_map = GlyphClassMap::new();
+            for glyph_index in coverage {
+                coverage_map.insert_range(glyph_index, glyph_index, 0);
+            }
+            Some(KerningSubtable::Classes {
+                coverage: coverage_map,
+                left_classes: parse_class_def(bytes.get(left_class_def_offset..).ok_or(Error)?)?,
+                right_classes: parse_class_def(bytes.get(right_class_def_offset..).ok_or(Error)?)?,
+                right_class_count,
+                values,
+            })
+        }
+        _ => None,
+    })
+}
+
+fn parse_substitution_table(
+    bytes: &[u8],
+    gdef_table_bytes: Option<&[u8]>,
+) -> Result<SubstitutionTable> {
+    let mut reader = Reader::new(bytes);
+    reader.skip(4)?;
+    let script_list_offset = reader.read_u16()? as usize;
+    let feature_list_offset = reader.read_u16()? as usize;
+    let lookup_list_offset = reader.read_u16()? as usize;
+    let script_list_bytes = bytes.get(script_list_offset..).ok_or(Error)?;
+    let feature_list_bytes = bytes.get(feature_list_offset..).ok_or(Error)?;
+    let lookup_list_bytes = bytes.get(lookup_list_offset..).ok_or(Error)?;
+    let mut substitution_table = SubstitutionTable::new();
+
+    // We only use the default language system of each script.
+    let mut reader = Reader::new(script_list_bytes);
+    let script_count = reader.read_u16()? as usize;
+    for _ in 0..script_count {
+        let tag = reader.read_u32()?.to_be_bytes();
+        let script_offset = reader.read_u16()? as usize;
+        let script_bytes = script_list_bytes.get(script_offset..).ok_or(Error)?;
+        let default_lang_sys_offset = Reader::new(script_bytes).read_u16()? as usize;
+        let mut feature_indices = Vec::new();
+        if default_lang_sys_offset != 0 {
+            let mut reader = Reader::new(script_bytes.get(default_lang_sys_offset..).ok_or(Error)?);
+            reader.skip(2)?;
+            let required_feature_index = reader.read_u16()?;
+            if required_feature_index != 0xFFFF {
+                feature_indices.push(required_feature_index as usize);
+            }
+            let feature_index_count = reader.read_u16()? as usize;
+            for _ in 0..feature_index_count {
+                feature_indices.push(reader.read_u16()? as usize);
+            }
+        }
+        substitution_table.scripts.push(SubstitutionScript {
+            tag,
+            feature_indices,
+        });
+    }
+
+    let mut reader = Reader::new(feature_list_bytes);
+    let feature_count = reader.read_u16()? as usize;
+    for _ in 0..feature_count {
+        let tag = reader.read_u32()?.to_be_bytes();
+        let feature_offset = reader.read_u16()? as usize;
+        let mut reader = Reader::new(feature_list_bytes.get(feature_offset..).ok_or(Error)?);
+        reader.skip(2)?;
+        let lookup_index_count = reader.read_u16()? as usize;
+        let mut lookup_indices = Vec::with_capacity(lookup_index_count);
+        for _ in 0..lookup_index_count {
+            lookup_indices.push(reader.read_u16()? as usize);
+        }
+        substitution_table.features.push(SubstitutionFeature {
+            tag,
+            lookup_indices,
+        });
+    }
+
+    let mut reader = Reader::new(lookup_list_bytes);
+    let lookup_count = reader.read_u16()? as usize;
+    for _ in 0..lookup_count {
+        let lookup_offset = reader.read_u16()? as usize;
+        let lookup_bytes = lookup_list_bytes.get(lookup_offset..).ok_or(Error)?;
+        let mut reader = Reader::new(lookup_bytes);
+        let lookup_type = reader.read_u16()?;
+        let flags = reader.read_u16()?;
+        let subtable_count = reader.read_u16()? as usize;
+        let mut lookup = SubstitutionLookup {
+            flags,
+            subtables: Vec::with_capacity(subtable_count),
+        };
+        for _ in 0..subtable_count {
+            let subtable_offset = reader.read_u16()? as usize;
+            let mut subtable_bytes = lookup_bytes.get(subtable_offset..).ok_or(Error)?;
+            let mut subtable_type = lookup_type;
+            // Extension subtables point to a subtable of another type with a 32-bit offset.
+            if subtable_type == 7 {
+                let mut reader = Reader::new(subtable_bytes);
+                reader.skip(2)?;
+                subtable_type = reader.read_u16()?;
+                let extension_offset = reader.read_u32()? as usize;
+                subtable_bytes = subtable_bytes.get(extension_offset..).ok_or(Error)?;
+            }
+            // Multiple, alternate and reverse chaining substitutions are not supported.
+            let subtable = match subtable_type {
+                1 => parse_single_substitution_subtable(subtable_bytes)?,
+                4 => parse_ligature_substitution_subtable(subtable_bytes)?,
+                5 => parse_context_substitution_subtable(subtable_bytes)?,
+                6 => parse_chained_context_substitution_subtable(subtable_bytes)?,
+                _ => None,
+            };
+            if let Some(subtable) = subtable {
+                lookup.subtables.push(subtable);
+            }
+        }
+        substitution_table.lookups.push(lookup);
+    }
+
+    if let Some(bytes) = gdef_table_bytes {
+        let mut reader = Reader::new(bytes);
+        reader.skip(4)?;
+        let glyph_class_def_offset = reader.read_u16()? as usize;
+        if glyph_class_def_offset != 0 {
+            substitution_table.glyph_classes =
+                parse_class_def(bytes.get(glyph_class_def_offset..).ok_or(Error)?)?;
+        }
+    }
+    Ok(substitution_table)
+}
+
+fn parse_single_substitution_subtable(bytes: &[u8]) -> Result<Option<SubstitutionSubtable>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let coverage = parse_coverage(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+    let mut substitutions = Vec::with_capacity(coverage.len());
+    match format {
+        1 => {
+            let delta_glyph_id = reader.read_i16()?;
+            for glyph_index in coverage {
+                let substitute = (glyph_index as u16).wrapping_add(delta_glyph_id as u16);
+                substitutions.push((glyph_index, substitute as usize));
+            }
+        }
+        2 => {
+            let glyph_count = reader.read_u16()? as usize;
+            if glyph_count != coverage.len() {
+                return Err(Error);
+            }
+            for glyph_index in coverage {
+                substitutions.push((glyph_index, reader.read_u16()? as usize));
+            }
+        }
+        _ => return Ok(None),
+    }
+    substitutions.sort_unstable();
+    substitutions.dedup_by_key(|&mut (glyph_index, _)| glyph_index);
+    Ok(Some(SubstitutionSubtable::Single(substitutions)))
+}
+
+fn parse_ligature_substitution_subtable(bytes: &[u8]) -> Result<Option<SubstitutionSubtable>> {
+    let mut reader = Reader::new(bytes);
+    if reader.read_u16()? != 1 {
+        return Ok(None);
+    }
+    let coverage = parse_coverage(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+    let ligature_set_count = reader.read_u16()? as usize;
+    if ligature_set_count != coverage.len() {
+        return Err(Error);
+    }
+    let mut ligatures = Vec::new();
+    for first in coverage {
+        let ligature_set_bytes = bytes.get(reader.read_u16()? as usize..).ok_or(Error)?;
+        let mut reader = Reader::new(ligature_set_bytes);
+        let ligature_count = reader.read_u16()? as usize;
+        for _ in 0..ligature_count {
+            let mut reader = Reader::new(ligature_set_bytes.get(reader.read_u16()? as usize..).ok_or(Error)?);
+            let glyph_index = reader.read_u16()? as usize;
+            let component_count = reader.read_u16()? as usize;
+            let mut components = Vec::with_capacity(component_count.saturating_sub(1));
+            for _ in 1..component_count {
+                components.push(reader.read_u16()? as usize);
+            }
+            ligatures.push(Ligature {
+                first,
+                components,
+                glyph_index,
+            });
+        }
+    }
+    // The sort is stable, so ligatures with the same first component stay in order of preference.
+    ligatures.sort_by_key(|ligature| ligature.first);
+    Ok(Some(SubstitutionSubtable::Ligature(ligatures)))
+}
+
+fn parse_context_substitution_subtable(bytes: &[u8]) -> Result<Option<SubstitutionSubtable>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    Ok(Some(match format {
+        1 | 2 => {
+            let coverage_offset = reader.read_u16()? as usize;
+            let mut context = ContextSubtable::default();
+            if format == 2 {
+                context.input_classes = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+                context.rule_sets_by_class = true;
+            }
+            context.coverage = parse_coverage_map(bytes.get(coverage_offset..).ok_or(Error)?)?;
+            let rule_set_count = reader.read_u16()? as usize;
+            for _ in 0..rule_set_count {
+                let rule_set_offset = reader.read_u16()? as usize;
+                let mut rules = Vec::new();
+                if rule_set_offset != 0 {
+                    let rule_set_bytes = bytes.get(rule_set_offset..).ok_or(Error)?;
+                    let mut reader = Reader::new(rule_set_bytes);
+                    let rule_count = reader.read_u16()? as usize;
+                    for _ in 0..rule_count {
+                        let mut reader = Reader::new(rule_set_bytes.get(reader.read_u16()? as usize..).ok_or(Error)?);
+                        let glyph_count = reader.read_u16()? as usize;
+                        let lookup_count = reader.read_u16()? as usize;
+                        let input = read_glyph_matchers(&mut reader, glyph_count.saturating_sub(1), format)?;
+                        rules.push(ContextRule {
+                            input,
+                            lookups: read_sequence_lookups(&mut reader, lookup_count)?,
+                            ..ContextRule::default()
+                        });
+                    }
+                }
+                context.rule_sets.push(rules);
+            }
+            SubstitutionSubtable::Context(context)
+        }
+        3 => {
+            let glyph_count = reader.read_u16()? as usize;
+            let lookup_count = reader.read_u16()? as usize;
+            let mut coverages = read_coverages(bytes, &mut reader, glyph_count)?;
+            SubstitutionSubtable::Context(ContextSubtable {
+                coverage: first_coverage(&mut coverages)?,
+                rule_sets: vec![vec![ContextRule {
+                    input: coverages,
+                    lookups: read_sequence_lookups(&mut reader, lookup_count)?,
+                    ..ContextRule::default()
+                }]],
+                ..ContextSubtable::default()
+            })
+        }
+        _ => return Ok(None),
+    }))
+}
+
+fn parse_chained_context_substitution_subtable(bytes: &[u8]) -> Result<Option<SubstitutionSubtable>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    Ok(Some(match format {
+        1 | 2 => {
+            let coverage_offset = reader.read_u16()? as usize;
+            let mut context = ContextSubtable::default();
+            if format == 2 {
+                context.backtrack_classes = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+                context.input_classes = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+                context.lookahead_classes = parse_class_def(bytes.get(reader.read_u16()? as usize..).ok_or(Error)?)?;
+                context.rule_sets_by_class = true;
+            }
+            context.coverage = parse_coverage_map(bytes.get(coverage_offset..).ok_or(Error)?)?;
+            let rule_set_count = reader.read_u16()? as usize;
+            for _ in 0..rule_set_count {
+                let rule_set_offset = reader.read_u16()? as usize;
+                let mut rules = Vec::new();
+                if rule_set_offset != 0 {
+                    let rule_set_bytes = bytes.get(rule_set_offset..).ok_or(Error)?;
+                    let mut reader = Reader::new(rule_set_bytes);
+                    let rule_count = reader.read_u16()? as usize;
+                    for _ in 0..rule_count {
+                        let mut reader = Reader::new(rule_set_bytes.get(reader.read_u16()? as usize..).ok_or(Error)?);
+                        let backtrack_count = reader.read_u16()? as usize;
+                        let backtrack = read_glyph_matchers(&mut reader, backtrack_count, format)?;
+                        let input_count = reader.read_u16()? as usize;
+                        let input = read_glyph_matchers(&mut reader, input_count.saturating_sub(1), format)?;
+                        let lookahead_count = reader.read_u16()? as usize;
+                        let lookahead = read_glyph_matchers(&mut reader, lookahead_count, format)?;
+                        let lookup_count = reader.read_u16()? as usize;
+                        rules.push(ContextRule {
+                            backtrack,
+                            input,
+                            lookahead,
+                            lookups: read_sequence_lookups(&mut reader, lookup_count)?,
+                        });
+                    }
+                }
+                context.rule_sets.push(rules);
+            }
+            SubstitutionSubtable::Context(context)
+        }
+        3 => {
+            let backtrack_count = reader.read_u16()? as usize;
+            let backtrack = read_coverages(bytes, &mut reader, backtrack_count)?;
+            let input_count = reader.read_u16()? as usize;
+            let mut input = read_coverages(bytes, &mut reader, input_count)?;
+            let lookahead_count = reader.read_u16()? as usize;
+            let lookahead = read_coverages(bytes, &mut reader, lookahead_count)?;
+            let lookup_count = reader.read_u16()? as usize;
+            SubstitutionSubtable::Context(ContextSubtable {
+                coverage: first_coverage(&mut input)?,
+                rule_sets: vec![vec![ContextRule {
+                    backtrack,
+                    input,
+                    lookahead,
+                    lookups: read_sequence_lookups(&mut reader, lookup_count)?,
+                }]],
+                ..ContextSubtable::default()
+            })
+        }
+        _ => return Ok(None),
+    }))
+}
+
+// Reads a sequence of glyph ids (format 1) or classes (format 2) in a context rule.
+fn read_glyph_matchers(reader: &mut Reader, count: usize, format: u16) -> Result<Vec<GlyphMatcher>> {
+    let mut matchers = Vec::with_capacity(count);
+    for _ in 0..count {
+        let value = reader.read_u16()? as usize;
+        matchers.push(if format == 1 {
+            GlyphMatcher::Glyph(value)
+        } else {
+            GlyphMatcher::Class(value)
+        });
+    }
+    Ok(matchers)
+}
+
+// Reads a sequence of coverage offsets in a format 3 context subtable.
+fn read_coverages(bytes: &[u8], reader: &mut Reader, count: usize) -> Result<Vec<GlyphMatcher>> {
+    let mut coverages = Vec::with_capacity(count);
+    for _ in 0..count {
+        let coverage_offset = reader.read_u16()? as usize;
+        coverages.push(GlyphMatcher::Coverage(parse_coverage_map(
+            bytes.get(coverage_offset..).ok_or(Error)?,
+        )?));
+    }
+    Ok(coverages)
+}
+
+// The first glyph of the input sequence of a format 3 context subtable determines whether the
+// subtable applies, so its coverage becomes that of the subtable, with a single rule set.
+fn first_coverage(input: &mut Vec<GlyphMatcher>) -> Result<GlyphClassMap> {
+    if input.is_empty() {
+        return Err(Error);
+    }
+    match input.remove(0) {
+        GlyphMatcher::Coverage(coverage) => {
+            let mut coverage_map = GlyphClassMap::new();
+            for (first_glyph_index, last_glyph_index, _) in coverage.ranges() {
+                coverage_map.insert_range(first_glyph_index, last_glyph_index, 0);
+            }
+            Ok(coverage_map)
+        }
+        _ => Err(Error),
+    }
+}
+
+fn read_sequence_lookups(reader: &mut Reader, count: usize) -> Result<Vec<(usize, usize)>> {
+    let mut lookups = Vec::with_capacity(count);
+    for _ in 0..count {
+        lookups.push((reader.read_u16()? as usize, reader.read_u16()? as usize));
+    }
+    Ok(lookups)
+}
+
+// A value record consists of one 16-bit field for every bit that is set in the low byte of its
+// format.
+fn value_record_size(value_format: u16) -> usize {
+    (value_format & 0xFF).count_ones() as usize * 2
+}
+
+// The horizontal advance of the first glyph in a pair is what we use as its kerning value. It
+// comes after the horizontal and vertical placement, if those are present.
+fn read_x_advance(bytes: &[u8], value_format: u16) -> Result<f32> {
+    if value_format & 0x4 == 0 {
+        return Ok(0.0);
+    }
+    let mut reader = Reader::new(bytes);
+    reader.skip((value_format & 0x3).count_ones() as usize * 2)?;
+    Ok(reader.read_i16()? as f32)
+}
+
+// Returns the covered glyph indices, in order of coverage index.
+fn parse_coverage(bytes: &[u8]) -> Result<Vec<usize>> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let mut glyph_indices = Vec::new();
+    match format {
+        1 => {
+            let glyph_count = reader.read_u16()? as usize;
+            for _ in 0..glyph_count {
+                glyph_indices.push(reader.read_u16()? as usize);
+            }
+        }
+        2 => {
+            let range_count = reader.read_u16()? as usize;
+            for _ in 0..range_count {
+                let start_glyph_id = reader.read_u16()? as usize;
+                let end_glyph_id = reader.read_u16()? as usize;
+                let start_coverage_index = reader.read_u16()? as usize;
+                if start_coverage_index != glyph_indices.len() || start_glyph_id > end_glyph_id {
+                    return Err(Error);
+                }
+                glyph_indices.extend(start_glyph_id..=end_glyph_id);
+            }
+        }
+        _ => return Err(Error),
+    }
+    Ok(glyph_indices)
+}
+
+// Returns a map from the covered glyph indices to their coverage indices.
+fn parse_coverage_map(bytes: &[u8]) -> Result<GlyphClassMap> {
+    let mut coverage_map = GlyphClassMap::new();
+    for (coverage_index, glyph_index) in parse_coverage(bytes)?.into_iter().enumerate() {
+        coverage_map.insert_range(glyph_index, glyph_index, coverage_index);
+    }
+    Ok(coverage_map)
+}
+
+fn parse_class_def(bytes: &[u8]) -> Result<GlyphClassMap> {
+    let mut reader = Reader::new(bytes);
+    let format = reader.read_u16()?;
+    let mut class_map = GlyphClassMap::new();
+    match format {
+        1 => {
+            let start_glyph_id = reader.read_u16()? as usize;
+            let glyph_count = reader.read_u16()? as usize;
+            for glyph_index in start_glyph_id..start_glyph_id + glyph_count {
+                let class = reader.read_u16()? as usize;
+                if class != 0 {
+                    class_map.insert_range(glyph_index, glyph_index, class);
+                }
+            }
+        }
+        2 => {
+            let class_range_count = reader.read_u16()? as usize;
+            for _ in 0..class_range_count {
+                let start_glyph_id = reader.read_u16()? as usize;
+                let end_glyph_id = reader.read_u16()? as usize;
+                let class = reader.read_u16()? as usize;
+                class_map.insert_range(start_glyph_id, end_glyph_id, class);
+            }
+        }
+        _ => return Err(Error),
+    }
+    Ok(class_map)
+}
+
+fn parse_horizontal_metrics(
+    hmtx_table_bytes: &[u8],
+    advance_width_count: usize,
+    index: usize,
+) -> Result<HorizontalMetrics> {
+    let mut reader = Reader::new(hmtx_table_bytes);
+    if index < advance_width_count {
+        reader.skip(index * 4)?;
+        Ok(HorizontalMetrics {
+            advance_width: reader.read_u16()? as f32,
+            left_side_bearing: reader.read_i16()? as f32,
+        })
+    } else {
+        if advance_width_count == 0 {
+            return Err(Error);
+        }
+        reader.skip((advance_width_count - 1) * 4)?;
+        let advance_width = reader.read_u16()? as f32;
+        reader.skip(2)?;
+        reader.skip((index - advance_width_count) * 2)?;
+        Ok(HorizontalMetrics {
+            advance_width,
+            left_side_bearing: reader.read_i16()? as f32,
+        })
+    }
+}
+
+// Unlike the `glyf` table, the `CFF ` and `CFF2` tables don't store the bounds of each glyph, so
+// we use the bounds of its control points instead.
+fn parse_cff_glyphs(
+    outlines: Vec<Outline>,
+    glyph_count: usize,
+    advance_width_count: usize,
+    hmtx_table_bytes: &[u8],
+) -> Result<Vec<Glyph>> {
+    if outlines.len() != glyph_count {
+        return Err(Error);
+    }
+    outlines
+        .into_iter()
+        .enumerate()
+        .map(|(index, outline)| {
+            let mut points = outline.points().iter().map(|point| point.point);
+            let bounds = match points.next() {
+                Some(first_point) => points.fold(
+                    Rectangle::new(first_point, first_point),
+                    |bounds, point| {
+                        Rectangle::new(
+                            Point::new(bounds.p_min.x.min(point.x), bounds.p_min.y.min(point.y)),
+                            Point::new(bounds.p_max.x.max(point.x), bounds.p_max.y.max(point.y)),
+                        )
+                    },
+                ),
+                None => Rectangle::default(),
+            };
+            Ok(Glyph {
+                horizontal_metrics: parse_horizontal_metrics(
+                    hmtx_table_bytes,
+                    advance_width_count,
+                    index,
+                )?,
+                bounds,
+                outline,
+            })
+        })
+        .collect()
 }
@@ -1,18 +1,533 @@
+use makepad_geometry::Point;
+use makepad_path::PathCommand;
 use makepad_ttf_parser;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+fn resources_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../resources")
+}
+
+// Returns a copy of the given font in which the table with the given tag is replaced by the given
+// one. The new table is appended to the end of the font, and the table directory is updated to
+// point to it.
+fn with_table(font_bytes: &[u8], tag: &[u8; 4], table_bytes: &[u8]) -> Vec<u8> {
+    let mut bytes = font_bytes.to_vec();
+    bytes.resize((bytes.len() + 3) & !3, 0);
+    let offset = bytes.len() as u32;
+    bytes.extend_from_slice(table_bytes);
+    let record = find_table_record(&bytes, tag);
+    bytes[record + 8..record + 12].copy_from_slice(&offset.to_be_bytes());
+    bytes[record + 12..record + 16].copy_from_slice(&(table_bytes.len() as u32).to_be_bytes());
+    bytes
+}
+
+fn with_cmap_table(font_bytes: &[u8], cmap_table_bytes: &[u8]) -> Vec<u8> {
+    with_table(font_bytes, b"cmap", cmap_table_bytes)
+}
+
+fn find_table_record(font_bytes: &[u8], tag: &[u8; 4]) -> usize {
+    let table_count = u16::from_be_bytes([font_bytes[4], font_bytes[5]]) as usize;
+    (0..table_count)
+        .map(|index| 12 + index * 16)
+        .find(|&record| &font_bytes[record..record + 4] == tag)
+        .unwrap_or_else(|| panic!("font has no {:?} table", tag))
+}
+
+// Builds a cmap table from a list of encoding records and their subtables.
+fn cmap_table(subtables: &[(u16, u16, Vec<u8>)]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0u16.to_be_bytes());
+    bytes.extend_from_slice(&(subtables.len() as u16).to_be_bytes());
+    let mut offset = 4 + subtables.len() * 8;
+    for (platform_id, encoding_id, subtable) in subtables {
+        bytes.extend_from_slice(&platform_id.to_be_bytes());
+        bytes.extend_from_slice(&encoding_id.to_be_bytes());
+        bytes.extend_from_slice(&(offset as u32).to_be_bytes());
+        offset += subtable.len();
+    }
+    for (_, _, subtable) in subtables {
+        bytes.extend_from_slice(subtable);
+    }
+    bytes
+}
+
+fn liberation_mono() -> Vec<u8> {
+    fs::read(resources_dir().join("LiberationMono-Regular.ttf")).unwrap()
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]])
+}
+
+fn push_u24(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_be_bytes()[1..]);
+}
 
 #[test]
 fn main() {
-    for entry in fs::read_dir(Path::new(env!("CARGO_MANIFEST_DIR")).join("../../../resources")).unwrap() {
+    for entry in fs::read_dir(resources_dir()).unwrap() {
         let path = entry.unwrap().path();
         if path.extension().unwrap() != "ttf" {
             continue;
         }
         println!("{}", path.file_stem().unwrap().to_str().unwrap());
         let font = makepad_ttf_parser::parse_ttf(&fs::read(path).unwrap()).unwrap();
-        for char_code in 0..font.char_code_to_glyph_index_map.len() {
-            assert!(font.char_code_to_glyph_index_map[char_code] <= font.glyphs.len());
+        for (_, glyph_index) in font.char_code_to_glyph_index_map.iter() {
+            assert!(glyph_index < font.glyphs.len());
+        }
+        for c in '!'..='~' {
+            assert_ne!(font.glyph_index(c), 0);
+        }
+    }
+}
+
+#[test]
+fn format_4_agrees_with_format_6() {
+    // Ubuntu has both a Windows Unicode BMP subtable in format 4, and a Macintosh Roman subtable
+    // in format 6. Both of them should map ASCII to the same glyphs.
+    let font_bytes = fs::read(resources_dir().join("Ubuntu-R.ttf")).unwrap();
+    let font = makepad_ttf_parser::parse_ttf(&font_bytes).unwrap();
+    let cmap_table_bytes = {
+        let record = find_table_record(&font_bytes, b"cmap");
+        let offset = read_u32(&font_bytes, record + 8);
+        let length = read_u32(&font_bytes, record + 12);
+        &font_bytes[offset as usize..(offset + length) as usize]
+    };
+    let subtable_count = u16::from_be_bytes([cmap_table_bytes[2], cmap_table_bytes[3]]) as usize;
+    let subtable = (0..subtable_count)
+        .map(|index| &cmap_table_bytes[4 + index * 8..12 + index * 8])
+        .find(|record| record[0..4] == [0, 1, 0, 0])
+        .map(|record| {
+            let offset = read_u32(record, 4) as usize;
+            cmap_table_bytes[offset..].to_vec()
+        })
+        .unwrap();
+    let mac_font = makepad_ttf_parser::parse_ttf(&with_cmap_table(
+        &font_bytes,
+        &cmap_table(&[(1, 0, subtable)]),
+    ))
+    .unwrap();
+    for c in ' '..='~' {
+        assert_eq!(font.glyph_index(c), mac_font.glyph_index(c), "{:?}", c);
+    }
+}
+
+#[test]
+fn format_0() {
+    let mut subtable = Vec::new();
+    subtable.extend_from_slice(&0u16.to_be_bytes());
+    subtable.extend_from_slice(&262u16.to_be_bytes());
+    subtable.extend_from_slice(&0u16.to_be_bytes());
+    subtable.extend((0..=255u8).map(|code| if code == b'A' { 36 } else { 0 }));
+    let font = makepad_ttf_parser::parse_ttf(&with_cmap_table(
+        &liberation_mono(),
+        &cmap_table(&[(1, 0, subtable)]),
+    ))
+    .unwrap();
+    assert_eq!(font.glyph_index('A'), 36);
+    assert_eq!(font.glyph_index('B'), 0);
+}
+
+#[test]
+fn format_12_and_14() {
+    let mut subtable_12 = Vec::new();
+    let groups = [(0x41, 0x5A, 36), (0x1F600, 0x1F602, 100)];
+    subtable_12.extend_from_slice(&12u16.to_be_bytes());
+    subtable_12.extend_from_slice(&0u16.to_be_bytes());
+    subtable_12.extend_from_slice(&(16 + groups.len() as u32 * 12).to_be_bytes());
+    subtable_12.extend_from_slice(&0u32.to_be_bytes());
+    subtable_12.extend_from_slice(&(groups.len() as u32).to_be_bytes());
+    for &(start_char_code, end_char_code, start_glyph_id) in &groups {
+        subtable_12.extend_from_slice(&(start_char_code as u32).to_be_bytes());
+        subtable_12.extend_from_slice(&(end_char_code as u32).to_be_bytes());
+        subtable_12.extend_from_slice(&(start_glyph_id as u32).to_be_bytes());
+    }
+
+    let mut subtable_14 = Vec::new();
+    subtable_14.extend_from_slice(&14u16.to_be_bytes());
+    subtable_14.extend_from_slice(&38u32.to_be_bytes());
+    subtable_14.extend_from_slice(&1u32.to_be_bytes());
+    push_u24(&mut subtable_14, 0xFE0F);
+    subtable_14.extend_from_slice(&21u32.to_be_bytes());
+    subtable_14.extend_from_slice(&29u32.to_be_bytes());
+    // Default UVS table
+    subtable_14.extend_from_slice(&1u32.to_be_bytes());
+    push_u24(&mut subtable_14, 0x41);
+    subtable_14.push(1);
+    // Non-default UVS table
+    subtable_14.extend_from_slice(&1u32.to_be_bytes());
+    push_u24(&mut subtable_14, 0x1F600);
+    subtable_14.extend_from_slice(&200u16.to_be_bytes());
+    assert_eq!(subtable_14.len(), 38);
+
+    let font = makepad_ttf_parser::parse_ttf(&with_cmap_table(
+        &liberation_mono(),
+        &cmap_table(&[(3, 10, subtable_12), (0, 5, subtable_14)]),
+    ))
+    .unwrap();
+    let map = &font.char_code_to_glyph_index_map;
+    assert_eq!(font.glyph_index('A'), 36);
+    assert_eq!(font.glyph_index('Z'), 61);
+    assert_eq!(font.glyph_index('a'), 0);
+    assert_eq!(font.glyph_index('\u{1F600}'), 100);
+    assert_eq!(font.glyph_index('\u{1F602}'), 102);
+    assert_eq!(font.glyph_index('\u{1F603}'), 0);
+    assert_eq!(map.get_variant(0x41, 0xFE0F), Some(36));
+    assert_eq!(map.get_variant(0x42, 0xFE0F), Some(37));
+    assert_eq!(map.get_variant(0x43, 0xFE0F), None);
+    assert_eq!(map.get_variant(0x1F600, 0xFE0F), Some(200));
+    assert_eq!(map.get_variant(0x1F600, 0xFE0E), None);
+}
+
+#[test]
+fn kerning() {
+    for name in &["Ubuntu-R", "IBMPlexSans-Text", "IBMPlexSans-SemiBold"] {
+        let font_bytes = fs::read(resources_dir().join(format!("{}.ttf", name))).unwrap();
+        let font = makepad_ttf_parser::parse_ttf(&font_bytes).unwrap();
+        for &(left, right) in &[('A', 'V'), ('T', 'o')] {
+            let kerning = font.kerning(font.glyph_index(left), font.glyph_index(right));
+            assert!(kerning < 0.0, "{} {}{} {}", name, left, right, kerning);
         }
+        assert_eq!(font.kerning(font.glyph_index('a'), font.glyph_index('b')), 0.0);
+    }
+    let font = makepad_ttf_parser::parse_ttf(&liberation_mono()).unwrap();
+    assert!(font.kerning_table.is_empty());
+}
+
+fn shape(font: &makepad_font::TTFFont, text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut glyphs = Vec::new();
+    for (range, script) in makepad_font::script_runs(&chars, 0..chars.len()) {
+        font.shape(&chars, range, script, false, &mut glyphs);
     }
+    glyphs
+        .iter()
+        .map(|glyph| (glyph.glyph_index, glyph.char_index))
+        .collect()
+}
+
+#[test]
+fn substitution() {
+    for name in &["Ubuntu-R", "IBMPlexSans-Text", "IBMPlexSans-SemiBold"] {
+        let font_bytes = fs::read(resources_dir().join(format!("{}.ttf", name))).unwrap();
+        let font = makepad_ttf_parser::parse_ttf(&font_bytes).unwrap();
+        assert!(!font.substitution_table.is_empty());
+        let glyphs = shape(&font, "fix");
+        assert_eq!(glyphs.len(), 2, "{}", name);
+        assert_ne!(glyphs[0].0, font.glyph_index('f'));
+        assert_eq!(glyphs[0].1, 0);
+        assert_eq!(glyphs[1], (font.glyph_index('x'), 2));
+        let glyphs = shape(&font, "ab");
+        assert_eq!(glyphs, vec![(font.glyph_index('a'), 0), (font.glyph_index('b'), 1)]);
+    }
+    let font = makepad_ttf_parser::parse_ttf(&liberation_mono()).unwrap();
+    assert!(font.substitution_table.is_empty());
+    let glyphs = shape(&font, "fix");
+    assert_eq!(
+        glyphs,
+        vec![(font.glyph_index('f'), 0), (font.glyph_index('i'), 1), (font.glyph_index('x'), 2)]
+    );
+}
+
+// Returns a copy of LiberationMono in which the TrueType outlines are replaced by the given table
+// with CFF outlines for the given number of glyphs, the first of which is mapped to by 'A'.
+fn with_cff_table(tag: &[u8; 4], table_bytes: &[u8], glyph_count: u16) -> Vec<u8> {
+    let mut cmap_subtable = Vec::new();
+    cmap_subtable.extend_from_slice(&0u16.to_be_bytes());
+    cmap_subtable.extend_from_slice(&262u16.to_be_bytes());
+    cmap_subtable.extend_from_slice(&0u16.to_be_bytes());
+    cmap_subtable.extend((0..=255u8).map(|code| if code == b'A' { 1 } else { 0 }));
+    let mut bytes = with_cmap_table(&liberation_mono(), &cmap_table(&[(1, 0, cmap_subtable)]));
+    bytes[0..4].copy_from_slice(b"OTTO");
+    let glyf_record = find_table_record(&bytes, b"glyf");
+    bytes[glyf_record..glyf_record + 4].copy_from_slice(tag);
+    let loca_record = find_table_record(&bytes, b"loca");
+    bytes[loca_record..loca_record + 4].copy_from_slice(b"xxxx");
+    let bytes = with_table(&bytes, tag, table_bytes);
+    let maxp_record = find_table_record(&bytes, b"maxp");
+    let mut maxp_table_bytes = bytes[read_u32(&bytes, maxp_record + 8) as usize..][..6].to_vec();
+    maxp_table_bytes[0..4].copy_from_slice(&0x00005000u32.to_be_bytes());
+    maxp_table_bytes[4..6].copy_from_slice(&glyph_count.to_be_bytes());
+    with_table(&bytes, b"maxp", &maxp_table_bytes)
+}
+
+fn cff_index(objects: &[Vec<u8>], is_cff2: bool) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    if is_cff2 {
+        bytes.extend_from_slice(&(objects.len() as u32).to_be_bytes());
+    } else {
+        bytes.extend_from_slice(&(objects.len() as u16).to_be_bytes());
+    }
+    if objects.is_empty() {
+        return bytes;
+    }
+    bytes.push(4);
+    let mut offset = 1u32;
+    bytes.extend_from_slice(&offset.to_be_bytes());
+    for object in objects {
+        offset += object.len() as u32;
+        bytes.extend_from_slice(&offset.to_be_bytes());
+    }
+    for object in objects {
+        bytes.extend_from_slice(object);
+    }
+    bytes
+}
+
+// Encodes a dict entry, using the 5-byte encoding for all operands so that the size of a dict
+// doesn't depend on the offsets in it.
+fn cff_dict_entry(bytes: &mut Vec<u8>, operands: &[i32], op: &[u8]) {
+    for operand in operands {
+        bytes.push(29);
+        bytes.extend_from_slice(&operand.to_be_bytes());
+    }
+    bytes.extend_from_slice(op);
+}
+
+// Encodes a charstring from a list of numbers and operators.
+fn char_string(tokens: &[Token]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for token in tokens {
+        match *token {
+            Token::Num(value) => {
+                bytes.push(28);
+                bytes.extend_from_slice(&value.to_be_bytes());
+            }
+            Token::Op(op) => bytes.push(op),
+            Token::Escaped(op) => bytes.extend_from_slice(&[12, op]),
+            Token::Mask(mask) => bytes.push(mask),
+        }
+    }
+    bytes
+}
+
+#[derive(Clone, Copy)]
+enum Token {
+    Num(i16),
+    Op(u8),
+    Escaped(u8),
+    Mask(u8),
+}
+
+fn outline_points(font: &makepad_font::TTFFont, index: usize) -> Vec<(bool, f32, f32)> {
+    font.glyphs[index]
+        .outline
+        .points()
+        .iter()
+        .map(|point| (point.is_on_curve, point.point.x, point.point.y))
+        .collect()
+}
+
+#[test]
+fn cff() {
+    use makepad_internal_iter::InternalIterator;
+    use Token::*;
+
+    let char_strings = vec![
+        // A .notdef glyph with only a width.
+        char_string(&[Num(500), Op(14)]),
+        // A rectangle, with a width before the first moveto.
+        char_string(&[
+            Num(600), Num(100), Num(0), Op(21),
+            Num(400), Num(400), Num(-400), Op(6),
+            Op(14),
+        ]),
+        // A shape that uses hints, a local and a global subroutine, and a curve back to the
+        // first point.
+        char_string(&[
+            Num(0), Num(10), Num(20), Num(10), Op(18),
+            Num(30), Num(10), Op(19), Mask(0xF0),
+            Num(100), Num(0), Op(21),
+            Num(-107), Op(10),
+            Num(-107), Op(29),
+            Num(-200), Op(6),
+            Op(14),
+        ]),
+        // A curve with hhcurveto and hflex.
+        char_string(&[
+            Num(0), Num(0), Op(21),
+            Num(5), Num(10), Num(20), Num(10), Num(10), Op(27),
+            Num(10), Num(10), Num(-10), Num(10), Num(10), Num(10), Num(10), Escaped(34),
+            Num(-100), Num(-15), Op(5),
+            Op(14),
+        ]),
+    ];
+    let local_subrs = vec![char_string(&[
+        Num(0), Num(55), Num(45), Num(45), Num(55), Num(0), Op(8),
+        Op(11),
+    ])];
+    let global_subrs = vec![char_string(&[
+        Num(55), Num(45), Num(-45), Num(-55), Op(31),
+        Op(11),
+    ])];
+
+    let header = vec![1, 0, 4, 4];
+    let name_index = cff_index(&[b"Test".to_vec()], false);
+    let string_index = cff_index(&[], false);
+    let global_subr_index = cff_index(&global_subrs, false);
+    let char_strings_index = cff_index(&char_strings, false);
+    // The local subroutines directly follow the private dict, which consists of a single entry.
+    let mut private_dict = Vec::new();
+    cff_dict_entry(&mut private_dict, &[6], &[19]);
+    assert_eq!(private_dict.len(), 6);
+    let local_subr_index = cff_index(&local_subrs, false);
+    let top_dict_len = 5 + 1 + 5 + 5 + 1;
+    let top_dict_index_len = cff_index(&[vec![0; top_dict_len]], false).len();
+    let char_strings_offset = header.len()
+        + name_index.len()
+        + top_dict_index_len
+        + string_index.len()
+        + global_subr_index.len();
+    let private_dict_offset = char_strings_offset + char_strings_index.len();
+    let mut top_dict = Vec::new();
+    cff_dict_entry(&mut top_dict, &[char_strings_offset as i32], &[17]);
+    cff_dict_entry(
+        &mut top_dict,
+        &[private_dict.len() as i32, private_dict_offset as i32],
+        &[18],
+    );
+    assert_eq!(top_dict.len(), top_dict_len);
+
+    let mut cff_table_bytes = header;
+    cff_table_bytes.extend(name_index);
+    cff_table_bytes.extend(cff_index(&[top_dict], false));
+    cff_table_bytes.extend(string_index);
+    cff_table_bytes.extend(global_subr_index);
+    cff_table_bytes.extend(char_strings_index);
+    cff_table_bytes.extend(private_dict);
+    cff_table_bytes.extend(local_subr_index);
+
+    let font = makepad_ttf_parser::parse_ttf(&with_cff_table(b"CFF ", &cff_table_bytes, 4)).unwrap();
+    assert_eq!(font.glyphs.len(), 4);
+    assert_eq!(font.glyph_index('A'), 1);
+    assert!(font.glyphs[0].outline.points().is_empty());
+    assert!(font.glyphs[1].outline.is_cubic());
+    assert_eq!(
+        outline_points(&font, 1),
+        [
+            (true, 100.0, 0.0),
+            (true, 500.0, 0.0),
+            (true, 500.0, 400.0),
+            (true, 100.0, 400.0),
+        ]
+    );
+    assert_eq!(font.glyphs[1].bounds.p_min.x, 100.0);
+    assert_eq!(font.glyphs[1].bounds.p_max.y, 400.0);
+    assert_eq!(
+        outline_points(&font, 2),
+        [
+            (true, 100.0, 0.0),
+            (false, 100.0, 55.0),
+            (false, 145.0, 100.0),
+            (true, 200.0, 100.0),
+            (false, 255.0, 100.0),
+            (false, 300.0, 55.0),
+            (true, 300.0, 0.0),
+        ]
+    );
+    let mut commands = Vec::new();
+    font.glyphs[2].outline.commands().for_each(&mut |command| {
+        commands.push(command);
+        true
+    });
+    assert_eq!(
+        commands,
+        [
+            PathCommand::MoveTo(Point::new(100.0, 0.0)),
+            PathCommand::CubicTo(
+                Point::new(100.0, 55.0),
+                Point::new(145.0, 100.0),
+                Point::new(200.0, 100.0)
+            ),
+            PathCommand::CubicTo(
+                Point::new(255.0, 100.0),
+                Point::new(300.0, 55.0),
+                Point::new(300.0, 0.0)
+            ),
+            PathCommand::LineTo(Point::new(100.0, 0.0)),
+            PathCommand::Close,
+        ]
+    );
+    assert_eq!(
+        outline_points(&font, 3),
+        [
+            (true, 0.0, 0.0),
+            (false, 10.0, 5.0),
+            (false, 30.0, 15.0),
+            (true, 40.0, 15.0),
+            (false, 50.0, 15.0),
+            (false, 60.0, 5.0),
+            (true, 70.0, 5.0),
+            (false, 80.0, 5.0),
+            (false, 90.0, 15.0),
+            (true, 100.0, 15.0),
+        ]
+    );
+}
+
+#[test]
+fn cff2() {
+    use Token::*;
+
+    let char_strings = vec![
+        Vec::new(),
+        // A rectangle whose first point is blended with one region, and which has no endchar.
+        char_string(&[
+            Num(100), Num(0), Num(5), Num(0), Num(2), Op(16), Op(21),
+            Num(400), Num(400), Num(-400), Op(6),
+        ]),
+    ];
+
+    let top_dict_len = 5 + 1 + 5 + 2 + 5 + 1;
+    let header_len = 5;
+    let global_subr_index = cff_index(&[], true);
+    let char_strings_index = cff_index(&char_strings, true);
+    let char_strings_offset = header_len + top_dict_len + global_subr_index.len();
+    let fd_array_offset = char_strings_offset + char_strings_index.len();
+    let private_dict_offset = fd_array_offset + cff_index(&[vec![0; 11]], true).len();
+    let mut font_dict = Vec::new();
+    cff_dict_entry(&mut font_dict, &[0, private_dict_offset as i32], &[18]);
+    let fd_array_index = cff_index(&[font_dict], true);
+    let vstore_offset = private_dict_offset;
+
+    // An item variation store with a single item variation data subtable with one region.
+    let mut vstore = Vec::new();
+    vstore.extend_from_slice(&20u16.to_be_bytes());
+    vstore.extend_from_slice(&1u16.to_be_bytes());
+    vstore.extend_from_slice(&0u32.to_be_bytes());
+    vstore.extend_from_slice(&1u16.to_be_bytes());
+    vstore.extend_from_slice(&12u32.to_be_bytes());
+    vstore.extend_from_slice(&0u16.to_be_bytes());
+    vstore.extend_from_slice(&0u16.to_be_bytes());
+    vstore.extend_from_slice(&1u16.to_be_bytes());
+    vstore.extend_from_slice(&0u16.to_be_bytes());
+    assert_eq!(vstore.len(), 2 + 20);
+
+    let mut top_dict = Vec::new();
+    cff_dict_entry(&mut top_dict, &[char_strings_offset as i32], &[17]);
+    cff_dict_entry(&mut top_dict, &[fd_array_offset as i32], &[12, 36]);
+    cff_dict_entry(&mut top_dict, &[vstore_offset as i32], &[24]);
+    assert_eq!(top_dict.len(), top_dict_len);
+
+    let mut cff2_table_bytes = vec![2, 0, header_len as u8];
+    cff2_table_bytes.extend_from_slice(&(top_dict_len as u16).to_be_bytes());
+    cff2_table_bytes.extend(top_dict);
+    cff2_table_bytes.extend(global_subr_index);
+    cff2_table_bytes.extend(char_strings_index);
+    cff2_table_bytes.extend(fd_array_index);
+    cff2_table_bytes.extend(vstore);
+
+    let font = makepad_ttf_parser::parse_ttf(&with_cff_table(b"CFF2", &cff2_table_bytes, 2)).unwrap();
+    assert_eq!(font.glyphs.len(), 2);
+    assert!(font.glyphs[0].outline.points().is_empty());
+    assert_eq!(
+        outline_points(&font, 1),
+        [
+            (true, 100.0, 0.0),
+            (true, 500.0, 0.0),
+            (true, 500.0, 400.0),
+            (true, 100.0, 400.0),
+        ]
+    );
 }
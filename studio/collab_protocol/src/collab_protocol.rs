@@ -2,6 +2,8 @@ use {
     crate::{
         makepad_editor_core::{
             delta::Delta,
+            position_set::PositionSet,
+            range_set::RangeSet,
             text::Text
         },
         makepad_live_id::*,
@@ -9,6 +11,7 @@ use {
         unix_path::UnixPathBuf,
         unix_str::UnixString,
     },
+    std::collections::HashMap,
 };
 
 /// Types for the collab protocol.
@@ -32,6 +35,26 @@ use {
 /// Whenever a server applies a delta to a file, it notifies all the participants of that file
 /// except the one from which the request to apply the delta originated of this fact. This allows
 /// the participants to update their revision of the file accordingly.
+/// 
+/// Deltas are only applied to the copy of the file in the memory of the server. A participant can
+/// request to save the file, which writes the newest revision back to disk. The server can also be
+/// configured to save a file automatically when its last participant closes it.
+/// 
+/// Clients can also create, rename, move and delete files and directories. All paths are relative
+/// to the root of the server, and may not point outside of it. Whenever the file tree changes, the
+/// server notifies every other connected client, so they can keep their copy of the tree in sync.
+/// 
+/// The server can watch its root for changes made by other programs. Those are sent as the same
+/// notifications. For open files without unsaved deltas, the server computes a delta from the
+/// contents in memory to the contents on disk, and applies it as if it came from a client. Open
+/// files with unsaved deltas have a conflict instead, and are not saved again until a participant
+/// explicitly requests to overwrite the changes on disk.
+/// 
+/// Finally, each participant can tell the others where its carets and selections are, along with a
+/// name and color to show them in. Like deltas, this presence is sent for a given revision of the
+/// file. The server transforms it against the deltas that the participant has not seen yet, and
+/// keeps it up to date as further deltas are applied. Participants that open a file receive the
+/// presence of every other participant along with its contents.
  
 /// A type for representing a request to the collab server.
 #[derive(Clone, Debug, SerBin, DeBin)]
@@ -45,10 +68,29 @@ pub enum CollabRequest {
     /// Requests the collab server to apply the given delta to the given revision of the file with
     /// the given id.
     ApplyDelta(TextFileId, u32, Delta),
+    /// Requests the collab server to write the newest revision of the file with the given id back
+    /// to disk.
+    SaveFile(TextFileId),
+    /// Requests the collab server to write the newest revision of the file with the given id back
+    /// to disk, even if another program changed the file on disk since. Those changes are lost.
+    OverwriteFile(TextFileId),
+    /// Requests the collab server to update the presence of the client for the given revision of
+    /// the file with the given id.
+    UpdatePresence(TextFileId, u32, Presence),
     /// Requests the collab server to remove the client as a participant from the file with the
     /// given id. If the client was the last participant for the file, this also closes the file on
     /// the collab server.
     CloseFile(TextFileId),
+    /// Requests the collab server to create an empty file at the given path.
+    CreateFile(UnixPathBuf),
+    /// Requests the collab server to create an empty directory at the given path.
+    CreateDirectory(UnixPathBuf),
+    /// Requests the collab server to rename the file or directory at the first path to the second
+    /// path. This is also used to move files or directories to another directory.
+    RenameEntry(UnixPathBuf, UnixPathBuf),
+    /// Requests the collab server to delete the file or directory at the given path. Directories
+    /// are deleted along with everything in them.
+    DeleteEntry(UnixPathBuf),
 }
 
 /// A type for representing either a response or a notification from the collab server.
@@ -66,14 +108,33 @@ pub enum CollabResponse {
     /// The result of requesting the collab server to return its file tree.
     LoadFileTree(Result<FileTreeData, CollabError>),
     /// The result of requesting the collab server to add the client as a participant to the file
-    /// with the given id.
-    OpenFile(Result<(TextFileId, u32, Text), CollabError>),
+    /// with the given id, along with the presence of every other participant for that file.
+    OpenFile(Result<(TextFileId, u32, Text, HashMap<ParticipantId, Presence>), CollabError>),
     /// The result of requesting the collab server to apply a delta to a revision of the file with
     /// the given id.
     ApplyDelta(Result<TextFileId, CollabError>),
+    /// The result of requesting the collab server to save the file with the given id, along with
+    /// the revision that is now on disk.
+    SaveFile(Result<(TextFileId, u32), CollabError>),
+    /// The result of requesting the collab server to overwrite the file with the given id, along
+    /// with the revision that is now on disk.
+    OverwriteFile(Result<(TextFileId, u32), CollabError>),
+    /// The result of requesting the collab server to update the presence of the client for the
+    /// file with the given id.
+    UpdatePresence(Result<TextFileId, CollabError>),
     /// The result of requesting the collab server to remove the client as a participant from the
     /// file with the given id.
     CloseFile(Result<TextFileId, CollabError>),
+    /// The result of requesting the collab server to create a file at the given path.
+    CreateFile(Result<UnixPathBuf, CollabError>),
+    /// The result of requesting the collab server to create a directory at the given path.
+    CreateDirectory(Result<UnixPathBuf, CollabError>),
+    /// The result of requesting the collab server to rename the file or directory at the first
+    /// path to the second path.
+    RenameEntry(Result<(UnixPathBuf, UnixPathBuf), CollabError>),
+    /// The result of requesting the collab server to delete the file or directory at the given
+    /// path.
+    DeleteEntry(Result<UnixPathBuf, CollabError>),
 }
 
 /// A type for representing data about a file tree.
@@ -105,14 +166,70 @@ pub struct DirectoryEntry {
     pub node: FileNodeData,
 }
 
+/// A type for representing a change to the file tree of the collab server.
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub enum FileTreeChange {
+    /// A file or directory was created at the given path.
+    Created {path: UnixPathBuf, node: FileNodeData},
+    /// The file or directory at the path `from` was renamed to the path `to`.
+    Renamed {from: UnixPathBuf, to: UnixPathBuf},
+    /// The file or directory at the given path was deleted.
+    Deleted {path: UnixPathBuf},
+}
+
 /// A type for representing a notification from the collab server.
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum CollabNotification {
     /// Notifies the client that another client applied the given delta to the file with the given
     /// id. This is only sent for files for which the client is a participant.
     DeltaWasApplied(TextFileId, Delta),
+    /// Notifies the client that another client saved the given revision of the file with the given
+    /// id to disk. This is only sent for files for which the client is a participant.
+    FileWasSaved(TextFileId, u32),
+    /// Notifies the client that another client, or another program, changed the file tree. This is
+    /// sent to every connected client.
+    FileTreeWasChanged(FileTreeChange),
+    /// Notifies the client that another program changed the file with the given id on disk, while
+    /// the collab server had unsaved deltas for it. The changes on disk are not applied, and the
+    /// file can only be saved by overwriting them. This is only sent for files for which the client
+    /// is a participant.
+    FileHasConflict(TextFileId),
+    /// Notifies the client that another participant for the file with the given id updated its
+    /// presence. The presence is for the newest revision of the file that the client has been told
+    /// about.
+    PresenceWasUpdated(TextFileId, ParticipantId, Presence),
+    /// Notifies the client that another participant was removed from the file with the given id,
+    /// so its presence should no longer be shown.
+    PresenceWasRemoved(TextFileId, ParticipantId),
+}
+
+/// A type for representing the presence of a participant in a file. That is, who the participant
+/// is, and where its carets and selections are.
+#[derive(Clone, Debug, Default, Eq, PartialEq, SerBin, DeBin)]
+pub struct Presence {
+    /// The name to show for the participant.
+    pub name: String,
+    /// The color to show the carets and selections of the participant in, as RGBA.
+    pub color: u32,
+    /// The carets of the participant.
+    pub carets: PositionSet,
+    /// The selections of the participant.
+    pub selections: RangeSet,
 }
 
+impl Presence {
+    /// Applies the given delta to the carets and selections of this presence.
+    pub fn apply_delta(&mut self, delta: &Delta) {
+        self.carets.apply_delta(delta);
+        self.selections.apply_delta(delta);
+    }
+}
+
+/// An identifier for a participant, which is unique among the clients connected to the collab
+/// server.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, SerBin, DeBin)]
+pub struct ParticipantId(pub u64);
+
 /// A type for representing errors from the collab server.
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum CollabError {
@@ -122,6 +239,18 @@ pub enum CollabError {
     /// Attempted to either apply a delta to, or remove the client as a participant from a file for
     /// which it was not a participant.
     NotAParticipant,
+    /// Attempted to use a path that is absolute, contains `..`, or otherwise points outside the
+    /// root of the collab server.
+    InvalidPath(UnixPathBuf),
+    /// Attempted to create or rename to a path that already exists.
+    AlreadyExists(UnixPathBuf),
+    /// Attempted to rename or delete a path that does not exist.
+    NotFound(UnixPathBuf),
+    /// Attempted to delete a path that is (or contains) a file that is currently open.
+    FileIsOpen(UnixPathBuf),
+    /// Attempted to save the file with the given id after another program changed it on disk.
+    /// Only overwriting the file is allowed until then.
+    FileHasConflict(TextFileId),
     /// Unknown error
     Unknown(String),
 }
@@ -1,30 +1,40 @@
 use {
     crate::{
         makepad_editor_core::{
-            delta::Delta,
+            delta::{self, Delta},
+            size::Size,
             text::Text
         },
         makepad_live_id::LiveIdMap,
+        file_watcher::FileWatcher,
         makepad_collab_protocol::{
             DirectoryEntry,
             TextFileId,
             FileNodeData,
+            FileTreeChange,
             FileTreeData,
             CollabError,
             CollabNotification,
             CollabRequest,
             CollabResponse,
+            ParticipantId,
+            Presence,
+            unix_path::{Component, UnixPath, UnixPathBuf},
             unix_str::UnixString,
         },
     },
     std::{ 
         cmp::Ordering,
         collections::{HashMap, VecDeque},
+        ffi::OsString,
         fmt,
         fs,
+        io::{self, Write},
         mem,
         path::{Path, PathBuf},
         sync::{Arc, Mutex, RwLock},
+        thread,
+        time::SystemTime,
     },
 };
 
@@ -47,12 +57,60 @@ impl CollabServer {
             next_connection_id: 0,
             shared: Arc::new(RwLock::new(Shared {
                 path: path.into(),
+                autosave: false,
+                connections: Mutex::new(HashMap::new()),
+                snapshot: None,
                 files: LiveIdMap::new(),
                 file_ids_by_path: HashMap::new(),
             })),
         }
     }
     
+    /// Sets whether files are saved to disk when their last participant closes them. This is off
+    /// by default, in which case any unsaved deltas are discarded when the file is closed.
+    pub fn set_autosave(&mut self, autosave: bool) {
+        self.shared.write().unwrap().autosave = autosave;
+    }
+    
+    /// Starts watching the root of this collab server for changes made by other programs, such as
+    /// another editor or `git checkout`.
+    /// 
+    /// Changes to the file tree are sent to every client. Changes to open files are applied as a
+    /// delta if the server has no unsaved deltas for the file, and reported as a conflict
+    /// otherwise. The watching happens on a background thread, which stops when the server is
+    /// dropped.
+    pub fn start_watching(&mut self) {
+        {
+            let mut shared_guard = self.shared.write().unwrap();
+            shared_guard.snapshot = Some(take_snapshot(&shared_guard.path));
+        }
+        let shared = Arc::downgrade(&self.shared);
+        thread::spawn(move || {
+            let mut file_watcher = FileWatcher::new();
+            loop {
+                match shared.upgrade() {
+                    Some(shared) => {
+                        // Directories can come and go, so update the set of watched directories
+                        // every time.
+                        let shared_guard = shared.read().unwrap();
+                        file_watcher.watch_directory(&shared_guard.path);
+                        for (path, entry_state) in shared_guard.snapshot.as_ref().unwrap() {
+                            if entry_state.is_directory {
+                                file_watcher.watch_directory(path);
+                            }
+                        }
+                    }
+                    None => return,
+                }
+                file_watcher.wait();
+                match shared.upgrade() {
+                    Some(shared) => shared.write().unwrap().handle_external_changes(),
+                    None => return,
+                }
+            }
+        });
+    }
+    
     /// Creates a new connection to this collab server, and returns a handle for the connection.
     /// 
     /// The given `notification_sender` is called whenever the server wants to send a notification
@@ -60,6 +118,7 @@ impl CollabServer {
     pub fn connect(&mut self, notification_sender: Box<dyn NotificationSender>) -> CollabConnection {
         let connection_id = ConnectionId(self.next_connection_id);
         self.next_connection_id += 1;
+        self.shared.read().unwrap().connections.lock().unwrap().insert(connection_id, notification_sender.clone());
         CollabConnection {
             connection_id,
             shared: self.shared.clone(),
@@ -84,98 +143,75 @@ impl CollabConnection {
     /// The embedder is responsible for receiving requests, calling this method to handle them, and
     /// sending back the response.
     pub fn handle_request(&self, request: CollabRequest) -> CollabResponse {
-        use std::{ffi::OsString, os::unix::ffi::OsStringExt};
-
         match request {
             CollabRequest::LoadFileTree {with_data} => CollabResponse::LoadFileTree(self.load_file_tree(with_data)),
             CollabRequest::OpenFile(path) => {
-                let path = PathBuf::from(OsString::from_vec(path.into_unix_string().into_vec()));
-                let mut base_path = self.shared.read().unwrap().path.clone();
-                base_path.push(path);
-                CollabResponse::OpenFile(self.open_file(base_path))
+                CollabResponse::OpenFile(self.resolve_path(&path).and_then( | path | self.open_file(path)))
             }
             CollabRequest::ApplyDelta(text_file_id, revision, delta) => {
                 CollabResponse::ApplyDelta(self.apply_delta(text_file_id, revision, delta))
             }
+            CollabRequest::SaveFile(text_file_id) => CollabResponse::SaveFile(self.save_file(text_file_id, false)),
+            CollabRequest::OverwriteFile(text_file_id) => {
+                CollabResponse::OverwriteFile(self.save_file(text_file_id, true))
+            }
+            CollabRequest::UpdatePresence(text_file_id, revision, presence) => {
+                CollabResponse::UpdatePresence(self.update_presence(text_file_id, revision, presence))
+            }
             CollabRequest::CloseFile(path) => CollabResponse::CloseFile(self.close_file(path)),
+            CollabRequest::CreateFile(path) => CollabResponse::CreateFile(self.create_entry(path, false)),
+            CollabRequest::CreateDirectory(path) => CollabResponse::CreateDirectory(self.create_entry(path, true)),
+            CollabRequest::RenameEntry(from, to) => CollabResponse::RenameEntry(self.rename_entry(from, to)),
+            CollabRequest::DeleteEntry(path) => CollabResponse::DeleteEntry(self.delete_entry(path)),
         }
     }
     
-    // Handles a `LoadFileTree` request.
-    fn load_file_tree(&self, with_data: bool) -> Result<FileTreeData, CollabError> {
-        use std::os::unix::ffi::OsStringExt;
-        
-        // A recursive helper function for traversing the entries of a directory and creating the
-        // data structures that describe them.
-        fn get_directory_entries(path: &Path, with_data: bool) -> Result<Vec<DirectoryEntry>, CollabError> {
-            let mut entries = Vec::new();
-            for entry in fs::read_dir(path).map_err( | error | CollabError::Unknown(error.to_string()))? {
-                // We can't get the entry for some unknown reason. Raise an error.
-                let entry = entry.map_err( | error | CollabError::Unknown(error.to_string()))?;
-                // Get the path for the entry.
-                let entry_path = entry.path();
-                // Get the file name for the entry.
-                let name = entry.file_name();
-                if let Ok(name_string) = name.into_string() {
-                    if entry_path.is_dir() && name_string == "target"
-                        || name_string.starts_with('.') {
-                        // Skip over directories called "target". This is sort of a hack. The reason
-                        // it's here is that the "target" directory for Rust projects is huge, and
-                        // our current implementation of the file tree widget is not yet fast enough
-                        // to display vast numbers of nodes. We paper over this by pretending the
-                        // "target" directory does not exist.
-                        continue;
-                    }
-                }
-                else {
-                    // Skip over entries with a non UTF-8 file name.
-                    continue;
+    // Resolves a path relative to the root of the server to a path on disk. Paths that are
+    // absolute, contain `..`, or refer to the root itself are rejected. So are paths that lie
+    // outside the root once symlinks are resolved, including a symlink at the end of the path.
+    fn resolve_path(&self, path: &UnixPath) -> Result<PathBuf, CollabError> {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+        
+        let invalid_path = || CollabError::InvalidPath(path.to_unix_path_buf());
+        let root_path = self.shared.read().unwrap().path.clone();
+        let mut resolved_path = root_path.clone();
+        let mut is_root = true;
+        for component in path.components() {
+            match component {
+                Component::Normal(name) => {
+                    resolved_path.push(OsStr::from_bytes(name.as_bytes()));
+                    is_root = false;
                 }
-                // Create a `DirectoryEntry` for this entry and add it to the list of entries.
-                entries.push(DirectoryEntry {
-                    name: UnixString::from_vec(entry.file_name().into_vec()),
-                    node: if entry_path.is_dir() {
-                        // If this entry is a subdirectory, recursively create `DirectoryEntry`'s
-                        // for its entries as well.
-                        FileNodeData::Directory {
-                            entries: get_directory_entries(&entry_path, with_data) ?,
-                        }
-                    } else if entry_path.is_file() {
-                        if with_data {
-                            let bytes: Vec<u8> = fs::read(&entry_path).map_err(
-                                | error | CollabError::Unknown(error.to_string())
-                            ) ?;
-                            FileNodeData::File {data: Some(bytes)}
-                        }
-                        else {
-                            FileNodeData::File {data: None}
-                        }
-                    }
-                    else {
-                        // If this entry is neither a directory or a file, skip it. This ignores
-                        // things such as symlinks, for which we are not yet sure how we want to
-                        // handle them.
-                        continue
-                    },
-                });
+                Component::CurDir => (),
+                Component::RootDir | Component::ParentDir => return Err(invalid_path()),
             }
-            
-            // Sort all the entries by name, directories first, and files second.
-            entries.sort_by( | entry_0, entry_1 | {
-                match &entry_0.node{
-                    FileNodeData::Directory{..}=>match &entry_1.node{
-                        FileNodeData::Directory{..}=>entry_0.name.cmp(&entry_1.name),
-                        FileNodeData::File{..}=>Ordering::Less
-                    }
-                    FileNodeData::File{..}=>match &entry_1.node{
-                        FileNodeData::Directory{..}=>Ordering::Greater,
-                        FileNodeData::File{..}=>entry_0.name.cmp(&entry_1.name)
-                    }
-                }
-            });
-            Ok(entries)
+        }
+        if is_root {
+            return Err(invalid_path());
         }
         
+        // The components themselves can't leave the root anymore, but a symlink along the way
+        // still could, so compare the canonical paths as well.
+        let root_path = root_path.canonicalize().map_err( | error | CollabError::Unknown(error.to_string())) ?;
+        let parent_path = resolved_path.parent().unwrap().canonicalize().map_err(
+            | error | path_error(error, path)
+        ) ?;
+        if !parent_path.starts_with(&root_path) {
+            return Err(invalid_path());
+        }
+        // An entry that already exists can be a symlink itself. One that doesn't resolve could
+        // still create a file wherever it points to, so it is rejected as well.
+        if fs::symlink_metadata(&resolved_path).is_ok() {
+            let target_path = resolved_path.canonicalize().map_err( | _ | invalid_path()) ?;
+            if !target_path.starts_with(&root_path) {
+                return Err(invalid_path());
+            }
+        }
+        Ok(resolved_path)
+    }
+    
+    // Handles a `LoadFileTree` request.
+    fn load_file_tree(&self, with_data: bool) -> Result<FileTreeData, CollabError> {
         let path = self.shared.read().unwrap().path.clone();
 
         let root = FileNodeData::Directory {
@@ -185,7 +221,7 @@ impl CollabConnection {
     }
     
     // Handles an `OpenFile` request.
-    fn open_file(&self, path: PathBuf) -> Result<(TextFileId, u32, Text), CollabError> {
+    fn open_file(&self, path: PathBuf) -> Result<(TextFileId, u32, Text, HashMap<ParticipantId, Presence>), CollabError> {
         // We need to update the list of files in the shared state, so lock it for writing. This is
         // necessary so other clients cannot close the file while we are still in the process of
         // opening it.
@@ -205,6 +241,15 @@ impl CollabConnection {
                 let their_revision = file_guard.our_revision;
                 // Get a copy of the contents of the file.
                 let text = file_guard.text.clone();
+                // Get a copy of the presence of every other participant. These are always kept up
+                // to date with our revision.
+                let presences = file_guard
+                    .participants_by_connection_id
+                    .iter()
+                    .filter_map( | (connection_id, participant) | {
+                        Some((connection_id.participant_id(), participant.presence.clone() ?))
+                    })
+                    .collect::<HashMap<_, _ >>();
                 if file_guard
                     .participants_by_connection_id
                     .contains_key(&self.connection_id)
@@ -217,6 +262,7 @@ impl CollabConnection {
                     self.connection_id,
                     Participant {
                         their_revision,
+                        presence: None,
                         notification_sender: self.notification_sender.clone(),
                     },
                 );
@@ -226,7 +272,7 @@ impl CollabConnection {
                 
                 drop(shared_guard);
                 
-                Ok((file_id, their_revision as u32, text))
+                Ok((file_id, their_revision as u32, text, presences))
             }
             None => {
                 // The file was not yet opened, so we need to open it, and then add the client as
@@ -238,13 +284,7 @@ impl CollabConnection {
                 let bytes = fs::read(&path).map_err(
                     | error | CollabError::Unknown(error.to_string())
                 ) ?;
-                // Converts the file contents to a `Text`. This is necessarily a lossy conversion
-                // because `Text` assumes everything is UTF-8 encoded, and this isn't always the
-                // case for files on disk (is this a problem?)
-                let text: Text = Text::from_lines(String::from_utf8_lossy(&bytes)
-                    .lines()
-                    .map( | line | line.chars().collect::<Vec<_ >> ())
-                    .collect::<Vec<_ >>());
+                let contents = FileContents::from_bytes(&bytes);
                 
                 // Create the list of participants for this file and add the file to it.
                 let mut participants_by_connection_id = HashMap::new();
@@ -252,6 +292,7 @@ impl CollabConnection {
                     self.connection_id,
                     Participant {
                         their_revision: 0,
+                        presence: None,
                         notification_sender: self.notification_sender.clone(),
                     },
                 );
@@ -260,9 +301,13 @@ impl CollabConnection {
                 let file = Mutex::new(File {
                     path: path.clone(),
                     our_revision: 0,
-                    text: text.clone(),
+                    saved_revision: 0,
+                    line_ending: contents.line_ending,
+                    ends_with_newline: contents.ends_with_newline,
+                    text: contents.text.clone(),
                     outstanding_deltas: VecDeque::new(),
                     participants_by_connection_id,
+                    has_conflict: false,
                 });
                 
                 // Insert the file in the shared list of files.
@@ -272,7 +317,7 @@ impl CollabConnection {
                 // It's now safe to drop our locks.
                 drop(shared_guard);
                 
-                Ok((file_id, 0, text))
+                Ok((file_id, 0, contents.text, HashMap::new()))
             }
         }
     }
@@ -310,6 +355,7 @@ impl CollabConnection {
         file_guard.our_revision += 1;
         file_guard.text.apply_delta(delta.clone());
         file_guard.outstanding_deltas.push_back(delta.clone());
+        file_guard.apply_delta_to_presences(&delta);
         
         // Update the last revision that has been seen by the client.
         let participant = file_guard
@@ -334,8 +380,8 @@ impl CollabConnection {
         file_guard.outstanding_deltas.drain(..(settled_delta_count as usize));
         
         // Notify the other participants that a delta has been applied to this file.
-        file_guard.notify_other_participants(
-            self.connection_id,
+        file_guard.notify_participants(
+            Some(self.connection_id),
             CollabNotification::DeltaWasApplied(file_id, delta),
         );
         
@@ -347,6 +393,223 @@ impl CollabConnection {
         Ok(file_id)
     }
     
+    // Handles a `SaveFile` or `OverwriteFile` request.
+    fn save_file(&self, file_id: TextFileId, overwrite: bool) -> Result<(TextFileId, u32), CollabError> {
+        // We only need to get the list of files in the shared state, so lock it for reading. This
+        // is necessary so other clients cannot close the file while we are still saving it.
+        let shared_guard = self.shared.read().unwrap();
+        
+        // Lock the file for access so other clients cannot apply further deltas to the file while
+        // we are writing it to disk.
+        let mut file_guard = match shared_guard.files.get(&file_id) {
+            Some(file) => file.lock().unwrap(),
+            None => return Err(CollabError::NotAParticipant)
+        };
+        
+        if !file_guard
+            .participants_by_connection_id
+            .contains_key(&self.connection_id)
+        {
+            // The client is not a participant for this file. Raise an error.
+            return Err(CollabError::NotAParticipant);
+        }
+        
+        let saved_revision = file_guard.save(file_id, overwrite)?;
+        
+        // Notify the other participants that this revision of the file is now on disk.
+        file_guard.notify_participants(
+            Some(self.connection_id),
+            CollabNotification::FileWasSaved(file_id, saved_revision),
+        );
+        
+        // It's now safe to drop our locks.
+        drop(file_guard);
+        
+        drop(shared_guard);
+        
+        Ok((file_id, saved_revision))
+    }
+    
+    // Handles an `UpdatePresence` request.
+    fn update_presence(
+        &self,
+        file_id: TextFileId,
+        their_revision: u32,
+        presence: Presence,
+    ) -> Result<TextFileId, CollabError> {
+        // We only need to get the list of files in the shared state, so lock it for reading. This
+        // is necessary so other clients cannot close the file while we are still updating it.
+        let shared_guard = self.shared.read().unwrap();
+        
+        // Lock the file for access so other clients cannot apply further deltas to the file while
+        // we are transforming the presence.
+        let mut file_guard = match shared_guard.files.get(&file_id) {
+            Some(file) => file.lock().unwrap(),
+            None => return Err(CollabError::NotAParticipant)
+        };
+        
+        if !file_guard
+            .participants_by_connection_id
+            .contains_key(&self.connection_id)
+        {
+            // The client is not a participant for this file. Raise an error.
+            return Err(CollabError::NotAParticipant);
+        }
+        
+        // The number of deltas that has been seen by the server but not the client.
+        let unseen_delta_count = file_guard.our_revision - their_revision;
+        // The number of deltas that has been seen by both the server and the client.
+        let seen_delta_count = file_guard.outstanding_deltas.len() as u32 - unseen_delta_count;
+        
+        // Apply each delta that has been seen by the server but not by the client to the presence,
+        // to obtain a presence for the newest revision of the file.
+        let mut presence = presence;
+        for unseen_delta in file_guard.outstanding_deltas.iter().skip(seen_delta_count as usize) {
+            presence.apply_delta(unseen_delta);
+        }
+        
+        // Store the presence, so that we can send it to clients that open the file later on.
+        let participant = file_guard
+            .participants_by_connection_id
+            .get_mut(&self.connection_id)
+            .unwrap();
+        participant.presence = Some(presence.clone());
+        
+        // Notify the other participants that the presence of the client has been updated.
+        file_guard.notify_participants(
+            Some(self.connection_id),
+            CollabNotification::PresenceWasUpdated(file_id, self.connection_id.participant_id(), presence),
+        );
+        
+        // It's now safe to drop our locks.
+        drop(file_guard);
+        
+        drop(shared_guard);
+        
+        Ok(file_id)
+    }
+    
+    // Handles a `CreateFile` or `CreateDirectory` request.
+    fn create_entry(&self, path: UnixPathBuf, is_directory: bool) -> Result<UnixPathBuf, CollabError> {
+        let resolved_path = self.resolve_path(&path) ?;
+        
+        // Lock the shared state for writing, so other clients cannot change the file tree while we
+        // are still in the process of changing it.
+        let mut shared_guard = self.shared.write().unwrap();
+        
+        let node = if is_directory {
+            fs::create_dir(&resolved_path).map_err( | error | path_error(error, &path)) ?;
+            FileNodeData::Directory {entries: Vec::new()}
+        } else {
+            // Don't truncate a file that happens to exist already.
+            fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&resolved_path)
+                .map_err( | error | path_error(error, &path)) ?;
+            FileNodeData::File {data: Some(Vec::new())}
+        };
+        shared_guard.refresh_snapshot();
+        
+        // Notify the other clients that the file tree has changed.
+        shared_guard.notify_connections(
+            Some(self.connection_id),
+            CollabNotification::FileTreeWasChanged(FileTreeChange::Created {path: path.clone(), node}),
+        );
+        
+        // It's now safe to drop our locks.
+        drop(shared_guard);
+        
+        Ok(path)
+    }
+    
+    // Handles a `RenameEntry` request.
+    fn rename_entry(&self, from: UnixPathBuf, to: UnixPathBuf) -> Result<(UnixPathBuf, UnixPathBuf), CollabError> {
+        let from_path = self.resolve_path(&from) ?;
+        let to_path = self.resolve_path(&to) ?;
+        
+        // We need to update the paths of any open files that are renamed, so lock the shared state
+        // for writing. This also stops other clients from changing the file tree in the meantime.
+        let mut shared_guard = self.shared.write().unwrap();
+        
+        fs::symlink_metadata(&from_path).map_err( | error | path_error(error, &from)) ?;
+        if fs::symlink_metadata(&to_path).is_ok() {
+            // Renaming silently replaces existing files on unix, which is never what we want here.
+            return Err(CollabError::AlreadyExists(to));
+        }
+        if to_path.starts_with(&from_path) {
+            // A directory cannot be moved into itself.
+            return Err(CollabError::InvalidPath(to));
+        }
+        fs::rename(&from_path, &to_path).map_err( | error | path_error(error, &from)) ?;
+        
+        // Any open files that were renamed (or are in a directory that was renamed) have to be
+        // saved to their new path from now on.
+        let shared = &mut *shared_guard;
+        let renamed_paths = shared
+            .file_ids_by_path
+            .keys()
+            .filter( | path | path.starts_with(&from_path))
+            .cloned()
+            .collect::<Vec<_ >>();
+        for old_path in renamed_paths {
+            let file_id = shared.file_ids_by_path.remove(&old_path).unwrap();
+            let suffix = old_path.strip_prefix(&from_path).unwrap();
+            let new_path = if suffix.as_os_str().is_empty() {
+                to_path.clone()
+            } else {
+                to_path.join(suffix)
+            };
+            shared.files[file_id].lock().unwrap().path = new_path.clone();
+            shared.file_ids_by_path.insert(new_path, file_id);
+        }
+        shared.refresh_snapshot();
+        
+        // Notify the other clients that the file tree has changed.
+        shared.notify_connections(
+            Some(self.connection_id),
+            CollabNotification::FileTreeWasChanged(FileTreeChange::Renamed {from: from.clone(), to: to.clone()}),
+        );
+        
+        // It's now safe to drop our locks.
+        drop(shared_guard);
+        
+        Ok((from, to))
+    }
+    
+    // Handles a `DeleteEntry` request.
+    fn delete_entry(&self, path: UnixPathBuf) -> Result<UnixPathBuf, CollabError> {
+        let resolved_path = self.resolve_path(&path) ?;
+        
+        // Lock the shared state for writing, so other clients cannot open a file we are about to
+        // delete, or otherwise change the file tree in the meantime.
+        let mut shared_guard = self.shared.write().unwrap();
+        
+        if shared_guard.file_ids_by_path.keys().any( | open_path | open_path.starts_with(&resolved_path)) {
+            // Deleting an open file would leave its participants editing a file that no longer
+            // exists, so they have to close it first. Raise an error.
+            return Err(CollabError::FileIsOpen(path));
+        }
+        let metadata = fs::symlink_metadata(&resolved_path).map_err( | error | path_error(error, &path)) ?;
+        if metadata.is_dir() {
+            fs::remove_dir_all(&resolved_path)
+        } else {
+            fs::remove_file(&resolved_path)
+        }.map_err( | error | path_error(error, &path)) ?;
+        shared_guard.refresh_snapshot();
+        
+        // Notify the other clients that the file tree has changed.
+        shared_guard.notify_connections(
+            Some(self.connection_id),
+            CollabNotification::FileTreeWasChanged(FileTreeChange::Deleted {path: path.clone()}),
+        );
+        
+        // It's now safe to drop our locks.
+        drop(shared_guard);
+        
+        Ok(path)
+    }
+    
     // Handles a `CloseFile` request.
     fn close_file(&self, file_id: TextFileId) -> Result<TextFileId, CollabError> {
         // We need to update the list of files in the shared state, so lock it for writing. This is
@@ -377,22 +640,130 @@ impl CollabConnection {
             .remove(&self.connection_id);
         let is_empty = file_guard.participants_by_connection_id.is_empty();
         
+        let mut result = Ok(file_id);
         if is_empty {
             // If the list of participants for the file is now empty, it's time to close the file
-            // and remove it from the shared list of files.
+            // and remove it from the shared list of files. If autosave is on, write it back to
+            // disk first, unless that would overwrite changes made by another program. The file is
+            // closed even if that fails, since there is nobody left to retry the save.
+            if shared_guard.autosave {
+                if let Err(error) = file_guard.save(file_id, false) {
+                    result = Err(error);
+                }
+            }
             let path = mem::replace(&mut file_guard.path, PathBuf::new());
             drop(file_guard);
             shared_guard.file_ids_by_path.remove(&path);
             shared_guard.files.remove(&file_id);
         } else {
-            // Otherwise, we can just drop the lock for the file.
+            // Otherwise, notify the remaining participants that they should stop showing the
+            // presence of the client, and then we can just drop the lock for the file.
+            file_guard.notify_participants(
+                None,
+                CollabNotification::PresenceWasRemoved(file_id, self.connection_id.participant_id()),
+            );
             drop(file_guard);
         }
         
         // It's now safe to drop our remaining locks.
         drop(shared_guard);
         
-        Ok(file_id)
+        result
+    }
+}
+
+// A recursive helper function for traversing the entries of a directory and creating the
+// data structures that describe them.
+fn get_directory_entries(path: &Path, with_data: bool) -> Result<Vec<DirectoryEntry>, CollabError> {
+    use std::os::unix::ffi::OsStringExt;
+    
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(path).map_err( | error | CollabError::Unknown(error.to_string()))? {
+        // We can't get the entry for some unknown reason. Raise an error.
+        let entry = entry.map_err( | error | CollabError::Unknown(error.to_string()))?;
+        // Get the path for the entry.
+        let entry_path = entry.path();
+        // Get the file name for the entry.
+        let name = entry.file_name();
+        if is_ignored_entry(&entry_path, name) {
+            continue;
+        }
+        // Create a `DirectoryEntry` for this entry and add it to the list of entries.
+        entries.push(DirectoryEntry {
+            name: UnixString::from_vec(entry.file_name().into_vec()),
+            node: if entry_path.is_dir() {
+                // If this entry is a subdirectory, recursively create `DirectoryEntry`'s
+                // for its entries as well.
+                FileNodeData::Directory {
+                    entries: get_directory_entries(&entry_path, with_data) ?,
+                }
+            } else if entry_path.is_file() {
+                if with_data {
+                    let bytes: Vec<u8> = fs::read(&entry_path).map_err(
+                        | error | CollabError::Unknown(error.to_string())
+                    ) ?;
+                    FileNodeData::File {data: Some(bytes)}
+                }
+                else {
+                    FileNodeData::File {data: None}
+                }
+            }
+            else {
+                // If this entry is neither a directory or a file, skip it. This ignores
+                // things such as symlinks, for which we are not yet sure how we want to
+                // handle them.
+                continue
+            },
+        });
+    }
+    
+    // Sort all the entries by name, directories first, and files second.
+    entries.sort_by( | entry_0, entry_1 | {
+        match &entry_0.node{
+            FileNodeData::Directory{..}=>match &entry_1.node{
+                FileNodeData::Directory{..}=>entry_0.name.cmp(&entry_1.name),
+                FileNodeData::File{..}=>Ordering::Less
+            }
+            FileNodeData::File{..}=>match &entry_1.node{
+                FileNodeData::Directory{..}=>Ordering::Greater,
+                FileNodeData::File{..}=>entry_0.name.cmp(&entry_1.name)
+            }
+        }
+    });
+    Ok(entries)
+}
+
+// Returns whether the entry with the given path and file name is left out of the file tree.
+fn is_ignored_entry(entry_path: &Path, name: OsString) -> bool {
+    if let Ok(name_string) = name.into_string() {
+        // Skip over directories called "target". This is sort of a hack. The reason it's here is
+        // that the "target" directory for Rust projects is huge, and our current implementation of
+        // the file tree widget is not yet fast enough to display vast numbers of nodes. We paper
+        // over this by pretending the "target" directory does not exist. Hidden entries are
+        // skipped as well.
+        entry_path.is_dir() && name_string == "target" || name_string.starts_with('.')
+    }
+    else {
+        // Skip over entries with a non UTF-8 file name.
+        true
+    }
+}
+
+impl Drop for CollabConnection {
+    fn drop(&mut self) {
+        // Stop sending notifications for this connection.
+        if let Ok(shared_guard) = self.shared.read() {
+            shared_guard.connections.lock().unwrap().remove(&self.connection_id);
+        }
+    }
+}
+
+// Turns an io error for the given path into the corresponding collab error.
+fn path_error(error: io::Error, path: &UnixPath) -> CollabError {
+    match error.kind() {
+        io::ErrorKind::AlreadyExists => CollabError::AlreadyExists(path.to_unix_path_buf()),
+        io::ErrorKind::NotFound => CollabError::NotFound(path.to_unix_path_buf()),
+        _ => CollabError::Unknown(error.to_string()),
     }
 }
 
@@ -431,35 +802,224 @@ impl fmt::Debug for dyn NotificationSender {
 #[derive(Debug)]
 struct Shared {
     path: PathBuf,
+    // Whether files are saved when their last participant closes them
+    autosave: bool,
+    // Used to send notifications that concern every connection, such as changes to the file tree
+    connections: Mutex<HashMap<ConnectionId, Box<dyn NotificationSender >> >,
+    // The state of the file tree when we last looked, if we are watching it
+    snapshot: Option<Snapshot>,
     files: LiveIdMap<TextFileId, Mutex<File >>,
     file_ids_by_path: HashMap<PathBuf, TextFileId>,
 }
 
+impl Shared {
+    // Sends the given `notification` to every connection except the one with the given
+    // `connection_id`, which is usually the one that caused the notification. Notifications for
+    // changes made by other programs are sent to every connection.
+    fn notify_connections(&self, except: Option<ConnectionId>, notification: CollabNotification) {
+        for (other_connection_id, notification_sender) in self.connections.lock().unwrap().iter() {
+            if Some(*other_connection_id) == except {
+                continue;
+            }
+            notification_sender.send_notification(notification.clone())
+        }
+    }
+    
+    // Takes a new snapshot of the file tree if we are watching it, so that changes made by the
+    // server itself are not reported as changes made by other programs.
+    fn refresh_snapshot(&mut self) {
+        if self.snapshot.is_some() {
+            self.snapshot = Some(take_snapshot(&self.path));
+        }
+    }
+    
+    // Compares the file tree on disk with the last snapshot, and handles any changes made by other
+    // programs since then.
+    fn handle_external_changes(&mut self) {
+        let new_snapshot = take_snapshot(&self.path);
+        let old_snapshot = match self.snapshot.replace(new_snapshot) {
+            Some(old_snapshot) => old_snapshot,
+            None => return,
+        };
+        let new_snapshot = self.snapshot.as_ref().unwrap();
+        
+        // Only report the topmost entry that was created or deleted. Clients get the entries below
+        // it as part of the created node, or remove them along with the deleted node.
+        let is_new_in = | snapshot: &Snapshot, other: &Snapshot, path: &Path | {
+            snapshot.contains_key(path) && !other.contains_key(path)
+        };
+        let mut created_paths = new_snapshot
+            .keys()
+            .filter( | path | is_new_in(new_snapshot, &old_snapshot, path))
+            .filter( | path | !path.parent().is_some_and( | parent | is_new_in(new_snapshot, &old_snapshot, parent)))
+            .collect::<Vec<_ >>();
+        created_paths.sort();
+        for path in created_paths {
+            let node = if new_snapshot[path].is_directory {
+                FileNodeData::Directory {
+                    entries: get_directory_entries(path, false).unwrap_or_default(),
+                }
+            } else {
+                FileNodeData::File {data: None}
+            };
+            self.notify_connections(
+                None,
+                CollabNotification::FileTreeWasChanged(FileTreeChange::Created {path: self.relative_path(path), node}),
+            );
+        }
+        let mut deleted_paths = old_snapshot
+            .keys()
+            .filter( | path | is_new_in(&old_snapshot, new_snapshot, path))
+            .filter( | path | !path.parent().is_some_and( | parent | is_new_in(&old_snapshot, new_snapshot, parent)))
+            .collect::<Vec<_ >>();
+        deleted_paths.sort();
+        for path in deleted_paths {
+            self.notify_connections(
+                None,
+                CollabNotification::FileTreeWasChanged(FileTreeChange::Deleted {path: self.relative_path(path)}),
+            );
+        }
+        
+        // Bring any open files that were modified up to date.
+        for (path, entry_state) in new_snapshot {
+            if entry_state.is_directory || old_snapshot.get(path) == Some(entry_state) {
+                continue;
+            }
+            if let Some(&file_id) = self.file_ids_by_path.get(path) {
+                self.files[file_id].lock().unwrap().reload(file_id);
+            }
+        }
+    }
+    
+    // Returns the given path on disk relative to the root of the server.
+    fn relative_path(&self, path: &Path) -> UnixPathBuf {
+        use std::os::unix::ffi::OsStrExt;
+        
+        let path = path.strip_prefix(&self.path).unwrap_or(path);
+        UnixPathBuf::from(UnixString::from_vec(path.as_os_str().as_bytes().to_vec()))
+    }
+}
+
 /// An identifier for a connection.
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 struct ConnectionId(usize);
 
+impl ConnectionId {
+    // Returns the id by which the other participants know the client for this connection.
+    fn participant_id(self) -> ParticipantId {
+        ParticipantId(self.0 as u64)
+    }
+}
+
 #[derive(Debug)]
 struct File {
     // The path to this file on the disk
     path: PathBuf,
     // The current revision of the file
     our_revision: u32,
+    // The revision of the file that was last written to disk
+    saved_revision: u32,
+    // The line ending used by the file on disk
+    line_ending: &'static str,
+    // Whether the file on disk ended with a line ending
+    ends_with_newline: bool,
     // The current contents of this file
     text: Text,
     // The list of deltas that has been seen by the server, but not yet by *every* client.
     outstanding_deltas: VecDeque<Delta>,
     // A map from connection ids to the participants for this file.
     participants_by_connection_id: HashMap<ConnectionId, Participant>,
+    // Whether another program changed the file on disk while we had unsaved deltas for it. The
+    // file is then only written back to disk when a participant asks to overwrite it.
+    has_conflict: bool,
 }
 
 impl File {
+    // Writes the current revision of this file back to disk, and returns that revision. Nothing is
+    // written if that revision is already on disk. If another program changed the file on disk in
+    // the meantime, it is only written if `overwrite` is set.
+    fn save(&mut self, file_id: TextFileId, overwrite: bool) -> Result<u32, CollabError> {
+        if self.has_conflict && !overwrite {
+            return Err(CollabError::FileHasConflict(file_id));
+        }
+        if self.saved_revision == self.our_revision {
+            return Ok(self.saved_revision);
+        }
+        let mut string = String::new();
+        let mut sep = "";
+        for line in self.text.as_lines() {
+            string.push_str(sep);
+            string.extend(line.iter());
+            sep = self.line_ending;
+        }
+        if self.ends_with_newline {
+            string.push_str(self.line_ending);
+        }
+        write_atomic(&self.path, string.as_bytes()).map_err(
+            | error | CollabError::Unknown(error.to_string())
+        ) ?;
+        self.saved_revision = self.our_revision;
+        self.has_conflict = false;
+        Ok(self.saved_revision)
+    }
+    
+    // Brings this file up to date with its contents on disk, after another program changed it. If
+    // we have unsaved deltas for the file, it has a conflict instead, which the participants are
+    // told about.
+    fn reload(&mut self, file_id: TextFileId) {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            // The file was deleted or can't be read for some other reason. Keep what we have.
+            Err(_) => return,
+        };
+        let contents = FileContents::from_bytes(&bytes);
+        if contents.text == self.text {
+            // This is usually the result of our own save. Otherwise the other program changed the
+            // file to what we have, so there is nothing left to overwrite either.
+            self.has_conflict = false;
+            return;
+        }
+        if self.saved_revision != self.our_revision {
+            // Only tell the participants once, and not for every further change on disk.
+            if !self.has_conflict {
+                self.has_conflict = true;
+                self.notify_participants(None, CollabNotification::FileHasConflict(file_id));
+            }
+            return;
+        }
+        
+        // Apply the changes as if they came from a client that is always up to date, so the
+        // participants can transform their outstanding deltas against it as usual.
+        let delta = diff_texts(&self.text, &contents.text);
+        self.our_revision += 1;
+        self.text.apply_delta(delta.clone());
+        self.outstanding_deltas.push_back(delta.clone());
+        self.apply_delta_to_presences(&delta);
+        // The text in memory matches the disk again.
+        self.saved_revision = self.our_revision;
+        self.line_ending = contents.line_ending;
+        self.ends_with_newline = contents.ends_with_newline;
+        
+        self.notify_participants(None, CollabNotification::DeltaWasApplied(file_id, delta));
+    }
+    
+    // Applies a delta that was just applied to this file to the presence of every participant, so
+    // that they stay up to date with our revision.
+    fn apply_delta_to_presences(&mut self, delta: &Delta) {
+        for participant in self.participants_by_connection_id.values_mut() {
+            if let Some(presence) = &mut participant.presence {
+                presence.apply_delta(delta);
+            }
+        }
+    }
+    
     // Sends the given `notification` except for the one with the given `connection_id`. This is
     // usually the participant that sent the request that caused this notification to happen in
-    // the first place (so there's no need to notify it that something happened).
-    fn notify_other_participants(&self, connection_id: ConnectionId, notification: CollabNotification) {
+    // the first place (so there's no need to notify it that something happened). Notifications for
+    // changes made by other programs are sent to every participant.
+    fn notify_participants(&self, except: Option<ConnectionId>, notification: CollabNotification) {
         for (other_connection_id, other_participant) in &self.participants_by_connection_id {
-            if *other_connection_id == connection_id {
+            if Some(*other_connection_id) == except {
                 continue;
             }
             other_participant
@@ -469,11 +1029,309 @@ impl File {
     }
 }
 
+// The contents of a file on disk, converted to a `Text`.
+struct FileContents {
+    text: Text,
+    // `Text` has no notion of line endings, so remember them to restore them on save.
+    line_ending: &'static str,
+    ends_with_newline: bool,
+}
+
+impl FileContents {
+    fn from_bytes(bytes: &[u8]) -> FileContents {
+        // Converts the file contents to a `Text`. This is necessarily a lossy conversion because
+        // `Text` assumes everything is UTF-8 encoded, and this isn't always the case for files on
+        // disk (is this a problem?)
+        let string = String::from_utf8_lossy(bytes);
+        let mut lines = string
+            .lines()
+            .map( | line | line.chars().collect::<Vec<_ >> ())
+            .collect::<Vec<_ >>();
+        if lines.is_empty() {
+            // A `Text` always has at least one line, even if the file is empty.
+            lines.push(Vec::new());
+        }
+        FileContents {
+            text: Text::from_lines(lines),
+            line_ending: if string.contains("\r\n") {"\r\n"} else {"\n"},
+            ends_with_newline: string.ends_with('\n'),
+        }
+    }
+}
+
+// Returns a delta that turns the old text into the new text. The delta replaces the lines between
+// the longest common prefix and suffix of lines, which is good enough for changes made by other
+// programs.
+fn diff_texts(old_text: &Text, new_text: &Text) -> Delta {
+    let old_lines = old_text.as_lines();
+    let new_lines = new_text.as_lines();
+    // Keep at least one line in the middle, so that the replaced range never has to start or end
+    // past the end of the text.
+    let max_common_count = old_lines.len().min(new_lines.len()) - 1;
+    let prefix_count = old_lines
+        .iter()
+        .zip(new_lines)
+        .take(max_common_count)
+        .take_while( | (old_line, new_line) | old_line == new_line)
+        .count();
+    let suffix_count = old_lines
+        .iter()
+        .rev()
+        .zip(new_lines.iter().rev())
+        .take(max_common_count - prefix_count)
+        .take_while( | (old_line, new_line) | old_line == new_line)
+        .count();
+    
+    let old_last_line = old_lines.len() - 1 - suffix_count;
+    let mut builder = delta::Builder::new();
+    builder.retain(Size {line: prefix_count, column: 0});
+    builder.delete(Size {line: old_last_line - prefix_count, column: old_lines[old_last_line].len()});
+    builder.insert(Text::from_lines(new_lines[prefix_count..new_lines.len() - suffix_count].to_vec()));
+    builder.build()
+}
+
+// The state of every entry in the file tree, by path.
+type Snapshot = HashMap<PathBuf, EntryState>;
+
+// The state of an entry in the file tree, as far as watching for changes is concerned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct EntryState {
+    is_directory: bool,
+    modified: Option<SystemTime>,
+    len: u64,
+}
+
+// Takes a snapshot of the file tree below the given path, skipping the same entries as
+// `get_directory_entries`.
+fn take_snapshot(path: &Path) -> Snapshot {
+    fn add_directory_entries(path: &Path, snapshot: &mut Snapshot) {
+        let read_dir = match fs::read_dir(path) {
+            Ok(read_dir) => read_dir,
+            Err(_) => return,
+        };
+        for entry in read_dir.flatten() {
+            let entry_path = entry.path();
+            if is_ignored_entry(&entry_path, entry.file_name()) {
+                continue;
+            }
+            let metadata = match fs::metadata(&entry_path) {
+                Ok(metadata) => metadata,
+                Err(_) => continue,
+            };
+            if !metadata.is_dir() && !metadata.is_file() {
+                continue;
+            }
+            snapshot.insert(entry_path.clone(), EntryState {
+                is_directory: metadata.is_dir(),
+                modified: metadata.modified().ok(),
+                len: metadata.len(),
+            });
+            // Don't follow symlinks to directories, they could form a cycle.
+            let is_symlink = entry.file_type().is_ok_and( | file_type | file_type.is_symlink());
+            if metadata.is_dir() && !is_symlink {
+                add_directory_entries(&entry_path, snapshot);
+            }
+        }
+    }
+    
+    let mut snapshot = Snapshot::new();
+    add_directory_entries(path, &mut snapshot);
+    snapshot
+}
+
+// Writes the given bytes to a temporary file next to the given path, and then renames it over the
+// path. This way, a crash halfway through a save never leaves a truncated file behind. The
+// temporary file starts with a '.', so it is hidden from the file tree in the meantime.
+fn write_atomic(path: &Path, bytes: &[u8]) -> io::Result<()> {
+    let mut temp_name = OsString::from(".");
+    temp_name.push(path.file_name().unwrap_or_default());
+    temp_name.push(".collab-save");
+    let temp_path = path.with_file_name(temp_name);
+    
+    let result = (|| {
+        let mut file = fs::File::create(&temp_path) ?;
+        file.write_all(bytes) ?;
+        file.sync_all() ?;
+        // Keep the permissions of the file we are replacing, if there is one.
+        if let Ok(metadata) = fs::metadata(path) {
+            fs::set_permissions(&temp_path, metadata.permissions()) ?;
+        }
+        fs::rename(&temp_path, path)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
 // Information about a participant
 #[derive(Debug)]
 struct Participant {
     // The last revision that has been seen by this participant.
     their_revision: u32,
+    // The presence of this participant, for our revision, if it has told us about it.
+    presence: Option<Presence>,
     // Used to send notifications to (the connection of) this participant.
     notification_sender: Box<dyn NotificationSender>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // Diffs the given file contents, and checks that the delta turns the old text into the new one.
+    fn diff(old: &str, new: &str) -> Delta {
+        let old_text = FileContents::from_bytes(old.as_bytes()).text;
+        let new_text = FileContents::from_bytes(new.as_bytes()).text;
+        let delta = diff_texts(&old_text, &new_text);
+        let mut text = old_text.clone();
+        text.apply_delta(delta.clone());
+        assert_eq!(text, new_text);
+        delta
+    }
+    
+    // Creates an empty directory for a test to work in.
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("makepad_collab_server_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+    
+    #[test]
+    fn diff_keeps_common_prefix_and_suffix() {
+        let delta = diff("a\nb\nc\nd\n", "a\nx\ny\nz\nd\n");
+        let mut builder = delta::Builder::new();
+        builder.retain(Size {line: 1, column: 0});
+        builder.delete(Size {line: 1, column: 1});
+        builder.insert(Text::from("x\ny\nz"));
+        assert_eq!(delta, builder.build());
+    }
+    
+    #[test]
+    fn diff_from_and_to_empty_file() {
+        diff("", "a\nb\n");
+        diff("a\nb\n", "");
+        diff("", "");
+    }
+    
+    #[test]
+    fn diff_fully_replaced_file() {
+        let delta = diff("a\nb\n", "c\nd\ne\n");
+        let mut builder = delta::Builder::new();
+        builder.delete(Size {line: 1, column: 1});
+        builder.insert(Text::from("c\nd\ne"));
+        assert_eq!(delta, builder.build());
+    }
+    
+    #[test]
+    fn conflict_blocks_save_until_overwritten() {
+        let dir = temp_dir("conflict");
+        let path = dir.join("file.txt");
+        fs::write(&path, "a\n").unwrap();
+        // We have an unsaved revision of the file when another program changes it.
+        let contents = FileContents::from_bytes(b"b\n");
+        let mut file = File {
+            path: path.clone(),
+            our_revision: 1,
+            saved_revision: 0,
+            line_ending: contents.line_ending,
+            ends_with_newline: contents.ends_with_newline,
+            text: contents.text,
+            outstanding_deltas: VecDeque::new(),
+            participants_by_connection_id: HashMap::new(),
+            has_conflict: false,
+        };
+        fs::write(&path, "c\n").unwrap();
+        let file_id = TextFileId::default();
+        file.reload(file_id);
+        
+        assert!(matches!(file.save(file_id, false), Err(CollabError::FileHasConflict(_))));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "c\n");
+        assert_eq!(file.save(file_id, true).unwrap(), 1);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+    
+    // Connects to a collab server rooted at a new directory for the test.
+    fn connect(name: &str) -> (PathBuf, CollabConnection) {
+        let root = temp_dir(name);
+        let mut server = CollabServer::new(root.clone());
+        let connection = server.connect(Box::new( | _: CollabNotification | ()));
+        (root, connection)
+    }
+    
+    fn is_invalid_path(connection: &CollabConnection, path: &str) -> bool {
+        matches!(connection.resolve_path(UnixPath::new(path)), Err(CollabError::InvalidPath(_)))
+    }
+    
+    #[test]
+    fn resolve_path_inside_root() {
+        let (root, connection) = connect("resolve_inside");
+        fs::create_dir(root.join("dir")).unwrap();
+        assert_eq!(connection.resolve_path(UnixPath::new("dir/file.rs")).unwrap(), root.join("dir/file.rs"));
+        assert_eq!(connection.resolve_path(UnixPath::new("./file.rs")).unwrap(), root.join("file.rs"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn resolve_path_rejects_parent_dir() {
+        let (root, connection) = connect("resolve_parent");
+        fs::create_dir(root.join("dir")).unwrap();
+        assert!(is_invalid_path(&connection, "../file.rs"));
+        assert!(is_invalid_path(&connection, "dir/../../file.rs"));
+        // Even a `..` that would stay inside the root is rejected.
+        assert!(is_invalid_path(&connection, "dir/../file.rs"));
+        fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn resolve_path_rejects_absolute_path() {
+        let (root, connection) = connect("resolve_absolute");
+        assert!(is_invalid_path(&connection, "/etc/passwd"));
+        assert!(is_invalid_path(&connection, &root.join("file.rs").to_string_lossy()));
+        fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn resolve_path_rejects_root() {
+        let (root, connection) = connect("resolve_root");
+        assert!(is_invalid_path(&connection, ""));
+        assert!(is_invalid_path(&connection, "."));
+        fs::remove_dir_all(&root).unwrap();
+    }
+    
+    #[test]
+    fn resolve_path_rejects_symlink_outside_root() {
+        let (root, connection) = connect("resolve_symlink");
+        let outside = temp_dir("resolve_symlink_outside");
+        std::os::unix::fs::symlink(&outside, root.join("link")).unwrap();
+        assert!(is_invalid_path(&connection, "link/file.rs"));
+        // A symlink that stays inside the root is fine.
+        fs::create_dir(root.join("dir")).unwrap();
+        std::os::unix::fs::symlink(root.join("dir"), root.join("inner_link")).unwrap();
+        assert!(connection.resolve_path(UnixPath::new("inner_link/file.rs")).is_ok());
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+    
+    #[test]
+    fn resolve_path_rejects_final_symlink_outside_root() {
+        let (root, connection) = connect("resolve_final_symlink");
+        let outside = temp_dir("resolve_final_symlink_outside");
+        fs::write(outside.join("file.rs"), "").unwrap();
+        std::os::unix::fs::symlink(outside.join("file.rs"), root.join("link.rs")).unwrap();
+        assert!(is_invalid_path(&connection, "link.rs"));
+        std::os::unix::fs::symlink(&outside, root.join("dir_link")).unwrap();
+        assert!(is_invalid_path(&connection, "dir_link"));
+        // Writing through a dangling symlink would create the file outside the root.
+        std::os::unix::fs::symlink(outside.join("new.rs"), root.join("dangling.rs")).unwrap();
+        assert!(is_invalid_path(&connection, "dangling.rs"));
+        // A symlink to a file inside the root is fine.
+        fs::write(root.join("file.rs"), "").unwrap();
+        std::os::unix::fs::symlink(root.join("file.rs"), root.join("inner_link.rs")).unwrap();
+        assert!(connection.resolve_path(UnixPath::new("inner_link.rs")).is_ok());
+        fs::remove_dir_all(&root).unwrap();
+        fs::remove_dir_all(&outside).unwrap();
+    }
+}
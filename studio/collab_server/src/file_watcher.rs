@@ -0,0 +1,121 @@
+use std::{
+    path::Path,
+    thread,
+    time::Duration,
+};
+
+/// A watcher that blocks until something in a set of directories may have changed.
+///
+/// The watcher does not report what changed. The collab server compares a snapshot of its file tree
+/// after every wakeup instead, which works the same way whether we get events from the operating
+/// system or not. On Linux, the watcher uses inotify. Elsewhere, or if inotify is not available, it
+/// falls back to waking up at a fixed interval.
+pub struct FileWatcher {
+    #[cfg(target_os = "linux")]
+    inotify_fd: Option<i32>,
+}
+
+// How often the polling fallback wakes up.
+const POLL_INTERVAL: Duration = Duration::from_millis(1000);
+
+// How long to wait after an event before waking up, so that a burst of events (such as a
+// `git checkout`) is handled in one go.
+#[cfg(target_os = "linux")]
+const DEBOUNCE_INTERVAL: Duration = Duration::from_millis(100);
+
+impl FileWatcher {
+    /// Creates a new watcher that doesn't watch any directories yet.
+    pub fn new() -> FileWatcher {
+        FileWatcher {
+            #[cfg(target_os = "linux")]
+            inotify_fd: {
+                let fd = unsafe {inotify_sys::inotify_init1(inotify_sys::IN_CLOEXEC)};
+                if fd >= 0 {Some(fd)} else {None}
+            },
+        }
+    }
+
+    /// Adds the given directory to the set of watched directories. Its subdirectories are not
+    /// watched, they have to be added separately. Adding a directory more than once is harmless.
+    #[cfg(target_os = "linux")]
+    pub fn watch_directory(&mut self, path: &Path) {
+        use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+        if let Some(fd) = self.inotify_fd {
+            if let Ok(path) = CString::new(path.as_os_str().as_bytes()) {
+                unsafe {inotify_sys::inotify_add_watch(fd, path.as_ptr(), inotify_sys::IN_WATCH_MASK)};
+            }
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn watch_directory(&mut self, _path: &Path) {
+    }
+
+    /// Blocks until something in one of the watched directories may have changed.
+    #[cfg(target_os = "linux")]
+    pub fn wait(&mut self) {
+        if let Some(fd) = self.inotify_fd {
+            // We don't care about the events themselves, only that there were some.
+            let mut buffer = [0u8; 4096];
+            let result = unsafe {inotify_sys::read(fd, buffer.as_mut_ptr() as *mut _, buffer.len())};
+            if result > 0 {
+                thread::sleep(DEBOUNCE_INTERVAL);
+                return;
+            }
+            // Reading from inotify failed, so something is badly wrong with it. Fall back to
+            // polling from now on.
+            unsafe {inotify_sys::close(fd)};
+            self.inotify_fd = None;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn wait(&mut self) {
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+impl Drop for FileWatcher {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")]
+        if let Some(fd) = self.inotify_fd {
+            unsafe {inotify_sys::close(fd)};
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod inotify_sys {
+    use std::os::raw::{c_char, c_int, c_void};
+
+    pub const IN_CLOEXEC: c_int = 0o2000000;
+
+    pub const IN_MODIFY: u32 = 0x00000002;
+    pub const IN_ATTRIB: u32 = 0x00000004;
+    pub const IN_CLOSE_WRITE: u32 = 0x00000008;
+    pub const IN_MOVED_FROM: u32 = 0x00000040;
+    pub const IN_MOVED_TO: u32 = 0x00000080;
+    pub const IN_CREATE: u32 = 0x00000100;
+    pub const IN_DELETE: u32 = 0x00000200;
+    pub const IN_DELETE_SELF: u32 = 0x00000400;
+    pub const IN_MOVE_SELF: u32 = 0x00000800;
+
+    pub const IN_WATCH_MASK: u32 = IN_MODIFY
+        | IN_ATTRIB
+        | IN_CLOSE_WRITE
+        | IN_MOVED_FROM
+        | IN_MOVED_TO
+        | IN_CREATE
+        | IN_DELETE
+        | IN_DELETE_SELF
+        | IN_MOVE_SELF;
+
+    extern "C" {
+        pub fn inotify_init1(flags: c_int) -> c_int;
+        pub fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+        pub fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+        pub fn close(fd: c_int) -> c_int;
+    }
+}
@@ -2,6 +2,8 @@
 #[cfg(not(target_arch = "wasm32"))]
 pub mod collab_server;
 #[cfg(not(target_arch = "wasm32"))]
+mod file_watcher;
+#[cfg(not(target_arch = "wasm32"))]
 pub use collab_server::*;
 
 pub use makepad_micro_serde;
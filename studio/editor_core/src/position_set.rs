@@ -1,5 +1,7 @@
 use {
+    crate::makepad_micro_serde::*,
     crate::{
+        delta::Delta,
         position::Position,
         size::Size
     },
@@ -11,7 +13,7 @@ use {
 /// This type is useful if you have collection of positions, and you want to find all unique
 /// positions. A new position set can be created in `O(n log n)` time via the `Builder` type. Once
 /// created, the position set is immutable.
-#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub struct PositionSet {
     positions: Vec<Position>,
 }
@@ -94,6 +96,17 @@ impl PositionSet {
             position: Position::origin(),
         }
     }
+
+    /// Applies the given delta to each position in this set.
+    /// 
+    /// Positions that end up at the same place, such as positions inside a deleted range, are merged
+    /// into one. This method takes `O(n)` time.
+    pub fn apply_delta(&mut self, delta: &Delta) {
+        for position in &mut self.positions {
+            *position = position.apply_delta(delta);
+        }
+        self.positions.dedup();
+    }
 }
 
 impl Deref for PositionSet {
@@ -1,5 +1,7 @@
 use {
+    crate::makepad_micro_serde::*,
     crate::{
+        delta::Delta,
         position::Position,
         range::Range,
         size::Size
@@ -16,7 +18,7 @@ use {
 /// minimal set of non-overlapping ranges that covers all ranges in the original collection. A new
 /// range set can be created in `O(n log n)` time via the `Builder` type. Once created, the range
 /// set is immutable.
-#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq, SerBin, DeBin)]
 pub struct RangeSet {
     // We store the boundaries of each range. That is, every odd position is the start of a range,
     // and every even position is the end of a range. Note that ranges can never be adjacent to
@@ -141,6 +143,22 @@ impl RangeSet {
             is_included: false,
         }
     }
+
+    /// Applies the given delta to each range in this set.
+    ///
+    /// Ranges that become empty are removed, and ranges that end up overlapping or adjacent are
+    /// merged into one. This method takes `O(n log n)` time.
+    pub fn apply_delta(&mut self, delta: &Delta) {
+        let mut builder = Builder::new();
+        for range in self.positions.chunks(2) {
+            let (mut start, mut end) = (range[0], range[1]);
+            builder.include(Range {
+                start: start.apply_delta(delta),
+                end: end.apply_delta(delta),
+            });
+        }
+        *self = builder.build();
+    }
 }
 
 /// A builder for sets of non-overlapping ranges.
@@ -14,9 +14,12 @@ use {
         },
         collab_client::CollabClient,
         makepad_collab_protocol::{
+            FileNodeData,
+            FileTreeChange,
             FileTreeData,
             CollabRequest,
             CollabResponse,
+            CollabNotification,
             CollabClientAction,
             unix_path::{UnixPath, UnixPathBuf},
         },
@@ -24,10 +27,11 @@ use {
             builder_client::BuilderClient,
             builder_protocol::{
                 BuilderCmd,
+                BuilderMsg,
             }
         },
         app_state::{TabKind, AppState, SplitPanel, TabPanel, Panel, Tab},
-        log_view::{LogView},
+        log_view::{LogView, LogViewAction},
         editors::{Editors},
     },
 };
@@ -49,7 +53,7 @@ pub struct AppInner {
     slides_view: SlidesView,
     editors: Editors,
     collab_client: CollabClient,
-    builder_client: BuilderClient
+    builder_client: BuilderClient,
 }
 
 impl AppInner {
@@ -147,7 +151,7 @@ impl AppInner {
                     state.file_path_join(&["studio/component/src/shader_view.rs"]),
                     false
                 );
-                self.builder_client.send_cmd(BuilderCmd::CargoCheck);
+                self.builder_client.send_cmd(BuilderCmd::LoadWorkspace);
             }
             Event::Draw(draw_event) => {
                 self.draw(&mut Cx2d::new(cx, draw_event), state);
@@ -266,6 +270,8 @@ impl AppInner {
             }
         }
         
+        // the editors log file conflicts and failed saves in the messages of the log view
+        let message_count = state.editor_state.messages.len();
         for action in self.collab_client.handle_event(cx, event) {
             match action {
                 CollabClientAction::Response(response) => match response {
@@ -273,24 +279,55 @@ impl AppInner {
                         self.load_file_tree(cx, state, response.unwrap());
                         self.select_tab(cx, state, id!(file_tree).into(), id!(file_tree).into(), Animate::No);
                     }
+                    CollabResponse::CreateFile(Ok(path)) => {
+                        self.apply_file_tree_change(cx, state, FileTreeChange::Created {path, node: FileNodeData::File {data: None}});
+                    }
+                    CollabResponse::CreateDirectory(Ok(path)) => {
+                        self.apply_file_tree_change(cx, state, FileTreeChange::Created {path, node: FileNodeData::Directory {entries: Vec::new()}});
+                    }
+                    CollabResponse::RenameEntry(Ok((from, to))) => {
+                        self.apply_file_tree_change(cx, state, FileTreeChange::Renamed {from, to});
+                    }
+                    CollabResponse::DeleteEntry(Ok(path)) => {
+                        self.apply_file_tree_change(cx, state, FileTreeChange::Deleted {path});
+                    }
                     response => {
                         self.editors.handle_collab_response(cx, &mut state.editor_state, response, &mut self.collab_client.request_sender())
                     }
                 },
+                CollabClientAction::Notification(CollabNotification::FileTreeWasChanged(change)) => {
+                    self.apply_file_tree_change(cx, state, change);
+                }
                 CollabClientAction::Notification(notification) => {
                     self.editors.handle_collab_notification(cx, &mut state.editor_state, notification)
                 }
             }
         }
+        if state.editor_state.messages.len() != message_count {
+            self.log_view.redraw(cx);
+        }
         
-        let msgs = self.builder_client.handle_event(cx, event);
+        let mut msgs = self.builder_client.handle_event(cx, event);
+        msgs.retain( | wrap | {
+            if let BuilderMsg::Workspace(workspace) = &wrap.msg {
+                self.log_view.set_workspace(cx, workspace.clone());
+                return false
+            }
+            true
+        });
         if msgs.len()>0 {
             self.editors.handle_builder_messages(cx, &mut state.editor_state, msgs);
             // lets redraw the logview
             self.log_view.redraw(cx);
         }
         
-        self.log_view.handle_event_with_fn(cx, event,&mut |_,_|{});
+        let mut log_view_actions = Vec::new();
+        self.log_view.handle_event_with_fn(cx, event, &mut | _, action | log_view_actions.push(action));
+        for action in log_view_actions {
+            match action {
+                LogViewAction::BuilderCmd(cmd) => self.builder_client.send_cmd(cmd),
+            }
+        }
         self.shader_view.handle_event(cx, event);
         self.slides_view.handle_event(cx, event);
     }
@@ -303,6 +340,14 @@ impl AppInner {
         self.file_tree.redraw(cx);
     }
     
+    // changes are applied to the file tree that is shown, so its open folders stay open
+    fn apply_file_tree_change(&mut self, cx: &mut Cx, state: &mut AppState, change: FileTreeChange) {
+        for file_node_id in state.apply_file_tree_change(change) {
+            self.file_tree.forget_node(file_node_id);
+        }
+        self.file_tree.redraw(cx);
+    }
+    
     fn split_tab_panel(
         &mut self,
         cx: &mut Cx,
@@ -11,9 +11,9 @@ use {
         makepad_platform::*,
         editor_state::{EditorState, SessionId},
         makepad_collab_protocol::{
-            FileNodeData, FileTreeData,
-            unix_path::UnixPathBuf,
-            unix_str::UnixString,
+            FileNodeData, FileTreeChange, FileTreeData,
+            unix_path::{Component, UnixPath, UnixPathBuf},
+            unix_str::{UnixStr, UnixString},
         },
     },
 };
@@ -152,45 +152,6 @@ impl AppState {
     }
     
     pub fn load_file_tree(&mut self, tree_data: FileTreeData) {
-        fn create_file_node(
-            file_node_id: Option<FileNodeId>,
-            file_nodes: &mut LiveIdMap<FileNodeId, FileNode>,
-            parent_edge: Option<FileEdge>,
-            node: FileNodeData,
-        ) -> FileNodeId {
-            let file_node_id = file_node_id.unwrap_or(file_nodes.alloc_key());
-            let name = parent_edge.as_ref().map_or_else(
-                || String::from("root"),
-                | edge | edge.name.to_string_lossy().to_string(),
-            );
-            let node = FileNode {
-                parent_edge,
-                name,
-                child_edges: match node {
-                    FileNodeData::Directory {entries} => Some(
-                        entries
-                            .into_iter()
-                            .map( | entry | FileEdge {
-                            name: entry.name.clone(),
-                            file_node_id: create_file_node(
-                                None, 
-                                file_nodes,
-                                Some(FileEdge {
-                                    name: entry.name,
-                                    file_node_id,
-                                }),
-                                entry.node,
-                            ),
-                        })
-                            .collect::<Vec<_ >> (),
-                    ),
-                    FileNodeData::File{..} => None,
-                },
-            };
-            file_nodes.insert(file_node_id, node);
-            file_node_id
-        }
-        
         self.path = tree_data.path;
 
         self.file_nodes.clear();
@@ -202,6 +163,145 @@ impl AppState {
             tree_data.root,
         );
     }
+    
+    /// Applies a change that was made to the file tree of the collab server. The nodes that stay
+    /// keep their ids, and the ids of the nodes that were removed are returned.
+    pub fn apply_file_tree_change(&mut self, change: FileTreeChange) -> Vec<FileNodeId> {
+        let mut removed_ids = Vec::new();
+        match change {
+            FileTreeChange::Created {path, node} => {
+                // the same change can be reported more than once, the last report wins
+                self.remove_file_node(&path, &mut removed_ids);
+                if let Some((parent_id, name)) = self.find_parent_file_node(&path) {
+                    let file_node_id = create_file_node(
+                        None,
+                        &mut self.file_nodes,
+                        Some(FileEdge {name: name.clone(), file_node_id: parent_id}),
+                        node
+                    );
+                    self.file_nodes.get_mut(&parent_id).unwrap().child_edges.as_mut().unwrap().push(FileEdge {name, file_node_id});
+                }
+            }
+            FileTreeChange::Renamed {from, to} => {
+                if let Some(file_node_id) = self.detach_file_node(&from) {
+                    self.remove_file_node(&to, &mut removed_ids);
+                    if let Some((parent_id, name)) = self.find_parent_file_node(&to) {
+                        let file_node = self.file_nodes.get_mut(&file_node_id).unwrap();
+                        file_node.name = name.to_string_lossy().to_string();
+                        file_node.parent_edge = Some(FileEdge {name: name.clone(), file_node_id: parent_id});
+                        self.file_nodes.get_mut(&parent_id).unwrap().child_edges.as_mut().unwrap().push(FileEdge {name, file_node_id});
+                    }
+                    else {
+                        self.remove_detached_file_node(file_node_id, &mut removed_ids);
+                    }
+                }
+            }
+            FileTreeChange::Deleted {path} => {
+                self.remove_file_node(&path, &mut removed_ids);
+            }
+        }
+        removed_ids
+    }
+    
+    // Returns the id of the node at the given path, relative to the root of the file tree.
+    fn find_file_node(&self, path: &UnixPath) -> Option<FileNodeId> {
+        self.find_file_node_by_names(&entry_names(path) ?)
+    }
+    
+    fn find_file_node_by_names(&self, names: &[&UnixStr]) -> Option<FileNodeId> {
+        let mut file_node_id = id!(root).into();
+        for name in names {
+            let child_edges = self.file_nodes[file_node_id].child_edges.as_ref() ?;
+            file_node_id = child_edges.iter().find( | edge | *edge.name == **name) ?.file_node_id;
+        }
+        Some(file_node_id)
+    }
+    
+    // Returns the id of the directory that the given path is in, and the name of the path in it.
+    fn find_parent_file_node(&self, path: &UnixPath) -> Option<(FileNodeId, UnixString)> {
+        let names = entry_names(path) ?;
+        let (name, parent_names) = names.split_last() ?;
+        let parent_id = self.find_file_node_by_names(parent_names) ?;
+        self.file_nodes[parent_id].child_edges.as_ref() ?;
+        Some((parent_id, name.to_unix_string()))
+    }
+    
+    // Takes the node at the given path out of its directory, and returns its id.
+    fn detach_file_node(&mut self, path: &UnixPath) -> Option<FileNodeId> {
+        let file_node_id = self.find_file_node(path) ?;
+        let parent_edge = self.file_nodes.get_mut(&file_node_id).unwrap().parent_edge.take() ?;
+        let parent = self.file_nodes.get_mut(&parent_edge.file_node_id).unwrap();
+        parent.child_edges.as_mut().unwrap().retain( | edge | edge.file_node_id != file_node_id);
+        Some(file_node_id)
+    }
+    
+    fn remove_file_node(&mut self, path: &UnixPath, removed_ids: &mut Vec<FileNodeId>) {
+        if let Some(file_node_id) = self.detach_file_node(path) {
+            self.remove_detached_file_node(file_node_id, removed_ids);
+        }
+    }
+    
+    fn remove_detached_file_node(&mut self, file_node_id: FileNodeId, removed_ids: &mut Vec<FileNodeId>) {
+        if let Some(file_node) = self.file_nodes.remove(&file_node_id) {
+            for edge in file_node.child_edges.into_iter().flatten() {
+                self.remove_detached_file_node(edge.file_node_id, removed_ids);
+            }
+            removed_ids.push(file_node_id);
+        }
+    }
+}
+
+fn create_file_node(
+    file_node_id: Option<FileNodeId>,
+    file_nodes: &mut LiveIdMap<FileNodeId, FileNode>,
+    parent_edge: Option<FileEdge>,
+    node: FileNodeData,
+) -> FileNodeId {
+    let file_node_id = file_node_id.unwrap_or(file_nodes.alloc_key());
+    let name = parent_edge.as_ref().map_or_else(
+        || String::from("root"),
+        | edge | edge.name.to_string_lossy().to_string(),
+    );
+    let node = FileNode {
+        parent_edge,
+        name,
+        child_edges: match node {
+            FileNodeData::Directory {entries} => Some(
+                entries
+                    .into_iter()
+                    .map( | entry | FileEdge {
+                    name: entry.name.clone(),
+                    file_node_id: create_file_node(
+                        None, 
+                        file_nodes,
+                        Some(FileEdge {
+                            name: entry.name,
+                            file_node_id,
+                        }),
+                        entry.node,
+                    ),
+                })
+                    .collect::<Vec<_ >> (),
+            ),
+            FileNodeData::File{..} => None,
+        },
+    };
+    file_nodes.insert(file_node_id, node);
+    file_node_id
+}
+
+// The names of the entries along a path relative to the root of the file tree, or `None` for a
+// path that can't be in it.
+fn entry_names(path: &UnixPath) -> Option<Vec<&UnixStr>> {
+    let mut names = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => names.push(name),
+            Component::CurDir => (),
+            _ => return None
+        }
+    }
+    Some(names)
 }
 
 #[derive(Debug)]
@@ -306,3 +406,82 @@ pub struct FileEdge {
     pub file_node_id: FileNodeId,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use {
+        super::*,
+        crate::makepad_collab_protocol::DirectoryEntry,
+    };
+    
+    fn directory(entries: Vec<(&str, FileNodeData)>) -> FileNodeData {
+        FileNodeData::Directory {
+            entries: entries.into_iter().map( | (name, node) | DirectoryEntry {name: UnixString::from_vec(name.into()), node}).collect()
+        }
+    }
+    
+    fn file() -> FileNodeData {
+        FileNodeData::File {data: None}
+    }
+    
+    fn paths(state: &AppState) -> Vec<String> {
+        let mut paths: Vec<String> = state.file_nodes.keys()
+            .filter( | file_node_id | **file_node_id != id!(root).into())
+            .map( | file_node_id | state.file_node_path(*file_node_id).into_unix_string().to_string_lossy().to_string())
+            .collect();
+        paths.sort();
+        paths
+    }
+    
+    fn find(state: &AppState, path: &str) -> Option<FileNodeId> {
+        state.find_file_node(UnixPath::new(path))
+    }
+    
+    #[test]
+    fn file_tree_changes_keep_the_other_nodes() {
+        let mut state = AppState::new();
+        state.load_file_tree(FileTreeData {
+            path: "".into(),
+            root: directory(vec![("src", directory(vec![("lib.rs", file())])), ("README.md", file())]),
+        });
+        let src_id = find(&state, "src").unwrap();
+        let lib_id = find(&state, "src/lib.rs").unwrap();
+        
+        let removed_ids = state.apply_file_tree_change(FileTreeChange::Created {path: "src/main.rs".into(), node: file()});
+        assert!(removed_ids.is_empty());
+        assert_eq!(paths(&state), ["README.md", "src", "src/lib.rs", "src/main.rs"]);
+        
+        state.apply_file_tree_change(FileTreeChange::Renamed {from: "src".into(), to: "source".into()});
+        assert_eq!(paths(&state), ["README.md", "source", "source/lib.rs", "source/main.rs"]);
+        assert_eq!(find(&state, "source"), Some(src_id));
+        assert_eq!(find(&state, "source/lib.rs"), Some(lib_id));
+        
+        let main_id = find(&state, "source/main.rs").unwrap();
+        let mut removed_ids = state.apply_file_tree_change(FileTreeChange::Deleted {path: "source".into()});
+        removed_ids.sort_by_key( | file_node_id | file_node_id.0.0);
+        let mut expected_ids = vec![src_id, lib_id, main_id];
+        expected_ids.sort_by_key( | file_node_id | file_node_id.0.0);
+        assert_eq!(removed_ids, expected_ids);
+        assert_eq!(paths(&state), ["README.md"]);
+    }
+    
+    #[test]
+    fn repeated_and_stale_file_tree_changes_are_harmless() {
+        let mut state = AppState::new();
+        state.load_file_tree(FileTreeData {path: "".into(), root: directory(vec![("a.rs", file())])});
+        // a change can be reported both in a response and in a notification
+        state.apply_file_tree_change(FileTreeChange::Created {path: "b".into(), node: directory(vec![])});
+        state.apply_file_tree_change(FileTreeChange::Created {path: "b".into(), node: directory(vec![])});
+        assert_eq!(paths(&state), ["a.rs", "b"]);
+        // renaming over an entry replaces it
+        state.apply_file_tree_change(FileTreeChange::Renamed {from: "a.rs".into(), to: "b".into()});
+        assert_eq!(paths(&state), ["b"]);
+        assert!(state.file_nodes[find(&state, "b").unwrap()].is_file());
+        // changes to entries that aren't there, or to paths that can't be, change nothing
+        state.apply_file_tree_change(FileTreeChange::Deleted {path: "c".into()});
+        state.apply_file_tree_change(FileTreeChange::Renamed {from: "c".into(), to: "d".into()});
+        state.apply_file_tree_change(FileTreeChange::Created {path: "b/c.rs".into(), node: file()});
+        state.apply_file_tree_change(FileTreeChange::Created {path: "../e.rs".into(), node: file()});
+        assert_eq!(paths(&state), ["b"]);
+    }
+}
@@ -26,7 +26,67 @@ impl BuilderCmdId{
 
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum BuilderCmd {
-    CargoCheck
+    /// Lists the packages in the workspace and their targets through `cargo metadata`, and the
+    /// profiles of the workspace manifest, answered with `BuilderMsg::Workspace`
+    LoadWorkspace,
+    CargoCheck(CargoArgs),
+    CargoBuild(CargoArgs),
+    CargoRun(CargoArgs),
+    CargoTest(CargoArgs),
+}
+
+/// What a cargo command should operate on
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub struct CargoArgs {
+    pub package: String,
+    /// `None` leaves it up to cargo, which usually means the lib and bins of the package
+    pub target: Option<CargoTarget>,
+    pub profile: CargoProfile,
+    pub features: Vec<String>,
+}
+
+impl CargoArgs {
+    pub fn new(package: &str) -> Self {
+        Self {
+            package: package.to_string(),
+            target: None,
+            profile: CargoProfile::Dev,
+            features: Vec::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin)]
+pub enum CargoTarget {
+    Lib,
+    Bin(String),
+    Example(String),
+    Test(String),
+    Bench(String),
+}
+
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin)]
+pub enum CargoProfile {
+    Dev,
+    Release,
+    Custom(String),
+}
+
+/// What can be built in the workspace
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub struct BuilderWorkspace {
+    pub packages: Vec<BuilderPackage>,
+    /// Dev and release first, followed by the custom profiles of the workspace manifest
+    pub profiles: Vec<CargoProfile>,
+}
+
+/// A package in the workspace, as discovered through `cargo metadata`
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub struct BuilderPackage {
+    pub name: String,
+    pub manifest_path: String,
+    pub targets: Vec<CargoTarget>,
+    pub features: Vec<String>,
 }
 
 #[derive(Clone, Debug, SerBin, DeBin)]
@@ -59,5 +119,6 @@ pub struct BuilderMsgBare{
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum BuilderMsg {
     Bare(BuilderMsgBare),
-    Location(BuilderMsgLocation)
+    Location(BuilderMsgLocation),
+    Workspace(BuilderWorkspace)
 }
@@ -5,6 +5,7 @@ use {
         makepad_editor_core::range::{Range},
         builder::{
             builder_protocol::*,
+            cargo_metadata::*,
             child_process::{
                 ChildProcess,
                 ChildLine
@@ -14,7 +15,8 @@ use {
     },
     std::{
         fmt,
-        path::{PathBuf},
+        fs,
+        path::{Path, PathBuf},
         sync::{Arc, RwLock},
     },
 };
@@ -54,8 +56,20 @@ pub struct BuilderConnection {
 impl BuilderConnection {
     pub fn handle_cmd(&self, cmd_wrap: BuilderCmdWrap) {
         match cmd_wrap.cmd {
-            BuilderCmd::CargoCheck => {
-                self.cargo_check(cmd_wrap.cmd_id);
+            BuilderCmd::LoadWorkspace => {
+                self.cargo_metadata(cmd_wrap.cmd_id);
+            }
+            BuilderCmd::CargoCheck(args) => {
+                self.cargo(cmd_wrap.cmd_id, "check", &args);
+            }
+            BuilderCmd::CargoBuild(args) => {
+                self.cargo(cmd_wrap.cmd_id, "build", &args);
+            }
+            BuilderCmd::CargoRun(args) => {
+                self.cargo(cmd_wrap.cmd_id, "run", &args);
+            }
+            BuilderCmd::CargoTest(args) => {
+                self.cargo(cmd_wrap.cmd_id, "test", &args);
             }
         }
     }
@@ -110,18 +124,106 @@ impl BuilderConnection {
         }
     }
     
-    pub fn cargo_check(&self, cmd_id: BuilderCmdId) {
-        
-        // alright lets run a cargo check and parse its output
+    pub fn cargo_metadata(&self, cmd_id: BuilderCmdId) {
         let path = self.shared.read().unwrap().path.clone();
         let args = [
-            "check",
-            "-p",
-            "cmdline_example",
-            "--message-format=json"
+            "metadata",
+            "--format-version",
+            "1",
+            "--no-deps"
         ];
         
-        let process = ChildProcess::start("cargo", &args, path, &[]).expect("Cannot start process");
+        let process = match ChildProcess::start("cargo", &args, path, &[]) {
+            Ok(process) => process,
+            Err(err) => {
+                self.send_bare_msg(cmd_id, BuilderMsgLevel::Error, format!("Cannot start cargo: {}", err));
+                return
+            }
+        };
+        // the metadata is a single line of json, cargo only writes progress and errors to stderr
+        let mut json = String::new();
+        while let Ok(line) = process.line_receiver.recv() {
+            match line {
+                ChildLine::StdOut(line) => json.push_str(&line),
+                ChildLine::StdErr(line) => self.send_bare_msg(cmd_id, BuilderMsgLevel::Log, line),
+                ChildLine::Term => break
+            }
+        }
+        let parsed: Result<CargoMetadata, DeJsonErr> = DeJson::deserialize_json(json.trim());
+        match parsed {
+            Ok(metadata) => {
+                let packages = metadata.packages.iter().map( | package | {
+                    let mut features = package.features.keys().cloned().collect::<Vec<_ >>();
+                    features.sort();
+                    BuilderPackage {
+                        name: package.name.clone(),
+                        manifest_path: package.manifest_path.clone(),
+                        targets: package.targets.iter().filter_map( | target | {
+                            // build scripts can't be picked as a target, so they are left out
+                            match target.kind.first()?.as_str() {
+                                "lib" | "rlib" | "dylib" | "cdylib" | "staticlib" | "proc-macro" => Some(CargoTarget::Lib),
+                                "bin" => Some(CargoTarget::Bin(target.name.clone())),
+                                "example" => Some(CargoTarget::Example(target.name.clone())),
+                                "test" => Some(CargoTarget::Test(target.name.clone())),
+                                "bench" => Some(CargoTarget::Bench(target.name.clone())),
+                                _ => None
+                            }
+                        }).collect(),
+                        features,
+                    }
+                }).collect();
+                // a manifest that can't be read leaves just the profiles every workspace has
+                let manifest_path = Path::new(&metadata.workspace_root).join("Cargo.toml");
+                let manifest = fs::read_to_string(manifest_path).unwrap_or_default();
+                let mut profiles = vec![CargoProfile::Dev, CargoProfile::Release];
+                profiles.extend(manifest_profiles(&manifest).into_iter().filter_map( | name | {
+                    match name.as_str() {
+                        "dev" | "release" => None,
+                        _ => Some(CargoProfile::Custom(name))
+                    }
+                }));
+                self.msg_sender.send_message(cmd_id.wrap_msg(BuilderMsg::Workspace(BuilderWorkspace {
+                    packages,
+                    profiles,
+                })));
+            }
+            Err(err) => {
+                self.send_bare_msg(cmd_id, BuilderMsgLevel::Error, format!("Cannot parse cargo metadata: {:?}", err));
+            }
+        }
+    }
+    
+    pub fn cargo(&self, cmd_id: BuilderCmdId, subcommand: &str, cargo_args: &CargoArgs) {
+        
+        // alright lets run cargo and parse its output
+        let path = self.shared.read().unwrap().path.clone();
+        let mut args = vec![subcommand, "-p", cargo_args.package.as_str()];
+        match &cargo_args.target {
+            None => (),
+            Some(CargoTarget::Lib) => args.push("--lib"),
+            Some(CargoTarget::Bin(name)) => args.extend(["--bin", name.as_str()]),
+            Some(CargoTarget::Example(name)) => args.extend(["--example", name.as_str()]),
+            Some(CargoTarget::Test(name)) => args.extend(["--test", name.as_str()]),
+            Some(CargoTarget::Bench(name)) => args.extend(["--bench", name.as_str()]),
+        }
+        match &cargo_args.profile {
+            CargoProfile::Dev => (),
+            CargoProfile::Release => args.push("--release"),
+            CargoProfile::Custom(name) => args.extend(["--profile", name.as_str()]),
+        }
+        let features = cargo_args.features.join(",");
+        if !features.is_empty() {
+            args.extend(["--features", features.as_str()]);
+        }
+        args.push("--message-format=json");
+        
+        let process = match ChildProcess::start("cargo", &args, path, &[]) {
+            Ok(process) => process,
+            Err(err) => {
+                self.send_bare_msg(cmd_id, BuilderMsgLevel::Error, format!("Cannot start cargo: {}", err));
+                return
+            }
+        };
         while let Ok(line) = process.line_receiver.recv() {
             match line {
                 ChildLine::StdOut(line) => {
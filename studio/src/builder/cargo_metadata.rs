@@ -0,0 +1,202 @@
+use {
+    crate::makepad_micro_serde::*,
+    std::{
+        collections::HashMap,
+        str::Chars,
+    },
+};
+
+// cargo metadata output json structs. Unlike the compiler output we only keep the fields we use
+// and skip the others, because every cargo release adds some.
+#[derive(Clone, Debug, Default)]
+pub struct CargoMetadata {
+    pub packages: Vec<CargoMetadataPackage>,
+    pub workspace_root: String,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CargoMetadataPackage {
+    pub name: String,
+    pub manifest_path: String,
+    pub targets: Vec<CargoMetadataTarget>,
+    pub features: HashMap<String, Vec<String >>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CargoMetadataTarget {
+    pub name: String,
+    pub kind: Vec<String>,
+}
+
+// like the DeJson derive, but unknown keys are skipped instead of being an error, and missing
+// keys keep their default value
+macro_rules!impl_de_json_known_keys {
+    ( $ ty: ident { $ ( $ key: literal => $ field: ident), *}) => {
+        impl DeJson for $ ty {
+            fn de_json(s: &mut DeJsonState, i: &mut Chars) -> Result<Self, DeJsonErr> {
+                let mut r = Self::default();
+                s.curly_open(i) ?;
+                while s.next_str().is_some() {
+                    match s.strbuf.as_ref() {
+                        $ ( $ key => {
+                            s.next_colon(i) ?;
+                            r. $ field = DeJson::de_json(s, i) ?;
+                        }) *
+                        _ => {
+                            s.next_colon(i) ?;
+                            skip_json_value(s, i) ?;
+                        }
+                    }
+                    s.eat_comma_curly(i) ?;
+                }
+                s.curly_close(i) ?;
+                Ok(r)
+            }
+        }
+    }
+}
+
+impl_de_json_known_keys!(CargoMetadata {
+    "packages" => packages,
+    "workspace_root" => workspace_root
+});
+
+impl_de_json_known_keys!(CargoMetadataPackage {
+    "name" => name,
+    "manifest_path" => manifest_path,
+    "targets" => targets,
+    "features" => features
+});
+
+impl_de_json_known_keys!(CargoMetadataTarget {
+    "name" => name,
+    "kind" => kind
+});
+
+// skips a json value of any type, including nested objects and arrays
+fn skip_json_value(s: &mut DeJsonState, i: &mut Chars) -> Result<(), DeJsonErr> {
+    match s.tok {
+        DeJsonTok::CurlyOpen => {
+            s.curly_open(i) ?;
+            while s.tok != DeJsonTok::CurlyClose {
+                s.string(i) ?;
+                s.colon(i) ?;
+                skip_json_value(s, i) ?;
+                s.eat_comma_curly(i) ?;
+            }
+            s.curly_close(i)
+        }
+        DeJsonTok::BlockOpen => {
+            s.block_open(i) ?;
+            while s.tok != DeJsonTok::BlockClose {
+                skip_json_value(s, i) ?;
+                s.eat_comma_block(i) ?;
+            }
+            s.block_close(i)
+        }
+        DeJsonTok::Str
+            | DeJsonTok::U64(_)
+            | DeJsonTok::I64(_)
+            | DeJsonTok::F64(_)
+            | DeJsonTok::Bool(_)
+            | DeJsonTok::BareIdent
+            | DeJsonTok::Null => s.next_tok(i),
+        _ => Err(s.err_token("value")),
+    }
+}
+
+/// The names of the profiles that a manifest configures, in the order they first appear. Cargo
+/// metadata leaves profiles out, so they are read from the `[profile.<name>]` tables, which
+/// includes the ones that only tweak a package like `[profile.dev.package.foo]`.
+pub fn manifest_profiles(manifest: &str) -> Vec<String> {
+    let mut profiles = Vec::new();
+    for line in manifest.lines() {
+        // arrays of tables and anything after the header are of no interest
+        let header = match line.trim().strip_prefix('[') {
+            Some(header) if !header.starts_with('[') => header,
+            _ => continue
+        };
+        let header = match header.split_once(']') {
+            Some((header, _)) => header.trim(),
+            None => continue
+        };
+        let name = match header.strip_prefix("profile.") {
+            Some(rest) => rest.split('.').next().unwrap().trim().trim_matches('"'),
+            None => continue
+        };
+        if !name.is_empty() && !profiles.iter().any( | profile | profile == name) {
+            profiles.push(name.to_string());
+        }
+    }
+    profiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    // trimmed down from the output of `cargo metadata --format-version 1 --no-deps`, with every
+    // kind of json value in keys we don't know about
+    const METADATA: &str = r#"{
+        "packages": [{
+            "name": "cmdline_example",
+            "version": "0.1.0",
+            "id": "cmdline_example 0.1.0 (path+file:///work/cmdline_example)",
+            "license": null,
+            "dependencies": [{"name": "makepad-live-id", "optional": false, "kind": null, "target": null}],
+            "targets": [
+                {"kind": ["bin"], "crate_types": ["bin"], "name": "cmdline_example", "edition": "2021", "doctest": false, "test": true},
+                {"kind": ["example"], "name": "hello", "required-features": ["nightly"]}
+            ],
+            "features": {"default": ["nightly"], "nightly": []},
+            "manifest_path": "/work/cmdline_example/Cargo.toml",
+            "metadata": {"docs": {"rs": {"all-features": true, "weight": -1.5}}},
+            "rust_version": 1
+        }],
+        "workspace_members": ["cmdline_example 0.1.0 (path+file:///work/cmdline_example)"],
+        "resolve": null,
+        "version": 1,
+        "workspace_root": "/work"
+    }"#;
+    
+    #[test]
+    fn skips_unknown_keys() {
+        let metadata: CargoMetadata = DeJson::deserialize_json(METADATA).unwrap();
+        assert_eq!(metadata.workspace_root, "/work");
+        assert_eq!(metadata.packages.len(), 1);
+        let package = &metadata.packages[0];
+        assert_eq!(package.name, "cmdline_example");
+        assert_eq!(package.manifest_path, "/work/cmdline_example/Cargo.toml");
+        assert_eq!(package.features["default"], vec!["nightly".to_string()]);
+        assert_eq!(package.targets.len(), 2);
+        assert_eq!(package.targets[0].name, "cmdline_example");
+        assert_eq!(package.targets[0].kind, vec!["bin".to_string()]);
+        assert_eq!(package.targets[1].name, "hello");
+        assert_eq!(package.targets[1].kind, vec!["example".to_string()]);
+    }
+    
+    #[test]
+    fn finds_the_profiles_of_a_manifest() {
+        let manifest = r#"
+            [workspace]
+            members = ["studio"]
+            
+            [profile.release]
+            debug = true
+            
+            [profile.dev.package.makepad-platform]
+            opt-level = 2
+            
+            [ profile."small" ] # for the web
+            inherits = "release"
+            opt-level = "z"
+            
+            [[bin]]
+            name = "profile.fake"
+            
+            [profile.release.build-override]
+            opt-level = 0
+        "#;
+        assert_eq!(manifest_profiles(manifest), ["release", "dev", "small"]);
+    }
+}
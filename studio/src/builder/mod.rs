@@ -1,5 +1,6 @@
 pub mod builder_client;
 pub mod builder_protocol;
 pub mod builder_server;
+pub mod cargo_metadata;
 pub mod child_process;
 pub mod rustc_json;
@@ -32,9 +32,9 @@ use {
         builder::{
             builder_protocol::{BuilderMsg, BuilderMsgLevel}
         },
-        makepad_collab_protocol::CollabRequest,
+        makepad_collab_protocol::{CollabRequest, ParticipantId, Presence},
     },
-    std::mem,
+    std::{collections::HashMap, mem},
 };
 
 live_register!{
@@ -69,7 +69,7 @@ live_register!{
                 sdf.box(self.next_x, self.rect_size.y, self.next_w, self.rect_size.y, BORDER_RADIUS);
                 sdf.gloop(GLOOPINESS);
             }
-            return sdf.fill(COLOR_EDITOR_SELECTED);
+            return sdf.fill(self.color);
         }
     }
     
@@ -150,6 +150,9 @@ live_register!{
             color: (COLOR_FG_CURSOR)
         }
         
+        selection_color: (COLOR_EDITOR_SELECTED)
+        presence_selection_alpha: 0.35
+        
         current_line_quad: {
             no_h_scroll: true
             color: (COLOR_BG_CURSOR)
@@ -210,8 +213,11 @@ pub struct CodeEditorImpl {
     state: State,
     
     selection_quad: DrawSelection,
+    selection_color: Vec4,
     code_text: DrawText,
     caret_quad: DrawColor,
+    presence_caret_quad: DrawColor,
+    presence_selection_alpha: f32,
     line_num_quad: DrawColor,
     line_num_text: DrawText,
     indent_line_quad: DrawIndentLine,
@@ -234,6 +240,7 @@ pub struct CodeEditorImpl {
 #[repr(C)]
 pub struct DrawSelection {
     draw_super: DrawQuad,
+    color: Vec4,
     prev_x: f32,
     prev_w: f32,
     next_x: f32,
@@ -425,6 +432,7 @@ impl CodeEditorImpl {
         self.code_text.begin_many_instances(cx);
         self.indent_line_quad.begin_many_instances(cx);
         self.msg_line_quad.begin_many_instances(cx);
+        self.presence_caret_quad.begin_many_instances(cx);
         self.caret_quad.begin_many_instances(cx);
     }
     
@@ -433,6 +441,7 @@ impl CodeEditorImpl {
         self.code_text.end_many_instances(cx);
         self.indent_line_quad.end_many_instances(cx);
         self.msg_line_quad.end_many_instances(cx);
+        self.presence_caret_quad.end_many_instances(cx);
         self.caret_quad.end_many_instances(cx);
     }
     
@@ -473,6 +482,34 @@ impl CodeEditorImpl {
         selections: &RangeSet,
         text: &Text,
         lines_layout: &LinesLayout,
+    ) {
+        self.selection_quad.color = self.selection_color;
+        self.draw_selections_inner(cx, selections, text, lines_layout);
+    }
+    
+    // draws the carets and selections of the other participants in their own color
+    pub fn draw_presences(
+        &mut self,
+        cx: &mut Cx2d,
+        presences: &HashMap<ParticipantId, Presence>,
+        text: &Text,
+        lines_layout: &LinesLayout,
+    ) {
+        for presence in presences.values() {
+            let color = Vec4::from_u32(presence.color);
+            self.selection_quad.color = Vec4 {w: self.presence_selection_alpha, ..color};
+            self.draw_selections_inner(cx, &presence.selections, text, lines_layout);
+            self.presence_caret_quad.color = color;
+            self.draw_carets_inner(cx, &presence.selections, &presence.carets, lines_layout, true);
+        }
+    }
+    
+    fn draw_selections_inner(
+        &mut self,
+        cx: &mut Cx2d,
+        selections: &RangeSet,
+        text: &Text,
+        lines_layout: &LinesLayout,
     ) {
         let origin = cx.turtle().pos();
         let start_x = origin.x + self.line_num_width;
@@ -750,6 +787,17 @@ impl CodeEditorImpl {
         selections: &RangeSet,
         carets: &PositionSet,
         lines_layout: &LinesLayout,
+    ) {
+        self.draw_carets_inner(cx, selections, carets, lines_layout, false);
+    }
+    
+    fn draw_carets_inner(
+        &mut self,
+        cx: &mut Cx2d,
+        selections: &RangeSet,
+        carets: &PositionSet,
+        lines_layout: &LinesLayout,
+        is_presence: bool,
     ) {
         let mut caret_iter = carets.iter().peekable();
         loop {
@@ -772,14 +820,21 @@ impl CodeEditorImpl {
                             continue;
                         }
                         let pos = self.position_to_vec2(*caret, lines_layout);
-                        self.caret_quad.draw_abs(cx, Rect {
+                        let rect = Rect {
                             pos: pos + origin,
                             size: Vec2 {
                                 x: 1.5 * layout.font_scale,
                                 y: self.text_glyph_size.y * layout.font_scale,
                             },
                             
-                        });
+                        };
+                        // remote carets don't blink, so they have their own quad
+                        if is_presence {
+                            self.presence_caret_quad.draw_abs(cx, rect);
+                        }
+                        else {
+                            self.caret_quad.draw_abs(cx, rect);
+                        }
                     }
                     _ => break,
                 }
@@ -853,6 +908,12 @@ impl CodeEditorImpl {
             }
         }
         
+        // remember the carets and selections, so we can tell the other participants if they changed
+        let carets_and_selections = self.session_id.map( | session_id | {
+            let session = &state.sessions[session_id];
+            (session.carets.clone(), session.selections.clone())
+        });
+        
         match event.hits(cx, self.scroll_view.area()) {
             HitEvent::Trigger(_) => { //
                 self.handle_select_scroll_in_trigger(cx, state, lines_layout);
@@ -1023,6 +1084,15 @@ impl CodeEditorImpl {
                     dispatch_action(cx, CodeEditorAction::RedrawViewsForDocument(session.document_id))
                 }
             }
+            HitEvent::KeyDown(KeyEvent {
+                key_code: KeyCode::KeyS,
+                modifiers,
+                ..
+            }) if modifiers.control || modifiers.logo => {
+                if let Some(session_id) = self.session_id {
+                    state.save(session_id, send_request);
+                }
+            }
             HitEvent::KeyDown(KeyEvent { 
                 key_code: KeyCode::KeyA,
                 modifiers,
@@ -1073,6 +1143,13 @@ impl CodeEditorImpl {
             }
             _ => {}
         }
+        
+        if let (Some(session_id), Some((carets, selections))) = (self.session_id, carets_and_selections) {
+            let session = &state.sessions[session_id];
+            if session.carets != carets || session.selections != selections {
+                state.update_presence(session_id, send_request);
+            }
+        }
     }
     
     fn handle_select_scroll_in_finger_move(&mut self, fe: &FingerMoveHitEvent) {
@@ -84,6 +84,9 @@ impl CollabClientInner {
         let base_path = env::current_dir().unwrap();
         let final_path = base_path.join(subdir.split('/').collect::<PathBuf>());
         let mut server = CollabServer::new(final_path);
+        // the local server is the only copy of the edits, so don't lose them when a tab closes
+        server.set_autosave(true);
+        server.start_watching();
         spawn_local_request_handler(
             request_receiver,
             server.connect(Box::new({
@@ -6,10 +6,11 @@ use {
             position::Position,
             position_set::PositionSet,
             range_set::RangeSet,
+            range::Range,
             size::Size,
             text::Text,
         },
-        builder::builder_protocol::BuilderMsg,
+        builder::builder_protocol::{BuilderMsg, BuilderMsgBare, BuilderMsgLevel, BuilderMsgLocation},
         code_editor::{
             cursor_set::CursorSet,
             indent_cache::IndentCache,
@@ -18,6 +19,8 @@ use {
         rust_editor::rust_tokenizer::token_cache::TokenCache,
         makepad_collab_protocol::{
             CollabRequest,
+            ParticipantId,
+            Presence,
             TextFileId,
             unix_path::UnixPathBuf,
         },
@@ -66,12 +69,25 @@ pub struct EditorState {
     /// been created, but we have not yet received its contents from the collab server.
     pub outstanding_document_queue: VecDeque<DocumentId>,
     pub messages: Vec<BuilderMsg>,
+    /// The name under which the other participants see our carets and selections.
+    pub user_name: String,
+    /// The color in which the other participants see our carets and selections, as RGBA.
+    pub user_color: u32,
 }
 
 impl EditorState {
     /// Creates a new `EditorState`.
+    ///
+    /// The user name is taken from the environment. The user color is derived from it, so that the
+    /// same user always shows up in the same color.
     pub fn new() -> EditorState {
-        EditorState::default()
+        let user_name = std::env::var("USER").unwrap_or_else( | _ | "anonymous".to_string());
+        let hue = (LiveId::from_str_unchecked(&user_name).0 % 360) as f32 / 360.0;
+        EditorState {
+            user_color: Vec4::from_hsva(vec4(hue, 0.7, 0.9, 1.0)).to_u32(),
+            user_name,
+            ..EditorState::default()
+        }
     }
 
     /// Either gets or creates the document for the file with the given `path`, and then creates a
@@ -162,6 +178,7 @@ impl EditorState {
         file_id: TextFileId,
         revision: u32,
         text: Text,
+        presences: HashMap<ParticipantId, Presence>,
         send_request: &mut dyn FnMut(CollabRequest),
     ) -> DocumentId {
         let document_id = self.outstanding_document_queue.pop_front().unwrap();
@@ -181,6 +198,16 @@ impl EditorState {
             undo_stack: Vec::new(),
             redo_stack: Vec::new(),
             outstanding_deltas: VecDeque::new(),
+            save_requested: false,
+            overwrite_on_save: false,
+            saved_revision: None,
+            presence: Presence {
+                name: self.user_name.clone(),
+                color: self.user_color,
+                ..Presence::default()
+            },
+            presence_requested: false,
+            presences,
         });
         self.documents_by_file.insert(file_id, document_id);
         if document.should_be_destroyed {
@@ -223,6 +250,139 @@ impl EditorState {
         send_request(CollabRequest::CloseFile(file_id))
     }
 
+    /// Requests the collab server to save the document for the session with the given `session_id`.
+    ///
+    /// The save is deferred until the collab server has confirmed all outstanding deltas for the
+    /// document, so that the revision on disk includes every edit made so far.
+    pub fn save(&mut self, session_id: SessionId, send_request: &mut dyn FnMut(CollabRequest)) {
+        let session = &self.sessions[session_id];
+        let document = &mut self.documents[session.document_id];
+        let document_inner = document.inner.as_mut().unwrap();
+        if document_inner.outstanding_deltas.is_empty() {
+            send_request(document_inner.save_request());
+        } else {
+            document_inner.save_requested = true;
+        }
+    }
+
+    /// Handles a save file response from the collab server.
+    pub fn handle_save_file_response(&mut self, file_id: TextFileId, revision: u32) {
+        self.handle_file_saved(file_id, revision);
+    }
+
+    /// Handles a save file response from the collab server that the file was changed on disk by
+    /// another program. The user is told, and the next save overwrites those changes.
+    pub fn handle_save_file_conflict_response(&mut self, file_id: TextFileId) {
+        // The document could have been closed while the save was underway.
+        if let Some(document_id) = self.documents_by_file.get(&file_id).cloned() {
+            let document_inner = self.documents[document_id].inner.as_mut().unwrap();
+            document_inner.overwrite_on_save = true;
+            self.add_document_message(
+                document_id,
+                BuilderMsgLevel::Error,
+                "Cannot save file, it was changed on disk. Save again to overwrite those changes".to_string()
+            );
+        }
+    }
+
+    /// Adds a message about the document with the given `document_id` to the messages shown in
+    /// the log view.
+    pub fn add_document_message(&mut self, document_id: DocumentId, level: BuilderMsgLevel, msg: String) {
+        let file_name = self.documents[document_id].path.as_unix_str().to_string_lossy().to_string();
+        self.messages.push(BuilderMsg::Location(BuilderMsgLocation {
+            level,
+            file_name,
+            range: Range::default(),
+            msg,
+        }));
+    }
+
+    /// Adds a message that is not about any document in particular to the messages shown in the
+    /// log view.
+    pub fn add_message(&mut self, level: BuilderMsgLevel, line: String) {
+        self.messages.push(BuilderMsg::Bare(BuilderMsgBare {level, line}));
+    }
+
+    /// Handles a notification from the collab server that a remote client saved a file.
+    pub fn handle_file_saved_notification(&mut self, file_id: TextFileId, revision: u32) {
+        self.handle_file_saved(file_id, revision);
+    }
+
+    fn handle_file_saved(&mut self, file_id: TextFileId, revision: u32) {
+        // The document could have been closed while the save was underway.
+        if let Some(document_id) = self.documents_by_file.get(&file_id) {
+            let document_inner = self.documents[*document_id].inner.as_mut().unwrap();
+            document_inner.saved_revision = Some(revision as usize);
+            document_inner.overwrite_on_save = false;
+        }
+    }
+
+    /// Requests the collab server to update our presence in the document for the session with the
+    /// given `session_id` to the carets and selections of that session. Nothing is sent if they
+    /// did not change since the last update.
+    ///
+    /// Like saving, the update is deferred until the collab server has confirmed all outstanding
+    /// deltas for the document, since the carets and selections are for a revision of the document
+    /// that the collab server does not know about yet.
+    pub fn update_presence(&mut self, session_id: SessionId, send_request: &mut dyn FnMut(CollabRequest)) {
+        let session = &self.sessions[session_id];
+        let document = &mut self.documents[session.document_id];
+        let document_inner = match document.inner.as_mut() {
+            Some(document_inner) => document_inner,
+            None => return,
+        };
+        if document_inner.presence.carets == session.carets
+            && document_inner.presence.selections == session.selections
+        {
+            return;
+        }
+        document_inner.presence.carets = session.carets.clone();
+        document_inner.presence.selections = session.selections.clone();
+        if document_inner.outstanding_deltas.is_empty() {
+            send_request(CollabRequest::UpdatePresence(
+                document_inner.file_id,
+                document_inner.revision as u32,
+                document_inner.presence.clone(),
+            ));
+        } else {
+            document_inner.presence_requested = true;
+        }
+    }
+
+    /// Handles a notification from the collab server that a remote participant updated its
+    /// presence.
+    pub fn handle_presence_updated_notification(
+        &mut self,
+        file_id: TextFileId,
+        participant_id: ParticipantId,
+        presence: Presence,
+    ) -> DocumentId {
+        let document_id = self.documents_by_file[file_id];
+        let document_inner = self.documents[document_id].inner.as_mut().unwrap();
+
+        // The presence is for the last revision we received from the collab server, so apply our
+        // outstanding deltas to it to bring it up to date with our text.
+        let mut presence = presence;
+        for outstanding_delta in &document_inner.outstanding_deltas {
+            presence.apply_delta(outstanding_delta);
+        }
+        document_inner.presences.insert(participant_id, presence);
+        document_id
+    }
+
+    /// Handles a notification from the collab server that a remote participant was removed from a
+    /// file.
+    pub fn handle_presence_removed_notification(
+        &mut self,
+        file_id: TextFileId,
+        participant_id: ParticipantId,
+    ) -> DocumentId {
+        let document_id = self.documents_by_file[file_id];
+        let document_inner = self.documents[document_id].inner.as_mut().unwrap();
+        document_inner.presences.remove(&participant_id);
+        document_id
+    }
+
     /// Adds a cursor to the cursor set of the session with the given `session_id`, wotj tje caret
     /// at the given position.
     pub fn add_cursor(&mut self, session_id: SessionId, position: Position) {
@@ -806,6 +966,19 @@ impl EditorState {
                 document_inner.revision as u32,
                 outstanding_delta.clone(),
             ));
+        } else {
+            if document_inner.save_requested {
+                document_inner.save_requested = false;
+                send_request(document_inner.save_request());
+            }
+            if document_inner.presence_requested {
+                document_inner.presence_requested = false;
+                send_request(CollabRequest::UpdatePresence(
+                    file_id,
+                    document_inner.revision as u32,
+                    document_inner.presence.clone(),
+                ));
+            }
         }
     }
 
@@ -919,6 +1092,11 @@ impl Document {
         inner.indent_cache.invalidate(&delta);
         inner.msg_cache.invalidate(&delta);
 
+        inner.presence.apply_delta(&delta);
+        for presence in inner.presences.values_mut() {
+            presence.apply_delta(&delta);
+        }
+
         inner.text.apply_delta(delta);
 
         inner.token_cache.refresh(&inner.text);
@@ -975,6 +1153,31 @@ pub struct DocumentInner {
     /// applied to the local document, but we have not yet received confirmation from the collab
     /// server that it has been applied to the remote document.
     pub outstanding_deltas: VecDeque<Delta>,
+    /// Whether a save was requested while there were still outstanding deltas for this document.
+    pub save_requested: bool,
+    /// Whether a save was refused because another program changed the file on disk, so that the
+    /// next save overwrites those changes.
+    pub overwrite_on_save: bool,
+    /// The last revision of this document that is known to be saved to disk.
+    pub saved_revision: Option<usize>,
+    /// Our presence in this document, as last sent to the collab server (or to be sent, if
+    /// `presence_requested` is set).
+    pub presence: Presence,
+    /// Whether our presence changed while there were still outstanding deltas for this document.
+    pub presence_requested: bool,
+    /// The presence of every other participant for this document.
+    pub presences: HashMap<ParticipantId, Presence>,
+}
+
+impl DocumentInner {
+    // Returns the request to save this document with.
+    fn save_request(&self) -> CollabRequest {
+        if self.overwrite_on_save {
+            CollabRequest::OverwriteFile(self.file_id)
+        } else {
+            CollabRequest::SaveFile(self.file_id)
+        }
+    }
 }
 
 /// An `EditGroup` keeps track of whether the last typed character was a backspace character or a
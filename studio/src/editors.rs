@@ -16,6 +16,7 @@ use {
             },
         },
         makepad_collab_protocol::{
+            CollabError,
             CollabNotification,
             CollabRequest,
             CollabResponse,
@@ -24,7 +25,8 @@ use {
         builder::{
             builder_protocol::{
                 BuilderMsgWrap,
-                BuilderMsg
+                BuilderMsg,
+                BuilderMsgLevel,
             }
         },
         rust_editor::{
@@ -192,14 +194,19 @@ impl Editors {
     ) {
         match response {
             CollabResponse::OpenFile(response) => {
-                let (file_id, revision, text) = response.unwrap();
-                let document_id = state.handle_open_file_response(file_id, revision, text, send_request);
+                let (file_id, revision, text, presences) = response.unwrap();
+                let document_id = state.handle_open_file_response(file_id, revision, text, presences, send_request);
                 self.redraw_views_for_document(cx, state, document_id);
             }
             CollabResponse::ApplyDelta(response) => {
                 let file_id = response.unwrap();
                 state.handle_apply_delta_response(file_id, send_request);
             }
+            CollabResponse::SaveFile(response) | CollabResponse::OverwriteFile(response) => match response {
+                Ok((file_id, revision)) => state.handle_save_file_response(file_id, revision),
+                Err(CollabError::FileHasConflict(file_id)) => state.handle_save_file_conflict_response(file_id),
+                Err(error) => state.add_message(BuilderMsgLevel::Error, format!("Cannot save file: {:?}", error)),
+            }
             _ => {}
         }
     }
@@ -215,6 +222,26 @@ impl Editors {
                 let document_id = state.handle_delta_applied_notification(file_id, delta);
                 self.redraw_views_for_document(cx, state, document_id);
             }
+            CollabNotification::FileWasSaved(file_id, revision) => {
+                state.handle_file_saved_notification(file_id, revision);
+            }
+            CollabNotification::FileHasConflict(file_id) => {
+                let document_id = state.documents_by_file[file_id];
+                state.add_document_message(
+                    document_id,
+                    BuilderMsgLevel::Warning,
+                    "File was changed on disk while it had unsaved edits".to_string()
+                );
+            }
+            CollabNotification::PresenceWasUpdated(file_id, participant_id, presence) => {
+                let document_id = state.handle_presence_updated_notification(file_id, participant_id, presence);
+                self.redraw_views_for_document(cx, state, document_id);
+            }
+            CollabNotification::PresenceWasRemoved(file_id, participant_id) => {
+                let document_id = state.handle_presence_removed_notification(file_id, participant_id);
+                self.redraw_views_for_document(cx, state, document_id);
+            }
+            CollabNotification::FileTreeWasChanged(_) => {}
         }
     }
     
@@ -5,10 +5,23 @@ use {
     crate::{
         builder::{
             builder_protocol::{
+                BuilderCmd,
                 BuilderMsg,
-                BuilderMsgLevel
+                BuilderMsgBare,
+                BuilderMsgLevel,
+                BuilderPackage,
+                BuilderWorkspace,
+                CargoArgs,
+                CargoProfile,
+                CargoTarget,
             }
         },
+        makepad_component::{
+            button::Button,
+            button_logic::ButtonAction,
+            drop_down::{DropDown, DropDownAction},
+            frame_component::FrameComponent,
+        },
         makepad_studio_component::{
             log_icon::LogIconType,
             log_list::{LogList, LogListAction}
@@ -25,16 +38,38 @@ live_register!{
     use makepad_platform::shader::std::*;
     
     LogView: {{LogView}} {
+        build_bar: {
+            align: {y: 0.5},
+            padding: {left: 4.0, top: 2.0, right: 4.0, bottom: 2.0}
+        }
+        profile_drop_down: {width: Size::Fixed(80.0)}
+        check_button: {label: "Check"}
+        build_button: {label: "Build"}
+        run_button: {label: "Run"}
+        test_button: {label: "Test"}
     }
 }
 
-#[derive(Live, LiveHook)]
+/// The log of the builder and the collab client, with a bar on top to run cargo on a package of
+/// the workspace.
+#[derive(Live)]
 pub struct LogView {
-    log_list: LogList
+    log_list: LogList,
+    build_bar: Layout,
+    package_drop_down: DropDown,
+    target_drop_down: DropDown,
+    feature_drop_down: DropDown,
+    profile_drop_down: DropDown,
+    check_button: Button,
+    build_button: Button,
+    run_button: Button,
+    test_button: Button,
+    #[rust] packages: Vec<BuilderPackage>,
+    #[rust] profiles: Vec<CargoProfile>,
 }
 
 pub enum LogViewAction {
-    None
+    BuilderCmd(BuilderCmd),
 }
 
 impl Into<LogIconType> for BuilderMsgLevel{
@@ -47,22 +82,123 @@ impl Into<LogIconType> for BuilderMsgLevel{
     }
 }
 
+impl LiveHook for LogView {
+    fn after_new(&mut self, cx: &mut Cx) {
+        self.set_profiles(cx, vec![CargoProfile::Dev, CargoProfile::Release]);
+        self.update_package(cx);
+    }
+}
+
+fn profile_label(profile: &CargoProfile) -> String {
+    match profile {
+        CargoProfile::Dev => "dev".to_string(),
+        CargoProfile::Release => "release".to_string(),
+        CargoProfile::Custom(name) => name.clone(),
+    }
+}
+
+// the default feature is already on unless features are turned off, so it isn't offered
+fn package_features(package: &BuilderPackage) -> impl Iterator<Item = &String> {
+    package.features.iter().filter( | feature | *feature != "default")
+}
+
+fn target_label(target: &CargoTarget) -> String {
+    match target {
+        CargoTarget::Lib => "lib".to_string(),
+        CargoTarget::Bin(name) => format!("bin {}", name),
+        CargoTarget::Example(name) => format!("example {}", name),
+        CargoTarget::Test(name) => format!("test {}", name),
+        CargoTarget::Bench(name) => format!("bench {}", name),
+    }
+}
+
 impl LogView {
     pub fn redraw(&mut self, cx:&mut Cx){
         self.log_list.redraw(cx)
     }
     
+    /// Sets the packages and profiles of the workspace that the build bar can pick from.
+    pub fn set_workspace(&mut self, cx: &mut Cx, workspace: BuilderWorkspace) {
+        self.package_drop_down.set_labels(cx, workspace.packages.iter().map( | package | package.name.clone()).collect());
+        self.packages = workspace.packages;
+        self.package_drop_down.selected = 0;
+        self.update_package(cx);
+        self.set_profiles(cx, workspace.profiles);
+    }
+    
+    fn set_profiles(&mut self, cx: &mut Cx, profiles: Vec<CargoProfile>) {
+        self.profile_drop_down.set_labels(cx, profiles.iter().map(profile_label).collect());
+        self.profiles = profiles;
+        self.profile_drop_down.selected = 0;
+    }
+    
+    fn update_package(&mut self, cx: &mut Cx) {
+        // leaving the target and features up to cargo comes first
+        let mut target_labels = vec!["default".to_string()];
+        let mut feature_labels = vec!["default features".to_string()];
+        if let Some(package) = self.packages.get(self.package_drop_down.selected) {
+            target_labels.extend(package.targets.iter().map(target_label));
+            feature_labels.extend(package_features(package).cloned());
+        }
+        self.target_drop_down.set_labels(cx, target_labels);
+        self.target_drop_down.selected = 0;
+        self.feature_drop_down.set_labels(cx, feature_labels);
+        self.feature_drop_down.selected = 0;
+    }
+    
+    // The arguments for the package, target, feature and profile picked in the build bar.
+    fn cargo_args(&self) -> Option<CargoArgs> {
+        let package = self.packages.get(self.package_drop_down.selected)?;
+        let mut args = CargoArgs::new(&package.name);
+        args.target = self.target_drop_down.selected.checked_sub(1).and_then( | index | package.targets.get(index).cloned());
+        args.features = self.feature_drop_down.selected.checked_sub(1)
+            .and_then( | index | package_features(package).nth(index).cloned())
+            .into_iter()
+            .collect();
+        if let Some(profile) = self.profiles.get(self.profile_drop_down.selected) {
+            args.profile = profile.clone();
+        }
+        Some(args)
+    }
+    
+    fn draw_build_bar(&mut self, cx: &mut Cx2d) {
+        cx.begin_turtle(Walk::size(Size::Fill, Size::Fit), self.build_bar);
+        let walk = self.package_drop_down.get_walk();
+        self.package_drop_down.draw_walk(cx, walk);
+        let walk = self.target_drop_down.get_walk();
+        self.target_drop_down.draw_walk(cx, walk);
+        let walk = self.feature_drop_down.get_walk();
+        self.feature_drop_down.draw_walk(cx, walk);
+        let walk = self.profile_drop_down.get_walk();
+        self.profile_drop_down.draw_walk(cx, walk);
+        for button in [&mut self.check_button, &mut self.build_button, &mut self.run_button, &mut self.test_button] {
+            let walk = button.get_walk();
+            button.draw_walk(cx, walk);
+        }
+        cx.end_turtle();
+    }
+    
     pub fn draw(&mut self, cx: &mut Cx2d, state: &EditorState) {
         let mut file = String::new();
         let mut body = String::new();
+        cx.begin_turtle(Walk::default(), Layout::flow_down());
+        self.draw_build_bar(cx);
         if self.log_list.begin(cx).is_ok(){
             for (index, msg) in state.messages.iter().enumerate(){
+                // plain output at the log level, like the Compiling lines of cargo, would drown
+                // out everything else
+                if let BuilderMsg::Bare(BuilderMsgBare{level: BuilderMsgLevel::Log, ..}) = msg{
+                    continue;
+                }
                 if self.log_list.should_node_draw(cx){
                     file.clear();
                     body.clear();
                     let id = LiveId(index as  u64).into();
                     match msg{
-                        BuilderMsg::Bare(_msg)=>{
+                        BuilderMsg::Bare(msg)=>{
+                            self.log_list.draw_node(cx, msg.level.into(), id, "", &msg.line, true);
+                        }
+                        BuilderMsg::Workspace(_)=>{
                         }
                         BuilderMsg::Location(msg)=>{
                             write!(file, "{}:{}", msg.file_name, msg.range.start.line).unwrap();
@@ -71,18 +207,52 @@ impl LogView {
                     }
                 }
             }
+            // shader compile errors of studio itself, already mapped back onto the live DSL
+            let compile_errors = cx.draw_shaders.compile_errors.clone();
+            for (index, err) in compile_errors.iter().enumerate(){
+                if self.log_list.should_node_draw(cx){
+                    file.clear();
+                    let id = LiveId((state.messages.len() + index) as u64).into();
+                    write!(file, "{}:{}", err.file, err.span.start.line + 1).unwrap();
+                    self.log_list.draw_node(cx, LogIconType::Error, id, &file, &err.message, true);
+                }
+            }
             self.log_list.end(cx);
         }
+        cx.end_turtle();
     }
     
     pub fn handle_event_with_fn(
         &mut self,
         cx: &mut Cx,
         event: &mut Event,
-        _dispatch_action: &mut dyn FnMut(&mut Cx, LogListAction),
+        dispatch_action: &mut dyn FnMut(&mut Cx, LogViewAction),
     ) {
-        self.log_list.handle_event_with_fn(cx, event, &mut |_cx, _action|{
+        self.log_list.handle_event_with_fn(cx, event, &mut |_cx, _action: LogListAction|{
             
-        })
+        });
+        
+        if let DropDownAction::Changed(_) = self.package_drop_down.handle_event(cx, event) {
+            self.update_package(cx);
+        }
+        self.target_drop_down.handle_event(cx, event);
+        self.feature_drop_down.handle_event(cx, event);
+        self.profile_drop_down.handle_event(cx, event);
+        
+        let check = self.check_button.handle_event(cx, event);
+        let build = self.build_button.handle_event(cx, event);
+        let run = self.run_button.handle_event(cx, event);
+        let test = self.test_button.handle_event(cx, event);
+        // nothing can be built until the packages are known
+        if let Some(cargo_args) = self.cargo_args() {
+            let cmd = match (check, build, run, test) {
+                (ButtonAction::WasClicked, _, _, _) => BuilderCmd::CargoCheck(cargo_args),
+                (_, ButtonAction::WasClicked, _, _) => BuilderCmd::CargoBuild(cargo_args),
+                (_, _, ButtonAction::WasClicked, _) => BuilderCmd::CargoRun(cargo_args),
+                (_, _, _, ButtonAction::WasClicked) => BuilderCmd::CargoTest(cargo_args),
+                _ => return
+            };
+            dispatch_action(cx, LogViewAction::BuilderCmd(cmd));
+        }
     }
 }
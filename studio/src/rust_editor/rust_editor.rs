@@ -182,6 +182,13 @@ impl RustEditor {
                 &self.lines_layout,
             );
             
+            self.editor_impl.draw_presences(
+                cx,
+                &document_inner.presences,
+                &document_inner.text,
+                &self.lines_layout,
+            );
+            
             self.editor_impl.draw_indent_guides(
                 cx,
                 &document_inner.indent_cache,
@@ -50,6 +50,8 @@ fn main() {
     });
     println!("Server listening on {}", addr);
     let mut clb_server = CollabServer::new("./");
+    clb_server.set_autosave(args.iter().any(|arg| arg == "--autosave"));
+    clb_server.start_watching();
     let mut clb_connections = HashMap::new();
     
     while let Ok(message) = rx_request.recv() {